@@ -0,0 +1,149 @@
+//! Benchmarks for each stage of the pipeline (scan, parse, compile, and
+//! execute) over a shared corpus of FeInt sources:
+//!
+//! - The pure FeInt std modules in `src/modules/std`
+//! - The representative scripts in `benches/corpus`
+//! - A large synthetic script generated from the corpus scripts
+//!
+//! Run with `cargo bench --features bench`. To run a single stage, pass
+//! a filter: `cargo bench --features bench -- scan`.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion,
+    Throughput,
+};
+
+use feint::bench;
+use feint::exe::Executor;
+
+/// Number of copies of the corpus scripts in the synthetic script.
+const SYNTHETIC_COPIES: usize = 25;
+
+struct Source {
+    name: String,
+    text: String,
+    /// Only scripts are executed. Modules are scanned, parsed, and
+    /// compiled but may depend on being imported.
+    is_script: bool,
+}
+
+fn read_dir(dir: &Path, is_script: bool) -> Vec<Source> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("Could not read {}: {err}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "fi"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            let name =
+                if is_script { stem.into_owned() } else { format!("std.{stem}") };
+            let text = fs::read_to_string(&path).unwrap();
+            Source { name, text, is_script }
+        })
+        .collect()
+}
+
+/// Generate a large script by repeating the corpus scripts. Globals
+/// are simply redefined by each copy.
+fn synthetic(scripts: &[Source], copies: usize) -> Source {
+    let mut text = String::new();
+    for i in 0..copies {
+        for script in scripts {
+            text.push_str(&format!("# Copy {i} of {}\n\n", script.name));
+            text.push_str(&script.text);
+            text.push('\n');
+        }
+    }
+    Source { name: "synthetic".to_owned(), text, is_script: true }
+}
+
+fn corpus() -> Vec<Source> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut sources = read_dir(&root.join("src/modules/std"), false);
+    let scripts = read_dir(&root.join("benches/corpus"), true);
+    sources.push(synthetic(&scripts, SYNTHETIC_COPIES));
+    sources.extend(scripts);
+    sources
+}
+
+fn new_executor() -> Executor {
+    let mut exe = Executor::new(0, vec![], false, false, false);
+    exe.set_deterministic(true);
+    exe.bootstrap().unwrap();
+    exe
+}
+
+fn bench_stages(c: &mut Criterion) {
+    let corpus = corpus();
+
+    // Make sure the corpus is valid before measuring anything.
+    for source in corpus.iter() {
+        if let Err(err) = bench::compile(&source.text, false) {
+            panic!("Could not compile {}: {err}", source.name);
+        }
+    }
+
+    let mut group = c.benchmark_group("scan");
+    for source in corpus.iter() {
+        group.throughput(Throughput::Bytes(source.text.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(&source.name),
+            source,
+            |b, s| b.iter(|| bench::scan(black_box(&s.text)).unwrap()),
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("parse");
+    for source in corpus.iter() {
+        group.throughput(Throughput::Bytes(source.text.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(&source.name),
+            source,
+            |b, s| b.iter(|| bench::parse(black_box(&s.text)).unwrap()),
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("compile");
+    for source in corpus.iter() {
+        group.throughput(Throughput::Bytes(source.text.len() as u64));
+        for optimize in [false, true] {
+            let id = if optimize {
+                format!("{}/opt", source.name)
+            } else {
+                source.name.clone()
+            };
+            group.bench_with_input(BenchmarkId::from_parameter(id), source, |b, s| {
+                b.iter(|| bench::compile(black_box(&s.text), optimize).unwrap())
+            });
+        }
+    }
+    group.finish();
+
+    // Bootstrapping isn't included in the execute measurements.
+    let mut group = c.benchmark_group("execute");
+    group.sample_size(20);
+    for source in corpus.iter().filter(|s| s.is_script) {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(&source.name),
+            source,
+            |b, s| {
+                b.iter_batched(
+                    new_executor,
+                    |mut exe| exe.execute_text(&s.text).unwrap(),
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_stages);
+criterion_main!(benches);
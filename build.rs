@@ -4,6 +4,7 @@ use std::fs::{self, File};
 use std::io::Error;
 use std::path::Path;
 use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use clap_complete::{self, shells};
 use flate2::{Compression, GzBuilder};
@@ -25,6 +26,7 @@ fn main() -> Result<(), Error> {
     let out_dir = Path::new(&out_dir);
 
     stamp(out_dir)?;
+    set_build_info();
     make_shell_completion_scripts(out_dir)?;
     make_module_archive(out_dir)?;
 
@@ -39,6 +41,59 @@ fn stamp(out_dir: &Path) -> Result<(), Error> {
     Ok(())
 }
 
+/// Sets env vars with info about the build, which are read via `env!`
+/// in `src/version.rs`.
+fn set_build_info() {
+    let git_hash = command_output("git", &["rev-parse", "--short", "HEAD"]);
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let rustc_version = command_output(&rustc, &["--version"]);
+
+    // Respect SOURCE_DATE_EPOCH for reproducible builds.
+    let timestamp = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.parse().unwrap_or(0),
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+
+    println!("cargo:rustc-env=FEINT_GIT_HASH={git_hash}");
+    println!("cargo:rustc-env=FEINT_BUILD_DATE={}", format_date(timestamp));
+    println!("cargo:rustc-env=FEINT_RUSTC_VERSION={rustc_version}");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// Run command and return its trimmed output or "unknown" if it fails.
+fn command_output(program: &str, args: &[&str]) -> String {
+    match process::Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_owned()
+        }
+        _ => "unknown".to_owned(),
+    }
+}
+
+/// Format UNIX timestamp as a UTC date (YYYY-MM-DD).
+///
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn format_date(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
 fn make_shell_completion_scripts(out_dir: &Path) -> Result<(), Error> {
     let mut cmd = build_cli();
     clap_complete::generate_to(shells::Bash, &mut cmd, "feint", out_dir)?;
@@ -56,6 +111,10 @@ fn make_module_archive(out_dir: &Path) -> Result<(), Error> {
             .write(archive_file, Compression::best()),
     );
 
+    // `std` and `std.system` are always needed for bootstrap. The other
+    // std modules are only included with the std-min feature.
+    let include_all = env::var_os("CARGO_FEATURE_STD_MIN").is_some();
+
     let mut add_modules = |dir_name| {
         let dir_path = Path::new("src").join("modules").join(dir_name);
         println!("cargo:rerun-if-changed={}", dir_path.display());
@@ -71,6 +130,9 @@ fn make_module_archive(out_dir: &Path) -> Result<(), Error> {
             if file.extension() == Some(OsStr::new("fi")) {
                 let file_name = file.file_name().unwrap().to_str().unwrap();
                 let file_name = file_name.strip_suffix(".fi").unwrap();
+                if !(include_all || matches!(file_name, "std" | "system")) {
+                    continue;
+                }
                 let name = if file_name == "std" {
                     "std".to_owned()
                 } else {
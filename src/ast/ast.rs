@@ -42,7 +42,9 @@ pub struct Statement {
 pub enum StatementKind {
     Break(Expr),
     Continue,
+    Global(Vec<String>),
     Import(String, Option<String>),
+    ImportFrom(String, Vec<(String, Option<String>)>),
     Jump(String),
     Label(String, Expr),
     Return(Expr),
@@ -64,6 +66,10 @@ impl Statement {
         Self::new(StatementKind::Continue, start, end)
     }
 
+    pub fn new_global(names: Vec<String>, start: Location, end: Location) -> Self {
+        Self::new(StatementKind::Global(names), start, end)
+    }
+
     pub fn new_import(
         name: String,
         as_name: Option<String>,
@@ -73,6 +79,15 @@ impl Statement {
         Self::new(StatementKind::Import(name, as_name), start, end)
     }
 
+    pub fn new_import_from(
+        path: String,
+        names: Vec<(String, Option<String>)>,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(StatementKind::ImportFrom(path, names), start, end)
+    }
+
     pub fn new_jump(name: String, start: Location, end: Location) -> Self {
         Self::new(StatementKind::Jump(name), start, end)
     }
@@ -117,6 +132,7 @@ impl fmt::Debug for StatementKind {
         match self {
             Self::Break(expr) => write!(f, "break {expr:?}"),
             Self::Continue => write!(f, "continue"),
+            Self::Global(names) => write!(f, "global {}", names.join(", ")),
             Self::Import(name, as_name) => {
                 if let Some(as_name) = as_name {
                     write!(f, "import {name:?} as {as_name:?}")
@@ -124,6 +140,9 @@ impl fmt::Debug for StatementKind {
                     write!(f, "import {name:?}")
                 }
             }
+            Self::ImportFrom(path, names) => {
+                write!(f, "from {path:?} import {names:?}")
+            }
             Self::Jump(label_index) => write!(f, "jump: {label_index}",),
             Self::Label(label_index, expr) => {
                 write!(f, "label: {label_index} {expr:?}")
@@ -149,14 +168,27 @@ pub enum ExprKind {
     Tuple(Vec<Expr>),
     List(Vec<Expr>),
     Map(Vec<(Expr, Expr)>),
+    Set(Vec<Expr>),
     Literal(Literal),
     FormatString(Vec<Expr>),
     Ident(Ident),
     Block(StatementBlock),
+    // Block that's only compiled in debug mode (`$debug -> ...`)
+    DebugBlock(StatementBlock),
     Conditional(Vec<(Expr, StatementBlock)>, Option<StatementBlock>),
+    // subject, arms, default block
+    Match(Box<Expr>, Vec<MatchArm>, Option<StatementBlock>),
     Loop(Box<Expr>, StatementBlock),
+    // try block, catch clauses (err type, var name, block), ensure block
+    Try(
+        StatementBlock,
+        Vec<(Option<Expr>, String, StatementBlock)>,
+        Option<StatementBlock>,
+    ),
     Func(Func),
     Call(Call),
+    Await(Box<Expr>),
+    Subscript(Box<Expr>, Box<Expr>),
     DeclarationAndAssignment(Box<Expr>, Box<Expr>),
     Assignment(Box<Expr>, Box<Expr>),
     UnaryOp(UnaryOperator, Box<Expr>),
@@ -183,6 +215,10 @@ impl Expr {
         Self::new(ExprKind::Map(entries), start, end)
     }
 
+    pub fn new_set(items: Vec<Expr>, start: Location, end: Location) -> Self {
+        Self::new(ExprKind::Set(items), start, end)
+    }
+
     fn new_literal(literal: Literal, start: Location, end: Location) -> Self {
         Self::new(ExprKind::Literal(literal), start, end)
     }
@@ -227,6 +263,14 @@ impl Expr {
         Self::new(ExprKind::Block(block), start, end)
     }
 
+    pub fn new_debug_block(
+        block: StatementBlock,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(ExprKind::DebugBlock(block), start, end)
+    }
+
     pub fn new_conditional(
         branches: Vec<(Expr, StatementBlock)>,
         default: Option<StatementBlock>,
@@ -236,6 +280,26 @@ impl Expr {
         Self::new(ExprKind::Conditional(branches, default), start, end)
     }
 
+    pub fn new_match(
+        subject: Expr,
+        arms: Vec<MatchArm>,
+        default: Option<StatementBlock>,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(ExprKind::Match(Box::new(subject), arms, default), start, end)
+    }
+
+    pub fn new_try(
+        block: StatementBlock,
+        catch_clauses: Vec<(Option<Expr>, String, StatementBlock)>,
+        ensure: Option<StatementBlock>,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(ExprKind::Try(block, catch_clauses, ensure), start, end)
+    }
+
     pub fn new_loop(
         expr: Expr,
         block: StatementBlock,
@@ -281,10 +345,11 @@ impl Expr {
     pub fn new_func(
         params: Params,
         block: StatementBlock,
+        is_async: bool,
         start: Location,
         end: Location,
     ) -> Self {
-        Self::new(ExprKind::Func(Func::new(params, block)), start, end)
+        Self::new(ExprKind::Func(Func::new(params, block, is_async)), start, end)
     }
 
     pub fn new_call(
@@ -296,6 +361,14 @@ impl Expr {
         Self::new(ExprKind::Call(Call::new(callable, args)), start, end)
     }
 
+    pub fn new_await(expr: Expr, start: Location, end: Location) -> Self {
+        Self::new(ExprKind::Await(Box::new(expr)), start, end)
+    }
+
+    pub fn new_subscript(obj: Expr, key: Expr, start: Location, end: Location) -> Self {
+        Self::new(ExprKind::Subscript(Box::new(obj), Box::new(key)), start, end)
+    }
+
     pub fn new_unary_op(
         op_token: &Token,
         a: Expr,
@@ -310,6 +383,11 @@ impl Expr {
         Self::new(kind, start, end)
     }
 
+    /// Convert an expression in a format string to its repr (`!r`).
+    pub fn new_repr(a: Expr, start: Location, end: Location) -> Self {
+        Self::new(ExprKind::UnaryOp(UnaryOperator::Repr, Box::new(a)), start, end)
+    }
+
     pub fn new_binary_op(
         a: Expr,
         op_token: &Token,
@@ -410,11 +488,74 @@ impl Expr {
         }
     }
 
+    /// Check if expression is an enum declaration, which is a call to
+    /// `enum` (e.g., `enum (Red, Green, Blue)`). If so, return the args,
+    /// which should be the enum's variants.
+    pub fn is_enum(&self) -> Option<&[Expr]> {
+        if let ExprKind::Call(Call { callable, args }) = &self.kind {
+            if callable.is_ident().as_deref() == Some("enum") {
+                return Some(args.as_slice());
+            }
+        }
+        None
+    }
+
+    /// Check if expression is a literal string. If so, return its
+    /// value.
+    pub fn is_string(&self) -> Option<String> {
+        if let ExprKind::Literal(Literal { kind: LiteralKind::String(value) }) =
+            &self.kind
+        {
+            Some(value.clone())
+        } else {
+            None
+        }
+    }
+
     /// Check if expression is a function.
     pub fn is_func(&self) -> bool {
         matches!(self.kind, ExprKind::Func(_))
     }
 
+    /// Get short, source-like text for identifiers and attribute,
+    /// call, and subscript chains built from them (e.g.,
+    /// `user.get_profile(...).name`), for use in error messages.
+    /// Call args and subscript keys are elided.
+    pub fn short_text(&self) -> Option<String> {
+        match &self.kind {
+            ExprKind::Ident(_) => self.ident_name(),
+            ExprKind::BinaryOp(obj, BinaryOperator::Dot, name)
+            | ExprKind::ShortCircuitCompareOp(
+                obj,
+                ShortCircuitCompareOperator::NilSafeDot,
+                name,
+            ) => {
+                let obj = obj.short_text()?;
+                let name = if let Some(name) = name.ident_name() {
+                    name
+                } else if let ExprKind::Literal(Literal { kind: LiteralKind::Int(i) }) =
+                    &name.kind
+                {
+                    i.to_string()
+                } else {
+                    return None;
+                };
+                let dot = if matches!(self.kind, ExprKind::BinaryOp(..)) {
+                    "."
+                } else {
+                    "?."
+                };
+                Some(format!("{obj}{dot}{name}"))
+            }
+            ExprKind::Call(Call { callable, args }) => {
+                let args = if args.is_empty() { "" } else { "..." };
+                Some(format!("{}({args})", callable.short_text()?))
+            }
+            ExprKind::Subscript(obj, _) => Some(format!("{}[...]", obj.short_text()?)),
+            _ => None,
+        }
+    }
+
     pub fn tuple_items(&self) -> Option<&Vec<Expr>> {
         if let ExprKind::Tuple(items) = &self.kind {
             Some(items)
@@ -436,6 +577,7 @@ impl fmt::Debug for ExprKind {
             Self::Tuple(items) => write!(f, "({items:?})"),
             Self::List(items) => write!(f, "[{items:?}]"),
             Self::Map(entries) => write!(f, "[{entries:?}]"),
+            Self::Set(items) => write!(f, "{{{items:?}}}"),
             Self::Literal(literal) => write!(f, "{literal:?}"),
             Self::FormatString(items) => write!(f, "{items:?}"),
             Self::Ident(ident) => write!(f, "{ident:?}"),
@@ -444,12 +586,21 @@ impl fmt::Debug for ExprKind {
             }
             Self::Assignment(ident, expr) => write!(f, "{ident:?} = {expr:?}"),
             Self::Block(block) => write!(f, "block {block:?}"),
+            Self::DebugBlock(block) => write!(f, "$debug {block:?}"),
             Self::Conditional(branches, default) => {
                 write!(f, "{branches:?} {default:?}")
             }
+            Self::Match(subject, arms, default) => {
+                write!(f, "match {subject:?} {arms:?} {default:?}")
+            }
             Self::Loop(expr, block) => write!(f, "loop {expr:?} {block:?}"),
+            Self::Try(block, catch_clauses, ensure) => {
+                write!(f, "try {block:?} {catch_clauses:?} {ensure:?}")
+            }
             Self::Func(func) => write!(f, "{func:?}"),
             Self::Call(func) => write!(f, "{func:?}"),
+            Self::Await(expr) => write!(f, "await {expr:?}"),
+            Self::Subscript(obj, key) => write!(f, "{obj:?}[{key:?}]"),
             Self::UnaryOp(op, a) => write!(f, "({op:?}{a:?})"),
             Self::BinaryOp(a, op, b) => write!(f, "({a:?} {op:?} {b:?})"),
             Self::CompareOp(a, op, b) => write!(f, "({a:?} {op:?} {b:?})"),
@@ -459,6 +610,42 @@ impl fmt::Debug for ExprKind {
     }
 }
 
+/// Match arm - a pattern with an optional guard and the block to run
+/// when the pattern matches and the guard (if present) is true.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub block: StatementBlock,
+}
+
+impl MatchArm {
+    pub fn new(pattern: Pattern, guard: Option<Expr>, block: StatementBlock) -> Self {
+        Self { pattern, guard, block }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// Matches values equal to the expression (e.g., `1` or `x`).
+    Value(Expr),
+    /// Matches values equal to any of the expressions (e.g.,
+    /// `1 | 2 | 3`).
+    Alt(Vec<Expr>),
+    /// Matches any value and binds it to the name.
+    Binding(String),
+    /// Matches instances of the type and binds them to the name (e.g.,
+    /// `Int i`).
+    Type(Expr, String),
+    /// Matches tuples with the same number of items where each item
+    /// matches the corresponding pattern (e.g., `(0, y)`).
+    Tuple(Vec<Pattern>),
+    /// Matches strings that start with the prefix and/or end with the
+    /// suffix and binds the rest of the string to the name (e.g.,
+    /// `"GET " + path` or `name + ".txt"`).
+    Str(Option<String>, String, Option<String>),
+}
+
 /// Block - a list of statements in a new scope.
 #[derive(Clone, PartialEq)]
 pub struct StatementBlock {
@@ -486,11 +673,12 @@ impl fmt::Debug for StatementBlock {
 pub struct Func {
     pub params: Params,
     pub block: StatementBlock,
+    pub is_async: bool,
 }
 
 impl Func {
-    pub fn new(params: Params, block: StatementBlock) -> Self {
-        Self { params, block }
+    pub fn new(params: Params, block: StatementBlock, is_async: bool) -> Self {
+        Self { params, block, is_async }
     }
 }
 
@@ -504,7 +692,8 @@ impl fmt::Debug for Func {
             }
         }
         let names = names.join(", ");
-        write!(f, "func ({names}) {:?}", self.block)
+        let prefix = if self.is_async { "async " } else { "" };
+        write!(f, "{prefix}func ({names}) {:?}", self.block)
     }
 }
 
@@ -5,8 +5,8 @@ use std::collections::HashSet;
 
 /// Find import statements in module AST.
 ///
-/// XXX: Currently, this only looks at top level statements... but
-///      perhaps import should only be allowed at the top level anyway?
+/// NOTE: This only looks at top level statements. Other imports, such
+///       as those in function bodies, are loaded lazily at runtime.
 pub struct ImportVisitor {
     imports: Vec<(String, Option<String>)>,
 }
@@ -29,10 +29,20 @@ impl ImportVisitor {
     }
 
     fn visit_statement(&mut self, statement: &ast::Statement) {
-        if let ast::StatementKind::Import(name, as_name) = &statement.kind {
-            if !self.imports.iter().any(|(n, _)| n == name) {
-                self.imports.push((name.to_owned(), as_name.to_owned()));
+        match &statement.kind {
+            ast::StatementKind::Import(name, as_name) => {
+                self.add_import(name, as_name.to_owned());
+            }
+            ast::StatementKind::ImportFrom(path, _names) => {
+                self.add_import(path, None);
             }
+            _ => (),
+        }
+    }
+
+    fn add_import(&mut self, name: &str, as_name: Option<String>) {
+        if !self.imports.iter().any(|(n, _)| n == name) {
+            self.imports.push((name.to_owned(), as_name));
         }
     }
 }
@@ -87,12 +97,28 @@ impl GlobalsNamesVisitor {
                 name.split('.').last().unwrap()
             };
             self.global_names.insert(declared_name.to_owned());
+        } else if let ast::StatementKind::ImportFrom(_path, names) = &statement.kind {
+            for (name, as_name) in names {
+                let declared_name = as_name.as_ref().unwrap_or(name);
+                self.global_names.insert(declared_name.to_owned());
+            }
         } else if let Some(expr) = statement.expr() {
-            if let Some((left, _right)) = expr.assignment() {
-                if let Some(name) = left.ident_name() {
-                    self.global_names.insert(name);
-                }
+            // Chained assignments (`a = b = 0`) assign to each target.
+            let mut expr = expr;
+            while let Some((left, right)) = expr.assignment() {
+                self.add_target_names(left);
+                expr = right;
             }
         }
     }
+
+    /// Add names from assignment target, which may be a tuple of
+    /// targets (e.g., `a, b = b, a`).
+    fn add_target_names(&mut self, target: &ast::Expr) {
+        if let Some(name) = target.ident_name() {
+            self.global_names.insert(name);
+        } else if let Some(items) = target.tuple_items() {
+            items.iter().for_each(|item| self.add_target_names(item));
+        }
+    }
 }
@@ -0,0 +1,44 @@
+//! Entry points for the individual stages of the pipeline, which are
+//! otherwise internal, so they can be measured separately by the
+//! benchmarks in `benches/`. Only built with the `bench` feature.
+//!
+//! Errors are returned as strings since the error types aren't public.
+use crate::ast;
+use crate::compiler::Compiler;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::source::source_from_text;
+
+/// Scan text and return the number of tokens.
+pub fn scan(text: &str) -> Result<usize, String> {
+    let mut source = source_from_text(text);
+    let mut count = 0;
+    for result in Scanner::new(&mut source) {
+        result.map_err(|err| format!("{err:?}"))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Scan and parse text and return the number of top level statements.
+pub fn parse(text: &str) -> Result<usize, String> {
+    parse_module(text).map(|module| module.statements.len())
+}
+
+/// Scan, parse, and compile text and return the number of top level
+/// instructions.
+pub fn compile(text: &str, optimize: bool) -> Result<usize, String> {
+    let module = parse_module(text)?;
+    let mut compiler = Compiler::default();
+    compiler.set_optimize(optimize);
+    let code = compiler
+        .compile_module_to_code("$bench", module)
+        .map_err(|err| format!("{err:?}"))?;
+    Ok(code.len_chunk())
+}
+
+fn parse_module(text: &str) -> Result<ast::Module, String> {
+    let mut source = source_from_text(text);
+    let mut parser = Parser::new(Scanner::new(&mut source));
+    parser.parse().map_err(|err| format!("{err:?}"))
+}
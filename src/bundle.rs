@@ -0,0 +1,508 @@
+//! Bundle a script and the modules it imports into a single script
+//! that can be run without a module search path (e.g., to share a
+//! program as one snippet or to run it in the browser).
+//!
+//! Each imported module that's found on the search path is included
+//! once, before the first module that imports it. Its globals are
+//! renamed (e.g., `x` in `app.util` becomes `app_util__x`) so they
+//! don't collide with globals from other modules, and a `Module` with
+//! the original names is created for importers to use in place of the
+//! import. std modules are built in, so imports of them are kept.
+//!
+//! Globals are renamed at the token level: every occurrence of a
+//! global's name in its module is renamed, except attribute names
+//! (e.g., `b` in `a.b`). Locals that shadow a global are renamed too,
+//! which doesn't change what they refer to.
+//!
+//! NOTE: Imported modules are executed when the bundle starts rather
+//!       than when they're imported, which only matters for imports
+//!       in functions.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::visitors::GlobalsNamesVisitor;
+use crate::format::{split_conversion, FormatStrToken};
+use crate::parser::{ParseErrKind, Parser};
+use crate::result::{ExeErr, ExeErrKind};
+use crate::scanner::{ScanTokensResult, Scanner, Token, TokenWithLocation};
+use crate::source::{source_from_text, Location};
+
+/// Bundle the script at `path` along with the modules it imports,
+/// using `find_module` to find the file for a module name.
+pub(crate) fn bundle(
+    path: &Path,
+    find_module: &dyn Fn(&str) -> Option<PathBuf>,
+) -> Result<String, ExeErr> {
+    let mut bundler = Bundler { find_module, modules: vec![], loading: vec![] };
+    let script = bundler.load("$main", path)?;
+    Ok(bundler.render(script))
+}
+
+struct Bundler<'a> {
+    find_module: &'a dyn Fn(&str) -> Option<PathBuf>,
+    // Imported modules, in the order they're included. Each module
+    // comes after the modules it imports.
+    modules: Vec<BundledModule>,
+    // Modules that are currently being loaded, used to detect circular
+    // imports.
+    loading: Vec<String>,
+}
+
+struct BundledModule {
+    name: String,
+    path: PathBuf,
+    text: String,
+    tokens: Vec<TokenWithLocation>,
+    imports: Vec<ImportStatement>,
+    // Sorted so the bundle is the same every time.
+    global_names: Vec<String>,
+}
+
+/// An `import` or `from ... import` statement.
+struct ImportStatement {
+    path: String,
+    // Index of the first token and the last token in the statement.
+    first: usize,
+    last: usize,
+    // The attribute of the module that's imported (or `None` for the
+    // module itself) and the name it's bound to.
+    bindings: Vec<(Option<String>, String)>,
+    is_from: bool,
+}
+
+impl Bundler<'_> {
+    /// Load module from `path`, first loading any modules it imports.
+    fn load(&mut self, name: &str, path: &Path) -> Result<BundledModule, ExeErr> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            let message = format!("{}: {err}", path.display());
+            ExeErr::new(ExeErrKind::CouldNotReadSourceFile(message))
+        })?;
+
+        let tokens =
+            scan(&text).map_err(|err| ExeErr::new(ExeErrKind::ScanErr(err.kind)))?;
+
+        let mut source = source_from_text(&text);
+        let ast_module =
+            Parser::new(Scanner::new(&mut source)).parse().map_err(|mut errors| {
+                match errors.swap_remove(0).kind {
+                    ParseErrKind::ScanErr(scan_err) => {
+                        ExeErr::new(ExeErrKind::ScanErr(scan_err.kind))
+                    }
+                    kind => ExeErr::new(ExeErrKind::ParseErr(kind)),
+                }
+            })?;
+        let mut visitor = GlobalsNamesVisitor::default();
+        visitor.visit_module(&ast_module);
+        let mut global_names: Vec<String> =
+            visitor.take_global_names().into_iter().collect();
+        global_names.sort();
+
+        let imports = find_imports(&tokens);
+        self.loading.push(name.to_owned());
+        for import in imports.iter() {
+            self.load_import(&import.path)?;
+        }
+        self.loading.pop();
+
+        Ok(BundledModule {
+            name: name.to_owned(),
+            path: path.to_path_buf(),
+            text,
+            tokens,
+            imports,
+            global_names,
+        })
+    }
+
+    /// Load an imported module unless it's a std module or has already
+    /// been loaded.
+    fn load_import(&mut self, name: &str) -> Result<(), ExeErr> {
+        if is_std_module(name) || self.modules.iter().any(|m| m.name == name) {
+            return Ok(());
+        }
+        if let Some(pos) = self.loading.iter().position(|n| n == name) {
+            let mut chain = self.loading[pos..].to_vec();
+            chain.push(name.to_owned());
+            return Err(ExeErr::new(ExeErrKind::CircularImport(chain.join(" -> "))));
+        }
+        let path = (self.find_module)(name)
+            .ok_or_else(|| ExeErr::new(ExeErrKind::ModuleNotFound(name.to_owned())))?;
+        let module = self.load(name, &path)?;
+        self.modules.push(module);
+        Ok(())
+    }
+
+    /// Render the bundle. Imported modules come first, each followed by
+    /// the creation of its `Module`, then the script.
+    fn render(&self, script: BundledModule) -> String {
+        // Names used anywhere in the bundle, which new names must not
+        // collide with.
+        let mut taken: HashSet<String> = HashSet::new();
+        for module in self.modules.iter().chain([&script]) {
+            collect_names(&module.tokens, &mut taken);
+        }
+
+        let mut module_vars: HashMap<&str, String> = HashMap::new();
+        let mut renames: Vec<HashMap<String, String>> = vec![];
+        for module in self.modules.iter() {
+            let var = unique_name(&mangle(&module.name, "module"), &mut taken);
+            module_vars.insert(&module.name, var);
+            let module_renames = module
+                .global_names
+                .iter()
+                .map(|name| {
+                    let new_name = unique_name(&mangle(&module.name, name), &mut taken);
+                    (name.clone(), new_name)
+                })
+                .collect();
+            renames.push(module_renames);
+        }
+
+        // Globals in the script keep their names unless an imported
+        // module uses the same name for something other than one of its
+        // own globals (e.g., if the script shadows a builtin that a
+        // module uses).
+        let mut free_names = HashSet::new();
+        for module in self.modules.iter() {
+            let mut names = HashSet::new();
+            collect_names(&module.tokens, &mut names);
+            free_names.extend(
+                names.into_iter().filter(|name| !module.global_names.contains(name)),
+            );
+        }
+        let script_renames = script
+            .global_names
+            .iter()
+            .filter(|name| !name.starts_with('$') && free_names.contains(*name))
+            .map(|name| (name.clone(), unique_name(&mangle("main", name), &mut taken)))
+            .collect();
+
+        let script_name = script.path.file_name().unwrap_or_default().to_string_lossy();
+        let mut output = format!("# Bundled from {script_name} by `feint bundle`\n");
+        for (module, renames) in self.modules.iter().zip(renames.iter()) {
+            let path = module.path.to_string_lossy();
+            output.push_str(&format!("\n# Module: {} ({path})\n", module.name));
+            output.push_str(&rewrite(module, renames, &module_vars));
+            let attrs: Vec<String> = module
+                .global_names
+                .iter()
+                .map(|name| format!("{}: {}", quote(name), renames[name]))
+                .collect();
+            output.push_str(&format!(
+                "{} = Module.new({}, {}, \"\", {{{}}})\n",
+                module_vars[module.name.as_str()],
+                quote(&module.name),
+                quote(&path),
+                attrs.join(", ")
+            ));
+        }
+        output.push_str(&format!("\n# Script: {script_name}\n"));
+        output.push_str(&rewrite(&script, &script_renames, &module_vars));
+        output
+    }
+}
+
+// Rewriting -----------------------------------------------------------
+
+/// A replacement for the text in a byte range.
+type Edit = (usize, usize, String);
+
+/// Rewrite a module's text, renaming its globals and replacing imports
+/// of bundled modules with assignments.
+fn rewrite(
+    module: &BundledModule,
+    renames: &HashMap<String, String>,
+    module_vars: &HashMap<&str, String>,
+) -> String {
+    let text = module.text.as_str();
+    let offsets = LineOffsets::new(text);
+    let mut edits = vec![];
+    let mut skip = HashSet::new();
+
+    for import in module.imports.iter() {
+        skip.extend(import.first..=import.last);
+        let start = offsets.start(&module.tokens[import.first].start);
+        let end = offsets.end(&module.tokens[import.last].end);
+        let rename = |name: &String| renames.get(name).unwrap_or(name).clone();
+        let replacement = if let Some(var) = module_vars.get(import.path.as_str()) {
+            let (targets, values): (Vec<_>, Vec<_>) = import
+                .bindings
+                .iter()
+                .map(|(attr, name)| {
+                    let value = match attr {
+                        Some(attr) => format!("{var}.{attr}"),
+                        None => var.clone(),
+                    };
+                    (rename(name), value)
+                })
+                .unzip();
+            format!("{} = {}", targets.join(", "), values.join(", "))
+        } else if import.bindings.iter().any(|(_, name)| renames.contains_key(name)) {
+            let names: Vec<String> = import
+                .bindings
+                .iter()
+                .map(|(attr, name)| {
+                    let attr = attr.as_ref().unwrap_or(&import.path);
+                    format!("{attr} as {}", rename(name))
+                })
+                .collect();
+            if import.is_from {
+                format!("from {} import {}", import.path, names.join(", "))
+            } else {
+                format!("import {}", names.join(", "))
+            }
+        } else {
+            continue;
+        };
+        edits.push((start, end, replacement));
+    }
+
+    rename_edits(text, &module.tokens, 0, renames, &skip, &mut edits);
+    edits.sort_by_key(|(start, _, _)| *start);
+
+    let mut output = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (start, end, replacement) in edits {
+        output.push_str(&text[pos..start]);
+        output.push_str(&replacement);
+        pos = end;
+    }
+    output.push_str(&text[pos..]);
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+/// Find the edits needed to rename idents in `text`, which starts at
+/// byte `base` of the module, including idents in the expressions in
+/// format strings. Tokens at the indexes in `skip` are skipped.
+fn rename_edits(
+    text: &str,
+    tokens: &[TokenWithLocation],
+    base: usize,
+    renames: &HashMap<String, String>,
+    skip: &HashSet<usize>,
+    edits: &mut Vec<Edit>,
+) {
+    let offsets = LineOffsets::new(text);
+    let mut prev_token = None;
+    for (i, token) in tokens.iter().enumerate() {
+        if skip.contains(&i) {
+            prev_token = Some(&token.token);
+            continue;
+        }
+        if let Token::FormatStr(_) = &token.token {
+            let start = offsets.start(&token.start);
+            let end = offsets.end(&token.end);
+            // Skip the $ and the quotes.
+            let inner = &text[start + 2..end - 1];
+            format_string_edits(inner, base + start + 2, renames, edits);
+        } else if let Some(name) = ident_name(&token.token) {
+            if let (Some(new_name), false) =
+                (renames.get(name), prev_token == Some(&Token::Dot))
+            {
+                let start = base + offsets.start(&token.start);
+                let end = base + offsets.end(&token.end);
+                edits.push((start, end, new_name.clone()));
+            }
+        }
+        prev_token = Some(&token.token);
+    }
+}
+
+/// Find the edits needed to rename idents in the expressions in the
+/// text of a format string. This mirrors `scan_format_string`.
+fn format_string_edits(
+    text: &str,
+    base: usize,
+    renames: &HashMap<String, String>,
+    edits: &mut Vec<Edit>,
+) {
+    let mut stack = vec![];
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && stack.is_empty() {
+            escaped = true;
+        } else if c == '{' {
+            stack.push(i);
+        } else if c == '}' {
+            if let Some(open) = stack.pop() {
+                if stack.is_empty() {
+                    let expr = &text[open + 1..i];
+                    let (trimmed, _) = split_conversion(expr.trim());
+                    let expr_base =
+                        base + open + 1 + (expr.len() - expr.trim_start().len());
+                    if let Ok(tokens) = scan(trimmed) {
+                        let skip = HashSet::new();
+                        rename_edits(
+                            trimmed, &tokens, expr_base, renames, &skip, edits,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Byte offsets of the lines in a text, used to convert token
+/// locations to byte offsets.
+struct LineOffsets<'a> {
+    text: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineOffsets<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self { text, starts }
+    }
+
+    /// Get the offset of the char at `loc`.
+    fn start(&self, loc: &Location) -> usize {
+        let line_start = self.starts[loc.line - 1];
+        self.text[line_start..]
+            .char_indices()
+            .nth(loc.col - 1)
+            .map_or(self.text.len(), |(i, _)| line_start + i)
+    }
+
+    /// Get the offset just past the char at `loc`.
+    fn end(&self, loc: &Location) -> usize {
+        let start = self.start(loc);
+        start + self.text[start..].chars().next().map_or(0, char::len_utf8)
+    }
+}
+
+// Utilities -----------------------------------------------------------
+
+fn scan(text: &str) -> ScanTokensResult {
+    let mut source = source_from_text(text);
+    Scanner::new(&mut source).collect()
+}
+
+fn is_std_module(name: &str) -> bool {
+    name == "std" || name.starts_with("std.")
+}
+
+fn ident_name(token: &Token) -> Option<&str> {
+    match token {
+        Token::Ident(name)
+        | Token::ConstIdent(name)
+        | Token::TypeIdent(name)
+        | Token::SpecialIdent(name) => Some(name.as_str()),
+        _ => None,
+    }
+}
+
+/// Collect the names of all idents in `tokens`, including idents in
+/// format strings.
+fn collect_names(tokens: &[TokenWithLocation], names: &mut HashSet<String>) {
+    for token in tokens.iter() {
+        if let Token::FormatStr(parts) = &token.token {
+            for part in parts.iter() {
+                if let FormatStrToken::Expr(expr_tokens)
+                | FormatStrToken::ReprExpr(expr_tokens) = part
+                {
+                    collect_names(expr_tokens, names);
+                }
+            }
+        } else if let Some(name) = ident_name(&token.token) {
+            names.insert(name.to_owned());
+        }
+    }
+}
+
+/// Find the import statements in `tokens`.
+fn find_imports(tokens: &[TokenWithLocation]) -> Vec<ImportStatement> {
+    let token_at = |i: usize| tokens.get(i).map(|t| &t.token);
+    // Get the name bound by an optional `as <name>` at i, if present,
+    // along with the index of the last token.
+    let as_name = |i: usize| match (token_at(i), token_at(i + 1).and_then(ident_name)) {
+        (Some(Token::As), Some(name)) => Some((name.to_owned(), i + 1)),
+        _ => None,
+    };
+
+    let mut imports = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        let path = match (&token.token, token_at(i + 1)) {
+            (Token::Import | Token::From, Some(Token::ImportPath(path))) => path,
+            _ => continue,
+        };
+        let mut bindings = vec![];
+        let mut last = i + 1;
+        let is_from = token.token == Token::From;
+        if is_from {
+            // from <path> import <name> [as <name>], ...
+            let mut j = i + 3;
+            while let Some(name) = token_at(j).and_then(ident_name) {
+                let (bound_name, end) =
+                    as_name(j + 1).unwrap_or_else(|| (name.to_owned(), j));
+                bindings.push((Some(name.to_owned()), bound_name));
+                last = end;
+                if token_at(end + 1) != Some(&Token::Comma) {
+                    break;
+                }
+                j = end + 2;
+            }
+        } else {
+            // import <path> [as <name>]
+            let default_name = path.split('.').next_back().unwrap().to_owned();
+            let (bound_name, end) = as_name(i + 2).unwrap_or((default_name, last));
+            bindings.push((None, bound_name));
+            last = end;
+        }
+        imports.push(ImportStatement {
+            path: path.clone(),
+            first: i,
+            last,
+            bindings,
+            is_from,
+        });
+    }
+    imports
+}
+
+/// Make a new name for a global in a module, keeping the style of the
+/// original name so it's scanned as the same kind of ident:
+///
+/// - `x` in `app.util` -> `app_util__x`
+/// - `MAX` in `app.util` -> `APP_UTIL__MAX`
+/// - `Point` in `app.util` -> `AppUtilPoint`
+fn mangle(module_name: &str, name: &str) -> String {
+    let name = name.trim_start_matches('$');
+    let prefix = module_name.replace('.', "_");
+    if !name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        format!("{prefix}__{name}")
+    } else if !name.contains(|c: char| c.is_ascii_lowercase()) {
+        format!("{}__{name}", prefix.to_ascii_uppercase())
+    } else {
+        let prefix: String = prefix
+            .split('_')
+            .filter(|part| !part.is_empty())
+            .map(|part| part[..1].to_ascii_uppercase() + &part[1..])
+            .collect();
+        format!("{prefix}{name}")
+    }
+}
+
+/// Return `name`, adding a number if it's already taken, and mark the
+/// result as taken.
+fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let mut unique = name.to_owned();
+    let mut n = 2;
+    while !taken.insert(unique.clone()) {
+        unique = format!("{name}{n}");
+        n += 1;
+    }
+    unique
+}
+
+/// Quote `s` as a string literal.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -18,12 +18,41 @@ pub fn build_cli() -> Command {
         .num_args(1)
         .help("Use this to run short snippets of code");
 
+    let no_print_arg = Arg::new("no_print")
+        .long("no-print")
+        .action(ArgAction::SetTrue)
+        .help("Don't print the value of the last expression when running code via -c?");
+
+    let watch_arg = Arg::new("watch")
+        .short('w')
+        .long("watch")
+        .action(ArgAction::SetTrue)
+        .requires("FILE_NAME")
+        .conflicts_with("code")
+        .help("Re-run script when it or the modules it imports change?");
+
     let dis_arg = Arg::new("dis")
         .short('i')
         .long("dis")
         .action(ArgAction::SetTrue)
         .help("disassemble instructions?");
 
+    let dis_json_arg = Arg::new("dis_json")
+        .long("dis-json")
+        .action(ArgAction::SetTrue)
+        .help("Disassemble instructions to JSON (for external tools)?");
+
+    let debugger_arg = Arg::new("debugger")
+        .long("debugger")
+        .action(ArgAction::SetTrue)
+        .help("Run script in the interactive debugger?");
+
+    let opt_arg = Arg::new("opt")
+        .short('O')
+        .long("opt")
+        .action(ArgAction::SetTrue)
+        .help("Enable optimizer (constant folding, jump optimization, etc)?");
+
     let history_path_arg = Arg::new("history_path")
         .long("history-path")
         .required(false)
@@ -36,6 +65,13 @@ pub fn build_cli() -> Command {
         .action(ArgAction::SetTrue)
         .help("Disable REPL history? [default: history enabled]");
 
+    let path_arg = Arg::new("path")
+        .short('p')
+        .long("path")
+        .action(ArgAction::Append)
+        .num_args(1)
+        .help("Add directory to module search path (can be repeated)");
+
     let argv_help = concat!(
         "Additional args will be set as system.argv.\n",
         "Can be used when running a script and with -c.\n",
@@ -45,7 +81,7 @@ pub fn build_cli() -> Command {
         Arg::new("argv").index(2).trailing_var_arg(true).num_args(0..).help(argv_help);
 
     Command::new("FeInt")
-        .version("0.0.0")
+        .version(env!("CARGO_PKG_VERSION"))
         .arg(
             Arg::new("max_call_depth")
                 .short('x')
@@ -62,14 +98,28 @@ pub fn build_cli() -> Command {
                 .action(ArgAction::SetTrue)
                 .value_parser(FalseyValueParser::new())
                 .env("FEINT_DEBUG")
-                .help("Enable debug mode?"),
+                .help("Enable debug mode? ($debug blocks are only run in debug mode)"),
+        )
+        .arg(
+            Arg::new("deterministic")
+                .long("deterministic")
+                .action(ArgAction::SetTrue)
+                .value_parser(FalseyValueParser::new())
+                .env("FEINT_DETERMINISTIC")
+                .help("Enable deterministic mode (fixed random seed, frozen clock)?"),
         )
         // Subcommand: run (when no subcommand is specified)
         .arg(&file_name_arg)
         .arg(&code_arg)
+        .arg(&no_print_arg)
+        .arg(&watch_arg)
         .arg(&dis_arg)
+        .arg(&dis_json_arg)
+        .arg(&debugger_arg)
+        .arg(&opt_arg)
         .arg(&history_path_arg)
         .arg(&no_history_arg)
+        .arg(&path_arg)
         .arg(&argv_arg)
         .subcommands([
             // Subcommand: run
@@ -77,13 +127,94 @@ pub fn build_cli() -> Command {
                 .about("Run script or code")
                 .arg(&file_name_arg)
                 .arg(&code_arg)
+                .arg(&no_print_arg)
+                .arg(&watch_arg)
                 .arg(&dis_arg)
+                .arg(&dis_json_arg)
+                .arg(&debugger_arg)
+                .arg(&opt_arg)
                 .arg(&history_path_arg)
                 .arg(&no_history_arg)
+                .arg(&path_arg)
                 .arg(&argv_arg),
+            // Subcommand: dis
+            Command::new("dis")
+                .about("Compile script or code without running it and disassemble it")
+                .arg(
+                    Arg::new("FILE_NAME")
+                        .index(1)
+                        .required_unless_present("code")
+                        .help(file_name_help),
+                )
+                .arg(
+                    Arg::new("code")
+                        .short('c')
+                        .long("code")
+                        .num_args(1)
+                        .conflicts_with("FILE_NAME")
+                        .help("Disassemble a short snippet of code"),
+                )
+                .arg(
+                    Arg::new("func")
+                        .short('f')
+                        .long("func")
+                        .num_args(1)
+                        .help("Only disassemble the function with this name"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Disassemble to JSON (for external tools)?"),
+                )
+                .arg(&opt_arg)
+                .arg(&path_arg),
+            // Subcommand: check
+            Command::new("check")
+                .about("Check scripts for errors without running them")
+                .arg(
+                    Arg::new("FILE_NAMES")
+                        .index(1)
+                        .required(true)
+                        .num_args(1..)
+                        .help("Script files or directories (searched for .fi files)"),
+                ),
+            // Subcommand: fmt
+            Command::new("fmt")
+                .about("Format scripts")
+                .arg(
+                    Arg::new("FILE_NAMES")
+                        .index(1)
+                        .required(true)
+                        .num_args(1..)
+                        .help("Script files or directories (searched for .fi files)"),
+                )
+                .arg(Arg::new("check").long("check").action(ArgAction::SetTrue).help(
+                    "List files that aren't formatted instead of formatting them",
+                )),
+            // Subcommand: lsp
+            Command::new("lsp").about("Run language server on stdin and stdout"),
+            // Subcommand: bundle
+            Command::new("bundle")
+                .about("Bundle script and the modules it imports into one file")
+                .arg(
+                    Arg::new("FILE_NAME")
+                        .index(1)
+                        .required(true)
+                        .help("Script to bundle"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .num_args(1)
+                        .help("File to write bundle to [default: stdout]"),
+                )
+                .arg(&path_arg),
             // Subcommand: test
             Command::new("test")
                 .about("Run test")
+                .arg(&path_arg)
                 .arg(Arg::new("argv").index(1).trailing_var_arg(true).num_args(0..)),
         ])
 }
@@ -1,7 +1,9 @@
 pub(crate) use compiler::Compiler;
 pub(crate) use result::{CompErr, CompErrKind};
+pub(crate) use scope::Var;
 
 mod compiler;
+mod optimizer;
 mod result;
 mod scope;
 mod visitor;
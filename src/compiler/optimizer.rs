@@ -0,0 +1,595 @@
+//! Optimizer. This runs on the code for a module or function after the
+//! compiler is done with it--after placeholders have been replaced and
+//! captured vars have been resolved--because removing instructions
+//! invalidates the addresses recorded during compilation.
+//!
+//! Passes:
+//!
+//! 1. Fold constant arithmetic and comparisons (e.g., `2 + 3` -> `5`)
+//! 2. Collapse jumps to unconditional jumps into a single jump
+//! 3. Remove unreachable code after unconditional jumps
+//! 4. Remove NoOps
+//! 5. Hoist loop-invariant loads out of loops
+//!
+//! Jump addresses are relative, so the jumps that cross removed or
+//! inserted instructions are adjusted. The addresses in the code's
+//! location table are adjusted too.
+use std::collections::HashSet;
+use std::mem;
+
+use num_traits::Zero;
+
+use crate::op::{BinaryOperator, CompareOperator, UnaryOperator};
+use crate::types::{new, ObjectRef, ObjectTrait};
+use crate::util::Symbol;
+use crate::vm::globals::{self, SHARED_INTS, SHARED_INT_INDEX};
+use crate::vm::{Code, Inst, LocationTable};
+
+/// `assigned_globals` are the globals that functions in the module
+/// assign (see `global`). Loads of these globals aren't hoisted, since
+/// any call could change them.
+pub fn optimize(code: &mut Code, assigned_globals: &HashSet<Symbol>) {
+    let mut chunk = code.take_chunk();
+
+    // XXX: All placeholders should have been replaced by now, but if
+    //      they haven't, their addresses can't be adjusted.
+    if chunk.iter().any(is_placeholder) {
+        code.set_chunk(chunk);
+        return;
+    }
+
+    let mut locations = code.take_locations();
+
+    let mut remove = vec![false; chunk.len()];
+    fold_constants(code, &mut chunk, &mut remove);
+    remove_insts(&mut chunk, &mut locations, &remove);
+
+    collapse_jumps(&mut chunk);
+
+    let mut remove = vec![false; chunk.len()];
+    find_unreachable(&chunk, &mut remove);
+    for (addr, inst) in chunk.iter().enumerate() {
+        if let Inst::NoOp = inst {
+            remove[addr] = true;
+        }
+    }
+    remove_insts(&mut chunk, &mut locations, &remove);
+
+    hoist_loop_invariants(&mut chunk, &mut locations, assigned_globals);
+
+    code.set_chunk(chunk);
+    code.set_locations(locations);
+}
+
+// Constant Folding ----------------------------------------------------
+
+/// Fold operations on constant Ints and Floats. This works like a
+/// stack machine that only tracks the constants loaded since the last
+/// instruction that couldn't be folded. The first operand of a folded
+/// operation is replaced with the result, and the other instructions
+/// are marked for removal.
+///
+/// Operations are only folded when none of their instructions other
+/// than the first operand is a jump target.
+fn fold_constants(code: &mut Code, chunk: &mut [Inst], remove: &mut [bool]) {
+    let targets = jump_targets(chunk);
+    let mut operands: Vec<(usize, ObjectRef)> = vec![];
+
+    for addr in 0..chunk.len() {
+        if targets[addr] {
+            operands.clear();
+        }
+
+        if let Some(obj_ref) = get_const(code, &chunk[addr]) {
+            operands.push((addr, obj_ref));
+            continue;
+        }
+
+        let result = match &chunk[addr] {
+            Inst::UnaryOp(op) if !operands.is_empty() => {
+                let (a_addr, a) = operands.pop().unwrap();
+                fold_unary_op(op, &a).map(|result| (a_addr, vec![addr], result))
+            }
+            Inst::BinaryOp(op) if operands.len() > 1 => {
+                let (b_addr, b) = operands.pop().unwrap();
+                let (a_addr, a) = operands.pop().unwrap();
+                fold_binary_op(op, &a, &b)
+                    .map(|result| (a_addr, vec![b_addr, addr], result))
+            }
+            Inst::CompareOp(op) if operands.len() > 1 => {
+                let (b_addr, b) = operands.pop().unwrap();
+                let (a_addr, a) = operands.pop().unwrap();
+                fold_compare_op(op, &a, &b)
+                    .map(|result| (a_addr, vec![b_addr, addr], result))
+            }
+            _ => None,
+        };
+
+        if let Some((result_addr, removed_addrs, result)) = result {
+            chunk[result_addr] = load_const(code, result.clone());
+            for removed_addr in removed_addrs {
+                remove[removed_addr] = true;
+            }
+            operands.push((result_addr, result));
+        } else {
+            operands.clear();
+        }
+    }
+}
+
+/// Get the constant loaded by inst if it's an Int or Float.
+fn get_const(code: &Code, inst: &Inst) -> Option<ObjectRef> {
+    let obj_ref = match inst {
+        Inst::LoadConst(index) => code.get_const(*index).ok()?.clone(),
+        Inst::LoadGlobalConst(index) if *index >= SHARED_INT_INDEX => {
+            SHARED_INTS.get(index - SHARED_INT_INDEX)?.clone()
+        }
+        _ => return None,
+    };
+    if is_number(&*obj_ref.read().unwrap()) {
+        Some(obj_ref)
+    } else {
+        None
+    }
+}
+
+/// Get the instruction that loads obj, which must be an Int, Float, or
+/// Bool. Shared Ints are loaded the same way the compiler loads them.
+fn load_const(code: &mut Code, obj_ref: ObjectRef) -> Inst {
+    let obj = obj_ref.read().unwrap();
+    if let Some(value) = obj.get_bool_val() {
+        return if *value { Inst::LoadTrue } else { Inst::LoadFalse };
+    }
+    if let Some(index) = obj.get_int_val().and_then(globals::shared_int_index) {
+        return Inst::LoadGlobalConst(index);
+    }
+    drop(obj);
+    Inst::LoadConst(code.add_const(obj_ref))
+}
+
+fn is_number(obj: &dyn ObjectTrait) -> bool {
+    obj.is_int() || obj.is_float()
+}
+
+fn is_zero(obj: &dyn ObjectTrait) -> bool {
+    if let Some(value) = obj.get_int_val() {
+        value.is_zero()
+    } else if let Some(value) = obj.get_float_val() {
+        *value == 0.0
+    } else {
+        false
+    }
+}
+
+fn fold_unary_op(op: &UnaryOperator, a: &ObjectRef) -> Option<ObjectRef> {
+    let a = a.read().unwrap();
+    let result = match op {
+        UnaryOperator::Negate => a.negate().ok()?,
+        _ => return None,
+    };
+    check_result(result)
+}
+
+fn fold_binary_op(
+    op: &BinaryOperator,
+    a: &ObjectRef,
+    b: &ObjectRef,
+) -> Option<ObjectRef> {
+    use BinaryOperator::*;
+    let a = a.read().unwrap();
+    let b = b.read().unwrap();
+    let b = &*b;
+    // Division by zero is left as is so the error is raised at runtime.
+    if matches!(op, Div | FloorDiv | Mod) && is_zero(b) {
+        return None;
+    }
+    let result = match op {
+        Pow => a.pow(b),
+        Mul => a.mul(b),
+        Div => a.div(b),
+        FloorDiv => a.floor_div(b),
+        Mod => a.modulo(b),
+        Add => a.add(b),
+        Sub => a.sub(b),
+        _ => return None,
+    };
+    check_result(result.ok()?)
+}
+
+fn fold_compare_op(
+    op: &CompareOperator,
+    a: &ObjectRef,
+    b: &ObjectRef,
+) -> Option<ObjectRef> {
+    use CompareOperator::*;
+    let a = a.read().unwrap();
+    let b = b.read().unwrap();
+    let b = &*b;
+    let result = match op {
+        IsEqual => a.is_equal(b),
+        NotEqual => !a.is_equal(b),
+        LessThan => a.less_than(b).ok()?,
+        LessThanOrEqual => a.less_than(b).ok()? || a.is_equal(b),
+        GreaterThan => a.greater_than(b).ok()?,
+        GreaterThanOrEqual => a.greater_than(b).ok()? || a.is_equal(b),
+        _ => return None,
+    };
+    Some(new::bool(result))
+}
+
+/// Only keep results that are numbers. In particular, operations
+/// that return an `Err` object aren't folded.
+fn check_result(result: ObjectRef) -> Option<ObjectRef> {
+    if is_number(&*result.read().unwrap()) {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+// Loop-Invariant Loads ------------------------------------------------
+
+/// A load that gives the same value in every iteration of a loop, as
+/// long as the loop doesn't assign the name.
+#[derive(Clone, Copy, PartialEq)]
+enum InvariantLoad {
+    Builtin(Symbol),
+    Global(Symbol),
+    Captured(Symbol),
+}
+
+impl InvariantLoad {
+    fn from_inst(inst: &Inst) -> Option<Self> {
+        match inst {
+            Inst::LoadBuiltin(name) => Some(Self::Builtin(*name)),
+            Inst::LoadGlobal(name) => Some(Self::Global(*name)),
+            Inst::LoadCaptured(name) => Some(Self::Captured(*name)),
+            _ => None,
+        }
+    }
+
+    fn to_inst(self) -> Inst {
+        match self {
+            Self::Builtin(name) => Inst::LoadBuiltin(name),
+            Self::Global(name) => Inst::LoadGlobal(name),
+            Self::Captured(name) => Inst::LoadCaptured(name),
+        }
+    }
+
+    fn name(self) -> Symbol {
+        match self {
+            Self::Builtin(name) | Self::Global(name) | Self::Captured(name) => name,
+        }
+    }
+}
+
+/// Hoist loads of builtins, globals, and captured vars out of loops.
+/// Each load is done once, before the loop's condition is first
+/// evaluated, and stored in a new slot in the loop's scope. The loads
+/// in the loop are replaced with loads from the slot.
+///
+/// Constants aren't hoisted since they're already loaded by index.
+///
+/// Globals and captured vars are only hoisted when they aren't
+/// assigned in the loop. Globals that functions assign are never
+/// hoisted. Captured vars aren't hoisted out of loops that await, since
+/// the function that owns the captured var could assign it while the
+/// loop is suspended.
+///
+/// NOTE: A hoisted global is looked up even if the loop's body never
+///       runs, so a global that isn't defined yet when the loop starts
+///       is an error.
+fn hoist_loop_invariants(
+    chunk: &mut Vec<Inst>,
+    locations: &mut LocationTable,
+    assigned_globals: &HashSet<Symbol>,
+) {
+    // Loops are found by their backward jumps to the top of the loop.
+    // They're processed from the bottom up so that inserting
+    // instructions into one loop doesn't move the tops of the loops
+    // that haven't been processed yet.
+    let mut loop_tops: Vec<usize> = chunk
+        .iter()
+        .enumerate()
+        .filter_map(|(addr, inst)| match inst {
+            Inst::Jump(_, false, _) => get_target(addr, inst),
+            _ => None,
+        })
+        .collect();
+    loop_tops.sort_unstable();
+    loop_tops.dedup();
+    for top in loop_tops.into_iter().rev() {
+        hoist_loop_loads(chunk, locations, top, assigned_globals);
+    }
+}
+
+fn hoist_loop_loads(
+    chunk: &mut Vec<Inst>,
+    locations: &mut LocationTable,
+    top: usize,
+    assigned_globals: &HashSet<Symbol>,
+) {
+    // The bottom of the loop is the last jump back to the top, which
+    // may be a `continue` jump or the jump at the end of the loop.
+    let bottom = chunk
+        .iter()
+        .enumerate()
+        .skip(top)
+        .filter(|(addr, inst)| {
+            is_jump(inst) && *addr > top && get_target(*addr, inst) == Some(top)
+        })
+        .map(|(addr, _)| addr)
+        .next_back();
+    let (bottom, declare_addr) = match (bottom, loop_scope_locals(chunk, top)) {
+        (Some(bottom), Some(declare_addr)) => (bottom, declare_addr),
+        _ => return,
+    };
+
+    let body = &chunk[top..=bottom];
+    let awaits = body.iter().any(|inst| matches!(inst, Inst::Await));
+    let mut assigned: HashSet<Symbol> = assigned_globals.clone();
+    for inst in body.iter() {
+        match inst {
+            Inst::DeclareVar(name) | Inst::AssignVar(name) | Inst::AssignCell(name) => {
+                assigned.insert(*name);
+            }
+            Inst::DeclareLocals(names) => assigned.extend(names.iter().copied()),
+            _ => (),
+        }
+    }
+
+    // Loads with the same name as a load that's already hoisted are
+    // skipped so that the names of the new slots are unique.
+    let mut loads: Vec<InvariantLoad> = vec![];
+    for load in body.iter().filter_map(InvariantLoad::from_inst) {
+        let is_invariant = match load {
+            InvariantLoad::Builtin(_) => true,
+            InvariantLoad::Global(name) => !assigned.contains(&name),
+            InvariantLoad::Captured(name) => !awaits && !assigned.contains(&name),
+        };
+        if is_invariant && !loads.iter().any(|l| l.name() == load.name()) {
+            loads.push(load);
+        }
+    }
+    if loads.is_empty() {
+        return;
+    }
+
+    let first_slot = match &mut chunk[declare_addr] {
+        Inst::DeclareLocals(names) => {
+            let first_slot = names.len();
+            names.extend(
+                loads.iter().map(|l| Symbol::intern(&format!("$hoisted.{}", l.name()))),
+            );
+            first_slot
+        }
+        _ => unreachable!("Expected DeclareLocals at {declare_addr}"),
+    };
+
+    // Replace the loads in the loop, tracking how deeply nested in the
+    // loop's scope each one is.
+    let mut depth = 0;
+    for inst in chunk[top..=bottom].iter_mut() {
+        match inst {
+            Inst::ScopeStart => depth += 1,
+            Inst::ScopeEnd => depth -= 1,
+            _ => {
+                let slot = InvariantLoad::from_inst(inst)
+                    .and_then(|load| loads.iter().position(|l| *l == load));
+                if let Some(slot) = slot {
+                    *inst = Inst::LoadHoisted(depth, first_slot + slot);
+                }
+            }
+        }
+    }
+
+    let mut hoisted = vec![];
+    for (slot, load) in loads.into_iter().enumerate() {
+        hoisted.push(load.to_inst());
+        hoisted.push(Inst::StoreLocal(first_slot + slot));
+        hoisted.push(Inst::Pop);
+    }
+    insert_insts(chunk, locations, top, hoisted);
+}
+
+/// Find the DeclareLocals instruction of the scope that contains the
+/// top of a loop. This is the scope the compiler enters before a loop's
+/// condition, which is only entered once.
+fn loop_scope_locals(chunk: &[Inst], top: usize) -> Option<usize> {
+    let mut nesting = 0;
+    for addr in (0..top).rev() {
+        match &chunk[addr] {
+            Inst::ScopeEnd => nesting += 1,
+            Inst::ScopeStart if nesting == 0 => {
+                return match chunk.get(addr + 1) {
+                    Some(Inst::DeclareLocals(_)) => Some(addr + 1),
+                    _ => None,
+                };
+            }
+            Inst::ScopeStart => nesting -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+// Jumps ---------------------------------------------------------------
+
+/// Replace jumps that land on an unconditional jump with a jump to the
+/// final target. The scope exit counts of the jumps are combined.
+fn collapse_jumps(chunk: &mut [Inst]) {
+    for addr in 0..chunk.len() {
+        if !is_jump(&chunk[addr]) {
+            continue;
+        }
+        let original_target = get_target(addr, &chunk[addr]).unwrap();
+        let mut target = original_target;
+        let mut scope_exits = 0;
+        // The hop limit guards against jump cycles (e.g., `loop -> nil`
+        // compiled to a jump to itself).
+        for _ in 0..chunk.len() {
+            match chunk.get(target) {
+                Some(inst @ Inst::Jump(_, _, count)) if target != addr => {
+                    scope_exits += *count;
+                    target = get_target(target, inst).unwrap();
+                }
+                _ => break,
+            }
+        }
+        if target != original_target {
+            set_target(addr, &mut chunk[addr], target);
+            add_scope_exits(&mut chunk[addr], scope_exits);
+        }
+    }
+}
+
+/// Mark the instructions after an unconditional jump that aren't jump
+/// targets, up to the next jump target, for removal.
+fn find_unreachable(chunk: &[Inst], remove: &mut [bool]) {
+    let targets = jump_targets(chunk);
+    let mut reachable = true;
+    for (addr, inst) in chunk.iter().enumerate() {
+        if targets[addr] {
+            reachable = true;
+        }
+        if !reachable {
+            remove[addr] = true;
+        } else if let Inst::Jump(..) = inst {
+            reachable = false;
+        }
+    }
+}
+
+fn is_jump(inst: &Inst) -> bool {
+    matches!(
+        inst,
+        Inst::Jump(..)
+            | Inst::JumpPushNil(..)
+            | Inst::JumpIf(..)
+            | Inst::JumpIfNot(..)
+            | Inst::JumpIfNotNil(..)
+    )
+}
+
+fn is_placeholder(inst: &Inst) -> bool {
+    matches!(
+        inst,
+        Inst::Placeholder(..)
+            | Inst::FreeVarPlaceholder(..)
+            | Inst::BreakPlaceholder(..)
+            | Inst::ContinuePlaceholder(..)
+            | Inst::ReturnPlaceholder(..)
+    )
+}
+
+/// Get the absolute target address of a jump or handler instruction.
+fn get_target(addr: usize, inst: &Inst) -> Option<usize> {
+    use Inst::*;
+    match inst {
+        Jump(rel_addr, forward, _)
+        | JumpPushNil(rel_addr, forward, _)
+        | JumpIf(rel_addr, forward, _)
+        | JumpIfNot(rel_addr, forward, _)
+        | JumpIfNotNil(rel_addr, forward, _) => {
+            Some(if *forward { addr + rel_addr } else { addr - rel_addr })
+        }
+        PushHandler(rel_addr) => Some(addr + rel_addr),
+        _ => None,
+    }
+}
+
+/// Point the jump or handler instruction at `addr` to `target`.
+fn set_target(addr: usize, inst: &mut Inst, target: usize) {
+    use Inst::*;
+    match inst {
+        Jump(rel_addr, forward, _)
+        | JumpPushNil(rel_addr, forward, _)
+        | JumpIf(rel_addr, forward, _)
+        | JumpIfNot(rel_addr, forward, _)
+        | JumpIfNotNil(rel_addr, forward, _) => {
+            *forward = target >= addr;
+            *rel_addr = target.abs_diff(addr);
+        }
+        PushHandler(rel_addr) => *rel_addr = target - addr,
+        _ => panic!("Expected jump or handler instruction: {inst:?}"),
+    }
+}
+
+fn add_scope_exits(inst: &mut Inst, n: usize) {
+    use Inst::*;
+    match inst {
+        Jump(_, _, count)
+        | JumpPushNil(_, _, count)
+        | JumpIf(_, _, count)
+        | JumpIfNot(_, _, count)
+        | JumpIfNotNil(_, _, count) => *count += n,
+        _ => panic!("Expected jump instruction: {inst:?}"),
+    }
+}
+
+/// Find the addresses that jumps and handlers point to. The result
+/// has an extra entry for the address just past the end of the chunk.
+fn jump_targets(chunk: &[Inst]) -> Vec<bool> {
+    let mut targets = vec![false; chunk.len() + 1];
+    for (addr, inst) in chunk.iter().enumerate() {
+        if let Some(target) = get_target(addr, inst) {
+            targets[target] = true;
+        }
+    }
+    targets
+}
+
+/// Insert instructions before the instruction at `addr` and adjust
+/// jumps. Jumps to `addr` land after the inserted instructions. The
+/// inserted instructions belong to the statement that contains `addr`.
+fn insert_insts(
+    chunk: &mut Vec<Inst>,
+    locations: &mut LocationTable,
+    addr: usize,
+    insts: Vec<Inst>,
+) {
+    let n = insts.len();
+    let new_addr =
+        |old_addr: usize| if old_addr >= addr { old_addr + n } else { old_addr };
+    for (old_addr, inst) in chunk.iter_mut().enumerate() {
+        if let Some(target) = get_target(old_addr, inst) {
+            set_target(new_addr(old_addr), inst, new_addr(target));
+        }
+    }
+    chunk.splice(addr..addr, insts);
+    locations.remap(|old_addr| if old_addr > addr { old_addr + n } else { old_addr });
+}
+
+/// Remove the instructions marked for removal and adjust jumps. A jump
+/// to a removed instruction is redirected to the next instruction
+/// that's kept. Likewise, the location of a statement that starts with
+/// a removed instruction starts at the next instruction that's kept.
+fn remove_insts(chunk: &mut Vec<Inst>, locations: &mut LocationTable, remove: &[bool]) {
+    if !remove.iter().any(|r| *r) {
+        return;
+    }
+
+    // Map old addresses to new addresses, including the address just
+    // past the end of the chunk.
+    let mut new_addrs = Vec::with_capacity(chunk.len() + 1);
+    let mut new_addr = 0;
+    for removed in remove.iter() {
+        new_addrs.push(new_addr);
+        if !removed {
+            new_addr += 1;
+        }
+    }
+    new_addrs.push(new_addr);
+
+    let old_chunk = mem::take(chunk);
+    for (addr, mut inst) in old_chunk.into_iter().enumerate() {
+        if remove[addr] {
+            continue;
+        }
+        if let Some(target) = get_target(addr, &inst) {
+            set_target(new_addrs[addr], &mut inst, new_addrs[target]);
+        }
+        chunk.push(inst);
+    }
+    locations.remap(|addr| new_addrs[addr]);
+}
@@ -73,10 +73,42 @@ impl CompErr {
         Self::new(CompErrKind::GlobalNotFound(name.into(), start, end))
     }
 
+    pub fn global_after_local(name: String, start: Location, end: Location) -> Self {
+        Self::new(CompErrKind::GlobalAfterLocal(name, start, end))
+    }
+
+    pub fn global_not_declared(name: String, start: Location, end: Location) -> Self {
+        Self::new(CompErrKind::GlobalNotDeclared(name, start, end))
+    }
+
     pub fn var_args_must_be_last(start: Location, end: Location) -> Self {
         Self::new(CompErrKind::VarArgsMustBeLast(start, end))
     }
 
+    pub fn expected_type_member(start: Location, end: Location) -> Self {
+        Self::new(CompErrKind::ExpectedTypeMember(start, end))
+    }
+
+    pub fn type_fields_cannot_have_var_args(start: Location, end: Location) -> Self {
+        Self::new(CompErrKind::TypeFieldsCannotHaveVarArgs(start, end))
+    }
+
+    pub fn expected_enum_variant(start: Location, end: Location) -> Self {
+        Self::new(CompErrKind::ExpectedEnumVariant(start, end))
+    }
+
+    pub fn duplicate_enum_variant(
+        name: String,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(CompErrKind::DuplicateEnumVariant(name, start, end))
+    }
+
+    pub fn this_outside_method(start: Location, end: Location) -> Self {
+        Self::new(CompErrKind::ThisOutsideMethod(start, end))
+    }
+
     pub fn print<S: Into<String>>(msg: S, start: Location, end: Location) -> Self {
         Self::new(CompErrKind::Print(msg.into(), start, end))
     }
@@ -93,7 +125,14 @@ impl CompErr {
             CannotReassignSpecialIdent(_, start, end) => (start, end),
             MainMustBeFunc(start, end) => (start, end),
             GlobalNotFound(_, start, end) => (start, end),
+            GlobalAfterLocal(_, start, end) => (start, end),
+            GlobalNotDeclared(_, start, end) => (start, end),
             VarArgsMustBeLast(start, end) => (start, end),
+            ExpectedTypeMember(start, end) => (start, end),
+            TypeFieldsCannotHaveVarArgs(start, end) => (start, end),
+            ExpectedEnumVariant(start, end) => (start, end),
+            DuplicateEnumVariant(_, start, end) => (start, end),
+            ThisOutsideMethod(start, end) => (start, end),
             Print(_, start, end) => (start, end),
         };
         (*start, *end)
@@ -112,6 +151,13 @@ pub enum CompErrKind {
     CannotReassignSpecialIdent(String, Location, Location),
     MainMustBeFunc(Location, Location),
     GlobalNotFound(String, Location, Location),
+    GlobalAfterLocal(String, Location, Location),
+    GlobalNotDeclared(String, Location, Location),
     VarArgsMustBeLast(Location, Location),
+    ExpectedTypeMember(Location, Location),
+    TypeFieldsCannotHaveVarArgs(Location, Location),
+    ExpectedEnumVariant(Location, Location),
+    DuplicateEnumVariant(String, Location, Location),
+    ThisOutsideMethod(Location, Location),
     Print(String, Location, Location),
 }
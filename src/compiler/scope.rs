@@ -1,7 +1,11 @@
 //! The scope tree keeps track of nested scopes during compilation.
-//! Currently, it's only used resolve jump targets to labels.
+//! It's used to resolve vars and jump targets. The language server also
+//! uses it to find where a module's globals are declared.
 use std::collections::{HashMap, HashSet};
 
+use crate::source::Location;
+
+#[derive(Clone)]
 pub struct ScopeTree {
     storage: Vec<Scope>,
     pointer: usize,
@@ -52,6 +56,17 @@ impl ScopeTree {
         }
     }
 
+    /// Get global vars for this scope tree in the order they were
+    /// declared. Like `global_names()`, this will only be populated if
+    /// the tree's initial scope is a module.
+    pub fn global_vars(&self) -> &[Var] {
+        if self.is_module() {
+            &self.storage[0].vars
+        } else {
+            &[]
+        }
+    }
+
     // Traversal -------------------------------------------------------
 
     fn parent_index(&self) -> Option<usize> {
@@ -135,27 +150,44 @@ impl ScopeTree {
 
     // Vars ------------------------------------------------------------
 
-    /// Add var to *current* scope if it's not already present.
-    pub fn add_var<S: Into<String>>(&mut self, addr: usize, name: S, assigned: bool) {
+    /// Add var to *current* scope if it's not already present. `start`
+    /// is where the var is declared in the source.
+    pub fn add_var<S: Into<String>>(
+        &mut self,
+        addr: usize,
+        name: S,
+        assigned: bool,
+        start: Location,
+    ) {
         let name = name.into();
         let pointer = self.pointer;
-        if !self.get(pointer).vars.iter().any(|v| v.name == name) {
+        if !self.get(pointer).var_index.contains_key(&name) {
             let depth = self.depth();
-            self.current_mut().vars.push(Var { addr, pointer, name, assigned, depth });
+            let scope = self.current_mut();
+            let index = scope.vars.len();
+            scope.var_index.insert(name.clone(), index);
+            scope.vars.push(Var { addr, pointer, name, assigned, depth, index, start });
         }
     }
 
+    /// Get the names of the vars in the specified scope in the order
+    /// they were added (i.e., in slot order).
+    pub fn var_names(&self, pointer: usize) -> Vec<String> {
+        self.get(pointer).vars.iter().map(|v| v.name.clone()).collect()
+    }
+
+    /// Whether var is a module global, as opposed to a local var.
+    /// Globals are accessed by name; locals are accessed by slot.
+    pub fn is_global(&self, var: &Var) -> bool {
+        self.is_module() && var.pointer == 0
+    }
+
     /// Mark var in scope with name as assigned. Note that var *must*
     /// exist in the specified scope or this will panic.
     pub fn mark_assigned(&mut self, pointer: usize, name: &str) {
         let scope = self.get_mut(pointer);
-        let result =
-            scope.vars.iter().position(|v| v.pointer == pointer && v.name == name);
-        if let Some(index) = result {
-            let var = &scope.vars[index];
-            let mut new_var = var.clone();
-            new_var.assigned = true;
-            scope.vars[index] = new_var;
+        if let Some(&index) = scope.var_index.get(name) {
+            scope.vars[index].assigned = true;
         } else {
             panic!("Var does not exist in scope {pointer}: {name}")
         }
@@ -169,8 +201,8 @@ impl ScopeTree {
             self.current()
         };
         loop {
-            if let Some(var) = scope.vars.iter().find(|v| v.name == name) {
-                return Some(var.clone());
+            if let Some(&index) = scope.var_index.get(name) {
+                return Some(scope.vars[index].clone());
             }
             if let Some(parent_index) = scope.parent {
                 scope = self.get(parent_index)
@@ -222,15 +254,23 @@ pub struct Var {
     pub name: String,
     pub assigned: bool,
     pub depth: usize,
+    // Slot index of the var in its scope.
+    pub index: usize,
+    // Where the var is declared. This is the default location for vars
+    // that aren't declared in the source, such as params and temp vars.
+    pub start: Location,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Scope {
     kind: ScopeKind,
     index: usize,
     parent: Option<usize>,
     children: Vec<usize>,
     vars: Vec<Var>,
+    /// var name => var index, so lookups don't have to scan all the
+    /// vars in large (e.g., machine-generated) modules
+    var_index: HashMap<String, usize>,
     /// target label name => jump inst address
     jumps: Vec<(String, usize)>,
     /// label name => label inst address
@@ -245,6 +285,7 @@ impl Scope {
             parent,
             children: vec![],
             vars: vec![],
+            var_index: HashMap::new(),
             jumps: vec![],
             labels: HashMap::new(),
         }
@@ -2,6 +2,9 @@
 use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Formatter;
+use std::mem;
+
+use num_bigint::BigInt;
 
 use crate::ast;
 use crate::modules::std::STD;
@@ -10,17 +13,24 @@ use crate::op::{
     UnaryOperator,
 };
 use crate::source::Location;
-use crate::types::{new, ObjectRef};
+use crate::types::{new, ObjectRef, Params};
+use crate::util::Symbol;
 use crate::vm::{globals, Code, Inst, PrintFlags};
 
 use super::result::{CompErr, VisitResult};
-use super::scope::{Scope, ScopeKind, ScopeTree};
-
-type FuncNode = (
-    String,    // name
-    usize,     // address
-    usize,     // scope tree pointer
-    ast::Func, // node
+use super::scope::{Scope, ScopeKind, ScopeTree, Var};
+
+/// List, tuple, and map literals with more items than this are built
+/// in chunks so that all of their items don't have to be pushed onto
+/// the value stack at once.
+const LITERAL_CHUNK_SIZE: usize = 256;
+
+type FuncUnit = (
+    String,          // name
+    usize,           // address of function constant placeholder
+    Params,          // params
+    bool,            // is method
+    CompilerVisitor, // visitor holding the function's code
 );
 
 /// This visitor traverses the AST generated by the parser and creates
@@ -31,10 +41,50 @@ pub struct CompilerVisitor {
     initial_scope_kind: ScopeKind,
     global_names: HashSet<String>,
     name: String,
+    // Whether `this` can be referenced, which is only the case in
+    // methods and functions nested in methods.
+    allow_this: bool,
+    // For functions nested in methods, the level of the enclosing
+    // scope tree of the method, which `this` is captured from. This is
+    // `None` for methods and other code units, which use their own
+    // `this`.
+    this_capture_level: Option<usize>,
+    // Whether `$debug` blocks are compiled. When they aren't, they're
+    // replaced with nil.
+    debug: bool,
     pub(crate) code: Code,
     pub(crate) scope_tree: ScopeTree,
     pub(crate) scope_depth: usize,
-    pub(crate) func_nodes: Vec<FuncNode>,
+    // Addresses of the DeclareLocals instructions for the scopes that
+    // are currently being visited.
+    declare_locals_addrs: Vec<usize>,
+    // Snapshots of the scope trees of the enclosing functions, taken
+    // where this function is defined, from innermost to outermost.
+    enclosing_scopes: Vec<ScopeTree>,
+    // Vars captured from enclosing functions along with the index of
+    // the enclosing scope tree where each var is defined.
+    captures: Vec<(String, usize)>,
+    // Vars in this code unit that are captured by nested functions.
+    cell_vars: Vec<Var>,
+    // Names declared `global` in this function along with the location
+    // of each declaration.
+    pub(crate) global_decls: Vec<(String, Location, Location)>,
+    // Nested functions that have been visited but not finalized.
+    pub(crate) funcs: Vec<FuncUnit>,
+    // Scope depths of the loops that are currently being visited.
+    loop_scope_depths: Vec<usize>,
+    // `try` blocks that are currently being visited, from outermost to
+    // innermost. These are exited early by `break`, `continue`, and
+    // `return`.
+    try_blocks: Vec<TryBlock>,
+}
+
+struct TryBlock {
+    // Scope depth where the `try` starts.
+    scope_depth: usize,
+    ensure: Option<ast::StatementBlock>,
+    // Whether a handler pushed for the `try` is currently active.
+    handler_active: bool,
 }
 
 impl CompilerVisitor {
@@ -42,25 +92,58 @@ impl CompilerVisitor {
         initial_scope_kind: ScopeKind,
         name: &str,
         global_names: HashSet<String>,
+        allow_this: bool,
+        this_capture_level: Option<usize>,
+        debug: bool,
+        enclosing_scopes: Vec<ScopeTree>,
     ) -> Self {
         assert!(matches!(initial_scope_kind, ScopeKind::Module | ScopeKind::Func));
         Self {
             initial_scope_kind,
             name: name.to_owned(),
             global_names,
+            allow_this,
+            this_capture_level,
+            debug,
             code: Code::default(),
             scope_tree: ScopeTree::new(initial_scope_kind),
             scope_depth: 0,
-            func_nodes: vec![],
+            declare_locals_addrs: vec![],
+            enclosing_scopes,
+            captures: vec![],
+            cell_vars: vec![],
+            global_decls: vec![],
+            funcs: vec![],
+            loop_scope_depths: vec![],
+            try_blocks: vec![],
         }
     }
 
-    pub(crate) fn for_module(name: &str, global_names: HashSet<String>) -> Self {
-        Self::new(ScopeKind::Module, name, global_names)
+    pub(crate) fn for_module(
+        name: &str,
+        global_names: HashSet<String>,
+        debug: bool,
+    ) -> Self {
+        Self::new(ScopeKind::Module, name, global_names, false, None, debug, vec![])
     }
 
-    pub(crate) fn for_func(name: &str, global_names: HashSet<String>) -> Self {
-        Self::new(ScopeKind::Func, name, global_names)
+    fn for_func(
+        name: &str,
+        global_names: HashSet<String>,
+        allow_this: bool,
+        this_capture_level: Option<usize>,
+        debug: bool,
+        enclosing_scopes: Vec<ScopeTree>,
+    ) -> Self {
+        Self::new(
+            ScopeKind::Func,
+            name,
+            global_names,
+            allow_this,
+            this_capture_level,
+            debug,
+            enclosing_scopes,
+        )
     }
 
     // Entry Point Visitors --------------------------------------------
@@ -76,8 +159,10 @@ impl CompilerVisitor {
         Ok(())
     }
 
-    pub(crate) fn visit_func(&mut self, node: ast::Func) -> VisitResult {
+    fn visit_func(&mut self, node: ast::Func) -> VisitResult {
         let params = node.params;
+        let is_async = node.is_async;
+        self.code.set_async(is_async);
 
         // Return nil when the last statement is NOT an expression.
         let last_statement = node
@@ -88,8 +173,10 @@ impl CompilerVisitor {
 
         let return_nil = !matches!(last_statement.kind, ast::StatementKind::Expr(_));
 
-        // Add var for this.
-        self.scope_tree.add_var(0, "this", true);
+        // Add var for this. The VM always declares `this` first when a
+        // function is called, so it always occupies the first slot,
+        // but it can only be referenced in methods.
+        self.scope_tree.add_var(0, "this", true, Location::default());
 
         // Add vars for function parameters.
         let param_count = params.len();
@@ -98,7 +185,7 @@ impl CompilerVisitor {
             for (i, name) in params.iter().enumerate() {
                 if name.is_empty() {
                     if i == last {
-                        self.scope_tree.add_var(0, "$args", true);
+                        self.scope_tree.add_var(0, "$args", true, Location::default());
                     } else {
                         return Err(CompErr::var_args_must_be_last(
                             node.block.start,
@@ -106,14 +193,23 @@ impl CompilerVisitor {
                         ));
                     }
                 } else {
-                    self.scope_tree.add_var(0, name, true);
+                    self.scope_tree.add_var(0, name, true, Location::default());
                 }
             }
         }
 
+        let num_args = self.scope_tree.var_names(0).len();
         self.visit_statements(node.block.statements)?;
         assert_eq!(self.scope_tree.pointer(), 0);
         assert!(self.scope_tree.in_func_scope());
+        self.fix_cell_vars();
+
+        // Locals in the function's top level scope are declared by the
+        // VM after `this` and the args when the function is called.
+        let mut local_names = self.scope_tree.var_names(0);
+        let local_names = local_names.split_off(num_args);
+        self.code
+            .set_local_names(local_names.iter().map(|n| Symbol::intern(n)).collect());
 
         if return_nil {
             self.push_nil();
@@ -133,6 +229,12 @@ impl CompilerVisitor {
             }
         }
 
+        // The result of a call in tail position in an async function
+        // is the result of the task, so it has to be a regular call.
+        if !is_async {
+            self.mark_tail_calls();
+        }
+
         Ok(())
     }
 
@@ -147,6 +249,22 @@ impl CompilerVisitor {
         Ok(())
     }
 
+    /// The awaited task is left at TOS by AWAIT until it's done, at
+    /// which point it's replaced with the task's result.
+    fn visit_await(&mut self, expr: ast::Expr) -> VisitResult {
+        self.visit_expr(expr, None)?;
+        self.push(Inst::Await);
+        Ok(())
+    }
+
+    /// This pushes the object onto the stack first and then the key.
+    fn visit_subscript(&mut self, obj: ast::Expr, key: ast::Expr) -> VisitResult {
+        self.visit_expr(obj, None)?;
+        self.visit_expr(key, None)?;
+        self.push(Inst::GetItem);
+        Ok(())
+    }
+
     // Visitors --------------------------------------------------------
 
     fn visit_statements(&mut self, statements: Vec<ast::Statement>) -> VisitResult {
@@ -154,7 +272,7 @@ impl CompilerVisitor {
         if num_statements > 0 {
             let last = num_statements - 1;
             for (i, statement) in statements.into_iter().enumerate() {
-                self.push(Inst::StatementStart(statement.start, statement.end));
+                self.code.add_location(statement.start, statement.end);
                 self.visit_statement(statement)?;
                 if i != last {
                     self.push(Inst::Pop);
@@ -169,7 +287,13 @@ impl CompilerVisitor {
         match node.kind {
             Kind::Break(expr) => self.visit_break(expr)?,
             Kind::Continue => self.visit_continue()?,
-            Kind::Import(path, as_name) => self.visit_import(path, as_name)?,
+            Kind::Global(names) => self.visit_global(names, node.start, node.end)?,
+            Kind::Import(path, as_name) => {
+                self.visit_import(path, as_name, node.start)?
+            }
+            Kind::ImportFrom(path, names) => {
+                self.visit_import_from(path, names, node.start)?
+            }
             Kind::Jump(name) => {
                 let jump_addr = self.push_placeholder(
                     Inst::Jump(0, true, 0),
@@ -196,30 +320,81 @@ impl CompilerVisitor {
 
     fn visit_break(&mut self, expr: ast::Expr) -> VisitResult {
         self.visit_expr(expr, None)?;
+        self.exit_try_blocks(self.loop_scope_depth())?;
         self.push(Inst::BreakPlaceholder(self.len(), self.scope_depth));
         Ok(())
     }
 
     fn visit_continue(&mut self) -> VisitResult {
+        self.exit_try_blocks(self.loop_scope_depth())?;
         self.push(Inst::ContinuePlaceholder(self.len(), self.scope_depth));
         Ok(())
     }
 
-    fn visit_import(&mut self, name: String, as_name: Option<String>) -> VisitResult {
+    /// Visit `global` declaration. Assignments to the declared names in
+    /// the rest of the function store to the module's globals instead
+    /// of creating locals. Loads don't need special handling, since the
+    /// names are free vars, which are resolved to globals.
+    fn visit_global(
+        &mut self,
+        names: Vec<String>,
+        start: Location,
+        end: Location,
+    ) -> VisitResult {
+        for name in names {
+            if self.scope_tree.find_var(&name, None).is_some() {
+                return Err(CompErr::global_after_local(name, start, end));
+            }
+            if !self.is_global_decl(&name) {
+                self.global_decls.push((name, start, end));
+            }
+        }
+        self.push_nil();
+        Ok(())
+    }
+
+    fn visit_import(
+        &mut self,
+        name: String,
+        as_name: Option<String>,
+        start: Location,
+    ) -> VisitResult {
         if let Some(var_name) = as_name {
-            self.scope_tree.add_var(self.len(), &var_name, true);
-            self.push(Inst::DeclareVar(var_name.clone()));
-            self.push(Inst::LoadModule(name.clone()));
-            self.push(Inst::AssignVar(var_name.clone()));
+            self.declare_var(&var_name, true, start);
+            self.push_load_module(name.as_str());
+            self.push_assign_var(var_name);
         } else {
             let var_name = name
                 .split('.')
                 .last()
                 .expect("Import path should have at least one segment");
-            self.scope_tree.add_var(self.len(), var_name, true);
-            self.push(Inst::DeclareVar(var_name.to_owned()));
-            self.push(Inst::LoadModule(name.clone()));
-            self.push(Inst::AssignVar(var_name.to_owned()));
+            self.declare_var(var_name, true, start);
+            self.push_load_module(name.as_str());
+            self.push_assign_var(var_name.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Each imported name is bound to the corresponding attribute of
+    /// the module. Like other statements, this leaves a single value
+    /// on the stack (the last imported object).
+    fn visit_import_from(
+        &mut self,
+        path: String,
+        names: Vec<(String, Option<String>)>,
+        start: Location,
+    ) -> VisitResult {
+        let last = names.len() - 1;
+        for (i, (name, as_name)) in names.into_iter().enumerate() {
+            let var_name = as_name.unwrap_or_else(|| name.clone());
+            self.declare_var(&var_name, true, start);
+            self.push_load_module(path.as_str());
+            self.visit_literal(ast::Literal::new_string(name.as_str()))?;
+            self.push(Inst::BinaryOp(BinaryOperator::Dot));
+            self.push_assign_var(var_name);
+            if i != last {
+                self.push(Inst::Pop);
+            }
         }
         Ok(())
     }
@@ -264,6 +439,7 @@ impl CompilerVisitor {
 
     fn visit_return(&mut self, expr: ast::Expr) -> VisitResult {
         self.visit_expr(expr, None)?;
+        self.exit_try_blocks(0)?;
         self.push(Inst::ReturnPlaceholder(self.len(), self.scope_depth));
         Ok(())
     }
@@ -283,33 +459,50 @@ impl CompilerVisitor {
             Kind::Tuple(items) => self.visit_tuple(items)?,
             Kind::List(items) => self.visit_list(items)?,
             Kind::Map(entries) => self.visit_map(entries)?,
+            Kind::Set(items) => self.visit_set(items)?,
             Kind::Literal(literal) => self.visit_literal(literal)?,
             Kind::FormatString(items) => self.visit_format_string(items)?,
             Kind::Ident(ident) => self.visit_ident(ident, node.start, node.end)?,
+            Kind::DeclarationAndAssignment(lhs_expr, value_expr)
+                if value_expr.assignment().is_some() =>
+            {
+                self.visit_multi_assignment(*lhs_expr, *value_expr)?
+            }
             Kind::DeclarationAndAssignment(lhs_expr, value_expr) => {
                 self.visit_declaration(*lhs_expr.clone())?;
                 self.visit_assignment(*lhs_expr, *value_expr)?
             }
+            Kind::Assignment(lhs_expr, value_expr)
+                if lhs_expr.tuple_items().is_some()
+                    || value_expr.assignment().is_some() =>
+            {
+                self.visit_multi_assignment(*lhs_expr, *value_expr)?
+            }
             Kind::Assignment(lhs_expr, value_expr) => {
                 self.visit_assignment(*lhs_expr, *value_expr)?
             }
             Kind::Block(block) => self.visit_block(block)?,
+            Kind::DebugBlock(block) => {
+                if self.debug {
+                    self.visit_block(block)?
+                } else {
+                    self.push_nil();
+                }
+            }
             Kind::Conditional(branches, default) => {
                 self.visit_conditional(branches, default)?
             }
+            Kind::Match(subject, arms, default) => {
+                self.visit_match(*subject, arms, default, node.start, node.end)?
+            }
             Kind::Loop(expr, block) => self.visit_loop(*expr, block)?,
-            Kind::Func(func) => {
-                let name = name.map_or_else(|| "<anonymous>".to_owned(), |name| name);
-                let addr = self.push_placeholder(
-                    Inst::LoadConst(0),
-                    "Function constant index not updated",
-                );
-                let pointer = self.scope_tree.pointer();
-                self.func_nodes.push((name, addr, pointer, func));
-                self.push(Inst::CaptureSet(vec![]));
-                self.push(Inst::MakeFunc);
+            Kind::Try(block, catch_clauses, ensure) => {
+                self.visit_try(block, catch_clauses, ensure, node.start, node.end)?
             }
+            Kind::Func(func) => self.visit_func_def(func, name, false)?,
             Kind::Call(call) => self.visit_call(call)?,
+            Kind::Await(expr) => self.visit_await(*expr)?,
+            Kind::Subscript(obj, key) => self.visit_subscript(*obj, *key)?,
             Kind::UnaryOp(op, b) => self.visit_unary_op(op, *b)?,
             Kind::BinaryOp(a, op, b) => self.visit_binary_op(*a, op, *b)?,
             Kind::CompareOp(a, op, b) => self.visit_compare_op(*a, op, *b)?,
@@ -324,6 +517,9 @@ impl CompilerVisitor {
     fn visit_tuple(&mut self, items: Vec<ast::Expr>) -> VisitResult {
         if items.is_empty() {
             self.push_empty_tuple();
+        } else if items.len() > LITERAL_CHUNK_SIZE {
+            self.visit_list(items)?;
+            self.push(Inst::ListToTuple);
         } else {
             let num_items = items.len();
             self.visit_exprs(items)?;
@@ -332,20 +528,66 @@ impl CompilerVisitor {
         Ok(())
     }
 
+    /// Large lists are built in chunks--the first chunk is used to
+    /// make the list and subsequent chunks are appended to it.
     fn visit_list(&mut self, items: Vec<ast::Expr>) -> VisitResult {
-        let num_items = items.len();
-        self.visit_exprs(items)?;
+        let mut items = items.into_iter();
+        let first: Vec<_> = items.by_ref().take(LITERAL_CHUNK_SIZE).collect();
+        let num_items = first.len();
+        self.visit_exprs(first)?;
         self.push(Inst::MakeList(num_items));
+        loop {
+            let chunk: Vec<_> = items.by_ref().take(LITERAL_CHUNK_SIZE).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            let num_items = chunk.len();
+            self.visit_exprs(chunk)?;
+            self.push(Inst::ExtendList(num_items));
+        }
         Ok(())
     }
 
+    /// Large maps are built in chunks like large lists.
     fn visit_map(&mut self, entries: Vec<(ast::Expr, ast::Expr)>) -> VisitResult {
-        let num_items = entries.len();
+        let mut entries = entries.into_iter();
+        let first: Vec<_> = entries.by_ref().take(LITERAL_CHUNK_SIZE).collect();
+        let num_items = first.len();
+        self.visit_map_entries(first)?;
+        self.push(Inst::MakeMap(num_items * 2));
+        loop {
+            let chunk: Vec<_> = entries.by_ref().take(LITERAL_CHUNK_SIZE).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            let num_items = chunk.len();
+            self.visit_map_entries(chunk)?;
+            self.push(Inst::ExtendMap(num_items * 2));
+        }
+        Ok(())
+    }
+
+    /// Large sets are built from a list like large tuples.
+    fn visit_set(&mut self, items: Vec<ast::Expr>) -> VisitResult {
+        if items.len() > LITERAL_CHUNK_SIZE {
+            self.visit_list(items)?;
+            self.push(Inst::ListToSet);
+        } else {
+            let num_items = items.len();
+            self.visit_exprs(items)?;
+            self.push(Inst::MakeSet(num_items));
+        }
+        Ok(())
+    }
+
+    fn visit_map_entries(
+        &mut self,
+        entries: Vec<(ast::Expr, ast::Expr)>,
+    ) -> VisitResult {
         for (name, val) in entries {
             self.visit_expr(name, None)?;
             self.visit_expr(val, None)?;
         }
-        self.push(Inst::MakeMap(num_items * 2));
         Ok(())
     }
 
@@ -387,6 +629,92 @@ impl CompilerVisitor {
         Ok(())
     }
 
+    /// Visit function definition. The function is visited right away
+    /// with a snapshot of the enclosing function scopes, so the vars it
+    /// captures are the ones visible where it's defined. It's finalized
+    /// later by the `Compiler`, which resolves its remaining free vars
+    /// to globals and replaces the placeholder with the function
+    /// constant.
+    fn visit_func_def(
+        &mut self,
+        func: ast::Func,
+        name: Option<String>,
+        is_method: bool,
+    ) -> VisitResult {
+        let name = name.unwrap_or_else(|| "<anonymous>".to_owned());
+        let addr = self.push_placeholder(
+            Inst::LoadConst(0),
+            "Function constant index not updated",
+        );
+
+        // Module scopes aren't included since globals aren't captured.
+        let mut enclosing_scopes = vec![];
+        if self.is_func() {
+            enclosing_scopes.push(self.scope_tree.clone());
+            enclosing_scopes.extend(self.enclosing_scopes.iter().cloned());
+        }
+
+        // Functions nested in methods can also reference `this`, which
+        // they capture from the method.
+        let this_capture_level = match self.this_capture_level {
+            _ if is_method => None,
+            Some(level) => Some(level + 1),
+            None if self.allow_this => Some(0),
+            None => None,
+        };
+        let params = func.params.clone();
+        let mut visitor = Self::for_func(
+            &name,
+            self.global_names.clone(),
+            is_method || self.allow_this,
+            this_capture_level,
+            self.debug,
+            enclosing_scopes,
+        );
+        visitor.visit_func(func)?;
+
+        // Vars captured from this function's scope are converted to
+        // cells. Vars captured from further out have to be captured by
+        // this function too so they can be passed down.
+        let mut capture_names = vec![];
+        for (var_name, level) in visitor.captures.iter() {
+            if *level == 0 {
+                let var = self
+                    .scope_tree
+                    .find_var(var_name, None)
+                    .expect("Expected captured var to be in scope");
+                if !self
+                    .cell_vars
+                    .iter()
+                    .any(|v| v.pointer == var.pointer && v.index == var.index)
+                {
+                    self.cell_vars.push(var);
+                }
+            } else {
+                self.add_capture(var_name, level - 1);
+            }
+            capture_names.push(Symbol::intern(var_name));
+        }
+
+        self.funcs.push((name, addr, params, is_method, visitor));
+        self.push(Inst::CaptureSet(capture_names));
+        self.push(Inst::MakeFunc);
+        Ok(())
+    }
+
+    /// Visit the value of a type member or attribute. Functions
+    /// defined here can be bound, so they're compiled as methods.
+    fn visit_member_value(
+        &mut self,
+        value_expr: ast::Expr,
+        name: Option<String>,
+    ) -> VisitResult {
+        match value_expr.kind {
+            ast::ExprKind::Func(func) => self.visit_func_def(func, name, true),
+            _ => self.visit_expr(value_expr, name),
+        }
+    }
+
     /// Visit identifier (AKA name) as expression (i.e., not as part of
     /// an assignment).
     fn visit_ident(
@@ -397,6 +725,18 @@ impl CompilerVisitor {
     ) -> VisitResult {
         let name = node.name();
 
+        if name == "this" && !self.allow_this {
+            return Err(CompErr::this_outside_method(start, end));
+        }
+
+        if name == "this" {
+            if let Some(level) = self.this_capture_level {
+                self.add_capture(&name, level);
+                self.push(Inst::LoadCaptured(Symbol::intern(&name)));
+                return Ok(());
+            }
+        }
+
         // NOTE: When a function is being compiled, find_var will
         //       traverse up as far as the top level scope of the
         //       function. It will NOT proceed up into a function's
@@ -404,7 +744,7 @@ impl CompilerVisitor {
         //       a module.
         if let Some(var) = self.scope_tree.find_var(&name, None) {
             if var.assigned {
-                self.push(Inst::LoadVar(name, self.scope_depth - var.depth));
+                self.push_load_var(var);
             } else {
                 // This happens whenever an identifier appears on both
                 // sides of an assignment, e.g. `x = x`, where the LHS
@@ -422,17 +762,17 @@ impl CompilerVisitor {
                 // scope of LHS `x`, which could be multiple levels up
                 // from RHS `x`.
                 if let Some(outer_var) = self.scope_tree.find_var_in_parent(&var) {
-                    self.push(Inst::LoadVar(name, self.scope_depth - outer_var.depth));
+                    self.push_load_var(outer_var);
                 } else if self.is_module() {
                     if self.global_names.contains(&name) {
-                        self.push(Inst::LoadGlobal(name));
+                        self.push(Inst::LoadGlobal(Symbol::intern(&name)));
                     } else if self.has_builtin(&name) {
-                        self.push(Inst::LoadBuiltin(name));
+                        self.push(Inst::LoadBuiltin(Symbol::intern(&name)));
                     } else {
                         return Err(CompErr::name_not_found(name, start, end));
                     }
                 } else if self.is_func() {
-                    self.code.add_free_var(name.as_str(), start, end);
+                    self.push_free_var(name, start, end);
                 } else {
                     panic!("Unexpected scope type: {:?}", self.initial_scope_kind);
                 }
@@ -442,16 +782,16 @@ impl CompilerVisitor {
             // point, so if the name doesn't resolve to a builtin,
             // that's an error.
             if self.global_names.contains(&name) {
-                self.push(Inst::LoadGlobal(name));
+                self.push(Inst::LoadGlobal(Symbol::intern(&name)));
             } else if self.has_builtin(&name) {
-                self.push(Inst::LoadBuiltin(name));
+                self.push(Inst::LoadBuiltin(Symbol::intern(&name)));
             } else {
                 return Err(CompErr::name_not_found(name, start, end));
             }
         } else if self.is_func() {
             // When compiling a function, vars may be defined in an
-            // enclosing scope. These free vars will be resolved later.
-            self.code.add_free_var(name.as_str(), start, end);
+            // enclosing function or be globals.
+            self.push_free_var(name, start, end);
         } else {
             panic!("Unexpected scope type: {:?}", self.initial_scope_kind);
         }
@@ -464,16 +804,28 @@ impl CompilerVisitor {
         obj_expr: ast::Expr,
         name_expr: ast::Expr,
     ) -> VisitResult {
+        let obj_text = obj_expr.short_text();
         self.visit_expr(obj_expr, None)?;
-        if let Some(name) = name_expr.ident_name() {
-            self.visit_literal(ast::Literal::new_string(name.as_str()))?;
+        self.visit_attr_name(name_expr)?;
+        if let Some(obj_text) = obj_text {
+            self.push(Inst::GetAttr(obj_text.into()));
         } else {
-            self.visit_expr(name_expr, None)?;
+            self.push(Inst::BinaryOp(BinaryOperator::Dot));
         }
-        self.push(Inst::BinaryOp(BinaryOperator::Dot));
         Ok(())
     }
 
+    /// Push the name of an attribute. The RHS of `a.b` is pushed as a
+    /// string if it's an identifier. Otherwise, it's evaluated (e.g.,
+    /// `a.0`).
+    fn visit_attr_name(&mut self, name_expr: ast::Expr) -> VisitResult {
+        if let Some(name) = name_expr.ident_name() {
+            self.visit_literal(ast::Literal::new_string(name.as_str()))
+        } else {
+            self.visit_expr(name_expr, None)
+        }
+    }
+
     fn visit_block(&mut self, node: ast::StatementBlock) -> VisitResult {
         self.enter_scope(ScopeKind::Block);
         self.visit_statements(node.statements)?;
@@ -553,6 +905,225 @@ impl CompilerVisitor {
         Ok(())
     }
 
+    /// Visit `match`. The subject is evaluated once and stored in a
+    /// temporary var. Each arm is then compiled like a conditional
+    /// branch where the condition is a series of checks for the arm's
+    /// pattern followed by its guard. If any check fails, the arm is
+    /// skipped. Names bound by the pattern are declared in the arm's
+    /// scope, so they're visible in its guard and block.
+    fn visit_match(
+        &mut self,
+        subject: ast::Expr,
+        arms: Vec<ast::MatchArm>,
+        default: Option<ast::StatementBlock>,
+        start: Location,
+        end: Location,
+    ) -> VisitResult {
+        let temp_var = "$match";
+        self.declare_var(temp_var, false, Location::default());
+        self.visit_expr(subject, None)?;
+        self.scope_tree.mark_assigned(self.scope_tree.pointer(), temp_var);
+        self.push_assign_var(temp_var.to_owned());
+        self.push(Inst::Pop);
+
+        let temp_ident = ast::Ident::new_special_ident(temp_var.to_owned());
+        let temp_expr = ast::Expr::new_ident(temp_ident, start, end);
+        let mut jump_out_addrs: Vec<usize> = vec![];
+
+        for arm in arms {
+            self.enter_scope(ScopeKind::Block);
+
+            // Addresses of jumps taken when a check fails.
+            let mut miss_addrs: Vec<usize> = vec![];
+            self.visit_pattern(arm.pattern, temp_expr.clone(), &mut miss_addrs)?;
+            if let Some(guard) = arm.guard {
+                self.visit_expr(guard, None)?;
+                self.push_pattern_check(&mut miss_addrs);
+            }
+
+            self.visit_statements(arm.block.statements)?;
+            self.push(Inst::ScopeEnd); // NOTE: ScopeStart is at top of for loop
+            jump_out_addrs.push(
+                self.push_placeholder(Inst::Jump(0, true, 0), "Match jump out not set"),
+            );
+
+            // When a check fails, discard its result and replace it
+            // with nil, the same as a conditional branch.
+            let miss_addr = self.len();
+            for addr in miss_addrs {
+                self.replace(addr, Inst::JumpIfNot(miss_addr - addr, true, 0));
+            }
+            self.push(Inst::Pop);
+            self.push_nil();
+
+            self.exit_scope();
+        }
+
+        if let Some(default_block) = default {
+            self.visit_block(default_block)?;
+        } else {
+            self.push_nil();
+        }
+
+        let after_addr = self.len();
+        for addr in jump_out_addrs {
+            self.replace(addr, Inst::Jump(after_addr - addr, true, 0));
+        }
+
+        Ok(())
+    }
+
+    /// Push checks for pattern against subject, which is an expression
+    /// that can be evaluated multiple times. Each check pushes a Bool;
+    /// the addresses of the jumps taken when a check is false are
+    /// added to `miss_addrs`.
+    fn visit_pattern(
+        &mut self,
+        pattern: ast::Pattern,
+        subject: ast::Expr,
+        miss_addrs: &mut Vec<usize>,
+    ) -> VisitResult {
+        let (start, end) = (subject.start, subject.end);
+        match pattern {
+            ast::Pattern::Value(expr) => {
+                self.visit_expr(subject, None)?;
+                self.visit_expr(expr, None)?;
+                self.push(Inst::CompareOp(CompareOperator::IsEqual));
+                self.push_pattern_check(miss_addrs);
+            }
+            ast::Pattern::Alt(exprs) => {
+                // Jump to the check as soon as one of the values is
+                // equal, leaving true on the stack.
+                let mut hit_addrs = vec![];
+                let num_exprs = exprs.len();
+                for (i, expr) in exprs.into_iter().enumerate() {
+                    self.visit_expr(subject.clone(), None)?;
+                    self.visit_expr(expr, None)?;
+                    self.push(Inst::CompareOp(CompareOperator::IsEqual));
+                    if i < num_exprs - 1 {
+                        hit_addrs.push(self.push_placeholder(
+                            Inst::JumpIf(0, true, 0),
+                            "Match alternative jump not set",
+                        ));
+                        self.push(Inst::Pop);
+                    }
+                }
+                let check_addr = self.len();
+                for addr in hit_addrs {
+                    self.replace(addr, Inst::JumpIf(check_addr - addr, true, 0));
+                }
+                self.push_pattern_check(miss_addrs);
+            }
+            ast::Pattern::Binding(name) => {
+                self.visit_pattern_binding(name, subject)?;
+            }
+            ast::Pattern::Type(type_expr, name) => {
+                self.visit_get_attr_by_name(subject.clone(), "$type")?;
+                self.visit_expr(type_expr, None)?;
+                self.push(Inst::CompareOp(CompareOperator::IsEqual));
+                self.push_pattern_check(miss_addrs);
+                self.visit_pattern_binding(name, subject)?;
+            }
+            ast::Pattern::Tuple(items) => {
+                self.visit_get_attr_by_name(subject.clone(), "$type")?;
+                let tuple_ident = ast::Ident::new_type_ident("Tuple".to_owned());
+                self.visit_ident(tuple_ident, start, end)?;
+                self.push(Inst::CompareOp(CompareOperator::IsEqual));
+                self.push_pattern_check(miss_addrs);
+
+                self.visit_get_attr_by_name(subject.clone(), "length")?;
+                self.visit_expr(
+                    ast::Expr::new_int(BigInt::from(items.len()), start, end),
+                    None,
+                )?;
+                self.push(Inst::CompareOp(CompareOperator::IsEqual));
+                self.push_pattern_check(miss_addrs);
+
+                for (i, item) in items.into_iter().enumerate() {
+                    let index = ast::Expr::new_int(BigInt::from(i), start, end);
+                    let item_subject = ast::Expr::new(
+                        ast::ExprKind::BinaryOp(
+                            Box::new(subject.clone()),
+                            BinaryOperator::Dot,
+                            Box::new(index),
+                        ),
+                        start,
+                        end,
+                    );
+                    self.visit_pattern(item, item_subject, miss_addrs)?;
+                }
+            }
+            ast::Pattern::Str(prefix, name, suffix) => {
+                self.visit_get_attr_by_name(subject.clone(), "$type")?;
+                let str_ident = ast::Ident::new_type_ident("Str".to_owned());
+                self.visit_ident(str_ident, start, end)?;
+                self.push(Inst::CompareOp(CompareOperator::IsEqual));
+                self.push_pattern_check(miss_addrs);
+
+                // The name is bound to the subject and then to what's
+                // left after stripping the prefix and suffix. Stripping
+                // returns nil when the string doesn't start or end with
+                // the prefix or suffix.
+                let name_ident = ast::Ident::new_ident(name.clone());
+                let name_expr = ast::Expr::new_ident(name_ident, start, end);
+                self.visit_pattern_binding(name.clone(), subject)?;
+                for (meth, affix) in
+                    [("strip_prefix", prefix), ("strip_suffix", suffix)]
+                {
+                    if let Some(affix) = affix {
+                        self.visit_literal(ast::Literal::new_string(affix))?;
+                        self.visit_get_attr_by_name(name_expr.clone(), meth)?;
+                        self.push(Inst::Call(1));
+                        self.push_assign_var(name.clone());
+                        self.push_nil();
+                        self.push(Inst::CompareOp(CompareOperator::NotEqual));
+                        self.push_pattern_check(miss_addrs);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Bind subject to name in the current scope.
+    fn visit_pattern_binding(
+        &mut self,
+        name: String,
+        subject: ast::Expr,
+    ) -> VisitResult {
+        let (start, end) = (subject.start, subject.end);
+        let ident = ast::Ident::new_ident(name.clone());
+        self.visit_declaration(ast::Expr::new_ident(ident, start, end))?;
+        self.visit_expr(subject, None)?;
+        self.scope_tree.mark_assigned(self.scope_tree.pointer(), name.as_str());
+        self.push_assign_var(name);
+        self.push(Inst::Pop);
+        Ok(())
+    }
+
+    /// Push jump out of the current `match` arm for when the check at
+    /// TOS is false. If the check is true, it's popped.
+    fn push_pattern_check(&mut self, miss_addrs: &mut Vec<usize>) {
+        miss_addrs.push(
+            self.push_placeholder(
+                Inst::JumpIfNot(0, true, 0),
+                "Match check jump not set",
+            ),
+        );
+        self.push(Inst::Pop);
+    }
+
+    fn visit_get_attr_by_name(
+        &mut self,
+        obj_expr: ast::Expr,
+        name: &str,
+    ) -> VisitResult {
+        self.visit_expr(obj_expr, None)?;
+        self.visit_literal(ast::Literal::new_string(name))?;
+        self.push(Inst::BinaryOp(BinaryOperator::Dot));
+        Ok(())
+    }
+
     fn visit_loop(
         &mut self,
         expr: ast::Expr,
@@ -573,9 +1144,16 @@ impl CompilerVisitor {
             } else {
                 return Err(CompErr::expected_ident(lhs.start, lhs.end));
             };
+            let (start, end) = (lhs.start, lhs.end);
             self.visit_declaration(*lhs.clone())?;
             self.visit_assignment(*lhs, *val)?;
-            self.push(Inst::LoadVar(name, 0))
+            if self.is_global_decl(&name) {
+                let loop_addr = self.len();
+                self.push_free_var(name, start, end);
+                loop_addr
+            } else {
+                self.push_load_local_var(&name)
+            }
         } else {
             let loop_addr = self.len();
             if expr.is_false() {
@@ -593,11 +1171,30 @@ impl CompilerVisitor {
         // Pop result of loop condition evaluation.
         self.push(Inst::Pop);
 
+        // Placeholder for renewing the cells of captured loop vars.
+        let renew_cells_addr = self
+            .push_placeholder(Inst::RenewCells(vec![]), "Renew cells for loop not set");
+        let num_funcs = self.funcs.len();
+
         // Run the loop body.
         let block_start_addr = self.len();
+        self.loop_scope_depths.push(loop_scope_depth);
         self.visit_statements(block.statements)?;
+        self.loop_scope_depths.pop();
         let block_end_addr = self.len();
 
+        // Vars in the loop scope can only be captured if a function is
+        // defined in the loop body. In that case, each iteration gets
+        // new cells for the captured vars, so closures created in
+        // different iterations don't share them.
+        let renew_cells = if self.funcs.len() > num_funcs {
+            let num_vars = self.scope_tree.var_names(self.scope_tree.pointer()).len();
+            Inst::RenewCells((0..num_vars).collect())
+        } else {
+            Inst::NoOp
+        };
+        self.replace(renew_cells_addr, renew_cells);
+
         // Jump to top of loop.
         let rel_addr = self.len() - loop_addr;
         self.push(Inst::Jump(rel_addr, false, 0));
@@ -631,6 +1228,204 @@ impl CompilerVisitor {
         Ok(())
     }
 
+    /// The value of a `try` expression is the value of its block or,
+    /// if an error is caught, the value of the matching `catch` block.
+    /// If the `try` block evaluates to an `Err` object, it's raised so
+    /// that errors returned from functions can be caught too.
+    ///
+    /// The `ensure` block is run after the `try` and `catch` blocks
+    /// whether or not an error occurred, including when an error isn't
+    /// caught (in which case it's re-raised after the `ensure` block is
+    /// run). Its value is discarded.
+    ///
+    /// When a `try` or `catch` block is exited early via `break`,
+    /// `continue`, or `return`, its handler is popped and its `ensure`
+    /// block is run before jumping out (see [`Self::exit_try_blocks`]).
+    ///
+    /// NOTE: `ensure` blocks are *not* run when jumping out of a `try`
+    ///       or `catch` block to a label.
+    fn visit_try(
+        &mut self,
+        block: ast::StatementBlock,
+        catch_clauses: Vec<(Option<ast::Expr>, String, ast::StatementBlock)>,
+        ensure: Option<ast::StatementBlock>,
+        start: Location,
+        end: Location,
+    ) -> VisitResult {
+        assert!(
+            !catch_clauses.is_empty() || ensure.is_some(),
+            "At least one catch clause or an ensure block is required for try"
+        );
+
+        let err_var = "$err";
+
+        // Enter scope for the var that holds the caught error.
+        self.enter_scope(ScopeKind::Block);
+
+        let handler_addr =
+            self.push_placeholder(Inst::PushHandler(0), "Try handler not set");
+        self.try_blocks.push(TryBlock {
+            scope_depth: self.scope_depth - 1,
+            ensure: ensure.clone(),
+            handler_active: true,
+        });
+        self.visit_block(block)?;
+        self.push(Inst::RaiseIfErr);
+        self.push(Inst::PopHandler);
+        let jump_ok_addr =
+            self.push_placeholder(Inst::Jump(0, true, 0), "Try jump out not set");
+
+        // Addresses of instructions that jump to the re-raise target.
+        let mut reraise_addrs = vec![];
+
+        // Addresses of handlers that target the re-raise target.
+        let mut reraise_handler_addrs = vec![];
+
+        if catch_clauses.is_empty() {
+            reraise_handler_addrs.push(handler_addr);
+        } else {
+            // Handler. The caught error is at TOS.
+            let rel_addr = self.len() - handler_addr;
+            self.replace(handler_addr, Inst::PushHandler(rel_addr));
+
+            self.declare_var(err_var, false, Location::default());
+            self.scope_tree.mark_assigned(self.scope_tree.pointer(), err_var);
+            self.push_assign_var(err_var.to_owned());
+            self.push(Inst::Pop);
+
+            // If there's an ensure block, errors in catch blocks need
+            // to be handled too so the ensure block can be run before
+            // re-raising.
+            if ensure.is_some() {
+                reraise_handler_addrs.push(
+                    self.push_placeholder(
+                        Inst::PushHandler(0),
+                        "Catch handler not set",
+                    ),
+                );
+            }
+
+            // The catch handler is active in catch blocks only when
+            // there's an ensure block.
+            self.try_blocks.last_mut().unwrap().handler_active = ensure.is_some();
+
+            // Addresses of jumps out of catch blocks.
+            let mut jump_caught_addrs = vec![];
+
+            for (err_type, name, block) in catch_clauses {
+                let (start, end) = (block.start, block.end);
+                let err_ident = ast::Ident::new_special_ident(err_var.to_owned());
+
+                self.enter_scope(ScopeKind::Block);
+
+                // Check the error's type, skipping this clause if it
+                // doesn't match.
+                let jump_miss_addr = if let Some(err_type) = err_type {
+                    self.visit_ident(err_ident.clone(), start, end)?;
+                    self.visit_literal(ast::Literal::new_string("type"))?;
+                    self.push(Inst::BinaryOp(BinaryOperator::Dot));
+                    self.visit_expr(err_type, None)?;
+                    self.push(Inst::CompareOp(CompareOperator::IsEqual));
+                    let addr = self.push_placeholder(
+                        Inst::JumpIfNot(0, true, 0),
+                        "Catch type jump not set",
+                    );
+                    self.push(Inst::Pop);
+                    Some(addr)
+                } else {
+                    None
+                };
+
+                // Bind the error to the clause's var.
+                let ident = ast::Ident::new_ident(name.clone());
+                self.visit_declaration(ast::Expr::new_ident(ident, start, end))?;
+                self.visit_ident(err_ident, start, end)?;
+                self.scope_tree.mark_assigned(self.scope_tree.pointer(), name.as_str());
+                self.push_assign_var(name);
+                self.push(Inst::Pop);
+
+                self.visit_statements(block.statements)?;
+
+                if let Some(jump_miss_addr) = jump_miss_addr {
+                    self.push(Inst::ScopeEnd);
+                    jump_caught_addrs.push(self.push_placeholder(
+                        Inst::Jump(0, true, 0),
+                        "Catch jump out not set",
+                    ));
+                    let rel_addr = self.len() - jump_miss_addr;
+                    self.replace(jump_miss_addr, Inst::JumpIfNot(rel_addr, true, 0));
+                    // Discard the result of the type check and exit the
+                    // clause's scope.
+                    self.push(Inst::Pop);
+                    self.push_nil();
+                    self.exit_scope();
+                    self.push(Inst::Pop);
+                } else {
+                    self.exit_scope();
+                    jump_caught_addrs.push(self.push_placeholder(
+                        Inst::Jump(0, true, 0),
+                        "Catch jump out not set",
+                    ));
+                }
+            }
+
+            // No clause matched.
+            if ensure.is_some() {
+                self.push(Inst::PopHandler);
+            }
+            let err_ident = ast::Ident::new_special_ident(err_var.to_owned());
+            self.visit_ident(err_ident, Location::default(), Location::default())?;
+            reraise_addrs.push(
+                self.push_placeholder(Inst::Jump(0, true, 0), "Re-raise jump not set"),
+            );
+
+            // A clause matched.
+            for addr in jump_caught_addrs {
+                let rel_addr = self.len() - addr;
+                self.replace(addr, Inst::Jump(rel_addr, true, 0));
+            }
+            if ensure.is_some() {
+                self.push(Inst::PopHandler);
+            }
+        }
+
+        // No error or error caught.
+        self.try_blocks.pop();
+        let rel_addr = self.len() - jump_ok_addr;
+        self.replace(jump_ok_addr, Inst::Jump(rel_addr, true, 0));
+        self.exit_scope();
+        if let Some(ensure) = ensure.clone() {
+            self.visit_block(ensure)?;
+            self.push(Inst::Pop);
+        }
+        let jump_out_addr =
+            self.push_placeholder(Inst::Jump(0, true, 0), "Try jump out not set");
+
+        // Error not caught. The error is at TOS. Exit the try scope,
+        // run the ensure block (if present), and re-raise.
+        for addr in reraise_addrs {
+            let rel_addr = self.len() - addr;
+            self.replace(addr, Inst::Jump(rel_addr, true, 0));
+        }
+        for addr in reraise_handler_addrs {
+            let rel_addr = self.len() - addr;
+            self.replace(addr, Inst::PushHandler(rel_addr));
+        }
+        self.push(Inst::ScopeEnd);
+        if let Some(ensure) = ensure {
+            self.visit_block(ensure)?;
+            self.push(Inst::Pop);
+        }
+        // Report re-raised errors at the location of the try.
+        self.code.add_location(start, end);
+        self.push(Inst::Raise);
+
+        let rel_addr = self.len() - jump_out_addr;
+        self.replace(jump_out_addr, Inst::Jump(rel_addr, true, 0));
+
+        Ok(())
+    }
+
     fn visit_unary_op(&mut self, op: UnaryOperator, expr: ast::Expr) -> VisitResult {
         self.visit_expr(expr, None)?;
         self.push(Inst::UnaryOp(op));
@@ -682,13 +1477,15 @@ impl CompilerVisitor {
                     ident_expr.end,
                 ));
             }
-        } else if let Some(_name) = ident_expr.is_type_ident() {
-            todo!("Implement custom types")
+        } else if let Some(name) = ident_expr.is_type_ident() {
+            name
         } else {
             return Err(CompErr::expected_ident(ident_expr.start, ident_expr.end));
         };
-        self.scope_tree.add_var(self.len(), name.as_str(), false);
-        self.push(Inst::DeclareVar(name));
+        // Names declared global aren't declared locally.
+        if !self.is_global_decl(&name) {
+            self.declare_var(&name, false, ident_expr.start);
+        }
         Ok(())
     }
 
@@ -697,7 +1494,6 @@ impl CompilerVisitor {
         lhs_expr: ast::Expr,
         value_expr: ast::Expr,
     ) -> VisitResult {
-        // TODO: Allow assignment to attributes
         if let Some(name) = lhs_expr.ident_name() {
             if name == "$main" && !value_expr.is_func() {
                 return Err(CompErr::main_must_be_func(
@@ -705,13 +1501,255 @@ impl CompilerVisitor {
                     value_expr.end,
                 ));
             }
-            self.visit_expr(value_expr, Some(name.clone()))?;
+            if lhs_expr.is_type_ident().is_some() && value_expr.is_func() {
+                self.visit_type(name.as_str(), value_expr)?;
+            } else if lhs_expr.is_type_ident().is_some()
+                && value_expr.is_enum().is_some()
+            {
+                self.visit_enum(name.as_str(), value_expr)?;
+            } else {
+                self.visit_expr(value_expr, Some(name.clone()))?;
+            }
+            if self.is_global_decl(&name) {
+                self.push(Inst::StoreGlobal(Symbol::intern(&name)));
+                return Ok(());
+            }
             self.scope_tree.mark_assigned(self.scope_tree.pointer(), name.as_str());
-            self.push(Inst::AssignVar(name));
-            Ok(())
+            self.push_assign_var(name);
+            return Ok(());
+        }
+        match lhs_expr.kind {
+            ast::ExprKind::BinaryOp(obj_expr, BinaryOperator::Dot, name_expr) => {
+                self.visit_set_attr(*obj_expr, *name_expr, value_expr)
+            }
+            ast::ExprKind::Subscript(obj_expr, key_expr) => {
+                self.visit_set_item(*obj_expr, *key_expr, value_expr)
+            }
+            _ => Err(CompErr::expected_ident(lhs_expr.start, lhs_expr.end)),
+        }
+    }
+
+    /// Visit chained assignment (`a = b = 0`) or destructuring
+    /// assignment (`a, b = b, a`), which may be combined. The value is
+    /// evaluated once and stored in a temporary var. Then it's assigned
+    /// to each target from left to right, the same as if each target
+    /// had been assigned individually (e.g., `a = $assign`). The
+    /// result is the value.
+    fn visit_multi_assignment(
+        &mut self,
+        lhs_expr: ast::Expr,
+        value_expr: ast::Expr,
+    ) -> VisitResult {
+        let temp_var = "$assign";
+        let (start, end) = (lhs_expr.start, value_expr.end);
+
+        let mut targets = vec![lhs_expr];
+        let mut value_expr = value_expr;
+        while let ast::ExprKind::DeclarationAndAssignment(lhs_expr, next_value_expr)
+        | ast::ExprKind::Assignment(lhs_expr, next_value_expr) = value_expr.kind
+        {
+            targets.push(*lhs_expr);
+            value_expr = *next_value_expr;
+        }
+
+        let name = targets.iter().find_map(|target| target.is_ident());
+        self.declare_var(temp_var, false, Location::default());
+        self.visit_expr(value_expr, name)?;
+        self.scope_tree.mark_assigned(self.scope_tree.pointer(), temp_var);
+        self.push_assign_var(temp_var.to_owned());
+        self.push(Inst::Pop);
+
+        let temp_ident = ast::Ident::new_special_ident(temp_var.to_owned());
+        let temp_expr = ast::Expr::new_ident(temp_ident, start, end);
+        for target in targets {
+            self.visit_assignment_target(target, temp_expr.clone())?;
+            self.push(Inst::Pop);
+        }
+
+        self.visit_expr(temp_expr, None)
+    }
+
+    /// Assign value to target, which may be a tuple of targets. The
+    /// value expression is evaluated twice, so it should be a var.
+    fn visit_assignment_target(
+        &mut self,
+        target: ast::Expr,
+        value_expr: ast::Expr,
+    ) -> VisitResult {
+        let (start, end) = (target.start, target.end);
+        match target.kind {
+            ast::ExprKind::Tuple(items) => {
+                self.visit_expr(value_expr.clone(), None)?;
+                self.visit_unpack(items)?;
+                self.visit_expr(value_expr, None)
+            }
+            _ if target.ident_name().is_some() => self.visit_expr(
+                ast::Expr::new_declaration_and_assignment(
+                    target, value_expr, start, end,
+                ),
+                None,
+            ),
+            _ => self.visit_expr(
+                ast::Expr::new_assignment(target, value_expr, start, end),
+                None,
+            ),
+        }
+    }
+
+    /// Unpack the sequence at TOS into targets, which may themselves be
+    /// tuples of targets. Each item is stored in a temporary var and
+    /// then assigned to its target like a regular assignment, so the
+    /// targets can be vars, attributes, or subscripts.
+    fn visit_unpack(&mut self, targets: Vec<ast::Expr>) -> VisitResult {
+        let temp_var = "$unpack";
+        self.push(Inst::UnpackSequence(targets.len()));
+        for target in targets {
+            let (start, end) = (target.start, target.end);
+            if let ast::ExprKind::Tuple(items) = target.kind {
+                self.visit_unpack(items)?;
+                continue;
+            }
+            self.declare_var(temp_var, false, Location::default());
+            self.scope_tree.mark_assigned(self.scope_tree.pointer(), temp_var);
+            self.push_assign_var(temp_var.to_owned());
+            self.push(Inst::Pop);
+            let temp_ident = ast::Ident::new_special_ident(temp_var.to_owned());
+            let temp_expr = ast::Expr::new_ident(temp_ident, start, end);
+            self.visit_assignment_target(target, temp_expr)?;
+            self.push(Inst::Pop);
+        }
+        Ok(())
+    }
+
+    /// Visit assignment to attribute (e.g., `obj.attr = value`). This
+    /// pushes the object, the attribute name, and then the value.
+    fn visit_set_attr(
+        &mut self,
+        obj_expr: ast::Expr,
+        name_expr: ast::Expr,
+        value_expr: ast::Expr,
+    ) -> VisitResult {
+        let name = name_expr.ident_name();
+        self.visit_expr(obj_expr, None)?;
+        self.visit_attr_name(name_expr)?;
+        self.visit_member_value(value_expr, name)?;
+        self.push(Inst::SetAttr);
+        Ok(())
+    }
+
+    /// Visit assignment to item (e.g., `list[0] = value`). This pushes
+    /// the object, the key, and then the value.
+    fn visit_set_item(
+        &mut self,
+        obj_expr: ast::Expr,
+        key_expr: ast::Expr,
+        value_expr: ast::Expr,
+    ) -> VisitResult {
+        self.visit_expr(obj_expr, None)?;
+        self.visit_expr(key_expr, None)?;
+        self.visit_expr(value_expr, None)?;
+        self.push(Inst::SetItem);
+        Ok(())
+    }
+
+    /// Visit custom type definition, which looks like a function
+    /// definition assigned to a type name:
+    ///
+    /// ```text
+    /// Point = (x, y) =>
+    ///     "Docstring (optional)"
+    ///     sum = () => this.x + this.y
+    /// ```
+    ///
+    /// The params are the type's fields and each statement in the body
+    /// must be an assignment defining a member (typically a method).
+    /// Member values are evaluated when the type is defined. Besides
+    /// regular names, the special members `$str` and `$repr` can be
+    /// defined.
+    fn visit_type(&mut self, name: &str, node: ast::Expr) -> VisitResult {
+        let (start, end) = (node.start, node.end);
+        let func = if let ast::ExprKind::Func(func) = node.kind {
+            func
         } else {
-            Err(CompErr::expected_ident(lhs_expr.start, lhs_expr.end))
+            panic!("Expected type definition to be a function node: {node:?}");
+        };
+
+        if func.params.iter().any(|name| name.is_empty()) {
+            return Err(CompErr::type_fields_cannot_have_var_args(start, end));
         }
+
+        let mut statements = func.block.statements;
+        let mut num_members = 0;
+
+        // Docstring
+        if let Some(statement) = statements.first() {
+            if let Some(doc) = statement.expr().and_then(|expr| expr.is_string()) {
+                self.visit_literal(ast::Literal::new_string("$doc"))?;
+                self.visit_literal(ast::Literal::new_string(doc))?;
+                statements.remove(0);
+                num_members += 1;
+            }
+        }
+
+        for statement in statements {
+            let (start, end) = (statement.start, statement.end);
+            let member = if let ast::StatementKind::Expr(expr) = statement.kind {
+                if let ast::ExprKind::DeclarationAndAssignment(lhs, value) = expr.kind {
+                    // `$str` and `$repr` customize how instances are
+                    // converted to strings.
+                    let special = lhs
+                        .is_special_ident()
+                        .filter(|name| matches!(name.as_str(), "$str" | "$repr"));
+                    lhs.is_ident().or(special).map(|name| (name, *value))
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+            if let Some((member_name, value)) = member {
+                self.visit_literal(ast::Literal::new_string(member_name.as_str()))?;
+                self.visit_member_value(value, Some(member_name))?;
+                num_members += 1;
+            } else {
+                return Err(CompErr::expected_type_member(start, end));
+            }
+        }
+
+        self.push(Inst::MakeType(
+            Box::new((name.to_owned(), func.params)),
+            num_members,
+        ));
+        Ok(())
+    }
+
+    /// Visit enum definition, which looks like a call to `enum` with
+    /// the variant names as args assigned to a type name:
+    ///
+    /// ```text
+    /// Color = enum (Red, Green, Blue)
+    /// ```
+    ///
+    /// Variant names must be type names and must be unique.
+    fn visit_enum(&mut self, name: &str, node: ast::Expr) -> VisitResult {
+        let args = node.is_enum().expect("Expected enum definition");
+        let mut variants: Vec<String> = vec![];
+        for arg in args {
+            match arg.is_type_ident() {
+                Some(variant) if variants.contains(&variant) => {
+                    return Err(CompErr::duplicate_enum_variant(
+                        variant, arg.start, arg.end,
+                    ));
+                }
+                Some(variant) => variants.push(variant),
+                None => return Err(CompErr::expected_enum_variant(arg.start, arg.end)),
+            }
+        }
+        if variants.is_empty() {
+            return Err(CompErr::expected_enum_variant(node.start, node.end));
+        }
+        self.push(Inst::MakeEnum(Box::new((name.to_owned(), variants))));
+        Ok(())
     }
 
     fn visit_compare_op(
@@ -792,6 +1830,26 @@ impl CompilerVisitor {
                 let jump_target = self.push(Inst::NoOp) - jump_addr;
                 self.replace(jump_addr, Inst::JumpIfNotNil(jump_target, true, 0));
             }
+            NilSafeDot => {
+                // Look up the attribute if LHS is not nil. Otherwise,
+                // skip the lookup, leaving nil as the result.
+                let attr_jump_addr = self.push_placeholder(
+                    Inst::JumpIfNotNil(0, true, 0),
+                    "Jump target for ?. not updated",
+                );
+                let end_jump_addr = self.push_placeholder(
+                    Inst::Jump(0, true, 0),
+                    "Jump target for ?. not updated",
+                );
+
+                let attr_target = self.len() - attr_jump_addr;
+                self.replace(attr_jump_addr, Inst::JumpIfNotNil(attr_target, true, 0));
+                self.visit_attr_name(expr_b)?;
+                self.push(Inst::BinaryOp(BinaryOperator::Dot));
+
+                let end_target = self.push(Inst::NoOp) - end_jump_addr;
+                self.replace(end_jump_addr, Inst::Jump(end_target, true, 0));
+            }
         }
         Ok(())
     }
@@ -802,13 +1860,62 @@ impl CompilerVisitor {
         op: InplaceOperator,
         expr_b: ast::Expr,
     ) -> VisitResult {
-        // TODO: Allow in place attribute updates
-        if expr_a.is_ident().is_none() {
-            return Err(CompErr::expected_ident(expr_a.start, expr_a.end));
+        if let Some(name) = expr_a.is_ident() {
+            // In a function, a name that isn't local or captured from
+            // an enclosing function refers to a global or builtin,
+            // which can only be updated when it's declared global.
+            if self.is_func()
+                && self.scope_tree.find_var(&name, None).is_none()
+                && !self.is_global_decl(&name)
+                && !self
+                    .enclosing_scopes
+                    .iter()
+                    .any(|scope_tree| scope_tree.find_var(&name, None).is_some())
+            {
+                return Err(CompErr::global_not_declared(
+                    name,
+                    expr_a.start,
+                    expr_a.end,
+                ));
+            }
+            self.visit_expr(expr_a, None)?;
+            self.visit_expr(expr_b, None)?;
+            // Globals are loaded as temporaries, so the result has to
+            // be stored explicitly.
+            if self.is_global_decl(&name) {
+                self.push(Inst::BinaryOp(op.binary_op()));
+                self.push(Inst::StoreGlobal(Symbol::intern(&name)));
+            } else {
+                self.push(Inst::InplaceOp(op));
+            }
+            return Ok(());
+        }
+        // Attributes and items are updated by loading the current value,
+        // applying the corresponding binary op, and storing the result.
+        // The object and attribute name/key are duplicated so they can
+        // be used for both the load and the store.
+        let (start, end) = (expr_a.start, expr_a.end);
+        match expr_a.kind {
+            ast::ExprKind::BinaryOp(obj_expr, BinaryOperator::Dot, name_expr) => {
+                self.visit_expr(*obj_expr, None)?;
+                self.visit_attr_name(*name_expr)?;
+                self.push(Inst::DupTop(2));
+                self.push(Inst::BinaryOp(BinaryOperator::Dot));
+                self.visit_expr(expr_b, None)?;
+                self.push(Inst::BinaryOp(op.binary_op()));
+                self.push(Inst::SetAttr);
+            }
+            ast::ExprKind::Subscript(obj_expr, key_expr) => {
+                self.visit_expr(*obj_expr, None)?;
+                self.visit_expr(*key_expr, None)?;
+                self.push(Inst::DupTop(2));
+                self.push(Inst::GetItem);
+                self.visit_expr(expr_b, None)?;
+                self.push(Inst::BinaryOp(op.binary_op()));
+                self.push(Inst::SetItem);
+            }
+            _ => return Err(CompErr::expected_ident(start, end)),
         }
-        self.visit_expr(expr_a, None)?;
-        self.visit_expr(expr_b, None)?;
-        self.push(Inst::InplaceOp(op));
         Ok(())
     }
 
@@ -826,6 +1933,10 @@ impl CompilerVisitor {
         self.scope_tree.in_global_scope()
     }
 
+    fn is_global_decl(&self, name: &str) -> bool {
+        self.global_decls.iter().any(|(n, ..)| n == name)
+    }
+
     fn has_builtin(&self, name: &str) -> bool {
         STD.read().unwrap().has_global(name)
     }
@@ -846,7 +1957,7 @@ impl CompilerVisitor {
     }
 
     fn push_placeholder(&mut self, inst: Inst, msg: &str) -> usize {
-        self.push(Inst::Placeholder(self.len(), Box::new(inst), msg.to_owned()))
+        self.push(Inst::Placeholder(self.len(), Box::new((inst, msg.to_owned()))))
     }
 
     // Global constants ------------------------------------------------
@@ -891,23 +2002,201 @@ impl CompilerVisitor {
         index
     }
 
+    fn push_load_module(&mut self, name: &str) -> usize {
+        self.push(Inst::LoadModule(Symbol::intern(name)))
+    }
+
+    // Vars ------------------------------------------------------------
+
+    /// Declare var in the current scope. Globals are declared by name
+    /// when the declaration is executed. Locals are assigned a slot in
+    /// their scope and are declared by the VM when the scope is entered
+    /// (or, for a function's top level scope, when it's called).
+    fn declare_var(&mut self, name: &str, assigned: bool, start: Location) {
+        self.scope_tree.add_var(self.len(), name, assigned, start);
+        if self.in_global_scope() {
+            self.push(Inst::DeclareVar(Symbol::intern(name)));
+        }
+    }
+
+    /// Assign TOS to var, which must be declared in the current scope.
+    fn push_assign_var(&mut self, name: String) {
+        let var = self.find_var_in_current_scope(&name);
+        if self.scope_tree.is_global(&var) {
+            self.push(Inst::AssignVar(Symbol::intern(&name)));
+        } else {
+            self.push(Inst::StoreLocal(var.index));
+        }
+    }
+
+    /// Load var, which can be in the current scope or any ancestor
+    /// scope. Globals are loaded by name and locals by slot.
+    fn push_load_var(&mut self, var: Var) -> usize {
+        let offset = self.scope_depth - var.depth;
+        if self.scope_tree.is_global(&var) {
+            self.push(Inst::LoadVar(Symbol::intern(&var.name), offset))
+        } else {
+            self.push(Inst::LoadLocal(offset, var.index))
+        }
+    }
+
+    /// Load var, which must be declared in the current scope.
+    fn push_load_local_var(&mut self, name: &str) -> usize {
+        let var = self.find_var_in_current_scope(name);
+        self.push_load_var(var)
+    }
+
+    /// Load free var, a var that isn't defined in the function being
+    /// visited. If it's defined in an enclosing function, it's loaded
+    /// from the cells captured by the closure. Otherwise, it's presumed
+    /// to be a global and is resolved when the function is finalized.
+    /// Names declared `global` are always globals.
+    fn push_free_var(&mut self, name: String, start: Location, end: Location) {
+        let level = if self.is_global_decl(&name) {
+            None
+        } else {
+            self.enclosing_scopes
+                .iter()
+                .position(|scope_tree| scope_tree.find_var(&name, None).is_some())
+        };
+        if let Some(level) = level {
+            self.add_capture(&name, level);
+            self.push(Inst::LoadCaptured(Symbol::intern(&name)));
+        } else {
+            self.code.add_free_var(name, start, end);
+        }
+    }
+
+    fn add_capture(&mut self, name: &str, level: usize) {
+        if !self.captures.iter().any(|(n, _)| n == name) {
+            self.captures.push((name.to_owned(), level));
+        }
+    }
+
+    /// Convert accesses of vars captured by nested functions to cell
+    /// accesses. Only accesses in the scope where a var is declared
+    /// are converted, since the same slot in nested scopes belongs to
+    /// other vars.
+    fn fix_cell_vars(&mut self) {
+        for var in mem::take(&mut self.cell_vars) {
+            let name = Symbol::intern(&var.name);
+            self.code.add_cell_var(name);
+            let mut replacements = vec![];
+            let mut nesting = 0;
+            for (addr, inst) in self.code.iter_chunk().enumerate().skip(var.addr) {
+                match inst {
+                    Inst::ScopeStart => {
+                        nesting += 1;
+                    }
+                    Inst::ScopeEnd if nesting == 0 => {
+                        break;
+                    }
+                    Inst::ScopeEnd => {
+                        nesting -= 1;
+                    }
+                    Inst::StoreLocal(i) if nesting == 0 && *i == var.index => {
+                        replacements.push((addr, Inst::AssignCell(name)));
+                    }
+                    Inst::LoadLocal(0, i) if nesting == 0 && *i == var.index => {
+                        replacements.push((addr, Inst::LoadCell(name)));
+                    }
+                    _ => (),
+                }
+            }
+            for (addr, inst) in replacements {
+                self.replace(addr, inst);
+            }
+        }
+    }
+
+    /// Convert calls that are the last operation before RETURN to tail
+    /// calls. A call is in tail position when only scope exits and
+    /// forward jumps come between it and RETURN.
+    fn mark_tail_calls(&mut self) {
+        let mut replacements = vec![];
+        for (addr, inst) in self.code.iter_chunk().enumerate() {
+            if let Inst::Call(num_args) = inst {
+                let mut next = addr + 1;
+                let is_tail_call = loop {
+                    match self.code[next] {
+                        Inst::ScopeEnd => next += 1,
+                        Inst::Jump(rel_addr, true, _) => next += rel_addr,
+                        Inst::Return => break true,
+                        _ => break false,
+                    }
+                };
+                if is_tail_call {
+                    replacements.push((addr, Inst::TailCall(*num_args)));
+                }
+            }
+        }
+        for (addr, inst) in replacements {
+            self.replace(addr, inst);
+        }
+    }
+
+    fn find_var_in_current_scope(&self, name: &str) -> Var {
+        let pointer = self.scope_tree.pointer();
+        match self.scope_tree.find_var(name, Some(pointer)) {
+            Some(var) if var.pointer == pointer => var,
+            _ => panic!("Var not declared in current scope: {name}"),
+        }
+    }
+
     // Scopes ----------------------------------------------------------
 
     /// Add nested scope to current scope then make the new scope the
     /// current scope.
     fn enter_scope(&mut self, kind: ScopeKind) {
         self.push(Inst::ScopeStart);
+        // The scope's locals aren't known until the scope is exited.
+        let addr = self.push(Inst::DeclareLocals(vec![]));
+        self.declare_locals_addrs.push(addr);
         self.scope_tree.add(kind);
         self.scope_depth += 1;
     }
 
     /// Move up to the parent scope of the current scope.
     fn exit_scope(&mut self) {
+        let addr = self.declare_locals_addrs.pop().expect("Expected scope to exit");
+        let names = self.scope_tree.var_names(self.scope_tree.pointer());
+        let names = names.iter().map(|n| Symbol::intern(n)).collect();
+        self.replace(addr, Inst::DeclareLocals(names));
         self.push(Inst::ScopeEnd);
         self.scope_tree.move_up();
         self.scope_depth -= 1;
     }
 
+    /// Scope depth of the innermost loop being visited.
+    fn loop_scope_depth(&self) -> usize {
+        self.loop_scope_depths.last().copied().unwrap_or(0)
+    }
+
+    /// Exit the `try` blocks that start at or below the specified scope
+    /// depth, innermost first, before jumping out of them. For each,
+    /// its active handler is popped so it can't catch errors raised
+    /// after the jump, then its `ensure` block is run. The value at
+    /// TOS (e.g., the value of a `break`) is left in place.
+    fn exit_try_blocks(&mut self, scope_depth: usize) -> VisitResult {
+        let try_blocks: Vec<_> = self
+            .try_blocks
+            .iter()
+            .rev()
+            .take_while(|try_block| try_block.scope_depth >= scope_depth)
+            .map(|try_block| (try_block.handler_active, try_block.ensure.clone()))
+            .collect();
+        for (handler_active, ensure) in try_blocks {
+            if handler_active {
+                self.push(Inst::PopHandler);
+            }
+            if let Some(ensure) = ensure {
+                self.visit_block(ensure)?;
+                self.push(Inst::Pop);
+            }
+        }
+        Ok(())
+    }
+
     /// Update jump instructions with their target label addresses.
     fn fix_jumps(&mut self) -> VisitResult {
         let code = &mut self.code;
@@ -1,162 +1,375 @@
-use std::fmt;
+//! Disassembler. Shows the instructions of a code unit along with its
+//! constants and free vars, either as a table or as JSON for external
+//! tools. Relative jumps are resolved to absolute addresses, and jump
+//! targets are marked with `>>` in the table.
+use std::collections::HashSet;
+use std::fmt::{self, Write};
 
+use crate::types::{FuncTrait, ObjectRef};
+use crate::util::Symbol;
 use crate::vm::{globals, Code, Inst};
 
 pub struct Disassembler {
     curr_line_no: usize,
-    new_line: bool,
 }
 
 impl Disassembler {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self { curr_line_no: 0, new_line: false }
+        Self { curr_line_no: 0 }
     }
 
     pub fn disassemble(&mut self, code: &Code) {
         use Inst::*;
         let width = 8;
-        let iter = code.iter_chunk().enumerate();
+        let targets = jump_targets(code);
         println!("{: <width$}    {:<width$}    INSTRUCTION", "LINE", "IP");
-        for (ip, inst) in iter {
-            let line = self.format_inst(code, inst);
+        for (ip, inst) in code.iter_chunk().enumerate() {
+            let (name, value) = self.format_inst(code, ip, inst);
+            let line = align(name, value);
+            // The line number is shown where the instructions for a new
+            // line start.
+            let new_line = match code.get_location(ip) {
+                Some((start, _)) if start.line != self.curr_line_no => {
+                    self.curr_line_no = start.line;
+                    true
+                }
+                _ => false,
+            };
             let line_no = if matches!(inst, Halt(_) | Pop) {
                 println!();
                 "".to_string()
-            } else if self.new_line {
+            } else if new_line {
                 println!();
-                self.new_line = false;
                 self.curr_line_no.to_string()
             } else {
                 "".to_string()
             };
-            println!("{line_no: <width$}    {ip:0>width$}    {line}");
+            let marker = if targets.contains(&ip) { ">>" } else { "" };
+            println!("{line_no: <width$}  {marker:<2}{ip:0>width$}    {line}");
+        }
+        if code.iter_constants().next().is_some() {
+            println!();
+            println!("CONSTANTS");
+            for (index, obj_ref) in code.iter_constants().enumerate() {
+                let obj = obj_ref.read().unwrap();
+                let class = obj.class();
+                let class = class.read().unwrap();
+                println!("{index: <width$}    {:<16}{:?}", class.name(), &*obj);
+            }
+        }
+        if !code.free_vars().is_empty() {
+            println!();
+            println!("FREE VARS");
+            for (_, name, start, _) in code.free_vars() {
+                println!("{name: <width$}    {start}");
+            }
         }
         for obj_ref in code.iter_constants() {
             let obj = obj_ref.read().unwrap();
             if let Some(func) = obj.down_to_func() {
                 println!();
-                let heading = format!("{func:?} ");
+                let kind = if func.is_method() { "(method) " } else { "" };
+                let heading = format!("{func:?} {kind}");
                 println!("{heading:=<79}");
                 self.disassemble(func.code());
             }
         }
     }
 
-    /// Align instruction name and any additional data, such as a
-    /// constant index, var name, etc.
-    fn align<T: fmt::Display>(&self, name: &str, value: T) -> String {
-        format!("{name: <w$}{value}", w = 24)
+    /// Disassemble code to JSON. The output is an object with these
+    /// keys:
+    ///
+    /// - `instructions`: objects with `ip`, `line` (the line of the
+    ///   statement the instruction belongs to or null), `op`, `arg`
+    ///   (formatted as in the table; may be empty), and `target` (the
+    ///   absolute address a jump goes to or null)
+    /// - `constants`: objects with `index`, `type`, and `value` (the
+    ///   constant's repr); functions also have a `name` and a `code`
+    ///   object with the same structure as the top level object
+    /// - `free_vars`: objects with `name`, `line`, and `col`
+    pub fn to_json(&self, code: &Code) -> String {
+        let mut out = String::new();
+        self.write_json(code, &mut out);
+        out
     }
 
-    fn format_inst(&mut self, code: &Code, inst: &Inst) -> String {
+    fn write_json(&self, code: &Code, out: &mut String) {
+        out.push_str("{\"instructions\":[");
+        for (ip, inst) in code.iter_chunk().enumerate() {
+            if ip > 0 {
+                out.push(',');
+            }
+            let (name, value) = self.format_inst(code, ip, inst);
+            let line = match code.get_location(ip) {
+                Some((start, _)) => start.line.to_string(),
+                None => "null".to_owned(),
+            };
+            let target = match jump_target(ip, inst) {
+                Some(addr) => addr.to_string(),
+                None => "null".to_owned(),
+            };
+            write!(out, "{{\"ip\":{ip},\"line\":{line},").unwrap();
+            write!(out, "\"op\":{},", json_str(name)).unwrap();
+            write!(out, "\"arg\":{},", json_str(&value)).unwrap();
+            write!(out, "\"target\":{target}}}").unwrap();
+        }
+        out.push_str("],\"constants\":[");
+        for (index, obj_ref) in code.iter_constants().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            let obj = obj_ref.read().unwrap();
+            let class = obj.class();
+            let class = class.read().unwrap();
+            write!(
+                out,
+                "{{\"index\":{index},\"type\":{},\"value\":{}",
+                json_str(class.name()),
+                json_str(&format!("{:?}", &*obj))
+            )
+            .unwrap();
+            if let Some(func) = obj.down_to_func() {
+                write!(out, ",\"name\":{},\"code\":", json_str(func.name())).unwrap();
+                self.write_json(func.code(), out);
+            }
+            out.push('}');
+        }
+        out.push_str("],\"free_vars\":[");
+        for (i, (_, name, start, _)) in code.free_vars().iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(
+                out,
+                "{{\"name\":{},\"line\":{},\"col\":{}}}",
+                json_str(name),
+                start.line,
+                start.col
+            )
+            .unwrap();
+        }
+        out.push_str("]}");
+    }
+
+    /// Get the name of an instruction along with any additional data,
+    /// such as a constant index, var name, etc. `ip` is the address of
+    /// the instruction, which is used to resolve jump targets.
+    fn format_inst(
+        &self,
+        code: &Code,
+        ip: usize,
+        inst: &Inst,
+    ) -> (&'static str, String) {
         use Inst::*;
         match inst {
-            NoOp => self.align("NOOP", "ø"),
-            Pop => self.align("POP", ""),
+            NoOp => op("NOOP", "ø"),
+            Pop => op("POP", ""),
+            DupTop(n) => op("DUP_TOP", n),
             LoadGlobalConst(index) => {
                 let op_code = "LOAD_GLOBAL_CONST";
                 let index = *index;
                 if let Some(obj) = globals::get_global_constant(index) {
-                    self.align(op_code, format!("{index} ({})", obj.read().unwrap()))
+                    op(op_code, format!("{index} ({})", obj.read().unwrap()))
                 } else {
-                    self.align(
-                        op_code,
-                        format!("{index} ([global constant does not exist])"),
-                    )
+                    op(op_code, format!("{index} ([global constant does not exist])"))
                 }
             }
-            LoadNil => self.align("LOAD_NIL", "nil"),
-            LoadTrue => self.align("LOAD_TRUE", "true"),
-            LoadFalse => self.align("LOAD_FALSE", "false"),
-            LoadAlways => self.align("LOAD_ALWAYS", "@"),
-            LoadEmptyStr => self.align("LOAD_EMPTY_STR", "\"\""),
-            LoadNewline => self.align("LOAD_NEWLINE", "\"\\n\""),
-            LoadEmptyTuple => self.align("LOAD_EMPTY_TUPLE", "()"),
-            ScopeStart => self.align("SCOPE_START", ""),
-            ScopeEnd => self.align("SCOPE_END", ""),
-            StatementStart(start, _) => {
-                self.new_line = start.line != self.curr_line_no;
-                self.curr_line_no = start.line;
-                self.align("STATEMENT_START", "")
-            }
+            LoadNil => op("LOAD_NIL", "nil"),
+            LoadTrue => op("LOAD_TRUE", "true"),
+            LoadFalse => op("LOAD_FALSE", "false"),
+            LoadAlways => op("LOAD_ALWAYS", "@"),
+            LoadEmptyStr => op("LOAD_EMPTY_STR", "\"\""),
+            LoadNewline => op("LOAD_NEWLINE", "\"\\n\""),
+            LoadEmptyTuple => op("LOAD_EMPTY_TUPLE", "()"),
+            ScopeStart => op("SCOPE_START", ""),
+            ScopeEnd => op("SCOPE_END", ""),
             LoadConst(index) => {
                 let constant = match code.get_const(*index) {
                     Ok(obj) => obj.read().unwrap().to_string(),
                     Err(err) => err.to_string(),
                 };
-                self.align("LOAD_CONST", format!("{index} ({constant:?})"))
-            }
-            DeclareVar(name) => self.align("DECLARE_VAR", name),
-            AssignVar(name) => self.align("ASSIGN_VAR", name),
-            LoadVar(name, offset) => {
-                self.align("LOAD_VAR", format!("{name} @ -{offset}"))
-            }
-            LoadGlobal(name) => self.align("LOAD_GLOBAL", name),
-            LoadBuiltin(name) => self.align("LOAD_BUILTIN", name),
-            AssignCell(name) => self.align("ASSIGN_CELL", name),
-            LoadCell(name) => self.align("LOAD_CELL", name),
-            LoadCaptured(name) => self.align("LOAD_CAPTURED", name),
+                op("LOAD_CONST", format!("{index} ({constant:?})"))
+            }
+            DeclareVar(name) => op("DECLARE_VAR", name),
+            AssignVar(name) => op("ASSIGN_VAR", name),
+            LoadVar(name, offset) => op("LOAD_VAR", format!("{name} @ -{offset}")),
+            DeclareLocals(names) => op("DECLARE_LOCALS", join_names(names)),
+            LoadLocal(offset, index) => {
+                op("LOAD_LOCAL", format!("{index} @ -{offset}"))
+            }
+            StoreLocal(index) => op("STORE_LOCAL", index),
+            LoadHoisted(offset, index) => {
+                op("LOAD_HOISTED", format!("{index} @ -{offset}"))
+            }
+            LoadGlobal(name) => op("LOAD_GLOBAL", name),
+            StoreGlobal(name) => op("STORE_GLOBAL", name),
+            LoadBuiltin(name) => op("LOAD_BUILTIN", name),
+            AssignCell(name) => op("ASSIGN_CELL", name),
+            LoadCell(name) => op("LOAD_CELL", name),
+            LoadCaptured(name) => op("LOAD_CAPTURED", name),
+            RenewCells(indices) => {
+                let indices: Vec<String> =
+                    indices.iter().map(|i| i.to_string()).collect();
+                op("RENEW_CELLS", indices.join(", "))
+            }
             Jump(rel_addr, forward, _) => {
-                let kind = if *forward { "forward" } else { "backward" };
-                self.align("JUMP", format!("{rel_addr} ({kind})"))
+                op("JUMP", format_jump(ip, *rel_addr, *forward))
             }
             JumpPushNil(rel_addr, forward, _) => {
-                let kind = if *forward { "forward" } else { "backward" };
-                self.align("JUMP_PUSH_NIL", format!("{rel_addr} ({kind})"))
+                op("JUMP_PUSH_NIL", format_jump(ip, *rel_addr, *forward))
             }
             JumpIf(rel_addr, forward, _) => {
-                let kind = if *forward { "forward" } else { "backward" };
-                self.align("JUMP_IF", format!("{rel_addr} ({kind})"))
+                op("JUMP_IF", format_jump(ip, *rel_addr, *forward))
             }
             JumpIfNot(rel_addr, forward, _) => {
-                let kind = if *forward { "forward" } else { "backward" };
-                self.align("JUMP_IF_NOT", format!("{rel_addr} ({kind})"))
+                op("JUMP_IF_NOT", format_jump(ip, *rel_addr, *forward))
             }
             JumpIfNotNil(rel_addr, forward, _) => {
-                let kind = if *forward { "forward" } else { "backward" };
-                self.align("JUMP_IF_NIL", format!("{rel_addr} ({kind})"))
-            }
-            UnaryOp(op) => self.align("UNARY_OP", op),
-            BinaryOp(op) => self.align("BINARY_OP", op),
-            CompareOp(op) => self.align("COMPARE_OP", op),
-            InplaceOp(op) => self.align("INPLACE_OP", op),
-            Call(num_args) => self.align("CALL", num_args),
-            Return => self.align("RETURN", ""),
-            MakeString(n) => self.align("MAKE_STRING", n),
-            MakeTuple(n) => self.align("MAKE_TUPLE", n),
-            MakeList(n) => self.align("MAKE_LIST", n),
-            MakeMap(n) => self.align("MAKE_MAP", n),
-            CaptureSet(names) => {
-                self.align("CAPTURE_SET", format!("[{}]", names.join(", ")))
-            }
-            MakeFunc => self.align("MAKE_FUNC", ""),
-            LoadModule(name) => self.align("IMPORT", name),
-            Halt(code) => self.align("HALT", code),
-            HaltTop => self.align("HALT_TOP", ""),
+                op("JUMP_IF_NIL", format_jump(ip, *rel_addr, *forward))
+            }
+            PushHandler(rel_addr) => {
+                op("PUSH_HANDLER", format!("{rel_addr} -> {}", ip + rel_addr))
+            }
+            PopHandler => op("POP_HANDLER", ""),
+            RaiseIfErr => op("RAISE_IF_ERR", ""),
+            Raise => op("RAISE", ""),
+            UnaryOp(o) => op("UNARY_OP", o),
+            BinaryOp(o) => op("BINARY_OP", o),
+            CompareOp(o) => op("COMPARE_OP", o),
+            InplaceOp(o) => op("INPLACE_OP", o),
+            GetAttr(obj_text) => op("GET_ATTR", obj_text),
+            GetItem => op("GET_ITEM", ""),
+            UnpackSequence(n) => op("UNPACK_SEQUENCE", n),
+            SetAttr => op("SET_ATTR", ""),
+            SetItem => op("SET_ITEM", ""),
+            Call(num_args) => op("CALL", num_args),
+            TailCall(num_args) => op("TAIL_CALL", num_args),
+            Await => op("AWAIT", ""),
+            Return => op("RETURN", ""),
+            MakeString(n) => op("MAKE_STRING", n),
+            MakeTuple(n) => op("MAKE_TUPLE", n),
+            MakeList(n) => op("MAKE_LIST", n),
+            MakeMap(n) => op("MAKE_MAP", n),
+            MakeSet(n) => op("MAKE_SET", n),
+            ExtendList(n) => op("EXTEND_LIST", n),
+            ExtendMap(n) => op("EXTEND_MAP", n),
+            ListToTuple => op("LIST_TO_TUPLE", ""),
+            ListToSet => op("LIST_TO_SET", ""),
+            CaptureSet(names) => op("CAPTURE_SET", format!("[{}]", join_names(names))),
+            MakeFunc => op("MAKE_FUNC", ""),
+            MakeType(type_args, n) => {
+                let (name, fields) = &**type_args;
+                op("MAKE_TYPE", format!("{name} ({}) {n}", fields.join(", ")))
+            }
+            MakeEnum(type_args) => {
+                let (name, variants) = &**type_args;
+                op("MAKE_ENUM", format!("{name} ({})", variants.join(", ")))
+            }
+            LoadModule(name) => op("IMPORT", name),
+            Halt(code) => op("HALT", code),
+            HaltTop => op("HALT_TOP", ""),
             // None of the following should ever appear in the list. If they
             // do, something has gone horribly wrong.
-            Placeholder(addr, inst, message) => {
-                let formatted_inst = self.format_inst(code, inst);
-                self.align(
-                    "PLACEHOLDER",
-                    format!("{formatted_inst} @ {addr} ({message})"),
-                )
+            Placeholder(addr, inst_and_message) => {
+                let (inst, message) = &**inst_and_message;
+                let (name, value) = self.format_inst(code, *addr, inst);
+                let formatted_inst = align(name, value);
+                op("PLACEHOLDER", format!("{formatted_inst} @ {addr} ({message})"))
             }
             FreeVarPlaceholder(addr, name) => {
-                self.align("PLACEHOLDER", format!("VAR {name} @ {addr}"))
-            }
-            BreakPlaceholder(addr, _) => {
-                self.align("PLACEHOLDER", format!("BREAK @ {addr}"))
+                op("PLACEHOLDER", format!("VAR {name} @ {addr}"))
             }
+            BreakPlaceholder(addr, _) => op("PLACEHOLDER", format!("BREAK @ {addr}")),
             ContinuePlaceholder(addr, _) => {
-                self.align("PLACEHOLDER", format!("CONTINUE @ {addr}"))
+                op("PLACEHOLDER", format!("CONTINUE @ {addr}"))
+            }
+            ReturnPlaceholder(addr, _) => op("PLACEHOLDER", format!("RETURN @ {addr}")),
+            Print(flags) => op("PRINT_TOP", format!("flags = {flags:?}")),
+            DisplayStack(message) => op("DISPLAY_STACK", message),
+        }
+    }
+}
+
+/// Find the function with the specified name in the constants of
+/// `code` or, recursively, in the constants of the functions defined
+/// there. Functions are searched depth first in the order they're
+/// defined.
+pub fn find_func(code: &Code, name: &str) -> Option<ObjectRef> {
+    for obj_ref in code.iter_constants() {
+        let obj = obj_ref.read().unwrap();
+        if let Some(func) = obj.down_to_func() {
+            if func.name() == name {
+                return Some(obj_ref.clone());
             }
-            ReturnPlaceholder(addr, _) => {
-                self.align("PLACEHOLDER", format!("RETURN @ {addr}"))
+            if let Some(func_ref) = find_func(func.code(), name) {
+                return Some(func_ref);
             }
-            Print(flags) => self.align("PRINT_TOP", format!("flags = {flags:?}")),
-            DisplayStack(message) => self.align("DISPLAY_STACK", message),
         }
     }
+    None
+}
+
+/// Align instruction name and any additional data, such as a constant
+/// index, var name, etc.
+fn align(name: &str, value: String) -> String {
+    format!("{name: <w$}{value}", w = 24)
+}
+
+fn op<T: fmt::Display>(name: &'static str, value: T) -> (&'static str, String) {
+    (name, value.to_string())
+}
+
+fn format_jump(ip: usize, rel_addr: usize, forward: bool) -> String {
+    if forward {
+        format!("{rel_addr} (forward) -> {}", ip + rel_addr)
+    } else {
+        format!("{rel_addr} (backward) -> {}", ip - rel_addr)
+    }
+}
+
+/// Get the absolute address the jump instruction at `ip` goes to (or
+/// where the handler pushed by a `PUSH_HANDLER` instruction starts).
+fn jump_target(ip: usize, inst: &Inst) -> Option<usize> {
+    use Inst::*;
+    match inst {
+        Jump(rel_addr, forward, _)
+        | JumpPushNil(rel_addr, forward, _)
+        | JumpIf(rel_addr, forward, _)
+        | JumpIfNot(rel_addr, forward, _)
+        | JumpIfNotNil(rel_addr, forward, _) => {
+            Some(if *forward { ip + rel_addr } else { ip - rel_addr })
+        }
+        PushHandler(rel_addr) => Some(ip + rel_addr),
+        _ => None,
+    }
+}
+
+fn jump_targets(code: &Code) -> HashSet<usize> {
+    code.iter_chunk()
+        .enumerate()
+        .filter_map(|(ip, inst)| jump_target(ip, inst))
+        .collect()
+}
+
+fn json_str(string: &str) -> String {
+    let mut out = String::with_capacity(string.len() + 2);
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn join_names(names: &[Symbol]) -> String {
+    names.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", ")
 }
@@ -1,19 +1,30 @@
 //! Front end for executing code from a source on a VM.
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
-use std::fs::canonicalize;
-use std::io::{BufRead, Read};
-use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::env;
+use std::fs::{self, canonicalize};
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Once, RwLock};
+use std::time::SystemTime;
 
 use flate2::read::GzDecoder;
 use once_cell::sync::Lazy;
 use tar::Archive as TarArchive;
 
+use crate::ast;
+use crate::bundle;
 use crate::compiler::{CompErr, CompErrKind, Compiler};
+#[cfg(feature = "dis")]
+use crate::dis;
+use crate::modules::registry::{self, FuncSpec};
 use crate::modules::std::{self as stdlib, STD};
-use crate::modules::{add_module, maybe_get_module, MODULES};
-use crate::parser::{ParseErr, ParseErrKind, Parser};
+use crate::modules::{add_module, maybe_get_module, remove_module, MODULES};
+use crate::native::NativeModuleBuilder;
+use crate::parser::{ParseErr, ParseErrKind, ParseWarning, ParseWarningKind, Parser};
 use crate::result::ExeErrKind::ModuleNotFound;
 use crate::result::{ExeErr, ExeErrKind, ExeResult};
 use crate::scanner::{ScanErr, ScanErrKind, Scanner, Token, TokenWithLocation};
@@ -22,12 +33,14 @@ use crate::source::{
     Source,
 };
 use crate::types::gen::obj_ref;
-use crate::types::{new, Module, ObjectRef, ObjectTrait};
+use crate::types::{new, FromObjectRef, IntoObjectRef, Module, ObjectRef, ObjectTrait};
+use crate::util::{deterministic, Symbol};
+use crate::value::Value;
 use crate::vm::{
-    CallDepth, Inst, ModuleExecutionContext, PrintFlags, RuntimeErr, RuntimeErrKind,
-    VMExeResult, VMState, VM,
+    CallDepth, CancelHandle, Code, ImportResolver, Inst, ModuleExecutionContext,
+    OutputHandler, PrintFlags, RuntimeErr, RuntimeErrKind, RuntimeObjResult,
+    TraceFrame, VMExeResult, VMState, VM,
 };
-use crate::{ast, dis};
 
 /// At build time, a compressed archive is created containing the
 /// std .fi module files (see `build.rs`).
@@ -56,14 +69,84 @@ static STD_FI_MODULES: Lazy<HashMap<String, Vec<u8>>> = Lazy::new(|| {
     modules
 });
 
+thread_local! {
+    /// The number of `catch_panic` calls in progress on this thread.
+    static CATCHING_PANIC: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Wrap the current panic hook so that it's skipped for panics that
+/// will be caught by `catch_panic`. The hook is process-wide, so it's
+/// only ever wrapped once, and panics on other threads (or outside of
+/// `catch_panic`) are still reported by the original hook.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING_PANIC.with(|depth| depth.get()) == 0 {
+                default_hook(info);
+            }
+        }));
+    });
+}
+
+/// Directories that are searched for modules that aren't std modules.
+/// `import a.b` resolves to `a/b.fi` in the first directory that
+/// contains it. The directories are searched in this order:
+///
+/// 1. The directory containing the script being executed or, if not
+///    executing a script, the current directory
+/// 2. Directories added via `Executor::add_search_path` (e.g., via the
+///    `--path` CLI option)
+/// 3. Directories in the `FEINT_PATH` environment variable
+#[derive(Clone, Default)]
+struct ModuleSearchPath {
+    script_dir: Option<PathBuf>,
+    dirs: Vec<PathBuf>,
+}
+
+impl ModuleSearchPath {
+    fn dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.script_dir.clone().unwrap_or_else(|| ".".into())];
+        dirs.extend(self.dirs.iter().cloned());
+        if let Some(paths) = env::var_os("FEINT_PATH") {
+            dirs.extend(env::split_paths(&paths).filter(|p| !p.as_os_str().is_empty()));
+        }
+        dirs
+    }
+
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        let rel_path: PathBuf = name.split('.').collect();
+        let rel_path = rel_path.with_extension("fi");
+        self.dirs().into_iter().map(|dir| dir.join(&rel_path)).find(|p| p.is_file())
+    }
+}
+
 pub struct Executor {
     vm: VM,
     argv: Vec<String>,
     incremental: bool,
     dis: bool,
+    #[cfg_attr(not(feature = "dis"), allow(dead_code))]
+    dis_json: bool,
+    #[cfg_attr(not(feature = "dis"), allow(dead_code))]
+    dis_func: Option<String>,
     debug: bool,
+    optimize: bool,
+    print_result: bool,
+    debugger: bool,
     current_file_name: String,
     imports: VecDeque<String>,
+    // Shared with the VM's import resolver so modules loaded at
+    // runtime are found in the same places.
+    search_path: Arc<RwLock<ModuleSearchPath>>,
+    // Names and paths of modules loaded from the file system. Shared
+    // with the VM's import resolver so modules loaded at runtime are
+    // included.
+    module_files: Arc<RwLock<Vec<(String, PathBuf)>>>,
+    // Modules that are currently being loaded, used to detect circular
+    // imports.
+    loading: Vec<String>,
 }
 
 impl Executor {
@@ -74,16 +157,37 @@ impl Executor {
         dis: bool,
         debug: bool,
     ) -> Self {
-        let vm = VM::new(ModuleExecutionContext::default(), max_call_depth);
+        let search_path = Arc::new(RwLock::new(ModuleSearchPath::default()));
+        let module_files = Arc::new(RwLock::new(vec![]));
+        let resolver_search_path = search_path.clone();
+        let resolver_module_files = module_files.clone();
+        let mut vm = VM::new(ModuleExecutionContext::default(), max_call_depth);
+        vm.set_import_resolver(Box::new(move |name| {
+            Self::resolve_import(
+                name,
+                max_call_depth,
+                debug,
+                &resolver_search_path,
+                &resolver_module_files,
+            )
+        }));
 
         Self {
             vm,
             argv,
             incremental,
             dis,
+            dis_json: false,
+            dis_func: None,
             debug,
+            optimize: false,
+            print_result: false,
+            debugger: false,
             current_file_name: "<none>".to_owned(),
             imports: VecDeque::new(),
+            search_path,
+            module_files,
+            loading: vec![],
         }
     }
 
@@ -96,10 +200,94 @@ impl Executor {
         };
     }
 
+    /// Set current file name from the path of the script being
+    /// executed, which also determines the script directory that's
+    /// searched for modules.
+    fn set_script_file(&mut self, path: &Path) {
+        self.set_current_file_name(path);
+        let script_dir = Path::new(&self.current_file_name).parent();
+        self.search_path.write().unwrap().script_dir =
+            script_dir.map(|dir| dir.to_path_buf());
+    }
+
+    /// Enable or disable process-wide deterministic mode. This should
+    /// be called before `bootstrap` so that `system.deterministic` is
+    /// set correctly.
+    pub fn set_deterministic(&mut self, enabled: bool) {
+        deterministic::set_enabled(enabled);
+    }
+
+    /// Enable or disable the optimizer for modules compiled by this
+    /// executor. Code entered in the REPL isn't optimized.
+    pub fn set_optimize(&mut self, enabled: bool) {
+        self.optimize = enabled;
+    }
+
+    /// Output disassembly as JSON instead of as a table. This only has
+    /// an effect when disassembly is enabled.
+    pub fn set_dis_json(&mut self, enabled: bool) {
+        self.dis_json = enabled;
+    }
+
+    /// Disassemble only the function with the specified name, which is
+    /// searched for in the main module and, recursively, in the
+    /// functions it defines. This only has an effect when disassembly
+    /// is enabled.
+    pub fn set_dis_func(&mut self, name: Option<String>) {
+        self.dis_func = name;
+    }
+
+    /// Enable or disable the interactive debugger. When enabled, the
+    /// debugger is attached before the main module is executed, so
+    /// execution stops at its first statement. Debugger commands are
+    /// read from stdin.
+    pub fn set_debugger(&mut self, enabled: bool) {
+        self.debugger = enabled;
+    }
+
+    /// Enable or disable printing the value of the last expression in
+    /// text run via `execute_text`. Values are printed to stdout the
+    /// same way as in the REPL, except that nil isn't printed.
+    pub fn set_print_result(&mut self, enabled: bool) {
+        self.print_result = enabled;
+    }
+
+    /// Inject the time returned by `std.time.now()` in deterministic
+    /// mode. Passing `None` restores the default frozen time.
+    pub fn set_clock(&mut self, time: Option<SystemTime>) {
+        deterministic::set_clock(time);
+    }
+
+    /// Replace the default import resolver, which loads std modules,
+    /// with a custom resolver. This allows the host to provide modules
+    /// that are imported at runtime (e.g., plugins).
+    pub fn set_import_resolver(&mut self, resolver: ImportResolver) {
+        self.vm.set_import_resolver(resolver);
+    }
+
+    /// Capture output, including error messages, instead of writing it
+    /// to stdout/stderr (e.g., to show it in a web page).
+    pub fn set_output_handler(&mut self, handler: OutputHandler) {
+        self.vm.set_output_handler(handler);
+    }
+
+    /// Add a directory to search for non-std modules. Directories are
+    /// searched in the order they're added, after the script directory.
+    pub fn add_search_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.search_path.write().unwrap().dirs.push(path.into());
+    }
+
     pub fn install_sigint_handler(&mut self) {
         self.vm.install_sigint_handler();
     }
 
+    /// Get a handle that can be used to cancel execution from another
+    /// thread. Cancelled execution returns an error for which
+    /// `ExeErr::is_cancelled()` is true.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.vm.cancel_handle()
+    }
+
     // Bootstrap -------------------------------------------------------
 
     /// Bootstrap and return error on failure.
@@ -112,7 +300,8 @@ impl Executor {
         // Add the `system` module next because other modules may rely
         // on it (except for `std`), and its where we store system
         // information, such as loaded modules, `argv`, etc.
-        let system_ref = self.load_module("std.system")?;
+        let system_ref = stdlib::SYSTEM.clone();
+        self.extend_intrinsic_module(system_ref.clone(), "std.system")?;
         self.add_module("std.system", system_ref.clone());
 
         // Set `system.argv` before adding any other modules in case
@@ -121,9 +310,42 @@ impl Executor {
             let mut system = system_ref.write().unwrap();
             system.ns_mut().insert("modules", MODULES.clone());
             system.ns_mut().insert("argv", new::argv_tuple(&self.argv));
+            system
+                .ns_mut()
+                .insert("deterministic", new::bool(deterministic::is_enabled()));
+            system.ns_mut().insert("keywords", new::keywords_tuple());
+        }
+
+        self.add_module("std.async", stdlib::ASYNC.clone());
+        self.add_module("std.collections", stdlib::COLLECTIONS.clone());
+
+        // The remaining intrinsic modules are optional. Those that
+        // don't need the OS are included in std-min.
+        #[cfg(feature = "std-min")]
+        {
+            self.add_module("std.itertools", stdlib::ITERTOOLS.clone());
+            self.add_module("std.json", stdlib::JSON.clone());
+            self.add_module("std.math", stdlib::MATH.clone());
+            self.add_module("std.random", stdlib::RANDOM.clone());
+        }
+
+        #[cfg(feature = "std-full")]
+        {
+            self.add_module("std.proc", stdlib::PROC.clone());
+            self.add_module("std.os", stdlib::OS.clone());
+            self.add_module("std.fs", stdlib::FS.clone());
+            self.add_module("std.glob", stdlib::GLOB.clone());
+            self.add_module("std.hash", stdlib::HASH.clone());
+            self.add_module("std.thread", stdlib::THREAD.clone());
+            self.add_module("std.time", stdlib::TIME.clone());
         }
 
-        self.add_module("std.proc", stdlib::PROC.clone());
+        #[cfg(feature = "http")]
+        self.add_module("std.http", stdlib::HTTP.clone());
+
+        // All intrinsic modules and types have been initialized at
+        // this point, so all intrinsic functions have been registered.
+        registry::check().map_err(|msg| ExeErr::new(ExeErrKind::Bootstrap(msg)))?;
 
         Ok(())
     }
@@ -139,8 +361,13 @@ impl Executor {
         let fi_module = fi_module.read().unwrap();
         let fi_module = fi_module.down_to_mod().unwrap();
         let mut base_module = base_module.write().unwrap();
-        for (name, val) in fi_module.iter_globals() {
-            base_module.ns_mut().insert(name, val.clone());
+        let registry = registry::registry();
+        for (global, val) in fi_module.iter_globals() {
+            if registry.get(name, global).is_some() {
+                let msg = format!("{name}.{global} is defined in both Rust and FeInt");
+                return Err(ExeErr::new(ExeErrKind::Bootstrap(msg)));
+            }
+            base_module.ns_mut().insert(global, val.clone());
         }
         Ok(())
     }
@@ -168,7 +395,9 @@ impl Executor {
         let source = &mut source_from_text(text);
         let ast_module = self.parse_source(source)?;
         let mut compiler = Compiler::new(global_names);
-        let comp_result = compiler.compile_module_to_code("$repl", ast_module);
+        compiler.set_debug(self.debug);
+        let comp_result =
+            self.catch_panic(|_| compiler.compile_module_to_code("$repl", ast_module))?;
 
         let mut code = comp_result.map_err(|err| {
             self.handle_comp_err(&err, source);
@@ -182,8 +411,9 @@ impl Executor {
                 | PrintFlags::NL
                 | PrintFlags::REPR
                 | PrintFlags::NO_NIL;
-            code.push_inst(Inst::DeclareVar("_".to_owned()));
-            code.push_inst(Inst::AssignVar("_".to_owned()));
+            let name = Symbol::intern("_");
+            code.push_inst(Inst::DeclareVar(name));
+            code.push_inst(Inst::AssignVar(name));
             code.push_inst(Inst::Print(print_flags));
         } else {
             let last_inst = match last_inst {
@@ -193,39 +423,48 @@ impl Executor {
             panic!("Expected module chunk to end with POP; got {last_inst}");
         }
 
-        {
-            let mut module = module.write().unwrap();
-            let module = module.down_to_mod_mut().unwrap();
-            module.code_mut().extend(code);
+        // The first chunk starts from a clean slate so that state left
+        // over from bootstrapping doesn't leak into the REPL. Subsequent
+        // chunks continue from where the previous chunk left off.
+        if start == 0 {
+            self.vm.reset();
         }
 
-        let vm_state = {
+        let result = {
             let module = module.read().unwrap();
             let module = module.down_to_mod().unwrap();
-            self.execute_module(module, start, source, false)?
+            self.execute_chunk(module, &code, source)
         };
 
+        // NOTE: Globals are synced even if the chunk failed so that
+        //       bindings from statements that executed successfully
+        //       before the error aren't lost. Globals declared by the
+        //       failed statement itself were already removed by the VM
+        //       (see `VM::continue_with`).
         {
             let mut module = module.write().unwrap();
             let module = module.down_to_mod_mut().unwrap();
+            module.code_mut().extend(code);
             for (name, obj) in self.vm.ctx.globals().iter() {
-                module.add_global(name, obj.clone());
+                module.add_global(name.as_str(), obj.clone());
             }
         }
 
-        Ok(vm_state)
+        result
     }
 
     /// Execute source from file as script.
     pub fn execute_file(&mut self, file_path: &Path) -> ExeResult {
         match source_from_file(file_path) {
             Ok(mut source) => {
-                self.set_current_file_name(file_path);
-                self.execute_script_from_source(&mut source)
+                self.set_script_file(file_path);
+                self.execute_script_from_source(&mut source, false)
             }
             Err(err) => {
                 let message = format!("{}: {err}", file_path.display());
-                Err(ExeErr::new(ExeErrKind::CouldNotReadSourceFile(message)))
+                let err = ExeErr::new(ExeErrKind::CouldNotReadSourceFile(message));
+                self.handle_import_err(&err);
+                Err(err)
             }
         }
     }
@@ -234,24 +473,38 @@ impl Executor {
     pub fn execute_stdin(&mut self) -> ExeResult {
         self.current_file_name = "<stdin>".to_owned();
         let mut source = source_from_stdin();
-        self.execute_script_from_source(&mut source)
+        self.execute_script_from_source(&mut source, false)
     }
 
     /// Execute text as script.
     pub fn execute_text(&mut self, text: &str) -> ExeResult {
         self.current_file_name = "<text>".to_owned();
         let mut source = source_from_text(text);
-        self.execute_script_from_source(&mut source)
+        self.execute_script_from_source(&mut source, self.print_result)
     }
 
     /// Execute source as script. The source will be compiled into a
     /// module. If the module contains a global `$main` function, it
     /// will be run automatically.
+    ///
+    /// If `print_result` is set, the value of the last expression in
+    /// the module will be printed (unless it's nil).
     fn execute_script_from_source<T: BufRead>(
         &mut self,
         source: &mut Source<T>,
+        print_result: bool,
     ) -> ExeResult {
-        let module = self.compile_module("$main", source)?;
+        let mut module = self.compile_module("$main", source)?;
+        if print_result {
+            // Print TOS instead of popping it
+            let code = module.code_mut();
+            let last = code.len_chunk().checked_sub(1);
+            if let Some(last) = last.filter(|&i| matches!(code[i], Inst::Pop)) {
+                let print_flags =
+                    PrintFlags::NL | PrintFlags::REPR | PrintFlags::NO_NIL;
+                code.replace_inst(last, Inst::Print(print_flags));
+            }
+        }
         let module_ref = obj_ref!(module);
         self.add_module("$main", module_ref.clone());
         let module = module_ref.read().unwrap();
@@ -260,7 +513,13 @@ impl Executor {
     }
 
     pub fn execute_module_as_script(&mut self, name: &str) -> ExeResult {
-        let module = self.get_or_add_module(name)?;
+        let module = match self.get_or_add_module(name) {
+            Ok(module) => module,
+            Err(err) => {
+                self.handle_import_err(&err);
+                return Err(err);
+            }
+        };
         let module = module.read().unwrap();
         let module = module.down_to_mod().unwrap();
         self.execute_module(module, 0, &mut source_from_bytes(&vec![]), true)
@@ -278,8 +537,32 @@ impl Executor {
         is_main: bool,
     ) -> ExeResult {
         if self.dis && is_main {
-            let mut disassembler = dis::Disassembler::new();
-            disassembler.disassemble(module.code());
+            #[cfg(feature = "dis")]
+            {
+                let func_ref = match &self.dis_func {
+                    Some(name) => match dis::find_func(module.code(), name) {
+                        Some(func_ref) => Some(func_ref),
+                        None => {
+                            eprintln!("Function not found: {name}");
+                            return Ok(VMState::Halted(1));
+                        }
+                    },
+                    None => None,
+                };
+                let func = func_ref.as_ref().map(|func_ref| func_ref.read().unwrap());
+                let code = match &func {
+                    Some(func) => func.down_to_func().unwrap().code(),
+                    None => module.code(),
+                };
+                let mut disassembler = dis::Disassembler::new();
+                if self.dis_json {
+                    println!("{}", disassembler.to_json(code));
+                } else {
+                    disassembler.disassemble(code);
+                }
+            }
+            #[cfg(not(feature = "dis"))]
+            eprintln!("Cannot disassemble: FeInt was built without the dis feature");
             if self.debug {
                 self.display_stack();
             }
@@ -288,26 +571,42 @@ impl Executor {
 
         self.load_imported_modules()?;
 
-        let mut result = self.vm.execute_module(module, start);
-
-        if result.is_ok() && is_main {
-            if let Some(main) = module.get_main() {
-                let main = main.read().unwrap();
-                let args = self.argv.iter().map(new::str).collect();
-                if let Some(main) = main.down_to_func() {
-                    result = self
-                        .vm
-                        .call_func(main, None, args, None)
-                        .and_then(|_| self.vm.halt_top());
-                } else if let Some(main) = main.down_to_intrinsic_func() {
-                    result = self
-                        .vm
-                        .call_intrinsic_func(main, None, args)
-                        .and_then(|_| self.vm.halt_top());
+        if self.debugger && is_main {
+            self.vm.attach_debugger(Box::new(BufReader::new(io::stdin())));
+        }
+
+        let result = self.catch_panic(|exe| {
+            let mut result = exe.vm.execute_module(module, start);
+            if result.is_ok() && is_main {
+                if let Some(main) = module.get_main() {
+                    let args = exe.argv.iter().map(new::str).collect();
+                    result = exe.vm.call(main, args).and_then(|_| exe.vm.halt_top());
                 }
             }
-        }
+            result
+        })?;
 
+        self.handle_execution_result(result, source)
+    }
+
+    /// Execute a chunk of code in the context of the specified module,
+    /// continuing from the VM's current state (see `VM::continue_with`).
+    fn execute_chunk<T: BufRead>(
+        &mut self,
+        module: &Module,
+        code: &Code,
+        source: &mut Source<T>,
+    ) -> ExeResult {
+        self.load_imported_modules()?;
+        let result = self.catch_panic(|exe| exe.vm.continue_with(module, code))?;
+        self.handle_execution_result(result, source)
+    }
+
+    fn handle_execution_result<T: BufRead>(
+        &mut self,
+        result: VMExeResult,
+        source: &mut Source<T>,
+    ) -> ExeResult {
         if self.debug {
             self.display_stack();
             self.display_vm_state(&result);
@@ -316,21 +615,110 @@ impl Executor {
         match result {
             Ok(()) => Ok(self.vm.state.clone()),
             Err(err) => {
-                if let RuntimeErrKind::Exit(_) = err.kind {
+                // Exits and cancellations aren't errors in the code
+                // being executed, so no error message is printed.
+                if let RuntimeErrKind::Exit(_) | RuntimeErrKind::Cancelled = err.kind {
                     Err(ExeErr::new(ExeErrKind::RuntimeErr(err.kind)))
                 } else {
+                    let traceback = self.vm.take_traceback();
                     let start = self.vm.loc().0;
-                    let line = source
-                        .get_line(start.line)
-                        .unwrap_or("<source line not available>");
-                    self.print_err_line(start.line, line);
-                    self.handle_runtime_err(&err);
+                    match traceback.last() {
+                        // The error occurred in a function defined in
+                        // another module.
+                        Some(frame) if frame.module_path != self.current_file_name => {
+                            let path = frame.module_path.as_str();
+                            let line = read_source_line(path, start.line);
+                            let line = line
+                                .as_deref()
+                                .unwrap_or("<source line not available>");
+                            self.print_source_line("Error", path, start.line, line);
+                        }
+                        _ => {
+                            let line = source
+                                .get_line(start.line)
+                                .unwrap_or("<source line not available>");
+                            self.print_err_line(start.line, line);
+                        }
+                    }
+                    self.handle_runtime_err(&err, &traceback);
                     Err(ExeErr::new(ExeErrKind::RuntimeErr(err.kind)))
                 }
             }
         }
     }
 
+    // Embedding -------------------------------------------------------
+
+    /// Compile and execute module source text once and add the module
+    /// to `system.modules` under `name`. Its global functions can then
+    /// be called from Rust via `call`.
+    pub fn load_module_from_text(
+        &mut self,
+        name: &str,
+        text: &str,
+    ) -> Result<(), ExeErr> {
+        let current_file_name = self.current_file_name.clone();
+        self.current_file_name = format!("<{name}>");
+        let result = self.load_module_from_source(name, &mut source_from_text(text));
+        self.current_file_name = current_file_name;
+        self.add_module(name, result?);
+        Ok(())
+    }
+
+    /// Call a global function in a loaded module with Rust-native args
+    /// and convert its return value back to a Rust-native value.
+    ///
+    /// NOTE: Unlike script execution, runtime errors are returned but
+    ///       *not* printed.
+    pub fn call(
+        &mut self,
+        module_name: &str,
+        func_name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, ExeErr> {
+        let module = self.get_module(module_name)?;
+        let func = {
+            let module = module.read().unwrap();
+            let module = module.down_to_mod().unwrap();
+            module.get_global(func_name)
+        };
+        let func = func
+            .ok_or_else(|| RuntimeErr::name_err(format!("{module_name}.{func_name}")));
+        let args = args.into_iter().map(|arg| arg.into_obj_ref()).collect();
+        let result = self.catch_panic(|exe| {
+            func.and_then(|func| exe.vm.call(func, args))
+                .and_then(|_| exe.vm.pop_obj())
+                .and_then(|obj| Value::from_obj_ref(&obj))
+        })?;
+        result.map_err(|err| ExeErr::new(ExeErrKind::RuntimeErr(err.kind)))
+    }
+
+    /// Get the specs of the registered intrinsic functions, grouped by
+    /// the module or type they belong to (e.g., for generating docs).
+    pub fn intrinsic_func_specs(&self) -> Vec<(String, Vec<FuncSpec>)> {
+        let registry = registry::registry();
+        registry
+            .group_names()
+            .into_iter()
+            .map(|group| {
+                let specs = registry.specs(group).into_iter().cloned().collect();
+                (group.to_owned(), specs)
+            })
+            .collect()
+    }
+
+    /// Register a native module whose functions are Rust closures,
+    /// making it importable by scripts. `build` adds the module's
+    /// functions to the builder (see `NativeModuleBuilder`).
+    pub fn register_native_module<F>(&mut self, name: &str, build: F)
+    where
+        F: FnOnce(&mut NativeModuleBuilder),
+    {
+        let mut builder = NativeModuleBuilder::new(name);
+        build(&mut builder);
+        self.add_module(name, builder.build());
+    }
+
     // Parsing ---------------------------------------------------------
 
     /// Parse source text, file, etc into AST module node.
@@ -340,17 +728,34 @@ impl Executor {
     ) -> Result<ast::Module, ExeErr> {
         let scanner = Scanner::new(source);
         let mut parser = Parser::new(scanner);
-        match parser.parse() {
+        let result = parser.parse();
+        let warnings = parser.warnings().to_vec();
+        drop(parser);
+        match result {
             Ok(ast_module) => {
+                for warning in warnings.iter() {
+                    self.handle_parse_warning(warning, source);
+                }
                 self.find_imports(&ast_module);
                 Ok(ast_module)
             }
-            Err(err) => {
+            Err(errors) => {
+                // All errors are reported, but only the first one is
+                // returned. When parsing incrementally (in the REPL),
+                // only the first error is reported since more input
+                // might fix it, in which case the rest are spurious.
+                let num_reported = if self.incremental { 1 } else { errors.len() };
+                for err in errors.iter().take(num_reported) {
+                    if let ParseErrKind::ScanErr(scan_err) = &err.kind {
+                        self.handle_scan_err(scan_err, source);
+                    } else {
+                        self.handle_parse_err(err, source);
+                    }
+                }
+                let err = errors.into_iter().next().unwrap();
                 if let ParseErrKind::ScanErr(scan_err) = err.kind {
-                    self.handle_scan_err(&scan_err, source);
                     Err(ExeErr::new(ExeErrKind::ScanErr(scan_err.kind)))
                 } else {
-                    self.handle_parse_err(&err, source);
                     Err(ExeErr::new(ExeErrKind::ParseErr(err.kind)))
                 }
             }
@@ -367,34 +772,110 @@ impl Executor {
     ) -> Result<Module, ExeErr> {
         let ast_module = self.parse_source(source)?;
         let mut compiler = Compiler::default();
-        let module = compiler
-            .compile_module(name, self.current_file_name.as_str(), ast_module)
-            .map_err(|err| {
-                self.handle_comp_err(&err, source);
-                ExeErr::new(ExeErrKind::CompErr(err.kind))
-            })?;
+        compiler.set_optimize(self.optimize);
+        compiler.set_debug(self.debug);
+        let file_name = self.current_file_name.clone();
+        let comp_result = self.catch_panic(|_| {
+            compiler.compile_module(name, file_name.as_str(), ast_module)
+        })?;
+        let module = comp_result.map_err(|err| {
+            self.handle_comp_err(&err, source);
+            ExeErr::new(ExeErrKind::CompErr(err.kind))
+        })?;
         Ok(module)
     }
 
     // Modules/Imports -------------------------------------------------
 
-    /// Load .fi module from file system and compile it to a `Module`.
+    /// Load .fi module and compile it to a `Module`. std modules are
+    /// loaded from the embedded archive. Other modules are loaded from
+    /// the file system (see `ModuleSearchPath`).
     ///
     /// XXX: This will load the module regardless of whether it has
     ///      already been loaded.
     fn load_module(&mut self, name: &str) -> Result<ObjectRef, ExeErr> {
-        // TODO: Handle non-std modules
-        if let Some(file_data) = STD_FI_MODULES.get(name) {
+        if self.loading.iter().any(|n| n == name) {
+            let mut chain = self.loading.clone();
+            chain.push(name.to_owned());
+            return Err(ExeErr::new(ExeErrKind::CircularImport(chain.join(" -> "))));
+        }
+
+        let current_file_name = self.current_file_name.clone();
+        self.loading.push(name.to_owned());
+        let result = if let Some(file_data) = STD_FI_MODULES.get(name) {
             self.set_current_file_name(Path::new(&format!("<{name}>")));
-            let mut source = source_from_bytes(file_data);
-            let mut module = self.compile_module(name, &mut source)?;
-            self.execute_module(&module, 0, &mut source, false)?;
+            self.load_module_from_source(name, &mut source_from_bytes(file_data))
+        } else {
+            let path = self.search_path.read().unwrap().find(name);
+            match path {
+                Some(path) => self.load_module_from_file(name, &path),
+                None => Err(ExeErr::new(ModuleNotFound(name.to_owned()))),
+            }
+        };
+        self.loading.pop();
+        self.current_file_name = current_file_name;
+        result
+    }
+
+    fn load_module_from_file(
+        &mut self,
+        name: &str,
+        path: &Path,
+    ) -> Result<ObjectRef, ExeErr> {
+        // The file is recorded even if the module fails to load so that
+        // it can be watched for fixes.
+        self.module_files.write().unwrap().push((name.to_owned(), path.to_path_buf()));
+        match source_from_file(path) {
+            Ok(mut source) => {
+                self.set_current_file_name(path);
+                self.load_module_from_source(name, &mut source)
+            }
+            Err(err) => {
+                let message = format!("{}: {err}", path.display());
+                Err(ExeErr::new(ExeErrKind::CouldNotReadSourceFile(message)))
+            }
+        }
+    }
+
+    fn load_module_from_source<T: BufRead>(
+        &mut self,
+        name: &str,
+        source: &mut Source<T>,
+    ) -> Result<ObjectRef, ExeErr> {
+        let mut module = self.compile_module(name, source)?;
+
+        // The module is executed in a fresh context so that only its
+        // own globals are collected. The context of whatever was being
+        // executed when the module was loaded (e.g., the REPL) is
+        // restored afterwards, whether or not the module succeeded, so
+        // globals don't leak between modules in either direction.
+        let outer_ctx = mem::take(&mut self.vm.ctx);
+        let result = self.execute_module(&module, 0, source, false);
+        if result.is_ok() {
             for (name, obj) in self.vm.ctx.globals().iter() {
-                module.add_global(name, obj.clone());
+                module.add_global(name.as_str(), obj.clone());
             }
-            Ok(obj_ref!(module))
-        } else {
-            Err(ExeErr::new(ModuleNotFound(name.to_owned())))
+        }
+        self.vm.reset();
+        self.vm.ctx = outer_ctx;
+
+        result?;
+        Ok(obj_ref!(module))
+    }
+
+    /// Get the paths of the modules that have been loaded from the
+    /// file system, including modules loaded at runtime.
+    pub fn module_files(&self) -> Vec<PathBuf> {
+        let module_files = self.module_files.read().unwrap();
+        module_files.iter().map(|(_, path)| path.clone()).collect()
+    }
+
+    /// Unload the modules that have been loaded from the file system so
+    /// that they'll be loaded again the next time they're imported
+    /// (e.g., after they've been modified).
+    pub fn unload_module_files(&mut self) {
+        for (name, _) in self.module_files.write().unwrap().drain(..) {
+            remove_module(&name);
         }
     }
 
@@ -424,6 +905,26 @@ impl Executor {
         }
     }
 
+    /// Load a module at runtime (e.g., a module imported inside a
+    /// function). A separate executor is used so that the state of the
+    /// VM that triggered the import isn't disturbed. The module is
+    /// registered once loaded, so it's only loaded once.
+    fn resolve_import(
+        name: &str,
+        max_call_depth: CallDepth,
+        debug: bool,
+        search_path: &RwLock<ModuleSearchPath>,
+        module_files: &Arc<RwLock<Vec<(String, PathBuf)>>>,
+    ) -> RuntimeObjResult {
+        let mut executor = Executor::new(max_call_depth, vec![], false, false, debug);
+        *executor.search_path.write().unwrap() = search_path.read().unwrap().clone();
+        executor.module_files = module_files.clone();
+        executor.get_or_add_module(name).map_err(|err| match err.kind {
+            ModuleNotFound(name) => RuntimeErr::module_not_found(name),
+            kind => RuntimeErr::import_err(format!("{name}: {kind}")),
+        })
+    }
+
     /// Find imports at the top level of the specified AST module.
     fn find_imports(&mut self, ast_module: &ast::Module) {
         let mut visitor = ast::visitors::ImportVisitor::new();
@@ -436,19 +937,121 @@ impl Executor {
     }
 
     /// Load modules imported by the current module.
+    ///
+    /// NOTE: Errors that aren't reported elsewhere (e.g., a module that
+    ///       isn't found) are reported only by the outermost call, not
+    ///       when loading the imports of an imported module.
     fn load_imported_modules(&mut self) -> Result<(), ExeErr> {
         while let Some(name) = self.imports.pop_front() {
-            self.get_or_add_module(&name)?;
+            if let Err(err) = self.get_or_add_module(&name) {
+                if self.loading.is_empty() {
+                    self.handle_import_err(&err);
+                }
+                self.imports.clear();
+                return Err(err);
+            }
         }
         Ok(())
     }
 
+    // Bundling --------------------------------------------------------
+
+    /// Bundle the script at `file_path` and the modules it imports
+    /// into a single script that can be run without a module search
+    /// path. Modules are found the same way they would be if the
+    /// script were run (see `ModuleSearchPath`).
+    pub fn bundle_file(&mut self, file_path: &Path) -> Result<String, ExeErr> {
+        self.set_script_file(file_path);
+        let search_path = self.search_path.read().unwrap().clone();
+        bundle::bundle(file_path, &|name| search_path.find(name))
+    }
+
+    // Checking --------------------------------------------------------
+
+    /// Scan, parse, and compile the file at `file_path` without running
+    /// it or loading the modules it imports. Errors are reported the
+    /// same way they are when the file is run.
+    pub fn check_file(&mut self, file_path: &Path) -> Result<(), ExeErr> {
+        let mut source = match source_from_file(file_path) {
+            Ok(source) => source,
+            Err(err) => {
+                let message = format!("{}: {err}", file_path.display());
+                let err = ExeErr::new(ExeErrKind::CouldNotReadSourceFile(message));
+                self.handle_import_err(&err);
+                return Err(err);
+            }
+        };
+        self.set_current_file_name(file_path);
+        let result = self.compile_module("$main", &mut source);
+        self.imports.clear();
+        result.map(|_| ())
+    }
+
     // Error Handling --------------------------------------------------
 
+    /// Run f, converting any panic into an internal error so that bugs
+    /// in the interpreter are reported instead of crashing the host.
+    /// The VM is reset after a panic since its state can't be trusted,
+    /// and locks poisoned by the panic are cleared so the executor
+    /// remains usable.
+    ///
+    /// The default panic message is suppressed while f runs since the
+    /// panic is reported as an internal error instead.
+    fn catch_panic<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> Result<T, ExeErr> {
+        install_panic_hook();
+        CATCHING_PANIC.with(|depth| depth.set(depth.get() + 1));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        CATCHING_PANIC.with(|depth| depth.set(depth.get() - 1));
+        result.map_err(|payload| {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                (*message).to_owned()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "Unknown panic".to_owned()
+            };
+            self.vm.reset();
+            self.clear_poisoned_locks();
+            let err = ExeErr::new(ExeErrKind::InternalErr(message));
+            self.vm.write_output(&format!("{err}\n"), true);
+            err
+        })
+    }
+
+    /// Clear the poison from locks that may have been held when a
+    /// panic occurred. Otherwise, every subsequent access would panic
+    /// too (e.g., when importing after a panic during an import).
+    fn clear_poisoned_locks(&self) {
+        MODULES.clear_poison();
+        STD.clear_poison();
+        let modules = MODULES.read().unwrap();
+        let entries = modules.down_to_map().unwrap().entries();
+        entries.clear_poison();
+        for module in entries.read().unwrap().values() {
+            module.clear_poison();
+        }
+        self.search_path.clear_poison();
+        self.module_files.clear_poison();
+    }
+
     fn print_err_line(&self, line_no: usize, line: &str) {
-        let file_name = self.current_file_name.as_str();
+        self.print_source_line("Error", &self.current_file_name, line_no, line);
+    }
+
+    fn print_source_line(
+        &self,
+        label: &str,
+        file_name: &str,
+        line_no: usize,
+        line: &str,
+    ) {
         let line = line.trim_end();
-        eprintln!("\n  Error in {file_name} on line {line_no}:\n\n    |\n    |{line}");
+        self.vm.write_output(
+            &format!(
+                "\n  {label} in {file_name} on line {line_no}:\n\n    |\n    |{line}\n"
+            ),
+            true,
+        );
     }
 
     fn print_err_message(&self, message: String, start: Location, end: Location) {
@@ -460,7 +1063,16 @@ impl Executor {
                 let end_pos = if end.col == 0 { 0 } else { end.col - start.col };
                 format!("{:>start_pos$}^{:^>end_pos$}", "", "")
             };
-            eprintln!("    |{marker}\n\n  {message}\n");
+            self.vm.write_output(&format!("    |{marker}\n\n  {message}\n\n"), true);
+        }
+    }
+
+    fn handle_import_err(&self, err: &ExeErr) {
+        use ExeErrKind::{CircularImport, CouldNotReadSourceFile};
+        if let ModuleNotFound(_) | CircularImport(_) | CouldNotReadSourceFile(_) =
+            err.kind
+        {
+            self.vm.write_output(&format!("\n  {err}\n\n"), true);
         }
     }
 
@@ -473,6 +1085,8 @@ impl Executor {
                     | ExpectedIndentedBlock(_)
                     | UnmatchedOpeningBracket(_)
                     | UnterminatedStr(_)
+                    | UnterminatedComment
+                    | ExpectedLineContinuation
             );
         if ignore {
             return;
@@ -481,57 +1095,7 @@ impl Executor {
             source.line_no,
             source.get_current_line().unwrap_or("<none>"),
         );
-        let mut loc = err.location;
-        let col = loc.col;
-        let mut message = match &err.kind {
-            UnexpectedChar(c) => {
-                format!("Syntax error: Unexpected character at column {col}: '{c}'")
-            }
-            UnmatchedOpeningBracket(_) => {
-                format!("Unmatched open bracket at {loc}")
-            }
-            UnterminatedStr(_) => {
-                format!("Syntax error: Unterminated string literal at {loc}")
-            }
-            InvalidIndent(num_spaces) => {
-                format!("Syntax error: Invalid indent with {num_spaces} spaces (should be a multiple of 4)")
-            }
-            ExpectedBlock => "Syntax error: Expected block".to_string(),
-            ExpectedIndentedBlock(_) => {
-                "Syntax error: Expected indented block".to_string()
-            }
-            UnexpectedIndent(_) => "Syntax error: Unexpected indent".to_string(),
-            WhitespaceAfterIndent | UnexpectedWhitespace => {
-                "Syntax error: Unexpected whitespace".to_string()
-            }
-            InvalidLabel(msg) => {
-                format!("Syntax error: Invalid label: {msg}")
-            }
-            FormatStrErr(err) => {
-                use crate::format::FormatStrErr::*;
-                match err {
-                    EmptyExpr(pos) => {
-                        loc = Location::new(loc.line, loc.col + pos + 2);
-                        "Syntax error in format string: expected expression".to_string()
-                    }
-                    UnmatchedOpeningBracket(pos) => {
-                        loc = Location::new(loc.line, loc.col + pos + 2);
-                        "Unmatched opening bracket in format string".to_string()
-                    }
-                    UnmatchedClosingBracket(pos) => {
-                        loc = Location::new(loc.line, loc.col + pos + 2);
-                        "Unmatched closing bracket in format string".to_string()
-                    }
-                    ScanErr(pos, _) => {
-                        loc = Location::new(loc.line, loc.col + pos + 2);
-                        "Error while scanning format string".to_string()
-                    }
-                }
-            }
-            kind => {
-                format!("Unhandled scan error at {loc}: {kind:?}")
-            }
-        };
+        let (mut message, loc) = scan_err_message(err);
         if self.debug {
             message = format!("SCAN ERROR: {message}");
         }
@@ -545,61 +1109,25 @@ impl Executor {
         }
         let loc = err.loc();
         self.print_err_line(loc.line, source.get_line(loc.line).unwrap_or("<none>"));
-        let mut message = match &err.kind {
-            ScanErr(_) => {
-                unreachable!("Handle ScanErr before calling handle_parse_err")
-            }
-            UnexpectedToken(TokenWithLocation {
-                token: Token::EndOfStatement, ..
-            }) => {
-                format!("Syntax error at {loc} (unexpected end of statement)")
-            }
-            UnexpectedToken(token) => {
-                format!("Parse error: unexpected token at {loc}: {:?}", token.token)
-            }
-            ExpectedBlock(loc) => {
-                format!("Parse error: expected indented block at {loc}")
-            }
-            ExpectedToken(loc, token) => {
-                format!("Parse error: expected token '{token}' at {loc}")
-            }
-            ExpectedExpr(loc) => {
-                format!("Parse error: expected expression at {loc}")
-            }
-            ExpectedIdent(loc) => {
-                format!("Parse error: expected identifier at {loc}")
-            }
-            UnexpectedImport(loc) => {
-                format!(
-                    "Syntax error: unexpected import at {loc} (imports are only allowed in the global/module scope)"
-                )
-            }
-            UnexpectedBreak(loc) => {
-                format!(
-                    "Parse error: unexpected break at {loc} (break must be in a loop)"
-                )
-            }
-            UnexpectedContinue(loc) => {
-                format!("Parse error: unexpected continue at {loc} (continue must be in a loop)")
-            }
-            UnexpectedReturn(loc) => {
-                format!("Parse error: unexpected return at {loc} (return must be in a function)")
-            }
-            InlineMatchNotAllowed(_) => {
-                "Parse error: match blocks must be indented".to_string()
-            }
-            MatchDefaultMustBeLast(_) => {
-                "Parse error: extra match arm found after default match arm".to_string()
-            }
-            SyntaxErr(loc) => format!("Syntax error at {loc}"),
-            kind => format!("Unhandled parse error: {kind:?}"),
-        };
+        let mut message = parse_err_message(err);
         if self.debug {
             message = format!("PARSE ERROR: {message}");
         }
         self.print_err_message(message, loc, loc);
     }
 
+    fn handle_parse_warning<T: BufRead>(
+        &self,
+        warning: &ParseWarning,
+        source: &Source<T>,
+    ) {
+        let start = warning.start;
+        let line = source.get_line(start.line).unwrap_or("<none>");
+        self.print_source_line("Warning", &self.current_file_name, start.line, line);
+        let message = parse_warning_message(warning);
+        self.print_err_message(message, start, warning.end);
+    }
+
     fn handle_comp_err<T: BufRead>(&self, err: &CompErr, source: &Source<T>) {
         use CompErrKind::*;
         if self.incremental && matches!(&err.kind, LabelNotFoundInScope(..)) {
@@ -610,40 +1138,13 @@ impl Executor {
             start.line,
             source.get_line(start.line).unwrap_or("<none>"),
         );
-        let message = match &err.kind {
-            NameNotFound(name, ..) =>format!("Name not found: {name}"),
-            LabelNotFoundInScope(name, ..) => format!("label not found in scope: {name}"),
-            CannotJumpOutOfFunc(name, ..) => format!(
-                "cannot jump out of function: label {name} not found or defined in outer scope"
-            ),
-            DuplicateLabelInScope(name, ..) => format!("duplicate label in scope: {name}"),
-            ExpectedIdent(..) => {
-                "expected identifier".to_string()
-            },
-            CannotAssignSpecialIdent(name, ..) => {
-                format!("cannot assign to special name: {name}")
-            }
-            CannotReassignSpecialIdent(name, ..) => {
-                format!("cannot reassign special name: {name}")
-            }
-            MainMustBeFunc(..) => {
-                "$main must be a function".to_owned()
-            }
-            GlobalNotFound(name, ..) => {
-                format!("global var not found: {name}")
-            }
-            VarArgsMustBeLast(..) => {
-                "var args must be last in parameter list".to_owned()
-            }
-            Print(msg, ..) => {
-                format!("$print error: {msg}")
-            }
-        };
-        let message = format!("COMPILATION ERROR: {message}");
+        let message = format!("COMPILATION ERROR: {}", comp_err_message(err));
         self.print_err_message(message, start, end);
     }
 
-    fn handle_runtime_err(&self, err: &RuntimeErr) {
+    /// Print a runtime error message. If the error occurred in a
+    /// function, the traceback is printed after the message.
+    fn handle_runtime_err(&self, err: &RuntimeErr, traceback: &[TraceFrame]) {
         use RuntimeErrKind::*;
         let (start, end) = self.vm.loc();
         let mut message = match &err.kind {
@@ -660,15 +1161,33 @@ impl Executor {
                     --max-call-depth option to raise the limit"
                 )
             }
+            ValueStackOverflow(max_size) => {
+                format!("Value stack overflow; the maximum size is {max_size}")
+            }
             NameErr(message) => format!("Name error: {message}"),
             TypeErr(message) => format!("Type error: {message}"),
             NotCallable(type_name) => format!("Object is not callable: {type_name}"),
+            Raised(err_obj) => {
+                format!("Uncaught error: {}", err_obj.read().unwrap())
+            }
+            RuntimeErrKind::ModuleNotFound(name) => format!("Module not found: {name}"),
+            ImportErr(message) => format!("Could not import module: {message}"),
+            UnexpectedState(message) => format!("Unexpected VM state: {message}"),
             kind => format!("Unhandled runtime error: {kind}"),
         };
         if self.debug {
             message = format!("RUNTIME ERROR: {message}");
         }
         self.print_err_message(message, start, end);
+        if traceback.len() > 1 {
+            let mut text = "  Traceback (most recent call last):\n\n".to_owned();
+            for frame in traceback {
+                let TraceFrame { name, module_path, loc } = frame;
+                text.push_str(&format!("    {module_path}:{} in {name}\n", loc.0.line));
+            }
+            text.push('\n');
+            self.vm.write_output(&text, true);
+        }
     }
 
     // Miscellaneous ---------------------------------------------------
@@ -683,3 +1202,203 @@ impl Executor {
         eprintln!("{result:?}");
     }
 }
+
+/// Read line `line_no` (starting at 1) of the source file at `path`.
+fn read_source_line(path: &str, line_no: usize) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    text.lines().nth(line_no.checked_sub(1)?).map(str::to_owned)
+}
+
+// Error Messages ------------------------------------------------------
+
+/// Get the message for a scan error along with the location it refers
+/// to, which is adjusted for errors in format strings.
+pub(crate) fn scan_err_message(err: &ScanErr) -> (String, Location) {
+    use ScanErrKind::*;
+    let mut loc = err.location;
+    let col = loc.col;
+    let message = match &err.kind {
+        UnexpectedChar(c) => {
+            format!("Syntax error: Unexpected character at column {col}: '{c}'")
+        }
+        UnmatchedOpeningBracket(_) => {
+            format!("Unmatched open bracket at {loc}")
+        }
+        ExpectedLineContinuation => {
+            format!("Syntax error: Expected line to be continued at {loc}")
+        }
+        UnterminatedStr(_) => {
+            format!("Syntax error: Unterminated string literal at {loc}")
+        }
+        UnterminatedComment => {
+            format!("Syntax error: Unterminated block comment at {loc}")
+        }
+        InvalidIndent(num_spaces) => {
+            format!("Syntax error: Invalid indent with {num_spaces} spaces (should be a multiple of 4)")
+        }
+        ExpectedBlock => "Syntax error: Expected block".to_string(),
+        ExpectedIndentedBlock(_) => "Syntax error: Expected indented block".to_string(),
+        UnexpectedIndent(_) => "Syntax error: Unexpected indent".to_string(),
+        WhitespaceAfterIndent | UnexpectedWhitespace => {
+            "Syntax error: Unexpected whitespace".to_string()
+        }
+        InvalidLabel(msg) => {
+            format!("Syntax error: Invalid label: {msg}")
+        }
+        LineTooLong(max) => {
+            format!("Syntax error: Line {} is too long (> {max})", loc.line)
+        }
+        FormatStrErr(err) => {
+            use crate::format::FormatStrErr::*;
+            match err {
+                EmptyExpr(pos) => {
+                    loc = Location::new(loc.line, loc.col + pos + 2);
+                    "Syntax error in format string: expected expression".to_string()
+                }
+                UnmatchedOpeningBracket(pos) => {
+                    loc = Location::new(loc.line, loc.col + pos + 2);
+                    "Unmatched opening bracket in format string".to_string()
+                }
+                UnmatchedClosingBracket(pos) => {
+                    loc = Location::new(loc.line, loc.col + pos + 2);
+                    "Unmatched closing bracket in format string".to_string()
+                }
+                ScanErr(pos, _) => {
+                    loc = Location::new(loc.line, loc.col + pos + 2);
+                    "Error while scanning format string".to_string()
+                }
+            }
+        }
+        kind => {
+            format!("Unhandled scan error at {loc}: {kind:?}")
+        }
+    };
+    (message, loc)
+}
+
+/// Get the message for a parse error. Scan errors wrapped in parse
+/// errors have to be handled with `scan_err_message()` instead.
+pub(crate) fn parse_err_message(err: &ParseErr) -> String {
+    use ParseErrKind::*;
+    let loc = err.loc();
+    match &err.kind {
+        ScanErr(_) => {
+            unreachable!("Handle ScanErr before calling parse_err_message")
+        }
+        UnexpectedToken(TokenWithLocation { token: Token::EndOfStatement, .. }) => {
+            format!("Syntax error at {loc} (unexpected end of statement)")
+        }
+        UnexpectedToken(token) => {
+            format!("Parse error: unexpected token at {loc}: {:?}", token.token)
+        }
+        ExpectedBlock(loc) => {
+            format!("Parse error: expected indented block at {loc}")
+        }
+        ExpectedToken(loc, token) => {
+            format!("Parse error: expected token '{token}' at {loc}")
+        }
+        ExpectedExpr(loc) => {
+            format!("Parse error: expected expression at {loc}")
+        }
+        ExpectedIdent(loc) => {
+            format!("Parse error: expected identifier at {loc}")
+        }
+        KeywordAsIdent(loc, name) => {
+            format!("Parse error: cannot use keyword '{name}' as identifier at {loc}")
+        }
+        UnexpectedBreak(loc) => {
+            format!("Parse error: unexpected break at {loc} (break must be in a loop)")
+        }
+        UnexpectedContinue(loc) => {
+            format!("Parse error: unexpected continue at {loc} (continue must be in a loop)")
+        }
+        UnexpectedReturn(loc) => {
+            format!("Parse error: unexpected return at {loc} (return must be in a function)")
+        }
+        UnexpectedGlobal(loc) => {
+            format!("Parse error: unexpected global at {loc} (global must be in a function)")
+        }
+        UnexpectedAwait(loc) => {
+            format!("Parse error: unexpected await at {loc} (await must be in an async function)")
+        }
+        InlineMatchNotAllowed(_) => {
+            "Parse error: match blocks must be indented".to_string()
+        }
+        MatchDefaultMustBeLast(_) => {
+            "Parse error: extra match arm found after default match arm".to_string()
+        }
+        ExpectedCatchOrEnsure(loc) => {
+            format!("Parse error: expected catch or ensure after try block at {loc}")
+        }
+        SyntaxErr(loc) => format!("Syntax error at {loc}"),
+        kind => format!("Unhandled parse error: {kind:?}"),
+    }
+}
+
+pub(crate) fn parse_warning_message(warning: &ParseWarning) -> String {
+    use ParseWarningKind::*;
+    let start = warning.start;
+    match &warning.kind {
+        NonExhaustiveMatch(missing) => {
+            let missing = missing.join(", ");
+            format!(
+                "Warning: match is not exhaustive at {start} (missing: {missing})\n  \
+                Add the missing arms or a default arm (*)"
+            )
+        }
+    }
+}
+
+pub(crate) fn comp_err_message(err: &CompErr) -> String {
+    use CompErrKind::*;
+    match &err.kind {
+        NameNotFound(name, ..) =>format!("Name not found: {name}"),
+        LabelNotFoundInScope(name, ..) => format!("label not found in scope: {name}"),
+        CannotJumpOutOfFunc(name, ..) => format!(
+            "cannot jump out of function: label {name} not found or defined in outer scope"
+        ),
+        DuplicateLabelInScope(name, ..) => format!("duplicate label in scope: {name}"),
+        ExpectedIdent(..) => {
+            "expected identifier".to_string()
+        },
+        CannotAssignSpecialIdent(name, ..) => {
+            format!("cannot assign to special name: {name}")
+        }
+        CannotReassignSpecialIdent(name, ..) => {
+            format!("cannot reassign special name: {name}")
+        }
+        MainMustBeFunc(..) => {
+            "$main must be a function".to_owned()
+        }
+        GlobalNotFound(name, ..) => {
+            format!("global var not found: {name}")
+        }
+        GlobalAfterLocal(name, ..) => {
+            format!("cannot declare local var {name} global")
+        }
+        GlobalNotDeclared(name, ..) => {
+            format!("cannot update var {name} without declaring it global")
+        }
+        VarArgsMustBeLast(..) => {
+            "var args must be last in parameter list".to_owned()
+        }
+        ExpectedTypeMember(..) => {
+            "expected type member definition (name = value)".to_owned()
+        }
+        TypeFieldsCannotHaveVarArgs(..) => {
+            "type fields cannot include var args".to_owned()
+        }
+        ExpectedEnumVariant(..) => {
+            "expected enum variant (a type name like Red)".to_owned()
+        }
+        DuplicateEnumVariant(name, ..) => {
+            format!("duplicate enum variant: {name}")
+        }
+        ThisOutsideMethod(..) => {
+            "this can only be used in methods".to_owned()
+        }
+        Print(msg, ..) => {
+            format!("$print error: {msg}")
+        }
+    }
+}
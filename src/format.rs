@@ -7,6 +7,8 @@ use crate::vm::RuntimeObjResult;
 pub enum FormatStrToken {
     Str(String),
     Expr(Vec<TWL>),
+    /// Expression converted using its repr (`{expr!r}`).
+    ReprExpr(Vec<TWL>),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -78,10 +80,14 @@ pub fn scan_format_string(
                     if expr.is_empty() {
                         return Err(EmptyExpr(open_pos));
                     }
+                    let (expr, is_repr) = split_conversion(expr);
                     let mut source = source_from_text(expr);
                     let scanner = Scanner::new(&mut source);
                     let result: ScanTokensResult = scanner.collect();
                     match result {
+                        Ok(expr_tokens) if is_repr => {
+                            tokens.push(ReprExpr(expr_tokens))
+                        }
                         Ok(expr_tokens) => tokens.push(Expr(expr_tokens)),
                         Err(_) => return Err(ScanErr(open_pos + open_delim_len, pos)),
                     }
@@ -110,6 +116,22 @@ pub fn scan_format_string(
     Ok(tokens)
 }
 
+/// Split the `!r` conversion flag off the end of a format string
+/// expression, returning the expression and whether the flag was
+/// present. The flag must directly follow the expression so that it's
+/// not confused with negation (e.g., `{a == !r}`).
+pub fn split_conversion(expr: &str) -> (&str, bool) {
+    if let Some(stripped) = expr.strip_suffix("!r") {
+        let follows_expr = stripped.chars().last().is_some_and(|c| {
+            c.is_alphanumeric() || matches!(c, '_' | ')' | ']' | '}' | '"' | '\'')
+        });
+        if follows_expr {
+            return (stripped, true);
+        }
+    }
+    (expr, false)
+}
+
 pub fn render_template(
     template_ref: ObjectRef,
     context_ref: ObjectRef,
@@ -142,30 +164,33 @@ pub fn render_template(
     let mut output = String::with_capacity(template.len());
 
     for format_token in format_tokens {
-        match format_token {
+        let (tokens, is_repr) = match format_token {
             FormatStrToken::Str(string) => {
                 output.push_str(string.as_str());
+                continue;
             }
-            FormatStrToken::Expr(tokens) => match &tokens[..] {
-                [TWL { token: Ident(name), .. }, TWL { token: EndOfStatement, .. }] => {
-                    if let Some(val) = context.get(name.as_str()) {
-                        let val = val.read().unwrap();
-                        let val = val.to_string();
-                        output.push_str(val.as_str());
-                    } else {
-                        let msg = format!("Name not found in context: {name}");
-                        return Ok(new::string_err(msg, template_ref.clone()));
-                    }
-                }
-                _ => {
-                    let tokens: Vec<Token> =
-                        tokens.iter().map(|t| &t.token).cloned().collect();
-                    let msg = format!(
-                        "Template is contains an invalid expression: {tokens:?}"
-                    );
+            FormatStrToken::Expr(tokens) => (tokens, false),
+            FormatStrToken::ReprExpr(tokens) => (tokens, true),
+        };
+        match &tokens[..] {
+            [TWL { token: Ident(name), .. }, TWL { token: EndOfStatement, .. }] => {
+                if let Some(val) = context.get(name.as_str()) {
+                    let val = val.read().unwrap();
+                    let val =
+                        if is_repr { format!("{:?}", &*val) } else { val.to_string() };
+                    output.push_str(val.as_str());
+                } else {
+                    let msg = format!("Name not found in context: {name}");
                     return Ok(new::string_err(msg, template_ref.clone()));
                 }
-            },
+            }
+            _ => {
+                let tokens: Vec<Token> =
+                    tokens.iter().map(|t| &t.token).cloned().collect();
+                let msg =
+                    format!("Template is contains an invalid expression: {tokens:?}");
+                return Ok(new::string_err(msg, template_ref.clone()));
+            }
         }
     }
 
@@ -0,0 +1,860 @@
+//! Format source code in a canonical style: 4-space indents, one space
+//! around binary operators, one statement per line, and no more than
+//! two consecutive blank lines at the top level of a module (one in
+//! blocks).
+//!
+//! The source is parsed to an AST, which is then re-emitted. Comments
+//! and type hints aren't part of the AST, so they're collected by the
+//! scanner as trivia and put back based on their locations. Some things
+//! are kept as they're written in the source:
+//!
+//! - Literals (e.g., `0xff` stays `0xff` and `'a'` stays `'a'`)
+//! - Whether blocks are inline (`-> x`) or indented
+//! - Whether the items of collections, call args, and function params
+//!   are on one line or each on their own line
+//!
+//! NOTE: Long lines aren't wrapped.
+use std::collections::{HashMap, VecDeque};
+
+use crate::ast;
+use crate::op::{BinaryOperator, ShortCircuitCompareOperator};
+use crate::parser::{parse_tokens, ParseErrKind};
+use crate::result::{ExeErr, ExeErrKind};
+use crate::scanner::{ScanTokensResult, Scanner, Trivia};
+use crate::source::{source_from_text, Location};
+
+const INDENT: &str = "    ";
+
+/// Format source text. An error is returned if the text can't be
+/// scanned or parsed.
+pub fn format_text(text: &str) -> Result<String, ExeErr> {
+    let mut source = source_from_text(text);
+    let mut scanner = Scanner::new(&mut source);
+    scanner.collect_trivia();
+    let tokens = scanner
+        .by_ref()
+        .collect::<ScanTokensResult>()
+        .map_err(|err| ExeErr::new(ExeErrKind::ScanErr(err.kind)))?;
+    let trivia = scanner.take_trivia();
+    let module = parse_tokens(tokens).map_err(|mut errors| {
+        match errors.swap_remove(0).kind {
+            ParseErrKind::ScanErr(scan_err) => {
+                ExeErr::new(ExeErrKind::ScanErr(scan_err.kind))
+            }
+            kind => ExeErr::new(ExeErrKind::ParseErr(kind)),
+        }
+    })?;
+    Ok(Formatter::new(text, trivia).module(&module))
+}
+
+/// The header of a clause in an `if` or `try` expression.
+enum Header<'a> {
+    Keyword(&'static str),
+    Cond(&'static str, &'a ast::Expr),
+    Catch(Option<&'a ast::Expr>, &'a str),
+}
+
+struct Formatter {
+    lines: Vec<Vec<char>>,
+    // Comments that haven't been emitted yet, in source order.
+    comments: VecDeque<(String, Location)>,
+    // Type hints keyed by the end location of the identifier they
+    // follow.
+    type_hints: HashMap<Location, String>,
+    // Start of the code that follows the statements currently being
+    // formatted, used to determine which comments belong to them.
+    next_start: Option<Location>,
+    // The last source line that's been emitted, used to preserve blank
+    // lines.
+    last_line: usize,
+}
+
+impl Formatter {
+    fn new(text: &str, trivia: Vec<Trivia>) -> Self {
+        let mut comments = VecDeque::new();
+        let mut type_hints = HashMap::new();
+        for item in trivia {
+            match item {
+                Trivia::Comment(comment, loc) => comments.push_back((comment, loc)),
+                Trivia::TypeHint(hint, loc) => {
+                    type_hints.insert(loc, hint);
+                }
+            }
+        }
+        Self {
+            lines: text.lines().map(|line| line.chars().collect()).collect(),
+            comments,
+            type_hints,
+            next_start: None,
+            last_line: 0,
+        }
+    }
+
+    fn module(mut self, module: &ast::Module) -> String {
+        let mut lines = self.statements(&module.statements, 0);
+        self.comments_before(None, 0, 0, &mut lines);
+        if lines.is_empty() {
+            String::new()
+        } else {
+            lines.join("\n") + "\n"
+        }
+    }
+
+    // Statements ------------------------------------------------------
+
+    /// Format statements along with the comments and blank lines around
+    /// them. The returned lines are indented.
+    fn statements(
+        &mut self,
+        statements: &[ast::Statement],
+        indent: usize,
+    ) -> Vec<String> {
+        let pad = INDENT.repeat(indent);
+        let outer_next_start = self.next_start;
+        let mut lines = vec![];
+        for (i, statement) in statements.iter().enumerate() {
+            self.next_start =
+                statements.get(i + 1).map(|s| s.start).or(outer_next_start);
+            self.comments_before(Some(statement.start), 0, indent, &mut lines);
+            self.blank_lines(statement.start.line, indent, &mut lines);
+            self.last_line = statement.start.line;
+            let text = self.statement(statement, indent);
+            // Comments inside the statement that weren't emitted along
+            // with its blocks or items are moved above it.
+            while let Some((comment, _)) = self.comment_if(|loc| loc < statement.end) {
+                lines.push(format!("{pad}{comment}"));
+            }
+            lines.extend(indent_first_line(&pad, &text));
+            if let Some((comment, _)) =
+                self.comment_if(|loc| loc.line == statement.end.line)
+            {
+                let last = lines.last_mut().unwrap();
+                last.push_str("  ");
+                last.push_str(&comment);
+            }
+            self.last_line = self.last_line.max(statement.end.line);
+        }
+        self.next_start = outer_next_start;
+        lines
+    }
+
+    fn statement(&mut self, statement: &ast::Statement, indent: usize) -> String {
+        use ast::StatementKind::*;
+        match &statement.kind {
+            Break(expr) => self.keyword_and_expr("break", expr, statement, indent),
+            Continue => "continue".to_owned(),
+            Global(names) => format!("global {}", names.join(", ")),
+            Import(name, Some(as_name)) => format!("import {name} as {as_name}"),
+            Import(name, None) => format!("import {name}"),
+            ImportFrom(path, names) => {
+                let names: Vec<String> = names
+                    .iter()
+                    .map(|(name, as_name)| match as_name {
+                        Some(as_name) => format!("{name} as {as_name}"),
+                        None => name.clone(),
+                    })
+                    .collect();
+                format!("from {path} import {}", names.join(", "))
+            }
+            Jump(name) => format!("jump {name}"),
+            Label(name, expr) => {
+                self.keyword_and_expr(&format!(":{name}:"), expr, statement, indent)
+            }
+            Return(expr) => self.keyword_and_expr("return", expr, statement, indent),
+            Halt(expr) => format!("$halt {}", self.expr(expr, indent)),
+            Print(expr) => format!("$print {}", self.expr(expr, indent)),
+            Expr(expr) => self.expr(expr, indent),
+        }
+    }
+
+    /// Format `break`, `return`, or a label, which may or may not be
+    /// followed by an expression. When there's no expression, the
+    /// parser adds a `nil` at the start of the statement.
+    fn keyword_and_expr(
+        &mut self,
+        keyword: &str,
+        expr: &ast::Expr,
+        statement: &ast::Statement,
+        indent: usize,
+    ) -> String {
+        let is_implicit = expr.start == statement.start
+            && matches!(
+                expr.kind,
+                ast::ExprKind::Literal(ast::Literal { kind: ast::LiteralKind::Nil })
+            );
+        if is_implicit {
+            keyword.to_owned()
+        } else {
+            format!("{keyword} {}", self.expr(expr, indent))
+        }
+    }
+
+    // Blocks ----------------------------------------------------------
+
+    /// Format a block, including the `->` or `=>` that starts it. If an
+    /// inline block is followed by more code on the same line (e.g.,
+    /// `else`), a statement that ends with a block is parenthesized so
+    /// it doesn't swallow what follows.
+    fn block(
+        &mut self,
+        block: &ast::StatementBlock,
+        arrow: &str,
+        indent: usize,
+        is_followed: bool,
+    ) -> String {
+        if self.is_inline(block) {
+            let statement = &block.statements[0];
+            let text = self.statement(statement, indent);
+            if is_followed && statement.expr().is_some_and(ends_with_block) {
+                format!(" {arrow} ({text})")
+            } else {
+                format!(" {arrow} {text}")
+            }
+        } else {
+            let mut lines = self.statements(&block.statements, indent + 1);
+            let next_start = self.next_start;
+            self.comments_before(
+                next_start,
+                (indent + 1) * 4 + 1,
+                indent + 1,
+                &mut lines,
+            );
+            format!(" {arrow}\n{}", lines.join("\n"))
+        }
+    }
+
+    /// Check whether a block is inline (i.e., its statement is on the
+    /// same line as the `->` or `=>` that starts it).
+    fn is_inline(&self, block: &ast::StatementBlock) -> bool {
+        let start = block.statements[0].start;
+        let before: String = self.line(start.line)[..start.col - 1].iter().collect();
+        let before = before.trim_end();
+        before.ends_with("->") || before.ends_with("=>")
+    }
+
+    /// Get the line of the header (e.g., `else ->`) that a block
+    /// belongs to, which is the last line with code before an indented
+    /// block.
+    fn header_line(&self, block: &ast::StatementBlock) -> usize {
+        let line = block.statements[0].start.line;
+        if self.is_inline(block) {
+            return line;
+        }
+        (1..line)
+            .rev()
+            .find(|&n| {
+                let text: String = self.line(n).iter().collect();
+                let text = text.trim();
+                !text.is_empty() && !text.starts_with('#')
+            })
+            .unwrap_or(1)
+    }
+
+    /// Format clauses (e.g., `if`/`else`), each of which is a header
+    /// followed by a block. The clauses are put on one line if all of
+    /// their blocks are inline. Otherwise, each goes on its own line.
+    fn clauses(
+        &mut self,
+        clauses: &[(Header, &ast::StatementBlock)],
+        indent: usize,
+    ) -> String {
+        let is_one_line = clauses.iter().all(|(_, block)| self.is_inline(block));
+        let outer_next_start = self.next_start;
+        let mut text = String::new();
+        for (i, (header, block)) in clauses.iter().enumerate() {
+            if i > 0 && is_one_line {
+                text.push(' ');
+            } else if i > 0 {
+                text.push('\n');
+                text.push_str(&INDENT.repeat(indent));
+            }
+            let next = clauses.get(i + 1);
+            self.next_start = match next {
+                Some((_, next)) => Some(Location::new(self.header_line(next), 1)),
+                None => outer_next_start,
+            };
+            let header = match header {
+                Header::Keyword(keyword) => keyword.to_string(),
+                Header::Cond(keyword, cond) => {
+                    format!("{keyword} {}", self.operand(cond, 0, false, indent))
+                }
+                Header::Catch(Some(err_type), name) => {
+                    format!("catch {} {name}", self.operand(err_type, 0, false, indent))
+                }
+                Header::Catch(None, name) => format!("catch {name}"),
+            };
+            text.push_str(&header);
+            text.push_str(&self.block(
+                block,
+                "->",
+                indent,
+                is_one_line && next.is_some(),
+            ));
+            if !is_one_line && next.is_some() && self.is_inline(block) {
+                if let Some((comment, _)) =
+                    self.comment_if(|loc| loc.line == block.end.line)
+                {
+                    text.push_str("  ");
+                    text.push_str(&comment);
+                }
+            }
+        }
+        self.next_start = outer_next_start;
+        text
+    }
+
+    /// Format `match`. Arms are always on their own lines.
+    fn match_(
+        &mut self,
+        subject: &ast::Expr,
+        arms: &[ast::MatchArm],
+        default: Option<&ast::StatementBlock>,
+        indent: usize,
+    ) -> String {
+        let subject = self.operand(subject, 0, false, indent);
+        let pad = INDENT.repeat(indent + 1);
+        let outer_next_start = self.next_start;
+        let mut blocks: Vec<&ast::StatementBlock> =
+            arms.iter().map(|arm| &arm.block).collect();
+        blocks.extend(default);
+        let mut lines = vec![];
+        for (i, block) in blocks.iter().enumerate() {
+            let header_line = self.header_line(block);
+            let header_start = Location::new(header_line, 1);
+            self.comments_before(Some(header_start), 0, indent + 1, &mut lines);
+            self.blank_lines(header_line, indent + 1, &mut lines);
+            self.last_line = header_line;
+            self.next_start = match blocks.get(i + 1) {
+                Some(next) => Some(Location::new(self.header_line(next), 1)),
+                None => outer_next_start,
+            };
+            let pattern = match arms.get(i) {
+                Some(arm) => self.arm_pattern(arm, indent + 1),
+                None => "*".to_owned(),
+            };
+            let mut text =
+                format!("{pattern}{}", self.block(block, "->", indent + 1, false));
+            if let Some((comment, _)) =
+                self.comment_if(|loc| loc.line == block.end.line)
+            {
+                text.push_str("  ");
+                text.push_str(&comment);
+            }
+            lines.extend(indent_first_line(&pad, &text));
+            self.last_line = self.last_line.max(block.end.line);
+        }
+        let min_col = (indent + 1) * 4 + 1;
+        self.comments_before(outer_next_start, min_col, indent + 1, &mut lines);
+        self.next_start = outer_next_start;
+        format!("match {subject} ->\n{}", lines.join("\n"))
+    }
+
+    fn arm_pattern(&mut self, arm: &ast::MatchArm, indent: usize) -> String {
+        let pattern = self.pattern(&arm.pattern, indent);
+        match &arm.guard {
+            Some(guard) => {
+                format!("{pattern} if {}", self.operand(guard, 0, false, indent))
+            }
+            None => pattern,
+        }
+    }
+
+    fn pattern(&mut self, pattern: &ast::Pattern, indent: usize) -> String {
+        use ast::Pattern::*;
+        match pattern {
+            Value(expr) => self.operand(expr, 0, false, indent),
+            // Alternatives are parsed as `|` operations, so each one
+            // has to bind more tightly than `|`.
+            Alt(exprs) => exprs
+                .iter()
+                .map(|expr| self.operand(expr, 6, false, indent))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            Binding(name) => name.clone(),
+            Type(type_expr, name) => {
+                format!("{} {name}", self.operand(type_expr, 0, false, indent))
+            }
+            Tuple(patterns) => {
+                let items: Vec<String> = patterns
+                    .iter()
+                    .map(|pattern| self.pattern(pattern, indent))
+                    .collect();
+                let trailing_comma = if items.len() == 1 { "," } else { "" };
+                format!("({}{trailing_comma})", items.join(", "))
+            }
+            Str(prefix, name, suffix) => {
+                let mut parts = vec![];
+                parts.extend(prefix.as_deref().map(quote));
+                parts.push(name.clone());
+                parts.extend(suffix.as_deref().map(quote));
+                parts.join(" + ")
+            }
+        }
+    }
+
+    // Expressions -----------------------------------------------------
+
+    /// Format an expression. `indent` is the indent level of the
+    /// statement containing the expression.
+    fn expr(&mut self, expr: &ast::Expr, indent: usize) -> String {
+        use ast::ExprKind::*;
+        match &expr.kind {
+            // Tuples without parens come from destructuring assignments
+            // (`a, b = b, a`).
+            Tuple(items) if self.char_at(expr.start) != Some('(') => items
+                .iter()
+                .map(|item| self.operand(item, 0, true, indent))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Tuple(items) => {
+                let mut text = self.exprs(items, "()", (expr.start, expr.end), indent);
+                if items.len() == 1 && !text.contains('\n') {
+                    text.insert(text.len() - 1, ',');
+                }
+                text
+            }
+            List(items) => self.exprs(items, "[]", (expr.start, expr.end), indent),
+            Set(items) => self.exprs(items, "{}", (expr.start, expr.end), indent),
+            Map(entries) => self.items(
+                entries.as_slice(),
+                "{}",
+                (expr.start, expr.end),
+                indent,
+                |(key, val)| (key.start, val.end),
+                |f, (key, val), indent| {
+                    let key = f.operand(key, 0, false, indent);
+                    format!("{key}: {}", f.operand(val, 0, true, indent))
+                },
+            ),
+            Literal(literal) => self.literal(literal, expr),
+            FormatString(_) => self.string_text(expr.start),
+            Ident(ident) => match self.type_hints.get(&expr.end) {
+                Some(hint) => format!("{}: {hint}", ident.name()),
+                None => ident.name(),
+            },
+            Block(block) => format!("block{}", self.block(block, "->", indent, false)),
+            DebugBlock(block) => {
+                format!("$debug{}", self.block(block, "->", indent, false))
+            }
+            Conditional(branches, default) => {
+                let mut clauses: Vec<(Header, &ast::StatementBlock)> = branches
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (cond, block))| {
+                        let keyword = if i == 0 { "if" } else { "else if" };
+                        (Header::Cond(keyword, cond), block)
+                    })
+                    .collect();
+                if let Some(default) = default {
+                    clauses.push((Header::Keyword("else"), default));
+                }
+                self.clauses(&clauses, indent)
+            }
+            Match(subject, arms, default) => {
+                self.match_(subject, arms, default.as_ref(), indent)
+            }
+            Loop(cond, block) => {
+                // `loop -> ...` is parsed as `loop true -> ...`.
+                let is_bare = cond.is_true() && {
+                    let rest: String = self.text_from(cond.start);
+                    rest.trim_start().starts_with("->")
+                };
+                let cond = if is_bare {
+                    String::new()
+                } else {
+                    format!(" {}", self.operand(cond, 0, false, indent))
+                };
+                format!("loop{cond}{}", self.block(block, "->", indent, false))
+            }
+            Try(block, catch_clauses, ensure) => {
+                let mut clauses = vec![(Header::Keyword("try"), block)];
+                for (err_type, name, block) in catch_clauses {
+                    clauses.push((Header::Catch(err_type.as_ref(), name), block));
+                }
+                if let Some(ensure) = ensure {
+                    clauses.push((Header::Keyword("ensure"), ensure));
+                }
+                self.clauses(&clauses, indent)
+            }
+            Func(func) => self.func(func, expr, indent),
+            Call(call) => {
+                let callable = self.operand(&call.callable, 13, false, indent);
+                let end = call.callable.end;
+                let open = Location::new(end.line, end.col + 1);
+                let args = self.exprs(&call.args, "()", (open, expr.end), indent);
+                format!("{callable}{args}")
+            }
+            Await(a) => format!("await {}", self.operand(a, 11, true, indent)),
+            Subscript(obj, key) => {
+                let obj = self.operand(obj, 13, false, indent);
+                format!("{obj}[{}]", self.expr(key, indent))
+            }
+            DeclarationAndAssignment(a, b) | Assignment(a, b) => {
+                self.binary_op(a, "=", b, (2, 1), indent)
+            }
+            UnaryOp(op, a) => {
+                // Nested unary ops are parenthesized so that, e.g.,
+                // `!(!a)` isn't formatted as `!!a`.
+                let min_prec = if matches!(a.kind, UnaryOp(..)) { 12 } else { 11 };
+                format!("{op}{}", self.operand(a, min_prec, true, indent))
+            }
+            BinaryOp(a, op, b) => {
+                use BinaryOperator::*;
+                let precs = match op {
+                    Pow => (11, 10),
+                    Mul | Div | FloorDiv | Mod => (9, 10),
+                    Add | Sub => (8, 9),
+                    Range | RangeInclusive => (7, 8),
+                    BitAnd => (6, 7),
+                    BitOr => (5, 6),
+                    Dot => (13, 14),
+                };
+                self.binary_op(a, &op.to_string(), b, precs, indent)
+            }
+            CompareOp(a, op, b) => {
+                self.binary_op(a, &op.to_string(), b, (4, 5), indent)
+            }
+            ShortCircuitCompareOp(a, op, b) => {
+                use ShortCircuitCompareOperator::*;
+                let precs = match op {
+                    And => (3, 4),
+                    Or | NilOr => (2, 3),
+                    NilSafeDot => (13, 14),
+                };
+                self.binary_op(a, &op.to_string(), b, precs, indent)
+            }
+            InplaceOp(a, op, b) => {
+                self.binary_op(a, &op.to_string(), b, (2, 2), indent)
+            }
+        }
+    }
+
+    /// Format an operand, parenthesizing it if its precedence is lower
+    /// than `min_prec` or if it ends with a block and isn't the last
+    /// part of the enclosing expression (since the block would swallow
+    /// whatever follows it).
+    fn operand(
+        &mut self,
+        expr: &ast::Expr,
+        min_prec: u8,
+        is_last: bool,
+        indent: usize,
+    ) -> String {
+        let text = self.expr(expr, indent);
+        if precedence(expr) < min_prec || (!is_last && ends_with_block(expr)) {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    /// Format a binary operation. Operators are surrounded by spaces
+    /// except for attribute access and ranges.
+    fn binary_op(
+        &mut self,
+        a: &ast::Expr,
+        op: &str,
+        b: &ast::Expr,
+        (left_prec, right_prec): (u8, u8),
+        indent: usize,
+    ) -> String {
+        let a = self.operand(a, left_prec, false, indent);
+        let b = self.operand(b, right_prec, true, indent);
+        if matches!(op, "." | "?." | ".." | "..=") {
+            format!("{a}{op}{b}")
+        } else {
+            format!("{a} {op} {b}")
+        }
+    }
+
+    fn exprs(
+        &mut self,
+        items: &[ast::Expr],
+        brackets: &str,
+        span: (Location, Location),
+        indent: usize,
+    ) -> String {
+        self.items(
+            items,
+            brackets,
+            span,
+            indent,
+            |item| (item.start, item.end),
+            |f, item, indent| f.operand(item, 0, true, indent),
+        )
+    }
+
+    /// Format the items of a collection or the args of a call. `span` is
+    /// the location of the opening and closing brackets. If the first
+    /// item is on a later line than the opening bracket in the source,
+    /// each item is put on its own line and followed by a comma.
+    /// Otherwise, all of the items are put on one line.
+    fn items<T>(
+        &mut self,
+        items: &[T],
+        brackets: &str,
+        (open, close): (Location, Location),
+        indent: usize,
+        item_span: impl Fn(&T) -> (Location, Location),
+        format_item: impl Fn(&mut Self, &T, usize) -> String,
+    ) -> String {
+        let (open_bracket, close_bracket) = brackets.split_at(1);
+        let is_multi_line =
+            items.first().is_some_and(|item| item_span(item).0.line > open.line);
+        if !is_multi_line {
+            let items: Vec<String> =
+                items.iter().map(|item| format_item(self, item, indent)).collect();
+            return format!("{open_bracket}{}{close_bracket}", items.join(", "));
+        }
+        let pad = INDENT.repeat(indent + 1);
+        let mut lines = vec![open_bracket.to_owned()];
+        for item in items {
+            let (start, end) = item_span(item);
+            while let Some((comment, _)) = self.comment_if(|loc| loc < start) {
+                lines.push(format!("{pad}{comment}"));
+            }
+            let mut line = format!("{pad}{},", format_item(self, item, indent + 1));
+            if let Some((comment, _)) = self.comment_if(|loc| loc.line == end.line) {
+                line.push_str("  ");
+                line.push_str(&comment);
+            }
+            lines.push(line);
+        }
+        while let Some((comment, _)) = self.comment_if(|loc| loc < close) {
+            lines.push(format!("{pad}{comment}"));
+        }
+        lines.push(format!("{}{close_bracket}", INDENT.repeat(indent)));
+        lines.join("\n")
+    }
+
+    fn func(&mut self, func: &ast::Func, expr: &ast::Expr, indent: usize) -> String {
+        // Params aren't expressions, so their type hints are found by
+        // location instead.
+        let body_start = func.block.statements[0].start;
+        let hints: HashMap<String, &String> = self
+            .type_hints
+            .iter()
+            .filter(|(loc, _)| **loc > expr.start && **loc < body_start)
+            .map(|(loc, hint)| (self.ident_ending_at(*loc), hint))
+            .collect();
+        let params: Vec<String> = func
+            .params
+            .iter()
+            .map(|name| match hints.get(name) {
+                _ if name.is_empty() => "...".to_owned(),
+                Some(hint) => format!("{name}: {hint}"),
+                None => name.clone(),
+            })
+            .collect();
+        // Params are put on their own lines if nothing follows the
+        // opening paren on its line in the source.
+        let line = self.line(expr.start.line);
+        let is_multi_line = !params.is_empty()
+            && line[expr.start.col - 1..].iter().position(|&c| c == '(').is_some_and(
+                |i| {
+                    let rest: String = line[expr.start.col + i..].iter().collect();
+                    let rest = rest.trim();
+                    rest.is_empty() || rest.starts_with('#')
+                },
+            );
+        let params = if is_multi_line {
+            let pad = INDENT.repeat(indent + 1);
+            let params: String =
+                params.iter().map(|param| format!("{pad}{param},\n")).collect();
+            format!("(\n{params}{})", INDENT.repeat(indent))
+        } else {
+            format!("({})", params.join(", "))
+        };
+        let prefix = if func.is_async { "async " } else { "" };
+        format!("{prefix}{params}{}", self.block(&func.block, "=>", indent, false))
+    }
+
+    /// Format a literal. Numbers and strings are copied from the source
+    /// as written.
+    fn literal(&self, literal: &ast::Literal, expr: &ast::Expr) -> String {
+        use ast::LiteralKind;
+        match &literal.kind {
+            LiteralKind::Nil => "nil".to_owned(),
+            LiteralKind::Bool(value) => value.to_string(),
+            LiteralKind::Always => "@".to_owned(),
+            LiteralKind::Ellipsis => "...".to_owned(),
+            LiteralKind::Int(_) | LiteralKind::Float(_) => {
+                let (start, end) = (expr.start, expr.end);
+                self.line(start.line)[start.col - 1..end.col].iter().collect()
+            }
+            LiteralKind::String(_) => self.string_text(expr.start),
+        }
+    }
+
+    // Comments & Blank Lines ------------------------------------------
+
+    /// Take the next comment if its location satisfies the condition.
+    fn comment_if(
+        &mut self,
+        condition: impl FnOnce(Location) -> bool,
+    ) -> Option<(String, Location)> {
+        match self.comments.front() {
+            Some((_, loc)) if condition(*loc) => self.comments.pop_front(),
+            _ => None,
+        }
+    }
+
+    /// Emit the comments that start at or after `min_col` and come
+    /// before `loc` (or all of them if `loc` is `None`) along with the
+    /// blank lines before them.
+    fn comments_before(
+        &mut self,
+        loc: Option<Location>,
+        min_col: usize,
+        indent: usize,
+        lines: &mut Vec<String>,
+    ) {
+        let pad = INDENT.repeat(indent);
+        let is_before = |comment_loc: Location| match loc {
+            Some(loc) => comment_loc < loc,
+            None => true,
+        };
+        while let Some((comment, comment_loc)) =
+            self.comment_if(|c| c.col >= min_col && is_before(c))
+        {
+            self.blank_lines(comment_loc.line, indent, lines);
+            lines.push(format!("{pad}{comment}"));
+            self.last_line = comment_loc.line + comment.matches('\n').count();
+        }
+    }
+
+    /// Emit the blank lines between the last line that was emitted and
+    /// `line`, up to two at the top level of a module and one in
+    /// blocks. Blank lines at the start of a block are dropped.
+    fn blank_lines(&mut self, line: usize, indent: usize, lines: &mut Vec<String>) {
+        if lines.is_empty() {
+            return;
+        }
+        let max = if indent == 0 { 2 } else { 1 };
+        let count = (self.last_line + 1..line)
+            .filter(|&n| self.line(n).iter().all(|c| c.is_whitespace()))
+            .count();
+        lines.extend(vec![String::new(); count.min(max)]);
+    }
+
+    // Source ----------------------------------------------------------
+
+    /// Get source line `n` (1-based).
+    fn line(&self, n: usize) -> &[char] {
+        self.lines.get(n.wrapping_sub(1)).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    fn char_at(&self, loc: Location) -> Option<char> {
+        self.line(loc.line).get(loc.col.wrapping_sub(1)).copied()
+    }
+
+    /// Get the source text from `loc` to the end of its line.
+    fn text_from(&self, loc: Location) -> String {
+        let line = self.line(loc.line);
+        line.get(loc.col.saturating_sub(1)..).unwrap_or_default().iter().collect()
+    }
+
+    /// Get the identifier that ends at `loc`.
+    fn ident_ending_at(&self, loc: Location) -> String {
+        let line = self.line(loc.line);
+        let end = loc.col.min(line.len());
+        let start = line[..end]
+            .iter()
+            .rposition(|&c| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .map_or(0, |i| i + 1);
+        line[start..end].iter().collect()
+    }
+
+    /// Get the source text of the string or format string that starts
+    /// at `start`, which may span multiple lines.
+    fn string_text(&self, start: Location) -> String {
+        let mut text = String::new();
+        let (mut line, mut col) = (start.line, start.col - 1);
+        let mut quote = None;
+        let mut is_escaped = false;
+        while line <= self.lines.len() {
+            let Some(&c) = self.line(line).get(col) else {
+                text.push('\n');
+                line += 1;
+                col = 0;
+                is_escaped = false;
+                continue;
+            };
+            text.push(c);
+            col += 1;
+            match quote {
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None => (),
+                Some(_) if is_escaped => is_escaped = false,
+                Some(_) if c == '\\' => is_escaped = true,
+                Some(q) if c == q => break,
+                Some(_) => (),
+            }
+        }
+        text
+    }
+}
+
+// Utilities -----------------------------------------------------------
+
+/// Get the precedence of an expression, which is the precedence of its
+/// operator for operations. Expressions that aren't operations have
+/// the highest precedence.
+fn precedence(expr: &ast::Expr) -> u8 {
+    use ast::ExprKind::*;
+    match &expr.kind {
+        DeclarationAndAssignment(..) | Assignment(..) | InplaceOp(..) => 1,
+        ShortCircuitCompareOp(_, op, _) => match op {
+            ShortCircuitCompareOperator::Or | ShortCircuitCompareOperator::NilOr => 2,
+            ShortCircuitCompareOperator::And => 3,
+            ShortCircuitCompareOperator::NilSafeDot => 13,
+        },
+        CompareOp(..) => 4,
+        BinaryOp(_, op, _) => match op {
+            BinaryOperator::BitOr => 5,
+            BinaryOperator::BitAnd => 6,
+            BinaryOperator::Range | BinaryOperator::RangeInclusive => 7,
+            BinaryOperator::Add | BinaryOperator::Sub => 8,
+            BinaryOperator::Mul
+            | BinaryOperator::Div
+            | BinaryOperator::FloorDiv
+            | BinaryOperator::Mod => 9,
+            BinaryOperator::Pow => 10,
+            BinaryOperator::Dot => 13,
+        },
+        UnaryOp(..) | Await(..) => 11,
+        Call(_) | Subscript(..) => 13,
+        _ => 14,
+    }
+}
+
+/// Check whether an expression ends with a block (e.g., `x = () => 1`).
+fn ends_with_block(expr: &ast::Expr) -> bool {
+    use ast::ExprKind::*;
+    match &expr.kind {
+        Block(_) | DebugBlock(_) | Conditional(..) | Match(..) | Loop(..) | Try(..)
+        | Func(_) => true,
+        UnaryOp(_, a) | Await(a) => ends_with_block(a),
+        BinaryOp(_, BinaryOperator::Dot, _)
+        | ShortCircuitCompareOp(_, ShortCircuitCompareOperator::NilSafeDot, _) => false,
+        BinaryOp(_, _, b)
+        | CompareOp(_, _, b)
+        | ShortCircuitCompareOp(_, _, b)
+        | InplaceOp(_, _, b)
+        | DeclarationAndAssignment(_, b)
+        | Assignment(_, b) => ends_with_block(b),
+        _ => false,
+    }
+}
+
+/// Split text into lines and indent the first one. The other lines are
+/// either already indented or are inside multi-line strings.
+fn indent_first_line(pad: &str, text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = text.split('\n').map(str::to_owned).collect();
+    lines[0].insert_str(0, pad);
+    lines
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -4,16 +4,28 @@
 #[macro_use]
 extern crate bitflags;
 
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod cli;
+#[cfg(feature = "dis")]
 pub mod dis;
 pub mod exe;
+pub mod formatter;
+pub mod lsp;
+pub mod native;
 pub mod op;
+#[cfg(feature = "repl")]
 pub mod repl;
 pub mod result;
 pub mod source;
+pub mod value;
+pub mod version;
 pub mod vm;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 mod ast;
+mod bundle;
 mod compiler;
 mod format;
 mod modules;
@@ -0,0 +1,316 @@
+//! Just enough JSON for the language server's JSON-RPC messages.
+//! Numbers are stored as f64, which is fine for the ids, line numbers,
+//! and columns used by the protocol.
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use indexmap::IndexMap;
+
+static NULL: Json = Json::Null;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(IndexMap<String, Json>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser { chars: text.chars().peekable() };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            Some(c) => Err(format!("Unexpected character after JSON value: {c:?}")),
+            None => Ok(value),
+        }
+    }
+
+    /// Make an object from key/value pairs.
+    pub fn object<const N: usize>(entries: [(&str, Json); N]) -> Self {
+        Self::Object(entries.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+    }
+
+    /// Get the value for key if this is an object. null is returned if
+    /// this isn't an object or the key isn't present, so lookups can
+    /// be chained (e.g., `params.get("textDocument").get("uri")`).
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Self::Object(entries) => entries.get(key).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Self::Num(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(val: bool) -> Self {
+        Self::Bool(val)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(val: usize) -> Self {
+        Self::Num(val as f64)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(val: i64) -> Self {
+        Self::Num(val as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(val: &str) -> Self {
+        Self::Str(val.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(val: String) -> Self {
+        Self::Str(val)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(val: Vec<Json>) -> Self {
+        Self::Array(val)
+    }
+}
+
+/// Format as compact JSON.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(val) => write!(f, "{val}"),
+            // Integral numbers are written without a fraction.
+            Self::Num(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                write!(f, "{}", *n as i64)
+            }
+            Self::Num(n) if n.is_finite() => write!(f, "{n}"),
+            Self::Num(_) => write!(f, "null"),
+            Self::Str(string) => write_str(f, string),
+            Self::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(entries) => {
+                f.write_char('{')?;
+                for (i, (key, val)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_str(f, key)?;
+                    write!(f, ":{val}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_str(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in string.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+// Parsing -------------------------------------------------------------
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::Str(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('n') => self.literal("null", Json::Null),
+            Some(c) => Err(format!("Unexpected character {c:?}")),
+            None => Err("Unexpected end of input".to_owned()),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.chars.next(); // {
+        let mut entries = IndexMap::new();
+        self.skip_whitespace();
+        if self.next_if('}') {
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err("Expected object key".to_owned());
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.next_if(':') {
+                return Err("Expected ':'".to_owned());
+            }
+            entries.insert(key, self.value()?);
+            self.skip_whitespace();
+            if self.next_if('}') {
+                return Ok(Json::Object(entries));
+            } else if !self.next_if(',') {
+                return Err("Expected ',' or '}'".to_owned());
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.chars.next(); // [
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.next_if(']') {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            if self.next_if(']') {
+                return Ok(Json::Array(items));
+            } else if !self.next_if(',') {
+                return Err("Expected ',' or ']'".to_owned());
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.chars.next(); // "
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('/') => string.push('/'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('u') => string.push(self.unicode_escape()?),
+                    _ => return Err("Invalid escape sequence".to_owned()),
+                },
+                Some(c) => string.push(c),
+                None => return Err("Unterminated string".to_owned()),
+            }
+        }
+    }
+
+    /// Decode the hex digits of a `\u` escape, including the low half
+    /// of a surrogate pair.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let code = self.hex_digits()?;
+        let code = if (0xd800..0xdc00).contains(&code) {
+            if !(self.next_if('\\') && self.next_if('u')) {
+                return Err("Expected low surrogate".to_owned());
+            }
+            let low = self.hex_digits()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err("Invalid low surrogate".to_owned());
+            }
+            0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            code
+        };
+        char::from_u32(code).ok_or_else(|| "Invalid unicode escape".to_owned())
+    }
+
+    fn hex_digits(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.next().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err("Expected hex digit".to_owned()),
+            }
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let mut text = String::new();
+        while let Some(c @ ('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) =
+            self.chars.peek().copied()
+        {
+            self.chars.next();
+            text.push(c);
+        }
+        text.parse::<f64>()
+            .map(Json::Num)
+            .map_err(|_| format!("Invalid number: {text}"))
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for c in word.chars() {
+            if !self.next_if(c) {
+                return Err(format!("Expected {word}"));
+            }
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.chars.next();
+        }
+    }
+
+    fn next_if(&mut self, c: char) -> bool {
+        self.chars.next_if_eq(&c).is_some()
+    }
+}
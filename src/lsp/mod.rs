@@ -0,0 +1,7 @@
+pub use server::serve;
+
+mod json;
+mod server;
+
+#[cfg(test)]
+pub(crate) use json::Json;
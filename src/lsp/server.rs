@@ -0,0 +1,417 @@
+//! Language server for FeInt scripts, which speaks the Language Server
+//! Protocol (LSP) over stdin and stdout. It supports:
+//!
+//! - Diagnostics for scan, parse, and compilation errors, which are
+//!   published when a document is opened or changed
+//! - Go to definition for module-level names
+//! - Completion of globals and builtins
+//!
+//! Documents are synced in full on every change. They're checked the
+//! same way `feint check` checks files: they're compiled without being
+//! run or loading the modules they import.
+//!
+//! NOTE: Definitions are found by name, so a local that shadows a
+//!       global resolves to the global.
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::compiler::{Compiler, Var};
+use crate::exe::{
+    comp_err_message, parse_err_message, parse_warning_message, scan_err_message,
+};
+use crate::modules::std::STD;
+use crate::parser::{ParseErrKind, Parser};
+use crate::scanner::Scanner;
+use crate::source::{source_from_text, Location};
+
+use super::json::Json;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const TEXT_DOCUMENT_SYNC_FULL: usize = 1;
+const SEVERITY_ERROR: usize = 1;
+const SEVERITY_WARNING: usize = 2;
+const COMPLETION_ITEM_KIND_VARIABLE: usize = 6;
+
+/// Serve requests read from reader, writing responses and diagnostics
+/// to writer, until the client sends `exit` or closes the connection.
+/// The returned exit code is 0 if the client sent `shutdown` first and
+/// 1 otherwise.
+///
+/// The builtins defined in std.fi are only known after bootstrapping
+/// (see `Executor::bootstrap()`), so that should be done first.
+pub fn serve<R: BufRead, W: Write>(mut reader: R, writer: W) -> io::Result<u8> {
+    let mut server = Server::new(writer);
+    while !server.exit {
+        let message = match read_message(&mut reader)? {
+            Some(message) => message,
+            None => break,
+        };
+        match Json::parse(&message) {
+            Ok(message) => server.handle(&message)?,
+            Err(err) => server.send_error(&Json::Null, PARSE_ERROR, &err)?,
+        }
+    }
+    Ok(if server.shutdown { 0 } else { 1 })
+}
+
+/// Read the content of the next message. None is returned when the
+/// client closes the connection.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, val)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = val.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Expected Content-Length header",
+        ));
+    };
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[derive(Default)]
+struct Document {
+    text: String,
+    // Globals as of the last time the document compiled, so they can
+    // still be found while errors are being fixed.
+    globals: Vec<Var>,
+}
+
+struct Server<W: Write> {
+    writer: W,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(writer: W) -> Self {
+        Self { writer, documents: HashMap::new(), shutdown: false, exit: false }
+    }
+
+    fn handle(&mut self, message: &Json) -> io::Result<()> {
+        let id = message.get("id");
+        let params = message.get("params");
+        let method = match message.get("method").as_str() {
+            Some(method) => method,
+            // The server doesn't send requests, so it doesn't expect
+            // any responses.
+            None => return Ok(()),
+        };
+
+        // Notifications don't have an ID and don't get a response.
+        if id.is_null() {
+            match method {
+                "exit" => self.exit = true,
+                "textDocument/didOpen" => {
+                    let document = params.get("textDocument");
+                    let uri = document.get("uri").as_str();
+                    let text = document.get("text").as_str();
+                    if let (Some(uri), Some(text)) = (uri, text) {
+                        self.update(uri, text)?;
+                    }
+                }
+                "textDocument/didChange" => {
+                    // Since changes are synced in full, the last change
+                    // has all of the text.
+                    let uri = params.get("textDocument").get("uri").as_str();
+                    let text = params
+                        .get("contentChanges")
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change.get("text").as_str());
+                    if let (Some(uri), Some(text)) = (uri, text) {
+                        self.update(uri, text)?;
+                    }
+                }
+                "textDocument/didClose" => {
+                    if let Some(uri) = params.get("textDocument").get("uri").as_str() {
+                        self.documents.remove(uri);
+                        self.publish_diagnostics(uri, vec![])?;
+                    }
+                }
+                _ => (),
+            }
+            return Ok(());
+        }
+
+        if self.shutdown {
+            return self.send_error(id, INVALID_REQUEST, "Server is shut down");
+        }
+
+        let result = match method {
+            "initialize" => initialize_result(),
+            "shutdown" => {
+                self.shutdown = true;
+                Json::Null
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                let message = format!("Method not found: {method}");
+                return self.send_error(id, METHOD_NOT_FOUND, &message);
+            }
+        };
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.clone()),
+            ("result", result),
+        ]))
+    }
+
+    /// Update the text of the document at uri, then check it and
+    /// publish its diagnostics.
+    fn update(&mut self, uri: &str, text: &str) -> io::Result<()> {
+        let (diagnostics, globals) = check(text);
+        let document = self.documents.entry(uri.to_owned()).or_default();
+        document.text = text.to_owned();
+        if let Some(globals) = globals {
+            document.globals = globals;
+        }
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    /// Find where the global at the requested position is declared.
+    fn definition(&self, params: &Json) -> Json {
+        let uri = params.get("textDocument").get("uri");
+        let document = match uri.as_str().and_then(|uri| self.documents.get(uri)) {
+            Some(document) => document,
+            None => return Json::Null,
+        };
+        let name = match ident_at(&document.text, params.get("position")) {
+            Some(name) => name,
+            None => return Json::Null,
+        };
+        match document.globals.iter().find(|var| var.name == name) {
+            Some(var) => {
+                let lines: Vec<&str> = document.text.lines().collect();
+                Json::object([
+                    ("uri", uri.clone()),
+                    ("range", range(&lines, var.start, var.start)),
+                ])
+            }
+            None => Json::Null,
+        }
+    }
+
+    /// Complete the globals of the requested document and builtins.
+    /// Clients filter completions by what's been typed, so all of them
+    /// are returned.
+    fn completion(&self, params: &Json) -> Json {
+        let mut names = HashSet::new();
+        let mut items = vec![];
+        let uri = params.get("textDocument").get("uri").as_str();
+        if let Some(document) = uri.and_then(|uri| self.documents.get(uri)) {
+            for var in document.globals.iter() {
+                names.insert(var.name.as_str());
+                items.push(completion_item(&var.name, "global"));
+            }
+        }
+        let builtins = STD.read().unwrap();
+        for (name, _) in builtins.iter_globals() {
+            if !names.contains(name.as_str()) {
+                items.push(completion_item(name, "builtin"));
+            }
+        }
+        items.into()
+    }
+
+    fn publish_diagnostics(
+        &mut self,
+        uri: &str,
+        diagnostics: Vec<Json>,
+    ) -> io::Result<()> {
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Json::object([
+                    ("uri", uri.into()),
+                    ("diagnostics", diagnostics.into()),
+                ]),
+            ),
+        ]))
+    }
+
+    fn send_error(&mut self, id: &Json, code: i64, message: &str) -> io::Result<()> {
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.clone()),
+            (
+                "error",
+                Json::object([("code", code.into()), ("message", message.into())]),
+            ),
+        ]))
+    }
+
+    fn send(&mut self, message: Json) -> io::Result<()> {
+        let content = message.to_string();
+        write!(self.writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+        self.writer.flush()
+    }
+}
+
+fn initialize_result() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", TEXT_DOCUMENT_SYNC_FULL.into()),
+                ("definitionProvider", true.into()),
+                ("completionProvider", Json::object([])),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "feint".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn completion_item(name: &str, detail: &str) -> Json {
+    Json::object([
+        ("label", name.into()),
+        ("kind", COMPLETION_ITEM_KIND_VARIABLE.into()),
+        ("detail", detail.into()),
+    ])
+}
+
+/// Scan, parse, and compile text, returning diagnostics for the errors
+/// and warnings found. If the text compiles, its globals are returned
+/// too.
+fn check(text: &str) -> (Vec<Json>, Option<Vec<Var>>) {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut diagnostics = vec![];
+
+    let mut source = source_from_text(text);
+    let mut parser = Parser::new(Scanner::new(&mut source));
+    let result = parser.parse();
+    for warning in parser.warnings() {
+        diagnostics.push(diagnostic(
+            &lines,
+            SEVERITY_WARNING,
+            parse_warning_message(warning),
+            warning.start,
+            warning.end,
+        ));
+    }
+    let module = match result {
+        Ok(module) => module,
+        Err(errors) => {
+            for err in errors.iter() {
+                let (message, loc) = match &err.kind {
+                    ParseErrKind::ScanErr(scan_err) => scan_err_message(scan_err),
+                    _ => (parse_err_message(err), err.loc()),
+                };
+                diagnostics.push(diagnostic(&lines, SEVERITY_ERROR, message, loc, loc));
+            }
+            return (diagnostics, None);
+        }
+    };
+
+    // A panic in the compiler is a bug, but it shouldn't take down the
+    // server. The panic message is still printed to stderr.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        Compiler::default().compile_module_with_scope_tree("$main", module)
+    }));
+    match result {
+        Ok(Ok((_, scope_tree))) => {
+            // Temp vars (e.g., `$match`) aren't declared in the source.
+            let globals = scope_tree
+                .global_vars()
+                .iter()
+                .filter(|var| var.start != Location::default())
+                .cloned()
+                .collect();
+            return (diagnostics, Some(globals));
+        }
+        Ok(Err(err)) => {
+            let (start, end) = err.loc();
+            let message = comp_err_message(&err);
+            diagnostics.push(diagnostic(&lines, SEVERITY_ERROR, message, start, end));
+        }
+        Err(_) => (),
+    }
+    (diagnostics, None)
+}
+
+fn diagnostic(
+    lines: &[&str],
+    severity: usize,
+    message: String,
+    start: Location,
+    end: Location,
+) -> Json {
+    Json::object([
+        ("range", range(lines, start, end)),
+        ("severity", severity.into()),
+        ("source", "feint".into()),
+        ("message", message.into()),
+    ])
+}
+
+/// Convert the span from start to end, inclusive, to an LSP range.
+/// LSP positions are 0-based with columns in UTF-16 code units whereas
+/// locations are 1-based with columns in chars.
+fn range(lines: &[&str], start: Location, end: Location) -> Json {
+    Json::object([
+        ("start", position(lines, start.line, start.col.saturating_sub(1))),
+        ("end", position(lines, end.line, end.col)),
+    ])
+}
+
+/// Make an LSP position from a 1-based line number and the index of a
+/// char in that line.
+fn position(lines: &[&str], line_no: usize, index: usize) -> Json {
+    let line_index = line_no.saturating_sub(1);
+    let line = lines.get(line_index).copied().unwrap_or("");
+    let character: usize = line.chars().take(index).map(char::len_utf16).sum();
+    Json::object([("line", line_index.into()), ("character", character.into())])
+}
+
+/// Get the identifier at an LSP position in text, if there is one.
+fn ident_at(text: &str, position: &Json) -> Option<String> {
+    let line = text.lines().nth(position.get("line").as_usize()?)?;
+    let character = position.get("character").as_usize()?;
+    let chars: Vec<char> = line.chars().collect();
+    let mut index = 0;
+    let mut offset = 0;
+    while index < chars.len() && offset < character {
+        offset += chars[index].len_utf16();
+        index += 1;
+    }
+    let is_ident_char = |c: &&char| c.is_alphanumeric() || **c == '_' || **c == '$';
+    let start = index - chars[..index].iter().rev().take_while(is_ident_char).count();
+    let end = index + chars[index..].iter().take_while(is_ident_char).count();
+    if start == end {
+        None
+    } else {
+        Some(chars[start..end].iter().collect())
+    }
+}
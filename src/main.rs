@@ -1,24 +1,37 @@
 use std::ffi::OsStr;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
 use clap::{parser::ValueSource, ArgMatches};
 
 use feint::cli;
 use feint::exe::Executor;
+use feint::formatter::format_text;
+use feint::lsp;
+#[cfg(feature = "repl")]
 use feint::repl::Repl;
 use feint::result::ExeResult;
+#[cfg(not(feature = "repl"))]
+use feint::result::{ExeErr, ExeErrKind};
+use feint::version::LONG_VERSION;
 use feint::vm::{CallDepth, VMState, DEFAULT_MAX_CALL_DEPTH};
 
+/// How often watched files are checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
 /// Interpret a file if one is specified. Otherwise, run the REPL.
 fn main() -> ExitCode {
     env_logger::init();
 
-    let app = cli::build_cli();
+    let app = cli::build_cli().long_version(LONG_VERSION);
     let matches = app.get_matches();
     let max_call_depth = *matches.get_one("max_call_depth").unwrap();
     let debug = *matches.get_one::<bool>("debug").unwrap();
+    let deterministic = *matches.get_one::<bool>("deterministic").unwrap();
 
     let max_call_depth = match matches.value_source("max_call_depth") {
         Some(ValueSource::DefaultValue) => DEFAULT_MAX_CALL_DEPTH,
@@ -26,9 +39,18 @@ fn main() -> ExitCode {
     };
 
     let return_code = match matches.subcommand() {
-        Some(("run", matches)) => handle_run(matches, max_call_depth, debug),
-        Some(("test", matches)) => handle_test(matches, max_call_depth, debug),
-        None => handle_run(&matches, max_call_depth, debug),
+        Some(("run", matches)) => {
+            handle_run(matches, max_call_depth, debug, deterministic)
+        }
+        Some(("dis", matches)) => handle_dis(matches, max_call_depth, debug),
+        Some(("check", matches)) => handle_check(matches, debug),
+        Some(("fmt", matches)) => handle_fmt(matches),
+        Some(("lsp", _)) => handle_lsp(debug),
+        Some(("bundle", matches)) => handle_bundle(matches),
+        Some(("test", matches)) => {
+            handle_test(matches, max_call_depth, debug, deterministic)
+        }
+        None => handle_run(&matches, max_call_depth, debug, deterministic),
         Some((name, _)) => {
             unreachable!("Subcommand not defined: {}", name);
         }
@@ -38,10 +60,20 @@ fn main() -> ExitCode {
 }
 
 /// Subcommand: run
-fn handle_run(matches: &ArgMatches, max_call_depth: CallDepth, debug: bool) -> u8 {
+fn handle_run(
+    matches: &ArgMatches,
+    max_call_depth: CallDepth,
+    debug: bool,
+    deterministic: bool,
+) -> u8 {
     let file_name = matches.get_one::<String>("FILE_NAME");
     let code = matches.get_one::<String>("code");
-    let dis = *matches.get_one::<bool>("dis").unwrap();
+    let no_print = *matches.get_one::<bool>("no_print").unwrap();
+    let watch = *matches.get_one::<bool>("watch").unwrap();
+    let dis_json = *matches.get_one::<bool>("dis_json").unwrap();
+    let dis = *matches.get_one::<bool>("dis").unwrap() || dis_json;
+    let debugger = *matches.get_one::<bool>("debugger").unwrap();
+    let optimize = *matches.get_one::<bool>("opt").unwrap();
     let history_path = matches.get_one::<String>("history_path");
     let save_repl_history = !matches.get_one::<bool>("no_history").unwrap();
     let mut argv: Vec<String> = matches
@@ -63,7 +95,29 @@ fn handle_run(matches: &ArgMatches, max_call_depth: CallDepth, debug: bool) -> u
     // error.
     let incremental = !(code.is_some() || file_name.is_some());
 
-    let mut exe = Executor::new(max_call_depth, argv, incremental, dis, debug);
+    let new_executor = || {
+        let mut exe =
+            Executor::new(max_call_depth, argv.clone(), incremental, dis, debug);
+        exe.set_deterministic(deterministic);
+        exe.set_optimize(optimize);
+        exe.set_dis_json(dis_json);
+        exe.set_debugger(debugger);
+        exe.set_print_result(code.is_some() && !no_print);
+        add_search_paths(&mut exe, matches);
+        exe
+    };
+
+    if watch {
+        let path = file_name.filter(|name| *name != "-").and_then(get_script_file_path);
+        return if let Some(path) = path {
+            watch_script(&path, &new_executor)
+        } else {
+            eprintln!("--watch requires a script file");
+            255
+        };
+    }
+
+    let mut exe = new_executor();
 
     if let Err(err) = exe.bootstrap() {
         return handle_exe_result(Err(err));
@@ -72,39 +126,292 @@ fn handle_run(matches: &ArgMatches, max_call_depth: CallDepth, debug: bool) -> u
     let exe_result = if let Some(code) = code {
         exe.execute_text(code)
     } else if let Some(file_name) = file_name {
-        if file_name == "-" {
-            exe.execute_stdin()
-        } else if let Some(path) = get_script_file_path(file_name) {
-            exe.execute_file(path.as_path())
-        } else {
-            exe.execute_module_as_script(file_name)
-        }
+        execute_script(&mut exe, file_name)
     } else {
-        let history_path = create_repl_history_file(&save_repl_history, history_path);
-        exe.install_sigint_handler();
-        let mut repl = Repl::new(history_path, exe);
-        repl.run()
+        run_repl(exe, save_repl_history, history_path)
     };
 
     handle_exe_result(exe_result)
 }
 
+/// Execute script from stdin (when `file_name` is a dash), a script
+/// file, or a module.
+fn execute_script(exe: &mut Executor, file_name: &String) -> ExeResult {
+    if file_name == "-" {
+        exe.execute_stdin()
+    } else if let Some(path) = get_script_file_path(file_name) {
+        exe.execute_file(path.as_path())
+    } else {
+        exe.execute_module_as_script(file_name)
+    }
+}
+
+#[cfg(feature = "repl")]
+fn run_repl(
+    mut exe: Executor,
+    save_history: bool,
+    history_path: Option<&String>,
+) -> ExeResult {
+    let history_path = create_repl_history_file(&save_history, history_path);
+    exe.install_sigint_handler();
+    let mut repl = Repl::new(history_path, exe);
+    repl.run()
+}
+
+#[cfg(not(feature = "repl"))]
+fn run_repl(
+    _exe: Executor,
+    _save_history: bool,
+    _history_path: Option<&String>,
+) -> ExeResult {
+    let msg = "FeInt was built without the repl feature".to_owned();
+    Err(ExeErr::new(ExeErrKind::ReplErr(msg)))
+}
+
+/// Subcommand: dis
+///
+/// The script is compiled but not run, so the modules it imports aren't
+/// loaded either. Nested functions are disassembled along with the
+/// module unless a function is specified.
+fn handle_dis(matches: &ArgMatches, max_call_depth: CallDepth, debug: bool) -> u8 {
+    let file_name = matches.get_one::<String>("FILE_NAME");
+    let code = matches.get_one::<String>("code");
+    let mut exe = Executor::new(max_call_depth, vec![], false, true, debug);
+    exe.set_optimize(*matches.get_one::<bool>("opt").unwrap());
+    exe.set_dis_json(*matches.get_one::<bool>("json").unwrap());
+    exe.set_dis_func(matches.get_one::<String>("func").cloned());
+    add_search_paths(&mut exe, matches);
+    if let Err(err) = exe.bootstrap() {
+        return handle_exe_result(Err(err));
+    }
+    let exe_result = if let Some(code) = code {
+        exe.execute_text(code)
+    } else {
+        execute_script(&mut exe, file_name.unwrap())
+    };
+    handle_exe_result(exe_result)
+}
+
+/// Subcommand: check
+///
+/// All of the files are checked even when some of them have errors.
+/// Directories are searched recursively for .fi files.
+fn handle_check(matches: &ArgMatches, debug: bool) -> u8 {
+    let files = get_files(matches);
+    let mut exe = Executor::new(DEFAULT_MAX_CALL_DEPTH, vec![], false, false, debug);
+    if let Err(err) = exe.bootstrap() {
+        return handle_exe_result(Err(err));
+    }
+    let num_errors = files.iter().filter(|path| exe.check_file(path).is_err()).count();
+    if num_errors == 0 {
+        0
+    } else {
+        let ess = if files.len() == 1 { "" } else { "s" };
+        eprintln!("{num_errors} of {} file{ess} had errors", files.len());
+        255
+    }
+}
+
+/// Subcommand: fmt
+fn handle_fmt(matches: &ArgMatches) -> u8 {
+    let check = *matches.get_one::<bool>("check").unwrap();
+    let files = get_files(matches);
+    let mut num_errors = 0;
+    let mut unformatted = vec![];
+    for path in files.iter() {
+        let result =
+            fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|text| {
+                match format_text(&text) {
+                    Ok(formatted) => Ok((text, formatted)),
+                    Err(err) => Err(err.to_string()),
+                }
+            });
+        let (text, formatted) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("Could not format {}: {err}", path.display());
+                num_errors += 1;
+                continue;
+            }
+        };
+        if formatted == text {
+            continue;
+        }
+        if check {
+            println!("{}", path.display());
+            unformatted.push(path);
+        } else if let Err(err) = fs::write(path, formatted) {
+            eprintln!("Could not write {}: {err}", path.display());
+            num_errors += 1;
+        }
+    }
+    if num_errors > 0 {
+        255
+    } else if !unformatted.is_empty() {
+        let ess = if files.len() == 1 { "" } else { "s" };
+        eprintln!(
+            "{} of {} file{ess} would be reformatted",
+            unformatted.len(),
+            files.len()
+        );
+        1
+    } else {
+        0
+    }
+}
+
+/// Subcommand: lsp
+fn handle_lsp(debug: bool) -> u8 {
+    // Bootstrapping adds the builtins defined in std.fi to std, which
+    // the language server completes.
+    let mut exe = Executor::new(DEFAULT_MAX_CALL_DEPTH, vec![], false, false, debug);
+    if let Err(err) = exe.bootstrap() {
+        return handle_exe_result(Err(err));
+    }
+    match lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Language server error: {err}");
+            255
+        }
+    }
+}
+
+/// Subcommand: bundle
+fn handle_bundle(matches: &ArgMatches) -> u8 {
+    let file_name = matches.get_one::<String>("FILE_NAME").unwrap();
+    let output = matches.get_one::<String>("output");
+    let mut exe = Executor::new(DEFAULT_MAX_CALL_DEPTH, vec![], false, false, false);
+    add_search_paths(&mut exe, matches);
+    let bundle = match exe.bundle_file(Path::new(file_name)) {
+        Ok(bundle) => bundle,
+        Err(err) => {
+            eprintln!("Could not bundle {file_name}: {err}");
+            return 255;
+        }
+    };
+    if let Some(output) = output {
+        if let Err(err) = fs::write(output, bundle) {
+            eprintln!("Could not write bundle to {output}: {err}");
+            return 255;
+        }
+    } else {
+        print!("{bundle}");
+    }
+    0
+}
+
 /// Subcommand: test
-fn handle_test(matches: &ArgMatches, max_call_depth: CallDepth, debug: bool) -> u8 {
+fn handle_test(
+    matches: &ArgMatches,
+    max_call_depth: CallDepth,
+    debug: bool,
+    deterministic: bool,
+) -> u8 {
     let argv: Vec<String> = matches
         .get_many::<String>("argv")
         .unwrap_or_default()
         .map(|v| v.to_string())
         .collect();
     let mut exe = Executor::new(max_call_depth, argv, false, false, debug);
+    exe.set_deterministic(deterministic);
+    add_search_paths(&mut exe, matches);
     if let Err(err) = exe.bootstrap() {
         return handle_exe_result(Err(err));
     }
     handle_exe_result(exe.execute_module_as_script("std.test"))
 }
 
+/// Run script, then run it again whenever it or any of the modules it
+/// imports from the file system change. Each run uses a new executor so
+/// no state is carried over between runs. This only returns if the
+/// executor can't be bootstrapped.
+fn watch_script(path: &Path, new_executor: &dyn Fn() -> Executor) -> u8 {
+    loop {
+        let mut exe = new_executor();
+        if let Err(err) = exe.bootstrap() {
+            return handle_exe_result(Err(err));
+        }
+        let return_code = handle_exe_result(exe.execute_file(path));
+        let mut files = vec![path.to_path_buf()];
+        files.extend(exe.module_files());
+        // Modified modules have to be reloaded on the next run.
+        exe.unload_module_files();
+        let ess = if files.len() == 1 { "" } else { "s" };
+        eprintln!(
+            "[watch] Exited with code {return_code}; watching {} file{ess} for changes",
+            files.len()
+        );
+        wait_for_change(&files);
+        eprintln!("{}", "-".repeat(72));
+    }
+}
+
+/// Wait until any of files is modified, created, or removed. The files
+/// are polled since watching them would require platform-specific
+/// APIs.
+fn wait_for_change(files: &[PathBuf]) {
+    let modified_times = |files: &[PathBuf]| -> Vec<Option<SystemTime>> {
+        files
+            .iter()
+            .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    };
+    let initial = modified_times(files);
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        if modified_times(files) != initial {
+            return;
+        }
+    }
+}
+
 // Utilities -----------------------------------------------------------
 
+/// Add directories specified via `--path` to the module search path.
+fn add_search_paths(exe: &mut Executor, matches: &ArgMatches) {
+    for path in matches.get_many::<String>("path").unwrap_or_default() {
+        exe.add_search_path(path);
+    }
+}
+
+/// Get the files specified by the FILE_NAMES arg, searching any
+/// directories for .fi files.
+fn get_files(matches: &ArgMatches) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for name in matches.get_many::<String>("FILE_NAMES").unwrap() {
+        let path = Path::new(name);
+        if path.is_dir() {
+            find_fi_files(path, &mut files);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    files
+}
+
+/// Add the paths of the .fi files in `dir` and its subdirectories to
+/// `files`, sorted so the order is stable.
+fn find_fi_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => {
+            entries.filter_map(|entry| entry.ok()).map(|e| e.path()).collect()
+        }
+        Err(err) => {
+            eprintln!("Could not read directory {}: {err}", dir.display());
+            return;
+        }
+    };
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            find_fi_files(&path, files);
+        } else if path.extension() == Some(OsStr::new("fi")) {
+            files.push(path);
+        }
+    }
+}
+
 /// Get script file path from `name`.
 ///
 /// If `name` refers to an existing file path _or_ is absolute _or_ has
@@ -141,6 +448,7 @@ fn get_script_file_path(name: &String) -> Option<PathBuf> {
 }
 
 /// Convert REPL history path from CLI to a `PathBuf`, if possible.
+#[cfg(feature = "repl")]
 fn create_repl_history_file(cond: &bool, path: Option<&String>) -> Option<PathBuf> {
     if !cond {
         return None;
@@ -187,7 +495,7 @@ fn create_repl_history_file(cond: &bool, path: Option<&String>) -> Option<PathBu
 fn handle_exe_result(exe_result: ExeResult) -> u8 {
     match exe_result {
         Ok(vm_state) => match vm_state {
-            VMState::Running => {
+            VMState::Running | VMState::Paused(_) => {
                 eprintln!("VM should be idle or halted, not running");
                 255
             }
@@ -208,6 +516,7 @@ fn handle_exe_result(exe_result: ExeResult) -> u8 {
 /// Get path for str, expanding leading ~ to user home directory. The
 /// default path is used when the input path is None, "", or the home
 /// directory isn't found.
+#[cfg(feature = "repl")]
 fn str_to_path_buf(path: Option<&String>, default: Option<&String>) -> Option<PathBuf> {
     let home = dirs::home_dir();
 
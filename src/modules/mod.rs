@@ -4,6 +4,7 @@ use once_cell::sync::Lazy;
 use crate::types::gen::{obj_ref, obj_ref_t};
 use crate::types::{Map, ObjectRef, ObjectTrait};
 
+pub mod registry;
 pub mod std;
 
 /// This mirrors `system.modules`. It provides a way to access
@@ -17,6 +18,13 @@ pub fn add_module(name: &str, module: ObjectRef) {
     modules.insert(name, module);
 }
 
+/// Remove module from `std.system.modules`.
+pub fn remove_module(name: &str) -> Option<ObjectRef> {
+    let modules = MODULES.write().unwrap();
+    let modules = modules.down_to_map().unwrap();
+    modules.remove(name)
+}
+
 /// Get module from `system.modules`.
 ///
 /// XXX: Panics if the module doesn't exist (since that shouldn't be
@@ -0,0 +1,142 @@
+//! Registry of intrinsic functions. Intrinsic functions are
+//! registered as they're attached to intrinsic modules and types, and
+//! the specs are grouped by the full name of the module or type they
+//! belong to.
+//!
+//! Registering a function with the same name as one that's already
+//! registered in the same group is a conflict, since one of the
+//! functions would otherwise silently replace the other. Conflicts
+//! are reported by `check()`, which is called during bootstrap.
+//!
+//! The registry can also be used to list the available intrinsic
+//! functions (e.g., to generate docs).
+use std::sync::{RwLock, RwLockReadGuard};
+
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+
+use crate::types::{FuncTrait, ObjectRef};
+
+pub static FUNC_REGISTRY: Lazy<RwLock<FuncRegistry>> =
+    Lazy::new(|| RwLock::new(FuncRegistry::default()));
+
+/// Get read access to the registry.
+pub fn registry() -> RwLockReadGuard<'static, FuncRegistry> {
+    FUNC_REGISTRY.read().unwrap()
+}
+
+/// Register the intrinsic functions in entries, which are the entries
+/// of an intrinsic module or the attributes of an intrinsic type.
+/// Entries that aren't intrinsic functions or props are skipped.
+///
+/// If the group was already registered (e.g., when a native module is
+/// rebuilt), its specs are replaced.
+pub fn register_group(group: &str, entries: &[(&str, ObjectRef)]) {
+    let specs: Vec<FuncSpec> = entries
+        .iter()
+        .filter_map(|(name, obj_ref)| FuncSpec::from_obj(group, name, obj_ref))
+        .collect();
+    let mut registry = FUNC_REGISTRY.write().unwrap();
+    registry.remove_group(group);
+    for spec in specs {
+        registry.register(spec);
+    }
+}
+
+/// Check for conflicting registrations.
+pub fn check() -> Result<(), String> {
+    let registry = registry();
+    let conflicts = registry.conflicts();
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Intrinsic functions registered more than once: {}",
+            conflicts.join(", ")
+        ))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FuncSpec {
+    pub group: String,
+    pub name: String,
+    pub params: Vec<String>,
+    pub doc: String,
+    pub is_prop: bool,
+}
+
+impl FuncSpec {
+    fn from_obj(group: &str, name: &str, obj_ref: &ObjectRef) -> Option<Self> {
+        let obj = obj_ref.read().unwrap();
+        let (func_ref, is_prop) = if let Some(prop) = obj.down_to_prop() {
+            (prop.getter(), true)
+        } else {
+            (obj_ref.clone(), false)
+        };
+        drop(obj);
+        let func = func_ref.read().unwrap();
+        let func = func.down_to_intrinsic_func()?;
+        let doc = func.get_doc();
+        let doc = doc.read().unwrap();
+        Some(Self {
+            group: group.to_owned(),
+            name: name.to_owned(),
+            params: func.params().clone(),
+            doc: doc.get_str_val().unwrap_or_default().to_owned(),
+            is_prop,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FuncRegistry {
+    groups: IndexMap<String, IndexMap<String, FuncSpec>>,
+    // group name => names registered more than once
+    conflicts: IndexMap<String, Vec<String>>,
+}
+
+impl FuncRegistry {
+    /// Register spec. If a spec with the same name is already
+    /// registered in the same group, the original spec is kept and
+    /// the conflict is recorded.
+    pub fn register(&mut self, spec: FuncSpec) {
+        let group = self.groups.entry(spec.group.clone()).or_default();
+        if group.contains_key(&spec.name) {
+            self.conflicts.entry(spec.group).or_default().push(spec.name);
+        } else {
+            group.insert(spec.name.clone(), spec);
+        }
+    }
+
+    /// Remove group along with any conflicts recorded for it.
+    pub fn remove_group(&mut self, group: &str) {
+        self.groups.remove(group);
+        self.conflicts.remove(group);
+    }
+
+    pub fn get(&self, group: &str, name: &str) -> Option<&FuncSpec> {
+        self.groups.get(group).and_then(|specs| specs.get(name))
+    }
+
+    /// Get the names of the registered groups in registration order.
+    pub fn group_names(&self) -> Vec<&str> {
+        self.groups.keys().map(|name| name.as_str()).collect()
+    }
+
+    /// Get the specs registered in group in registration order.
+    pub fn specs(&self, group: &str) -> Vec<&FuncSpec> {
+        self.groups.get(group).map_or_else(Vec::new, |specs| specs.values().collect())
+    }
+
+    /// Get the full names of the functions that were registered more
+    /// than once.
+    pub fn conflicts(&self) -> Vec<String> {
+        self.conflicts
+            .iter()
+            .flat_map(|(group, names)| {
+                names.iter().map(move |name| format!("{group}.{name}"))
+            })
+            .collect()
+    }
+}
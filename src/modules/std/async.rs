@@ -0,0 +1,133 @@
+//! Running async functions. Calling an async function returns a Task,
+//! which is run by passing it to `run()` or by awaiting it in another
+//! async function.
+//!
+//! NOTE: Since `async` is a keyword, this module has to be imported
+//!       with `from std.async import ...` or `import std.async as ...`.
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use num_traits::ToPrimitive;
+use once_cell::sync::Lazy;
+
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module};
+use crate::vm::RuntimeErr;
+
+pub static ASYNC: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.async",
+        "<std.async>",
+        "Async functions and tasks",
+        &[
+            (
+                "run",
+                new::intrinsic_func(
+                    "std.async",
+                    "run",
+                    None,
+                    &["task"],
+                    "Run an event loop until task finishes.
+
+                    If the task raises an error, the error is raised
+                    again here. Tasks that were spawned but not awaited
+                    may not finish.
+
+                    # Args
+
+                    - task: Task
+
+                    # Returns
+
+                    Any: The task's result
+
+                    ",
+                    |_, args, vm| {
+                        let arg = gen::use_arg!(args, 0);
+                        if !arg.is_task() {
+                            let msg = "run() expected task to be a Task";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                        drop(arg);
+                        vm.run_until_complete(args[0].clone())
+                    },
+                ),
+            ),
+            (
+                "spawn",
+                new::intrinsic_func(
+                    "std.async",
+                    "spawn",
+                    None,
+                    &["task"],
+                    "Schedule task to run concurrently with the current
+                    task.
+
+                    The spawned task starts running when the current task
+                    finishes or awaits another task. This can only be
+                    used while the event loop is running.
+
+                    # Args
+
+                    - task: Task
+
+                    # Returns
+
+                    Task: The spawned task
+
+                    ",
+                    |_, args, vm| {
+                        let arg = gen::use_arg!(args, 0);
+                        if !arg.is_task() {
+                            let msg = "spawn() expected task to be a Task";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                        drop(arg);
+                        vm.spawn(&args[0])?;
+                        Ok(args[0].clone())
+                    },
+                ),
+            ),
+            (
+                "sleep",
+                new::intrinsic_func(
+                    "std.async",
+                    "sleep",
+                    None,
+                    &["seconds"],
+                    "Create a Task that finishes with nil after the
+                    specified number of seconds. Other tasks can run
+                    while the task that awaits it is waiting.
+
+                    # Args
+
+                    - seconds: Int | Float
+
+                    # Returns
+
+                    Task
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let seconds = if let Some(val) = arg.get_int_val() {
+                            val.to_f64()
+                        } else {
+                            arg.get_float_val().copied()
+                        };
+                        match seconds {
+                            Some(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+                                Ok(new::sleep_task(Duration::from_secs_f64(seconds)))
+                            }
+                            _ => {
+                                let msg =
+                                    "sleep() expected seconds to be a non-negative number";
+                                Ok(new::arg_err(msg, new::nil()))
+                            }
+                        }
+                    },
+                ),
+            ),
+        ],
+    )
+});
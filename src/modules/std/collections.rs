@@ -0,0 +1,21 @@
+//! Collection types that aren't builtins. Deque and PriorityQueue are
+//! implemented in Rust since implementing them in FeInt on top of List
+//! would be slow.
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::types::gen::obj_ref_t;
+use crate::types::{self, new, Module};
+
+pub static COLLECTIONS: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.collections",
+        "<std.collections>",
+        "Collection types",
+        &[
+            ("Deque", types::deque::DEQUE_TYPE.clone()),
+            ("PriorityQueue", types::priority_queue::PRIORITY_QUEUE_TYPE.clone()),
+        ],
+    )
+});
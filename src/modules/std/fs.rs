@@ -0,0 +1,673 @@
+//! File system utilities. Functions that access the file system
+//! return an Err instead of raising when an operation fails, so the
+//! result should be checked with `.err` (or `.ok`).
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+
+use crate::types::file::{data_to_write, FileMode};
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::RuntimeErr;
+
+/// Get the data to write from a Str, Bytes, or ByteBuffer arg or
+/// return an arg Err if it's some other type.
+macro_rules! use_data_arg {
+    ( $func_name:ident, $arg:ident ) => {{
+        if let Some(data) = data_to_write(&*$arg) {
+            data
+        } else {
+            let msg = format!(
+                "{}() expected data to be a Str, Bytes, or ByteBuffer",
+                stringify!($func_name)
+            );
+            return Ok(new::arg_err(msg, new::nil()));
+        }
+    }};
+}
+
+pub static FS: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.fs",
+        "<std.fs>",
+        "File system module",
+        &[
+            // Files ---------------------------------------------------
+            (
+                "open",
+                new::intrinsic_func(
+                    "std.fs",
+                    "open",
+                    None,
+                    &["path", ""],
+                    "Open a file.
+
+                    # Args
+
+                    - path: Str
+                    - mode?: Str
+
+                      - \"r\": Read an existing file (default)
+                      - \"w\": Write a file, creating it if it doesn't
+                        exist and truncating it if it does
+                      - \"a\": Append to a file, creating it if it
+                        doesn't exist
+                      - \"x\": Write a new file; it's an error if the
+                        file already exists
+
+                    # Returns
+
+                    - File: Use `write()` to write files opened in
+                      write or append mode
+                    - Err: If the file couldn't be opened
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(open, path, arg);
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        let mode = match var_args.len() {
+                            0 => "r".to_owned(),
+                            1 => {
+                                let mode = var_args.get(0).unwrap();
+                                let mode = mode.read().unwrap();
+                                let mode = gen::use_arg_str!(open, mode, mode);
+                                mode.to_owned()
+                            }
+                            n => {
+                                let msg = format!(
+                                    "open() expected at most 2 args; got {}",
+                                    n + 1
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        let mut options = fs::OpenOptions::new();
+                        let mode = match mode.as_str() {
+                            "r" => {
+                                return Ok(if Path::new(path).is_file() {
+                                    new::file(path)
+                                } else {
+                                    new::file_not_found_err(path, new::nil())
+                                });
+                            }
+                            "w" => {
+                                options.write(true).create(true).truncate(true);
+                                FileMode::Write
+                            }
+                            "a" => {
+                                options.append(true).create(true);
+                                FileMode::Append
+                            }
+                            "x" => {
+                                options.write(true).create_new(true);
+                                FileMode::Write
+                            }
+                            _ => {
+                                let msg = "open() expected mode to be one of \
+                                    \"r\", \"w\", \"a\", or \"x\"";
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        Ok(match options.open(path) {
+                            Ok(_) => new::file_with_mode(path, mode),
+                            Err(err) => io_err(path, err, true),
+                        })
+                    },
+                ),
+            ),
+            (
+                "read_text",
+                new::intrinsic_func(
+                    "std.fs",
+                    "read_text",
+                    None,
+                    &["path"],
+                    "Read the entire contents of a text file.
+
+                    # Returns
+
+                    - Str
+                    - Err: If the file doesn't exist or couldn't be read
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(read_text, path, arg);
+                        Ok(match fs::read_to_string(path) {
+                            Ok(text) => new::str(text),
+                            Err(err) => io_err(path, err, false),
+                        })
+                    },
+                ),
+            ),
+            (
+                "read_bytes",
+                new::intrinsic_func(
+                    "std.fs",
+                    "read_bytes",
+                    None,
+                    &["path"],
+                    "Read the entire contents of a binary file.
+
+                    # Returns
+
+                    - Bytes
+                    - Err: If the file doesn't exist or couldn't be read
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(read_bytes, path, arg);
+                        Ok(match fs::read(path) {
+                            Ok(data) => new::bytes(data),
+                            Err(err) => io_err(path, err, false),
+                        })
+                    },
+                ),
+            ),
+            (
+                "read_lines",
+                new::intrinsic_func(
+                    "std.fs",
+                    "read_lines",
+                    None,
+                    &["path"],
+                    "Read the lines of a text file. Line endings aren't
+                    included.
+
+                    Use `File.lines()` to read large files lazily.
+
+                    # Returns
+
+                    - List: Of Strs
+                    - Err: If the file doesn't exist or couldn't be read
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(read_lines, path, arg);
+                        Ok(match fs::read_to_string(path) {
+                            Ok(text) => new::list(text.lines().map(new::str).collect()),
+                            Err(err) => io_err(path, err, false),
+                        })
+                    },
+                ),
+            ),
+            (
+                "write",
+                new::intrinsic_func(
+                    "std.fs",
+                    "write",
+                    None,
+                    &["path", "data"],
+                    "Write data to a file, creating the file if it doesn't
+                    exist and replacing its contents if it does.
+
+                    # Args
+
+                    - path: Str
+                    - data: Str | Bytes | ByteBuffer
+
+                    # Returns
+
+                    - nil: If the file was written
+                    - Err: If the file couldn't be written
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(write, path, arg);
+                        let data = gen::use_arg!(args, 1);
+                        let data = use_data_arg!(write, data);
+                        Ok(match fs::write(path, data) {
+                            Ok(()) => new::nil(),
+                            Err(err) => io_err(path, err, true),
+                        })
+                    },
+                ),
+            ),
+            (
+                "append",
+                new::intrinsic_func(
+                    "std.fs",
+                    "append",
+                    None,
+                    &["path", "data"],
+                    "Append data to a file, creating the file if it doesn't
+                    exist.
+
+                    # Args
+
+                    - path: Str
+                    - data: Str | Bytes | ByteBuffer
+
+                    # Returns
+
+                    - nil: If the file was written
+                    - Err: If the file couldn't be written
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(append, path, arg);
+                        let data = gen::use_arg!(args, 1);
+                        let data = use_data_arg!(append, data);
+                        let result = fs::OpenOptions::new()
+                            .append(true)
+                            .create(true)
+                            .open(path)
+                            .and_then(|mut file| file.write_all(&data));
+                        Ok(match result {
+                            Ok(()) => new::nil(),
+                            Err(err) => io_err(path, err, true),
+                        })
+                    },
+                ),
+            ),
+            (
+                "copy",
+                new::intrinsic_func(
+                    "std.fs",
+                    "copy",
+                    None,
+                    &["from", "to"],
+                    "Copy a file, replacing the destination if it exists.
+
+                    # Args
+
+                    - from: Str
+                    - to: Str
+
+                    # Returns
+
+                    - Int: The number of bytes copied
+                    - Err: If the file couldn't be copied
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let from = gen::use_arg_str!(copy, from, arg);
+                        let arg = gen::use_arg!(args, 1);
+                        let to = gen::use_arg_str!(copy, to, arg);
+                        Ok(match fs::copy(from, to) {
+                            Ok(n) => new::int(n),
+                            Err(err) => {
+                                let path =
+                                    if Path::new(from).exists() { to } else { from };
+                                io_err(path, err, true)
+                            }
+                        })
+                    },
+                ),
+            ),
+            (
+                "remove",
+                new::intrinsic_func(
+                    "std.fs",
+                    "remove",
+                    None,
+                    &["path"],
+                    "Remove a file or an empty directory.
+
+                    # Returns
+
+                    - nil: If the file or directory was removed
+                    - Err: If path doesn't exist, is a directory that
+                      isn't empty, or couldn't be removed
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(remove, path, arg);
+                        let result = if Path::new(path).is_dir() {
+                            fs::remove_dir(path)
+                        } else {
+                            fs::remove_file(path)
+                        };
+                        Ok(match result {
+                            Ok(()) => new::nil(),
+                            Err(err) => io_err(path, err, true),
+                        })
+                    },
+                ),
+            ),
+            // Directories ---------------------------------------------
+            (
+                "mkdir",
+                new::intrinsic_func(
+                    "std.fs",
+                    "mkdir",
+                    None,
+                    &["path"],
+                    "Create a directory along with any missing parent
+                    directories. It's not an error if the directory
+                    already exists.
+
+                    # Returns
+
+                    - nil: If the directory was created or already exists
+                    - Err: If the directory couldn't be created
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(mkdir, path, arg);
+                        Ok(match fs::create_dir_all(path) {
+                            Ok(()) => new::nil(),
+                            Err(err) => io_err(path, err, true),
+                        })
+                    },
+                ),
+            ),
+            (
+                "list_dir",
+                new::intrinsic_func(
+                    "std.fs",
+                    "list_dir",
+                    None,
+                    &["path"],
+                    "List the names of the entries in a directory, sorted
+                    by name. Names don't include the directory path; use
+                    `join()` to get the full path of an entry.
+
+                    # Returns
+
+                    - List: Of Strs
+                    - Err: If the directory doesn't exist or couldn't be
+                      read
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(list_dir, path, arg);
+                        let names = fs::read_dir(path).and_then(|entries| {
+                            entries
+                                .map(|entry| {
+                                    entry.map(|e| {
+                                        e.file_name().to_string_lossy().into_owned()
+                                    })
+                                })
+                                .collect::<io::Result<Vec<_>>>()
+                        });
+                        Ok(match names {
+                            Ok(mut names) => {
+                                names.sort();
+                                new::list(names.into_iter().map(new::str).collect())
+                            }
+                            Err(err) => io_err(path, err, false),
+                        })
+                    },
+                ),
+            ),
+            // Paths ---------------------------------------------------
+            (
+                "exists",
+                new::intrinsic_func(
+                    "std.fs",
+                    "exists",
+                    None,
+                    &["path"],
+                    "Check whether a file or directory exists at path.",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(exists, path, arg);
+                        Ok(new::bool(Path::new(path).exists()))
+                    },
+                ),
+            ),
+            (
+                "is_file",
+                new::intrinsic_func(
+                    "std.fs",
+                    "is_file",
+                    None,
+                    &["path"],
+                    "Check whether path is an existing file.",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(is_file, path, arg);
+                        Ok(new::bool(Path::new(path).is_file()))
+                    },
+                ),
+            ),
+            (
+                "is_dir",
+                new::intrinsic_func(
+                    "std.fs",
+                    "is_dir",
+                    None,
+                    &["path"],
+                    "Check whether path is an existing directory.",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(is_dir, path, arg);
+                        Ok(new::bool(Path::new(path).is_dir()))
+                    },
+                ),
+            ),
+            (
+                "join",
+                new::intrinsic_func(
+                    "std.fs",
+                    "join",
+                    None,
+                    &["path", ""],
+                    "Join path with any number of additional parts using
+                    the platform's path separator. If a part is an
+                    absolute path, it replaces everything before it.
+
+                    # Args
+
+                    - path: Str
+                    - ...parts: Str
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(join, path, arg);
+                        let mut path = PathBuf::from(path);
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        for part in var_args.iter() {
+                            let part = part.read().unwrap();
+                            let part = gen::use_arg_str!(join, parts, part);
+                            path.push(part);
+                        }
+                        Ok(new::str(path.to_string_lossy()))
+                    },
+                ),
+            ),
+            (
+                "dirname",
+                new::intrinsic_func(
+                    "std.fs",
+                    "dirname",
+                    None,
+                    &["path"],
+                    "Get the directory part of path (everything before
+                    the last part).
+
+                    # Returns
+
+                    Str: Empty if path has no directory part
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(dirname, path, arg);
+                        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+                        Ok(new::str(dir.to_string_lossy()))
+                    },
+                ),
+            ),
+            (
+                "basename",
+                new::intrinsic_func(
+                    "std.fs",
+                    "basename",
+                    None,
+                    &["path"],
+                    "Get the last part of path.
+
+                    # Returns
+
+                    Str: Empty if path ends with `..` or is a root
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(basename, path, arg);
+                        let name = Path::new(path).file_name().unwrap_or_default();
+                        Ok(new::str(name.to_string_lossy()))
+                    },
+                ),
+            ),
+            (
+                "ext",
+                new::intrinsic_func(
+                    "std.fs",
+                    "ext",
+                    None,
+                    &["path"],
+                    "Get the extension of the last part of path, without
+                    the leading dot.
+
+                    # Returns
+
+                    - Str
+                    - nil: If there's no extension
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(ext, path, arg);
+                        Ok(match Path::new(path).extension() {
+                            Some(ext) => new::str(ext.to_string_lossy()),
+                            None => new::nil(),
+                        })
+                    },
+                ),
+            ),
+            (
+                "abs",
+                new::intrinsic_func(
+                    "std.fs",
+                    "abs",
+                    None,
+                    &["path"],
+                    "Get the absolute path of an existing file or
+                    directory, with symlinks and `.`/`..` resolved.
+
+                    # Returns
+
+                    - Str
+                    - Err: If path doesn't exist
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(abs, path, arg);
+                        Ok(match fs::canonicalize(path) {
+                            Ok(abs_path) => new::str(abs_path.to_string_lossy()),
+                            Err(err) => io_err(path, err, false),
+                        })
+                    },
+                ),
+            ),
+            // Temporary Files -----------------------------------------
+            (
+                "temp_file",
+                new::intrinsic_func(
+                    "std.fs",
+                    "temp_file",
+                    None,
+                    &[],
+                    "Create an empty temporary file.
+
+                    The file is removed by calling `cleanup()` on the
+                    result or when the result is no longer referenced.
+
+                    # Returns
+
+                    - TempPath: With `is_dir` set to false
+                    - Err: If the file couldn't be created
+
+                    ",
+                    |_, _, _| Ok(make_temp_path(false)),
+                ),
+            ),
+            (
+                "temp_dir",
+                new::intrinsic_func(
+                    "std.fs",
+                    "temp_dir",
+                    None,
+                    &[],
+                    "Create an empty temporary directory.
+
+                    The directory and everything in it is removed by
+                    calling `cleanup()` on the result or when the result
+                    is no longer referenced.
+
+                    # Returns
+
+                    - TempPath: With `is_dir` set to true
+                    - Err: If the directory couldn't be created
+
+                    ",
+                    |_, _, _| Ok(make_temp_path(true)),
+                ),
+            ),
+        ],
+    )
+});
+
+/// Convert an I/O error for path to an Err. Errors other than the path
+/// not being found are reported as unreadable or unwritable depending
+/// on whether the operation was writing.
+fn io_err(path: &str, err: io::Error, writing: bool) -> ObjectRef {
+    let msg = format!("{path}: {err}");
+    if err.kind() == io::ErrorKind::NotFound {
+        new::file_not_found_err(msg, new::nil())
+    } else if writing {
+        new::file_unwritable_err(msg, new::nil())
+    } else {
+        new::file_unreadable_err(msg, new::nil())
+    }
+}
+
+/// Create a uniquely named file or directory in the system temp
+/// directory, retrying with a new name if one is already taken.
+fn make_temp_path(is_dir: bool) -> ObjectRef {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let temp_dir = std::env::temp_dir();
+    loop {
+        let count = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.subsec_nanos())
+            .unwrap_or(0);
+        let name = format!("feint-{}-{count}-{nanos}", std::process::id());
+        let path: PathBuf = temp_dir.join(name);
+        let result = if is_dir {
+            fs::create_dir(&path)
+        } else {
+            fs::OpenOptions::new().write(true).create_new(true).open(&path).map(|_| ())
+        };
+        match result {
+            Ok(()) => break new::temp_path(path, is_dir),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                let msg = format!("{}: {err}", path.display());
+                break new::file_unwritable_err(msg, new::nil());
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! File name pattern matching. Patterns use the usual shell syntax:
+//! `?` matches any single char, `*` matches any sequence of chars,
+//! `**` matches any number of directories, and `[...]` matches any of
+//! the enclosed chars.
+use std::sync::{Arc, RwLock};
+
+use ::glob::{glob, Pattern};
+use once_cell::sync::Lazy;
+
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module};
+use crate::vm::RuntimeErr;
+
+pub static GLOB: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.glob",
+        "<std.glob>",
+        "File name pattern matching",
+        &[
+            (
+                "glob",
+                new::intrinsic_func(
+                    "std.glob",
+                    "glob",
+                    None,
+                    &["pattern"],
+                    "Find paths matching pattern.
+
+                    Paths are found lazily as the resulting Iterator is
+                    advanced. If a directory can't be read while searching,
+                    an Err is produced in place of its path.
+
+                    # Args
+
+                    - pattern: Str
+
+                    # Returns
+
+                    Iterator: Produces the matching paths as Strs
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let pattern = gen::use_arg_str!(glob, pattern, arg);
+                        let paths = match glob(pattern) {
+                            Ok(paths) => paths,
+                            Err(err) => {
+                                let msg = format!("glob() got invalid pattern: {err}");
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        let source = paths.map(|path| match path {
+                            Ok(path) => new::str(path.to_string_lossy()),
+                            Err(err) => {
+                                new::file_unreadable_err(err.to_string(), new::nil())
+                            }
+                        });
+                        Ok(new::lazy_iterator(Box::new(source)))
+                    },
+                ),
+            ),
+            (
+                "fnmatch",
+                new::intrinsic_func(
+                    "std.glob",
+                    "fnmatch",
+                    None,
+                    &["name", "pattern"],
+                    "Check whether name matches pattern.
+
+                    Only the name is checked; the file system isn't
+                    accessed.
+
+                    # Args
+
+                    - name: Str
+                    - pattern: Str
+
+                    ",
+                    |_, args, _| {
+                        let name_arg = gen::use_arg!(args, 0);
+                        let name = gen::use_arg_str!(fnmatch, name, name_arg);
+                        let pattern_arg = gen::use_arg!(args, 1);
+                        let pattern = gen::use_arg_str!(fnmatch, pattern, pattern_arg);
+                        match Pattern::new(pattern) {
+                            Ok(pattern) => Ok(new::bool(pattern.matches(name))),
+                            Err(err) => {
+                                let msg =
+                                    format!("fnmatch() got invalid pattern: {err}");
+                                Ok(new::arg_err(msg, new::nil()))
+                            }
+                        }
+                    },
+                ),
+            ),
+        ],
+    )
+});
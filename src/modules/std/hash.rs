@@ -0,0 +1,140 @@
+//! Hashing module. Functions accept a Str (hashed as UTF-8), Bytes, or
+//! ByteBuffer, so files can be checksummed with, e.g.,
+//! `hash.sha256(fs.read_bytes(path))`.
+use std::sync::{Arc, RwLock};
+
+use md5::Md5;
+use once_cell::sync::Lazy;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::types::bytes::to_hex;
+use crate::types::file::data_to_write;
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::RuntimeErr;
+
+#[derive(Clone, Copy)]
+enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Crc32,
+}
+
+impl Algorithm {
+    const ALL: [Self; 4] = [Self::Md5, Self::Sha1, Self::Sha256, Self::Crc32];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Crc32 => "crc32",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| algorithm.name() == name)
+    }
+
+    /// Get the digest of data. CRC-32 checksums are big-endian.
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Md5 => Md5::digest(data).to_vec(),
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Crc32 => crc32fast::hash(data).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// Get the data to hash from a Str, Bytes, or ByteBuffer arg or return
+/// an arg Err if it's some other type.
+macro_rules! use_data_arg {
+    ( $func_name:expr, $arg:ident ) => {{
+        if let Some(data) = data_to_write(&*$arg) {
+            data
+        } else {
+            let msg = format!(
+                "{}() expected data to be a Str, Bytes, or ByteBuffer",
+                $func_name
+            );
+            return Ok(new::arg_err(msg, new::nil()));
+        }
+    }};
+}
+
+/// Create an entry for a function that returns the hex digest of its
+/// data arg.
+fn hex_func(algorithm: Algorithm, doc: &str) -> (&'static str, ObjectRef) {
+    let name = algorithm.name();
+    (
+        name,
+        new::intrinsic_func(
+            "std.hash",
+            name,
+            None,
+            &["data"],
+            doc,
+            move |_, args, _| {
+                let arg = gen::use_arg!(args, 0);
+                let data = use_data_arg!(name, arg);
+                Ok(new::str(to_hex(&algorithm.digest(&data))))
+            },
+        ),
+    )
+}
+
+pub static HASH: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    let names = Algorithm::ALL.iter().map(|algorithm| new::str(algorithm.name()));
+    new::intrinsic_module(
+        "std.hash",
+        "<std.hash>",
+        "Hashing module",
+        &[
+            ("algorithms", new::tuple(names.collect())),
+            hex_func(Algorithm::Md5, "Get the MD5 digest of data as a hex Str."),
+            hex_func(Algorithm::Sha1, "Get the SHA-1 digest of data as a hex Str."),
+            hex_func(Algorithm::Sha256, "Get the SHA-256 digest of data as a hex Str."),
+            hex_func(Algorithm::Crc32, "Get the CRC-32 checksum of data as a hex Str."),
+            (
+                "digest",
+                new::intrinsic_func(
+                    "std.hash",
+                    "digest",
+                    None,
+                    &["algorithm", "data"],
+                    "Get the digest of data as Bytes.
+
+                    # Args
+
+                    - algorithm: Str
+
+                      One of the names in `hash.algorithms`. CRC-32
+                      checksums are big-endian.
+
+                    - data: Str | Bytes | ByteBuffer
+
+                    # Returns
+
+                    - Bytes
+                    - Err: If the algorithm isn't known
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let name = gen::use_arg_str!(digest, algorithm, arg);
+                        let Some(algorithm) = Algorithm::from_name(name) else {
+                            let msg = format!("Unknown hash algorithm: {name}");
+                            return Ok(new::arg_err(msg, new::nil()));
+                        };
+                        let arg = gen::use_arg!(args, 1);
+                        let data = use_data_arg!("digest", arg);
+                        Ok(new::bytes(algorithm.digest(&data)))
+                    },
+                ),
+            ),
+        ],
+    )
+});
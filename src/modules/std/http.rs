@@ -0,0 +1,265 @@
+//! HTTP client module. Requests are blocking, and the whole response
+//! body is read before the Response is returned. A Response is returned
+//! for any status code; an Err is only returned if the request couldn't
+//! be sent or the response couldn't be read.
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use num_traits::ToPrimitive;
+use once_cell::sync::Lazy;
+
+use crate::types::file::data_to_write;
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::RuntimeErr;
+
+#[derive(Default)]
+struct Options {
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+}
+
+pub static HTTP: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.http",
+        "<std.http>",
+        "HTTP client module",
+        &[
+            (
+                "request",
+                new::intrinsic_func(
+                    "std.http",
+                    "request",
+                    None,
+                    &["method", "url", "body", ""],
+                    "Send an HTTP request.
+
+                    # Args
+
+                    - method: Str
+                    - url: Str
+                    - body: Str | Bytes | ByteBuffer | nil
+                    - options?: Map
+
+                      - headers: Map<Str>
+                      - timeout: Int | Float
+
+                        Seconds to wait for the whole request,
+                        including reading the body. Defaults to no
+                        timeout.
+
+                    # Returns
+
+                    - Response: For any status code
+                    - Err: If the request couldn't be sent or the
+                      response couldn't be read
+
+                    ",
+                    |_, args, _| {
+                        let method_arg = gen::use_arg!(args, 0);
+                        let method = gen::use_arg_str!(request, method, method_arg);
+                        let url_arg = gen::use_arg!(args, 1);
+                        let url = gen::use_arg_str!(request, url, url_arg);
+                        let body_arg = gen::use_arg!(args, 2);
+                        let body = if body_arg.is_nil() {
+                            None
+                        } else if let Some(data) = data_to_write(&*body_arg) {
+                            Some(data)
+                        } else {
+                            let msg = "request() expected body to be a Str, Bytes, \
+                                ByteBuffer, or nil";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        };
+                        Ok(match get_options("request", &args[3], 3) {
+                            Ok(options) => send(method, url, body.as_deref(), options),
+                            Err(err) => err,
+                        })
+                    },
+                ),
+            ),
+            (
+                "get",
+                new::intrinsic_func(
+                    "std.http",
+                    "get",
+                    None,
+                    &["url", ""],
+                    "Send a GET request.
+
+                    # Args
+
+                    - url: Str
+                    - options?: Map: See `request()`
+
+                    # Returns
+
+                    Response | Err: See `request()`
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let url = gen::use_arg_str!(get, url, arg);
+                        Ok(match get_options("get", &args[1], 1) {
+                            Ok(options) => send("GET", url, None, options),
+                            Err(err) => err,
+                        })
+                    },
+                ),
+            ),
+            (
+                "post",
+                new::intrinsic_func(
+                    "std.http",
+                    "post",
+                    None,
+                    &["url", "body", ""],
+                    "Send a POST request.
+
+                    # Args
+
+                    - url: Str
+                    - body: Str | Bytes | ByteBuffer
+                    - options?: Map: See `request()`
+
+                    # Returns
+
+                    Response | Err: See `request()`
+
+                    ",
+                    |_, args, _| {
+                        let url_arg = gen::use_arg!(args, 0);
+                        let url = gen::use_arg_str!(post, url, url_arg);
+                        let body_arg = gen::use_arg!(args, 1);
+                        let Some(body) = data_to_write(&*body_arg) else {
+                            let msg = "post() expected body to be a Str, Bytes, or \
+                                ByteBuffer";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        };
+                        Ok(match get_options("post", &args[2], 2) {
+                            Ok(options) => send("POST", url, Some(&body), options),
+                            Err(err) => err,
+                        })
+                    },
+                ),
+            ),
+        ],
+    )
+});
+
+/// Get options from the var args passed to `func_name`, which takes
+/// `num_args` args before the options. If the options are invalid, an
+/// arg Err is returned.
+fn get_options(
+    func_name: &str,
+    var_args: &ObjectRef,
+    num_args: usize,
+) -> Result<Options, ObjectRef> {
+    let arg_err = |msg: String| new::arg_err(msg, new::nil());
+    let var_args = var_args.read().unwrap();
+    let var_args = var_args.down_to_tuple().unwrap();
+    let options_arg = match var_args.len() {
+        0 => return Ok(Options::default()),
+        1 => var_args.get(0).unwrap(),
+        n => {
+            return Err(arg_err(format!(
+                "{func_name}() expected at most {} args; got {}",
+                num_args + 1,
+                num_args + n
+            )));
+        }
+    };
+    let options_arg = options_arg.read().unwrap();
+    let Some(options_map) = options_arg.get_map_val() else {
+        return Err(arg_err(format!("{func_name}() expected options to be a Map")));
+    };
+    let mut options = Options::default();
+    for (key, val) in options_map.entries().read().unwrap().iter() {
+        let val = val.read().unwrap();
+        match key.as_str() {
+            "headers" => {
+                let Some(headers) = val.get_map_val() else {
+                    let msg = format!("{func_name}() expected headers to be a Map");
+                    return Err(arg_err(msg));
+                };
+                for (name, header_val) in headers.entries().read().unwrap().iter() {
+                    let header_val = header_val.read().unwrap();
+                    if let Some(header_val) = header_val.get_str_val() {
+                        options.headers.push((name.clone(), header_val.to_owned()));
+                    } else {
+                        let msg =
+                            format!("{func_name}() expected header {name} to be a Str");
+                        return Err(arg_err(msg));
+                    }
+                }
+            }
+            "timeout" => {
+                let seconds = if let Some(val) = val.get_int_val() {
+                    val.to_f64()
+                } else {
+                    val.get_float_val().copied()
+                };
+                match seconds {
+                    Some(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                        options.timeout = Some(Duration::from_secs_f64(seconds));
+                    }
+                    _ => {
+                        let msg = format!(
+                            "{func_name}() expected timeout to be a positive number"
+                        );
+                        return Err(arg_err(msg));
+                    }
+                }
+            }
+            _ => {
+                return Err(arg_err(format!(
+                    "{func_name}() got unknown option: {key}"
+                )));
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Send a request and read the whole response.
+fn send(method: &str, url: &str, body: Option<&[u8]>, options: Options) -> ObjectRef {
+    let mut builder = ureq::AgentBuilder::new();
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    let mut request = builder.build().request(method, url);
+    for (name, val) in options.headers.iter() {
+        request = request.set(name, val);
+    }
+
+    let result = match body {
+        Some(body) => request.send_bytes(body),
+        None => request.call(),
+    };
+
+    // Error statuses are returned as Responses rather than Errs.
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => {
+            return new::http_err(format!("{method} {url}: {err}"), new::nil());
+        }
+    };
+
+    let final_url = response.get_url().to_owned();
+    let status = response.status();
+
+    let mut headers = IndexMap::new();
+    for name in response.headers_names() {
+        let val = response.all(&name).join(", ");
+        headers.insert(name.to_ascii_lowercase(), val);
+    }
+
+    let mut data = vec![];
+    if let Err(err) = response.into_reader().read_to_end(&mut data) {
+        let msg = format!("{method} {url}: could not read response: {err}");
+        return new::http_err(msg, new::nil());
+    }
+
+    new::response(final_url, status, headers, data)
+}
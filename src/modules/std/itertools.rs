@@ -0,0 +1,433 @@
+//! Lazy iterator utilities. All of the iterators produced here are
+//! backed by Rust iterators, so items are only computed when `next()`
+//! or `peek()` is called on the resulting `Iterator`.
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+
+use indexmap::IndexMap;
+use num_bigint::BigInt;
+use once_cell::sync::Lazy;
+
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::iterator::IteratorSource;
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::{RuntimeErr, VM};
+
+pub static ITERTOOLS: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.itertools",
+        "<std.itertools>",
+        "Lazy iterator utilities",
+        &[
+            (
+                "zip",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "zip",
+                    None,
+                    &[""],
+                    "Zip iterables together.
+
+                    Produces a tuple for each position containing the item
+                    from each iterable at that position. Stops when the
+                    shortest iterable is exhausted.
+
+                    # Args
+
+                    - ...: Tuple | List | Iterator
+
+                    ",
+                    |_, args, _| {
+                        let sources = match iterables_from_var_args("zip", &args[0]) {
+                            Ok(sources) => sources,
+                            Err(err) => return Ok(err),
+                        };
+                        Ok(new::lazy_iterator(zip(sources)))
+                    },
+                ),
+            ),
+            (
+                "unzip",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "unzip",
+                    None,
+                    &["iterable"],
+                    "Unzip an iterable of tuples into a tuple of tuples.
+
+                    This is the inverse of `zip`. Because every item has to
+                    be visited to build the result, this is *not* lazy.
+
+                    # Args
+
+                    - iterable: Tuple | List | Iterator
+
+                    ",
+                    |_, args, _| {
+                        let source = match to_source("unzip", &args[0]) {
+                            Ok(source) => source,
+                            Err(err) => return Ok(err),
+                        };
+                        let mut columns: Vec<Vec<ObjectRef>> = vec![];
+                        for (i, item) in source.enumerate() {
+                            let item = item.read().unwrap();
+                            let tuple = if let Some(tuple) = item.down_to_tuple() {
+                                tuple
+                            } else {
+                                let msg = format!(
+                                    "unzip() expected item {i} to be a Tuple; got {}",
+                                    item.class().read().unwrap()
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            };
+                            if i == 0 {
+                                columns = vec![vec![]; tuple.len()];
+                            } else if tuple.len() != columns.len() {
+                                let msg = format!(
+                                    "unzip() expected item {i} to have length {}",
+                                    columns.len()
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                            for (column, obj) in columns.iter_mut().zip(tuple.iter()) {
+                                column.push(obj.clone());
+                            }
+                        }
+                        let columns = columns.into_iter().map(new::tuple).collect();
+                        Ok(new::tuple(columns))
+                    },
+                ),
+            ),
+            (
+                "chain",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "chain",
+                    None,
+                    &[""],
+                    "Iterate over each iterable in turn.
+
+                    # Args
+
+                    - ...: Tuple | List | Iterator
+
+                    ",
+                    |_, args, _| {
+                        let sources = match iterables_from_var_args("chain", &args[0]) {
+                            Ok(sources) => sources,
+                            Err(err) => return Ok(err),
+                        };
+                        Ok(new::lazy_iterator(Box::new(sources.into_iter().flatten())))
+                    },
+                ),
+            ),
+            (
+                "windows",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "windows",
+                    None,
+                    &["iterable", "n"],
+                    "Produce overlapping windows of size `n` as tuples.
+
+                    # Args
+
+                    - iterable: Tuple | List | Iterator
+                    - n: Int (must be greater than 0)
+
+                    ",
+                    |_, args, _| {
+                        let n = gen::use_arg_usize!(windows, n, args, 1);
+                        if n == 0 {
+                            return Ok(new::arg_err(
+                                "windows() expected n to be greater than 0",
+                                new::nil(),
+                            ));
+                        }
+                        let mut source = match to_source("windows", &args[0]) {
+                            Ok(source) => source,
+                            Err(err) => return Ok(err),
+                        };
+                        let mut window: VecDeque<ObjectRef> =
+                            VecDeque::with_capacity(n);
+                        let iter = std::iter::from_fn(move || {
+                            if window.len() == n {
+                                window.pop_front();
+                            }
+                            while window.len() < n {
+                                window.push_back(source.next()?);
+                            }
+                            Some(new::tuple(window.iter().cloned().collect()))
+                        });
+                        Ok(new::lazy_iterator(Box::new(iter)))
+                    },
+                ),
+            ),
+            (
+                "chunks",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "chunks",
+                    None,
+                    &["iterable", "n"],
+                    "Produce non-overlapping chunks of size `n` as tuples.
+
+                    The last chunk will be shorter than `n` if the number
+                    of items isn't evenly divisible by `n`.
+
+                    # Args
+
+                    - iterable: Tuple | List | Iterator
+                    - n: Int (must be greater than 0)
+
+                    ",
+                    |_, args, _| {
+                        let n = gen::use_arg_usize!(chunks, n, args, 1);
+                        if n == 0 {
+                            return Ok(new::arg_err(
+                                "chunks() expected n to be greater than 0",
+                                new::nil(),
+                            ));
+                        }
+                        let mut source = match to_source("chunks", &args[0]) {
+                            Ok(source) => source,
+                            Err(err) => return Ok(err),
+                        };
+                        let iter = std::iter::from_fn(move || {
+                            let chunk: Vec<ObjectRef> =
+                                source.by_ref().take(n).collect();
+                            if chunk.is_empty() {
+                                None
+                            } else {
+                                Some(new::tuple(chunk))
+                            }
+                        });
+                        Ok(new::lazy_iterator(Box::new(iter)))
+                    },
+                ),
+            ),
+            (
+                "count",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "count",
+                    None,
+                    &["start", "step"],
+                    "Count up from `start` by `step` forever.
+
+                    # Args
+
+                    - start: Int
+                    - step: Int
+
+                    ",
+                    |_, args, _| {
+                        let start = use_arg_int("count", "start", &args[0]);
+                        let step = use_arg_int("count", "step", &args[1]);
+                        let (mut current, step) = match (start, step) {
+                            (Ok(start), Ok(step)) => (start, step),
+                            (Err(err), _) | (_, Err(err)) => return Ok(err),
+                        };
+                        let iter = std::iter::from_fn(move || {
+                            let obj = new::int(current.clone());
+                            current += &step;
+                            Some(obj)
+                        });
+                        Ok(new::lazy_iterator(Box::new(iter)))
+                    },
+                ),
+            ),
+            (
+                "repeat",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "repeat",
+                    None,
+                    &["obj", "n"],
+                    "Produce `obj` `n` times.
+
+                    # Args
+
+                    - obj: Any
+                    - n: Int
+
+                    ",
+                    |_, args, _| {
+                        let n = gen::use_arg_usize!(repeat, n, args, 1);
+                        let obj = args[0].clone();
+                        let iter = std::iter::repeat_n(obj, n);
+                        Ok(new::lazy_iterator(Box::new(iter)))
+                    },
+                ),
+            ),
+            (
+                "count_by",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "count_by",
+                    None,
+                    &["iterable", "key_fn"],
+                    "Count items by key. This is *not* lazy.
+
+                    # Args
+
+                    - iterable: Tuple | List | Iterator
+                    - key_fn: Func
+
+                      Called with each item to get its key. Keys that
+                      aren't Strs are converted to Strs.
+
+                    # Returns
+
+                    Map: Key => number of items with that key, in the
+                    order keys were first seen
+
+                    ```
+                    → count_by((1, 2, 3), (n) => n % 2 == 0)
+                    {'false' => 2, 'true' => 1}
+                    ```
+
+                    ",
+                    |_, args, vm| {
+                        let source = match to_source("count_by", &args[0]) {
+                            Ok(source) => source,
+                            Err(err) => return Ok(err),
+                        };
+                        let mut counts: IndexMap<String, usize> = IndexMap::new();
+                        for item in source {
+                            let key = item_key(&args[1], item, vm)?;
+                            *counts.entry(key).or_default() += 1;
+                        }
+                        let counts = counts
+                            .into_iter()
+                            .map(|(key, count)| (key, new::int(count)))
+                            .collect();
+                        Ok(new::map(counts))
+                    },
+                ),
+            ),
+            (
+                "group_by",
+                new::intrinsic_func(
+                    "std.itertools",
+                    "group_by",
+                    None,
+                    &["iterable", "key_fn"],
+                    "Group items by key. This is *not* lazy.
+
+                    # Args
+
+                    - iterable: Tuple | List | Iterator
+                    - key_fn: Func: See `count_by()`
+
+                    # Returns
+
+                    Map: Key => List of items with that key, in the order
+                    keys were first seen
+
+                    ",
+                    |_, args, vm| {
+                        let source = match to_source("group_by", &args[0]) {
+                            Ok(source) => source,
+                            Err(err) => return Ok(err),
+                        };
+                        let mut groups: IndexMap<String, Vec<ObjectRef>> =
+                            IndexMap::new();
+                        for item in source {
+                            let key = item_key(&args[1], item.clone(), vm)?;
+                            groups.entry(key).or_default().push(item);
+                        }
+                        let groups = groups
+                            .into_iter()
+                            .map(|(key, items)| (key, new::list(items)))
+                            .collect();
+                        Ok(new::map(groups))
+                    },
+                ),
+            ),
+        ],
+    )
+});
+
+/// Convert an iterable object to a lazy source of items. Tuples and
+/// lists are snapshotted; ranges and iterators are pulled from on
+/// demand. If the object isn't iterable, an `Err` object is returned.
+fn to_source(
+    func_name: &str,
+    obj_ref: &ObjectRef,
+) -> Result<IteratorSource, ObjectRef> {
+    let obj = obj_ref.read().unwrap();
+    if obj.is_iterator() {
+        let obj_ref = obj_ref.clone();
+        Ok(Box::new(std::iter::from_fn(move || {
+            let mut obj = obj_ref.write().unwrap();
+            obj.down_to_iterator_mut().unwrap().next_item()
+        })))
+    } else if let Some(tuple) = obj.down_to_tuple() {
+        let items: Vec<ObjectRef> = tuple.iter().cloned().collect();
+        Ok(Box::new(items.into_iter()))
+    } else if let Some(list) = obj.down_to_list() {
+        Ok(Box::new(list.to_vec().into_iter()))
+    } else if let Some(range) = obj.down_to_range() {
+        Ok(range.source())
+    } else if let Some(set) = obj.down_to_set() {
+        Ok(Box::new(set.to_vec().into_iter()))
+    } else {
+        let msg = format!(
+            "{func_name}() expected an iterable (Tuple, List, Range, Set, or \
+            Iterator); got {}",
+            obj.class().read().unwrap()
+        );
+        Err(new::arg_err(msg, new::nil()))
+    }
+}
+
+/// Convert each item in a var args tuple to a lazy source.
+fn iterables_from_var_args(
+    func_name: &str,
+    var_args: &ObjectRef,
+) -> Result<Vec<IteratorSource>, ObjectRef> {
+    let var_args = var_args.read().unwrap();
+    let var_args = var_args.down_to_tuple().unwrap();
+    var_args.iter().map(|obj| to_source(func_name, obj)).collect()
+}
+
+fn zip(mut sources: Vec<IteratorSource>) -> IteratorSource {
+    if sources.is_empty() {
+        return Box::new(std::iter::empty());
+    }
+    Box::new(std::iter::from_fn(move || {
+        let mut items = Vec::with_capacity(sources.len());
+        for source in sources.iter_mut() {
+            items.push(source.next()?);
+        }
+        Some(new::tuple(items))
+    }))
+}
+
+/// Call `key_fn` with `item` to get its Map key. Keys that aren't
+/// Strs are converted using their string representation.
+fn item_key(
+    key_fn: &ObjectRef,
+    item: ObjectRef,
+    vm: &mut VM,
+) -> Result<String, RuntimeErr> {
+    vm.call(key_fn.clone(), vec![item])?;
+    let key = vm.pop_obj()?;
+    let key = key.read().unwrap();
+    Ok(key.get_str_val().map_or_else(|| key.to_string(), str::to_owned))
+}
+
+/// Extract an Int arg. If the arg isn't an Int, an `Err` object is
+/// returned.
+fn use_arg_int(
+    func_name: &str,
+    arg_name: &str,
+    arg: &ObjectRef,
+) -> Result<BigInt, ObjectRef> {
+    let arg = arg.read().unwrap();
+    BigInt::try_from(&*arg).map_err(|_| {
+        let msg = format!("{func_name}() expected {arg_name} to be an Int");
+        new::arg_err(msg, new::nil())
+    })
+}
@@ -0,0 +1,528 @@
+//! JSON module. `decode()` parses JSON text into FeInt objects and
+//! `encode()` serializes FeInt objects as JSON text. Both return an Err
+//! (`ErrType.json`) instead of raising when the input is invalid.
+//!
+//! JSON objects are decoded as Maps and arrays as Lists. Numbers are
+//! decoded as Ints unless they have a fraction or exponent, in which
+//! case they're decoded as Floats.
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::sync::{Arc, RwLock};
+
+use indexmap::IndexMap;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use once_cell::sync::Lazy;
+
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::RuntimeErr;
+
+/// Max nesting depth of arrays and objects. This also keeps `encode()`
+/// from recursing forever when a List or Map contains itself.
+const MAX_DEPTH: usize = 256;
+
+pub static JSON: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.json",
+        "<std.json>",
+        "JSON module",
+        &[
+            (
+                "decode",
+                new::intrinsic_func(
+                    "std.json",
+                    "decode",
+                    None,
+                    &["text"],
+                    "Decode JSON text.
+
+                    # Args
+
+                    - text: Str
+
+                    # Returns
+
+                    - Map | List | Str | Int | Float | Bool | nil
+                    - Err: If text isn't valid JSON
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let text = gen::use_arg_str!(decode, text, arg);
+                        Ok(match Decoder::new(text).decode() {
+                            Ok(obj) => obj,
+                            Err(msg) => new::json_err(msg, new::nil()),
+                        })
+                    },
+                ),
+            ),
+            (
+                "encode",
+                new::intrinsic_func(
+                    "std.json",
+                    "encode",
+                    None,
+                    &["obj", ""],
+                    "Encode obj as JSON text.
+
+                    Maps are encoded as objects and Lists and Tuples are
+                    encoded as arrays. Other types of objects can't be
+                    encoded.
+
+                    # Args
+
+                    - obj: Map | List | Tuple | Str | Int | Float | Bool | nil
+                    - indent?: Int | Str | nil
+
+                      If an Int or Str is passed, the JSON is pretty
+                      printed with each nested item on its own line,
+                      indented by that number of spaces or that Str.
+                      By default, the JSON is printed on one line.
+
+                    # Returns
+
+                    - Str
+                    - Err: If obj or any object nested in it can't be
+                      encoded
+
+                    ",
+                    |_, args, _| {
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        let indent = match var_args.len() {
+                            0 => None,
+                            1 => {
+                                let indent = var_args.get(0).unwrap();
+                                let indent = indent.read().unwrap();
+                                if let Some(n) = indent.get_int_val() {
+                                    if let Some(n) = n.to_usize() {
+                                        Some(" ".repeat(n))
+                                    } else {
+                                        let msg = "encode() expected indent to be >= 0";
+                                        return Ok(new::arg_err(msg, new::nil()));
+                                    }
+                                } else if let Some(indent) = indent.get_str_val() {
+                                    Some(indent.to_owned())
+                                } else if indent.is_nil() {
+                                    None
+                                } else {
+                                    let msg =
+                                        "encode() expected indent to be an Int or Str";
+                                    return Ok(new::arg_err(msg, new::nil()));
+                                }
+                            }
+                            n => {
+                                let msg = format!(
+                                    "encode() expected at most 2 args; got {}",
+                                    n + 1
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        let mut encoder = Encoder::new(indent);
+                        Ok(match encoder.encode(&args[0], 0) {
+                            Ok(()) => new::str(encoder.out),
+                            Err(msg) => new::json_err(msg, args[0].clone()),
+                        })
+                    },
+                ),
+            ),
+        ],
+    )
+});
+
+// Decoding ------------------------------------------------------------
+
+type DecodeResult<T> = Result<T, String>;
+
+struct Decoder<'a> {
+    chars: Peekable<Chars<'a>>,
+    // Location of the last char consumed
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { chars: text.chars().peekable(), line: 1, col: 0 }
+    }
+
+    fn decode(&mut self) -> DecodeResult<ObjectRef> {
+        let obj = self.value(0)?;
+        self.skip_whitespace();
+        if self.chars.peek().is_some() {
+            return Err(self.err("Unexpected data after JSON value"));
+        }
+        Ok(obj)
+    }
+
+    fn value(&mut self, depth: usize) -> DecodeResult<ObjectRef> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => Ok(new::str(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", new::bool(true)),
+            Some('f') => self.literal("false", new::bool(false)),
+            Some('n') => self.literal("null", new::nil()),
+            Some(c) => Err(self.err(format!("Unexpected character {c:?}"))),
+            None => Err(self.err("Unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> DecodeResult<ObjectRef> {
+        if depth == MAX_DEPTH {
+            return Err(self.err("Too much nesting"));
+        }
+        self.next(); // {
+        let mut entries = IndexMap::new();
+        self.skip_whitespace();
+        if self.next_if('}') {
+            return Ok(new::map(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err(self.err("Expected object key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.next_if(':') {
+                return Err(self.err("Expected ':'"));
+            }
+            let val = self.value(depth + 1)?;
+            entries.insert(key, val);
+            self.skip_whitespace();
+            if self.next_if('}') {
+                return Ok(new::map(entries));
+            } else if !self.next_if(',') {
+                return Err(self.err("Expected ',' or '}'"));
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> DecodeResult<ObjectRef> {
+        if depth == MAX_DEPTH {
+            return Err(self.err("Too much nesting"));
+        }
+        self.next(); // [
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.next_if(']') {
+            return Ok(new::list(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            if self.next_if(']') {
+                return Ok(new::list(items));
+            } else if !self.next_if(',') {
+                return Err(self.err("Expected ',' or ']'"));
+            }
+        }
+    }
+
+    fn string(&mut self) -> DecodeResult<String> {
+        self.next(); // "
+        let mut string = String::new();
+        loop {
+            match self.chars.peek().copied() {
+                Some('"') => {
+                    self.next();
+                    return Ok(string);
+                }
+                Some('\\') => {
+                    self.next();
+                    let c = match self.chars.peek().copied() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            self.next();
+                            string.push(self.unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.err("Invalid escape sequence")),
+                    };
+                    self.next();
+                    string.push(c);
+                }
+                Some(c) if c < ' ' => {
+                    return Err(self.err("Unescaped control character in string"));
+                }
+                Some(c) => {
+                    self.next();
+                    string.push(c);
+                }
+                None => return Err(self.err("Unterminated string")),
+            }
+        }
+    }
+
+    /// Decode the hex digits of a `\u` escape. Chars outside the BMP
+    /// are escaped as a surrogate pair (e.g., `\ud83d\ude00`).
+    fn unicode_escape(&mut self) -> DecodeResult<char> {
+        let code = self.hex_digits()?;
+        let code = if (0xd800..0xdc00).contains(&code) {
+            if !(self.next_if('\\') && self.next_if('u')) {
+                return Err(self.err("Expected low surrogate"));
+            }
+            let low = self.hex_digits()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.err("Invalid low surrogate"));
+            }
+            0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            code
+        };
+        char::from_u32(code).ok_or_else(|| self.err("Invalid unicode escape"))
+    }
+
+    fn hex_digits(&mut self) -> DecodeResult<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    self.next();
+                    code = code * 16 + digit;
+                }
+                None => return Err(self.err("Expected hex digit")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> DecodeResult<ObjectRef> {
+        let mut text = String::new();
+        let mut is_float = false;
+        if self.next_if('-') {
+            text.push('-');
+        }
+        if self.next_if('0') {
+            text.push('0');
+        } else if !self.push_digits(&mut text) {
+            return Err(self.err("Expected digit"));
+        }
+        if self.next_if('.') {
+            is_float = true;
+            text.push('.');
+            if !self.push_digits(&mut text) {
+                return Err(self.err("Expected digit"));
+            }
+        }
+        if let Some(e @ ('e' | 'E')) = self.chars.peek().copied() {
+            is_float = true;
+            self.next();
+            text.push(e);
+            if let Some(sign @ ('+' | '-')) = self.chars.peek().copied() {
+                self.next();
+                text.push(sign);
+            }
+            if !self.push_digits(&mut text) {
+                return Err(self.err("Expected digit"));
+            }
+        }
+        // The text is made up of valid digits, so parsing can't fail.
+        Ok(if is_float {
+            new::float(text.parse::<f64>().unwrap())
+        } else {
+            new::int(text.parse::<BigInt>().unwrap())
+        })
+    }
+
+    fn literal(&mut self, word: &str, obj: ObjectRef) -> DecodeResult<ObjectRef> {
+        for c in word.chars() {
+            if !self.next_if(c) {
+                return Err(self.err(format!("Expected {word}")));
+            }
+        }
+        Ok(obj)
+    }
+
+    /// Consume a run of digits, adding them to text. Returns whether
+    /// there were any digits.
+    fn push_digits(&mut self, text: &mut String) -> bool {
+        let len = text.len();
+        while let Some(c @ '0'..='9') = self.chars.peek().copied() {
+            self.next();
+            text.push(c);
+        }
+        text.len() > len
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 0;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn next_if(&mut self, c: char) -> bool {
+        if self.chars.peek() == Some(&c) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Create an error message pointing at the next char.
+    fn err<S: Into<String>>(&self, msg: S) -> String {
+        format!("{} at line {}, column {}", msg.into(), self.line, self.col + 1)
+    }
+}
+
+// Encoding ------------------------------------------------------------
+
+type EncodeResult = Result<(), String>;
+
+struct Encoder {
+    indent: Option<String>,
+    out: String,
+}
+
+impl Encoder {
+    fn new(indent: Option<String>) -> Self {
+        Self { indent, out: String::new() }
+    }
+
+    fn encode(&mut self, obj_ref: &ObjectRef, depth: usize) -> EncodeResult {
+        if depth == MAX_DEPTH {
+            return Err(
+                "Too much nesting (a List or Map may contain itself)".to_owned()
+            );
+        }
+
+        // NOTE: The lock on obj is released before encoding nested
+        //       objects in case it contains itself.
+        let obj = obj_ref.read().unwrap();
+        if let Some(list) = obj.down_to_list() {
+            let items = list.to_vec();
+            drop(obj);
+            return self.array(&items, depth);
+        } else if let Some(tuple) = obj.down_to_tuple() {
+            let items: Vec<ObjectRef> = tuple.iter().cloned().collect();
+            drop(obj);
+            return self.array(&items, depth);
+        } else if let Some(map) = obj.down_to_map() {
+            let entries: Vec<(String, ObjectRef)> = map
+                .entries()
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(key, val)| (key.clone(), val.clone()))
+                .collect();
+            drop(obj);
+            return self.object(&entries, depth);
+        }
+
+        if obj.is_nil() {
+            self.out.push_str("null");
+        } else if let Some(val) = obj.get_bool_val() {
+            self.out.push_str(if *val { "true" } else { "false" });
+        } else if let Some(val) = obj.get_int_val() {
+            write!(self.out, "{val}").unwrap();
+        } else if let Some(val) = obj.get_float_val() {
+            if !val.is_finite() {
+                return Err(format!("Can't encode {val} as JSON"));
+            }
+            // Debug formatting always includes a fraction or exponent
+            // so the number will be decoded as a Float.
+            write!(self.out, "{val:?}").unwrap();
+        } else if let Some(val) = obj.get_str_val() {
+            self.string(val);
+        } else {
+            let class = obj.class();
+            let class = class.read().unwrap();
+            return Err(format!("Can't encode object of type {class} as JSON"));
+        }
+        Ok(())
+    }
+
+    fn array(&mut self, items: &[ObjectRef], depth: usize) -> EncodeResult {
+        self.out.push('[');
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+            self.encode(item, depth + 1)?;
+        }
+        if !items.is_empty() {
+            self.newline(depth);
+        }
+        self.out.push(']');
+        Ok(())
+    }
+
+    fn object(
+        &mut self,
+        entries: &[(String, ObjectRef)],
+        depth: usize,
+    ) -> EncodeResult {
+        self.out.push('{');
+        for (i, (key, val)) in entries.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+            self.string(key);
+            self.out.push(':');
+            if self.indent.is_some() {
+                self.out.push(' ');
+            }
+            self.encode(val, depth + 1)?;
+        }
+        if !entries.is_empty() {
+            self.newline(depth);
+        }
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn string(&mut self, string: &str) {
+        self.out.push('"');
+        for c in string.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                '\u{8}' => self.out.push_str("\\b"),
+                '\u{c}' => self.out.push_str("\\f"),
+                c if c < ' ' => write!(self.out, "\\u{:04x}", c as u32).unwrap(),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    /// When pretty printing, start a new line indented to depth.
+    fn newline(&mut self, depth: usize) {
+        if let Some(indent) = &self.indent {
+            self.out.push('\n');
+            for _ in 0..depth {
+                self.out.push_str(indent);
+            }
+        }
+    }
+}
@@ -0,0 +1,427 @@
+//! Math module. Functions accept Ints and Floats. Functions that can
+//! return exact results for Ints (e.g., `abs()` and `pow()`) return
+//! Ints when they're given Ints; the others return Floats.
+use std::f64::consts;
+use std::sync::{Arc, RwLock};
+
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+use once_cell::sync::Lazy;
+
+use crate::types::float::{digits_arg, round_to_digits, whole_float_to_int};
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::RuntimeErr;
+
+/// Get a number arg as an f64 or return an arg Err if it isn't an Int
+/// or Float.
+macro_rules! use_number_arg {
+    ( $func_name:literal, $arg_name:literal, $args:ident, $index:literal ) => {{
+        let arg = gen::use_arg!($args, $index);
+        if let Some(val) = arg.get_float_val() {
+            *val
+        } else if let Some(val) = arg.get_int_val() {
+            val.to_f64().unwrap_or(f64::NAN)
+        } else {
+            let msg = format!(
+                "{}() expected {} to be an Int or Float; got {}",
+                $func_name,
+                $arg_name,
+                arg.class().read().unwrap()
+            );
+            return Ok(new::arg_err(msg, new::nil()));
+        }
+    }};
+}
+
+/// Get an Int arg or return an arg Err if it isn't an Int.
+macro_rules! use_int_arg {
+    ( $func_name:literal, $arg_name:literal, $arg:expr ) => {{
+        let arg = $arg.read().unwrap();
+        if let Some(val) = arg.get_int_val() {
+            val.clone()
+        } else {
+            let msg = format!("{}() expected {} to be an Int", $func_name, $arg_name);
+            return Ok(new::arg_err(msg, new::nil()));
+        }
+    }};
+}
+
+/// Create an entry for a function that takes a single number and
+/// returns a Float.
+fn float_func(
+    name: &'static str,
+    doc: &str,
+    func: fn(f64) -> f64,
+) -> (&'static str, ObjectRef) {
+    (
+        name,
+        new::intrinsic_func("std.math", name, None, &["x"], doc, move |_, args, _| {
+            let arg = gen::use_arg!(args, 0);
+            let x = if let Some(val) = arg.get_float_val() {
+                *val
+            } else if let Some(val) = arg.get_int_val() {
+                val.to_f64().unwrap_or(f64::NAN)
+            } else {
+                let msg = format!("{name}() expected x to be an Int or Float");
+                return Ok(new::arg_err(msg, new::nil()));
+            };
+            Ok(new::float(func(x)))
+        }),
+    )
+}
+
+pub static MATH: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.math",
+        "<std.math>",
+        "Math module",
+        &[
+            ("pi", new::float(consts::PI)),
+            ("e", new::float(consts::E)),
+            ("inf", new::float(f64::INFINITY)),
+            ("nan", new::float(f64::NAN)),
+            float_func(
+                "sqrt",
+                "Square root. Returns nan for negative numbers.",
+                f64::sqrt,
+            ),
+            float_func("sin", "Sine of an angle in radians.", f64::sin),
+            float_func("cos", "Cosine of an angle in radians.", f64::cos),
+            float_func("tan", "Tangent of an angle in radians.", f64::tan),
+            float_func("exp", "e raised to the power of x.", f64::exp),
+            (
+                "log",
+                new::intrinsic_func(
+                    "std.math",
+                    "log",
+                    None,
+                    &["x", ""],
+                    "Logarithm of x.
+
+                    # Args
+
+                    - x: Int | Float
+                    - base?: Int | Float
+
+                      Defaults to e (i.e., the natural logarithm).
+
+                    # Returns
+
+                    Float: nan for negative numbers and -inf for 0
+
+                    ",
+                    |_, args, _| {
+                        let x = use_number_arg!("log", "x", args, 0);
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        let result = match var_args.len() {
+                            0 => x.ln(),
+                            1 => {
+                                let base = var_args.get(0).unwrap();
+                                let base = base.read().unwrap();
+                                if let Some(base) = base.get_float_val() {
+                                    x.log(*base)
+                                } else if let Some(base) = base.get_int_val() {
+                                    x.log(base.to_f64().unwrap_or(f64::NAN))
+                                } else {
+                                    let msg =
+                                        "log() expected base to be an Int or Float";
+                                    return Ok(new::arg_err(msg, new::nil()));
+                                }
+                            }
+                            n => {
+                                let msg = format!(
+                                    "log() expected at most 2 args; got {}",
+                                    n + 1
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        Ok(new::float(result))
+                    },
+                ),
+            ),
+            (
+                "abs",
+                new::intrinsic_func(
+                    "std.math",
+                    "abs",
+                    None,
+                    &["x"],
+                    "Absolute value of x. Returns an Int for Ints.",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        Ok(if let Some(val) = arg.get_int_val() {
+                            new::int(val.abs())
+                        } else if let Some(val) = arg.get_float_val() {
+                            new::float(val.abs())
+                        } else {
+                            new::arg_err(
+                                "abs() expected x to be an Int or Float",
+                                new::nil(),
+                            )
+                        })
+                    },
+                ),
+            ),
+            (
+                "floor",
+                new::intrinsic_func(
+                    "std.math",
+                    "floor",
+                    None,
+                    &["x"],
+                    "Round x down to the nearest Int.",
+                    |_, args, _| {
+                        if args[0].read().unwrap().is_int() {
+                            return Ok(args[0].clone());
+                        }
+                        let x = use_number_arg!("floor", "x", args, 0);
+                        Ok(whole_float_to_int("floor", x.floor()))
+                    },
+                ),
+            ),
+            (
+                "ceil",
+                new::intrinsic_func(
+                    "std.math",
+                    "ceil",
+                    None,
+                    &["x"],
+                    "Round x up to the nearest Int.",
+                    |_, args, _| {
+                        if args[0].read().unwrap().is_int() {
+                            return Ok(args[0].clone());
+                        }
+                        let x = use_number_arg!("ceil", "x", args, 0);
+                        Ok(whole_float_to_int("ceil", x.ceil()))
+                    },
+                ),
+            ),
+            (
+                "round",
+                new::intrinsic_func(
+                    "std.math",
+                    "round",
+                    None,
+                    &["x", ""],
+                    "Round x to the nearest Int or to a number of decimal
+                    places. Halves are rounded away from zero. Ints are
+                    returned as is.
+
+                    # Args
+
+                    - x: Int | Float
+                    - digits?: Int: See `Float.round()`
+
+                    # Returns
+
+                    - Int: If x is an Int or digits isn't specified
+                    - Float: If x is a Float and digits is specified
+
+                    ",
+                    |_, args, _| {
+                        let digits = match digits_arg("round", &args[1]) {
+                            Ok(digits) => digits,
+                            Err(err) => return Ok(err),
+                        };
+                        if args[0].read().unwrap().is_int() {
+                            return Ok(args[0].clone());
+                        }
+                        let x = use_number_arg!("round", "x", args, 0);
+                        Ok(match digits {
+                            None => whole_float_to_int("round", x.round()),
+                            Some(digits) => new::float(round_to_digits(x, digits)),
+                        })
+                    },
+                ),
+            ),
+            (
+                "min",
+                new::intrinsic_func(
+                    "std.math",
+                    "min",
+                    None,
+                    &["x", ""],
+                    "Get the smallest of the args. If more than one arg is
+                    the smallest, the first is returned.",
+                    |_, args, _| {
+                        let mut result = args[0].clone();
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        for arg in var_args.iter() {
+                            if arg
+                                .read()
+                                .unwrap()
+                                .less_than(&*result.read().unwrap())?
+                            {
+                                result = arg.clone();
+                            }
+                        }
+                        Ok(result)
+                    },
+                ),
+            ),
+            (
+                "max",
+                new::intrinsic_func(
+                    "std.math",
+                    "max",
+                    None,
+                    &["x", ""],
+                    "Get the largest of the args. If more than one arg is
+                    the largest, the first is returned.",
+                    |_, args, _| {
+                        let mut result = args[0].clone();
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        for arg in var_args.iter() {
+                            if arg
+                                .read()
+                                .unwrap()
+                                .greater_than(&*result.read().unwrap())?
+                            {
+                                result = arg.clone();
+                            }
+                        }
+                        Ok(result)
+                    },
+                ),
+            ),
+            (
+                "clamp",
+                new::intrinsic_func(
+                    "std.math",
+                    "clamp",
+                    None,
+                    &["x", "lo", "hi"],
+                    "Restrict x to the range [lo, hi].
+
+                    # Returns
+
+                    - lo: If x is less than lo
+                    - hi: If x is greater than hi
+                    - x: Otherwise
+                    - Err: If lo is greater than hi
+
+                    ",
+                    |_, args, _| {
+                        let x = args[0].read().unwrap();
+                        let lo = args[1].read().unwrap();
+                        let hi = args[2].read().unwrap();
+                        Ok(if lo.greater_than(&*hi)? {
+                            let msg =
+                                "clamp() expected lo to be less than or equal to hi";
+                            new::arg_err(msg, new::nil())
+                        } else if x.less_than(&*lo)? {
+                            args[1].clone()
+                        } else if x.greater_than(&*hi)? {
+                            args[2].clone()
+                        } else {
+                            args[0].clone()
+                        })
+                    },
+                ),
+            ),
+            (
+                "gcd",
+                new::intrinsic_func(
+                    "std.math",
+                    "gcd",
+                    None,
+                    &["a", "b"],
+                    "Greatest common divisor of a and b, which must be Ints.
+                    The result is always non-negative.",
+                    |_, args, _| {
+                        let mut a = use_int_arg!("gcd", "a", args[0]);
+                        let mut b = use_int_arg!("gcd", "b", args[1]);
+                        while !b.is_zero() {
+                            let rem = &a % &b;
+                            a = b;
+                            b = rem;
+                        }
+                        Ok(new::int(a.abs()))
+                    },
+                ),
+            ),
+            (
+                "pow",
+                new::intrinsic_func(
+                    "std.math",
+                    "pow",
+                    None,
+                    &["base", "exp", ""],
+                    "Raise base to the power of exp.
+
+                    # Args
+
+                    - base: Int | Float
+                    - exp: Int | Float
+                    - modulus?: Int
+
+                      If specified, the result is `(base ^ exp) % modulus`,
+                      computed without the intermediate result. base and
+                      exp must be Ints, and exp must not be negative.
+
+                    # Returns
+
+                    - Int: If base and exp are Ints and exp isn't negative
+                    - Float: Otherwise
+
+                    ",
+                    |_, args, _| {
+                        let var_args = args[2].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        match var_args.len() {
+                            0 => (),
+                            1 => {
+                                let base = use_int_arg!("pow", "base", args[0]);
+                                let exp = use_int_arg!("pow", "exp", args[1]);
+                                let modulus = var_args.get(0).unwrap();
+                                let modulus = use_int_arg!("pow", "modulus", modulus);
+                                return Ok(mod_pow(base, exp, modulus));
+                            }
+                            n => {
+                                let msg = format!(
+                                    "pow() expected at most 3 args; got {}",
+                                    n + 2
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        }
+                        let base_arg = gen::use_arg!(args, 0);
+                        let exp_arg = gen::use_arg!(args, 1);
+                        if let (Some(base), Some(exp)) =
+                            (base_arg.get_int_val(), exp_arg.get_int_val())
+                        {
+                            if !exp.is_negative() {
+                                return Ok(if let Some(exp) = exp.to_u32() {
+                                    new::int(base.pow(exp))
+                                } else {
+                                    new::arg_err(
+                                        "pow() exponent is too large",
+                                        new::nil(),
+                                    )
+                                });
+                            }
+                        }
+                        let base = use_number_arg!("pow", "base", args, 0);
+                        let exp = use_number_arg!("pow", "exp", args, 1);
+                        Ok(new::float(base.powf(exp)))
+                    },
+                ),
+            ),
+        ],
+    )
+});
+
+fn mod_pow(base: BigInt, exp: BigInt, modulus: BigInt) -> ObjectRef {
+    if exp.is_negative() {
+        new::arg_err("pow() expected exp to not be negative with a modulus", new::nil())
+    } else if modulus.is_zero() {
+        new::arg_err("pow() expected modulus to not be 0", new::nil())
+    } else {
+        new::int(base.modpow(&exp, &modulus))
+    }
+}
@@ -1,5 +1,57 @@
+pub use self::collections::COLLECTIONS;
+#[cfg(feature = "std-full")]
+pub use self::fs::FS;
+#[cfg(feature = "std-full")]
+pub use self::glob::GLOB;
+#[cfg(feature = "std-full")]
+pub use self::hash::HASH;
+#[cfg(feature = "http")]
+pub use self::http::HTTP;
+pub use self::r#async::ASYNC;
 pub use self::std::STD;
+#[cfg(feature = "std-min")]
+pub use itertools::ITERTOOLS;
+#[cfg(feature = "std-min")]
+pub use json::JSON;
+#[cfg(feature = "std-min")]
+pub use math::MATH;
+#[cfg(feature = "std-full")]
+pub use os::OS;
+#[cfg(feature = "std-full")]
 pub use proc::PROC;
+#[cfg(feature = "std-min")]
+pub use random::RANDOM;
+pub use system::SYSTEM;
+#[cfg(feature = "std-full")]
+pub use thread::THREAD;
+#[cfg(feature = "std-full")]
+pub use time::TIME;
 
+mod r#async;
+mod collections;
+#[cfg(feature = "std-full")]
+mod fs;
+#[cfg(feature = "std-full")]
+mod glob;
+#[cfg(feature = "std-full")]
+mod hash;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "std-min")]
+mod itertools;
+#[cfg(feature = "std-min")]
+mod json;
+#[cfg(feature = "std-min")]
+mod math;
+#[cfg(feature = "std-full")]
+mod os;
+#[cfg(feature = "std-full")]
 mod proc;
+#[cfg(feature = "std-min")]
+mod random;
 mod std;
+mod system;
+#[cfg(feature = "std-full")]
+mod thread;
+#[cfg(feature = "std-full")]
+mod time;
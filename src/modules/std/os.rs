@@ -0,0 +1,267 @@
+//! Operating system module. Provides access to environment variables,
+//! the current working directory, and the current process. `run()` is
+//! the same function as `std.proc.run()`; see `std.proc` for other ways
+//! to run subprocesses.
+use std::env;
+use std::sync::{Arc, RwLock};
+
+use indexmap::IndexMap;
+use num_traits::ToPrimitive;
+use once_cell::sync::Lazy;
+
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::RuntimeErr;
+
+use super::PROC;
+
+pub static OS: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.os",
+        "<std.os>",
+        "Operating system module",
+        &[
+            ("platform", new::str(env::consts::OS)),
+            ("run", PROC.read().unwrap().get_global("run").unwrap()),
+            (
+                "get_env",
+                new::intrinsic_func(
+                    "std.os",
+                    "get_env",
+                    None,
+                    &["name", ""],
+                    "Get the value of an environment variable.
+
+                    # Args
+
+                    - name: Str
+                    - default?: Any
+
+                    # Returns
+
+                    Str | Any: The value or, if the variable isn't set,
+                    default, which defaults to nil
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let name = gen::use_arg_str!(get_env, name, arg);
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        if var_args.len() > 1 {
+                            let msg = format!(
+                                "get_env() expected at most 2 args; got {}",
+                                var_args.len() + 1
+                            );
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                        Ok(match env::var_os(name) {
+                            Some(val) => new::str(val.to_string_lossy()),
+                            None => var_args.get(0).unwrap_or_else(new::nil),
+                        })
+                    },
+                ),
+            ),
+            (
+                "set_env",
+                new::intrinsic_func(
+                    "std.os",
+                    "set_env",
+                    None,
+                    &["name", "val"],
+                    "Set an environment variable for the current process
+                    and any subprocesses it runs.
+
+                    # Args
+
+                    - name: Str
+                    - val: Str
+
+                    # Returns
+
+                    - nil: If the variable was set
+                    - Err: If name is empty or contains `=` or a NUL
+                      char or val contains a NUL char
+
+                    ",
+                    |_, args, _| {
+                        let name_arg = gen::use_arg!(args, 0);
+                        let name = gen::use_arg_str!(set_env, name, name_arg);
+                        let val_arg = gen::use_arg!(args, 1);
+                        let val = gen::use_arg_str!(set_env, val, val_arg);
+                        if name.is_empty() || name.contains(['=', '\0']) {
+                            let msg = format!("set_env() got invalid name: {name:?}");
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                        if val.contains('\0') {
+                            let msg = "set_env() expected val to not contain NUL";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                        env::set_var(name, val);
+                        Ok(new::nil())
+                    },
+                ),
+            ),
+            (
+                "remove_env",
+                new::intrinsic_func(
+                    "std.os",
+                    "remove_env",
+                    None,
+                    &["name"],
+                    "Remove an environment variable from the current
+                    process. Does nothing if the variable isn't set.",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let name = gen::use_arg_str!(remove_env, name, arg);
+                        if name.is_empty() || name.contains(['=', '\0']) {
+                            let msg =
+                                format!("remove_env() got invalid name: {name:?}");
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                        env::remove_var(name);
+                        Ok(new::nil())
+                    },
+                ),
+            ),
+            (
+                "env",
+                new::intrinsic_func(
+                    "std.os",
+                    "env",
+                    None,
+                    &[],
+                    "Get all environment variables.
+
+                    # Returns
+
+                    Map: A copy of the environment. Modifying the Map
+                    doesn't modify the environment.
+
+                    ",
+                    |_, _, _| {
+                        let vars: IndexMap<String, ObjectRef> = env::vars_os()
+                            .map(|(name, val)| {
+                                (
+                                    name.to_string_lossy().into_owned(),
+                                    new::str(val.to_string_lossy()),
+                                )
+                            })
+                            .collect();
+                        Ok(new::map(vars))
+                    },
+                ),
+            ),
+            (
+                "cwd",
+                new::intrinsic_func(
+                    "std.os",
+                    "cwd",
+                    None,
+                    &[],
+                    "Get the current working directory.
+
+                    # Returns
+
+                    Str | Err
+
+                    ",
+                    |_, _, _| {
+                        Ok(match env::current_dir() {
+                            Ok(path) => new::str(path.to_string_lossy()),
+                            Err(err) => new::file_not_found_err(
+                                format!("Could not get current directory: {err}"),
+                                new::nil(),
+                            ),
+                        })
+                    },
+                ),
+            ),
+            (
+                "chdir",
+                new::intrinsic_func(
+                    "std.os",
+                    "chdir",
+                    None,
+                    &["path"],
+                    "Change the current working directory.
+
+                    # Args
+
+                    - path: Str
+
+                    # Returns
+
+                    - nil: If the directory was changed
+                    - Err: If path doesn't exist or isn't a directory
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let path = gen::use_arg_str!(chdir, path, arg);
+                        Ok(match env::set_current_dir(path) {
+                            Ok(()) => new::nil(),
+                            Err(err) => new::file_not_found_err(
+                                format!("{path}: {err}"),
+                                new::nil(),
+                            ),
+                        })
+                    },
+                ),
+            ),
+            (
+                "pid",
+                new::intrinsic_func(
+                    "std.os",
+                    "pid",
+                    None,
+                    &[],
+                    "Get the ID of the current process.",
+                    |_, _, _| Ok(new::int(std::process::id())),
+                ),
+            ),
+            (
+                "exit",
+                new::intrinsic_func(
+                    "std.os",
+                    "exit",
+                    None,
+                    &[""],
+                    "Exit the program.
+
+                    # Args
+
+                    - code?: Int
+
+                      The exit code, which defaults to 0. Codes outside
+                      of the range 0 to 255 are treated as 0.
+
+                    ",
+                    |_, args, vm| {
+                        let var_args = args[0].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        let code = match var_args.len() {
+                            0 => 0,
+                            1 => {
+                                let code = var_args.get(0).unwrap();
+                                let code = code.read().unwrap();
+                                if let Some(code) = code.get_int_val() {
+                                    code.to_u8().unwrap_or(0)
+                                } else {
+                                    let msg = "exit() expected code to be an Int";
+                                    return Ok(new::arg_err(msg, new::nil()));
+                                }
+                            }
+                            n => {
+                                let msg =
+                                    format!("exit() expected at most 1 arg; got {n}");
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        vm.halt(code).map(|()| new::nil())
+                    },
+                ),
+            ),
+        ],
+    )
+});
@@ -1,18 +1,243 @@
-//use std::process::Command;
+//! Process module. `run()` runs a program directly with a list of args,
+//! which is the safest way to run a command. `shell()` runs a command
+//! line via the system shell; use `quote()` to build command lines from
+//! untrusted input.
+use std::io;
+use std::process::{Command, Output};
 use std::sync::{Arc, RwLock};
 
 use once_cell::sync::Lazy;
 
-use crate::types::gen::obj_ref_t;
-use crate::types::{new, Module};
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::vm::RuntimeErr;
 
 pub static PROC: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
     new::intrinsic_module(
         "std.proc",
         "<std.proc>",
-        "Proc module",
+        "Process module",
         &[
-            // TODO:
+            (
+                "run",
+                new::intrinsic_func(
+                    "std.proc",
+                    "run",
+                    None,
+                    &["cmd", "args"],
+                    "Run a program and wait for it to finish.
+
+                    The program is run directly rather than via the shell,
+                    so args are passed through as is and don't need to be
+                    quoted.
+
+                    # Args
+
+                    - cmd: Str
+                    - args: List<Str> | Tuple<Str>
+
+                    # Returns
+
+                    - Map: With keys status (Int or nil if the process
+                      was killed by a signal), stdout, and stderr
+                    - Err: If the program couldn't be started
+
+                    ",
+                    |_, args, _| {
+                        let cmd_arg = gen::use_arg!(args, 0);
+                        let cmd = gen::use_arg_str!(run, cmd, cmd_arg);
+                        let args_arg = gen::use_arg!(args, 1);
+                        let items = if let Some(list) = args_arg.down_to_list() {
+                            list.to_vec()
+                        } else if let Some(tuple) = args_arg.down_to_tuple() {
+                            tuple.iter().cloned().collect()
+                        } else {
+                            let msg = "run() expected args to be a List or Tuple";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        };
+                        let mut cmd_args = vec![];
+                        for item in items {
+                            let item = item.read().unwrap();
+                            if let Some(arg) = item.get_str_val() {
+                                cmd_args.push(arg.to_owned());
+                            } else {
+                                let msg = format!(
+                                    "run() expected args to contain only Strs; got {}",
+                                    item.class().read().unwrap()
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        }
+                        let result = Command::new(cmd).args(cmd_args).output();
+                        Ok(output_to_map(cmd, result))
+                    },
+                ),
+            ),
+            (
+                "shell",
+                new::intrinsic_func(
+                    "std.proc",
+                    "shell",
+                    None,
+                    &["cmdline"],
+                    "Run a command line via the system shell and wait for
+                    it to finish.
+
+                    The command line is passed to `sh -c` or, on Windows,
+                    `cmd /C`. Any values interpolated into the command
+                    line should be quoted with `quote()`. Prefer `run()`
+                    when shell features aren't needed.
+
+                    # Args
+
+                    - cmdline: Str
+
+                    # Returns
+
+                    Map | Err: Same as `run()`
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let cmdline = gen::use_arg_str!(shell, cmdline, arg);
+                        let result = shell_command(cmdline).output();
+                        Ok(output_to_map(cmdline, result))
+                    },
+                ),
+            ),
+            (
+                "quote",
+                new::intrinsic_func(
+                    "std.proc",
+                    "quote",
+                    None,
+                    &["arg", ""],
+                    "Quote arg so it's passed as a single arg when
+                    included in a command line.
+
+                    # Args
+
+                    - arg: Str
+                    - style?: Str
+
+                      Either \"posix\" or \"windows\". Defaults to the
+                      style of the current platform.
+
+                      NOTE: Windows quoting follows the rules used to
+                      split args by most programs. It doesn't escape
+                      `cmd` metacharacters such as `&` and `|`.
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let arg = gen::use_arg_str!(quote, arg, arg);
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        let windows = match var_args.len() {
+                            0 => cfg!(windows),
+                            1 => {
+                                let style = var_args.get(0).unwrap();
+                                let style = style.read().unwrap();
+                                match style.get_str_val() {
+                                    Some("posix") => false,
+                                    Some("windows") => true,
+                                    _ => {
+                                        let msg = "quote() expected style to be \
+                                            \"posix\" or \"windows\"";
+                                        return Ok(new::arg_err(msg, new::nil()));
+                                    }
+                                }
+                            }
+                            n => {
+                                let msg = format!(
+                                    "quote() expected at most 2 args; got {}",
+                                    n + 1
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        Ok(new::str(if windows {
+                            quote_windows(arg)
+                        } else {
+                            quote_posix(arg)
+                        }))
+                    },
+                ),
+            ),
         ],
     )
 });
+
+#[cfg(not(windows))]
+fn shell_command(cmdline: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmdline);
+    command
+}
+
+/// NOTE: The command line is passed through as is since `cmd` doesn't
+///       parse its args the way `Command` quotes them.
+#[cfg(windows)]
+fn shell_command(cmdline: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut command = Command::new("cmd");
+    command.arg("/C").raw_arg(cmdline);
+    command
+}
+
+fn output_to_map(cmd: &str, result: io::Result<Output>) -> ObjectRef {
+    match result {
+        Ok(output) => new::map_from_keys_and_vals(
+            vec!["status".to_owned(), "stdout".to_owned(), "stderr".to_owned()],
+            vec![
+                output.status.code().map_or_else(new::nil, new::int),
+                new::str(String::from_utf8_lossy(&output.stdout)),
+                new::str(String::from_utf8_lossy(&output.stderr)),
+            ],
+        ),
+        Err(err) => new::proc_err(format!("{cmd}: {err}"), new::nil()),
+    }
+}
+
+/// Quote arg for a POSIX shell. Args that contain only safe chars are
+/// left as is. Otherwise, the arg is wrapped in single quotes, which
+/// disable all special chars, and each embedded single quote is
+/// replaced with `'"'"'`.
+fn quote_posix(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./-_".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\"'\"'"))
+    }
+}
+
+/// Quote arg using the rules of `CommandLineToArgvW`. Backslashes are
+/// only special when they precede a double quote, in which case they
+/// must be doubled.
+fn quote_windows(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.to_owned();
+    }
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    let mut backslashes = 0;
+    quoted.push('"');
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module};
+use crate::util::deterministic;
+use crate::vm::RuntimeErr;
+
+/// Generator state along with the deterministic mode generation it was
+/// seeded in. It's seeded lazily and reseeded when deterministic mode
+/// is set (see `deterministic::generation`) so that the seed reflects
+/// the current mode.
+static STATE: Mutex<Option<(usize, u64)>> = Mutex::new(None);
+
+/// SplitMix64: small, fast, and good enough for non-cryptographic use.
+fn next_u64() -> u64 {
+    let generation = deterministic::generation();
+    let mut state = STATE.lock().unwrap();
+    if !matches!(*state, Some((g, _)) if g == generation) {
+        *state = Some((generation, deterministic::random_seed()));
+    }
+    let (_, state) = state.as_mut().unwrap();
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub static RANDOM: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.random",
+        "<std.random>",
+        "Pseudo-random numbers (NOT suitable for cryptographic use)",
+        &[
+            (
+                "random",
+                new::intrinsic_func(
+                    "std.random",
+                    "random",
+                    None,
+                    &[],
+                    "Get a random Float in the range [0, 1).",
+                    |_, _, _| {
+                        // Use the high 53 bits to fill the Float mantissa.
+                        let val = (next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+                        Ok(new::float(val))
+                    },
+                ),
+            ),
+            (
+                "seed",
+                new::intrinsic_func(
+                    "std.random",
+                    "seed",
+                    None,
+                    &["seed"],
+                    "Reseed the generator.
+
+                    # Args
+
+                    - seed: Int
+
+                    ",
+                    |_, args, _| {
+                        let seed = gen::use_arg_usize!(seed, seed, args, 0);
+                        let generation = deterministic::generation();
+                        *STATE.lock().unwrap() = Some((generation, seed as u64));
+                        Ok(new::nil())
+                    },
+                ),
+            ),
+        ],
+    )
+});
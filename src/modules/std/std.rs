@@ -1,9 +1,10 @@
 //! Root of the std module hierarchy containing builtins/prelude.
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 
 use once_cell::sync::Lazy;
 
-use crate::types::{self, gen, new};
+use crate::types::{self, gen, new, FromObjectRef};
 use crate::vm::RuntimeErr;
 
 pub static STD: Lazy<gen::obj_ref_t!(types::module::Module)> = Lazy::new(|| {
@@ -14,10 +15,15 @@ pub static STD: Lazy<gen::obj_ref_t!(types::module::Module)> = Lazy::new(|| {
         &[
             ("Type", types::class::TYPE_TYPE.clone()),
             ("Always", types::always::ALWAYS_TYPE.clone()),
+            ("Atom", types::atom::ATOM_TYPE.clone()),
             ("Bool", types::bool::BOOL_TYPE.clone()),
             ("BoundFunc", types::bound_func::BOUND_FUNC_TYPE.clone()),
+            ("ByteBuffer", types::byte_buffer::BYTE_BUFFER_TYPE.clone()),
+            ("Bytes", types::bytes::BYTES_TYPE.clone()),
             ("IntrinsicFunc", types::intrinsic_func::INTRINSIC_FUNC_TYPE.clone()),
+            ("Channel", types::channel::CHANNEL_TYPE.clone()),
             ("Closure", types::closure::CLOSURE_TYPE.clone()),
+            ("DateTime", types::date_time::DATE_TIME_TYPE.clone()),
             ("Err", types::err::ERR_TYPE.clone()),
             ("ErrType", types::err_type::ERR_TYPE_TYPE.clone()),
             ("File", types::file::FILE_TYPE.clone()),
@@ -29,7 +35,13 @@ pub static STD: Lazy<gen::obj_ref_t!(types::module::Module)> = Lazy::new(|| {
             ("Map", types::map::MAP_TYPE.clone()),
             ("Module", types::module::MODULE_TYPE.clone()),
             ("Nil", types::nil::NIL_TYPE.clone()),
+            ("Range", types::range::RANGE_TYPE.clone()),
+            ("Response", types::response::RESPONSE_TYPE.clone()),
+            ("Set", types::set::SET_TYPE.clone()),
             ("Str", types::str::STR_TYPE.clone()),
+            ("Task", types::task::TASK_TYPE.clone()),
+            ("TempPath", types::temp_path::TEMP_PATH_TYPE.clone()),
+            ("Thread", types::thread::THREAD_TYPE.clone()),
             ("Tuple", types::tuple::TUPLE_TYPE.clone()),
             (
                 "new_type",
@@ -55,6 +67,107 @@ pub static STD: Lazy<gen::obj_ref_t!(types::module::Module)> = Lazy::new(|| {
                     },
                 ),
             ),
+            (
+                "new_enum",
+                new::intrinsic_func(
+                    "std",
+                    "new_enum",
+                    None,
+                    &["module", "name", "variants"],
+                    "Make a new enum type. This is the runtime equivalent of
+                    `Name = enum (A, B, ...)`.
+
+                    # Args
+
+                    - module: Module
+                    - name: Str
+                    - variants: List<Str> | Tuple<Str>
+
+                    ",
+                    |_, args, _| {
+                        let module = args[0].clone();
+                        let name_arg = gen::use_arg!(args, 1);
+                        let name = gen::use_arg_str!(new_enum, name, name_arg);
+                        let variants = match Vec::<String>::from_obj_ref(&args[2]) {
+                            Ok(variants) if is_valid_enum(&variants) => variants,
+                            _ => {
+                                let msg = "new_enum() expected variants to be a \
+                                    non-empty List or Tuple of unique Strs";
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        Ok(new::enum_type(module, name, &variants))
+                    },
+                ),
+            ),
+            (
+                "err",
+                new::intrinsic_func(
+                    "std",
+                    "err",
+                    None,
+                    &["type", "msg", ""],
+                    "Make a new Err. This is a shortcut for `Err.new()`.
+
+                    # Args
+
+                    - type: ErrType
+                    - msg: Str
+                    - data?: Any
+
+                    ```
+                    → e = err(ErrType.arg, $'bad value: {x}', {'value': x})
+                    → e.err.value
+                    ```
+
+                    ",
+                    |_, args, _| Ok(types::err::err_from_args("err()", &args)),
+                ),
+            ),
+            (
+                "divmod",
+                new::intrinsic_func(
+                    "std",
+                    "divmod",
+                    None,
+                    &["a", "b"],
+                    "Divide a by b, rounding toward negative infinity, and
+                    get the quotient and remainder.
+
+                    # Args
+
+                    - a: Int | Float
+                    - b: Int | Float
+
+                    # Returns
+
+                    (Int, Int | Float): The quotient is always an Int,
+                    the same as `a // b`. The remainder has the same sign
+                    as b so that `a == b * quotient + remainder`.
+
+                    ```
+                    → divmod(7, 2)
+                    (3, 1)
+                    → divmod(-7, 2)
+                    (-4, 1)
+                    ```
+
+                    ",
+                    |_, args, _| {
+                        let a = args[0].read().unwrap();
+                        let b = args[1].read().unwrap();
+                        let quotient = a.floor_div(&*b)?;
+                        let product = b.mul(&*quotient.read().unwrap())?;
+                        let remainder = a.sub(&*product.read().unwrap())?;
+                        Ok(new::tuple(vec![quotient, remainder]))
+                    },
+                ),
+            ),
         ],
     )
 });
+
+fn is_valid_enum(variants: &[String]) -> bool {
+    let unique: HashSet<&String> = variants.iter().collect();
+    !variants.is_empty() && unique.len() == variants.len()
+}
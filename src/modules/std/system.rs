@@ -0,0 +1,90 @@
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use indexmap::IndexMap;
+
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module, ObjectRef};
+use crate::version;
+use crate::vm::RuntimeErr;
+
+/// NOTE: This is extended with the globals from `system.fi` during
+///       bootstrap.
+pub static SYSTEM: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.system",
+        "<std.system>",
+        "System module",
+        &[
+            ("version", new::str(version::VERSION)),
+            ("build", build_info_map()),
+            (
+                "import",
+                new::intrinsic_func(
+                    "std.system",
+                    "import",
+                    None,
+                    &["name"],
+                    "Import module by name at runtime.
+
+                This is useful when the name of the module isn't known until
+                runtime (e.g., for plugins). The module is loaded via the
+                host's import resolver if it isn't already loaded.
+
+                # Args
+
+                - name: Str
+
+                # Returns
+
+                Module | Err
+
+                ",
+                    |_, args, vm| {
+                        let name_arg = gen::use_arg!(args, 0);
+                        let name = gen::use_arg_str!(import, name, name_arg).to_owned();
+                        drop(name_arg);
+                        match vm.import_module(name.as_str()) {
+                            Ok(module) => Ok(module),
+                            Err(err) => err.to_err_obj().ok_or(err),
+                        }
+                    },
+                ),
+            ),
+            (
+                "feature",
+                new::intrinsic_func(
+                    "std.system",
+                    "feature",
+                    None,
+                    &["name"],
+                    "Check whether the interpreter supports a language feature.
+
+                    # Args
+
+                    - name: Str
+
+                    # Returns
+
+                    Bool
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let name = gen::use_arg_str!(feature, name, arg);
+                        Ok(new::bool(version::has_feature(name)))
+                    },
+                ),
+            ),
+        ],
+    )
+});
+
+fn build_info_map() -> ObjectRef {
+    let mut info = IndexMap::new();
+    info.insert("git_hash".to_owned(), new::str(version::GIT_HASH));
+    info.insert("date".to_owned(), new::str(version::BUILD_DATE));
+    info.insert("rustc".to_owned(), new::str(version::RUSTC_VERSION));
+    new::map(info)
+}
@@ -0,0 +1,249 @@
+//! Threads and channels. Each thread runs a copy of a function in its
+//! own VM, which starts with copies of the spawning VM's globals, so
+//! mutable objects (e.g., Lists) modified in one thread aren't seen in
+//! others. `Atom`s and `Channel`s are the exception. They're shared so
+//! they can be used to update shared state and to pass objects between
+//! threads.
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use once_cell::sync::Lazy;
+
+use crate::types::gen::{self, obj_ref, obj_ref_t};
+use crate::types::thread::ThreadResult;
+use crate::types::{new, Module, Namespace, ObjectRef, ObjectTrait};
+use crate::vm::{RuntimeErr, VM};
+
+/// The VM and function for a new thread. The function and globals are
+/// copies (see `Copier`), so the only objects shared with the spawning
+/// thread are ones that are safe to share.
+struct ThreadStart {
+    vm: VM,
+    func: ObjectRef,
+}
+
+impl ThreadStart {
+    /// Copy func and the globals for a new thread. If they refer to an
+    /// object that can't be copied, an Err object is returned instead.
+    fn new(vm: &VM, func: &ObjectRef) -> Result<Self, ObjectRef> {
+        let mut copier = Copier::default();
+        let func = copier.copy(func)?;
+        let globals = vm
+            .ctx
+            .globals()
+            .iter()
+            .map(|(name, obj)| Ok((*name, copier.copy(obj)?)))
+            .collect::<Result<_, ObjectRef>>()?;
+        Ok(Self { vm: vm.fork(globals), func })
+    }
+
+    fn run(mut self) -> ThreadResult {
+        match self.vm.call(self.func, vec![]).and_then(|_| self.vm.pop_obj()) {
+            Ok(result) => ThreadResult::Ok(result),
+            Err(err) => match err.to_err_obj() {
+                Some(err_obj) => ThreadResult::Raised(err_obj),
+                None => ThreadResult::Failed(err.to_string()),
+            },
+        }
+    }
+}
+
+pub static THREAD: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.thread",
+        "<std.thread>",
+        "Threads and channels",
+        &[
+            (
+                "spawn",
+                new::intrinsic_func(
+                    "std.thread",
+                    "spawn",
+                    None,
+                    &["func"],
+                    "Run func in a new thread.
+
+                    func is called with no args. func, the objects it
+                    captures, and the current globals are copied into
+                    the thread, except for Atoms and Channels, which
+                    are shared, and immutable objects, functions, types,
+                    and modules, which don't need to be copied.
+                    Iterators can't be copied, so an Err is returned if
+                    any of these objects refer to one.
+
+                    # Args
+
+                    - func: Func
+
+                    # Returns
+
+                    Thread: Use `join()` to wait for func's result
+                    Err: If func or the globals refer to an Iterator
+
+                    ",
+                    |_, args, vm| {
+                        let arg = gen::use_arg!(args, 0);
+                        if !(arg.is_func() || arg.is_closure() || arg.is_bound_func()) {
+                            let msg = "spawn() expected func to be a function";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                        let start = match ThreadStart::new(vm, &args[0]) {
+                            Ok(start) => start,
+                            Err(err) => return Ok(err),
+                        };
+                        let handle = thread::spawn(move || start.run());
+                        Ok(new::thread(handle))
+                    },
+                ),
+            ),
+            (
+                "channel",
+                new::intrinsic_func(
+                    "std.thread",
+                    "channel",
+                    None,
+                    &[],
+                    "Create a Channel for sending objects between threads.",
+                    |_, _, _| Ok(new::channel()),
+                ),
+            ),
+        ],
+    )
+});
+
+// Copying -------------------------------------------------------------
+
+/// Deep copies objects for a new thread. Mutable containers, custom
+/// objects, cells, and the objects that hold them (e.g., closures and
+/// tuples) are copied. Everything else is shared, which is safe since
+/// it's either immutable (e.g., Ints and functions), designed to be
+/// shared (`Atom`s and `Channel`s), or synchronized internally (e.g.,
+/// `PriorityQueue`s and `File`s).
+///
+/// Iterators are rejected. They can't be copied without consuming
+/// them, and a shared iterator would be advanced by both threads.
+///
+/// Each object is copied once, so objects that are referenced from
+/// multiple places, including cyclic references, are referenced the
+/// same way in the copies.
+#[derive(Default)]
+struct Copier {
+    copies: HashMap<*const (), ObjectRef>,
+}
+
+impl Copier {
+    fn copy(&mut self, obj_ref: &ObjectRef) -> Result<ObjectRef, ObjectRef> {
+        if let Some(copy) = self.copied(obj_ref) {
+            return Ok(copy);
+        }
+        let obj = obj_ref.read().unwrap();
+        if obj.down_to_iterator().is_some() {
+            let msg = "spawn() can't copy an Iterator into a thread";
+            return Err(new::type_err(msg, new::nil()));
+        }
+        // Containers are added to the copies before their items are
+        // copied so that references back to them are resolved to the
+        // copies. The lock on the original is released first since
+        // the items may refer back to it.
+        let copy = if let Some(list) = obj.down_to_list() {
+            let items = list.to_vec();
+            drop(obj);
+            let copy = self.add(obj_ref, new::list(vec![]));
+            for item in items {
+                let item = self.copy(&item)?;
+                copy.read().unwrap().down_to_list().unwrap().push(item);
+            }
+            copy
+        } else if let Some(map) = obj.down_to_map() {
+            let entries = map.entries().read().unwrap().clone();
+            drop(obj);
+            let copy = self.add(obj_ref, new::map(Default::default()));
+            for (key, val) in entries {
+                let val = self.copy(&val)?;
+                copy.read().unwrap().down_to_map().unwrap().insert(key, val);
+            }
+            copy
+        } else if let Some(set) = obj.down_to_set() {
+            let items = set.to_vec();
+            drop(obj);
+            let copy = self.add(obj_ref, new::set(vec![]));
+            for item in items {
+                let item = self.copy(&item)?;
+                copy.read().unwrap().down_to_set().unwrap().add(item);
+            }
+            copy
+        } else if let Some(deque) = obj.down_to_deque() {
+            let items = deque.to_vec();
+            drop(obj);
+            let copy = self.add(obj_ref, new::deque(vec![]));
+            for item in items {
+                let item = self.copy(&item)?;
+                copy.read().unwrap().down_to_deque().unwrap().push_back(item);
+            }
+            copy
+        } else if let Some(buffer) = obj.down_to_byte_buffer() {
+            let copy = new::byte_buffer(buffer.to_vec());
+            drop(obj);
+            self.add(obj_ref, copy)
+        } else if let Some(custom) = obj.down_to_custom_obj() {
+            let attrs: Vec<_> =
+                custom.ns().iter().map(|(n, v)| (n.clone(), v.clone())).collect();
+            let copy = obj_ref!(custom.with_attrs(Namespace::default()));
+            drop(obj);
+            let copy = self.add(obj_ref, copy);
+            for (name, val) in attrs {
+                let val = self.copy(&val)?;
+                copy.write().unwrap().ns_mut().insert(name, val);
+            }
+            copy
+        } else if obj.down_to_cell().is_some() {
+            let val = obj.get_cell_val().unwrap();
+            drop(obj);
+            let copy = self.add(obj_ref, new::cell());
+            let val = self.copy(&val)?;
+            copy.write().unwrap().down_to_cell_mut().unwrap().set_value(val);
+            copy
+        } else if let Some(closure) = obj.down_to_closure() {
+            let (func, captured) = (closure.func(), closure.captured());
+            drop(obj);
+            let captured = self.copy(&captured)?;
+            // The closure may have been copied while copying the vars
+            // it captures (e.g., if it captures itself).
+            match self.copied(obj_ref) {
+                Some(copy) => copy,
+                None => self.add(obj_ref, new::closure(func, captured)),
+            }
+        } else if let Some(bound_func) = obj.down_to_bound_func() {
+            let (func, this) = (bound_func.func(), bound_func.this());
+            drop(obj);
+            let func = self.copy(&func)?;
+            let this = self.copy(&this)?;
+            match self.copied(obj_ref) {
+                Some(copy) => copy,
+                None => self.add(obj_ref, new::bound_func(func, this)),
+            }
+        } else if let Some(tuple) = obj.down_to_tuple() {
+            let items: Vec<_> = tuple.iter().cloned().collect();
+            drop(obj);
+            let items =
+                items.iter().map(|item| self.copy(item)).collect::<Result<_, _>>()?;
+            match self.copied(obj_ref) {
+                Some(copy) => copy,
+                None => self.add(obj_ref, new::tuple(items)),
+            }
+        } else {
+            obj_ref.clone()
+        };
+        Ok(copy)
+    }
+
+    fn copied(&self, obj_ref: &ObjectRef) -> Option<ObjectRef> {
+        self.copies.get(&(Arc::as_ptr(obj_ref) as *const ())).cloned()
+    }
+
+    fn add(&mut self, obj_ref: &ObjectRef, copy: ObjectRef) -> ObjectRef {
+        self.copies.insert(Arc::as_ptr(obj_ref) as *const (), copy.clone());
+        copy
+    }
+}
@@ -0,0 +1,194 @@
+//! Time module. See the `DateTime` type for date/time arithmetic,
+//! comparison, and formatting.
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use num_traits::ToPrimitive;
+use once_cell::sync::Lazy;
+
+use crate::types::date_time;
+use crate::types::gen::{self, obj_ref_t};
+use crate::types::{new, Module};
+use crate::util::deterministic;
+use crate::vm::RuntimeErr;
+
+/// Reference point for `ticks()`.
+static START: Lazy<Instant> = Lazy::new(Instant::now);
+
+pub static TIME: Lazy<obj_ref_t!(Module)> = Lazy::new(|| {
+    new::intrinsic_module(
+        "std.time",
+        "<std.time>",
+        "Time module",
+        &[
+            (
+                "now",
+                new::intrinsic_func(
+                    "std.time",
+                    "now",
+                    None,
+                    &[],
+                    "Get the current time as a UTC DateTime.
+
+                    In deterministic mode, this returns a frozen time.
+
+                    ",
+                    |_, _, _| {
+                        let now = date_time::from_system_time(deterministic::now());
+                        Ok(new::date_time(now))
+                    },
+                ),
+            ),
+            (
+                "timestamp",
+                new::intrinsic_func(
+                    "std.time",
+                    "timestamp",
+                    None,
+                    &[],
+                    "Get the current time as seconds since the Unix epoch.
+
+                    In deterministic mode, this returns a frozen time.
+
+                    ",
+                    |_, _, _| {
+                        let secs = match deterministic::now().duration_since(UNIX_EPOCH)
+                        {
+                            Ok(duration) => duration.as_secs_f64(),
+                            Err(err) => -err.duration().as_secs_f64(),
+                        };
+                        Ok(new::float(secs))
+                    },
+                ),
+            ),
+            (
+                "ticks",
+                new::intrinsic_func(
+                    "std.time",
+                    "ticks",
+                    None,
+                    &[],
+                    "Get seconds elapsed on a monotonic clock as a Float.
+                    Unlike `now()`, ticks never go backwards, so they're
+                    suitable for measuring elapsed time. Only the
+                    difference between two ticks is meaningful.
+
+                    In deterministic mode, this always returns 0.0.
+
+                    ",
+                    |_, _, _| {
+                        let ticks = if deterministic::is_enabled() {
+                            0.0
+                        } else {
+                            START.elapsed().as_secs_f64()
+                        };
+                        Ok(new::float(ticks))
+                    },
+                ),
+            ),
+            (
+                "sleep",
+                new::intrinsic_func(
+                    "std.time",
+                    "sleep",
+                    None,
+                    &["seconds"],
+                    "Block the current thread for the specified number of
+                    seconds. See `std.async.sleep()` to wait without
+                    blocking other tasks.
+
+                    # Args
+
+                    - seconds: Int | Float
+
+                    ",
+                    |_, args, _| {
+                        let arg = gen::use_arg!(args, 0);
+                        let seconds = if let Some(val) = arg.get_int_val() {
+                            val.to_f64()
+                        } else {
+                            arg.get_float_val().copied()
+                        };
+                        match seconds {
+                            Some(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+                                thread::sleep(Duration::from_secs_f64(seconds));
+                                Ok(new::nil())
+                            }
+                            _ => {
+                                let msg =
+                                    "sleep() expected seconds to be a non-negative number";
+                                Ok(new::arg_err(msg, new::nil()))
+                            }
+                        }
+                    },
+                ),
+            ),
+            (
+                "parse",
+                new::intrinsic_func(
+                    "std.time",
+                    "parse",
+                    None,
+                    &["text", ""],
+                    "Parse a DateTime.
+
+                    # Args
+
+                    - text: Str
+                    - pattern?: Str
+
+                      A strftime-like pattern (e.g., `%Y-%m-%d %H:%M`).
+                      If not specified, text must be in RFC 3339 format
+                      (e.g., `2000-01-01T00:00:00Z`). If the pattern
+                      doesn't include an offset (`%z`), the DateTime is
+                      in UTC. If it doesn't include a time, the time is
+                      midnight.
+
+                    # Returns
+
+                    - DateTime
+                    - Err: If text doesn't match the pattern
+
+                    ",
+                    |_, args, _| {
+                        let text_arg = gen::use_arg!(args, 0);
+                        let text = gen::use_arg_str!(parse, text, text_arg);
+                        let var_args = args[1].read().unwrap();
+                        let var_args = var_args.down_to_tuple().unwrap();
+                        let pattern_ref = match var_args.len() {
+                            0 => None,
+                            1 => var_args.get(0),
+                            n => {
+                                let msg = format!(
+                                    "parse() expected at most 2 args; got {}",
+                                    n + 1
+                                );
+                                return Ok(new::arg_err(msg, new::nil()));
+                            }
+                        };
+                        let pattern_arg =
+                            pattern_ref.as_ref().map(|arg| arg.read().unwrap());
+                        let pattern = match &pattern_arg {
+                            Some(arg) => match arg.get_str_val() {
+                                Some(pattern) => Some(pattern),
+                                None => {
+                                    let msg = "parse() expected pattern to be a Str";
+                                    return Ok(new::arg_err(msg, new::nil()));
+                                }
+                            },
+                            None => None,
+                        };
+                        Ok(match date_time::parse(text, pattern) {
+                            Ok(value) => new::date_time(value),
+                            Err(err) => new::time_err(
+                                format!("Could not parse {text:?}: {err}"),
+                                new::nil(),
+                            ),
+                        })
+                    },
+                ),
+            ),
+        ],
+    )
+});
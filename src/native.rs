@@ -0,0 +1,88 @@
+//! Native modules defined by host applications.
+//!
+//! A native module's functions are Rust closures that take and return
+//! [`Value`]s. Each closure is wrapped as an intrinsic function, so
+//! scripts call it like any other function once the module has been
+//! imported:
+//!
+//! ```ignore
+//! exe.register_native_module("app", |module| {
+//!     module.doc("Host application API");
+//!     module.func("greet", &["name"], |args| {
+//!         Ok(format!("Hello, {}", args[0]).into())
+//!     });
+//! });
+//! ```
+//!
+//! A var args param (an empty param name) collects the remaining args
+//! into a `Value::List`. When a closure returns `Err(message)`, the
+//! function returns an `Err` object of type `ErrType.native`.
+use std::sync::Arc;
+
+use crate::types::err_type::ErrKind;
+use crate::types::result::CallResult;
+use crate::types::{new, Args, FromObjectRef, IntoObjectRef, ObjectRef};
+use crate::value::Value;
+
+type NativeFn = dyn Fn(Vec<Value>) -> Result<Value, String> + Send + Sync;
+
+/// Passed to the `build` callback of `Executor::register_native_module`
+/// to add functions to a native module.
+pub struct NativeModuleBuilder {
+    name: String,
+    doc: String,
+    funcs: Vec<(String, Vec<String>, Arc<NativeFn>)>,
+}
+
+impl NativeModuleBuilder {
+    pub(crate) fn new(name: &str) -> Self {
+        Self { name: name.to_owned(), doc: String::new(), funcs: vec![] }
+    }
+
+    pub fn doc<S: Into<String>>(&mut self, doc: S) -> &mut Self {
+        self.doc = doc.into();
+        self
+    }
+
+    /// Add a function. Its params work the same way as the params of
+    /// other intrinsic functions.
+    pub fn func<F>(&mut self, name: &str, params: &[&str], func: F) -> &mut Self
+    where
+        F: Fn(Vec<Value>) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        let params = params.iter().map(|p| p.to_string()).collect();
+        self.funcs.push((name.to_owned(), params, Arc::new(func)));
+        self
+    }
+
+    pub(crate) fn build(self) -> ObjectRef {
+        let module_name = self.name.as_str();
+        let funcs: Vec<(&str, ObjectRef)> = self
+            .funcs
+            .iter()
+            .map(|(name, params, func)| {
+                let params: Vec<&str> = params.iter().map(|p| p.as_str()).collect();
+                let func = func.clone();
+                let func_obj = new::intrinsic_func(
+                    module_name,
+                    name,
+                    None,
+                    &params,
+                    "",
+                    move |_, args, _| call_native_func(&*func, args),
+                );
+                (name.as_str(), func_obj)
+            })
+            .collect();
+        let path = format!("<{module_name}>");
+        new::intrinsic_module(module_name, &path, &self.doc, &funcs)
+    }
+}
+
+fn call_native_func(func: &NativeFn, args: Args) -> CallResult {
+    let args = args.iter().map(Value::from_obj_ref).collect::<Result<_, _>>()?;
+    match func(args) {
+        Ok(value) => Ok(value.into_obj_ref()),
+        Err(message) => Ok(new::err(ErrKind::Native, message, new::nil())),
+    }
+}
@@ -12,6 +12,8 @@ pub enum UnaryOperator {
     Negate,
     Not,
     AsBool,
+    /// Convert to repr Str (`{x!r}` in a format string).
+    Repr,
 }
 
 impl UnaryOperator {
@@ -34,6 +36,7 @@ impl fmt::Display for UnaryOperator {
             Self::Negate => "-",
             Self::Not => "!",
             Self::AsBool => "!!",
+            Self::Repr => "!r",
         };
         write!(f, "{string}")
     }
@@ -55,7 +58,11 @@ pub enum BinaryOperator {
     Mod,
     Add,
     Sub,
+    BitOr,
+    BitAnd,
     Dot,
+    Range,
+    RangeInclusive,
 }
 
 impl BinaryOperator {
@@ -68,7 +75,11 @@ impl BinaryOperator {
             Token::Percent => Self::Mod,
             Token::Plus => Self::Add,
             Token::Minus => Self::Sub,
+            Token::Pipe => Self::BitOr,
+            Token::Ampersand => Self::BitAnd,
             Token::Dot => Self::Dot,
+            Token::DotDot => Self::Range,
+            Token::DotDotEqual => Self::RangeInclusive,
             _ => return Err(format!("Unknown binary operator: {token}")),
         };
         Ok(op)
@@ -85,7 +96,11 @@ impl fmt::Display for BinaryOperator {
             Self::Mod => "%",
             Self::Add => "+",
             Self::Sub => "-",
+            Self::BitOr => "|",
+            Self::BitAnd => "&",
             Self::Dot => ".",
+            Self::Range => "..",
+            Self::RangeInclusive => "..=",
         };
         write!(f, "{string}")
     }
@@ -110,6 +125,8 @@ pub enum CompareOperator {
     LessThanOrEqual,
     GreaterThan,
     GreaterThanOrEqual,
+    In,
+    NotIn,
 }
 
 impl CompareOperator {
@@ -125,6 +142,8 @@ impl CompareOperator {
             Token::LessThanOrEqual => Self::LessThanOrEqual,
             Token::GreaterThan => Self::GreaterThan,
             Token::GreaterThanOrEqual => Self::GreaterThanOrEqual,
+            Token::In => Self::In,
+            Token::NotIn => Self::NotIn,
             _ => return Err(format!("Unknown comparison operator: {token}")),
         };
         Ok(op)
@@ -134,7 +153,7 @@ impl CompareOperator {
 impl fmt::Display for CompareOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let string = match self {
-            Self::Is => "$",
+            Self::Is => "$$",
             Self::IsNot => "$!",
             Self::IsTypeEqual => "===",
             Self::IsNotTypeEqual => "!==",
@@ -144,6 +163,8 @@ impl fmt::Display for CompareOperator {
             Self::LessThanOrEqual => "<=",
             Self::GreaterThan => ">",
             Self::GreaterThanOrEqual => ">=",
+            Self::In => "in",
+            Self::NotIn => "not in",
         };
         write!(f, "{string}")
     }
@@ -161,6 +182,7 @@ pub enum ShortCircuitCompareOperator {
     And,
     Or,
     NilOr,
+    NilSafeDot,
 }
 
 impl ShortCircuitCompareOperator {
@@ -169,6 +191,7 @@ impl ShortCircuitCompareOperator {
             Token::And => Self::And,
             Token::Or => Self::Or,
             Token::NilOr => Self::NilOr,
+            Token::NilSafeDot => Self::NilSafeDot,
             _ => {
                 return Err(format!(
                     "Unknown short-circuiting comparison operator: {token}"
@@ -185,6 +208,7 @@ impl fmt::Display for ShortCircuitCompareOperator {
             Self::And => "&&",
             Self::Or => "||",
             Self::NilOr => "??",
+            Self::NilSafeDot => "?.",
         };
         write!(f, "{string}")
     }
@@ -216,6 +240,16 @@ impl InplaceOperator {
         };
         Ok(op)
     }
+
+    /// Get the binary operator corresponding to this inplace operator.
+    pub fn binary_op(&self) -> BinaryOperator {
+        match self {
+            Self::Mul => BinaryOperator::Mul,
+            Self::Div => BinaryOperator::Div,
+            Self::Add => BinaryOperator::Add,
+            Self::Sub => BinaryOperator::Sub,
+        }
+    }
 }
 
 impl fmt::Display for InplaceOperator {
@@ -1,5 +1,5 @@
-pub(crate) use parser::Parser;
-pub(crate) use result::{ParseErr, ParseErrKind};
+pub(crate) use parser::{parse_tokens, Parser};
+pub(crate) use result::{ParseErr, ParseErrKind, ParseWarning, ParseWarningKind};
 
 mod parser;
 mod precedence;
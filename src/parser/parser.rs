@@ -1,11 +1,13 @@
 //! Parse a stream of tokens into an AST.
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::iter::{Iterator, Peekable};
+use std::mem;
 
 use crate::ast;
 use crate::format::FormatStrToken;
+use crate::op::BinaryOperator;
 use crate::parser::result::StatementResult;
-use crate::scanner::{ScanErr, ScanTokenResult, Token, TokenWithLocation};
+use crate::scanner::{is_keyword, ScanErr, ScanTokenResult, Token, TokenWithLocation};
 use crate::source::Location;
 
 use super::precedence::{
@@ -13,7 +15,8 @@ use super::precedence::{
 };
 use super::result::{
     BlockResult, BoolResult, ExprResult, NextInfixResult, NextTokenResult, ParseErr,
-    ParseErrKind, ParseResult, PeekTokenResult, StatementsResult,
+    ParseErrKind, ParseResult, ParseWarning, ParseWarningKind, PeekTokenResult,
+    StatementsResult,
 };
 
 /// Parse tokens and return the resulting AST or error.
@@ -32,6 +35,16 @@ pub struct Parser<I: Iterator<Item = ScanTokenResult>> {
     expr_level: u32,
     func_level: u32,
     loop_level: u32,
+    // Whether the innermost function being parsed is async
+    in_async_func: bool,
+    // Depth of the scopes (blocks, functions, etc) the current token is
+    // in, used to find where to resume parsing after an error.
+    scope_depth: isize,
+    errors: Vec<ParseErr>,
+    warnings: Vec<ParseWarning>,
+    // Variants of the enums defined so far by type name, used to
+    // check whether matches on enum variants are exhaustive.
+    enums: HashMap<String, Vec<String>>,
 }
 
 impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
@@ -44,19 +57,44 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             expr_level: 0,
             func_level: 0,
             loop_level: 0,
+            in_async_func: false,
+            scope_depth: 0,
+            errors: vec![],
+            warnings: vec![],
+            enums: HashMap::new(),
         }
     }
 
+    /// Warnings found while parsing.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
     // Parse entry point -----------------------------------------------
 
     /// Parse token stream a produce a module, which is a sequence of
     /// statements.
+    ///
+    /// When a statement can't be parsed, the error is recorded and
+    /// parsing resumes with the next statement so that all errors can
+    /// be reported at once. Scan errors stop parsing since the tokens
+    /// after them can't be trusted.
     pub fn parse(&mut self) -> ParseResult {
         log::trace!("BEGIN MODULE");
-        let statements = self.statements()?;
-        let module = ast::Module::new(statements);
-        log::trace!("END MODULE");
-        Ok(module)
+        let result = self.statements();
+        let mut errors = mem::take(&mut self.errors);
+        match result {
+            Ok(statements) if errors.is_empty() => {
+                let module = ast::Module::new(statements);
+                log::trace!("END MODULE");
+                Ok(module)
+            }
+            Ok(_) => Err(errors),
+            Err(err) => {
+                errors.push(err);
+                Err(errors)
+            }
+        }
     }
 
     // Grammar ---------------------------------------------------------
@@ -69,12 +107,59 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             if !self.has_tokens()? || self.peek_token_is(&Token::ScopeEnd)? {
                 break;
             }
-            let statement = self.statement()?;
-            statements.push(statement);
+            let levels = (
+                self.statement_level,
+                self.expr_level,
+                self.func_level,
+                self.loop_level,
+                self.in_async_func,
+            );
+            let scope_depth = self.scope_depth;
+            match self.statement() {
+                Ok(statement) => statements.push(statement),
+                Err(err) if matches!(err.kind, ParseErrKind::ScanErr(_)) => {
+                    return Err(err);
+                }
+                Err(err) => {
+                    self.errors.push(err);
+                    (
+                        self.statement_level,
+                        self.expr_level,
+                        self.func_level,
+                        self.loop_level,
+                        self.in_async_func,
+                    ) = levels;
+                    self.synchronize(scope_depth)?;
+                }
+            }
         }
         Ok(statements)
     }
 
+    /// Skip the rest of a statement that couldn't be parsed. Tokens are
+    /// skipped up to and including the end of the statement or up to
+    /// the end of the scope containing the statement, which is at
+    /// `scope_depth`. Any scopes opened by the statement are skipped.
+    fn synchronize(&mut self, scope_depth: isize) -> Result<(), ParseErr> {
+        use Token::{EndOfStatement, ScopeEnd};
+        loop {
+            let token = match self.peek_token()? {
+                Some(token) => token.token.clone(),
+                None => return Ok(()),
+            };
+            if self.scope_depth <= scope_depth {
+                if token == ScopeEnd {
+                    return Ok(());
+                }
+                if token == EndOfStatement {
+                    self.next_token()?;
+                    return Ok(());
+                }
+            }
+            self.next_token()?;
+        }
+    }
+
     /// Get the next statement (which might be an expression). In
     /// certain cases, multiple statements may be returned (e.g.,
     /// loops).
@@ -83,13 +168,20 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         log::trace!("BEGIN STATEMENT level {level}");
         self.statement_level += 1;
         use Token::{
-            Break, Continue, EndOfStatement, Halt, Import, Jump, Label, Print, Return,
+            Break, Comma, Continue, EndOfStatement, From, Global, Halt, Import, Jump,
+            Label, Print, Return,
         };
         let token = self.expect_next_token()?;
         let start = token.start;
+        if is_keyword(&token.token) && self.peek_token_is(&Token::Equal)? {
+            let name = token.token.to_string();
+            return Err(self.err(ParseErrKind::KeywordAsIdent(start, name)));
+        }
         let statement = match token.token {
             Break => self.break_(start)?,
             Continue => self.continue_(start, token.end)?,
+            From => self.import_from(start)?,
+            Global => self.global(start)?,
             Import => self.import(start)?,
             Jump => self.jump(start)?,
             Label(name) => self.label(name, start)?,
@@ -97,8 +189,14 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             Halt => self.halt(start)?,
             Print => self.print(start)?,
             _ => {
-                self.lookahead_queue.push_front(token);
+                self.push_back_token(token);
                 let expr = self.expr(0)?;
+                let expr =
+                    if expr.assignment().is_none() && self.peek_token_is(&Comma)? {
+                        self.destructuring_assignment(expr)?
+                    } else {
+                        expr
+                    };
                 log::trace!("STATEMENT EXPR = {expr:?}");
                 log::trace!("NEXT TOKEN = {:?}", self.peek_token()?);
                 let end = expr.end;
@@ -111,6 +209,32 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(statement)
     }
 
+    /// Handle destructuring assignment without parens, such as
+    /// `a, b = b, a`. This is equivalent to `(a, b) = (b, a)`. The
+    /// first target has already been parsed.
+    fn destructuring_assignment(&mut self, first: ast::Expr) -> ExprResult {
+        let start = first.start;
+        let mut targets = vec![first];
+        while self.next_token_is(&Token::Comma)? {
+            // Parse target *without* consuming `=`.
+            targets.push(self.expr(1)?);
+        }
+        let targets_end = targets.last().unwrap().end;
+        self.expect_token(&Token::Equal)?;
+        let mut values = vec![self.expr(0)?];
+        while self.next_token_is(&Token::Comma)? {
+            values.push(self.expr(0)?);
+        }
+        let (values_start, end) = (values[0].start, values.last().unwrap().end);
+        let value = if values.len() == 1 {
+            values.pop().unwrap()
+        } else {
+            ast::Expr::new_tuple(values, values_start, end)
+        };
+        let lhs = ast::Expr::new_tuple(targets, start, targets_end);
+        Ok(ast::Expr::new_assignment(lhs, value, start, end))
+    }
+
     /// Handle jump statement.
     fn jump(&mut self, start: Location) -> StatementResult {
         if let Some(ident_token) = self.next_token()? {
@@ -159,6 +283,35 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(ast::Statement::new_return(expr, start, end))
     }
 
+    /// Handle `global <name>, ...`, ensuring it's contained in a
+    /// function.
+    fn global(&mut self, start: Location) -> StatementResult {
+        if self.func_level == 0 {
+            return Err(self.err(ParseErrKind::UnexpectedGlobal(start)));
+        }
+        let mut names = vec![];
+        let mut end;
+        loop {
+            if let Some(token) = self.peek_token_if(is_keyword)? {
+                let (loc, name) = (token.start, token.token.to_string());
+                return Err(self.err(ParseErrKind::KeywordAsIdent(loc, name)));
+            }
+            let token = self.expect_next_token()?;
+            let name = match token.token {
+                Token::Ident(name)
+                | Token::ConstIdent(name)
+                | Token::TypeIdent(name) => name,
+                _ => return Err(self.err(ParseErrKind::ExpectedIdent(token.start))),
+            };
+            end = token.end;
+            names.push(name);
+            if !self.next_token_is(&Token::Comma)? {
+                break;
+            }
+        }
+        Ok(ast::Statement::new_global(names, start, end))
+    }
+
     /// Handle `$halt`. Arg should be an int in the u8 range.
     fn halt(&mut self, start: Location) -> StatementResult {
         let expr = self.expr(0)?;
@@ -173,30 +326,69 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(ast::Statement::new_print(expr, start, end))
     }
 
-    /// Handle `import`.
+    /// Handle `import`. Imports at the top level of a module are loaded
+    /// before the module is executed. Other imports (e.g., in function
+    /// bodies) are loaded lazily at runtime.
     fn import(&mut self, start: Location) -> StatementResult {
-        if self.func_level != 0 {
-            return Err(self.err(ParseErrKind::UnexpectedImport(start)));
-        }
         let name_expr = self.expr(0)?;
         if let Some(name) = name_expr.is_ident() {
             let end = name_expr.end;
-            let as_name = if self.next_token_is(&Token::As)? {
-                let as_name_expr = self.expr(0)?;
-                if let Some(as_name) = as_name_expr.is_ident() {
-                    Some(as_name)
-                } else {
-                    return Err(self.err(ParseErrKind::ExpectedIdent(self.loc())));
-                }
-            } else {
-                None
-            };
+            let as_name = self.import_as_name()?;
             Ok(ast::Statement::new_import(name, as_name, start, end))
         } else {
             Err(self.err(ParseErrKind::ExpectedIdent(self.loc())))
         }
     }
 
+    /// Handle `from <path> import <name>, ...`. Each name can
+    /// optionally be aliased using `as`.
+    fn import_from(&mut self, start: Location) -> StatementResult {
+        let path = match self.expect_next_token()?.token {
+            Token::ImportPath(path) => path,
+            _ => return Err(self.err(ParseErrKind::ExpectedIdent(self.loc()))),
+        };
+        self.expect_token(&Token::Import)?;
+        let mut names = vec![];
+        let mut end;
+        loop {
+            if let Some(token) = self.peek_token_if(is_keyword)? {
+                let (loc, name) = (token.start, token.token.to_string());
+                return Err(self.err(ParseErrKind::KeywordAsIdent(loc, name)));
+            }
+            let token = self.expect_next_token()?;
+            let name = match token.token {
+                Token::Ident(name)
+                | Token::ConstIdent(name)
+                | Token::TypeIdent(name) => name,
+                _ => return Err(self.err(ParseErrKind::ExpectedIdent(token.start))),
+            };
+            end = token.end;
+            let as_name = self.import_as_name()?;
+            names.push((name, as_name));
+            if !self.next_token_is(&Token::Comma)? {
+                break;
+            }
+        }
+        Ok(ast::Statement::new_import_from(path, names, start, end))
+    }
+
+    /// Handle the optional `as <name>` part of an import.
+    fn import_as_name(&mut self) -> Result<Option<String>, ParseErr> {
+        if !self.next_token_is(&Token::As)? {
+            return Ok(None);
+        }
+        if let Some(token) = self.peek_token_if(is_keyword)? {
+            let (loc, name) = (token.start, token.token.to_string());
+            return Err(self.err(ParseErrKind::KeywordAsIdent(loc, name)));
+        }
+        let as_name_expr = self.expr(0)?;
+        if let Some(as_name) = as_name_expr.is_ident() {
+            Ok(Some(as_name))
+        } else {
+            Err(self.err(ParseErrKind::ExpectedIdent(self.loc())))
+        }
+    }
+
     /// Get the next expression, possibly recurring to handle nested
     /// expressions, unary & binary expressions, blocks, functions, etc.
     fn expr(&mut self, prec: u8) -> ExprResult {
@@ -211,13 +403,15 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             LParen => {
                 let expr = self.parenthesized(start, false)?;
                 if self.peek_token_is_func_scope_start()? {
-                    self.func(expr, start)?
+                    self.func(expr, start, false)?
                 } else {
                     expr
                 }
             }
+            Async => self.async_func(start)?,
+            Await => self.await_(start)?,
             LBracket => self.list(start)?,
-            LBrace => self.map(start)?,
+            LBrace => self.map_or_set(start)?,
             Nil => ast::Expr::new_nil(start, end),
             True => ast::Expr::new_true(start, end),
             False => ast::Expr::new_false(start, end),
@@ -236,12 +430,18 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             If => self.conditional(start)?,
             Match => self.match_conditional(start)?,
             Loop => self.loop_(start)?,
+            Try => self.try_(start)?,
             ImportPath(path) => {
                 ast::Expr::new_ident(ast::Ident::new_ident(path), start, end)
             }
             Ident(name) | ConstIdent(name) => {
                 ast::Expr::new_ident(ast::Ident::new_ident(name), start, end)
             }
+            SpecialIdent(name) if name == "$debug" => {
+                let block = self.block(ScopeKind::Block, start)?;
+                let end = block.end;
+                ast::Expr::new_debug_block(block, start, end)
+            }
             SpecialIdent(name) => {
                 ast::Expr::new_ident(ast::Ident::new_special_ident(name), start, end)
             }
@@ -330,13 +530,17 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(expr)
     }
 
-    fn map(&mut self, start: Location) -> ExprResult {
+    /// Handle map (`{k: v, ...}`) or set (`{a, b, ...}`) literal. The
+    /// first item determines which. `{}` is an empty map.
+    fn map_or_set(&mut self, start: Location) -> ExprResult {
         use Token::{Colon, Comma, RBrace};
         if self.next_token_is(&RBrace)? {
             return Ok(ast::Expr::new_map(vec![], start, self.loc()));
         }
         let name = self.expr(0)?;
-        self.expect_token(&Colon)?;
+        if !self.next_token_is(&Colon)? {
+            return self.set(name, start);
+        }
         let value = self.expr(0)?;
         let first_entry = (name, value);
         let expr = if self.peek_token_is(&Comma)? {
@@ -362,6 +566,23 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(expr)
     }
 
+    fn set(&mut self, first_item: ast::Expr, start: Location) -> ExprResult {
+        use Token::{Comma, RBrace};
+        let mut items = vec![first_item];
+        loop {
+            if self.next_token_is(&RBrace)? {
+                break;
+            }
+            self.expect_token(&Comma)?;
+            if self.next_token_is(&RBrace)? {
+                break;
+            }
+            let item = self.expr(0)?;
+            items.push(item);
+        }
+        Ok(ast::Expr::new_set(items, start, self.loc()))
+    }
+
     /// Handle format strings (AKA $ strings).
     fn format_string(
         &mut self,
@@ -371,13 +592,14 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
     ) -> ExprResult {
         let mut items = vec![];
         for format_string_token in format_string_tokens {
+            let is_repr = matches!(format_string_token, FormatStrToken::ReprExpr(_));
             match format_string_token {
                 FormatStrToken::Str(value) => {
                     // NOTE: Locations aren't correct, but it shouldn't
                     //       matter for string parts.
                     items.push(ast::Expr::new_string(value, start, end));
                 }
-                FormatStrToken::Expr(tokens) => {
+                FormatStrToken::Expr(tokens) | FormatStrToken::ReprExpr(tokens) => {
                     let mut adjusted_tokens = vec![];
                     for t in tokens.iter() {
                         let (s, e) = (t.start, t.end);
@@ -387,10 +609,16 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                             Location::new(end.line + s.line, end.col + e.col),
                         ));
                     }
-                    let program = parse_tokens(adjusted_tokens)?;
+                    let program = parse_tokens(adjusted_tokens)
+                        .map_err(|mut errors| errors.swap_remove(0))?;
                     for statement in program.statements {
                         if let ast::StatementKind::Expr(expr) = statement.kind {
-                            items.push(expr)
+                            if is_repr {
+                                let (start, end) = (expr.start, expr.end);
+                                items.push(ast::Expr::new_repr(expr, start, end));
+                            } else {
+                                items.push(expr);
+                            }
                         } else {
                             return Err(
                                 self.err(ParseErrKind::ExpectedExpr(statement.start))
@@ -421,8 +649,16 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         let statements = match (kind, self.next_token_token()?) {
             (Block, Some(ScopeStart)) | (Func, Some(FuncScopeStart)) => {
                 log::trace!("SUITE BLOCK");
+                let num_errors = self.errors.len();
                 let statements = self.statements()?;
                 if statements.is_empty() {
+                    // If none of the statements in the block could be
+                    // parsed, the last error is propagated so the
+                    // enclosing statement is skipped instead of also
+                    // reporting a missing block.
+                    if self.errors.len() > num_errors {
+                        return Err(self.errors.pop().unwrap());
+                    }
                     return Err(self.err(ExpectedBlock(self.next_loc())));
                 }
                 self.expect_token(&ScopeEnd)?;
@@ -472,16 +708,19 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
 
     /// Handle `match <expr> -> ...`. Inline `match` expressions aren't
     /// supported because they would be too confusing.
+    ///
+    /// Each arm has a pattern (see `pattern_from_expr`), which may be
+    /// followed by a guard (`<pattern> if <cond>`). A type pattern is
+    /// a type followed by a name (`Int i`).
     fn match_conditional(&mut self, start: Location) -> ExprResult {
         use ParseErrKind::{
             ExpectedToken, InlineMatchNotAllowed, MatchDefaultMustBeLast,
         };
         use Token::{
-            EndOfStatement, EqualEqual, InlineScopeStart, ScopeEnd, ScopeStart, Star,
+            EndOfStatement, Ident, If, InlineScopeStart, ScopeEnd, ScopeStart, Star,
         };
         let lhs = self.expr(0)?;
-        // let lhs = self.expr(0).map_err(|e| self.err({ ExpectedExpr(self.loc()) }))?;
-        let mut branches = vec![];
+        let mut arms = vec![];
         let mut default = None;
         let mut end = start;
         if self.next_token_is(&ScopeStart)? {
@@ -499,23 +738,37 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                     }
                     break;
                 } else {
-                    let rhs = self.expr(0)?;
-                    let rhs_end = rhs.end;
-                    let cond = ast::Expr::new_binary_op(
-                        lhs.clone(),
-                        &EqualEqual,
-                        rhs,
-                        start,
-                        rhs_end,
-                    );
+                    let expr = self.expr(0)?;
+                    let type_binding =
+                        match self.next_token_if(|t| matches!(t, Ident(_)))? {
+                            Some(TokenWithLocation { token: Ident(name), .. }) => {
+                                Some(name)
+                            }
+                            _ => None,
+                        };
+                    let guard = match self.next_token_is(&If)? {
+                        true => Some(self.expr(0)?),
+                        false => None,
+                    };
+                    let pattern = match type_binding {
+                        Some(name) => ast::Pattern::Type(expr, name),
+                        None => pattern_from_expr(expr, guard.is_some()),
+                    };
                     let block = self.block(ScopeKind::Block, start)?;
                     end = block.end;
-                    branches.push((cond, block));
+                    arms.push(ast::MatchArm::new(pattern, guard, block));
                     self.expect_token(&EndOfStatement)?;
                 }
             }
             self.expect_token(&ScopeEnd)?;
-            Ok(ast::Expr::new_conditional(branches, default, start, end))
+            if default.is_none() {
+                let missing = missing_match_arms(&arms, &self.enums);
+                if !missing.is_empty() {
+                    let kind = ParseWarningKind::NonExhaustiveMatch(missing);
+                    self.warnings.push(ParseWarning::new(kind, start, lhs.end));
+                }
+            }
+            Ok(ast::Expr::new_match(lhs, arms, default, start, end))
         } else if self.next_token_is(&InlineScopeStart)? {
             Err(self.err(InlineMatchNotAllowed(self.next_loc())))
         } else {
@@ -523,6 +776,20 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         }
     }
 
+    /// If `lhs = value` defines an enum (e.g., `Color = enum (Red,
+    /// Green)`), record its variants so matches on them can be checked
+    /// for exhaustiveness. Invalid definitions are reported by the
+    /// compiler, so they're ignored here.
+    fn record_enum(&mut self, lhs: &ast::Expr, value: &ast::Expr) {
+        if let (Some(name), Some(args)) = (lhs.is_type_ident(), value.is_enum()) {
+            let variants: Option<Vec<String>> =
+                args.iter().map(|arg| arg.is_type_ident()).collect();
+            if let Some(variants) = variants {
+                self.enums.insert(name, variants);
+            }
+        }
+    }
+
     /// Handle `loop -> ...` and `loop <cond> -> ...` (`while` loops).
     /// TODO: Handle `for` loops.
     fn loop_(&mut self, start: Location) -> ExprResult {
@@ -537,9 +804,83 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(ast::Expr::new_loop(cond, block, start, end))
     }
 
+    /// Handle `try -> ... catch [type] name -> ... ensure -> ...`. At
+    /// least one `catch` or `ensure` clause is required. A `catch`
+    /// clause without a type catches all errors.
+    fn try_(&mut self, start: Location) -> ExprResult {
+        use ParseErrKind::{ExpectedCatchOrEnsure, ExpectedIdent};
+        use Token::{Catch, EndOfStatement, Ensure, Ident};
+        let block = self.block(ScopeKind::Block, start)?;
+        let mut end = block.end;
+        let mut catch_clauses = vec![];
+        while self.next_tokens_are(vec![&EndOfStatement, &Catch])? {
+            let expr = self.expr(0)?;
+            let (err_type, name) =
+                match self.next_token_if(|t| matches!(t, Ident(_)))? {
+                    Some(TokenWithLocation { token: Ident(name), .. }) => {
+                        (Some(expr), name)
+                    }
+                    _ => match expr.is_ident() {
+                        Some(name) => (None, name),
+                        None => return Err(self.err(ExpectedIdent(expr.start))),
+                    },
+                };
+            let block = self.block(ScopeKind::Block, self.loc())?;
+            end = block.end;
+            catch_clauses.push((err_type, name, block));
+        }
+        let ensure = match self.next_tokens_are(vec![&EndOfStatement, &Ensure])? {
+            true => {
+                let block = self.block(ScopeKind::Block, self.loc())?;
+                end = block.end;
+                Some(block)
+            }
+            false => None,
+        };
+        if catch_clauses.is_empty() && ensure.is_none() {
+            return Err(self.err(ExpectedCatchOrEnsure(self.next_loc())));
+        }
+        Ok(ast::Expr::new_try(block, catch_clauses, ensure, start, end))
+    }
+
+    /// Handle async function definition (`async (...) => ...`).
+    fn async_func(&mut self, start: Location) -> ExprResult {
+        let token = self.expect_next_token()?;
+        if token.token != Token::LParen {
+            return Err(
+                self.err(ParseErrKind::ExpectedToken(token.start, Token::LParen))
+            );
+        }
+        let params_expr = self.parenthesized(token.start, false)?;
+        if !self.peek_token_is_func_scope_start()? {
+            return Err(self.err(ParseErrKind::ExpectedToken(
+                self.next_loc(),
+                Token::FuncScopeStart,
+            )));
+        }
+        self.func(params_expr, start, true)
+    }
+
+    /// Handle `await`, ensuring it's contained in an async function.
+    fn await_(&mut self, start: Location) -> ExprResult {
+        if !self.in_async_func {
+            return Err(self.err(ParseErrKind::UnexpectedAwait(start)));
+        }
+        let expr = self.expr(get_unary_precedence(&Token::Await))?;
+        let end = expr.end;
+        Ok(ast::Expr::new_await(expr, start, end))
+    }
+
     /// Handle function definition.
-    fn func(&mut self, params_expr: ast::Expr, start: Location) -> ExprResult {
+    fn func(
+        &mut self,
+        params_expr: ast::Expr,
+        start: Location,
+        is_async: bool,
+    ) -> ExprResult {
         self.func_level += 1;
+        let in_async_func = self.in_async_func;
+        self.in_async_func = is_async;
         log::trace!("FUNC level {}", self.func_level);
         let param_exprs = match params_expr.kind {
             // Function has multiple parameters.
@@ -572,11 +913,12 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         let block = self.block(ScopeKind::Func, start)?;
         let def_end = block.end;
         self.func_level -= 1;
+        self.in_async_func = in_async_func;
         // NOTE: The name for a func will be set later if the function
         //       is assigned to a var. Since this is done at compile
         //       time, the function will retain its initial name even
         //       if reassigned.
-        Ok(ast::Expr::new_func(params, block, start, def_end))
+        Ok(ast::Expr::new_func(params, block, is_async, start, def_end))
     }
 
     /// Handle function call.
@@ -592,6 +934,15 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(ast::Expr::new_call(callable, args, start, end))
     }
 
+    /// Handle subscript (e.g., `list[0]` or `map["key"]`).
+    fn subscript(&mut self, obj: ast::Expr) -> ExprResult {
+        let key = self.expr(0)?;
+        self.expect_token(&Token::RBracket)?;
+        let start = obj.start;
+        let end = self.loc();
+        Ok(ast::Expr::new_subscript(obj, key, start, end))
+    }
+
     /// The current token should represent a unary operator and should
     /// be followed by an expression.
     fn expect_unary_expr(&mut self, prefix_token: &TokenWithLocation) -> ExprResult {
@@ -635,6 +986,7 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                         log::trace!("ASSIGNMENT: get value expr");
                         let value = self.expr(infix_prec)?;
                         let end = value.end;
+                        self.record_enum(&lhs, &value);
                         if lhs.ident_name().is_some() {
                             log::trace!("ASSIGN TO IDENT: {lhs:?} = {value:?}");
                             ast::Expr::new_declaration_and_assignment(
@@ -652,6 +1004,11 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                         log::trace!("BINOP: call {lhs:?}");
                         self.call(lhs, infix_token.start)?
                     }
+                    // Subscript
+                    Token::LBracket => {
+                        log::trace!("BINOP: subscript {lhs:?}");
+                        self.subscript(lhs)?
+                    }
                     // Binary operation
                     _ => {
                         log::trace!("BINOP: get right-hand side");
@@ -690,12 +1047,14 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
     /// are left, return `None`.
     fn next_token(&mut self) -> NextTokenResult {
         if let Some(t) = self.lookahead_queue.pop_front() {
+            self.scope_depth += scope_depth_change(&t.token);
             self.current_token = Some(t.clone());
             return Ok(Some(t));
         }
         if let Some(result) = self.token_stream.next() {
             return result
                 .map(|t| {
+                    self.scope_depth += scope_depth_change(&t.token);
                     self.current_token = Some(t.clone());
                     Some(t)
                 })
@@ -704,6 +1063,12 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(None)
     }
 
+    /// Put a consumed token back so it will be the next token.
+    fn push_back_token(&mut self, token: TokenWithLocation) {
+        self.scope_depth -= scope_depth_change(&token.token);
+        self.lookahead_queue.push_front(token);
+    }
+
     fn next_token_token(&mut self) -> Result<Option<Token>, ParseErr> {
         if let Some(token_with_location) = self.next_token()? {
             Ok(Some(token_with_location.token))
@@ -765,7 +1130,7 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                 }
                 None => {
                     for twl in temp_queue {
-                        self.lookahead_queue.push_front(twl);
+                        self.push_back_token(twl);
                     }
                     return Ok(false);
                 }
@@ -882,3 +1247,137 @@ enum ScopeKind {
     Block,
     Func,
 }
+
+/// Convert the expression at the start of a `match` arm to a pattern:
+///
+/// - `a | b | ...` matches any of the values
+/// - `(a, b, ...)` matches tuples item by item, where each item is
+///   itself a pattern and plain names are bindings
+/// - `"prefix" + name`, `name + "suffix"`, and `"prefix" + name +
+///   "suffix"` match strings with the literal prefix and/or suffix and
+///   bind the rest of the string to the name
+/// - A plain name is a binding only when `bind` is set (i.e., when the
+///   arm has a guard); otherwise, it's compared by value like any other
+///   expression
+fn pattern_from_expr(expr: ast::Expr, bind: bool) -> ast::Pattern {
+    if bind {
+        if let Some(name) = expr.is_ident() {
+            return ast::Pattern::Binding(name);
+        }
+    }
+    if let Some(pattern) = str_pattern_from_expr(&expr) {
+        return pattern;
+    }
+    match expr.kind {
+        ast::ExprKind::Tuple(items) => ast::Pattern::Tuple(
+            items.into_iter().map(|item| pattern_from_expr(item, true)).collect(),
+        ),
+        ast::ExprKind::BinaryOp(_, BinaryOperator::BitOr, _) => {
+            let mut alts = vec![];
+            flatten_alts(expr, &mut alts);
+            ast::Pattern::Alt(alts)
+        }
+        _ => ast::Pattern::Value(expr),
+    }
+}
+
+/// Convert `"prefix" + name`, `name + "suffix"`, or `"prefix" + name +
+/// "suffix"` to a string pattern. Other expressions, including
+/// concatenations of names (e.g., `a + b`), aren't string patterns.
+fn str_pattern_from_expr(expr: &ast::Expr) -> Option<ast::Pattern> {
+    let ast::ExprKind::BinaryOp(a, BinaryOperator::Add, b) = &expr.kind else {
+        return None;
+    };
+    if let Some(suffix) = b.is_string() {
+        if let Some(name) = a.is_ident() {
+            return Some(ast::Pattern::Str(None, name, Some(suffix)));
+        }
+        if let ast::ExprKind::BinaryOp(a, BinaryOperator::Add, name) = &a.kind {
+            if let (Some(prefix), Some(name)) = (a.is_string(), name.is_ident()) {
+                return Some(ast::Pattern::Str(Some(prefix), name, Some(suffix)));
+            }
+        }
+    } else if let (Some(prefix), Some(name)) = (a.is_string(), b.is_ident()) {
+        return Some(ast::Pattern::Str(Some(prefix), name, None));
+    }
+    None
+}
+
+/// Collect the operands of `a | b | ...` from left to right.
+fn flatten_alts(expr: ast::Expr, alts: &mut Vec<ast::Expr>) {
+    if let ast::ExprKind::BinaryOp(a, BinaryOperator::BitOr, b) = expr.kind {
+        flatten_alts(*a, alts);
+        flatten_alts(*b, alts);
+    } else {
+        alts.push(expr);
+    }
+}
+
+/// Find the values not covered by the arms of a `match` that has no
+/// default branch. This can only be determined when the patterns are
+/// values of a type with a fixed set of values, which currently means
+/// Bools and the variants of enums defined earlier in the same source
+/// (e.g., `Color.Red`). In other cases, no values are returned. Arms
+/// with guards don't cover any values since their guards may be false.
+///
+/// NOTE: Enums are looked up by name without regard to scope, so a
+///       local that shadows an enum's name isn't accounted for.
+fn missing_match_arms(
+    arms: &[ast::MatchArm],
+    enums: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    let mut patterns = vec![];
+    for arm in arms.iter().filter(|arm| arm.guard.is_none()) {
+        match &arm.pattern {
+            ast::Pattern::Value(expr) => patterns.push(expr),
+            ast::Pattern::Alt(exprs) => patterns.extend(exprs),
+            _ => return vec![],
+        }
+    }
+    let mut missing = vec![];
+    if !patterns.is_empty() && patterns.iter().all(|p| p.is_bool()) {
+        if !patterns.iter().any(|p| p.is_true()) {
+            missing.push("true".to_owned());
+        }
+        if !patterns.iter().any(|p| p.is_false()) {
+            missing.push("false".to_owned());
+        }
+    } else if let Some((name, _)) = patterns.first().and_then(|p| enum_variant(p)) {
+        let covered: Option<Vec<String>> = patterns
+            .iter()
+            .map(|p| match enum_variant(p) {
+                Some((other_name, variant)) if other_name == name => Some(variant),
+                _ => None,
+            })
+            .collect();
+        if let (Some(covered), Some(variants)) = (covered, enums.get(&name)) {
+            for variant in variants.iter().filter(|v| !covered.contains(v)) {
+                missing.push(format!("{name}.{variant}"));
+            }
+        }
+    }
+    missing
+}
+
+/// Check if expression is an enum variant like `Color.Red`. If so,
+/// return the enum and variant names.
+fn enum_variant(expr: &ast::Expr) -> Option<(String, String)> {
+    if let ast::ExprKind::BinaryOp(obj, BinaryOperator::Dot, variant) = &expr.kind {
+        if let (Some(name), Some(variant)) =
+            (obj.is_type_ident(), variant.is_type_ident())
+        {
+            return Some((name, variant));
+        }
+    }
+    None
+}
+
+/// Get how much consuming `token` changes the scope depth.
+fn scope_depth_change(token: &Token) -> isize {
+    use Token::*;
+    match token {
+        ScopeStart | InlineScopeStart | FuncScopeStart | FuncInlineScopeStart => 1,
+        ScopeEnd | InlineScopeEnd => -1,
+        _ => 0,
+    }
+}
@@ -48,23 +48,35 @@ pub fn get_operator_precedence(token: &Token) -> (u8, u8) {
         | LessThan                       // a < b
         | LessThanOrEqual                // a <= b
         | GreaterThan                    // a > b
-        | GreaterThanOrEqual => (0, 4),  // a >= b
+        | GreaterThanOrEqual             // a >= b
+        | In                             // a in b      (membership)
+        | NotIn              => (0, 4),  // a not in b
+
+        | Pipe               => (0, 5),  // a | b       (union)
+        | Ampersand          => (0, 6),  // a & b       (intersection)
+
+        | DotDot                         // a..b        (range)
+        | DotDotEqual        => (0, 7),  // a..=b       (inclusive range)
         
         | Plus                           // +a, a + b
-        | Minus              => (8, 5),  // -a, a - b
+        | Minus              => (11, 8), // -a, a - b
         
         | Star                           // a * b
         | Slash                          // a / b       (floating point div)
         | DoubleSlash                    // a // b      (floor div)
-        | Percent            => (0, 6),  // a % b
+        | Percent            => (0, 9),  // a % b
        
-        | Caret              => (0, 7),  // a ^ b       (exponentiation)
+        | Caret              => (0, 10), // a ^ b       (exponentiation)
 
         | BangBang                       // !!a         (as bool)
-        | Bang               => (8, 0),  // !a          (logical not)
+        | Bang               => (11, 0), // !a          (logical not)
+
+        | Await              => (11, 0), // await a     (await task)
 
-        | LParen             => (0, 9),  // x(...)      (call)
-        | Dot                => (0, 10), // x.y
+        | LParen             => (0, 12), // x(...)      (call)
+        | LBracket           => (0, 12), // x[...]      (subscript)
+        | Dot                            // x.y
+        | NilSafeDot         => (0, 13), // x?.y
         
         _                    => (0, 0),  // not an operator
     }
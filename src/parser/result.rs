@@ -3,7 +3,9 @@ use crate::scanner::{ScanErr, Token, TokenWithLocation};
 use crate::source::Location;
 
 pub type BoolResult = Result<bool, ParseErr>;
-pub type ParseResult = Result<ast::Module, ParseErr>;
+/// Parsing continues after most errors, so all of the errors found are
+/// returned.
+pub type ParseResult = Result<ast::Module, Vec<ParseErr>>;
 pub type StatementResult = Result<ast::Statement, ParseErr>;
 pub type StatementsResult = Result<Vec<ast::Statement>, ParseErr>;
 pub type BlockResult = Result<ast::StatementBlock, ParseErr>;
@@ -32,14 +34,17 @@ impl ParseErr {
             ExpectedIdent(loc) => loc,
             ExpectedOperand(loc) => loc,
             ExpectedToken(loc, _) => loc,
-            UnexpectedImport(loc) => loc,
+            KeywordAsIdent(loc, _) => loc,
             UnexpectedBlock(loc) => loc,
             UnexpectedToken(twl) => &twl.start,
             UnexpectedBreak(loc) => loc,
             UnexpectedContinue(loc) => loc,
             UnexpectedReturn(loc) => loc,
+            UnexpectedGlobal(loc) => loc,
+            UnexpectedAwait(loc) => loc,
             InlineMatchNotAllowed(loc) => loc,
             MatchDefaultMustBeLast(loc) => loc,
+            ExpectedCatchOrEnsure(loc) => loc,
             VarArgsMustBeLast(loc) => loc,
             // TODO: Extract from ScanErr?
             ScanErr(_) => return Location::default(),
@@ -48,6 +53,28 @@ impl ParseErr {
     }
 }
 
+/// Warnings don't stop parsing. They're collected by the parser and can
+/// be reported after parsing is complete.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseWarning {
+    pub kind: ParseWarningKind,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl ParseWarning {
+    pub fn new(kind: ParseWarningKind, start: Location, end: Location) -> Self {
+        Self { kind, start, end }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseWarningKind {
+    /// A `match` without a default branch that doesn't cover every
+    /// possible value. Contains the missing values.
+    NonExhaustiveMatch(Vec<String>),
+}
+
 #[derive(Clone, Debug)]
 pub enum ParseErrKind {
     ScanErr(ScanErr),
@@ -63,7 +90,7 @@ pub enum ParseErrKind {
     ExpectedOperand(Location),
     ExpectedToken(Location, Token),
 
-    UnexpectedImport(Location),
+    KeywordAsIdent(Location, String),
 
     UnexpectedBlock(Location),
     UnexpectedToken(TokenWithLocation),
@@ -71,9 +98,13 @@ pub enum ParseErrKind {
     UnexpectedBreak(Location),
     UnexpectedContinue(Location),
     UnexpectedReturn(Location),
+    UnexpectedGlobal(Location),
+    UnexpectedAwait(Location),
 
     InlineMatchNotAllowed(Location),
     MatchDefaultMustBeLast(Location),
 
+    ExpectedCatchOrEnsure(Location),
+
     VarArgsMustBeLast(Location),
 }
@@ -1,32 +1,45 @@
 //! # FeInt REPL
+use std::fs;
 use std::path::PathBuf;
 
+use rustyline::completion::{Completer, Pair};
 use rustyline::config::Configurer;
 use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
 
 use crate::compiler::CompErrKind;
+#[cfg(feature = "dis")]
 use crate::dis;
 use crate::exe::Executor;
+use crate::modules::maybe_get_module;
+use crate::modules::std::STD;
 use crate::parser::ParseErrKind;
 use crate::result::{ExeErr, ExeErrKind, ExeResult};
-use crate::scanner::ScanErrKind;
-use crate::types::{new, ObjectRef, ObjectTrait};
+use crate::scanner::{ScanErrKind, KEYWORDS};
+use crate::types::{new, FromObjectRef, ObjectRef, ObjectTrait};
 use crate::vm::VMState;
 
 pub struct Repl {
     module: ObjectRef,
-    reader: rustyline::Editor<()>,
+    reader: rustyline::Editor<ReplHelper>,
     history_path: Option<PathBuf>,
     executor: Executor,
 }
 
 impl Repl {
     pub fn new(history_path: Option<PathBuf>, executor: Executor) -> Self {
-        let module = new::intrinsic_module("$repl", "$repl", "FeInt REPL module", &[]);
+        let module = new_module();
         let mut reader =
-            rustyline::Editor::<()>::new().expect("Could initialize readline");
+            rustyline::Editor::<ReplHelper>::new().expect("Could initialize readline");
+        reader.set_helper(Some(ReplHelper::new(module.clone())));
         reader.set_indent_size(4);
         reader.set_tab_stop(4);
+        // With bracketed paste, text pasted into a terminal that
+        // supports it is read as a single, multiline input.
+        reader.enable_bracketed_paste(true);
         Repl { module, reader, history_path, executor }
     }
 
@@ -34,6 +47,8 @@ impl Repl {
         println!("Welcome to the FeInt REPL (read/eval/print loop)");
         println!("Type a line of code, then hit Enter to evaluate it");
         self.load_history();
+        println!("Type .paste to paste a block of code");
+        println!("Type :help to show REPL commands");
         println!("Type .exit or .quit to exit");
 
         self.executor.add_module("$repl", self.module.clone());
@@ -92,6 +107,10 @@ impl Repl {
 
         if matches!(text, ".exit" | ".quit") {
             return Some(Ok(VMState::Halted(0)));
+        } else if text.trim() == ".paste" {
+            return self.paste();
+        } else if let Some(command) = text.trim().strip_prefix(':') {
+            return self.handle_magic_command(command);
         } else if self.handle_command(text) {
             return None;
         }
@@ -102,6 +121,7 @@ impl Repl {
             Ok(vm_state) => {
                 return match vm_state {
                     VMState::Running => None,
+                    VMState::Paused(_) => None,
                     VMState::Idle(_) => None,
                     // Halted:
                     state => Some(Ok(state)),
@@ -150,22 +170,167 @@ impl Repl {
         }
     }
 
+    /// Read lines verbatim until `.end` is entered on its own line (or
+    /// Ctrl-D is pressed), then evaluate them all at once. Unlike
+    /// regular input, lines aren't evaluated as they're entered and
+    /// blank lines don't end input, so indented blocks can be pasted
+    /// as is.
+    fn paste(&mut self) -> Option<ExeResult> {
+        println!("Paste mode; type .end on its own line or hit Ctrl-D to evaluate");
+        // Lines are collected here rather than by the editor so that
+        // `.end` is always seen on its own.
+        self.set_multiline(false);
+        let result = self.read_paste();
+        self.set_multiline(true);
+        result
+    }
+
+    fn read_paste(&mut self) -> Option<ExeResult> {
+        let mut lines = vec![];
+        loop {
+            match self.read_line("", false) {
+                Ok(Some(line)) if line.trim() == ".end" => break,
+                Ok(Some(line)) => lines.push(line),
+                Ok(None) => unreachable!(),
+                // User hit Ctrl-D
+                Err(ReadlineError::Eof) => break,
+                // User hit Ctrl-C
+                Err(ReadlineError::Interrupted) => {
+                    println!("Paste canceled");
+                    return None;
+                }
+                Err(err) => {
+                    let msg = format!("Could not read line: {err}");
+                    return Some(Err(ExeErr::new(ExeErrKind::ReplErr(msg))));
+                }
+            }
+        }
+        let text = lines.join("\n");
+        if text.trim().is_empty() {
+            return None;
+        }
+        self.eval(text.as_str(), false)
+    }
+
+    /// Handle a command that starts with `:` (the `:` has already been
+    /// stripped). Like `eval()`, `None` is returned to continue and an
+    /// `ExeResult` is returned to exit (e.g., when a loaded file halts).
+    fn handle_magic_command(&mut self, command: &str) -> Option<ExeResult> {
+        let (name, arg) = match command.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (command, ""),
+        };
+        match (name, arg) {
+            ("help", "") => self.show_help(),
+            #[cfg(feature = "dis")]
+            ("dis", "") => self.disassemble_module(),
+            #[cfg(feature = "dis")]
+            ("dis", name) => self.disassemble_global(name),
+            #[cfg(not(feature = "dis"))]
+            ("dis", _) => eprintln!("Disassembly requires the dis feature"),
+            ("vars", "") => self.show_vars(),
+            ("reset", "") => {
+                self.reset();
+                eprintln!("REPL session reset");
+            }
+            ("load", "") => eprintln!("Usage: :load <file>"),
+            ("load", path) => return self.load(path),
+            ("type", "") => eprintln!("Usage: :type <expr>"),
+            ("type", expr) => {
+                let result = self
+                    .executor
+                    .execute_repl(&format!("({expr}).$type"), self.module.clone());
+                return exit_result(result);
+            }
+            _ => eprintln!("Unknown REPL command: :{command} (type :help for help)"),
+        }
+        None
+    }
+
+    fn show_help(&self) {
+        eprintln!("{:=>72}", "");
+        eprintln!("FeInt Help");
+        eprintln!("{:->72}", "");
+        eprintln!(":help       -> show this help");
+        eprintln!(".exit       -> exit");
+        eprintln!(".paste      -> paste a block of code (end with .end)");
+        eprintln!(":vars       -> show REPL globals with their types");
+        eprintln!(":type expr  -> show the type of an expression");
+        eprintln!(":load file  -> execute a file in the REPL session");
+        eprintln!(":reset      -> clear all REPL globals");
+        #[cfg(feature = "dis")]
+        eprintln!(":dis [name] -> disassemble a function or the REPL module");
+        eprintln!(".globals    -> show REPL module globals");
+        eprintln!(".constants  -> show REPL module constants");
+        eprintln!(".stack      -> show VM stack (top first)");
+        eprintln!(".emacs      -> switch to emacs-style input (default)");
+        eprintln!(".vi         -> switch to vi-style input");
+        eprintln!("{:=>72}", "");
+    }
+
+    fn show_vars(&self) {
+        let module = self.module.read().unwrap();
+        for (name, val) in module.ns().iter() {
+            let val = val.read().unwrap();
+            println!("{name}: {} = {:?}", val.class().read().unwrap(), &*val);
+        }
+    }
+
+    #[cfg(feature = "dis")]
+    fn disassemble_module(&self) {
+        let module = self.module.read().unwrap();
+        let module = module.down_to_mod().unwrap();
+        let mut disassembler = dis::Disassembler::new();
+        disassembler.disassemble(module.code());
+    }
+
+    /// Disassemble the function (or bound function or closure) bound
+    /// to a global.
+    #[cfg(feature = "dis")]
+    fn disassemble_global(&self, name: &str) {
+        let obj_ref = self.module.read().unwrap().ns().get(name);
+        match obj_ref {
+            Some(obj_ref) => {
+                obj_ref.read().unwrap().get_attr("$dis", obj_ref.clone());
+            }
+            None => eprintln!("Global not found: {name}"),
+        }
+    }
+
+    /// Replace the REPL module with a new, empty module. The VM is
+    /// reset when the first chunk of the new module is executed.
+    fn reset(&mut self) {
+        self.module = new_module();
+        self.executor.add_module("$repl", self.module.clone());
+        if let Some(helper) = self.reader.helper_mut() {
+            helper.module = self.module.clone();
+        }
+    }
+
+    /// Execute a file in the REPL session. Its globals are added to
+    /// the REPL's globals.
+    fn load(&mut self, path: &str) -> Option<ExeResult> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let result = self.executor.execute_repl(&text, self.module.clone());
+                exit_result(result)
+            }
+            Err(err) => {
+                eprintln!("Could not read {path}: {err}");
+                None
+            }
+        }
+    }
+
+    fn set_multiline(&mut self, multiline: bool) {
+        if let Some(helper) = self.reader.helper_mut() {
+            helper.multiline = multiline;
+        }
+    }
+
     fn handle_command(&mut self, text: &str) -> bool {
         match text.trim() {
-            "?" | ".help" => {
-                eprintln!("{:=>72}", "");
-                eprintln!("FeInt Help");
-                eprintln!("{:->72}", "");
-                eprintln!(".help      -> show this help");
-                eprintln!(".exit      -> exit");
-                eprintln!(".globals   -> show REPL module globals");
-                eprintln!(".constants -> show REPL module constants");
-                eprintln!(".dis       -> disassemble REPL module");
-                eprintln!(".stack     -> show VM stack (top first)");
-                eprintln!(".emacs     -> switch to emacs-style input (default)");
-                eprintln!(".vi        -> switch to vi-style input");
-                eprintln!("{:=>72}", "");
-            }
+            "?" | ".help" => self.show_help(),
             ".globals" => {
                 let module = self.module.read().unwrap();
                 let module = module.down_to_mod().unwrap();
@@ -184,12 +349,8 @@ impl Repl {
                     println!("{i} = {:?}", &*val.read().unwrap());
                 }
             }
-            ".dis" => {
-                let module = self.module.read().unwrap();
-                let module = module.down_to_mod().unwrap();
-                let mut disassembler = dis::Disassembler::new();
-                disassembler.disassemble(module.code());
-            }
+            #[cfg(feature = "dis")]
+            ".dis" => self.disassemble_module(),
             ".stack" => {
                 self.executor.display_stack();
             }
@@ -213,6 +374,8 @@ impl Repl {
                     | ExpectedIndentedBlock(_)
                     | UnmatchedOpeningBracket(_)
                     | UnterminatedStr(_)
+                    | UnterminatedComment
+                    | ExpectedLineContinuation
             );
         } else if let ExeErrKind::ParseErr(kind) = &err.kind {
             use ParseErrKind::*;
@@ -252,3 +415,227 @@ impl Repl {
         }
     }
 }
+
+fn new_module() -> ObjectRef {
+    new::intrinsic_module("$repl", "$repl", "FeInt REPL module", &[])
+}
+
+/// Get the result to exit with, if any, from executing code in the
+/// REPL. Errors are reported by the executor, so they don't cause the
+/// REPL to exit, except for exits (e.g., `$halt`).
+fn exit_result(result: ExeResult) -> Option<ExeResult> {
+    match result {
+        Ok(VMState::Halted(code)) => Some(Ok(VMState::Halted(code))),
+        Ok(_) => None,
+        Err(err) => err.exit_code().map(|code| Ok(VMState::Halted(code))),
+    }
+}
+
+// Editor Helper -------------------------------------------------------
+
+/// Provides tab completion and multiline input for the REPL's editor.
+struct ReplHelper {
+    module: ObjectRef,
+    /// When set, input that's incomplete (e.g., has unclosed brackets
+    /// or an unfinished block) is continued on the next line instead
+    /// of being evaluated when Enter is hit.
+    multiline: bool,
+}
+
+impl ReplHelper {
+    fn new(module: ObjectRef) -> Self {
+        Self { module, multiline: true }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        // Tab indents when there's nothing to complete.
+        let line_start = line[..pos].rfind('\n').map_or(0, |i| i + 1);
+        if line[line_start..pos].trim().is_empty() {
+            let indent =
+                Pair { display: "".to_owned(), replacement: "    ".to_owned() };
+            return Ok((pos, vec![indent]));
+        }
+        let (start, names) = complete(&self.module, line, pos);
+        let candidates = names
+            .into_iter()
+            .map(|name| Pair { display: name.clone(), replacement: name })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+    ) -> rustyline::Result<ValidationResult> {
+        if self.multiline && is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+/// Get the names that complete the word ending at `pos` along with
+/// the position where the word starts. If the word contains dots
+/// (e.g., `math.fl`), the part before the last dot is looked up and
+/// its attribute names (`$names`) are completed. Otherwise, globals,
+/// builtins, and keywords are completed.
+pub(crate) fn complete(
+    module: &ObjectRef,
+    line: &str,
+    pos: usize,
+) -> (usize, Vec<String>) {
+    let is_word_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '$' | '.');
+    let start = line[..pos]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let word = &line[start..pos];
+
+    let (start, prefix, mut names) = if let Some((path, prefix)) = word.rsplit_once('.')
+    {
+        let names = match resolve(module, path) {
+            Some(obj_ref) => {
+                let obj = obj_ref.read().unwrap();
+                let names = obj.get_attr("$names", obj_ref.clone());
+                Vec::<String>::from_obj_ref(&names).unwrap_or_default()
+            }
+            None => vec![],
+        };
+        (pos - prefix.len(), prefix, names)
+    } else {
+        let mut names: Vec<String> = {
+            let module = module.read().unwrap();
+            module.ns().iter().map(|(name, _)| name.clone()).collect()
+        };
+        let std = STD.read().unwrap();
+        names.extend(std.ns().iter().map(|(name, _)| name.clone()));
+        names.extend(KEYWORDS.keys().map(|name| name.to_string()));
+        (start, word, names)
+    };
+
+    names.retain(|name| name.starts_with(prefix));
+    names.sort();
+    names.dedup();
+    (start, names)
+}
+
+/// Look up a dotted path like `a.b.c`. The first segment is looked up
+/// in the REPL's globals, then the builtins, then the loaded modules
+/// (in which case the whole path is used as the module name).
+fn resolve(module: &ObjectRef, path: &str) -> Option<ObjectRef> {
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let global = {
+        let module = module.read().unwrap();
+        module.ns().get(first)
+    };
+    let mut obj_ref = match global.or_else(|| STD.read().unwrap().ns().get(first)) {
+        Some(obj_ref) => obj_ref,
+        None => return maybe_get_module(path),
+    };
+    for name in segments {
+        let next = {
+            let obj = obj_ref.read().unwrap();
+            let class = obj.class();
+            let class = class.read().unwrap();
+            obj.ns().get(name).or_else(|| class.ns().get(name))
+        };
+        obj_ref = next?;
+    }
+    Some(obj_ref)
+}
+
+/// Check whether input is incomplete, meaning more lines should be
+/// read before evaluating it. This is the case when the input has
+/// unclosed brackets, strings, or block comments, when the last line
+/// ends with a block opener (`->` or `=>`) or a line continuation, or
+/// when the input contains a multiline block that hasn't been ended
+/// with a blank line.
+pub(crate) fn is_incomplete(text: &str) -> bool {
+    let mut chars = text.chars().peekable();
+    let mut bracket_depth = 0usize;
+    let mut comment_depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut has_block = false;
+    // The current line without comments or string contents.
+    let mut line = String::new();
+
+    while let Some(c) = chars.next() {
+        if comment_depth > 0 {
+            if c == '#' && chars.peek() == Some(&'[') {
+                chars.next();
+                comment_depth += 1;
+            } else if c == ']' && chars.peek() == Some(&'#') {
+                chars.next();
+                comment_depth -= 1;
+            }
+            continue;
+        }
+        if let Some(q) = quote {
+            if c == '\\' {
+                chars.next();
+            } else if c == q {
+                quote = None;
+                line.push(c);
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' => quote = Some(c),
+            '#' if chars.peek() == Some(&'[') => {
+                chars.next();
+                comment_depth += 1;
+                continue;
+            }
+            '#' => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '(' | '[' | '{' => bracket_depth += 1,
+            ')' | ']' | '}' => bracket_depth = bracket_depth.saturating_sub(1),
+            '\n' => {
+                has_block = has_block || ends_with_block_opener(&line);
+                line.clear();
+                continue;
+            }
+            _ => (),
+        }
+        line.push(c);
+    }
+
+    if quote.is_some() || comment_depth > 0 || bracket_depth > 0 {
+        return true;
+    }
+    let line = line.trim_end();
+    if ends_with_block_opener(line) || line.ends_with('\\') {
+        return true;
+    }
+    let last_line = text.rsplit('\n').next().unwrap_or_default();
+    has_block && !last_line.trim().is_empty()
+}
+
+fn ends_with_block_opener(line: &str) -> bool {
+    let line = line.trim_end();
+    line.ends_with("->") || line.ends_with("=>")
+}
@@ -27,6 +27,12 @@ impl ExeErr {
             None
         }
     }
+
+    /// Check whether this error indicates that execution was cancelled
+    /// by the host (see `Executor::cancel_handle()`).
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.kind, ExeErrKind::RuntimeErr(RuntimeErrKind::Cancelled))
+    }
 }
 
 #[derive(Debug)]
@@ -34,12 +40,16 @@ pub enum ExeErrKind {
     Bootstrap(String),
     ModuleDirNotFound(String),
     ModuleNotFound(String),
+    CircularImport(String),
     CouldNotReadSourceFile(String),
     ScanErr(ScanErrKind),
     ParseErr(ParseErrKind),
     CompErr(CompErrKind),
     RuntimeErr(RuntimeErrKind),
     ReplErr(String),
+    // A panic in the interpreter, which indicates a bug in FeInt rather
+    // than in the code being run.
+    InternalErr(String),
 }
 
 impl fmt::Display for ExeErr {
@@ -63,6 +73,9 @@ impl fmt::Display for ExeErrKind {
             ModuleNotFound(name) => {
                 format!("Module not found: {name}")
             }
+            CircularImport(chain) => {
+                format!("Circular import: {chain}")
+            }
             CouldNotReadSourceFile(file_name) => {
                 format!("Could not read source file: {file_name}")
             }
@@ -71,6 +84,14 @@ impl fmt::Display for ExeErrKind {
             CompErr(kind) => format!("Compilation error: {kind:?}"),
             RuntimeErr(kind) => format!("Runtime error: {kind:?}"),
             ReplErr(msg) => format!("REPL error: {msg}"),
+            InternalErr(msg) => format!(
+                concat!(
+                    "Internal error: {}\n",
+                    "This is a bug in FeInt. Please report it at {}/issues"
+                ),
+                msg,
+                env!("CARGO_PKG_REPOSITORY")
+            ),
         };
         write!(f, "{msg}")
     }
@@ -16,14 +16,21 @@ pub static KEYWORDS: Lazy<HashMap<&'static str, Token>> = Lazy::new(|| {
         ("else", Else),
         ("match", Match),
         ("loop", Loop),
+        ("try", Try),
+        ("catch", Catch),
+        ("ensure", Ensure),
         ("break", Break),
         ("continue", Continue),
         ("jump", Jump),
+        ("global", Global),
         ("import", Import),
         ("export", Export),
         ("from", From),
         ("package", Package),
         ("return", Return),
+        ("async", Async),
+        ("await", Await),
+        ("in", In),
         ("$halt", Halt),
         ("$print", Print),
     ]
@@ -31,3 +38,8 @@ pub static KEYWORDS: Lazy<HashMap<&'static str, Token>> = Lazy::new(|| {
     .cloned()
     .collect()
 });
+
+/// Check whether the specified token is a keyword token.
+pub fn is_keyword(token: &Token) -> bool {
+    KEYWORDS.values().any(|t| t == token)
+}
@@ -1,7 +1,7 @@
-pub use keywords::KEYWORDS;
+pub use keywords::{is_keyword, KEYWORDS};
 pub use result::{ScanErr, ScanErrKind, ScanTokenResult, ScanTokensResult};
 pub use scanner::Scanner;
-pub use token::{Token, TokenWithLocation};
+pub use token::{Token, TokenWithLocation, Trivia};
 
 mod keywords;
 mod result;
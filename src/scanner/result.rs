@@ -30,20 +30,23 @@ impl ScanErr {
 
 #[derive(Clone, Debug)]
 pub enum ScanErrKind {
-    InvalidIndent(u8), // Indent is not a multiple of 4 (number of spaces)
-    UnexpectedIndent(u8), // Indent in unexpected place (indent level)
+    InvalidIndent(usize), // Indent is not a multiple of 4 (number of spaces)
+    UnexpectedIndent(usize), // Indent in unexpected place (indent level)
     WhitespaceAfterIndent, // Non-space whitespace after indent
     UnexpectedWhitespace, // Other unexpected whitespace
-    ExpectedBlock,     // Block expected but not provided
-    ExpectedIndentedBlock(u8), // Expected an indented block
+    ExpectedBlock,        // Block expected but not provided
+    ExpectedIndentedBlock(usize), // Expected an indented block
     UnterminatedStr(String), // String with no closing quote
+    UnterminatedComment,  // Block comment with no closing ]#
     UnexpectedChar(char), // Char not recognized as token or start of token
+    ExpectedLineContinuation, // End of input after \ or trailing operator
     UnmatchedOpeningBracket(char), // Closing bracket with no matching opening bracket
     UnmatchedClosingBracket(char), // Opening bracket with no matching closing bracket
     ParseIntErr(ParseBigIntError),
     ParseFloatErr(ParseFloatError),
     FormatStrErr(FormatStrErr),
     TooMuchWhitespace,
+    LineTooLong(u64), // Line is longer than the max line length
 
     InvalidLabel(String),
 
@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
 use std::io::BufRead;
+use std::mem;
 
 use num_bigint::BigInt;
 use num_traits::Num;
@@ -8,13 +9,13 @@ use regex::Regex;
 
 use crate::format::scan_format_string;
 use crate::scanner::result::AddTokenResult;
-use crate::source::{Location, Source};
+use crate::source::{Location, Source, MAX_LINE_LENGTH};
 use crate::util::Stack;
 
 use super::keywords::KEYWORDS;
 use super::result::ScanErrKind as ErrKind;
 use super::result::{AddTokensResult, ScanErr, ScanTokenResult};
-use super::token::{Token, TokenWithLocation};
+use super::token::{Token, TokenWithLocation, Trivia};
 
 type NextOption<'a> = Option<(char, Option<&'a char>, Option<&'a char>)>;
 type NextTwoOption<'a> = Option<(char, char, Option<&'a char>)>;
@@ -31,7 +32,7 @@ pub struct Scanner<'a, T: BufRead> {
     queue: VecDeque<TokenWithLocation>,
     /// Keep track of whether we're at the start of a line so indents
     /// can be handled specially.
-    indent_level: u8,
+    indent_level: usize,
     /// Opening brackets are pushed and later popped when the closing
     /// bracket is encountered. This gives us a way to verify brackets
     /// are matched and also lets us know when we're inside a group
@@ -48,6 +49,13 @@ pub struct Scanner<'a, T: BufRead> {
     if_stack: Stack<Location>,
     /// The last token that was popped from the queue.
     last_token_from_queue: Token,
+    /// Set when `from <path>` is scanned so that the `import` that
+    /// follows is scanned as a plain keyword rather than being treated
+    /// as the start of an import path.
+    in_from_import: bool,
+    /// Comments and type hints, which aren't emitted as tokens, are
+    /// collected here when requested (see `collect_trivia`).
+    trivia: Option<Vec<Trivia>>,
 }
 
 impl<'a, T: BufRead> Scanner<'a, T> {
@@ -60,6 +68,26 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             inline_scope_stack: Stack::new(),
             if_stack: Stack::new(),
             last_token_from_queue: Token::EndOfStatement,
+            in_from_import: false,
+            trivia: None,
+        }
+    }
+
+    /// Collect comments and type hints while scanning. These aren't
+    /// emitted as tokens, so this is the only way to get at them (e.g.,
+    /// to preserve them when formatting source code).
+    pub fn collect_trivia(&mut self) {
+        self.trivia = Some(vec![]);
+    }
+
+    /// Take the trivia collected so far, in source order.
+    pub fn take_trivia(&mut self) -> Vec<Trivia> {
+        self.trivia.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    fn add_trivia(&mut self, item: Trivia) {
+        if let Some(trivia) = &mut self.trivia {
+            trivia.push(item);
         }
     }
 
@@ -69,7 +97,14 @@ impl<'a, T: BufRead> Scanner<'a, T> {
     /// returned.
     fn next_token_from_queue(&mut self) -> ScanTokenResult {
         while self.queue.is_empty() {
-            self.add_tokens_to_queue()?;
+            let result = self.add_tokens_to_queue();
+            // The source stops at a line that's too long, which would
+            // otherwise look like the end of the input.
+            if self.source.line_too_long {
+                let loc = Location::new(self.source.line_no, 1);
+                return Err(ScanErr::new(ErrKind::LineTooLong(MAX_LINE_LENGTH), loc));
+            }
+            result?;
         }
         let token = self.queue.pop_front().unwrap();
         self.last_token_from_queue = token.token.clone();
@@ -99,7 +134,8 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             Some((quote @ ('"' | '\''), _, _)) => self.handle_string(quote, start)?,
             Some(('$', Some('"' | '\''), _)) => self.handle_format_string(start)?,
             Some(('#', _, _)) => {
-                self.consume_comment();
+                self.consume_comment()?;
+                self.consume_whitespace();
                 return Ok(());
             }
             Some((':', _, _)) => {
@@ -176,6 +212,7 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             Some(('|', Some('|'), _)) => self.consume_char_and_return_token(Or),
             Some(('|', _, _)) => self.consume_char_and_return_token(Pipe),
             Some(('?', Some('?'), _)) => self.consume_char_and_return_token(NilOr),
+            Some(('?', Some('.'), _)) => self.consume_char_and_return_token(NilSafeDot),
             Some(('*', Some('='), _)) => self.consume_char_and_return_token(MulEqual),
             Some(('*', _, _)) => Star,
             Some(('/', Some('='), _)) => self.consume_char_and_return_token(DivEqual),
@@ -200,6 +237,9 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             Some(('.', Some('.'), Some('.'))) => {
                 self.consume_two_chars_and_return_token(Ellipsis)
             }
+            Some(('.', Some('.'), Some('='))) => {
+                self.consume_two_chars_and_return_token(DotDotEqual)
+            }
             Some(('.', Some('.'), _)) => self.consume_char_and_return_token(DotDot),
             Some(('.', _, _)) => Dot,
             Some(('%', _, _)) => Percent,
@@ -224,6 +264,14 @@ impl<'a, T: BufRead> Scanner<'a, T> {
                 self.handle_ident(c, IdentKind::Special, start)?
             }
             Some(('@', _, _)) => At,
+            Some(('\\', Some('\n'), _)) => {
+                // Explicit line continuation
+                self.source.next();
+                return self.continue_line(start);
+            }
+            Some(('\\', None, _)) => {
+                return Err(ScanErr::new(ExpectedLineContinuation, start));
+            }
             Some(('\n', _, _)) => return self.handle_newline(start),
             Some((c, _, _)) if c.is_whitespace() => {
                 return Err(ScanErr::new(UnexpectedWhitespace, start));
@@ -234,6 +282,7 @@ impl<'a, T: BufRead> Scanner<'a, T> {
 
         let end = self.source.loc();
         let is_ident = token.is_ident();
+        let continues_line = token.continues_line();
 
         self.add_token_to_queue(token, start, end);
         self.consume_whitespace();
@@ -253,6 +302,7 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         if is_ident && self.next_char_is(':') {
             let in_brackets = matches!(self.bracket_stack.peek(), Some(('[' | '{', _)));
             if !in_brackets {
+                let mut hint = String::new();
                 let test = |&c: &char| {
                     c.is_ascii_alphabetic()
                         || c.is_ascii_digit()
@@ -262,19 +312,28 @@ impl<'a, T: BufRead> Scanner<'a, T> {
                         || c == '>'
                         || c == '|'
                 };
-                while self.next_char_if(test).is_some() {}
+                while let Some((c, _, _)) = self.next_char_if(test) {
+                    hint.push(c);
+                }
+                self.add_trivia(Trivia::TypeHint(hint.trim().to_owned(), end));
             }
         }
 
         // The following ensures that if a token is followed by only
         // trailing whitespace and/or a comment that the EndOfStatement
         // token, if one is added, will have the correct location.
-        if self.next_char_is('#') {
-            self.consume_comment();
+        while self.next_char_is('#') {
+            self.consume_comment()?;
+            self.consume_whitespace();
         }
         if self.next_char_is('\n') {
             let loc = Location::new(end.line, end.col + 1);
-            self.handle_newline(loc)?;
+            if continues_line {
+                // Implicit line continuation after a binary operator
+                self.continue_line(loc)?;
+            } else {
+                self.handle_newline(loc)?;
+            }
         } else if self.source.peek().is_none() {
             let loc = Location::new(end.line, end.col + 1);
             self.handle_end_of_input(loc)?;
@@ -382,6 +441,12 @@ impl<'a, T: BufRead> Scanner<'a, T> {
 
         let ident = self.read_ident(first_char);
 
+        // NOTE: `not` is only special in `not in`. Otherwise, it's a
+        //       regular identifier.
+        if ident == "not" && self.next_word_is_in() {
+            return Ok(Token::NotIn);
+        }
+
         let kind = match self.check_ident(ident.as_str(), kind, start) {
             Ok(kind) => kind,
             Err(err) => {
@@ -395,17 +460,36 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             }
         };
 
+        // NOTE: `import` after a dot is an attribute name (e.g.,
+        //       `system.import(name)`), not an import statement.
+        let is_attr = matches!(self.last_token(), Token::Dot | Token::NilSafeDot);
+
         // Keyword (NOTE: keywords can use any ident style)
-        if let Some(token) = KEYWORDS.get(ident.as_str()) {
-            if token == &Token::If {
+        if let Some(token) = KEYWORDS
+            .get(ident.as_str())
+            .filter(|token| !(is_attr && **token == Token::Import))
+        {
+            if token == &Token::If || token == &Token::Try {
                 self.if_stack.push(start);
-            } else if token == &Token::Else {
+            } else if token == &Token::Else || token == &Token::Ensure {
                 if self.maybe_exit_inline_scope(start, true) {
                     self.add_token_to_queue(Token::EndOfStatement, start, start);
                 }
                 self.if_stack.pop();
-            } else if token == &Token::Import {
-                self.add_token_to_queue(Token::Import, start, start);
+            } else if token == &Token::Catch {
+                // NOTE: The matching `try` is left on the stack since
+                //       it may be followed by more `catch` clauses or
+                //       an `ensure` clause.
+                if self.maybe_exit_inline_scope(start, true) {
+                    self.add_token_to_queue(Token::EndOfStatement, start, start);
+                }
+            } else if token == &Token::Import && self.in_from_import {
+                // NOTE: In `from <path> import x, y`, the names after
+                //       `import` are scanned as regular identifiers.
+                self.in_from_import = false;
+            } else if token == &Token::Import || token == &Token::From {
+                self.in_from_import = token == &Token::From;
+                self.add_token_to_queue(token.clone(), start, start);
                 self.consume_whitespace();
                 let path = self.read_import_path();
                 if !IMPORT_PATH_REGEX.is_match(&path) {
@@ -437,9 +521,9 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         let end = Location::new(start.line, start.col + 1);
         self.source.next(); // consume >
         self.consume_whitespace();
-        if self.source.peek() == Some(&'#') {
-            self.source.next();
-            self.consume_comment();
+        while self.next_char_is('#') {
+            self.consume_comment()?;
+            self.consume_whitespace();
         }
         if self.source.peek().is_none() {
             return Err(ScanErr::new(ErrKind::ExpectedBlock, self.source.loc()));
@@ -475,10 +559,35 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         Ok(())
     }
 
+    /// Continue the current statement on the next line. This is called
+    /// after the newline following a `\` or a trailing binary operator
+    /// is consumed. Unlike a regular newline, no `EndOfStatement` token
+    /// is added and indentation is ignored. Blank lines and comment-only
+    /// lines are skipped.
+    fn continue_line(&mut self, loc: Location) -> AddTokensResult {
+        loop {
+            self.consume_whitespace();
+            if self.next_char_is('#') {
+                self.consume_comment()?;
+                continue;
+            }
+            if !self.next_char_is('\n') {
+                break;
+            }
+        }
+        if self.source.peek().is_none() {
+            return Err(ScanErr::new(ErrKind::ExpectedLineContinuation, loc));
+        }
+        Ok(())
+    }
+
     fn handle_end_of_input(&mut self, loc: Location) -> AddTokensResult {
         if let Some((c, bracket_loc)) = self.bracket_stack.pop() {
             return Err(ScanErr::new(ErrKind::UnmatchedOpeningBracket(c), bracket_loc));
         }
+        if self.last_token().continues_line() {
+            return Err(ScanErr::new(ErrKind::ExpectedLineContinuation, loc));
+        }
         self.add_token_to_queue(Token::EndOfInput, loc, loc);
         Ok(())
     }
@@ -502,19 +611,23 @@ impl<'a, T: BufRead> Scanner<'a, T> {
 
     /// Get the next indent level. Blank lines, whitespace-only lines,
     /// and comment-only lines are skipped over.
-    fn get_next_indent_level(&mut self) -> Result<u8, ScanErr> {
+    fn get_next_indent_level(&mut self) -> Result<usize, ScanErr> {
         use ErrKind::{InvalidIndent, WhitespaceAfterIndent};
         let next_level = loop {
             let num_spaces = self.consume_contiguous(' ');
             let whitespace_count = self.consume_whitespace();
+            // Code can follow a block comment on the same line, in
+            // which case the indent is the indent before the comment.
+            while self.next_char_is('#') {
+                self.consume_comment()?;
+                self.consume_whitespace();
+            }
             if let Some(char) = self.source.peek() {
                 if *char == '\n' {
-                    // Blank or whitespace-only line; skip it.
+                    // Blank, whitespace-only, or comment-only line;
+                    // skip it.
                     self.source.next();
                     continue;
-                } else if *char == '#' {
-                    self.consume_comment();
-                    continue;
                 }
                 if num_spaces % 4 != 0 {
                     let loc = self.source.loc();
@@ -565,7 +678,11 @@ impl<'a, T: BufRead> Scanner<'a, T> {
     /// is the same as the current indent level, do nothing. If it has
     /// increased, that signals the start of a block (scopes). If it has
     /// decreased, that signals the end of one or more blocks (scopes).
-    fn set_indent_level(&mut self, indent_level: u8, loc: Location) -> AddTokensResult {
+    fn set_indent_level(
+        &mut self,
+        indent_level: usize,
+        loc: Location,
+    ) -> AddTokensResult {
         let mut current_level = self.indent_level;
         if indent_level == current_level {
             // Stayed the same; nothing to do
@@ -598,9 +715,10 @@ impl<'a, T: BufRead> Scanner<'a, T> {
     /// input.
     ///
     /// If exiting because an `else` was encountered, exit back to the
-    /// matching `if`. If inside a bracket group, exit only as far back
-    /// as the start of the group. Otherwise, all inline scopes are
-    /// exited.
+    /// matching `if` (`catch` and `ensure` are handled the same way
+    /// with regard to `try`). If inside a bracket group, exit only as
+    /// far back as the start of the group. Otherwise, all inline scopes
+    /// are exited.
     fn exit_inline_scope(&mut self, loc: Location, is_else: bool) -> bool {
         let bracket_loc = match self.bracket_stack.peek() {
             Some((_, bracket_loc)) => (bracket_loc.line, bracket_loc.col),
@@ -753,7 +871,7 @@ impl<'a, T: BufRead> Scanner<'a, T> {
 
     /// Consume contiguous whitespace up to the end of the line. Return
     /// the number of whitespace characters consumed.
-    fn consume_whitespace(&mut self) -> u8 {
+    fn consume_whitespace(&mut self) -> usize {
         let mut count = 0;
         loop {
             match self.next_char_if(|&c| c != '\n' && c.is_whitespace()) {
@@ -763,13 +881,48 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         }
     }
 
-    /// Consume comment characters up to newline.
-    fn consume_comment(&mut self) {
-        while self.next_char_if(|&c| c != '\n').is_some() {}
+    /// Consume a comment. The leading `#` must already have been
+    /// consumed. Line comments are consumed up to the newline. Block
+    /// comments (`#[ ... ]#`) are consumed up to the matching `]#`;
+    /// they can be nested and can span multiple lines.
+    fn consume_comment(&mut self) -> AddTokensResult {
+        let start = self.source.loc();
+        let mut text = String::from("#");
+        if !self.next_char_is('[') {
+            while let Some((c, _, _)) = self.next_char_if(|&c| c != '\n') {
+                text.push(c);
+            }
+            self.add_trivia(Trivia::Comment(text.trim_end().to_owned(), start));
+            return Ok(());
+        }
+        text.push('[');
+        let mut depth = 1;
+        while depth > 0 {
+            match self.source.next() {
+                Some('#') => {
+                    text.push('#');
+                    if self.next_char_is('[') {
+                        text.push('[');
+                        depth += 1;
+                    }
+                }
+                Some(']') => {
+                    text.push(']');
+                    if self.next_char_is('#') {
+                        text.push('#');
+                        depth -= 1;
+                    }
+                }
+                Some(c) => text.push(c),
+                None => return Err(ScanErr::new(ErrKind::UnterminatedComment, start)),
+            }
+        }
+        self.add_trivia(Trivia::Comment(text, start));
+        Ok(())
     }
 
     /// Consume contiguous chars and return count.
-    fn consume_contiguous(&mut self, char: char) -> u8 {
+    fn consume_contiguous(&mut self, char: char) -> usize {
         let mut count = 0;
         while self.next_char_is(char) {
             count += 1;
@@ -920,6 +1073,28 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         }
     }
 
+    /// Check whether the next word on the current line is `in`,
+    /// consuming it along with any preceding whitespace if so.
+    fn next_word_is_in(&mut self) -> bool {
+        let mut n = 0;
+        while self.source.peek_nth(n).is_some_and(|&c| c == ' ' || c == '\t') {
+            n += 1;
+        }
+        if n == 0
+            || self.source.peek_nth(n) != Some(&'i')
+            || self.source.peek_nth(n + 1) != Some(&'n')
+            || self.source.peek_nth(n + 2).is_some_and(|&c| {
+                c.is_ascii_alphabetic() || c.is_ascii_digit() || c == '_'
+            })
+        {
+            return false;
+        }
+        for _ in 0..n + 2 {
+            self.source.next();
+        }
+        true
+    }
+
     /// Read identifier.
     fn read_ident(&mut self, first_char: char) -> String {
         let mut string = first_char.to_string();
@@ -16,10 +16,11 @@ pub enum Token {
     RBrace,   // }
 
     // Miscellaneous ---------------------------------------------------
-    Colon,    // :
-    DotDot,   // ..
-    Ellipsis, // ...
-    LoopFeed, // <-
+    Colon,       // :
+    DotDot,      // ..
+    DotDotEqual, // ..=
+    Ellipsis,    // ...
+    LoopFeed,    // <-
 
     // Fundamental types -----------------------------------------------
     At,                             // @ (used to represent the singleton Always)
@@ -55,6 +56,7 @@ pub enum Token {
     And,                // &&
     Or,                 // ||
     NilOr,              // ??
+    NilSafeDot,         // ?.
     LessThan,           // <
     LessThanOrEqual,    // <=
     GreaterThan,        // >
@@ -83,18 +85,26 @@ pub enum Token {
     Else,          // else
     Match,         // match
     Loop,          // ??? (while true, like Rust)
+    Try,           // try
+    Catch,         // catch
+    Ensure,        // ensure
     Break,         // break
     Continue,      // continue
     Return,        // return
+    Async,         // async (func)
+    Await,         // await task
     Jump,          // jump label
+    Global,        // global name (in function)
     Label(String), // :label:
+    In,            // in
+    NotIn,         // not in
     Halt,
     Print,
 
     // Import/export ---------------------------------------------------
     Import,             // import <path>
     ImportPath(String), // import <path>
-    From,               // import from <module>: x, y, z
+    From,               // from <path> import x, y, z
     Package,            // import from package.<module>: x, y, z
     Export,             // export <object>
     As,                 // import <path> as <name>
@@ -125,6 +135,7 @@ impl Token {
             Self::At => "@",
             Self::Colon => ":",
             Self::DotDot => "..",
+            Self::DotDotEqual => "..=",
             Self::Ellipsis => "...",
             Self::LoopFeed => "<-",
 
@@ -161,6 +172,7 @@ impl Token {
             Self::And => "&&",
             Self::Or => "||",
             Self::NilOr => "??",
+            Self::NilSafeDot => "?.",
             Self::LessThan => "<",
             Self::LessThanOrEqual => "<=",
             Self::GreaterThan => ">",
@@ -189,10 +201,18 @@ impl Token {
             Self::Else => "else",
             Self::Match => "match",
             Self::Loop => "loop",
+            Self::Try => "try",
+            Self::Catch => "catch",
+            Self::Ensure => "ensure",
             Self::Break => "break",
             Self::Continue => "continue",
             Self::Return => "return",
+            Self::Async => "async",
+            Self::Await => "await",
+            Self::In => "in",
+            Self::NotIn => "not in",
             Self::Jump => "jump",
+            Self::Global => "global",
             Self::Label(_name) => "label",
             Self::Halt => "$halt",
             Self::Print => "$print",
@@ -227,6 +247,44 @@ impl Token {
                 | Token::SpecialIdent(_)
         )
     }
+
+    /// Return true if the token is a binary operator that can't end an
+    /// expression. When one of these tokens is at the end of a line,
+    /// the statement continues on the next line.
+    pub fn continues_line(&self) -> bool {
+        use Token::*;
+        matches!(
+            self,
+            Caret
+                | Star
+                | Slash
+                | DoubleSlash
+                | Percent
+                | Plus
+                | Minus
+                | Pipe
+                | Ampersand
+                | Dot
+                | DotDot
+                | DotDotEqual
+                | DollarDollar
+                | DollarNot
+                | EqualEqualEqual
+                | NotEqualEqual
+                | EqualEqual
+                | NotEqual
+                | And
+                | Or
+                | NilOr
+                | NilSafeDot
+                | LessThan
+                | LessThanOrEqual
+                | GreaterThan
+                | GreaterThanOrEqual
+                | In
+                | NotIn
+        )
+    }
 }
 
 impl fmt::Display for Token {
@@ -265,3 +323,15 @@ impl fmt::Display for TokenWithLocation {
         )
     }
 }
+
+/// Source text that isn't emitted as a token but that can be collected
+/// while scanning (see `Scanner::collect_trivia`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trivia {
+    /// Comment text, including the leading `#`, and the location of the
+    /// `#`. Trailing whitespace is stripped from line comments.
+    Comment(String, Location),
+    /// Type hint text (e.g., `Int` in `x: Int`) and the location of the
+    /// end of the identifier it follows.
+    TypeHint(String, Location),
+}
@@ -19,7 +19,7 @@ const INITIAL_CAPACITY: usize = 255; // 2^8 - 1
 ///       this is likely to be relevant is when a user has a *really*
 ///       long literal string with no newlines that they for some reason
 ///       want to store in their source code.
-const MAX_LINE_LENGTH: u64 = 4096; // 2^12
+pub const MAX_LINE_LENGTH: u64 = 4096; // 2^12
 const MAX_LINE_LENGTH_USIZE: usize = MAX_LINE_LENGTH as usize;
 
 /// Create source from the specified file.
@@ -62,7 +62,7 @@ pub fn source_from_stdin() -> Source<BufReader<io::Stdin>> {
 /// - Normalizes \r\n line endings to \n. NOTE: \r as a line ending
 ///   is *not* handled. TODO: Detect use of \r as line ending?
 /// - Tracks current line and column.
-/// - Panics when lines are too long.
+/// - Stops reading when a line is too long (see `line_too_long`).
 pub struct Source<T: BufRead> {
     stream: T,
     /// String buffer the source reader reads lines into.
@@ -76,6 +76,9 @@ pub struct Source<T: BufRead> {
     // Indicates whether a newline was added because the source didn't
     // end with one.
     pub newline_added: bool,
+    // Indicates whether reading stopped because a line was too long.
+    // The source appears to end before the long line in that case.
+    pub line_too_long: bool,
 }
 
 impl<T: BufRead> Source<T> {
@@ -89,6 +92,7 @@ impl<T: BufRead> Source<T> {
             lines: Vec::with_capacity(INITIAL_CAPACITY),
             current_char: None,
             newline_added: false,
+            line_too_long: false,
         };
         source.queue.push_back('\n');
         source
@@ -110,7 +114,7 @@ impl<T: BufRead> Source<T> {
     }
 
     fn fill_queue(&mut self) {
-        if self.queue.is_empty() {
+        if self.queue.is_empty() && !self.line_too_long {
             // See if character queue can be refilled from next line.
             self.buffer.clear();
             match self.stream.read_line(&mut self.buffer) {
@@ -118,13 +122,14 @@ impl<T: BufRead> Source<T> {
                     // All lines read; done.
                 }
                 Ok(n) => {
-                    if n > MAX_LINE_LENGTH_USIZE {
-                        panic!("Line is too long (> {MAX_LINE_LENGTH})");
-                    }
                     self.line_no += 1;
                     self.col = 0;
                     // Store unmodified copy of current line.
                     self.lines.push(self.buffer.clone());
+                    if n > MAX_LINE_LENGTH_USIZE {
+                        self.line_too_long = true;
+                        return;
+                    }
                     self.queue.extend(self.buffer.chars());
                     if self.queue.back() == Some(&'\n') {
                         if self.queue.len() > 1 {
@@ -177,6 +182,12 @@ impl<T: BufRead> Source<T> {
         return (queue.get(0), queue.get(1), queue.get(2));
     }
 
+    /// Peek at the nth char (zero-based) on the current line.
+    pub fn peek_nth(&mut self, n: usize) -> Option<&char> {
+        self.fill_queue();
+        self.queue.get(n)
+    }
+
     pub fn loc(&self) -> Location {
         Location::new(self.line_no, self.col)
     }
@@ -191,7 +202,7 @@ impl<T: BufRead> Iterator for Source<T> {
 }
 
 /// Represents a line and column in the source.
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Location {
     pub line: usize,
     pub col: usize,
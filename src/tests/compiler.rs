@@ -1 +1,315 @@
+use num_bigint::BigInt;
 
+use crate::compiler::{CompErr, Compiler};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::source::source_from_text;
+use crate::types::FuncTrait;
+use crate::util::Symbol;
+use crate::vm::{globals, Code, Inst};
+
+fn compile_text(text: &str) -> Code {
+    compile(text, false)
+}
+
+fn compile_text_optimized(text: &str) -> Code {
+    compile(text, true)
+}
+
+fn compile(text: &str, optimize: bool) -> Code {
+    match try_compile(text, optimize) {
+        Ok(code) => code,
+        Err(err) => panic!("Failed to compile: {:?}", err.kind),
+    }
+}
+
+fn try_compile(text: &str, optimize: bool) -> Result<Code, CompErr> {
+    let mut source = source_from_text(text);
+    let scanner = Scanner::new(&mut source);
+    let mut parser = Parser::new(scanner);
+    let module = parser.parse().expect("Failed to parse");
+    let mut compiler = Compiler::default();
+    compiler.set_optimize(optimize);
+    compiler.compile_module_to_code("test", module)
+}
+
+/// Get the line where compiling text fails.
+fn err_line(text: &str) -> usize {
+    match try_compile(text, false) {
+        Ok(_) => panic!("Expected compilation to fail"),
+        Err(err) => err.loc().0.line,
+    }
+}
+
+/// Find the function with the specified name, searching nested
+/// functions too, and apply f to its code.
+fn with_func_code<T>(
+    code: &Code,
+    func_name: &str,
+    f: &dyn Fn(&Code) -> T,
+) -> Option<T> {
+    for obj_ref in code.iter_constants() {
+        let obj = obj_ref.read().unwrap();
+        if let Some(func) = obj.down_to_func() {
+            if func.name() == func_name {
+                return Some(f(func.code()));
+            }
+            if let Some(result) = with_func_code(func.code(), func_name, f) {
+                return Some(result);
+            }
+        }
+    }
+    None
+}
+
+fn is_cell_var(code: &Code, func_name: &str, var_name: &str) -> Option<bool> {
+    with_func_code(code, func_name, &|code| code.is_cell_var(Symbol::intern(var_name)))
+}
+
+fn has_inst(code: &Code, inst: &Inst) -> bool {
+    code.iter_chunk().any(|i| i == inst)
+}
+
+#[test]
+fn only_captured_args_are_cell_vars() {
+    let code = compile_text(
+        "f = (a, b, c) =>\n    \
+            g = () =>\n        \
+                h = () => a\n        \
+                h\n    \
+            k = () => b\n    \
+            (g, k, c)\n",
+    );
+    assert_eq!(is_cell_var(&code, "f", "a"), Some(true));
+    assert_eq!(is_cell_var(&code, "f", "b"), Some(true));
+    assert_eq!(is_cell_var(&code, "f", "c"), Some(false));
+    assert_eq!(is_cell_var(&code, "g", "a"), Some(false));
+}
+
+#[test]
+fn captured_locals_are_cell_vars() {
+    let code = compile_text("f = () =>\n    x = 1\n    () => x\n");
+    assert_eq!(is_cell_var(&code, "f", "x"), Some(true));
+}
+
+#[test]
+fn closures_capture_only_the_vars_they_use() {
+    let code = compile_text(
+        "f = (a) =>\n    \
+            b = 1\n    \
+            g = () =>\n        \
+                h = () => a\n        \
+                h\n    \
+            (g, b)\n",
+    );
+    let capture_sets = |func_name| {
+        with_func_code(&code, func_name, &|code| {
+            code.iter_chunk()
+                .filter_map(|i| match i {
+                    Inst::CaptureSet(names) => Some(names.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    let a = Symbol::intern("a");
+    assert_eq!(capture_sets("f"), Some(vec![vec![a]]));
+    assert_eq!(capture_sets("g"), Some(vec![vec![a]]));
+    assert_eq!(is_cell_var(&code, "f", "b"), Some(false));
+}
+
+#[test]
+fn errors_in_funcs_are_reported_in_order() {
+    assert_eq!(err_line("f = () => this\nx = this\n"), 1);
+    assert_eq!(err_line("x = this\nf = () => this\n"), 1);
+    assert_eq!(err_line("f = () =>\n    g = () => this\n    g\nthis\n"), 2);
+}
+
+#[test]
+fn funcs_can_refer_to_globals_defined_later() {
+    let code = compile_text("f = () => g()\ng = () => 1\n");
+    let g = Inst::LoadGlobal(Symbol::intern("g"));
+    assert_eq!(with_func_code(&code, "f", &|code| has_inst(code, &g)), Some(true));
+    assert_eq!(err_line("f = () => h()\ng = () => 1\n"), 1);
+}
+
+#[test]
+fn loops_renew_cells_only_when_funcs_are_defined() {
+    let is_renew_cells = |i: &Inst| matches!(i, Inst::RenewCells(_));
+    let code = compile_text(
+        "f = () =>\n    \
+            loop ->\n        \
+                x = 1\n        \
+                g = () => x\n        \
+                break g\n",
+    );
+    let result = with_func_code(&code, "f", &|code| {
+        code.iter_chunk().find(|i| is_renew_cells(i)).map(|i| format!("{i:?}"))
+    });
+    assert_eq!(result, Some(Some("RenewCells([0, 1])".to_owned())));
+    let code = compile_text("f = () =>\n    loop ->\n        x = 1\n        break x\n");
+    let count = with_func_code(&code, "f", &|code| {
+        code.iter_chunk().filter(|i| is_renew_cells(i)).count()
+    });
+    assert_eq!(count, Some(0));
+}
+
+#[test]
+fn globals_are_accessed_by_name() {
+    let code = compile_text("x = 1\ny = x\n");
+    let x = Symbol::intern("x");
+    let y = Symbol::intern("y");
+    assert!(has_inst(&code, &Inst::DeclareVar(x)));
+    assert!(has_inst(&code, &Inst::AssignVar(y)));
+    assert!(has_inst(&code, &Inst::LoadVar(x, 0)));
+    assert!(!code.iter_chunk().any(|i| matches!(i, Inst::StoreLocal(_))));
+}
+
+#[test]
+fn locals_are_accessed_by_slot() {
+    let code = compile_text(
+        "f = (a) =>\n    \
+            b = a\n    \
+            block ->\n        \
+                c = b\n        \
+                c\n",
+    );
+    // Slot 0 is this, slot 1 is a, and slot 2 is b.
+    let names = with_func_code(&code, "f", &|code| code.local_names().to_vec());
+    assert_eq!(names, Some(vec![Symbol::intern("b")]));
+    let check = |inst: Inst| with_func_code(&code, "f", &|code| has_inst(code, &inst));
+    assert_eq!(check(Inst::LoadLocal(0, 1)), Some(true));
+    assert_eq!(check(Inst::StoreLocal(2)), Some(true));
+    assert_eq!(check(Inst::DeclareLocals(vec![Symbol::intern("c")])), Some(true));
+    assert_eq!(check(Inst::LoadLocal(1, 2)), Some(true));
+    assert_eq!(check(Inst::StoreLocal(0)), Some(true));
+    assert_eq!(check(Inst::LoadLocal(0, 0)), Some(true));
+    let has_named_access = with_func_code(&code, "f", &|code| {
+        code.iter_chunk().any(|i| {
+            matches!(i, Inst::DeclareVar(_) | Inst::AssignVar(_) | Inst::LoadVar(..))
+        })
+    });
+    assert_eq!(has_named_access, Some(false));
+}
+
+fn count_insts(code: &Code, f: &dyn Fn(&Inst) -> bool) -> usize {
+    code.iter_chunk().filter(|i| f(i)).count()
+}
+
+#[test]
+fn operands_are_not_limited_to_16_bits() {
+    let n = u16::MAX as usize + 1_000;
+    let mut text = String::from("if false ->\n");
+    for i in 0..n {
+        text.push_str(&format!("    x = {i}\n"));
+    }
+    let code = compile_text(&text);
+    let wide = u16::MAX as usize;
+    let is_long_jump =
+        |inst: &Inst| matches!(inst, Inst::JumpIfNot(addr, ..) if *addr > wide);
+    assert_eq!(count_insts(&code, &is_long_jump), 1);
+    let is_wide_const =
+        |inst: &Inst| matches!(inst, Inst::LoadConst(index) if *index > wide);
+    assert!(count_insts(&code, &is_wide_const) > 0);
+}
+
+#[test]
+fn optimizer_folds_constants() {
+    let code = compile_text_optimized("x = 2 + 3 * 4\ny = 1 < 2\n");
+    let index = globals::shared_int_index(&BigInt::from(14)).unwrap();
+    assert!(has_inst(&code, &Inst::LoadGlobalConst(index)));
+    assert!(has_inst(&code, &Inst::LoadTrue));
+    assert_eq!(count_insts(&code, &|i| matches!(i, Inst::BinaryOp(_))), 0);
+    assert_eq!(count_insts(&code, &|i| matches!(i, Inst::CompareOp(_))), 0);
+}
+
+#[test]
+fn optimizer_does_not_fold_division_by_zero() {
+    let code = compile_text_optimized("x = 1 / 0\n");
+    assert_eq!(count_insts(&code, &|i| matches!(i, Inst::BinaryOp(_))), 1);
+}
+
+#[test]
+fn optimizer_does_not_fold_vars() {
+    let code = compile_text_optimized("x = 1\ny = x + 2\n");
+    assert_eq!(count_insts(&code, &|i| matches!(i, Inst::BinaryOp(_))), 1);
+}
+
+#[test]
+fn optimizer_removes_unreachable_code() {
+    let text = "f = (x) =>\n    return x\n    x + 1\n";
+    let code = compile_text(text);
+    let count = with_func_code(&code, "f", &|code| {
+        count_insts(code, &|i| matches!(i, Inst::BinaryOp(_)))
+    });
+    assert_eq!(count, Some(1));
+    let code = compile_text_optimized(text);
+    let count = with_func_code(&code, "f", &|code| {
+        count_insts(code, &|i| matches!(i, Inst::BinaryOp(_)))
+    });
+    assert_eq!(count, Some(0));
+}
+
+#[test]
+fn optimizer_removes_noops() {
+    let text = "a = true\nb = false\nc = a && b\n";
+    let code = compile_text(text);
+    assert_eq!(count_insts(&code, &|i| matches!(i, Inst::NoOp)), 2);
+    let code = compile_text_optimized(text);
+    assert_eq!(count_insts(&code, &|i| matches!(i, Inst::NoOp)), 0);
+}
+
+#[test]
+fn optimizer_hoists_loop_invariant_loads() {
+    let text = "g = 2\n\
+        h = (x) => x\n\
+        f = (xs) =>\n    \
+            i = 0\n    \
+            loop i < xs.length ->\n        \
+                h(xs.length * g)\n        \
+                h(g)\n        \
+                i += 1\n";
+    let count = |code: &Code, f: &dyn Fn(&Inst) -> bool| {
+        with_func_code(code, "f", &|code| count_insts(code, f)).unwrap()
+    };
+    let code = compile_text(text);
+    assert_eq!(count(&code, &|i| matches!(i, Inst::LoadGlobal(_))), 4);
+    let code = compile_text_optimized(text);
+    assert_eq!(count(&code, &|i| matches!(i, Inst::LoadGlobal(_))), 2);
+    assert_eq!(count(&code, &|i| matches!(i, Inst::LoadHoisted(..))), 4);
+}
+
+#[test]
+fn instructions_are_mapped_to_statement_locations() {
+    let text = "a = 1 + 2\nb = false\nif a > 2 && b ->\n    c = a\nd = a\n";
+    for code in [compile_text(text), compile_text_optimized(text)] {
+        let lines: Vec<usize> = (0..code.len_chunk())
+            .map(|addr| {
+                let (span, loc) = code.location_span(addr);
+                assert!(span.contains(&addr), "{addr} not in {span:?}");
+                loc.expect("Expected instruction to have a location").0.line
+            })
+            .collect();
+        assert_eq!(lines.first(), Some(&1));
+        assert_eq!(lines.last(), Some(&5));
+        assert!(lines.contains(&4));
+        assert!(lines.windows(2).all(|w| w[0] <= w[1]), "{lines:?}");
+    }
+}
+
+#[test]
+fn calls_in_tail_position_are_tail_calls() {
+    let code = compile_text(
+        "g = (n) => n\n\
+        f = (n) =>\n    \
+            if n == 0 ->\n        \
+                return g(n)\n    \
+            x = g(n)\n    \
+            if x -> f(n - 1) else -> g(x)\n",
+    );
+    let count = |f: &dyn Fn(&Inst) -> bool| {
+        with_func_code(&code, "f", &|code| count_insts(code, f)).unwrap()
+    };
+    assert_eq!(count(&|i| matches!(i, Inst::TailCall(_))), 3);
+    assert_eq!(count(&|i| matches!(i, Inst::Call(_))), 1);
+}
@@ -1,5 +1,8 @@
+use std::sync::{Arc, Mutex};
+
 use crate::exe::Executor;
 use crate::result::{ExeErrKind, ExeResult};
+use crate::types::{new, ObjectTrait};
 use crate::vm::RuntimeErrKind;
 
 fn execute(source: &str) -> ExeResult {
@@ -10,7 +13,7 @@ fn execute(source: &str) -> ExeResult {
 
 #[test]
 fn test_too_much_recursion() {
-    let result = execute("f = () => f()\nf()");
+    let result = execute("f = () => 1 + f()\nf()");
     assert!(result.is_err());
     let err = result.unwrap_err();
     assert!(matches!(
@@ -18,3 +21,546 @@ fn test_too_much_recursion() {
         ExeErrKind::RuntimeErr(RuntimeErrKind::RecursionDepthExceeded(_))
     ));
 }
+
+#[test]
+fn test_calling_missing_method_on_nil() {
+    let result = execute("config = nil\nconfig.get('x')");
+    let err = result.unwrap_err();
+    if let ExeErrKind::RuntimeErr(RuntimeErrKind::NotCallable(msg)) = &err.kind {
+        assert!(msg.contains("config is nil; use config?.get"), "{msg}");
+    } else {
+        panic!("Expected NotCallable; got {:?}", err.kind);
+    }
+}
+
+#[test]
+fn test_tail_calls_run_in_constant_stack_space() {
+    let result = execute(
+        "f = (n) => if n == 0 -> 'done' else -> f(n - 1)\n\
+        assert(f(1000) == 'done', f(1000), true)\n\
+        g = (n, acc) =>\n    \
+            if n == 0 ->\n        \
+                return acc\n    \
+            g(n - 1, acc + n)\n\
+        assert(g(1000, 0) == 500500, g(1000, 0), true)\n\
+        even = (n) => if n == 0 -> true else -> odd(n - 1)\n\
+        odd = (n) => if n == 0 -> false else -> even(n - 1)\n\
+        assert(even(1000), '', true)",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn test_tail_call_in_try_block_is_caught() {
+    let result = execute(
+        "f = () => 1 + nil\n\
+        g = () =>\n    \
+            try ->\n        \
+                return f()\n    \
+            catch err ->\n        \
+                'caught'\n\
+        assert(g() == 'caught', g(), true)",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[cfg(feature = "std-full")]
+#[test]
+fn test_deterministic_mode() {
+    use crate::util::deterministic;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // Deterministic mode is process wide, so make sure it's disabled
+    // again even if an assertion fails.
+    let _lock = crate::tests::lock_deterministic();
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            deterministic::set_enabled(false);
+            deterministic::set_clock(None);
+        }
+    }
+    let _reset = Reset;
+
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.set_deterministic(true);
+    exe.bootstrap().unwrap();
+    let result = exe.execute_text(
+        "import std.system\n\
+        import std.time\n\
+        assert(system.deterministic, 'expected deterministic', true)\n\
+        assert(time.timestamp() == 946684800.0, 'expected frozen clock', true)\n\
+        assert(time.now() == DateTime.from_timestamp(946684800), time.now(), true)\n\
+        assert(time.ticks() == 0.0, 'expected frozen ticks', true)",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+    assert_eq!(deterministic::random_seed(), deterministic::DETERMINISTIC_SEED);
+    assert_eq!(deterministic::now(), UNIX_EPOCH + deterministic::DETERMINISTIC_NOW);
+    let injected = UNIX_EPOCH + Duration::from_secs(42);
+    exe.set_clock(Some(injected));
+    assert_eq!(deterministic::now(), injected);
+    exe.set_clock(None);
+
+    // Each time deterministic mode is set, random numbers start over
+    // from the deterministic seed.
+    exe.load_module_from_text(
+        "det_random",
+        "import std.random\nnext = () => random.random()\n",
+    )
+    .unwrap();
+    let first = exe.call("det_random", "next", vec![]).unwrap();
+    assert_ne!(exe.call("det_random", "next", vec![]).unwrap(), first);
+    exe.set_deterministic(true);
+    assert_eq!(exe.call("det_random", "next", vec![]).unwrap(), first);
+    exe.set_deterministic(false);
+    assert_ne!(exe.call("det_random", "next", vec![]).unwrap(), first);
+}
+
+#[test]
+fn test_map_iteration_order() {
+    let result = execute(
+        "m = {'c': 1, 'a': 2, 'b': 3}\n\
+        m.add('0', 4)\n\
+        m.remove('a')\n\
+        assert(m.keys() == ('c', 'b', '0'), m.keys(), true)\n\
+        assert(m.values() == (1, 3, 4), m.values(), true)\n\
+        keys = []\n\
+        m.each((k, v) => keys.push(k))\n\
+        assert(keys == ['c', 'b', '0'], keys, true)",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn test_repl_keeps_bindings_defined_before_error() {
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    let module = new::intrinsic_module("$repl", "$repl", "", &[]);
+    exe.add_module("$repl", module.clone());
+    let result = exe.execute_repl("x = 1\ny = 1 + nil", module.clone());
+    assert!(result.is_err());
+    {
+        let module = module.read().unwrap();
+        let module = module.down_to_mod().unwrap();
+        assert!(module.get_global("x").is_some());
+        assert!(module.get_global("y").is_none());
+    }
+    let result = exe.execute_repl("assert(x == 1, 'x not kept', true)", module);
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn test_custom_import_resolver() {
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.set_import_resolver(Box::new(|name| {
+        if name == "plugins.hello" {
+            let module = new::intrinsic_module(name, "<plugin>", "", &[]);
+            crate::modules::add_module(name, module.clone());
+            Ok(module)
+        } else {
+            Err(crate::vm::RuntimeErr::module_not_found(name))
+        }
+    }));
+    let result = exe.execute_text(
+        "import std.system\n\
+        plugin = system.import('plugins.hello')\n\
+        assert(plugin.$name == 'plugins.hello', plugin, true)\n\
+        err = system.import('plugins.nope').err\n\
+        assert(err.type == ErrType.module_not_found, err, true)",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+/// Create a temp directory containing the specified module files.
+fn create_module_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("feint-{name}-{}", std::process::id()));
+    for (path, text) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_import_from_search_path() {
+    let dir = create_module_dir(
+        "search-path",
+        &[
+            ("sp_pkg/util.fi", "import sp_pkg.consts\nx = consts.y + 1\n"),
+            ("sp_pkg/consts.fi", "y = 1\n"),
+        ],
+    );
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.add_search_path(&dir);
+    let result = exe.execute_text(
+        "import std.system\n\
+        import sp_pkg.util\n\
+        assert(util.x == 2, util.x, true)\n\
+        assert(system.modules.has('sp_pkg.util'), 'not cached', true)",
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn test_repl_and_imported_module_globals_are_isolated() {
+    let dir = create_module_dir("isolated-globals", &[("ig_mod.fi", "y = 2\n")]);
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.add_search_path(&dir);
+    let module = new::intrinsic_module("$repl", "$repl", "", &[]);
+    exe.add_module("$repl", module.clone());
+    let result = exe.execute_repl("x = 1", module.clone());
+    assert!(result.is_ok(), "{:?}", result.err());
+    let result = exe.execute_repl("import ig_mod", module.clone());
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(result.is_ok(), "{:?}", result.err());
+    let result = exe.execute_repl(
+        "assert(x == 1, 'x not kept', true)\n\
+        assert(ig_mod.y == 2, ig_mod.y, true)",
+        module.clone(),
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+    {
+        let module = module.read().unwrap();
+        let module = module.down_to_mod().unwrap();
+        assert!(module.get_global("y").is_none());
+    }
+    let ig_mod = crate::modules::maybe_get_module("ig_mod").unwrap();
+    let ig_mod = ig_mod.read().unwrap();
+    let ig_mod = ig_mod.down_to_mod().unwrap();
+    assert!(ig_mod.get_global("x").is_none());
+}
+
+#[test]
+fn test_circular_import() {
+    let dir = create_module_dir(
+        "circular-import",
+        &[("ci_a.fi", "import ci_b\n"), ("ci_b.fi", "import ci_a\n")],
+    );
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.add_search_path(&dir);
+    let result = exe.execute_text("import ci_a");
+    std::fs::remove_dir_all(dir).unwrap();
+    match result {
+        Err(err) => match err.kind {
+            ExeErrKind::CircularImport(chain) => {
+                assert_eq!(chain, "ci_a -> ci_b -> ci_a")
+            }
+            kind => panic!("Expected circular import error; got {kind:?}"),
+        },
+        Ok(_) => panic!("Expected circular import error"),
+    }
+}
+
+#[test]
+fn test_call_from_rust() {
+    use crate::value::Value;
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.load_module_from_text(
+        "embedded",
+        "add = (a, b) => a + b\n\
+        scale = (items, n) => items.map((x) => x * n)\n\
+        greet = (name) => 'Hello, ' + name",
+    )
+    .unwrap();
+    let result = exe.call("embedded", "add", vec![1.into(), 2.into()]);
+    assert_eq!(result.unwrap(), Value::Int(3));
+    let result = exe.call("embedded", "add", vec![1.into(), 2.5.into()]);
+    assert_eq!(result.unwrap(), Value::Float(3.5));
+    let result = exe.call("embedded", "scale", vec![vec![1, 2].into(), 3.into()]);
+    assert_eq!(result.unwrap(), Value::from(vec![3, 6]));
+    let result = exe.call("embedded", "greet", vec!["Rust".into()]);
+    assert_eq!(String::try_from(result.unwrap()), Ok("Hello, Rust".to_owned()));
+    assert!(exe.call("embedded", "nope", vec![]).is_err());
+    assert!(exe.call("embedded", "add", vec![1.into()]).is_err());
+    // The VM is still usable after an error.
+    let result = exe.call("embedded", "add", vec![2.into(), 2.into()]);
+    assert_eq!(result.unwrap(), Value::Int(4));
+}
+
+#[test]
+fn test_native_module() {
+    use crate::value::Value;
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.register_native_module("native_test", |module| {
+        module
+            .doc("Native test module")
+            .func("add", &["a", "b"], |args| {
+                let a =
+                    i64::try_from(args[0].clone()).map_err(|_| "a: expected Int")?;
+                let b =
+                    i64::try_from(args[1].clone()).map_err(|_| "b: expected Int")?;
+                Ok(Value::Int(a + b))
+            })
+            .func("count", &[""], |args| match &args[0] {
+                Value::List(items) => Ok(Value::Int(items.len() as i64)),
+                _ => Err("expected var args".to_owned()),
+            });
+    });
+    let result = exe.execute_text(
+        "import native_test\n\
+        assert(native_test.add(1, 2) == 3, native_test.add(1, 2), true)\n\
+        assert(native_test.count(1, 2, 3) == 3, native_test.count(1, 2, 3), true)\n\
+        result = native_test.add(1, 'x').err\n\
+        assert(result.type == ErrType.native, result, true)",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+    let result = exe.call("native_test", "add", vec![2.into(), 3.into()]);
+    assert_eq!(result.unwrap(), Value::Int(5));
+}
+
+#[test]
+fn test_panic_is_reported_as_internal_err() {
+    use crate::value::Value;
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.register_native_module("panic_test", |module| {
+        module.func("boom", &[], |_| -> Result<Value, String> { panic!("boom") });
+    });
+    let result = exe.execute_text("import panic_test\npanic_test.boom()");
+    assert!(matches!(
+        result.unwrap_err().kind,
+        ExeErrKind::InternalErr(message) if message == "boom"
+    ));
+    // The executor is still usable after a panic.
+    let result = exe.execute_text("assert(1 + 1 == 2, '', true)");
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn test_panic_during_import_poisoning_module_lock() {
+    use crate::modules::maybe_get_module;
+    use crate::value::Value;
+    let dir = create_module_dir(
+        "panic-import",
+        &[("panic_import.fi", "import poison_test\npoison_test.boom()\n")],
+    );
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.add_search_path(&dir);
+    exe.register_native_module("poisoned", |_| {});
+    exe.register_native_module("poison_test", |module| {
+        module.func("boom", &[], |_| -> Result<Value, String> {
+            // Panic while holding a module's lock, poisoning it.
+            let poisoned = maybe_get_module("poisoned").unwrap();
+            let _guard = poisoned.write().unwrap();
+            panic!("boom")
+        });
+    });
+    let result = exe.execute_text("import panic_import");
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(matches!(
+        result.unwrap_err().kind,
+        ExeErrKind::InternalErr(message) if message == "boom"
+    ));
+    // The poisoned lock was cleared, so the module is still usable.
+    let result = exe.execute_text(
+        "import poisoned\n\
+        assert(poisoned.$name == 'poisoned', poisoned.$name, true)",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[cfg(feature = "std-full")]
+#[test]
+fn test_intrinsic_funcs_are_registered() {
+    use crate::modules::registry;
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    let specs = exe.intrinsic_func_specs();
+    let (_, proc_specs) = specs.iter().find(|(group, _)| group == "std.proc").unwrap();
+    assert_eq!(proc_specs.len(), 3);
+    let registry = registry::registry();
+    assert!(registry.get("std.Set", "add").is_some());
+    assert!(registry.get("std", "new_type").is_some());
+    let run = registry.get("std.proc", "run").unwrap();
+    assert_eq!(run.params, vec!["cmd", "args"]);
+    assert!(!run.is_prop);
+    assert!(registry.get("std.TempPath", "path").unwrap().is_prop);
+    assert!(registry.conflicts().is_empty());
+}
+
+#[test]
+fn test_intrinsic_func_conflicts_are_detected() {
+    use crate::modules::registry::{FuncRegistry, FuncSpec};
+    let spec = FuncSpec {
+        group: "test".to_owned(),
+        name: "f".to_owned(),
+        params: vec![],
+        doc: "first".to_owned(),
+        is_prop: false,
+    };
+    let mut registry = FuncRegistry::default();
+    registry.register(spec.clone());
+    registry.register(FuncSpec { doc: "second".to_owned(), ..spec });
+    assert_eq!(registry.conflicts(), vec!["test.f"]);
+    assert_eq!(registry.get("test", "f").unwrap().doc, "first");
+    registry.remove_group("test");
+    assert!(registry.conflicts().is_empty());
+}
+
+#[test]
+fn test_print_result() {
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.set_print_result(true);
+    exe.bootstrap().unwrap();
+    for source in ["1 + 2", "x = nil", "", "f = () => 1\nf()"] {
+        let result = exe.execute_text(source);
+        assert!(result.is_ok(), "{source:?}: {:?}", result.err());
+    }
+}
+
+#[test]
+fn test_output_handler() {
+    let output = Arc::new(Mutex::new(vec![]));
+    let handler_output = output.clone();
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.set_output_handler(Box::new(move |text, is_err| {
+        handler_output.lock().unwrap().push((text.to_owned(), is_err));
+    }));
+    exe.bootstrap().unwrap();
+    assert!(exe.execute_text("print('hi')").is_ok());
+    assert_eq!(output.lock().unwrap()[0], ("hi\n".to_owned(), false));
+    assert!(exe.execute_text("x").is_err());
+    assert!(output.lock().unwrap()[1..].iter().all(|(_, is_err)| *is_err));
+    assert!(output.lock().unwrap().len() > 1);
+}
+
+#[cfg(feature = "std-full")]
+#[test]
+fn test_output_handler_used_by_threads() {
+    let output = Arc::new(Mutex::new(vec![]));
+    let handler_output = output.clone();
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.set_output_handler(Box::new(move |text, is_err| {
+        handler_output.lock().unwrap().push((text.to_owned(), is_err));
+    }));
+    exe.bootstrap().unwrap();
+    let result = exe.execute_text(
+        "from std.thread import spawn\nspawn(() => print('thread')).join()",
+    );
+    assert!(result.is_ok(), "{:?}", result.err());
+    assert_eq!(output.lock().unwrap()[0], ("thread\n".to_owned(), false));
+}
+
+#[test]
+fn test_cancel_handle() {
+    use std::thread;
+    use std::time::Duration;
+
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    exe.load_module_from_text("spin", "forever = () =>\n    loop -> nil\n").unwrap();
+    let handle = exe.cancel_handle();
+    let cancel_later = || {
+        let handle = handle.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.cancel();
+        })
+    };
+
+    // Cancellation can't be caught by FeInt code.
+    let canceller = cancel_later();
+    let result = exe.execute_text("try ->\n    loop -> nil\ncatch e ->\n    nil\n");
+    canceller.join().unwrap();
+    assert!(result.unwrap_err().is_cancelled());
+
+    let canceller = cancel_later();
+    let result = exe.call("spin", "forever", vec![]);
+    canceller.join().unwrap();
+    assert!(result.unwrap_err().is_cancelled());
+
+    // The VM is still usable after being cancelled.
+    assert!(exe.execute_text("1 + 1").is_ok());
+}
+
+#[test]
+fn test_cancel_tail_recursion() {
+    use std::thread;
+    use std::time::Duration;
+
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    let handle = exe.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.cancel();
+    });
+    let result = exe.execute_text("f = (n) => f(n + 1)\nf(0)\n");
+    canceller.join().unwrap();
+    assert!(result.unwrap_err().is_cancelled());
+}
+
+#[test]
+fn test_bundle() {
+    let dir = create_module_dir(
+        "bundle",
+        &[
+            (
+                "bd_main.fi",
+                "import bd_pkg.util\n\
+                from bd_pkg.util import double as twice\n\
+                x = 'main'\n\
+                bd_pkg_util__x = 'taken'\n\
+                assert(util.double(2) == 4, util.double(2), true)\n\
+                assert(twice(3) == 6, twice(3), true)\n\
+                assert(util.x == 'util', util.x, true)\n\
+                assert(util.describe() == 'util 2', util.describe(), true)\n\
+                assert(x == 'main', x, true)\n\
+                assert(bd_pkg_util__x == 'taken', bd_pkg_util__x, true)\n",
+            ),
+            (
+                "bd_pkg/util.fi",
+                "import bd_pkg.consts\n\
+                x = 'util'\n\
+                double = (n) => n * consts.FACTOR\n\
+                describe = () => $\"{x} {consts.FACTOR}\"\n",
+            ),
+            ("bd_pkg/consts.fi", "FACTOR = 2\n"),
+        ],
+    );
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    let bundle = exe.bundle_file(&dir.join("bd_main.fi"));
+    let bundle_again = exe.bundle_file(&dir.join("bd_main.fi"));
+    std::fs::remove_dir_all(dir).unwrap();
+    let bundle = bundle.unwrap();
+    assert_eq!(bundle, bundle_again.unwrap());
+    assert!(!bundle.contains("import bd_pkg"), "{bundle}");
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.bootstrap().unwrap();
+    let result = exe.execute_text(&bundle);
+    assert!(result.is_ok(), "{:?}\n{bundle}", result.err());
+    assert!(crate::modules::maybe_get_module("bd_pkg.util").is_none());
+}
+
+#[test]
+fn test_bundle_circular_import() {
+    let dir = create_module_dir(
+        "bundle-circular-import",
+        &[
+            ("main.fi", "import bc_a\n"),
+            ("bc_a.fi", "import bc_b\n"),
+            ("bc_b.fi", "import bc_a\n"),
+        ],
+    );
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    let result = exe.bundle_file(&dir.join("main.fi"));
+    std::fs::remove_dir_all(dir).unwrap();
+    match result {
+        Err(err) => match err.kind {
+            ExeErrKind::CircularImport(chain) => {
+                assert_eq!(chain, "bc_a -> bc_b -> bc_a")
+            }
+            kind => panic!("Expected circular import error; got {kind:?}"),
+        },
+        Ok(_) => panic!("Expected circular import error"),
+    }
+}
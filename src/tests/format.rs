@@ -151,3 +151,23 @@ fn scan_alt_delimiters() {
     let result = scan_format_string("1}", delimiters);
     assert_eq!(result, Err(UnmatchedClosingBracket(1)));
 }
+
+#[test]
+fn scan_repr_conversion() {
+    let tokens = scan_ok("{x!r}", 1, None);
+    assert!(matches!(tokens.first(), Some(ReprExpr(_))), "{tokens:?}");
+    let tokens = scan_ok("a{f(1)!r}b", 3, None);
+    assert!(matches!(tokens.get(1), Some(ReprExpr(_))), "{tokens:?}");
+    // Negation isn't a conversion.
+    let tokens = scan_ok("{a == !r}", 1, None);
+    assert!(matches!(tokens.first(), Some(Expr(_))), "{tokens:?}");
+}
+
+#[test]
+fn split_conversion_flag() {
+    assert_eq!(split_conversion("x!r"), ("x", true));
+    assert_eq!(split_conversion("(1, 2)!r"), ("(1, 2)", true));
+    assert_eq!(split_conversion("x"), ("x", false));
+    assert_eq!(split_conversion("!r"), ("!r", false));
+    assert_eq!(split_conversion("a && !r"), ("a && !r", false));
+}
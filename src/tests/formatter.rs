@@ -0,0 +1,119 @@
+use crate::formatter::format_text;
+
+/// Check that text is formatted as expected and that formatting the
+/// result doesn't change it.
+fn check(text: &str, expected: &str) {
+    let formatted = format_text(text).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format_text(&formatted).unwrap(), expected);
+}
+
+#[test]
+fn test_empty() {
+    check("", "");
+    check("\n\n", "");
+}
+
+#[test]
+fn test_operator_spacing() {
+    check(
+        "x=1+2*  3\ny = a.b(1,2)[0]\nz = -x^2\nr = 1 .. 10\nn = a?.b ?? c\n",
+        "x = 1 + 2 * 3\ny = a.b(1, 2)[0]\nz = -x ^ 2\nr = 1..10\nn = a?.b ?? c\n",
+    );
+}
+
+#[test]
+fn test_parens() {
+    check(
+        "x = (1 + 2) * 3\ny = 1 + (2 * 3)\nz = !(!x)\nf = (() => 1)()\n",
+        "x = (1 + 2) * 3\ny = 1 + 2 * 3\nz = !(!x)\nf = (() => 1)()\n",
+    );
+}
+
+#[test]
+fn test_literals_are_kept() {
+    let text = "x = 0xFF + 1_000\ny = 'single' + \"double\"\nz = $\"{x} {y}\"\n";
+    check(text, text);
+}
+
+#[test]
+fn test_blocks() {
+    check(
+        "f = (a:Int,b)=>\n    if a>b -> a else -> b\n\ng = (x) => x*2\n",
+        "f = (a: Int, b) =>\n    if a > b -> a else -> b\n\ng = (x) => x * 2\n",
+    );
+    let text = "x = if true ->\n    1\nelse -> 2\n";
+    check(text, text);
+}
+
+#[test]
+fn test_blank_lines() {
+    check(
+        "x = 1\n\n\n\n\ny = () =>\n\n    a = 1\n\n\n    a\n",
+        "x = 1\n\n\ny = () =>\n    a = 1\n\n    a\n",
+    );
+}
+
+#[test]
+fn test_comments() {
+    check(
+        "# leading\nx = 1    # trailing\n\n\n\n# end\n",
+        "# leading\nx = 1  # trailing\n\n\n# end\n",
+    );
+    let text = "f = () =>\n    # a\n    x = 1  # b\n    # c\n\n# d\ny = 2\n";
+    check(text, text);
+}
+
+#[test]
+fn test_multi_line_items() {
+    check(
+        "x = [\n    1,\n    2  # two\n]\ny = f(\n  a, b)\nz = {\"a\": 1,\"b\": (1,)}\n",
+        "x = [\n    1,\n    2,  # two\n]\ny = f(\n    a,\n    b,\n)\nz = {\"a\": 1, \"b\": (1,)}\n",
+    );
+}
+
+#[test]
+fn test_formatted_text_is_unchanged() {
+    let text = "\
+import std.args as args
+from std.args import parse
+
+f = async (x, ...) =>
+    await x
+
+g = (
+    n: Int,
+    m,
+) =>
+    \"Docstring
+
+    More docs
+
+    \"
+    i = 0
+    loop i < n ->
+        i += 1
+        if i == 2 -> continue
+        if i > 5 -> break i
+    loop -> break
+    match n ->
+        0 | 1 -> \"small\"
+        Int i if i > 10 -> \"big\"
+        (a, b) -> a
+        \"x\" + rest -> rest
+        * ->
+            jump done
+    i = 0
+    :done:
+    return
+
+h = () =>
+    try ->
+        x = g(1)
+    catch Err e ->
+        nil
+    ensure ->
+        $print \"done\"
+";
+    check(text, text);
+}
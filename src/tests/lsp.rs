@@ -0,0 +1,129 @@
+use crate::lsp::{serve, Json};
+
+/// Serve messages, returning the messages sent by the server and its
+/// exit code.
+fn serve_messages(messages: &[&str]) -> (Vec<Json>, u8) {
+    let input: String = messages
+        .iter()
+        .map(|message| format!("Content-Length: {}\r\n\r\n{message}", message.len()))
+        .collect();
+    let mut output = vec![];
+    let code = serve(input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let mut output = output.as_str();
+    let mut sent = vec![];
+    while let Some(rest) = output.strip_prefix("Content-Length: ") {
+        let (len, rest) = rest.split_once("\r\n\r\n").unwrap();
+        let len = len.parse::<usize>().unwrap();
+        sent.push(Json::parse(&rest[..len]).unwrap());
+        output = &rest[len..];
+    }
+    assert_eq!(output, "");
+    (sent, code)
+}
+
+fn open(text: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"file:///a.fi","text":{}}}}}}}"#,
+        Json::from(text)
+    )
+}
+
+fn range(start: (usize, usize), end: (usize, usize)) -> Json {
+    let position = |(line, character): (usize, usize)| {
+        Json::object([("line", line.into()), ("character", character.into())])
+    };
+    Json::object([("start", position(start)), ("end", position(end))])
+}
+
+#[test]
+fn test_json() {
+    let text = r#" {"a": [1, -2.5, true, null], "b": "x\"é😀"} "#;
+    let json = Json::parse(text).unwrap();
+    assert_eq!(json.to_string(), "{\"a\":[1,-2.5,true,null],\"b\":\"x\\\"é😀\"}");
+    assert!(Json::parse("[1,]").is_err());
+    assert!(Json::parse("{} {}").is_err());
+}
+
+#[test]
+fn test_initialize_and_shutdown() {
+    let (sent, code) = serve_messages(&[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[0].get("id"), &Json::from(1usize));
+    let capabilities = sent[0].get("result").get("capabilities");
+    assert_eq!(capabilities.get("definitionProvider"), &Json::from(true));
+    assert_eq!(sent[1].get("id"), &Json::from(2usize));
+    assert!(sent[1].get("result").is_null());
+}
+
+#[test]
+fn test_exit_without_shutdown() {
+    let (sent, code) = serve_messages(&[r#"{"jsonrpc":"2.0","method":"exit"}"#]);
+    assert!(sent.is_empty());
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_method_not_found() {
+    let (sent, _) = serve_messages(&[r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#]);
+    assert_eq!(sent[0].get("error").get("code"), &Json::from(-32601i64));
+}
+
+#[test]
+fn test_diagnostics() {
+    let change = r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.fi"},"contentChanges":[{"text":"x = 1\n"}]}}"#;
+    let (sent, _) = serve_messages(&[&open("f = () => y\n"), change]);
+    assert_eq!(sent.len(), 2);
+    let params = sent[0].get("params");
+    assert_eq!(params.get("uri"), &Json::from("file:///a.fi"));
+    let diagnostics = params.get("diagnostics").as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].get("message"), &Json::from("Name not found: y"));
+    assert_eq!(diagnostics[0].get("range"), &range((0, 10), (0, 11)));
+    let diagnostics = sent[1].get("params").get("diagnostics");
+    assert_eq!(diagnostics.as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_syntax_err_diagnostics() {
+    let (sent, _) = serve_messages(&[&open("x = 1 +\ny = )\n")]);
+    let diagnostics = sent[0].get("params").get("diagnostics").as_array().unwrap();
+    assert!(!diagnostics.is_empty());
+    assert_eq!(diagnostics[0].get("severity"), &Json::from(1usize));
+}
+
+#[test]
+fn test_definition() {
+    let (sent, _) = serve_messages(&[
+        &open("x = 1\ny = x + 1\n"),
+        r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.fi"},"position":{"line":1,"character":4}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.fi"},"position":{"line":1,"character":7}}}"#,
+    ]);
+    let result = sent[1].get("result");
+    assert_eq!(result.get("uri"), &Json::from("file:///a.fi"));
+    assert_eq!(result.get("range"), &range((0, 0), (0, 1)));
+    assert!(sent[2].get("result").is_null());
+}
+
+#[test]
+fn test_completion() {
+    let (sent, _) = serve_messages(&[
+        &open("abc = 1\n"),
+        r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/completion","params":{"textDocument":{"uri":"file:///a.fi"},"position":{"line":1,"character":0}}}"#,
+    ]);
+    let items = sent[1].get("result").as_array().unwrap();
+    let detail = |label: &str| {
+        items
+            .iter()
+            .find(|item| item.get("label") == &Json::from(label))
+            .map(|item| item.get("detail").clone())
+    };
+    assert_eq!(detail("abc"), Some(Json::from("global")));
+    assert_eq!(detail("Int"), Some(Json::from("builtin")));
+}
@@ -1,11 +1,25 @@
+use std::sync::{Mutex, MutexGuard};
+
 mod ast;
 mod compiler;
 mod exe;
 mod format;
+mod formatter;
+mod lsp;
 mod parser;
+#[cfg(feature = "repl")]
 mod repl;
 mod run;
 mod scanner;
 mod types;
 mod util;
 mod vm;
+
+/// Deterministic mode is process wide, so tests that enable it and tests
+/// that depend on the real clock hold this lock so they don't run at the
+/// same time. A test that fails while holding the lock doesn't affect
+/// the others.
+fn lock_deterministic() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|err| err.into_inner())
+}
@@ -237,6 +237,98 @@ fn parse_precedence() {
     }
 }
 
+#[test]
+fn parse_set_and_map_literals() {
+    let set_items = |text: &str| match parse_text(text) {
+        Ok(program) => match &program.statements[0].kind {
+            ast::StatementKind::Expr(ast::Expr {
+                kind: ast::ExprKind::Set(items),
+                ..
+            }) => Some(items.len()),
+            _ => None,
+        },
+        Err(err) => panic!("Failed to parse {text}: {err:?}"),
+    };
+    assert_eq!(set_items("{1}"), Some(1));
+    assert_eq!(set_items("{1, 2, 3,}"), Some(3));
+    assert_eq!(set_items("{}"), None);
+    assert_eq!(set_items("{1: 2}"), None);
+    assert!(parse_text("{1, 2: 3}").is_err());
+}
+
+#[test]
+fn parse_match_patterns() {
+    let source = "\
+match v ->
+    1 | 2 | 3 -> 1
+    Int i -> 2
+    (x, 0, (y, z)) -> 3
+    n if n > 10 -> 4
+    n -> 5
+";
+    let arms = match parse_text(source) {
+        Ok(program) => match &program.statements[0].kind {
+            ast::StatementKind::Expr(ast::Expr {
+                kind: ast::ExprKind::Match(_, arms, None),
+                ..
+            }) => arms.clone(),
+            kind => panic!("Expected match; got {kind:?}"),
+        },
+        Err(err) => panic!("Failed to parse {source}: {err:?}"),
+    };
+    assert_eq!(arms.len(), 5);
+    assert!(matches!(&arms[0].pattern, ast::Pattern::Alt(exprs) if exprs.len() == 3));
+    assert!(matches!(&arms[1].pattern, ast::Pattern::Type(_, name) if name == "i"));
+    match &arms[2].pattern {
+        ast::Pattern::Tuple(items) => {
+            assert!(matches!(&items[0], ast::Pattern::Binding(name) if name == "x"));
+            assert!(matches!(&items[1], ast::Pattern::Value(_)));
+            assert!(
+                matches!(&items[2], ast::Pattern::Tuple(items) if items.len() == 2)
+            );
+        }
+        pattern => panic!("Expected tuple pattern; got {pattern:?}"),
+    }
+    assert!(matches!(&arms[3].pattern, ast::Pattern::Binding(name) if name == "n"));
+    assert!(arms[3].guard.is_some());
+    // Without a guard, a plain name is compared by value.
+    assert!(matches!(&arms[4].pattern, ast::Pattern::Value(_)));
+    assert!(arms[4].guard.is_none());
+}
+
+#[test]
+fn parse_match_str_patterns() {
+    let source = "\
+match v ->
+    \"GET \" + path -> 1
+    name + \".txt\" -> 2
+    \"<\" + tag + \">\" -> 3
+    a + b -> 4
+";
+    let arms = match parse_text(source) {
+        Ok(program) => match &program.statements[0].kind {
+            ast::StatementKind::Expr(ast::Expr {
+                kind: ast::ExprKind::Match(_, arms, None),
+                ..
+            }) => arms.clone(),
+            kind => panic!("Expected match; got {kind:?}"),
+        },
+        Err(err) => panic!("Failed to parse {source}: {err:?}"),
+    };
+    let str_pattern = |prefix: Option<&str>, name: &str, suffix: Option<&str>| {
+        ast::Pattern::Str(
+            prefix.map(str::to_owned),
+            name.to_owned(),
+            suffix.map(str::to_owned),
+        )
+    };
+    assert_eq!(arms[0].pattern, str_pattern(Some("GET "), "path", None));
+    assert_eq!(arms[1].pattern, str_pattern(None, "name", Some(".txt")));
+    assert_eq!(arms[2].pattern, str_pattern(Some("<"), "tag", Some(">")));
+    // Concatenated names are compared by value.
+    assert!(matches!(&arms[3].pattern, ast::Pattern::Value(_)));
+}
+
 #[test]
 fn parse_func() {
     let source = "\
@@ -254,3 +346,169 @@ sub(1, 2, 3)
         assert!(false, "Function def failed to parse: {:?}", err);
     }
 }
+
+#[test]
+fn parse_keyword_as_ident() {
+    for source in [
+        "if = 1",
+        "loop = 1",
+        "nil = 1",
+        "import std.system as block",
+        "from std.system import if",
+        "from std.system import argv as nil",
+    ] {
+        let result = parse_text(source);
+        assert!(
+            matches!(
+                result.as_ref().map_err(|errors| &errors[..]),
+                Err([ParseErr { kind: ParseErrKind::KeywordAsIdent(..) }, ..])
+            ),
+            "Expected keyword error for {source:?}; got {result:?}"
+        );
+    }
+}
+
+#[test]
+fn parse_import_from() {
+    let result = parse_text("from std.itertools import zip, unzip as uz");
+    let statements = result.expect("Parse failed").statements;
+    assert_eq!(statements.len(), 1);
+    assert_eq!(
+        statements[0].kind,
+        ast::StatementKind::ImportFrom(
+            "std.itertools".to_owned(),
+            vec![("zip".to_owned(), None), ("unzip".to_owned(), Some("uz".to_owned()))]
+        )
+    );
+}
+
+#[test]
+fn parse_destructuring_assignment() {
+    let statements = parse_text("a, b = b, a").expect("Parse failed").statements;
+    assert_eq!(statements.len(), 1);
+    let (lhs, value) = statements[0].expr().unwrap().assignment().unwrap();
+    let targets: Vec<_> =
+        lhs.tuple_items().unwrap().iter().map(|t| t.is_ident()).collect();
+    assert_eq!(targets, vec![Some("a".to_owned()), Some("b".to_owned())]);
+    assert_eq!(value.tuple_items().unwrap().len(), 2);
+
+    // Parenthesized targets are equivalent.
+    let statements = parse_text("(a, b) = (b, a)").expect("Parse failed").statements;
+    assert!(statements[0]
+        .expr()
+        .unwrap()
+        .assignment()
+        .unwrap()
+        .0
+        .tuple_items()
+        .is_some());
+
+    assert!(parse_text("a, b").is_err());
+}
+
+/// Parse the text and return the warnings, panicking on parse errors.
+fn parse_warnings(text: &str) -> Vec<ParseWarning> {
+    let mut source = source_from_text(text);
+    let scanner = Scanner::new(&mut source);
+    let mut parser = Parser::new(scanner);
+    if let Err(err) = parser.parse() {
+        panic!("Failed to parse {text:?}: {err:?}");
+    }
+    parser.warnings().to_vec()
+}
+
+#[test]
+fn parse_non_exhaustive_bool_match() {
+    let warnings = parse_warnings("match x ->\n    true -> 1\n");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].kind,
+        ParseWarningKind::NonExhaustiveMatch(vec!["false".to_owned()])
+    );
+    assert_eq!(warnings[0].start, Location::new(1, 1));
+}
+
+#[test]
+fn parse_non_exhaustive_enum_match() {
+    let warnings = parse_warnings(
+        "Color = enum (Red, Green, Blue)\n\
+        match c ->\n    Color.Red -> 1\n    Color.Blue -> 3\n",
+    );
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].kind,
+        ParseWarningKind::NonExhaustiveMatch(vec!["Color.Green".to_owned()])
+    );
+}
+
+#[test]
+fn parse_exhaustive_matches() {
+    for source in [
+        "match x ->\n    true -> 1\n    false -> 0\n",
+        "match x ->\n    true -> 1\n    * -> 0\n",
+        "match x ->\n    true -> 1\n    @ -> 0\n",
+        "match x ->\n    true | false -> 1\n",
+        "C = enum (A, B)\nmatch x ->\n    C.A -> 1\n    C.B -> 2\n",
+        "C = enum (A, B)\nmatch x ->\n    C.A | C.B -> 1\n",
+        // Exhaustiveness isn't checked for types with unbounded values
+        "match x ->\n    1 -> 1\n    2 -> 2\n",
+        // or for enums that aren't defined in the same source
+        "match x ->\n    C.A -> 1\n",
+    ] {
+        assert_eq!(parse_warnings(source), vec![], "{source:?}");
+    }
+}
+
+#[test]
+fn parse_reports_multiple_errors() {
+    let errors = parse_text("break\nx = 1\nreturn 2\ny = 3\nnil = 4\n").unwrap_err();
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(matches!(errors[0].kind, ParseErrKind::UnexpectedBreak(..)));
+    assert!(matches!(errors[1].kind, ParseErrKind::UnexpectedReturn(..)));
+    assert!(matches!(errors[2].kind, ParseErrKind::KeywordAsIdent(..)));
+}
+
+#[test]
+fn parse_recovers_in_nested_blocks() {
+    // The error in the function body doesn't stop the rest of the body
+    // or the statements after the function from being parsed.
+    // A block whose only statement fails is skipped without also
+    // reporting that the block is missing.
+    let errors = parse_text(
+        "f = () =>\n    break\n    1\ng = 2\nblock ->\n    continue\nh = 3\nbreak\n",
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(matches!(errors[0].kind, ParseErrKind::UnexpectedBreak(..)));
+    assert!(matches!(errors[1].kind, ParseErrKind::UnexpectedContinue(..)));
+    assert_eq!(errors[1].loc(), Location::new(6, 5));
+    assert!(matches!(errors[2].kind, ParseErrKind::UnexpectedBreak(..)));
+    assert_eq!(errors[2].loc(), Location::new(8, 1));
+}
+
+#[test]
+fn parse_global() {
+    let program = parse_text("f = () =>\n    global a, b\n    a = b\n").unwrap();
+    let func = match &program.statements[0].kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::DeclarationAndAssignment(_, value),
+            ..
+        }) => match &value.kind {
+            ast::ExprKind::Func(func) => func.clone(),
+            kind => panic!("Expected function; got {kind:?}"),
+        },
+        kind => panic!("Expected assignment; got {kind:?}"),
+    };
+    assert_eq!(
+        func.block.statements[0].kind,
+        ast::StatementKind::Global(vec!["a".to_owned(), "b".to_owned()])
+    );
+}
+
+#[test]
+fn parse_global_outside_func() {
+    let errors = parse_text("global a\n").unwrap_err();
+    assert!(matches!(errors[0].kind, ParseErrKind::UnexpectedGlobal(..)));
+    let errors = parse_text("f = () =>\n    global nil\n").unwrap_err();
+    assert!(matches!(errors[0].kind, ParseErrKind::KeywordAsIdent(..)));
+}
@@ -1,5 +1,9 @@
+use std::path::{Path, PathBuf};
+
 use crate::exe::Executor;
-use crate::repl::Repl;
+use crate::repl::{complete, is_incomplete, Repl};
+use crate::types::{new, ObjectRef};
+use crate::vm::VMState;
 
 #[test]
 fn eval_empty() {
@@ -33,14 +37,120 @@ fn eval_if_with_no_block() {
     eval("if true ->");
 }
 
+#[test]
+fn eval_pasted_block() {
+    eval("f = (x) =>\n    y = x + 1\n\n    y * 2\n\nassert(f(1) == 4, f(1), true)");
+}
+
+// Commands ------------------------------------------------------------
+
+#[test]
+fn eval_commands() {
+    eval(":help");
+    eval(":vars");
+    eval(":type 1 + 2");
+    eval(":reset");
+    eval(":no_such_command");
+    eval(":load no_such_file.fi");
+}
+
+#[test]
+fn eval_load() {
+    let path = fixture("hello.fi");
+    eval(&format!(":load {}", path.display()));
+}
+
+#[test]
+fn eval_load_halt_exits() {
+    let path = fixture("halt.fi");
+    let mut repl = Repl::new(None, executor());
+    let result = repl.eval(&format!(":load {}", path.display()), false);
+    assert!(matches!(result, Some(Ok(VMState::Halted(3)))), "{result:?}");
+}
+
+#[test]
+fn eval_reset_keeps_session_usable() {
+    let mut repl = Repl::new(None, executor());
+    assert!(repl.eval("x = 1", false).is_none());
+    assert!(repl.eval(":reset", false).is_none());
+    assert!(repl.eval("x = 2\nassert(x == 2, x, true)", false).is_none());
+}
+
+// Multiline input -----------------------------------------------------
+
+#[test]
+fn complete_input() {
+    assert!(!is_incomplete(""));
+    assert!(!is_incomplete("1 + 2"));
+    assert!(!is_incomplete("x = (\n    1,\n    2,\n)"));
+    assert!(!is_incomplete("x = '(' # ("));
+    assert!(!is_incomplete("f = () => 1"));
+    assert!(!is_incomplete("f = () =>\n    x = 1\n    x\n"));
+    assert!(!is_incomplete("#[ -> ]# 1"));
+}
+
+#[test]
+fn incomplete_input() {
+    assert!(is_incomplete("x = (1, 2"));
+    assert!(is_incomplete("x = {\n    'a': [\n        1,"));
+    assert!(is_incomplete("x = 'abc"));
+    assert!(is_incomplete("#[ comment"));
+    assert!(is_incomplete("if true ->"));
+    assert!(is_incomplete("f = (x) => # comment"));
+    assert!(is_incomplete("x = 1 + \\"));
+    // Multiline blocks are ended with a blank line.
+    assert!(is_incomplete("f = () =>\n    x = 1\n    x"));
+    assert!(is_incomplete("if true ->\n    1\nelse -> 2"));
+}
+
+// Completion ----------------------------------------------------------
+
+#[test]
+fn complete_globals_builtins_and_keywords() {
+    let module = module();
+    assert_eq!(complete(&module, "my_", 3), (0, vec!["my_value".to_owned()]));
+    assert_eq!(complete(&module, "x = divm", 8), (4, vec!["divmod".to_owned()]));
+    assert_eq!(complete(&module, "retu", 4), (0, vec!["return".to_owned()]));
+    assert_eq!(complete(&module, "no_such_", 8), (0, vec![]));
+}
+
+#[test]
+fn complete_attributes() {
+    let module = module();
+    let (start, names) = complete(&module, "Int.ne", 6);
+    assert_eq!(start, 4);
+    assert_eq!(names, vec!["new".to_owned()]);
+    let (start, names) = complete(&module, "Int.", 4);
+    assert_eq!(start, 4);
+    assert!(names.contains(&"abs".to_owned()), "{names:?}");
+    assert_eq!(complete(&module, "nope.x", 6), (5, vec![]));
+}
+
 // Utilities -----------------------------------------------------------
 
-fn eval(input: &str) {
+fn module() -> ObjectRef {
+    new::intrinsic_module(
+        "$repl_test",
+        "$repl_test",
+        "REPL test module",
+        &[("my_value", new::int(1))],
+    )
+}
+
+fn executor() -> Executor {
     let mut exe = Executor::new(16, vec![], false, false, false);
     if let Err(err) = exe.bootstrap() {
         panic!("{err}");
     }
-    let mut repl = Repl::new(None, exe);
+    exe
+}
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/integration/fixtures").join(name)
+}
+
+fn eval(input: &str) {
+    let mut repl = Repl::new(None, executor());
     match repl.eval(input, false) {
         Some(Ok(_)) => assert!(false),
         Some(Err(_)) => assert!(false),
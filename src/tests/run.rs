@@ -7,6 +7,19 @@ fn run_text(text: &str) -> ExeResult {
     exe.execute_text(text)
 }
 
+fn run_text_optimized(text: &str) -> ExeResult {
+    let mut exe = Executor::new(16, vec![], false, false, false);
+    exe.set_optimize(true);
+    exe.bootstrap()?;
+    exe.execute_text(text)
+}
+
+fn run_text_debug(text: &str) -> ExeResult {
+    let mut exe = Executor::new(16, vec![], false, false, true);
+    exe.bootstrap()?;
+    exe.execute_text(text)
+}
+
 fn assert_result_is_ok(result: ExeResult) {
     assert!(result.is_ok(), "{:?}", result.err());
 }
@@ -28,6 +41,46 @@ mod basics {
         assert_result_is_ok(run_text("1.to_str == \"1\""));
         assert_result_is_ok(run_text("[].to_str == \"[]\""));
     }
+
+    #[test]
+    fn test_inplace_ops_on_vars() {
+        assert_result_is_ok(run_text(
+            "x = 1\n\
+            block ->\n    y = 2\n    z = 3\n    x += y * z\n\
+            assert(x == 7, x, true)\n\
+            f = (a) =>\n    a *= 2\n    a\n\
+            assert(f(4) == 8, f(4), true)",
+        ));
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        assert_result_is_ok(run_text(
+            "x = 1 +\n    2 *\n    3\n\
+            assert(x == 7, x, true)\n\
+            f = (a) =>\n    b = a \\\n        + 1\n    b\n\
+            assert(f(1) == 2, f(1), true)\n\
+            assert(true &&\n    x > 1, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_debug_block() {
+        let text = "items = []\n\
+            $debug -> items.push(1)\n\
+            f = () =>\n    $debug ->\n        items.push(2)\n    nil\n\
+            f()\n\
+            x = $debug -> 3\n";
+        assert_result_is_ok(run_text(&format!(
+            "{text}assert(items == [] && x == nil, items, true)"
+        )));
+        assert_result_is_ok(run_text_debug(&format!(
+            "{text}assert(items == [1, 2] && x == 3, items, true)"
+        )));
+        // Debug blocks are stripped before names are resolved.
+        assert_result_is_ok(run_text("$debug -> not_defined()"));
+        assert_result_is_err(run_text_debug("$debug -> not_defined()"));
+    }
 }
 
 mod err {
@@ -40,6 +93,57 @@ mod err {
         ));
     }
 
+    #[test]
+    fn test_err_builtin_with_data() {
+        assert_result_is_ok(run_text(
+            "x = 42\n\
+            e = err(ErrType.arg, $'bad value: {x}', {'value': x, 'type': 'y'})\n\
+            assert(e.err.type == ErrType.arg, e, true)\n\
+            assert(e.err.message == 'bad value: 42', e, true)\n\
+            assert(e.err.value == 42 && e.value == 42, e, true)\n\
+            assert(e.data == {'value': 42, 'type': 'y'}, e.data, true)\n\
+            assert(e.err.data['type'] == 'y', e.data, true)\n\
+            assert(err(ErrType.arg, 'bad', (1, 2)).data == (1, 2), '', true)\n\
+            assert(Err.new(ErrType.arg, 'bad').data == nil, '', true)\n\
+            assert(Err.new(ErrType.arg, 'bad', {'a': 1}).a == 1, '', true)\n\
+            r = try -> err(ErrType.string, 'bad', {'code': 7}) catch e -> e.code\n\
+            assert(r == 7, r, true)\n\
+            assert(err(1, 'bad').err.message.starts_with('err() expected type'), '', true)\n\
+            assert(err(ErrType.arg, 'a', 1, 2).err.message.starts_with('err() expected 2 to 3'), '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_nil_attr_err() {
+        assert_result_is_ok(run_text(
+            "user = nil\n\
+            r = user.name\n\
+            assert(r.err.type == ErrType.attr_not_found, r, true)\n\
+            msg = 'user.name: user is nil; use user?.name if it can be nil'\n\
+            assert(r.err.message == msg, r.err.message, true)\n\
+            f = (x) => nil\n\
+            r = f(1).profile.name\n\
+            msg = 'f(...).profile: f(...) is nil; use f(...)?.profile if it can be nil'\n\
+            assert(r.err.message == msg, r.err.message, true)\n\
+            m = {}\n\
+            r = m.get('a').foo.bar\n\
+            msg = 'm.get(...).foo: m.get(...) is nil; use m.get(...)?.foo if it can be nil'\n\
+            assert(r.err.message == msg, r.err.message, true)",
+        ));
+    }
+
+    #[test]
+    fn test_nil_safe_dot() {
+        assert_result_is_ok(run_text(
+            "user = nil\n\
+            assert(user?.name == nil, user?.name, true)\n\
+            assert(user?.name?.length == nil, '', true)\n\
+            t = (1, 2)\n\
+            assert(t?.length == 2, t?.length, true)\n\
+            assert(t?.0 == 1, t?.0, true)",
+        ));
+    }
+
     #[test]
     fn test_every_obj_has_err_attr() {
         assert_result_is_ok(run_text("nil.err"));
@@ -49,6 +153,175 @@ mod err {
     }
 }
 
+mod bytes {
+    use super::*;
+
+    #[test]
+    fn test_new_hex_and_base64() {
+        assert_result_is_ok(run_text(
+            "b = Bytes.new([0, 1, 255])\n\
+            assert(b.length == 3 && b[2] == 255, b, true)\n\
+            assert(b.to_hex() == '0001ff', b, true)\n\
+            assert(Bytes.from_hex('0001FF') == b, b, true)\n\
+            assert(Bytes.from_hex('0g').err.type == ErrType.string, '', true)\n\
+            assert(Bytes.from_hex('0').err.type == ErrType.string, '', true)\n\
+            assert(Bytes.new('hello').to_base64() == 'aGVsbG8=', '', true)\n\
+            assert(Bytes.from_base64('aGVsbG8') == Bytes.new('hello'), '', true)\n\
+            assert(Bytes.from_base64('aGk=').to_str() == 'hi', '', true)\n\
+            assert(Bytes.from_base64('a*==').err.type == ErrType.string, '', true)\n\
+            assert(Bytes.new([256]).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_slice() {
+        assert_result_is_ok(run_text(
+            "b = Bytes.new('hello')\n\
+            assert(b.slice(1, 3) == Bytes.new('el'), b, true)\n\
+            assert(b.slice(3).to_str() == 'lo', b, true)\n\
+            assert(b.slice(5).is_empty, b, true)\n\
+            assert(b.slice(4, 2).err.type == ErrType.arg, b, true)\n\
+            assert(b.slice(0, 6).err.type == ErrType.arg, b, true)",
+        ));
+    }
+
+    #[test]
+    fn test_encode_and_decode() {
+        assert_result_is_ok(run_text(
+            "b = 'café'.encode('latin-1')\n\
+            assert(b == Bytes.new([99, 97, 102, 233]), b, true)\n\
+            assert(b.decode('Latin_1') == 'café', b, true)\n\
+            assert(b.decode('utf-8').err.type == ErrType.string, b, true)\n\
+            assert(b.decode('ascii').err.type == ErrType.string, b, true)\n\
+            assert('café'.encode('UTF-8').length == 5, '', true)\n\
+            assert('café'.encode('ascii').err.type == ErrType.string, '', true)\n\
+            assert('abc'.encode('ASCII').decode('ascii') == 'abc', '', true)\n\
+            assert('abc'.encode('nope').err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_byte_buffer() {
+        assert_result_is_ok(run_text(
+            "buf = ByteBuffer.new()\n\
+            assert(buf.is_empty, buf, true)\n\
+            assert(buf.push(1) == 1, buf, true)\n\
+            buf.extend([2, 3]).extend(Bytes.new('a'))\n\
+            assert(buf.length == 4, buf, true)\n\
+            buf[0] = 255\n\
+            assert(buf[0] == 255, buf, true)\n\
+            assert(3 in buf, buf, true)\n\
+            assert(buf.to_bytes() == Bytes.new([255, 2, 3, 97]), buf, true)\n\
+            assert(buf.slice(1, 3) == ByteBuffer.new([2, 3]), buf, true)\n\
+            assert(buf.to_hex() == 'ff020361', buf, true)\n\
+            assert(buf.pop() == 97, buf, true)\n\
+            assert(buf.push(256).err.type == ErrType.arg, buf, true)\n\
+            assert(Bytes.new(buf).to_buffer() == buf, buf, true)\n\
+            assert(buf.clear().is_empty, buf, true)\n\
+            assert(buf.pop() == nil, buf, true)",
+        ));
+    }
+
+    #[test]
+    fn test_set_byte_buffer_item_out_of_range() {
+        assert_result_is_err(run_text("buf = ByteBuffer.new([1])\nbuf[0] = 256"));
+    }
+}
+
+mod date_time {
+    use super::*;
+
+    #[test]
+    fn test_new_and_props() {
+        assert_result_is_ok(run_text(
+            "d = DateTime.new(2000, 2, 29, 13, 14, 15.5)\n\
+            assert(d.year == 2000 && d.month == 2 && d.day == 29, d, true)\n\
+            assert(d.hour == 13 && d.minute == 14 && d.second == 15, d, true)\n\
+            assert(d.nanosecond == 500000000, d.nanosecond, true)\n\
+            assert(d.weekday == 2 && d.day_of_year == 60 && d.offset == 0, d, true)\n\
+            assert(d.to_str == '2000-02-29T13:14:15.500+00:00', d, true)\n\
+            assert(DateTime.new(2001, 2, 29).err.type == ErrType.time, '', true)\n\
+            e = DateTime.from_timestamp(0)\n\
+            assert(e.to_str == '1970-01-01T00:00:00+00:00', e, true)\n\
+            assert(DateTime.from_timestamp(86400.5).timestamp == 86400.5, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_arithmetic_and_comparison() {
+        assert_result_is_ok(run_text(
+            "a = DateTime.new(2000, 1, 1)\n\
+            b = a + 90\n\
+            assert(b.minute == 1 && b.second == 30, b, true)\n\
+            assert(b - a == 90.0, b - a, true)\n\
+            assert(a - b == -90.0, a - b, true)\n\
+            assert(b - 90 == a, b - 90, true)\n\
+            assert(a + 0.25 - a == 0.25, a + 0.25, true)\n\
+            assert(a < b && b > a && a <= a && b >= a, '', true)\n\
+            assert(a != b, '', true)",
+        ));
+        assert_result_is_err(run_text("DateTime.new(2000, 1, 1) + 'x'"));
+        assert_result_is_err(run_text("DateTime.new(2000, 1, 1) < 1"));
+    }
+}
+
+mod file {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let path = std::env::temp_dir()
+            .join(format!("feint-file-lines-{}.txt", std::process::id()));
+        std::fs::write(&path, "a\nb\nc\n").unwrap();
+        let result = run_text(
+            format!(
+                "lines = File.new('{}').lines()\n\
+                assert(lines.next() == 'a', '', true)\n\
+                assert(lines.peek() == 'b', '', true)\n\
+                assert(lines.next() == 'b', '', true)\n\
+                assert(lines.next() == 'c', '', true)\n\
+                assert(lines.next() == nil, '', true)",
+                path.display()
+            )
+            .as_str(),
+        );
+        std::fs::remove_file(path).unwrap();
+        assert_result_is_ok(result);
+    }
+
+    #[test]
+    fn test_read_chunk_seek_and_tell() {
+        let path = std::env::temp_dir()
+            .join(format!("feint-file-chunks-{}.bin", std::process::id()));
+        std::fs::write(&path, [b'a', b'b', b'c', b'd', b'e', b'f', 255]).unwrap();
+        let result = run_text(
+            format!(
+                "file = File.new('{}')\n\
+                chunk = file.read_chunk(4)\n\
+                assert(chunk.length == 4, chunk, true)\n\
+                assert(chunk[0] == 97 && chunk[3] == 100, chunk, true)\n\
+                assert(98 in chunk, chunk, true)\n\
+                assert(file.tell() == 4, file.tell(), true)\n\
+                chunk = file.read_chunk(4)\n\
+                assert(chunk.length == 3, chunk, true)\n\
+                assert(chunk[2] == 255, chunk, true)\n\
+                assert(file.read_chunk(4).is_empty, '', true)\n\
+                assert(file.seek(1) == 1, '', true)\n\
+                chunk = file.read_chunk(2)\n\
+                file.seek(1)\n\
+                assert(file.read_chunk(2) == chunk, chunk, true)\n\
+                assert(chunk.to_str() == 'bc', chunk, true)\n\
+                file.seek(6)\n\
+                assert(file.read_chunk(1).to_str().err, '', true)",
+                path.display()
+            )
+            .as_str(),
+        );
+        std::fs::remove_file(path).unwrap();
+        assert_result_is_ok(result);
+    }
+}
+
 mod float {
     use super::*;
 
@@ -56,6 +329,250 @@ mod float {
     fn test_new() {
         assert_result_is_ok(run_text("Float.new(1)"));
     }
+
+    #[test]
+    fn test_arithmetic_temps() {
+        assert_result_is_ok(run_text(
+            "x = (0.5 + 1.0) * 2\n\
+            y = (0.25 + 1) * 1\n\
+            assert(x == 3.0, x, true)\n\
+            assert(y == 1.25, y, true)\n\
+            f = 0.5\n\
+            f += 1\n\
+            g = f\n\
+            f += 1\n\
+            assert(f == 2.5, f, true)\n\
+            assert(g == 1.5, g, true)\n\
+            assert(1000 + 0.5 == 1000.5, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_unboxed_arithmetic() {
+        assert_result_is_ok(run_text(
+            "assert(1 / 2 == 0.5, 1 / 2, true)\n\
+            assert(1.5 * 2 - 0.5 == 2.5, '', true)\n\
+            assert(0.5 < 1.5 + 1, '', true)\n\
+            n = 0.0 / 0.0\n\
+            assert(n != n, n, true)\n\
+            assert(!(n < n), n, true)",
+        ));
+    }
+
+    #[test]
+    fn test_floor_div_returns_int() {
+        assert_result_is_ok(run_text(
+            "x = 7.5\n\
+            assert(x // 2 == 3, x // 2, true)\n\
+            assert((x // 2).$type $$ Int, x // 2, true)\n\
+            assert(-x // 2 == -4, -x // 2, true)\n\
+            assert(x // 0.5 == 15, x // 0.5, true)\n\
+            assert(x / 2 == 3.75, x / 2, true)\n\
+            r = try -> x // 0.0 catch ErrType.arg e -> e.message\n\
+            assert(r == 'Division by zero', r, true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-full")]
+mod fs {
+    use super::*;
+
+    #[test]
+    fn test_temp_file() {
+        assert_result_is_ok(run_text(
+            "import std.fs\n\
+            temp = fs.temp_file()\n\
+            assert(!temp.is_dir, '', true)\n\
+            assert(File.new(temp.path).ok, '', true)\n\
+            assert(temp.cleanup() == nil, '', true)\n\
+            assert(temp.is_cleaned_up, '', true)\n\
+            assert(File.new(temp.path).err.type == ErrType.file_not_found, '', true)\n\
+            assert(temp.cleanup() == nil, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_temp_dir() {
+        assert_result_is_ok(run_text(
+            "import std.fs\n\
+            import std.glob\n\
+            temp = fs.temp_dir()\n\
+            assert(temp.is_dir, '', true)\n\
+            assert(glob.glob(temp.path).next() == temp.path, '', true)\n\
+            assert(temp.cleanup() == nil, '', true)\n\
+            assert(glob.glob(temp.path).next() == nil, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_read_write_and_append() {
+        assert_result_is_ok(run_text(
+            "import std.fs\n\
+            temp = fs.temp_dir()\n\
+            path = fs.join(temp.path, 'a.txt')\n\
+            assert(fs.read_text(path).err.type == ErrType.file_not_found, '', true)\n\
+            assert(fs.write(path, 'a\\nb\\n') == nil, '', true)\n\
+            assert(fs.append(path, 'c') == nil, '', true)\n\
+            assert(fs.read_text(path) == 'a\\nb\\nc', '', true)\n\
+            assert(fs.read_lines(path) == ['a', 'b', 'c'], '', true)\n\
+            assert(fs.write(path, 1).err.type == ErrType.arg, '', true)\n\
+            temp.cleanup()",
+        ));
+    }
+
+    #[test]
+    fn test_read_and_write_bytes() {
+        assert_result_is_ok(run_text(
+            "import std.fs\n\
+            temp = fs.temp_dir()\n\
+            path = fs.join(temp.path, 'a.bin')\n\
+            assert(fs.write(path, Bytes.from_hex('00ff')) == nil, '', true)\n\
+            file = fs.open(path, 'a')\n\
+            assert(file.write(ByteBuffer.new([1, 2])) == 2, '', true)\n\
+            assert(File.new(path).bytes == Bytes.new([0, 255, 1, 2]), '', true)\n\
+            assert(fs.read_bytes(path).to_hex() == '00ff0102', '', true)\n\
+            missing = fs.join(temp.path, 'nope.bin')\n\
+            assert(fs.read_bytes(missing).err.type == ErrType.file_not_found, '', true)\n\
+            temp.cleanup()",
+        ));
+    }
+
+    #[test]
+    fn test_open_modes() {
+        assert_result_is_ok(run_text(
+            "import std.fs\n\
+            temp = fs.temp_dir()\n\
+            path = fs.join(temp.path, 'a.txt')\n\
+            assert(fs.open(path).err.type == ErrType.file_not_found, '', true)\n\
+            file = fs.open(path, 'w')\n\
+            assert(file.mode == 'w', file.mode, true)\n\
+            assert(file.write('abc') == 3, '', true)\n\
+            assert(file.text == 'abc', file.text, true)\n\
+            file = fs.open(path, 'a')\n\
+            assert(file.write('def') == 3, '', true)\n\
+            assert(file.text == 'abcdef', file.text, true)\n\
+            file = fs.open(path)\n\
+            assert(file.write('x').err.type == ErrType.file_unwritable, '', true)\n\
+            assert(fs.open(path, 'x').err.type == ErrType.file_unwritable, '', true)\n\
+            assert(fs.open(path, 'w').text == '', '', true)\n\
+            assert(fs.open(path, 'z').err.type == ErrType.arg, '', true)\n\
+            temp.cleanup()",
+        ));
+    }
+
+    #[test]
+    fn test_copy_remove_and_dirs() {
+        assert_result_is_ok(run_text(
+            "import std.fs\n\
+            temp = fs.temp_dir()\n\
+            a = fs.join(temp.path, 'a.txt')\n\
+            b = fs.join(temp.path, 'b.txt')\n\
+            sub = fs.join(temp.path, 'sub', 'dir')\n\
+            fs.write(a, 'abc')\n\
+            assert(fs.copy(a, b) == 3, '', true)\n\
+            assert(fs.read_text(b) == 'abc', '', true)\n\
+            assert(fs.mkdir(sub) == nil, '', true)\n\
+            assert(fs.mkdir(sub) == nil, '', true)\n\
+            assert(fs.is_dir(sub), '', true)\n\
+            assert(fs.list_dir(temp.path) == ['a.txt', 'b.txt', 'sub'], '', true)\n\
+            assert(fs.remove(a) == nil, '', true)\n\
+            assert(!fs.exists(a), '', true)\n\
+            assert(fs.remove(a).err.type == ErrType.file_not_found, '', true)\n\
+            assert(fs.remove(fs.dirname(sub)).err.type == ErrType.file_unwritable, '', true)\n\
+            assert(fs.remove(sub) == nil, '', true)\n\
+            assert(fs.list_dir(a).err.type == ErrType.file_not_found, '', true)\n\
+            temp.cleanup()",
+        ));
+    }
+
+    #[test]
+    fn test_path_helpers() {
+        assert_result_is_ok(run_text(
+            "import std.fs\n\
+            path = fs.join('a', 'b', 'c.txt')\n\
+            assert(fs.basename(path) == 'c.txt', path, true)\n\
+            assert(fs.basename(fs.dirname(path)) == 'b', path, true)\n\
+            assert(fs.dirname('c.txt') == '', '', true)\n\
+            assert(fs.ext(path) == 'txt', path, true)\n\
+            assert(fs.ext('a') == nil, '', true)\n\
+            assert(fs.abs('.') == fs.abs(fs.join('.', '.')), '', true)\n\
+            assert(fs.abs('nope/nope').err.type == ErrType.file_not_found, '', true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-full")]
+mod glob {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        let dir =
+            std::env::temp_dir().join(format!("feint-glob-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.txt", "a.txt", "c.md"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let result = run_text(
+            format!(
+                "import std.glob\n\
+                dir = '{}'\n\
+                paths = glob.glob($'{{dir}}/*.txt')\n\
+                assert(paths.next() == $'{{dir}}/a.txt', '', true)\n\
+                assert(paths.next() == $'{{dir}}/b.txt', '', true)\n\
+                assert(paths.next() == nil, '', true)\n\
+                assert(glob.glob('[').err.type == ErrType.arg, '', true)",
+                dir.display()
+            )
+            .as_str(),
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+        assert_result_is_ok(result);
+    }
+
+    #[test]
+    fn test_fnmatch() {
+        assert_result_is_ok(run_text(
+            "from std.glob import fnmatch\n\
+            assert(fnmatch('main.rs', '*.rs'), '', true)\n\
+            assert(fnmatch('a1', '[ab]?'), '', true)\n\
+            assert(!fnmatch('main.rs', '*.fi'), '', true)\n\
+            assert(fnmatch('a', '[').err.type == ErrType.arg, '', true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-full")]
+mod hash {
+    use super::*;
+
+    #[test]
+    fn test_hex_digests() {
+        assert_result_is_ok(run_text(
+            "import std.hash\n\
+            assert(hash.md5('') == 'd41d8cd98f00b204e9800998ecf8427e', '', true)\n\
+            assert(hash.md5('abc') == '900150983cd24fb0d6963f7d28e17f72', '', true)\n\
+            assert(hash.sha1('abc') == 'a9993e364706816aba3e25717850c26c9cd0d89d', '', true)\n\
+            assert(hash.crc32('abc') == '352441c2', '', true)\n\
+            assert(hash.crc32(Bytes.new('abc')) == '352441c2', '', true)\n\
+            expected = 'ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad'\n\
+            assert(hash.sha256(ByteBuffer.new('abc')) == expected, '', true)\n\
+            assert(hash.sha256(1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_digest() {
+        assert_result_is_ok(run_text(
+            "import std.hash\n\
+            assert(hash.digest('crc32', 'abc') == Bytes.from_hex('352441c2'), '', true)\n\
+            assert(hash.digest('sha256', 'abc').length == 32, '', true)\n\
+            assert(hash.digest('md5', 'abc').to_hex() == hash.md5('abc'), '', true)\n\
+            assert(hash.digest('nope', 'abc').err.type == ErrType.arg, '', true)\n\
+            assert(hash.algorithms == ('md5', 'sha1', 'sha256', 'crc32'), '', true)",
+        ));
+    }
 }
 
 mod int {
@@ -65,76 +582,2415 @@ mod int {
     fn test_new() {
         assert_result_is_ok(run_text("Int.new(1)"));
     }
+
+    /// Int temporaries are pooled and reused, so make sure values
+    /// that are still referenced aren't clobbered.
+    #[test]
+    fn test_arithmetic_temps() {
+        assert_result_is_ok(run_text(
+            "x = (1000 + 1) * 2\n\
+            y = (2000 + 1) * 1\n\
+            assert(x == 2002, x, true)\n\
+            assert(y == 2001, y, true)\n\
+            i = 1000\n\
+            loop i < 1010 ->\n    i += 1\n\
+            assert(i == 1010, i, true)\n\
+            j = i\n\
+            i += 1\n\
+            assert(i == 1011, i, true)\n\
+            assert(j == 1010, j, true)",
+        ));
+    }
+
+    /// Ints that don't fit in 64 bits aren't unboxed.
+    #[test]
+    fn test_unboxed_arithmetic_overflow() {
+        assert_result_is_ok(run_text(
+            "x = 9223372036854775807\n\
+            y = x + 1\n\
+            assert(y == 9223372036854775808, y, true)\n\
+            assert(y - 1 == x, y - 1, true)\n\
+            assert(x * x > x, x * x, true)\n\
+            i = x\n\
+            i += 1\n\
+            assert(i == y, i, true)",
+        ));
+    }
+
+    #[test]
+    fn test_division() {
+        assert_result_is_ok(run_text(
+            "a = 7\n\
+            b = 2\n\
+            assert(a / b == 3.5, a / b, true)\n\
+            assert((4 / b).$type $$ Float, 4 / b, true)\n\
+            assert(a // b == 3, a // b, true)\n\
+            assert(-a // b == -4, -a // b, true)\n\
+            assert(a // -b == -4, a // -b, true)\n\
+            assert(-a // -b == 3, -a // -b, true)\n\
+            assert(a // 2.5 == 2, a // 2.5, true)\n\
+            assert((a // 2.5).$type $$ Int, a // 2.5, true)\n\
+            big = 9223372036854775808 * 3\n\
+            assert(-big // b == -13835058055282163712, -big // b, true)\n\
+            r = try -> a // 0 catch ErrType.arg e -> e.message\n\
+            assert(r == 'Division by zero', r, true)\n\
+            r = try -> a % 0 catch ErrType.arg e -> e.message\n\
+            assert(r == 'Division by zero', r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_divmod() {
+        assert_result_is_ok(run_text(
+            "assert(divmod(7, 2) == (3, 1), divmod(7, 2), true)\n\
+            assert(divmod(-7, 2) == (-4, 1), divmod(-7, 2), true)\n\
+            assert(divmod(7, -2) == (-4, -1), divmod(7, -2), true)\n\
+            assert(divmod(7.5, 2) == (3, 1.5), divmod(7.5, 2), true)\n\
+            assert(divmod(7.5, 2).0.$type $$ Int, divmod(7.5, 2), true)\n\
+            r = try -> divmod(1, 0) catch ErrType.arg e -> e.message\n\
+            assert(r == 'Division by zero', r, true)",
+        ));
+    }
 }
 
-mod list {
+#[cfg(feature = "std-min")]
+mod import {
     use super::*;
 
     #[test]
-    fn test_equal() {
-        assert_result_is_ok(run_text("print([] == [])"));
-        assert_result_is_ok(run_text("print([1] == [1.0])"));
-        assert_result_is_ok(run_text("print([1, 'a'] == [1, 'a'])"));
+    fn test_import_in_func() {
+        assert_result_is_ok(run_text(
+            "f = () =>\n    import std.args\n    args\n\
+            assert(f().$name == 'std.args', f(), true)\n\
+            assert(f() $$ f(), 'expected module to be loaded once', true)",
+        ));
     }
 
     #[test]
-    fn test_push() {
+    fn test_import_as() {
         assert_result_is_ok(run_text(
-            "l = []\nl.push(1)\nl.push('a')\nprint(l.length == 2)",
+            "import std.itertools as it\n\
+            assert(it.$name == 'std.itertools', it, true)",
         ));
     }
 
     #[test]
-    fn test_extend() {
+    fn test_import_from() {
         assert_result_is_ok(run_text(
-            "l = []\nl.extend([1, 'a', 2, ()])\nprint(l.length == 4)",
+            "from std.itertools import zip, unzip as uz\n\
+            assert(uz(zip((1, 2), ('a', 'b'))) == ((1, 2), ('a', 'b')), '', true)",
         ));
     }
 
     #[test]
-    fn test_pop() {
-        assert_result_is_ok(run_text("l = [1]\nl.pop()\nprint(l.length == 0)"));
+    fn test_import_from_in_func() {
+        assert_result_is_ok(run_text(
+            "f = () =>\n    from std.itertools import zip\n    zip\n\
+            assert(f() $$ f(), 'expected same object', true)",
+        ));
+    }
+
+    #[test]
+    fn test_import_in_func_not_found() {
+        assert_result_is_ok(run_text("f = () =>\n    import std.nope\n"));
+        assert_result_is_err(run_text("f = () =>\n    import std.nope\nf()"));
     }
 }
 
-mod str {
+#[cfg(feature = "std-min")]
+mod itertools {
     use super::*;
 
     #[test]
-    fn test_new() {
-        assert_result_is_ok(run_text("Str.new(\"string\")"));
-        assert_result_is_ok(run_text("Str.new(nil) == \"nil\""));
-        assert_result_is_ok(run_text("Str.new(true) == \"true\""));
-        assert_result_is_ok(run_text("Str.new(false) == \"false\""));
-        assert_result_is_ok(run_text("Str.new(1) == \"1\""));
-        assert_result_is_ok(run_text("Str.new(1.0) == \"1.0\""));
-        assert_result_is_ok(run_text("Str.new(()) == \"()\""));
-        assert_result_is_ok(run_text("Str.new([]) == \"[]\""));
-        assert_result_is_ok(run_text("Str.new({}) == \"{}\""));
+    fn test_zip() {
         assert_result_is_ok(run_text(
-            "Str.new(print).starts_with(\"function print/0+ @\")",
+            "import std.itertools\n\
+            i = itertools.zip((1, 2, 3), ['a', 'b'])\n\
+            assert(i.next() == (1, 'a'), '', true)\n\
+            assert(i.next() == (2, 'b'), '', true)\n\
+            assert(i.next() == nil, '', true)",
         ));
+    }
+
+    #[test]
+    fn test_unzip() {
         assert_result_is_ok(run_text(
-            "Str.new(() => nil).starts_with(\"function <anonymous>/0 @\")",
+            "import std.itertools\n\
+            i = itertools.zip((1, 2), ('a', 'b'))\n\
+            assert(itertools.unzip(i) == ((1, 2), ('a', 'b')), '', true)",
         ));
     }
 
     #[test]
-    fn test_starts_with() {
-        assert_result_is_ok(run_text("'abc'.starts_with('a')"));
+    fn test_chain() {
+        assert_result_is_ok(run_text(
+            "import std.itertools\n\
+            i = itertools.chain((1,), [2], (3,).iter())\n\
+            assert(i.next() == 1, '', true)\n\
+            assert(i.next() == 2, '', true)\n\
+            assert(i.next() == 3, '', true)\n\
+            assert(i.next() == nil, '', true)",
+        ));
     }
 
     #[test]
-    fn test_starts_with_bad_arg() {
-        assert_result_is_err(run_text("assert('abc'.starts_with(1), '', true)"));
+    fn test_windows_and_chunks() {
+        assert_result_is_ok(run_text(
+            "import std.itertools\n\
+            w = itertools.windows((1, 2, 3), 2)\n\
+            assert(w.next() == (1, 2), '', true)\n\
+            assert(w.next() == (2, 3), '', true)\n\
+            assert(w.next() == nil, '', true)\n\
+            c = itertools.chunks((1, 2, 3), 2)\n\
+            assert(c.next() == (1, 2), '', true)\n\
+            assert(c.next() == (3,), '', true)\n\
+            assert(c.next() == nil, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_count_is_lazy() {
+        assert_result_is_ok(run_text(
+            "import std.itertools\n\
+            c = itertools.count(10, 5)\n\
+            assert(c.next() == 10, '', true)\n\
+            assert(c.peek() == 15, '', true)\n\
+            assert(c.next() == 15, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_repeat() {
+        assert_result_is_ok(run_text(
+            "import std.itertools\n\
+            r = itertools.repeat('x', 2)\n\
+            assert(r.next() == 'x', '', true)\n\
+            assert(r.next() == 'x', '', true)\n\
+            assert(r.next() == nil, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_count_by_and_group_by() {
+        assert_result_is_ok(run_text(
+            "from std.itertools import count_by, group_by\n\
+            pairs = (('a', 1), ('b', 2), ('a', 3))\n\
+            counts = count_by(pairs, (p) => p[0])\n\
+            assert(counts == {'a': 2, 'b': 1}, counts, true)\n\
+            counts = count_by(pairs, (p) => p[1] > 1)\n\
+            assert(counts == {'false': 1, 'true': 2}, counts, true)\n\
+            groups = group_by(pairs, (p) => p[0])\n\
+            assert(groups == {'a': [('a', 1), ('a', 3)], 'b': [('b', 2)]}, groups, true)\n\
+            assert(count_by((), (p) => p) == {}, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_bad_arg() {
+        assert_result_is_ok(run_text(
+            "import std.itertools\nassert(itertools.chunks((1,), 0).err, '', true)",
+        ));
     }
 }
 
-mod tuple {
+mod list {
     use super::*;
 
     #[test]
-    fn test_map() {
-        assert_result_is_ok(run_text("t = (1, 2)\nt.map((item, i) => (item, i))"));
+    fn test_equal() {
+        assert_result_is_ok(run_text("print([] == [])"));
+        assert_result_is_ok(run_text("print([1] == [1.0])"));
+        assert_result_is_ok(run_text("print([1, 'a'] == [1, 'a'])"));
+    }
+
+    #[test]
+    fn test_push() {
+        assert_result_is_ok(run_text(
+            "l = []\nl.push(1)\nl.push('a')\nprint(l.length == 2)",
+        ));
+    }
+
+    #[test]
+    fn test_extend() {
+        assert_result_is_ok(run_text(
+            "l = []\nl.extend([1, 'a', 2, ()])\nprint(l.length == 4)",
+        ));
+    }
+
+    #[test]
+    fn test_pop() {
+        assert_result_is_ok(run_text("l = [1]\nl.pop()\nprint(l.length == 0)"));
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        assert_result_is_ok(run_text(
+            "l = [1, 3]\n\
+            assert(l.insert(1, 2) == 2, l, true)\n\
+            l.insert(3, 4)\n\
+            assert(l == [1, 2, 3, 4], l, true)\n\
+            assert(l.insert(5, 6).err.type == ErrType.index_out_of_bounds, l, true)\n\
+            assert(l.remove(0) == 1, l, true)\n\
+            assert(l == [2, 3, 4], l, true)\n\
+            assert(l.remove(3).err.type == ErrType.index_out_of_bounds, l, true)",
+        ));
+    }
+
+    #[test]
+    fn test_sort_and_reverse() {
+        assert_result_is_ok(run_text(
+            "l = [3, 1, 2]\n\
+            assert(l.sort() == [1, 2, 3], l, true)\n\
+            assert(l.reverse() == [3, 2, 1], l, true)\n\
+            l = ['bb', 'a', 'ccc']\n\
+            l.sort((a, b) => b.length - a.length)\n\
+            assert(l == ['ccc', 'bb', 'a'], l, true)\n\
+            assert(l.sort(1).err.type == ErrType.arg, l, true)",
+        ));
+    }
+
+    #[test]
+    fn test_sort_incomparable_items() {
+        assert_result_is_err(run_text("[1, 'a'].sort()"));
+    }
+
+    #[test]
+    fn test_functional_methods() {
+        assert_result_is_ok(run_text(
+            "l = [1, 2, 3, 4]\n\
+            assert(l.filter((i) => i % 2 == 0) == (2, 4), '', true)\n\
+            assert(l.reduce((acc, i) => acc + i, 0) == 10, '', true)\n\
+            assert(l.map((i) => i * 2) == (2, 4, 6, 8), '', true)\n\
+            assert(l.index_of(3) == 2, '', true)\n\
+            assert(l.index_of(5) == nil, '', true)\n\
+            assert(l.contains(4), '', true)\n\
+            assert(!l.contains(5), '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_large_literal() {
+        let items: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        let lines: Vec<String> = items.chunks(10).map(|c| c.join(", ")).collect();
+        let text = format!(
+            "l = [\n{}\n]\n\
+            assert(l.length == 1000, l.length, true)\n\
+            assert(l[0] == 0, l[0], true)\n\
+            assert(l[999] == 999, l[999], true)",
+            lines.join(",\n")
+        );
+        assert_result_is_ok(run_text(text.as_str()));
+    }
+}
+
+mod map {
+    use super::*;
+
+    #[test]
+    fn test_large_literal() {
+        let entries: Vec<String> = (0..1000).map(|i| format!("'k{i}': {i}")).collect();
+        let lines: Vec<String> = entries.chunks(10).map(|c| c.join(", ")).collect();
+        let text = format!(
+            "m = {{\n{}\n}}\n\
+            assert(m.length == 1000, m.length, true)\n\
+            assert(m['k0'] == 0, m['k0'], true)\n\
+            assert(m['k999'] == 999, m['k999'], true)",
+            lines.join(",\n")
+        );
+        assert_result_is_ok(run_text(text.as_str()));
+    }
+
+    #[test]
+    fn test_keys_values_and_entries() {
+        assert_result_is_ok(run_text(
+            "m = {'a': 1, 'b': 2}\n\
+            assert(m.keys() == ('a', 'b'), '', true)\n\
+            assert(m.values() == (1, 2), '', true)\n\
+            assert(m.entries() == (('a', 1), ('b', 2)), '', true)\n\
+            it = m.iter()\n\
+            assert(it.next() == ('a', 1), '', true)\n\
+            assert(it.next() == ('b', 2), '', true)\n\
+            assert(it.next() == nil, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_get_with_default() {
+        assert_result_is_ok(run_text(
+            "m = {'a': 1}\n\
+            assert(m.get('a', 2) == 1, '', true)\n\
+            assert(m.get('b') == nil, '', true)\n\
+            assert(m.get('b', 2) == 2, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_remove() {
+        assert_result_is_ok(run_text(
+            "m = {'a': 1, 'b': 2, 'c': 3}\n\
+            assert(m.remove('a') == 1, '', true)\n\
+            assert(m.remove('a') == nil, '', true)\n\
+            assert(m.keys() == ('b', 'c'), '', true)\n\
+            assert(!m.has('a'), '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_merge_and_update() {
+        assert_result_is_ok(run_text(
+            "m = {'a': 1, 'b': 2}\n\
+            n = m.merge({'b': 3, 'c': 4})\n\
+            assert(n == {'a': 1, 'b': 3, 'c': 4}, n, true)\n\
+            assert(m == {'a': 1, 'b': 2}, m, true)\n\
+            m.update({'c': 5})\n\
+            assert(m == {'a': 1, 'b': 2, 'c': 5}, m, true)\n\
+            m.update(m)\n\
+            assert(m.length == 3, m, true)\n\
+            assert(m.merge(1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_get_or_insert_and_update_key() {
+        assert_result_is_ok(run_text(
+            "groups = {}\n\
+            groups.get_or_insert('a', () => []).push(1)\n\
+            groups.get_or_insert('a', () => assert(false, '', true)).push(2)\n\
+            assert(groups == {'a': [1, 2]}, groups, true)\n\
+            counts = {}\n\
+            inc = (n) => if n == nil -> 1 else -> n + 1\n\
+            assert(counts.update('a', inc) == 1, counts, true)\n\
+            assert(counts.update('a', inc) == 2, counts, true)\n\
+            assert(counts == {'a': 2}, counts, true)\n\
+            assert(counts.update('a', inc, 1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+}
+
+mod subscript {
+    use super::*;
+
+    #[test]
+    fn test_list_and_tuple() {
+        assert_result_is_ok(run_text(
+            "l = [1, [2, 3]]\n\
+            assert(l[0] == 1, l[0], true)\n\
+            assert(l[1][1] == 3, l[1][1], true)\n\
+            i = 1\n\
+            assert((4, 5)[i] == 5, (4, 5)[i], true)",
+        ));
+    }
+
+    #[test]
+    fn test_map() {
+        assert_result_is_ok(run_text(
+            "m = {'a': 1, 'b': {'c': 2}}\n\
+            assert(m['a'] == 1, m['a'], true)\n\
+            assert(m['b']['c'] == 2, m['b']['c'], true)\n\
+            assert(m['x'].err.type == ErrType.key_not_found, m['x'], true)",
+        ));
+    }
+
+    #[test]
+    fn test_index_out_of_bounds() {
+        assert_result_is_ok(run_text(
+            "l = [1]\n\
+            assert(l[1].err.type == ErrType.index_out_of_bounds, l[1], true)",
+        ));
+    }
+}
+
+mod set_attr_and_item {
+    use super::*;
+
+    #[test]
+    fn test_set_list_item() {
+        assert_result_is_ok(run_text(
+            "l = [1, 2]\n\
+            l[0] = 'a'\n\
+            l.1 = 'b'\n\
+            assert(l == ['a', 'b'], l, true)\n\
+            assert((l[0] = 'c') == 'c', l, true)",
+        ));
+    }
+
+    #[test]
+    fn test_set_list_item_out_of_bounds() {
+        assert_result_is_err(run_text("l = [1]\nl[1] = 2"));
+    }
+
+    #[test]
+    fn test_set_map_item() {
+        assert_result_is_ok(run_text(
+            "m = {'a': 1}\n\
+            m['a'] = 2\n\
+            m['b'] = 3\n\
+            assert(m == {'a': 2, 'b': 3}, m, true)",
+        ));
+    }
+
+    #[test]
+    fn test_set_tuple_item() {
+        assert_result_is_err(run_text("t = (1, 2)\nt[0] = 3"));
+    }
+
+    #[test]
+    fn test_inplace_ops() {
+        assert_result_is_ok(run_text(
+            "l = [1, 2]\n\
+            l[0] += 1\n\
+            l.1 *= 3\n\
+            assert(l == [2, 6], l, true)\n\
+            m = {'count': 1}\n\
+            m['count'] -= 1\n\
+            assert(m['count'] == 0, m, true)\n\
+            assert((l[0] /= 2) == 1.0, l, true)",
+        ));
+    }
+
+    #[test]
+    fn test_set_attr_on_builtin() {
+        assert_result_is_err(run_text("x = 1\nx.y = 2"));
+    }
+}
+
+mod multi_assignment {
+    use super::*;
+
+    #[test]
+    fn test_chained() {
+        assert_result_is_ok(run_text(
+            "a = b = 0\n\
+            assert(a == 0, a, true)\n\
+            assert(b == 0, b, true)\n\
+            l = [1, 2]\n\
+            c = l[0] = l.1 = 3\n\
+            assert(c == 3, c, true)\n\
+            assert(l == [3, 3], l, true)",
+        ));
+    }
+
+    #[test]
+    fn test_value_is_evaluated_once() {
+        assert_result_is_ok(run_text(
+            "calls = []\n\
+            f = () => calls.push(1)\n\
+            a = b = f()\n\
+            assert(calls.length == 1, calls, true)",
+        ));
+    }
+
+    #[test]
+    fn test_targets_are_evaluated_left_to_right() {
+        assert_result_is_ok(run_text(
+            "order = []\n\
+            index = (n) =>\n    \
+                order.push(n)\n    \
+                0\n\
+            l = [0]\n\
+            m = [0]\n\
+            l[index(1)] = m[index(2)] = 1\n\
+            assert(order == [1, 2], order, true)\n\
+            assert((l, m) == ([1], [1]), (l, m), true)",
+        ));
+    }
+
+    #[test]
+    fn test_swap() {
+        assert_result_is_ok(run_text(
+            "a = 1\n\
+            b = 2\n\
+            a, b = b, a\n\
+            assert(a == 2, a, true)\n\
+            assert(b == 1, b, true)\n\
+            (a, b) = (b, a)\n\
+            assert((a, b) == (1, 2), (a, b), true)",
+        ));
+    }
+
+    #[test]
+    fn test_destructure_seq() {
+        assert_result_is_ok(run_text(
+            "pair = [1, (2, 3)]\n\
+            a, (b, c) = pair\n\
+            assert((a, b, c) == (1, 2, 3), (a, b, c), true)\n\
+            l = [0, 0]\n\
+            l.0, l.1 = 'x', 'y'\n\
+            assert(l == ['x', 'y'], l, true)\n\
+            r = ((x, y) = (4, 5))\n\
+            assert(r == (4, 5), r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_items_are_unpacked_before_assignment() {
+        assert_result_is_ok(run_text(
+            "l = [1, 2]\n\
+            l[1], l[0] = l\n\
+            assert(l == [2, 1], l, true)",
+        ));
+    }
+
+    #[test]
+    fn test_wrong_number_of_items() {
+        assert_result_is_err(run_text("a, b = 1, 2, 3"));
+        assert_result_is_err(run_text("a, b = [1]"));
+        assert_result_is_err(run_text("a, b = 1"));
+    }
+
+    #[test]
+    fn test_declares_in_current_scope() {
+        assert_result_is_ok(run_text(
+            "a = 1\n\
+            b = 2\n\
+            block ->\n    \
+                (a, b) = (b, a)\n    \
+                assert((a, b) == (2, 1), (a, b), true)\n\
+            assert((a, b) == (1, 2), (a, b), true)\n\
+            f = () =>\n    \
+                x = y = 3\n    \
+                x + y\n\
+            assert(f() == 6, f(), true)",
+        ));
+    }
+
+    #[test]
+    fn test_rhs_cannot_refer_to_new_targets() {
+        assert_result_is_err(run_text("a, b = 1, a"));
+    }
+}
+
+mod pattern_matching {
+    use super::*;
+
+    #[test]
+    fn test_alternatives() {
+        assert_result_is_ok(run_text(
+            "f = (v) =>\n    \
+                match v ->\n        \
+                    1 | 2 | 3 -> 'small'\n        \
+                    'a' | 'b' -> 'letter'\n        \
+                    * -> 'other'\n\
+            assert(f(2) == 'small', f(2), true)\n\
+            assert(f('b') == 'letter', f('b'), true)\n\
+            assert(f(4) == 'other', f(4), true)",
+        ));
+    }
+
+    #[test]
+    fn test_type_patterns() {
+        assert_result_is_ok(run_text(
+            "f = (v) =>\n    \
+                match v ->\n        \
+                    Int i -> i + 1\n        \
+                    Str s -> s + '!'\n\
+            assert(f(1) == 2, f(1), true)\n\
+            assert(f('a') == 'a!', f('a'), true)\n\
+            assert(f(1.0) == nil, f(1.0), true)",
+        ));
+    }
+
+    #[test]
+    fn test_tuple_patterns() {
+        assert_result_is_ok(run_text(
+            "f = (v) =>\n    \
+                match v ->\n        \
+                    (0, 0) -> 'origin'\n        \
+                    (x, 0) -> x\n        \
+                    (x, (y, z)) -> x + y + z\n        \
+                    (@, @) -> 'pair'\n        \
+                    * -> 'other'\n\
+            assert(f((0, 0)) == 'origin', f((0, 0)), true)\n\
+            assert(f((1, 0)) == 1, f((1, 0)), true)\n\
+            assert(f((1, (2, 3))) == 6, f((1, (2, 3))), true)\n\
+            assert(f((1, 2)) == 'pair', f((1, 2)), true)\n\
+            assert(f([0, 0]) == 'other', f([0, 0]), true)\n\
+            assert(f((1, 2, 3)) == 'other', f((1, 2, 3)), true)",
+        ));
+    }
+
+    #[test]
+    fn test_guards() {
+        assert_result_is_ok(run_text(
+            "f = (v) =>\n    \
+                match v ->\n        \
+                    (x, y) if x == y -> 'same'\n        \
+                    Int i if i < 0 -> 'negative'\n        \
+                    x if x > 10 -> 'big'\n        \
+                    * -> 'other'\n\
+            assert(f((1, 1)) == 'same', f((1, 1)), true)\n\
+            assert(f(1.5) == 'other', f(1.5), true)\n\
+            assert(f(-1) == 'negative', f(-1), true)\n\
+            assert(f(11) == 'big', f(11), true)\n\
+            assert(f(5) == 'other', f(5), true)",
+        ));
+    }
+
+    #[test]
+    fn test_bindings_are_scoped_to_arm() {
+        assert_result_is_ok(run_text(
+            "x = 1\n\
+            r = match (2, 3) ->\n    \
+                (x, y) -> x + y\n\
+            assert(r == 5, r, true)\n\
+            assert(x == 1, x, true)",
+        ));
+    }
+
+    #[test]
+    fn test_subject_is_evaluated_once() {
+        assert_result_is_ok(run_text(
+            "calls = []\n\
+            f = () =>\n    \
+                calls.push(1)\n    \
+                3\n\
+            r = match f() ->\n    \
+                1 | 2 -> 'low'\n    \
+                Int i if i > 5 -> 'high'\n    \
+                * -> 'mid'\n\
+            assert(r == 'mid', r, true)\n\
+            assert(calls.length == 1, calls, true)",
+        ));
+    }
+
+    #[test]
+    fn test_str_patterns() {
+        assert_result_is_ok(run_text(
+            "f = (line) =>\n    \
+                match line ->\n        \
+                    'GET ' + path -> ('get', path)\n        \
+                    '<' + tag + '>' -> ('tag', tag)\n        \
+                    name + '.txt' if name != '' -> ('text', name)\n        \
+                    ('x', 'y' + rest) -> ('tuple', rest)\n        \
+                    * -> 'other'\n\
+            assert(f('GET /index') == ('get', '/index'), f('GET /index'), true)\n\
+            assert(f('GET ') == ('get', ''), f('GET '), true)\n\
+            assert(f('<b>') == ('tag', 'b'), f('<b>'), true)\n\
+            assert(f('<') == 'other', f('<'), true)\n\
+            assert(f('a.txt') == ('text', 'a'), f('a.txt'), true)\n\
+            assert(f('.txt') == 'other', f('.txt'), true)\n\
+            assert(f(('x', 'yz')) == ('tuple', 'z'), f(('x', 'yz')), true)\n\
+            assert(f('POST /') == 'other', f('POST /'), true)\n\
+            assert(f(1) == 'other', f(1), true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-full")]
+mod proc {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run() {
+        assert_result_is_ok(run_text(
+            "import std.proc\n\
+            result = proc.run('echo', ['a  b', \"it's\"])\n\
+            assert(result['status'] == 0, '', true)\n\
+            assert(result['stdout'] == \"a  b it's\\n\", '', true)\n\
+            assert(result['stderr'] == '', '', true)\n\
+            result = proc.run('feint-no-such-program', [])\n\
+            assert(result.err.type == ErrType.proc, '', true)\n\
+            assert(proc.run('echo', [1]).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_shell() {
+        assert_result_is_ok(run_text(
+            "import std.proc\n\
+            arg = \"it's; echo injected\"\n\
+            result = proc.shell('echo ' + proc.quote(arg))\n\
+            assert(result['stdout'] == arg + '\\n', '', true)\n\
+            assert(proc.shell('exit 3')['status'] == 3, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_result_is_ok(run_text(
+            "from std.proc import quote\n\
+            assert(quote('a.txt', 'posix') == 'a.txt', '', true)\n\
+            assert(quote('', 'posix') == \"''\", '', true)\n\
+            assert(quote('a b', 'posix') == \"'a b'\", '', true)\n\
+            assert(quote(\"it's\", 'posix') == \"'it'\\\"'\\\"'s'\", '', true)\n\
+            assert(quote('a.txt', 'windows') == 'a.txt', '', true)\n\
+            assert(quote('', 'windows') == '\"\"', '', true)\n\
+            assert(quote('a b', 'windows') == '\"a b\"', '', true)\n\
+            assert(quote('a\\\\b c\\\\', 'windows') == '\"a\\\\b c\\\\\\\\\"', '', true)\n\
+            assert(quote('say \"hi\"', 'windows') == '\"say \\\\\"hi\\\\\"\"', '', true)\n\
+            assert(quote('a', 'sh').err.type == ErrType.arg, '', true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-min")]
+mod json {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_result_is_ok(run_text(
+            "import std.json\n\
+            obj = json.decode('{\"a\": [1, -2.5e1, true, null], \"b\": {\"c\": \"x\\\\n\\\\u00e9\\\\ud83d\\\\ude00\"}}')\n\
+            assert(obj == {'a': [1, -25.0, true, nil], 'b': {'c': 'x\\né😀'}}, obj, true)\n\
+            assert(json.decode(' 12345678901234567890 ') == 12345678901234567890, '', true)\n\
+            assert(json.decode('[]') == [], '', true)\n\
+            assert(json.decode('{}') == {}, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_decode_errs() {
+        assert_result_is_ok(run_text(
+            "import std.json\n\
+            assert(json.decode('').err.type == ErrType.json, '', true)\n\
+            assert(json.decode('[1,').err.type == ErrType.json, '', true)\n\
+            assert(json.decode('{\"a\" 1}').err.type == ErrType.json, '', true)\n\
+            assert(json.decode('[1] 2').err.type == ErrType.json, '', true)\n\
+            assert(json.decode('\"\\\\x\"').err.type == ErrType.json, '', true)\n\
+            assert(json.decode('\"\\\\ud800\"').err.type == ErrType.json, '', true)\n\
+            assert(json.decode('tru').err.type == ErrType.json, '', true)\n\
+            assert(json.decode('1.').err.type == ErrType.json, '', true)\n\
+            err = json.decode('[1,\\n  x]').err\n\
+            assert(err.message == \"Unexpected character 'x' at line 2, column 3\", err, true)",
+        ));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_result_is_ok(run_text(
+            "import std.json\n\
+            obj = {'a': (1, 2.0, true, nil), 'b': 'q\"\\\\\\n\\t'}\n\
+            text = json.encode(obj)\n\
+            assert(text.starts_with('{\"a\":[1,2.0,true,null],\"b\":'), text, true)\n\
+            assert(json.decode(text) == {'a': [1, 2.0, true, nil], 'b': obj['b']}, text, true)\n\
+            text = json.encode({'a': [1, 2], 'b': {}}, 2)\n\
+            expected = '{\\n  \"a\": [\\n    1,\\n    2\\n  ],\\n  \"b\": {}\\n}'\n\
+            assert(text == expected, text, true)\n\
+            assert(json.encode([1], '\\t') == '[\\n\\t1\\n]', '', true)\n\
+            assert(json.encode({1, 2}).err.type == ErrType.json, '', true)\n\
+            assert(json.encode([1], -1).err.type == ErrType.arg, '', true)\n\
+            l = []\n\
+            l.push(l)\n\
+            assert(json.encode(l).err.type == ErrType.json, '', true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-min")]
+mod math {
+    use super::*;
+
+    #[test]
+    fn test_constants_and_float_funcs() {
+        assert_result_is_ok(run_text(
+            "import std.math\n\
+            assert(math.pi > 3.14159 && math.pi < 3.1416, math.pi, true)\n\
+            assert(math.e > 2.718 && math.e < 2.719, math.e, true)\n\
+            assert(math.inf > 10 ^ 300, math.inf, true)\n\
+            assert(math.nan != math.nan, math.nan, true)\n\
+            assert(math.sqrt(16) == 4.0, '', true)\n\
+            assert(math.sin(0) == 0.0 && math.cos(0) == 1.0, '', true)\n\
+            assert(math.tan(0.0) == 0.0, '', true)\n\
+            assert(math.exp(0) == 1.0 && math.log(1) == 0.0, '', true)\n\
+            assert(math.log(8, 2) == 3.0, math.log(8, 2), true)\n\
+            assert(math.sqrt('4').err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_result_is_ok(run_text(
+            "import std.math\n\
+            assert(math.abs(-3) == 3 && math.abs(-2.5) == 2.5, '', true)\n\
+            assert(math.floor(2.7) == 2 && math.floor(-2.5) == -3, '', true)\n\
+            assert(math.ceil(2.1) == 3 && math.ceil(5) == 5, '', true)\n\
+            assert(math.round(2.5) == 3 && math.round(-2.5) == -3, '', true)\n\
+            assert(math.round(math.pi, 2) == 3.14, math.round(math.pi, 2), true)\n\
+            assert(math.round(1234.5, -2) == 1200.0, '', true)\n\
+            assert(math.round(7, 2) == 7, '', true)\n\
+            assert(math.floor(math.inf).err.type == ErrType.arg, '', true)\n\
+            assert((2.5).round() == 3 && (2.567).round(1) == 2.6, '', true)\n\
+            assert((2.5).floor() == 2 && (2.1).ceil() == 3, '', true)\n\
+            assert((-1.5).abs() == 1.5 && (-2).abs() == 2, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_min_max_and_clamp() {
+        assert_result_is_ok(run_text(
+            "import std.math\n\
+            assert(math.min(3, 1.5, 2) == 1.5, '', true)\n\
+            assert(math.max(3, 1.5, 7) == 7, '', true)\n\
+            assert(math.min(4) == 4, '', true)\n\
+            assert(math.clamp(15, 0, 10) == 10, '', true)\n\
+            assert(math.clamp(-1, 0, 10) == 0, '', true)\n\
+            assert(math.clamp(5, 0, 10) == 5, '', true)\n\
+            assert(math.clamp(5, 10, 0).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_gcd_and_pow() {
+        assert_result_is_ok(run_text(
+            "import std.math\n\
+            assert(math.gcd(12, 18) == 6 && math.gcd(-4, 6) == 2, '', true)\n\
+            assert(math.gcd(0, 5) == 5, '', true)\n\
+            assert(math.gcd(1.5, 3).err.type == ErrType.arg, '', true)\n\
+            assert(math.pow(2, 100) == 1267650600228229401496703205376, '', true)\n\
+            assert(math.pow(2, -1) == 0.5 && math.pow(4, 0.5) == 2.0, '', true)\n\
+            assert(math.pow(2, 100, 7) == 2, '', true)\n\
+            assert(math.pow(2, -1, 7).err.type == ErrType.arg, '', true)\n\
+            assert(math.pow(2, 3, 0).err.type == ErrType.arg, '', true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-full")]
+mod os {
+    use super::*;
+
+    #[test]
+    fn test_env() {
+        assert_result_is_ok(run_text(
+            "import std.os\n\
+            name = 'FEINT_TEST_OS_ENV'\n\
+            assert(os.get_env(name) == nil, '', true)\n\
+            assert(os.get_env(name, 'default') == 'default', '', true)\n\
+            assert(os.set_env(name, 'value') == nil, '', true)\n\
+            assert(os.get_env(name) == 'value', '', true)\n\
+            assert(os.env()[name] == 'value', '', true)\n\
+            os.remove_env(name)\n\
+            assert(os.get_env(name) == nil, '', true)\n\
+            assert(os.set_env('A=B', '').err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_cwd_and_process() {
+        assert_result_is_ok(run_text(
+            "import std.os\n\
+            cwd = os.cwd()\n\
+            assert(os.chdir(cwd) == nil, '', true)\n\
+            assert(os.cwd() == cwd, '', true)\n\
+            assert(os.chdir('feint-no-such-dir').err.type == ErrType.file_not_found, '', true)\n\
+            assert(os.platform.length > 0, '', true)\n\
+            assert(os.pid() > 0, '', true)",
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run() {
+        assert_result_is_ok(run_text(
+            "import std.os\n\
+            result = os.run('echo', ['a'])\n\
+            assert(result['status'] == 0, '', true)\n\
+            assert(result['stdout'] == 'a\\n', '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_exit() {
+        let result = run_text("import std.os\nos.exit(3)\nassert(false, '', true)");
+        assert_eq!(result.unwrap_err().exit_code(), Some(3));
+        let result = run_text("import std.os\nos.exit()");
+        assert_eq!(result.unwrap_err().exit_code(), Some(0));
+    }
+}
+
+#[cfg(feature = "http")]
+mod http {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    use super::*;
+
+    /// Serve a single canned response on a local port. The returned
+    /// handle yields the raw request once it's been handled.
+    fn serve_once(response: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let len = text[..end]
+                        .lines()
+                        .find_map(|line| {
+                            let line = line.to_ascii_lowercase();
+                            line.strip_prefix("content-length:")
+                                .map(|len| len.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if n == 0 || request.len() >= end + 4 + len {
+                        break;
+                    }
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_get() {
+        let (url, handle) = serve_once(
+            "HTTP/1.1 200 OK\r\n\
+            Content-Type: text/plain\r\n\
+            X-Multi: a\r\n\
+            X-Multi: b\r\n\
+            Content-Length: 5\r\n\
+            Connection: close\r\n\r\n\
+            hello",
+        );
+        assert_result_is_ok(run_text(&format!(
+            "import std.http\n\
+            r = http.get('{url}/path?q=1', {{'headers': {{'X-Test': 'yes'}}}})\n\
+            assert(r.status == 200, r, true)\n\
+            assert(r.ok, r, true)\n\
+            assert(r.headers['content-type'] == 'text/plain', r.headers, true)\n\
+            assert(r.headers['x-multi'] == 'a, b', r.headers, true)\n\
+            assert(r.text == 'hello', r.text, true)\n\
+            assert(r.body.length == 5 && r.body.to_str() == 'hello', r.body, true)"
+        )));
+        let request = handle.join().unwrap();
+        assert!(request.starts_with("GET /path?q=1 HTTP/1.1\r\n"), "{request}");
+        assert!(request.to_ascii_lowercase().contains("x-test: yes\r\n"), "{request}");
+    }
+
+    #[test]
+    fn test_post_with_error_status() {
+        let (url, handle) = serve_once(
+            "HTTP/1.1 404 Not Found\r\n\
+            Content-Length: 7\r\n\
+            Connection: close\r\n\r\n\
+            missing",
+        );
+        assert_result_is_ok(run_text(&format!(
+            "import std.http\n\
+            r = http.post('{url}/items', 'a=1&b=2', {{'timeout': 5}})\n\
+            assert(r.status == 404, r, true)\n\
+            assert(!r.ok, r, true)\n\
+            assert(r.text == 'missing', r.text, true)"
+        )));
+        let request = handle.join().unwrap();
+        assert!(request.starts_with("POST /items HTTP/1.1\r\n"), "{request}");
+        assert!(request.ends_with("\r\n\r\na=1&b=2"), "{request}");
+    }
+
+    #[test]
+    fn test_errs() {
+        // Bind then drop a listener to get a port that refuses connections.
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        assert_result_is_ok(run_text(&format!(
+            "import std.http\n\
+            assert(http.get('{url}/').err.type == ErrType.http, '', true)\n\
+            assert(http.get('not a url').err.type == ErrType.http, '', true)\n\
+            assert(http.get('{url}/', {{'nope': 1}}).err.type == ErrType.arg, '', true)\n\
+            assert(http.get('{url}/', {{'timeout': 0}}).err.type == ErrType.arg, '', true)\n\
+            assert(http.post('{url}/', 1).err.type == ErrType.arg, '', true)"
+        )));
+    }
+}
+
+#[cfg(feature = "std-full")]
+mod time {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        assert_result_is_ok(run_text(
+            "import std.time\n\
+            d = time.parse('2000-01-02 03:04', '%Y-%m-%d %H:%M')\n\
+            assert(d == DateTime.new(2000, 1, 2, 3, 4), d, true)\n\
+            assert(d.format('%d/%m/%Y %H:%M') == '02/01/2000 03:04', d, true)\n\
+            assert(d.format('%Q').err.type == ErrType.arg, '', true)\n\
+            d = time.parse('2000-01-01T12:00:00+02:00')\n\
+            assert(d.offset == 7200 && d.hour == 12, d, true)\n\
+            assert(d.to_utc().hour == 10 && d.to_utc() == d, d.to_utc(), true)\n\
+            assert(time.parse('2000-01-01', '%Y-%m-%d').hour == 0, '', true)\n\
+            assert(time.parse('nope').err.type == ErrType.time, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_now_ticks_and_sleep() {
+        let _lock = crate::tests::lock_deterministic();
+        assert_result_is_ok(run_text(
+            "import std.time\n\
+            assert(time.now() > DateTime.new(2020, 1, 1), time.now(), true)\n\
+            t = time.ticks()\n\
+            time.sleep(0.01)\n\
+            assert(time.ticks() - t >= 0.01, time.ticks() - t, true)\n\
+            assert(time.sleep(-1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+}
+
+mod range {
+    use super::*;
+
+    #[test]
+    fn test_literal() {
+        assert_result_is_ok(run_text(
+            "r = 1..10\n\
+            assert(r.length == 9, r.length, true)\n\
+            assert(r[0] == 1, r[0], true)\n\
+            assert(r[8] == 9, r[8], true)\n\
+            assert((1..=10).length == 10, (1..=10).length, true)\n\
+            assert((10..1).is_empty, 10..1, true)",
+        ));
+    }
+
+    #[test]
+    fn test_new_with_negative_step() {
+        assert_result_is_ok(run_text(
+            "r = Range.new(10, 0, -2)\n\
+            assert(r.map((i) => i) == (10, 8, 6, 4, 2), r, true)\n\
+            assert(Range.new(1, 10, 0).err, 'expected step err', true)",
+        ));
+    }
+
+    #[test]
+    fn test_in() {
+        assert_result_is_ok(run_text(
+            "assert(5 in 1..10, '5 in 1..10', true)\n\
+            assert(!(10 in 1..10), '10 in 1..10', true)\n\
+            assert(10 not in 1..10, '10 not in 1..10', true)\n\
+            assert(!(5 not in 1..10), '5 not in 1..10', true)\n\
+            assert(3 not in (1, 2), '3 not in (1, 2)', true)\n\
+            assert(10 in 1..=10, '10 in 1..=10', true)\n\
+            assert(2 in [1, 2], '2 in [1, 2]', true)\n\
+            assert('a' in {'a': 1}, 'a in map', true)\n\
+            assert('ell' in 'hello', 'ell in hello', true)",
+        ));
+    }
+
+    #[test]
+    fn test_iter() {
+        assert_result_is_ok(run_text(
+            "it = (1..=3).iter()\n\
+            assert(it.next() == 1, '', true)\n\
+            assert(it.next() == 2, '', true)\n\
+            assert(it.next() == 3, '', true)\n\
+            assert(it.next() == nil, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_non_int_bounds() {
+        assert_result_is_err(run_text("1..'a'"));
+    }
+}
+
+mod set {
+    use super::*;
+
+    #[test]
+    fn test_literal_and_new() {
+        assert_result_is_ok(run_text(
+            "s = {1, 2, 2, 3}\n\
+            assert(s.length == 3, s, true)\n\
+            assert(s == Set.new([3, 2, 1, 1]), s, true)\n\
+            assert(s == Set.new(1..4), s, true)\n\
+            assert(s != {1, 2}, s, true)\n\
+            assert(Set.new([]).is_empty, '', true)\n\
+            assert(Set.new(1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_add_remove_and_has() {
+        assert_result_is_ok(run_text(
+            "s = Set.new([])\n\
+            assert(s.add(1), s, true)\n\
+            assert(!s.add(1), s, true)\n\
+            assert(s.has(1), s, true)\n\
+            assert(1 in s, s, true)\n\
+            assert(2 not in s, s, true)\n\
+            assert(s.remove(1), s, true)\n\
+            assert(!s.remove(1), s, true)\n\
+            assert(s.is_empty, s, true)",
+        ));
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_result_is_ok(run_text(
+            "a = {1, 2, 3}\n\
+            b = {2, 3, 4}\n\
+            assert(a | b == {1, 2, 3, 4}, a | b, true)\n\
+            assert(a & b == {2, 3}, a & b, true)\n\
+            assert(a - b == {1}, a - b, true)\n\
+            assert(b - a == {4}, b - a, true)\n\
+            assert((a & b).length == 2, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_operator_with_non_set() {
+        assert_result_is_err(run_text("{1} | [1]"));
+    }
+
+    #[test]
+    fn test_iter() {
+        assert_result_is_ok(run_text(
+            "it = {1, 2}.iter()\n\
+            assert(it.next() == 1, '', true)\n\
+            assert(it.next() == 2, '', true)\n\
+            assert(it.next() == nil, '', true)",
+        ));
+    }
+}
+
+mod str {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert_result_is_ok(run_text("Str.new(\"string\")"));
+        assert_result_is_ok(run_text("Str.new(nil) == \"nil\""));
+        assert_result_is_ok(run_text("Str.new(true) == \"true\""));
+        assert_result_is_ok(run_text("Str.new(false) == \"false\""));
+        assert_result_is_ok(run_text("Str.new(1) == \"1\""));
+        assert_result_is_ok(run_text("Str.new(1.0) == \"1.0\""));
+        assert_result_is_ok(run_text("Str.new(()) == \"()\""));
+        assert_result_is_ok(run_text("Str.new([]) == \"[]\""));
+        assert_result_is_ok(run_text("Str.new({}) == \"{}\""));
+        assert_result_is_ok(run_text(
+            "Str.new(print).starts_with(\"function print/0+ @\")",
+        ));
+        assert_result_is_ok(run_text(
+            "Str.new(() => nil).starts_with(\"function <anonymous>/0 @\")",
+        ));
+    }
+
+    #[test]
+    fn test_format_string_repr() {
+        assert_result_is_ok(run_text(
+            "s = 'abc'\n\
+            assert($'{s}' == 'abc', $'{s}', true)\n\
+            assert($'{s!r}' == '\"abc\"', $'{s!r}', true)\n\
+            assert($'<{[s, 1]!r}>' == '<[\"abc\", 1]>', $'{[s, 1]!r}', true)\n\
+            r = false\n\
+            assert($'{true && !r}' == 'true', '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_starts_with() {
+        assert_result_is_ok(run_text("'abc'.starts_with('a')"));
+    }
+
+    #[test]
+    fn test_starts_with_bad_arg() {
+        assert_result_is_ok(run_text("assert('abc'.starts_with(1).err, '', true)"));
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        assert_result_is_ok(run_text(
+            "parts = 'a,b,,c'.split(',')\n\
+            assert(parts == ('a', 'b', '', 'c'), parts, true)\n\
+            assert('-'.join(parts) == 'a-b--c', '', true)\n\
+            assert(', '.join([1, nil, true]) == '1, nil, true', '', true)\n\
+            assert('a'.split('').err, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_splitn_and_partition() {
+        assert_result_is_ok(run_text(
+            "assert('a=b=c'.splitn('=', 2) == ('a', 'b=c'), '', true)\n\
+            assert('a=b=c'.partition('=') == ('a', '=', 'b=c'), '', true)\n\
+            assert('abc'.partition('=') == ('abc', '', ''), '', true)\n\
+            assert('abc'.partition('').err, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_strip_and_count() {
+        assert_result_is_ok(run_text(
+            "assert('# x'.strip_prefix('# ') == 'x', '', true)\n\
+            assert('x'.strip_prefix('#') == nil, '', true)\n\
+            assert('a.fi'.strip_suffix('.fi') == 'a', '', true)\n\
+            assert('a.rs'.strip_suffix('.fi') == nil, '', true)\n\
+            assert('banana'.count('an') == 2, '', true)\n\
+            assert('aaaa'.count('aa') == 2, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_trim_and_pad() {
+        assert_result_is_ok(run_text(
+            "assert('  a b \\n'.trim() == 'a b', '', true)\n\
+            assert('7'.pad_left(3) == '  7', '', true)\n\
+            assert('7'.pad_left(3, '0') == '007', '', true)\n\
+            assert('ab'.pad_right(4, '.') == 'ab..', '', true)\n\
+            assert('abc'.pad_left(2) == 'abc', '', true)\n\
+            assert('7'.pad_left(3, '00').err, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_find_chars_and_lines() {
+        assert_result_is_ok(run_text(
+            "assert('hello'.find('l') == 2, '', true)\n\
+            assert('hello'.find('z') == nil, '', true)\n\
+            assert('abc'.chars() == ('a', 'b', 'c'), '', true)\n\
+            assert('a\\nb'.lines() == ('a', 'b'), '', true)\n\
+            assert('ab'.repeat(2) == 'abab', '', true)\n\
+            assert('Ab'.upper() == 'AB' && 'Ab'.lower() == 'ab', '', true)",
+        ));
+    }
+}
+
+mod tuple {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        assert_result_is_ok(run_text("t = (1, 2)\nt.map((item, i) => (item, i))"));
+    }
+
+    #[test]
+    fn test_large_literal() {
+        let items: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        let lines: Vec<String> = items.chunks(10).map(|c| c.join(", ")).collect();
+        let text = format!(
+            "t = (\n{}\n)\n\
+            assert(t.length == 1000, t.length, true)\n\
+            assert(t[0] == 0, t[0], true)\n\
+            assert(t[999] == 999, t[999], true)",
+            lines.join(",\n")
+        );
+        assert_result_is_ok(run_text(text.as_str()));
+    }
+}
+
+mod truthiness {
+    use super::*;
+
+    /// The truthiness (`!!`) of each builtin type. Values that are
+    /// empty or zero are false, as are nil, false, and OK Errs. All
+    /// other objects are true.
+    const TABLE: &[(&str, bool)] = &[
+        ("nil", false),
+        ("true", true),
+        ("false", false),
+        ("0", false),
+        ("1", true),
+        ("-1", true),
+        ("0.0", false),
+        ("0.5", true),
+        ("''", false),
+        ("' '", true),
+        ("()", false),
+        ("(0,)", true),
+        ("[]", false),
+        ("[0]", true),
+        ("{}", false),
+        ("{'a': 0}", true),
+        ("Set.new([])", false),
+        ("{0}", true),
+        ("1..1", false),
+        ("1..2", true),
+        ("Bytes.new([])", false),
+        ("Bytes.new([0])", true),
+        ("ByteBuffer.new()", false),
+        ("ByteBuffer.new([0])", true),
+        ("Err.new(ErrType.arg, '')", true),
+        ("1.err", false),
+        ("Int", true),
+        ("print", true),
+        ("() => nil", true),
+        ("system", true),
+    ];
+
+    #[test]
+    fn test_truthiness_table() {
+        for (expr, expected) in TABLE {
+            let text = format!(
+                "import std.system\n\
+                x = {expr}\n\
+                assert(!!x == {expected}, x, true)\n\
+                assert(!x == !{expected}, x, true)\n\
+                r = if x -> true else -> false\n\
+                assert(r == {expected}, r, true)"
+            );
+            assert!(run_text(&text).is_ok(), "expected !!{expr} to be {expected}");
+        }
+    }
+
+    #[test]
+    fn test_is_nil_and_default() {
+        assert_result_is_ok(run_text(
+            "assert(is_nil(nil), '', true)\n\
+            assert(!is_nil(0), '', true)\n\
+            assert(!is_nil(false), '', true)\n\
+            assert(default(nil, 1) == 1, '', true)\n\
+            assert(default(0, 1) == 0, '', true)\n\
+            assert(default('', 'x') == '', '', true)\n\
+            assert(default([].get(0), 'none') == 'none', '', true)",
+        ));
+    }
+}
+
+mod try_catch {
+    use super::*;
+
+    #[test]
+    fn test_catch() {
+        assert_result_is_ok(run_text(
+            "r = try -> 1 + nil catch e -> e.type\n\
+            assert(r == ErrType.type, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_no_err() {
+        assert_result_is_ok(run_text(
+            "r = try -> 1 catch e -> 2\n\
+            assert(r == 1, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_catch_by_type() {
+        assert_result_is_ok(run_text(
+            "r = try -> (1, 2).3 catch ErrType.type e -> 1 catch ErrType.index_out_of_bounds e -> 2\n\
+            assert(r == 2, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_catch_returned_err() {
+        assert_result_is_ok(run_text(
+            "f = () => Err.new(ErrType.arg, 'bad')\n\
+            r = try -> f() catch ErrType.arg e -> e.message\n\
+            assert(r == 'bad', '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_catch_err_from_nested_call() {
+        assert_result_is_ok(run_text(
+            "f = () => 1 + nil\n\
+            g = () => f()\n\
+            r = try -> g() catch e -> 'caught'\n\
+            assert(r == 'caught', '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_ensure() {
+        assert_result_is_ok(run_text(
+            "x = 0\n\
+            r = try -> 1 + nil catch e -> 2 ensure -> x += 1\n\
+            assert(r == 2, '', true)\n\
+            assert(x == 1, '', true)\n\
+            r = try -> 3 ensure -> x += 1\n\
+            assert(r == 3, '', true)\n\
+            assert(x == 2, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_reraise_when_not_caught() {
+        assert_result_is_err(run_text("try -> 1 + nil catch ErrType.arg e -> nil"));
+        assert_result_is_err(run_text("try -> 1 + nil ensure -> nil"));
+        assert_result_is_err(run_text("try -> 1 + nil catch e -> 1 + nil"));
+    }
+
+    #[test]
+    fn test_ensure_runs_before_reraise() {
+        assert_result_is_ok(run_text(
+            "x = 0\n\
+            r = try -> (try -> 1 + nil ensure -> x += 1) catch e -> x\n\
+            assert(r == 1, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_break_out_of_try_pops_handler() {
+        assert_result_is_err(run_text(
+            "loop ->\n    \
+                try -> break nil catch e -> nil\n\
+            1 + nil",
+        ));
+        assert_result_is_err(run_text(
+            "i = 0\n\
+            loop i < 2 ->\n    \
+                i += 1\n    \
+                try -> continue catch e -> nil\n\
+            1 + nil",
+        ));
+        assert_result_is_err(run_text(
+            "f = () =>\n    \
+                try -> return 1 catch e -> nil\n\
+            f()\n\
+            1 + nil",
+        ));
+    }
+
+    #[test]
+    fn test_ensure_runs_on_break_continue_and_return() {
+        assert_result_is_ok(run_text(
+            "x = 0\n\
+            r = loop ->\n    \
+                try -> break 1 ensure -> x += 1\n\
+            assert(r == 1, r, true)\n\
+            assert(x == 1, x, true)\n\
+            i = 0\n\
+            loop i < 2 ->\n    \
+                i += 1\n    \
+                try ->\n        \
+                    try -> continue ensure -> x += 1\n    \
+                ensure -> x += 10\n\
+            assert(x == 23, x, true)\n\
+            f = () =>\n    \
+                global x\n    \
+                try -> 1 + nil catch e -> return 2 ensure -> x += 100\n\
+            assert(f() == 2, '', true)\n\
+            assert(x == 123, x, true)",
+        ));
+    }
+
+    #[test]
+    fn test_ensure_not_run_twice_on_break() {
+        assert_result_is_ok(run_text(
+            "x = 0\n\
+            loop ->\n    \
+                try -> nil ensure ->\n        \
+                    x += 1\n        \
+                    break\n\
+            assert(x == 1, x, true)",
+        ));
+    }
+
+    #[test]
+    fn test_try_requires_catch_or_ensure() {
+        assert_result_is_err(run_text("try -> 1"));
+    }
+}
+
+mod optimizer {
+    use super::*;
+
+    #[test]
+    fn test_folded_arithmetic() {
+        assert_result_is_ok(run_text_optimized(
+            "x = 2 + 3 * 4 - -1\n\
+            assert(x == 15, x, true)\n\
+            y = 1.5 * 2 + 1000\n\
+            assert(y == 1003.0, y, true)\n\
+            assert(1 < 2, '', true)\n\
+            assert(!(2 <= 1), '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_division_by_zero_is_not_folded() {
+        assert_result_is_err(run_text_optimized("1 // 0"));
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_result_is_ok(run_text_optimized(
+            "i = 0\n\
+            r = loop ->\n    \
+                j = 0\n    \
+                inner = loop ->\n        \
+                    if j > 2 ->\n            \
+                        break j * 10\n        \
+                    j += 1\n    \
+                if i > 3 ->\n        \
+                    break (i, inner)\n    \
+                i += 1\n\
+            assert(r == (4, 30), r, true)\n\
+            total = 0\n\
+            n = 0\n\
+            loop n < 10 ->\n    \
+                n += 1\n    \
+                if n % 2 == 0 ->\n        \
+                    continue\n    \
+                total += n\n\
+            assert(total == 25, total, true)",
+        ));
+    }
+
+    #[test]
+    fn test_funcs() {
+        assert_result_is_ok(run_text_optimized(
+            "f = (a) =>\n    \
+                if a < 0 ->\n        \
+                    return 0\n    \
+                b = a * (2 + 2)\n    \
+                () => b\n\
+            assert(f(2)() == 8, f(2)(), true)\n\
+            assert(f(-1) == 0, f(-1), true)",
+        ));
+    }
+
+    #[test]
+    fn test_hoisted_loads() {
+        assert_result_is_ok(run_text_optimized(
+            "xs = [1, 2, 3]\n\
+            f = (ys) =>\n    \
+                total = 0\n    \
+                i = 0\n    \
+                loop i < ys.length ->\n        \
+                    j = 0\n        \
+                    loop j < xs.length ->\n            \
+                        total += ys[i] * xs[j]\n            \
+                        j += 1\n        \
+                    i += 1\n    \
+                total\n\
+            r = f([1, 2])\n\
+            assert(r == 18, r, true)\n\
+            make_adder = (n) =>\n    \
+                add = (count) =>\n        \
+                    total = 0\n        \
+                    loop count > 0 ->\n            \
+                        total += n\n            \
+                        count -= 1\n        \
+                    total\n    \
+                add\n\
+            r = make_adder(5)(3)\n\
+            assert(r == 15, r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_hoisted_global_cant_be_updated_in_place() {
+        assert_result_is_err(run_text_optimized(
+            "g = 1\n\
+            f = () =>\n    \
+                i = 0\n    \
+                loop i < 1 ->\n        \
+                    g += 1\n        \
+                    i += 1\n\
+            f()",
+        ));
+    }
+
+    #[test]
+    fn test_try_catch() {
+        assert_result_is_ok(run_text_optimized(
+            "r = try -> 1 + nil catch e -> 2 * 3\n\
+            assert(r == 6, r, true)",
+        ));
+    }
+}
+
+mod locals {
+    use super::*;
+
+    #[test]
+    fn test_shadowing_in_block() {
+        assert_result_is_ok(run_text(
+            "f = () =>\n    \
+                x = 1\n    \
+                r = block ->\n        \
+                    y = x + 1\n        \
+                    x = 10\n        \
+                    (x, y)\n    \
+                (x, r)\n\
+            r = f()\n\
+            assert(r == (1, (10, 2)), r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_outer_local_assigned_in_loop() {
+        assert_result_is_ok(run_text(
+            "f = (n) =>\n    \
+                i = 0\n    \
+                total = 0\n    \
+                loop i < n ->\n        \
+                    i += 1\n        \
+                    total += i\n    \
+                total\n\
+            r = f(4)\n\
+            assert(r == 10, r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_recursive_calls_have_separate_locals() {
+        assert_result_is_ok(run_text(
+            "fact = (n) =>\n    \
+                m = n\n    \
+                if m < 2 -> 1 else -> m * fact(m - 1)\n\
+            r = fact(5)\n\
+            assert(r == 120, r, true)",
+        ));
+    }
+}
+
+mod closures {
+    use super::*;
+
+    #[test]
+    fn test_captured_args() {
+        assert_result_is_ok(run_text(
+            "f = (a, b) =>\n    \
+                g = () => a + b\n    \
+                g\n\
+            r = f(1, 2)()\n\
+            assert(r == 3, r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_captured_arg_in_nested_scope() {
+        assert_result_is_ok(run_text(
+            "f = (a) =>\n    \
+                block ->\n        \
+                    g = () => a\n        \
+                    (a, g())\n\
+            r = f(1)\n\
+            assert(r == (1, 1), r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_loop_vars_are_captured_per_iteration() {
+        assert_result_is_ok(run_text(
+            "f = () =>\n    \
+                fs = []\n    \
+                i = 0\n    \
+                loop i < 3 ->\n        \
+                    j = i\n        \
+                    fs.push(() => j)\n        \
+                    i += 1\n    \
+                fs.map((g) => g())\n\
+            r = f()\n\
+            assert(r == (0, 1, 2), r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_loop_var_is_shared_within_iteration() {
+        assert_result_is_ok(run_text(
+            "f = () =>\n    \
+                fs = []\n    \
+                i = 0\n    \
+                loop i < 2 ->\n        \
+                    j = i\n        \
+                    fs.push(() => j)\n        \
+                    j = j * 10\n        \
+                    i += 1\n    \
+                fs.map((g) => g())\n\
+            r = f()\n\
+            assert(r == (0, 10), r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_uncaptured_args() {
+        assert_result_is_ok(run_text(
+            "f = (a, b) =>\n    \
+                a = a + b\n    \
+                a\n\
+            r = f(1, 2)\n\
+            assert(r == 3, r, true)",
+        ));
+    }
+}
+
+mod globals {
+    use super::*;
+
+    #[test]
+    fn test_assign_global() {
+        assert_result_is_ok(run_text(
+            "count = 0\n\
+            name = 'a'\n\
+            f = () =>\n    \
+                global count, name\n    \
+                count += 1\n    \
+                name = name + 'b'\n\
+            f()\n\
+            f()\n\
+            assert(count == 2, count, true)\n\
+            assert(name == 'abb', name, true)\n\
+            count = 10\n\
+            f()\n\
+            assert(count == 11, count, true)",
+        ));
+    }
+
+    #[test]
+    fn test_read_global_after_assignment() {
+        assert_result_is_ok(run_text(
+            "x = 1\n\
+            set_x = (v) =>\n    \
+                global x\n    \
+                x = v\n\
+            get_x = () => x\n\
+            set_x(2)\n\
+            assert(get_x() == 2, get_x(), true)",
+        ));
+    }
+
+    #[test]
+    fn test_assign_global_in_nested_func() {
+        assert_result_is_ok(run_text(
+            "x = 1\n\
+            f = () =>\n    \
+                x = 'local'\n    \
+                g = () =>\n        \
+                    global x\n        \
+                    x = x + 1\n    \
+                g()\n    \
+                x\n\
+            r = f()\n\
+            assert(r == 'local', r, true)\n\
+            assert(x == 2, x, true)",
+        ));
+    }
+
+    #[test]
+    fn test_assign_global_in_loop() {
+        let text = "total = 0\n\
+            add = (n) =>\n    \
+                global total\n    \
+                total += n\n\
+            f = () =>\n    \
+                i = 0\n    \
+                seen = []\n    \
+                loop i < 3 ->\n        \
+                    seen.push(total)\n        \
+                    add(1)\n        \
+                    i += 1\n    \
+                seen\n\
+            r = f()\n\
+            assert(r == [0, 1, 2], r, true)\n\
+            assert(total == 3, total, true)";
+        assert_result_is_ok(run_text(text));
+        assert_result_is_ok(run_text_optimized(text));
+    }
+
+    #[test]
+    fn test_assignment_without_global_is_local() {
+        assert_result_is_ok(run_text(
+            "x = 1\n\
+            f = () =>\n    \
+                x = 2\n    \
+                x\n\
+            assert(f() == 2, '', true)\n\
+            assert(x == 1, x, true)",
+        ));
+    }
+
+    #[test]
+    fn test_global_not_found() {
+        assert_result_is_err(run_text("f = () =>\n    global x\n    x = 1"));
+    }
+
+    #[test]
+    fn test_global_after_local() {
+        assert_result_is_err(run_text(
+            "x = 1\nf = () =>\n    x = 2\n    global x\n    x = 3",
+        ));
+        assert_result_is_err(run_text("x = 1\nf = (x) =>\n    global x\n    x"));
+    }
+
+    #[test]
+    fn test_update_global_without_global_decl() {
+        assert_result_is_err(run_text("x = 1\nf = () =>\n    x += 1"));
+    }
+
+    #[test]
+    fn test_global_outside_func() {
+        assert_result_is_err(run_text("x = 1\nglobal x"));
+    }
+}
+
+mod collections {
+    use super::*;
+
+    #[test]
+    fn test_deque() {
+        assert_result_is_ok(run_text(
+            "from std.collections import Deque\n\
+            d = Deque.new([1, 2])\n\
+            d.push_front(0)\n\
+            d.push_back(3)\n\
+            assert(d.length == 4, d, true)\n\
+            assert(d.to_list() == [0, 1, 2, 3], d, true)\n\
+            assert(d.peek_front() == 0, d, true)\n\
+            assert(d.peek_back() == 3, d, true)\n\
+            assert(d.pop_front() == 0, d, true)\n\
+            assert(d.pop_back() == 3, d, true)\n\
+            assert(2 in d, d, true)\n\
+            d.clear()\n\
+            assert(d.is_empty, d, true)\n\
+            assert(d.pop_front() == nil, d, true)\n\
+            assert(!Deque.new(), '', true)\n\
+            assert(Deque.new(1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_priority_queue() {
+        assert_result_is_ok(run_text(
+            "from std.collections import PriorityQueue\n\
+            q = PriorityQueue.new()\n\
+            assert(q.pop_min() == nil, q, true)\n\
+            [5, 1, 4, 2, 3, 1].each((n) => q.push(n))\n\
+            assert(q.length == 6, q, true)\n\
+            assert(q.peek() == 1, q, true)\n\
+            items = []\n\
+            loop !q.is_empty ->\n    \
+                items.push(q.pop_min())\n\
+            assert(items == [1, 1, 2, 3, 4, 5], items, true)",
+        ));
+    }
+
+    #[test]
+    fn test_priority_queue_with_key() {
+        assert_result_is_ok(run_text(
+            "from std.collections import PriorityQueue\n\
+            q = PriorityQueue.new((item) => item[0])\n\
+            q.push((2, 'b'))\n\
+            q.push((1, 'a'))\n\
+            q.push((2, 'c'))\n\
+            q.push((1, 'd'))\n\
+            items = [q.pop_min(), q.pop_min(), q.pop_min(), q.pop_min()]\n\
+            assert(items.map((item) => item[1]).join('') == 'adbc', items, true)\n\
+            assert(PriorityQueue.new(1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_priority_queue_with_incomparable_items() {
+        assert_result_is_err(run_text(
+            "from std.collections import PriorityQueue\n\
+            q = PriorityQueue.new()\n\
+            q.push(1)\n\
+            q.push('a')",
+        ));
+    }
+}
+
+mod custom_types {
+    use super::*;
+
+    const POINT: &str = "Point = (x, y) =>\n    \"A point\"\n    \
+        sum = () => this.x + this.y\n    \
+        scaled_sum = (n) => this.sum() * n\n";
+
+    fn run_with_point(text: &str) -> ExeResult {
+        run_text(format!("{POINT}{text}").as_str())
+    }
+
+    #[test]
+    fn test_fields_and_methods() {
+        assert_result_is_ok(run_with_point(
+            "p = Point.new(1, 2)\n\
+            assert(p.x == 1, '', true)\n\
+            assert(p.y == 2, '', true)\n\
+            assert(p.sum() == 3, '', true)\n\
+            assert(p.scaled_sum(2) == 6, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_set_attr() {
+        assert_result_is_ok(run_with_point(
+            "p = Point.new(1, 2)\n\
+            p.x = 3\n\
+            assert(p.x == 3, '', true)\n\
+            assert(p.sum() == 5, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_inplace_attr() {
+        assert_result_is_ok(run_with_point(
+            "p = Point.new(1, 2)\n\
+            p.x += 10\n\
+            assert(p.x == 11, p.x, true)",
+        ));
+    }
+
+    #[test]
+    fn test_introspection() {
+        assert_result_is_ok(run_with_point(
+            "p = Point.new(1, 2)\n\
+            assert(p.$type $$ Point, '', true)\n\
+            assert(Point.$name == 'Point', '', true)\n\
+            assert(Point.$full_name == '$main.Point', '', true)\n\
+            assert(Point.$fields == ('x', 'y'), '', true)\n\
+            assert(Point.$doc == 'A point', '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_result_is_ok(run_with_point(
+            "assert(Point.new(1, 2) == Point.new(1, 2), '', true)\n\
+            assert(Point.new(1, 2) != Point.new(2, 1), '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_new_requires_all_fields() {
+        assert_result_is_err(run_with_point("Point.new(1)"));
+    }
+
+    #[test]
+    fn test_members_must_be_assignments() {
+        assert_result_is_err(run_text("Point = (x, y) =>\n    x + y"));
+    }
+
+    #[test]
+    fn test_str_and_repr_members() {
+        assert_result_is_ok(run_text(
+            "Money = (amount) =>\n    \
+                $str = () => $'${this.amount}'\n    \
+                $repr = () => $'Money({this.amount})'\n\
+            m = Money.new(5)\n\
+            assert($'{m}' == '$5', $'{m}', true)\n\
+            assert($'{m!r}' == 'Money(5)', $'{m!r}', true)",
+        ));
+    }
+
+    #[test]
+    fn test_str_member_must_return_str() {
+        assert_result_is_err(run_text(
+            "Box = (x) =>\n    $str = () => this.x\n\
+            b = Box.new(1)\n\
+            $'{b}'",
+        ));
+    }
+
+    #[test]
+    fn test_this_in_nested_func() {
+        assert_result_is_ok(run_text(
+            "Box = (x) =>\n    \
+                scaled = (n) =>\n        \
+                    f = () => this.x * n\n        \
+                    f()\n    \
+                getter = () =>\n        \
+                    outer = () =>\n            \
+                        () => this.x\n        \
+                    outer()\n\
+            assert(Box.new(2).scaled(3) == 6, '', true)\n\
+            get = Box.new(5).getter()\n\
+            assert(get() == 5, get(), true)",
+        ));
+    }
+
+    #[test]
+    fn test_this_outside_method() {
+        assert_result_is_err(run_text("this"));
+        assert_result_is_err(run_text("f = () => this\nf()"));
+        assert_result_is_err(run_text("Box = (x) =>\n    y = this"));
+    }
+}
+
+mod enums {
+    use super::*;
+
+    const COLOR: &str = "Color = enum (Red, Green, Blue)\n";
+
+    fn run_with_color(text: &str) -> ExeResult {
+        run_text(format!("{COLOR}{text}").as_str())
+    }
+
+    #[test]
+    fn test_variants() {
+        assert_result_is_ok(run_with_color(
+            "assert(Color.Red $$ Color.Red, '', true)\n\
+            assert(Color.Red == Color.Red, '', true)\n\
+            assert(Color.Red != Color.Green, '', true)\n\
+            assert(Color.Red.$type $$ Color, '', true)\n\
+            assert(Color.Green.name == 'Green', '', true)\n\
+            assert(Color.Blue.ordinal == 2, '', true)\n\
+            assert($\"{Color.Blue}\" == 'Color.Blue', '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert_result_is_ok(run_with_color(
+            "assert(Color.Red < Color.Green, '', true)\n\
+            assert(Color.Blue > Color.Green, '', true)\n\
+            assert(Color.Blue >= Color.Blue, '', true)",
+        ));
+        assert_result_is_err(run_with_color(
+            "Size = enum (Small, Large)\n\
+            Color.Red < Size.Small",
+        ));
+    }
+
+    #[test]
+    fn test_values() {
+        assert_result_is_ok(run_with_color(
+            "assert(Color.values() == (Color.Red, Color.Green, Color.Blue), '', true)\n\
+            names = Color.values().map((c) => c.name)\n\
+            assert(names == ('Red', 'Green', 'Blue'), names, true)",
+        ));
+    }
+
+    #[test]
+    fn test_match() {
+        assert_result_is_ok(run_with_color(
+            "c = Color.Green\n\
+            r = match c ->\n    \
+                Color.Red -> 'r'\n    \
+                Color.Green -> 'g'\n    \
+                * -> 'b'\n\
+            assert(r == 'g', r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_new_enum() {
+        assert_result_is_ok(run_text(
+            "import std.system\n\
+            Size = new_enum(system.modules.get('$main'), 'Size', ('S', 'M', 'L'))\n\
+            assert(Size.M.ordinal == 1, '', true)\n\
+            assert(Size.$full_name == '$main.Size', '', true)\n\
+            assert(new_enum(system.modules.get('$main'), 'E', ('A', 'A')).err, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_invalid_variants() {
+        assert_result_is_err(run_text("Color = enum (red, green)"));
+        assert_result_is_err(run_text("Color = enum (Red, Red)"));
+        assert_result_is_err(run_text("Color = enum ()"));
+    }
+}
+
+mod system {
+    use super::*;
+
+    #[test]
+    fn test_keywords() {
+        assert_result_is_ok(run_text(
+            "import std.system\n\
+            assert(system.keywords.length == 26, system.keywords.length, true)\n\
+            assert(system.keywords.0 == \"$halt\", system.keywords.0, true)",
+        ));
+    }
+
+    #[test]
+    fn test_version_and_build() {
+        assert_result_is_ok(run_text(
+            "import std.system\n\
+            assert(system.version.length > 0, system.version, true)\n\
+            assert(system.build.has('git_hash'), system.build, true)\n\
+            assert(system.build.has('date'), system.build, true)\n\
+            assert(system.build.has('rustc'), system.build, true)",
+        ));
+    }
+
+    #[test]
+    fn test_feature() {
+        assert_result_is_ok(run_text(
+            "import std.system\n\
+            assert(system.feature('ranges'), '', true)\n\
+            assert(!system.feature('time_travel'), '', true)\n\
+            assert(system.feature(1).err, '', true)",
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std-min")]
+    fn test_import() {
+        assert_result_is_ok(run_text(
+            "import std.system\n\
+            name = 'std.' + 'args'\n\
+            args = system.import(name)\n\
+            assert(args.$name == 'std.args', args, true)\n\
+            assert(system.import(name) $$ args, '', true)\n\
+            err = system.import('std.nope').err\n\
+            assert(err.type == ErrType.module_not_found, err, true)",
+        ));
+    }
+}
+
+#[cfg(feature = "std-full")]
+mod thread {
+    use super::*;
+
+    #[test]
+    fn test_spawn_and_join() {
+        assert_result_is_ok(run_text(
+            "from std.thread import spawn\n\
+            x = 2\n\
+            t = spawn(() => x + 1)\n\
+            assert(t.join() == 3, '', true)\n\
+            assert(t.done, t, true)\n\
+            assert(t.join() == 3, '', true)\n\
+            assert(spawn(1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_err_raised_by_join() {
+        assert_result_is_ok(run_text(
+            "from std.thread import spawn\n\
+            t = spawn(() => 1 + nil)\n\
+            r = try -> t.join() catch e -> e.type\n\
+            assert(r == ErrType.type, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_objects_copied_into_thread() {
+        assert_result_is_ok(run_text(
+            "from std.thread import spawn\n\
+            items = []\n\
+            t1 = spawn(() => items.push(1))\n\
+            t2 = spawn(() => items.push(2))\n\
+            t1.join()\n\
+            t2.join()\n\
+            assert(items.length == 0, items, true)\n\
+            make_counter = () =>\n    \
+                counts = []\n    \
+                () =>\n        \
+                    counts.push(1)\n        \
+                    counts.length\n\
+            counter = make_counter()\n\
+            assert(spawn(counter).join() == 1, '', true)\n\
+            assert(counter() == 1, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_cyclic_objects_copied_into_thread() {
+        assert_result_is_ok(run_text(
+            "from std.thread import spawn\n\
+            items = []\n\
+            items.push(items)\n\
+            r = spawn(() => items.0 $$ items).join()\n\
+            assert(r, '', true)\n\
+            make_fact = () =>\n    \
+                fact = (n) => if n < 2 -> 1 else -> n * fact(n - 1)\n    \
+                fact\n\
+            fact = make_fact()\n\
+            assert(spawn(() => fact(5)).join() == 120, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_iterators_not_copied_into_thread() {
+        assert_result_is_ok(run_text(
+            "from std.thread import spawn\n\
+            make_next = () =>\n    \
+                it = (1, 2).iter()\n    \
+                () => it.next()\n\
+            r = spawn(make_next())\n\
+            assert(r.err.type == ErrType.type, r, true)",
+        ));
+    }
+
+    #[test]
+    fn test_channel() {
+        assert_result_is_ok(run_text(
+            "from std.thread import channel, spawn\n\
+            ch = channel()\n\
+            produce = () =>\n    ch.send(1)\n    ch.send(2)\n    ch.close()\n\
+            t = spawn(produce)\n\
+            assert(ch.recv() == 1, '', true)\n\
+            assert(ch.recv() == 2, '', true)\n\
+            assert(ch.recv().err.type == ErrType.thread, '', true)\n\
+            assert(ch.send(3).err.type == ErrType.thread, '', true)\n\
+            t.join()",
+        ));
+    }
+
+    #[test]
+    fn test_atom() {
+        assert_result_is_ok(run_text(
+            "a = Atom.new(1)\n\
+            assert(a.get() == 1, '', true)\n\
+            assert(a.set(2) == 2, '', true)\n\
+            assert(a.swap((v) => v * 10) == 20, '', true)\n\
+            assert(a.get() == 20, '', true)\n\
+            assert(a.swap(1).err.type == ErrType.arg, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_atom_shared_between_threads() {
+        assert_result_is_ok(run_text(
+            "from std.thread import spawn\n\
+            counter = Atom.new(0)\n\
+            incr = () =>\n    i = 0\n    loop i < 100 ->\n        \
+                i += 1\n        counter.swap((n) => n + 1)\n\
+            t1 = spawn(incr)\n\
+            t2 = spawn(incr)\n\
+            t3 = spawn(incr)\n\
+            t1.join()\n\
+            t2.join()\n\
+            t3.join()\n\
+            assert(counter.get() == 300, counter.get(), true)",
+        ));
+    }
+}
+
+mod async_funcs {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        assert_result_is_ok(run_text(
+            "from std.async import run\n\
+            add = async (a, b) => a + b\n\
+            task = add(1, 2)\n\
+            assert(!task.done, task, true)\n\
+            assert(run(task) == 3, task, true)\n\
+            assert(task.done && task.result == 3, task, true)",
+        ));
+    }
+
+    #[test]
+    fn test_await() {
+        assert_result_is_ok(run_text(
+            "from std.async import run\n\
+            double = async (x) => x * 2\n\
+            main = async () =>\n    a = await double(1)\n    b = await double(a)\n    a + b\n\
+            assert(run(main()) == 6, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_spawn_and_sleep() {
+        assert_result_is_ok(run_text(
+            "from std.async import run, sleep, spawn\n\
+            log = []\n\
+            worker = async (name, delay) =>\n    await sleep(delay)\n    log.push(name)\n\
+            main = async () =>\n    a = spawn(worker('a', 0.02))\n    b = spawn(worker('b', 0.01))\n    await a\n    await b\n\
+            run(main())\n\
+            assert(log.join(',') == 'b,a', log, true)",
+        ));
+    }
+
+    #[test]
+    fn test_err_propagates_through_await() {
+        assert_result_is_ok(run_text(
+            "from std.async import run\n\
+            fail = async () => 1 + nil\n\
+            main = async () => try -> await fail() catch e -> e.type\n\
+            assert(run(main()) == ErrType.type, '', true)\n\
+            r = try -> run(fail()) catch e -> e.type\n\
+            assert(r == ErrType.type, '', true)",
+        ));
+    }
+
+    #[test]
+    fn test_await_outside_async_func() {
+        assert_result_is_err(run_text("f = () => await g()"));
+        assert_result_is_err(run_text("await g()"));
+    }
+
+    #[test]
+    fn test_spawn_requires_event_loop() {
+        assert_result_is_err(run_text(
+            "from std.async import spawn\n\
+            f = async () => nil\n\
+            spawn(f())",
+        ));
+    }
+}
+
+mod big_modules {
+    use super::*;
+
+    // Large enough that constant indexes, jump distances, etc. won't
+    // fit in 16 bits.
+    const N: usize = 70_000;
+
+    #[test]
+    fn test_many_constants() {
+        let mut text = String::from("total = 0\nnames = []\n");
+        for i in 0..N {
+            text.push_str(&format!("total = total + {i}\nnames.push(\"n{i}\")\n"));
+        }
+        let sum = N * (N - 1) / 2;
+        let last = N - 1;
+        text.push_str(&format!(
+            "assert(total == {sum}, total, true)\n\
+            assert(names.length == {N}, names.length, true)\n\
+            assert(names[{last}] == \"n{last}\", names[{last}], true)\n\
+            assert(1 == 1.0 && (1.0).$type $$ Float, 1.0, true)"
+        ));
+        assert_result_is_ok(run_text(&text));
+    }
+
+    #[test]
+    fn test_many_globals() {
+        let mut text = String::new();
+        for i in 0..N {
+            text.push_str(&format!("v{i} = {i}\n"));
+        }
+        let last = N - 1;
+        text.push_str(&format!("assert(v0 + v{last} == {last}, v{last}, true)"));
+        assert_result_is_ok(run_text(&text));
+    }
+
+    #[test]
+    fn test_long_jumps() {
+        let mut body = String::new();
+        for i in 0..N {
+            body.push_str(&format!("    x += {i}\n"));
+        }
+        // NOTE: `=` in a block declares a new local var, so the outer
+        //       `x` is updated with augmented assignment.
+        let text = format!(
+            "x = 0\n\
+            if false ->\n{body}else ->\n    x -= 1\n\
+            assert(x == -1, x, true)\n\
+            i = 0\n\
+            x = 0\n\
+            loop i < 2 ->\n{body}    i += 1\n\
+            assert(x == {}, x, true)",
+            N * (N - 1)
+        );
+        assert_result_is_ok(run_text(&text));
+    }
+
+    #[test]
+    fn test_deeply_nested_blocks() {
+        // 70 levels is 280 spaces of indentation at the deepest level
+        let depth = 70;
+        let mut text = String::from("i = 0\n");
+        for level in 0..depth {
+            let indent = " ".repeat(level * 4);
+            text.push_str(&format!("{indent}i += 1\n{indent}if true ->\n"));
+        }
+        text.push_str(&" ".repeat(depth * 4));
+        text.push_str(&format!("nil\nassert(i == {depth}, i, true)"));
+        // The parser and compiler recurse per level of nesting, which
+        // needs more stack than the default for test threads (2 MB).
+        let result = std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || {
+                run_text(&text).map(|_| ()).map_err(|err| format!("{err:?}"))
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(result.is_ok(), "{:?}", result.err());
     }
 }
@@ -217,6 +217,27 @@ fn scan_unexpected_indent_on_first_line() {
     }
 }
 
+#[test]
+fn scan_deeply_nested_blocks() {
+    // More than 255 spaces of indentation at the deepest level
+    let depth = 70;
+    let mut source = String::new();
+    for level in 0..depth {
+        source.push_str(&format!("{}block ->\n", " ".repeat(level * 4)));
+    }
+    source.push_str(&format!("{}x", " ".repeat(depth * 4)));
+    let tokens = scan_to_tokens(source.as_str());
+    let count = |token| tokens.iter().filter(|t| **t == token).count();
+    assert_eq!(count(Token::ScopeStart), depth);
+    assert_eq!(count(Token::ScopeEnd), depth);
+}
+
+#[test]
+fn scan_many_bangs() {
+    let source = format!("{}x", "!".repeat(300));
+    assert_eq!(scan_to_tokens(source.as_str())[0], Token::BangBang);
+}
+
 #[test]
 fn scan_brackets() {
     let source = "
@@ -250,12 +271,12 @@ b = 3
 
 #[test]
 fn scan_unknown() {
-    let source = "\\";
+    let source = "~";
     match scan_text(source) {
         Ok(_tokens) => assert!(false),
         Err(err) => match err {
             ScanErr { kind: ScanErrKind::UnexpectedChar(c), location } => {
-                assert_eq!(c, '\\');
+                assert_eq!(c, '~');
                 assert_eq!(location.line, 1);
                 assert_eq!(location.col, 1);
             }
@@ -438,6 +459,149 @@ fn scan_inline_block_5_with_parens() {
     );
 }
 
+#[test]
+fn scan_not_in() {
+    let tokens = scan_to_tokens("5 not  in r\nnot = 1\nnot inside");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Int(BigInt::from(5)),
+            Token::NotIn,
+            Token::Ident("r".to_owned()),
+            Token::EndOfStatement,
+            Token::Ident("not".to_owned()),
+            Token::Equal,
+            Token::Int(BigInt::from(1)),
+            Token::EndOfStatement,
+            Token::Ident("not".to_owned()),
+            Token::Ident("inside".to_owned()),
+            Token::EndOfStatement,
+        ]
+    );
+}
+
+#[test]
+fn scan_range() {
+    let tokens = scan_to_tokens("1..10 1..=10 5 in r");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Int(BigInt::from(1)),
+            Token::DotDot,
+            Token::Int(BigInt::from(10)),
+            Token::Int(BigInt::from(1)),
+            Token::DotDotEqual,
+            Token::Int(BigInt::from(10)),
+            Token::Int(BigInt::from(5)),
+            Token::In,
+            Token::Ident("r".to_owned()),
+            Token::EndOfStatement,
+        ]
+    );
+}
+
+#[test]
+fn scan_line_continuation() {
+    let tokens = scan_to_tokens("x = 1 \\\n    + 2 +  # comment\n\n  3\ny");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Ident("x".to_owned()),
+            Token::Equal,
+            Token::Int(BigInt::from(1)),
+            Token::Plus,
+            Token::Int(BigInt::from(2)),
+            Token::Plus,
+            Token::Int(BigInt::from(3)),
+            Token::EndOfStatement,
+            Token::Ident("y".to_owned()),
+            Token::EndOfStatement,
+        ]
+    );
+}
+
+#[test]
+fn scan_line_continuation_end_of_statement_location() {
+    let tokens = scan_optimistic("a &&\n    b");
+    assert_eq!(tokens.len(), 4);
+    check_token(tokens.get(2), Token::Ident("b".to_owned()), 2, 5, 2, 5);
+    check_token(tokens.get(3), Token::EndOfStatement, 2, 6, 2, 6);
+}
+
+#[test]
+fn scan_line_continuation_at_end_of_input() {
+    for text in ["1 +", "1 +\n", "1 \\", "1 \\\n# comment\n"] {
+        assert!(matches!(
+            scan_text(text),
+            Err(ScanErr { kind: ScanErrKind::ExpectedLineContinuation, .. })
+        ));
+    }
+}
+
+#[test]
+fn scan_block_comment() {
+    let tokens = scan_optimistic("x = #[ a\n#[ b ]# c ]# 1  #[ d ]#  # e\ny");
+    assert_eq!(tokens.len(), 6);
+    check_token(tokens.first(), Token::Ident("x".to_owned()), 1, 1, 1, 1);
+    check_token(tokens.get(1), Token::Equal, 1, 3, 1, 3);
+    check_token(tokens.get(2), Token::Int(BigInt::from(1)), 2, 14, 2, 14);
+    check_token(tokens.get(3), Token::EndOfStatement, 2, 15, 2, 15);
+    check_token(tokens.get(4), Token::Ident("y".to_owned()), 3, 1, 3, 1);
+}
+
+#[test]
+fn scan_block_comment_in_block() {
+    let tokens = scan_to_tokens(
+        "block ->\n    #[ a ]# x = 1\n    #[\nb\n    ]#\n\n    x\n#[ c ]#\ny",
+    );
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Block,
+            Token::ScopeStart,
+            Token::Ident("x".to_owned()),
+            Token::Equal,
+            Token::Int(BigInt::from(1)),
+            Token::EndOfStatement,
+            Token::Ident("x".to_owned()),
+            Token::EndOfStatement,
+            Token::ScopeEnd,
+            Token::EndOfStatement,
+            Token::Ident("y".to_owned()),
+            Token::EndOfStatement,
+        ]
+    );
+}
+
+#[test]
+fn scan_block_comment_unterminated() {
+    match scan_text("x = 1 #[ a #[ b ]#\n") {
+        Err(ScanErr { kind: ScanErrKind::UnterminatedComment, location }) => {
+            assert_eq!(location, Location::new(1, 7));
+        }
+        result => panic!("Expected unterminated comment error; got {result:?}"),
+    }
+}
+
+#[test]
+fn scan_trivia() {
+    let text = "# a\nx: Int = 1  #[ b ]#\nf = (y: List<Str>) => y  # c \n";
+    let mut source = source_from_text(text);
+    let mut scanner = Scanner::new(&mut source);
+    scanner.collect_trivia();
+    assert!(scanner.by_ref().all(|result| result.is_ok()));
+    assert_eq!(
+        scanner.take_trivia(),
+        vec![
+            Trivia::Comment("# a".to_owned(), Location::new(1, 1)),
+            Trivia::TypeHint("Int".to_owned(), Location::new(2, 1)),
+            Trivia::Comment("#[ b ]#".to_owned(), Location::new(2, 13)),
+            Trivia::TypeHint("List<Str>".to_owned(), Location::new(3, 6)),
+            Trivia::Comment("# c".to_owned(), Location::new(3, 26)),
+        ]
+    );
+}
+
 // Utilities -------------------------------------------------------
 
 /// Check token returned by scanner against expected token.
@@ -478,3 +642,47 @@ fn check_string_token(
         _ => assert!(false),
     }
 }
+
+#[test]
+fn scan_nil_safe_dot() {
+    let tokens = scan_to_tokens("a?.b ?? c");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Ident("a".to_owned()),
+            Token::NilSafeDot,
+            Token::Ident("b".to_owned()),
+            Token::NilOr,
+            Token::Ident("c".to_owned()),
+            Token::EndOfStatement,
+        ]
+    );
+}
+
+#[test]
+fn scan_import_attr() {
+    let tokens = scan_to_tokens("system.import(name)");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Ident("system".to_owned()),
+            Token::Dot,
+            Token::Ident("import".to_owned()),
+            Token::LParen,
+            Token::Ident("name".to_owned()),
+            Token::RParen,
+            Token::EndOfStatement,
+        ]
+    );
+}
+
+#[test]
+fn scan_line_too_long() {
+    let text = format!("x = 1\ns = '{}'\n", "a".repeat(5000));
+    match scan_text(&text) {
+        Err(ScanErr { kind: ScanErrKind::LineTooLong(4096), location }) => {
+            assert_eq!(location, Location::new(2, 1));
+        }
+        result => panic!("Expected line too long error; got {result:?}"),
+    }
+}
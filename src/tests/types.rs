@@ -167,3 +167,102 @@ mod custom {
         check_ne(t1_obj1.clone(), t2_obj1.clone());
     }
 }
+
+mod conv {
+    use std::collections::HashMap;
+
+    use crate::types::{FromObjectRef, IntoObjectRef};
+
+    use super::*;
+
+    #[test]
+    fn test_try_from_obj() {
+        let int = new::int(42);
+        let int = int.read().unwrap();
+        assert_eq!(i64::try_from(&*int).unwrap(), 42);
+        assert_eq!(usize::try_from(&*int).unwrap(), 42);
+        assert_eq!(f64::try_from(&*int).unwrap(), 42.0);
+        assert!(bool::try_from(&*int).is_err());
+        assert!(String::try_from(&*int).is_err());
+
+        let neg = new::int(-1);
+        let neg = neg.read().unwrap();
+        assert!(usize::try_from(&*neg).is_err());
+
+        let float = new::float(1.5);
+        let float = float.read().unwrap();
+        assert_eq!(f64::try_from(&*float).unwrap(), 1.5);
+        assert!(i64::try_from(&*float).is_err());
+
+        let s = new::str("abc");
+        let s = s.read().unwrap();
+        assert_eq!(String::try_from(&*s).unwrap(), "abc");
+
+        let b = new::bool(true);
+        let b = b.read().unwrap();
+        assert!(bool::try_from(&*b).unwrap());
+    }
+
+    #[test]
+    fn test_into_obj_ref() {
+        check_eq(42i64.into_obj_ref(), new::int(42));
+        check_eq(42usize.into_obj_ref(), new::int(42));
+        check_eq(1.5f64.into_obj_ref(), new::float(1.5));
+        check_eq("abc".into_obj_ref(), new::str("abc"));
+        check_eq(String::from("abc").into_obj_ref(), new::str("abc"));
+        check_is(true.into_obj_ref(), new::bool(true));
+        check_is(None::<i64>.into_obj_ref(), new::nil());
+        check_eq(Some(1i64).into_obj_ref(), new::int(1));
+    }
+
+    #[test]
+    fn test_containers() {
+        let list = new::list(vec![new::int(1), new::int(2)]);
+        assert_eq!(Vec::<i64>::from_obj_ref(&list).unwrap(), vec![1, 2]);
+        let tuple = new::tuple(vec![new::str("a"), new::nil()]);
+        let items = Vec::<Option<String>>::from_obj_ref(&tuple).unwrap();
+        assert_eq!(items, vec![Some("a".to_owned()), None]);
+        assert!(Vec::<i64>::from_obj_ref(&tuple).is_err());
+        assert!(Vec::<i64>::from_obj_ref(&new::int(1)).is_err());
+
+        let map =
+            new::map_from_keys_and_vals(vec!["a".to_owned()], vec![new::float(1.5)]);
+        let map = HashMap::<String, f64>::from_obj_ref(&map).unwrap();
+        assert_eq!(map, HashMap::from([("a".to_owned(), 1.5)]));
+
+        check_eq(vec![1i64, 2].into_obj_ref(), list);
+        check_eq(
+            HashMap::from([("a".to_owned(), true)]).into_obj_ref(),
+            new::map_from_keys_and_vals(vec!["a".to_owned()], vec![new::bool(true)]),
+        );
+        let nested = vec![vec!["a"], vec![]].into_obj_ref();
+        assert_eq!(
+            Vec::<Vec<String>>::from_obj_ref(&nested).unwrap(),
+            vec![vec!["a".to_owned()], vec![]]
+        );
+    }
+
+    #[test]
+    fn test_value() {
+        use crate::value::Value;
+
+        let value = Value::from(vec![Value::from(1), Value::from("a"), Value::Nil]);
+        let obj = value.clone().into_obj_ref();
+        let obj = obj.read().unwrap();
+        assert!(obj.is_list());
+        assert_eq!(Value::try_from(&*obj).unwrap(), value);
+
+        let tuple = new::tuple(vec![new::float(1.5), new::bool(true)]);
+        let tuple = tuple.read().unwrap();
+        let expected = Value::List(vec![Value::Float(1.5), Value::Bool(true)]);
+        assert_eq!(Value::try_from(&*tuple).unwrap(), expected);
+
+        let map = new::map_from_keys_and_vals(vec![], vec![]);
+        let map = map.read().unwrap();
+        assert!(Value::try_from(&*map).is_err());
+
+        assert_eq!(i64::try_from(Value::Int(1)), Ok(1));
+        assert_eq!(f64::try_from(Value::Int(1)), Ok(1.0));
+        assert_eq!(String::try_from(Value::Int(1)), Err(Value::Int(1)));
+    }
+}
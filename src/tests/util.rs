@@ -1,4 +1,4 @@
-use crate::util::Stack;
+use crate::util::{Stack, Symbol};
 
 #[test]
 fn new_stack_is_empty() {
@@ -58,3 +58,19 @@ fn clear() {
     stack.clear();
     assert_eq!(stack.len(), 0);
 }
+
+#[test]
+fn intern_symbol() {
+    let a = Symbol::intern("a");
+    assert_eq!(Symbol::intern("a"), a);
+    assert_ne!(Symbol::intern("b"), a);
+    assert_eq!(a.as_str(), "a");
+    assert_eq!(a.to_string(), "a");
+}
+
+#[test]
+fn lookup_symbol() {
+    let a = Symbol::intern("a");
+    assert_eq!(Symbol::lookup("a"), Some(a));
+    assert_eq!(Symbol::lookup("$not_interned"), None);
+}
@@ -1,4 +1,4 @@
-use crate::op::BinaryOperator;
+use crate::op::{BinaryOperator, CompareOperator, UnaryOperator};
 use crate::types::{new, Module};
 use crate::vm::*;
 
@@ -16,3 +16,144 @@ fn execute_simple_program() {
     assert!(matches!(vm.execute_module(&module, 0), Ok(())));
     assert!(matches!(vm.state, VMState::Idle(Some(_))));
 }
+
+#[test]
+fn bool_results_are_shared_singletons() {
+    for (chunk, expected) in [
+        (
+            vec![
+                Inst::LoadConst(0),
+                Inst::LoadConst(1),
+                Inst::CompareOp(CompareOperator::LessThan),
+            ],
+            true,
+        ),
+        (
+            vec![
+                Inst::LoadConst(0),
+                Inst::LoadConst(1),
+                Inst::CompareOp(CompareOperator::IsEqual),
+            ],
+            false,
+        ),
+        (vec![Inst::LoadConst(0), Inst::UnaryOp(UnaryOperator::AsBool)], true),
+    ] {
+        let mut code = Code::with_chunk(chunk);
+        code.add_const(new::int(1));
+        code.add_const(new::int(2));
+        let module = Module::new("test".to_owned(), "test".to_owned(), code, None);
+        let mut vm = VM::default();
+        assert!(matches!(vm.execute_module(&module, 0), Ok(())));
+        let VMState::Idle(Some(result)) = &vm.state else {
+            panic!("Expected a result");
+        };
+        let result = result.read().unwrap();
+        assert!(result.is(&*new::bool(expected).read().unwrap()));
+    }
+}
+
+#[test]
+fn start_index_out_of_bounds_is_an_err() {
+    let code = Code::with_chunk(vec![Inst::LoadNil]);
+    let module = Module::new("test".to_owned(), "test".to_owned(), code, None);
+    let mut vm = VM::default();
+    let result = vm.execute_module(&module, 2);
+    assert!(matches!(result.unwrap_err().kind, RuntimeErrKind::UnexpectedState(_)));
+}
+
+#[test]
+fn continue_with_chunks() {
+    let module =
+        Module::new("test".to_owned(), "test".to_owned(), Code::default(), None);
+    let mut vm = VM::default();
+
+    let mut code = Code::with_chunk(vec![Inst::LoadConst(0), Inst::Pop]);
+    code.add_const(new::int(1));
+    assert!(matches!(vm.continue_with(&module, &code), Ok(())));
+
+    let mut code = Code::with_chunk(vec![Inst::LoadConst(0), Inst::Pop]);
+    code.add_const(new::int(2));
+    assert!(matches!(vm.continue_with(&module, &code), Ok(())));
+    assert!(matches!(vm.state, VMState::Idle(_)));
+}
+
+#[test]
+fn continue_with_unexpected_state() {
+    let module =
+        Module::new("test".to_owned(), "test".to_owned(), Code::default(), None);
+    let mut vm = VM::default();
+
+    // Leave a value on the stack
+    let mut code = Code::with_chunk(vec![Inst::LoadConst(0)]);
+    code.add_const(new::int(1));
+    assert!(matches!(vm.continue_with(&module, &code), Ok(())));
+
+    let mut code = Code::with_chunk(vec![Inst::LoadConst(0), Inst::Pop]);
+    code.add_const(new::int(2));
+    let result = vm.continue_with(&module, &code);
+    assert!(matches!(
+        result,
+        Err(RuntimeErr { kind: RuntimeErrKind::UnexpectedState(_) })
+    ));
+
+    // The VM is unwound, so the next chunk can be executed
+    assert!(matches!(vm.continue_with(&module, &code), Ok(())));
+}
+
+#[test]
+fn value_stack_overflow() {
+    let mut code = Code::with_chunk(
+        (0..=MAX_VALUE_STACK_SIZE).map(|_| Inst::LoadConst(0)).collect(),
+    );
+    code.add_const(new::int(1));
+    let module = Module::new("test".to_owned(), "test".to_owned(), code, None);
+    let mut vm = VM::default();
+    let result = vm.execute_module(&module, 0);
+    assert!(matches!(
+        result,
+        Err(RuntimeErr { kind: RuntimeErrKind::ValueStackOverflow(_) })
+    ));
+}
+
+#[test]
+fn step_through_code() {
+    let mut code = Code::with_chunk(vec![
+        Inst::LoadConst(0),
+        Inst::LoadConst(1),
+        Inst::BinaryOp(BinaryOperator::Add),
+    ]);
+    code.add_const(new::int(1));
+    code.add_const(new::int(2));
+    let module =
+        Module::new("test".to_owned(), "test".to_owned(), Code::default(), None);
+    let mut vm = VM::default();
+    assert!(matches!(vm.step(&module, &code, 0, 0), Ok(VMState::Paused(0))));
+    assert!(matches!(vm.step(&module, &code, 0, 2), Ok(VMState::Paused(2))));
+    assert!(matches!(vm.state, VMState::Paused(2)));
+    assert!(matches!(vm.step(&module, &code, 2, 10), Ok(VMState::Idle(Some(_)))));
+}
+
+#[test]
+fn step_through_infinite_loop() {
+    let code = Code::with_chunk(vec![Inst::NoOp, Inst::Jump(1, false, 0)]);
+    let module =
+        Module::new("test".to_owned(), "test".to_owned(), Code::default(), None);
+    let mut vm = VM::default();
+    let mut ip = 0;
+    for _ in 0..100 {
+        ip = match vm.step(&module, &code, ip, 3) {
+            Ok(VMState::Paused(ip)) => ip,
+            result => panic!("Expected VM to be paused; got {result:?}"),
+        };
+    }
+    // Each step executes 3 instructions, so after an even number of
+    // instructions, the next instruction is the NoOp at the start.
+    assert_eq!(ip, 0);
+}
+
+#[test]
+fn instructions_are_compact() {
+    // Keeping instructions small keeps more of a chunk in cache
+    // during dispatch.
+    assert!(std::mem::size_of::<Inst>() <= 32);
+}
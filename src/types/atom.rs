@@ -0,0 +1,182 @@
+//! Atoms hold a single value that can be shared between threads. The
+//! value is replaced rather than mutated, so an Atom is a safe place to
+//! keep shared state that changes over time.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+
+use once_cell::sync::Lazy;
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// Atom Type -----------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Atom type
+
+An Atom holds a value that can be read and replaced from any thread.
+Use `swap()` to update the value based on its current value without
+losing updates made by other threads.
+
+```
+→ a = Atom.new(0)
+→ a.swap((v) => v + 1)
+1
+→ a.get()
+1
+```
+";
+
+gen::type_and_impls!(AtomType, Atom);
+
+pub static ATOM_TYPE: Lazy<gen::obj_ref_t!(AtomType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(AtomType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Class Methods -----------------------------------------------
+        gen::meth!(
+            "new",
+            type_ref,
+            &["value"],
+            "Create an Atom holding value.
+
+            # Args
+
+            - value: Any
+
+            ",
+            |_, args, _| Ok(new::atom(args[0].clone()))
+        ),
+        // Instance Methods --------------------------------------------
+        gen::meth!("get", type_ref, &[], "Get the current value.", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_atom().unwrap();
+            Ok(this.get())
+        }),
+        gen::meth!(
+            "set",
+            type_ref,
+            &["value"],
+            "Replace the current value and return the new value.",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_atom().unwrap();
+                this.set(args[0].clone());
+                Ok(args[0].clone())
+            }
+        ),
+        gen::meth!(
+            "swap",
+            type_ref,
+            &["func"],
+            "Replace the current value with the result of calling func
+            with the current value.
+
+            If another thread replaces the value while func is running,
+            func is called again with the new value, so func may be
+            called more than once and shouldn't have side effects.
+
+            # Args
+
+            - func: Func
+
+            # Returns
+
+            Any: The new value
+
+            ",
+            |this_ref, args, vm| {
+                let func = args[0].clone();
+                {
+                    let arg = func.read().unwrap();
+                    if !(arg.is_func()
+                        || arg.is_closure()
+                        || arg.is_bound_func()
+                        || arg.is_intrinsic_func())
+                    {
+                        let msg = "swap() expected func to be a function";
+                        return Ok(new::arg_err(msg, new::nil()));
+                    }
+                }
+                loop {
+                    let current = {
+                        let this = this_ref.read().unwrap();
+                        this.down_to_atom().unwrap().get()
+                    };
+                    // NOTE: The atom isn't locked while func runs so
+                    //       that func can use the atom too.
+                    vm.call(func.clone(), vec![current.clone()])?;
+                    let value = vm.pop_obj()?;
+                    let this = this_ref.read().unwrap();
+                    let this = this.down_to_atom().unwrap();
+                    if this.compare_and_set(&current, value.clone()) {
+                        break Ok(value);
+                    }
+                }
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Atom Object ---------------------------------------------------------
+
+pub struct Atom {
+    ns: Namespace,
+    value: Mutex<ObjectRef>,
+}
+
+gen::standard_object_impls!(Atom);
+
+impl Atom {
+    pub fn new(value: ObjectRef) -> Self {
+        Self { ns: Namespace::default(), value: Mutex::new(value) }
+    }
+
+    pub fn get(&self) -> ObjectRef {
+        self.value.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, value: ObjectRef) {
+        *self.value.lock().unwrap() = value;
+    }
+
+    /// Replace the current value with `value` only if the current value
+    /// is still `expected` (i.e., the same object). Returns `false` if
+    /// the value was replaced in the meantime.
+    pub fn compare_and_set(&self, expected: &ObjectRef, value: ObjectRef) -> bool {
+        let mut current = self.value.lock().unwrap();
+        if Arc::ptr_eq(&*current, expected) {
+            *current = value;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl ObjectTrait for Atom {
+    gen::object_trait_header!(ATOM_TYPE);
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<atom>")
+    }
+}
+
+impl fmt::Debug for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<atom {:?}>", &*self.get().read().unwrap())
+    }
+}
@@ -6,6 +6,7 @@ use std::sync::{Arc, RwLock};
 use num_bigint::BigInt;
 use num_traits::ToPrimitive;
 
+#[cfg(feature = "dis")]
 use crate::dis::Disassembler;
 use crate::modules::std::STD;
 use crate::types::FuncTrait;
@@ -16,12 +17,19 @@ use super::new;
 use super::ns::Namespace;
 
 use super::always::{Always, AlwaysType};
+use super::atom::{Atom, AtomType};
 use super::bool::{Bool, BoolType};
 use super::bound_func::{BoundFunc, BoundFuncType};
-use super::cell::{Cell, CellType};
+use super::byte_buffer::{ByteBuffer, ByteBufferType};
+use super::bytes::{Bytes, BytesType};
+use super::cell::{unwrap_cell, Cell, CellType};
+use super::channel::{Channel, ChannelType};
 use super::class::{Type, TypeType};
 use super::closure::{Closure, ClosureType};
 use super::custom::{CustomObj, CustomType};
+use super::date_time::{DateTime, DateTimeType};
+use super::deque::{Deque, DequeType};
+use super::enum_type::{EnumType, EnumVariant};
 use super::err::{ErrObj, ErrType};
 use super::err_type::{ErrTypeObj, ErrTypeType};
 use super::file::{File, FileType};
@@ -34,8 +42,15 @@ use super::list::{List, ListType};
 use super::map::{Map, MapType};
 use super::module::{Module, ModuleType};
 use super::nil::{Nil, NilType};
+use super::priority_queue::{PriorityQueue, PriorityQueueType};
 use super::prop::{Prop, PropType};
+use super::range::{Range, RangeType};
+use super::response::{Response, ResponseType};
+use super::set::{Set, SetType};
 use super::str::{Str, StrType};
+use super::task::{Task, TaskType};
+use super::temp_path::{TempPath, TempPathType};
+use super::thread::{Thread, ThreadType};
 use super::tuple::{Tuple, TupleType};
 
 pub type TypeRef = gen::obj_ref_t!(dyn TypeTrait);
@@ -136,7 +151,7 @@ macro_rules! make_bin_op {
 
 /// Objects in the system--instances of types--are backed by an
 /// implementation of `ObjectTrait`. Example: `Int`.
-pub trait ObjectTrait {
+pub trait ObjectTrait: Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 
@@ -213,6 +228,7 @@ pub trait ObjectTrait {
             return new::tuple(items);
         }
 
+        #[cfg(feature = "dis")]
         if name == "$dis" {
             // User functions, bound functions wrapping user functions,
             // and closures wrapping user functions can be disassembled.
@@ -251,7 +267,9 @@ pub trait ObjectTrait {
         // Check instance then instance type.
 
         if let Some(obj) = self.ns().get(name) {
-            return obj;
+            // Module globals that are assigned from functions are
+            // stored in cells.
+            return if self.is_mod() { unwrap_cell(obj) } else { obj };
         }
 
         if let Some(obj) = self.type_obj().read().unwrap().ns().get(name) {
@@ -281,17 +299,18 @@ pub trait ObjectTrait {
 
         // Error object associated with this object.
         //
-        // If this object *is* an error, a copy of the error that
-        // responds to bool is returned.
+        // If this object *is* an error, a copy of the error is
+        // returned.
         //
-        // If this object *is not* an error, the singleton OK object
-        // that responds to bool is returned.
+        // If this object *is not* an error, the singleton OK object is
+        // returned.
         if name == "err" {
             return if let Some(err) = this.read().unwrap().down_to_err() {
-                new::err_with_responds_to_bool(
+                new::err_with_data(
                     err.kind.clone(),
                     err.message.as_str(),
                     this.clone(),
+                    err.data.clone(),
                 )
             } else {
                 new::ok_err()
@@ -331,13 +350,17 @@ pub trait ObjectTrait {
         new::attr_not_found_err(name, obj)
     }
 
-    // Items (accessed by index) ---------------------------------------
+    // Items (accessed by index or key) --------------------------------
 
     fn get_item(&self, index: usize, this: ObjectRef) -> ObjectRef {
         // TODO: The default should be a "does not support" indexing err
         new::index_out_of_bounds_err(index, this)
     }
 
+    fn get_item_by_key(&self, key: &str, this: ObjectRef) -> ObjectRef {
+        new::type_err(format!("Object does not support lookup by key: {key}"), this)
+    }
+
     fn set_item(
         &mut self,
         index: usize,
@@ -348,6 +371,15 @@ pub trait ObjectTrait {
         new::index_out_of_bounds_err(index, this)
     }
 
+    fn set_item_by_key(
+        &mut self,
+        key: &str,
+        this: ObjectRef,
+        _value: ObjectRef,
+    ) -> ObjectRef {
+        new::type_err(format!("Object does not support assignment by key: {key}"), this)
+    }
+
     fn index_out_of_bounds(&self, index: usize, this: ObjectRef) -> ObjectRef {
         new::index_out_of_bounds_err(index, this)
     }
@@ -356,11 +388,17 @@ pub trait ObjectTrait {
 
     make_type_checker!(is_type_type, TypeType);
     make_type_checker!(is_always_type, AlwaysType);
+    make_type_checker!(is_atom_type, AtomType);
     make_type_checker!(is_bool_type, BoolType);
     make_type_checker!(is_bound_func_type, BoundFuncType);
+    make_type_checker!(is_byte_buffer_type, ByteBufferType);
+    make_type_checker!(is_bytes_type, BytesType);
     make_type_checker!(is_intrinsic_func_type, IntrinsicFuncType);
     make_type_checker!(is_cell_type, CellType);
+    make_type_checker!(is_channel_type, ChannelType);
     make_type_checker!(is_closure_type, ClosureType);
+    make_type_checker!(is_date_time_type, DateTimeType);
+    make_type_checker!(is_deque_type, DequeType);
     make_type_checker!(is_err_type, ErrType);
     make_type_checker!(is_err_type_type, ErrTypeType);
     make_type_checker!(is_file_type, FileType);
@@ -372,17 +410,30 @@ pub trait ObjectTrait {
     make_type_checker!(is_map_type, MapType);
     make_type_checker!(is_mod_type, ModuleType);
     make_type_checker!(is_nil_type, NilType);
+    make_type_checker!(is_priority_queue_type, PriorityQueueType);
     make_type_checker!(is_prop_type, PropType);
+    make_type_checker!(is_range_type, RangeType);
+    make_type_checker!(is_response_type, ResponseType);
+    make_type_checker!(is_set_type, SetType);
     make_type_checker!(is_str_type, StrType);
+    make_type_checker!(is_task_type, TaskType);
+    make_type_checker!(is_temp_path_type, TempPathType);
+    make_type_checker!(is_thread_type, ThreadType);
     make_type_checker!(is_tuple_type, TupleType);
 
     make_type_checker!(is_type, Type);
     make_type_checker!(is_always, Always);
+    make_type_checker!(is_atom, Atom);
     make_type_checker!(is_bool, Bool);
     make_type_checker!(is_bound_func, BoundFunc);
+    make_type_checker!(is_byte_buffer, ByteBuffer);
+    make_type_checker!(is_bytes, Bytes);
     make_type_checker!(is_intrinsic_func, IntrinsicFunc);
     make_type_checker!(is_cell, Cell);
+    make_type_checker!(is_channel, Channel);
     make_type_checker!(is_closure, Closure);
+    make_type_checker!(is_date_time, DateTime);
+    make_type_checker!(is_deque, Deque);
     make_type_checker!(is_err, ErrObj);
     make_type_checker!(is_err_type_obj, ErrTypeObj);
     make_type_checker!(is_file, File);
@@ -394,8 +445,15 @@ pub trait ObjectTrait {
     make_type_checker!(is_map, Map);
     make_type_checker!(is_mod, Module);
     make_type_checker!(is_nil, Nil);
+    make_type_checker!(is_priority_queue, PriorityQueue);
     make_type_checker!(is_prop, Prop);
+    make_type_checker!(is_range, Range);
+    make_type_checker!(is_response, Response);
+    make_type_checker!(is_set, Set);
     make_type_checker!(is_str, Str);
+    make_type_checker!(is_task, Task);
+    make_type_checker!(is_temp_path, TempPath);
+    make_type_checker!(is_thread, Thread);
     make_type_checker!(is_tuple, Tuple);
 
     /// Is this object a type object?
@@ -404,11 +462,27 @@ pub trait ObjectTrait {
     }
 
     fn is_immutable(&self) -> bool {
-        !(self.is_cell() || self.is_file() || self.is_list() || self.is_map())
+        !(self.is_atom()
+            || self.is_byte_buffer()
+            || self.is_cell()
+            || self.is_channel()
+            || self.is_deque()
+            || self.is_file()
+            || self.is_list()
+            || self.is_map()
+            || self.is_priority_queue()
+            || self.is_set()
+            || self.is_task()
+            || self.is_temp_path()
+            || self.is_thread())
     }
 
     fn is_seq(&self) -> bool {
-        self.is_list() || self.is_tuple()
+        self.is_list()
+            || self.is_tuple()
+            || self.is_range()
+            || self.is_bytes()
+            || self.is_byte_buffer()
     }
 
     // Downcasters -----------------------------------------------------
@@ -417,12 +491,19 @@ pub trait ObjectTrait {
 
     make_down_to!(down_to_type_type, TypeType);
     make_down_to!(down_to_always_type, AlwaysType);
+    make_down_to!(down_to_atom_type, AtomType);
     make_down_to!(down_to_bool_type, BoolType);
     make_down_to!(down_to_bound_func_type, BoundFuncType);
+    make_down_to!(down_to_byte_buffer_type, ByteBufferType);
+    make_down_to!(down_to_bytes_type, BytesType);
     make_down_to!(down_to_intrinsic_func_type, IntrinsicFuncType);
     make_down_to!(down_to_cell_type, CellType);
+    make_down_to!(down_to_channel_type, ChannelType);
     make_down_to!(down_to_closure_type, ClosureType);
     make_down_to!(down_to_custom_type, CustomType);
+    make_down_to!(down_to_date_time_type, DateTimeType);
+    make_down_to!(down_to_deque_type, DequeType);
+    make_down_to!(down_to_enum_type, EnumType);
     make_down_to!(down_to_err_type, ErrType);
     make_down_to!(down_to_err_type_type, ErrTypeType);
     make_down_to!(down_to_file_type, FileType);
@@ -434,25 +515,42 @@ pub trait ObjectTrait {
     make_down_to!(down_to_map_type, MapType);
     make_down_to!(down_to_mod_type, ModuleType);
     make_down_to!(down_to_nil_type, NilType);
+    make_down_to!(down_to_priority_queue_type, PriorityQueueType);
     make_down_to!(down_to_prop_type, PropType);
+    make_down_to!(down_to_range_type, RangeType);
+    make_down_to!(down_to_response_type, ResponseType);
+    make_down_to!(down_to_set_type, SetType);
     make_down_to!(down_to_str_type, StrType);
+    make_down_to!(down_to_task_type, TaskType);
+    make_down_to!(down_to_temp_path_type, TempPathType);
+    make_down_to!(down_to_thread_type, ThreadType);
     make_down_to!(down_to_tuple_type, TupleType);
 
     make_down_to!(down_to_type, Type);
     make_down_to!(down_to_always, Always);
+    make_down_to!(down_to_atom, Atom);
     make_down_to!(down_to_bool, Bool);
     make_down_to!(down_to_bound_func, BoundFunc);
+    make_down_to!(down_to_byte_buffer, ByteBuffer);
+    make_down_to!(down_to_bytes, Bytes);
     make_down_to!(down_to_intrinsic_func, IntrinsicFunc);
     make_down_to!(down_to_cell, Cell);
+    make_down_to!(down_to_channel, Channel);
     make_down_to_mut!(down_to_cell_mut, Cell);
     make_down_to!(down_to_closure, Closure);
+    make_down_to!(down_to_custom_obj, CustomObj);
+    make_down_to!(down_to_date_time, DateTime);
+    make_down_to!(down_to_deque, Deque);
+    make_down_to!(down_to_enum_variant, EnumVariant);
     make_down_to!(down_to_err, ErrObj);
     make_down_to!(down_to_err_type_obj, ErrTypeObj);
     make_down_to!(down_to_file, File);
     make_down_to_mut!(down_to_file_mut, File);
     make_down_to!(down_to_float, Float);
+    make_down_to_mut!(down_to_float_mut, Float);
     make_down_to!(down_to_func, Func);
     make_down_to!(down_to_int, Int);
+    make_down_to_mut!(down_to_int_mut, Int);
     make_down_to!(down_to_iterator, FIIterator);
     make_down_to_mut!(down_to_iterator_mut, FIIterator);
     make_down_to!(down_to_list, List);
@@ -460,8 +558,16 @@ pub trait ObjectTrait {
     make_down_to!(down_to_mod, Module);
     make_down_to_mut!(down_to_mod_mut, Module);
     make_down_to!(down_to_nil, Nil);
+    make_down_to!(down_to_priority_queue, PriorityQueue);
     make_down_to!(down_to_prop, Prop);
+    make_down_to!(down_to_range, Range);
+    make_down_to!(down_to_response, Response);
+    make_down_to!(down_to_set, Set);
     make_down_to!(down_to_str, Str);
+    make_down_to!(down_to_task, Task);
+    make_down_to_mut!(down_to_task_mut, Task);
+    make_down_to!(down_to_temp_path, TempPath);
+    make_down_to!(down_to_thread, Thread);
     make_down_to!(down_to_tuple, Tuple);
 
     fn as_func(&self) -> Option<&dyn FuncTrait> {
@@ -508,7 +614,13 @@ pub trait ObjectTrait {
     // Unary operations ------------------------------------------------
 
     make_unary_op!(negate, "-", RuntimeObjResult);
-    make_unary_op!(bool_val, "!!", RuntimeBoolResult);
+
+    /// Truthiness (`!!`). Objects are true by default. Types that
+    /// have a notion of being empty or zero override this so that
+    /// those values are false (e.g., `0`, `""`, `[]`, and `{}`).
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(true)
+    }
 
     fn not(&self) -> RuntimeBoolResult {
         match self.bool_val() {
@@ -546,6 +658,9 @@ pub trait ObjectTrait {
     make_bin_op!(less_than, "<", RuntimeBoolResult);
     make_bin_op!(greater_than, ">", RuntimeBoolResult);
 
+    // Check whether `member` is in this object (`member in obj`).
+    make_bin_op!(contains, "in", RuntimeBoolResult);
+
     make_bin_op!(pow, "^", RuntimeObjResult);
     make_bin_op!(modulo, "%", RuntimeObjResult);
     make_bin_op!(mul, "*", RuntimeObjResult);
@@ -553,6 +668,8 @@ pub trait ObjectTrait {
     make_bin_op!(floor_div, "//", RuntimeObjResult);
     make_bin_op!(add, "+", RuntimeObjResult);
     make_bin_op!(sub, "-", RuntimeObjResult);
+    make_bin_op!(bit_or, "|", RuntimeObjResult);
+    make_bin_op!(bit_and, "&", RuntimeObjResult);
 
     // Call ------------------------------------------------------------
 
@@ -616,10 +733,15 @@ impl fmt::Display for dyn ObjectTrait {
             AlwaysType,
             BoolType,
             BoundFuncType,
+            ByteBufferType,
+            BytesType,
             IntrinsicFuncType,
             CellType,
+            ChannelType,
             ClosureType,
             CustomType,
+            DateTimeType,
+            EnumType,
             ErrType,
             ErrTypeType,
             FileType,
@@ -632,7 +754,13 @@ impl fmt::Display for dyn ObjectTrait {
             ModuleType,
             NilType,
             PropType,
+            RangeType,
+            ResponseType,
+            SetType,
             StrType,
+            TaskType,
+            TempPathType,
+            ThreadType,
             TupleType
         );
         write_instance!(
@@ -642,10 +770,15 @@ impl fmt::Display for dyn ObjectTrait {
             Always,
             Bool,
             BoundFunc,
+            ByteBuffer,
+            Bytes,
             IntrinsicFunc,
             Cell,
+            Channel,
             Closure,
             CustomObj,
+            DateTime,
+            EnumVariant,
             ErrObj,
             ErrTypeObj,
             File,
@@ -658,7 +791,13 @@ impl fmt::Display for dyn ObjectTrait {
             Module,
             Nil,
             Prop,
+            Range,
+            Response,
+            Set,
             Str,
+            Task,
+            TempPath,
+            Thread,
             Tuple
         );
         panic!("Display must be defined");
@@ -674,10 +813,15 @@ impl fmt::Debug for dyn ObjectTrait {
             AlwaysType,
             BoolType,
             BoundFuncType,
+            ByteBufferType,
+            BytesType,
             IntrinsicFuncType,
             CellType,
+            ChannelType,
             ClosureType,
             CustomType,
+            DateTimeType,
+            EnumType,
             ErrType,
             ErrTypeType,
             FileType,
@@ -690,7 +834,13 @@ impl fmt::Debug for dyn ObjectTrait {
             ModuleType,
             NilType,
             PropType,
+            RangeType,
+            ResponseType,
+            SetType,
             StrType,
+            TaskType,
+            TempPathType,
+            ThreadType,
             TupleType
         );
         debug_instance!(
@@ -700,10 +850,15 @@ impl fmt::Debug for dyn ObjectTrait {
             Always,
             Bool,
             BoundFunc,
+            ByteBuffer,
+            Bytes,
             IntrinsicFunc,
             Cell,
+            Channel,
             Closure,
             CustomObj,
+            DateTime,
+            EnumVariant,
             ErrObj,
             ErrTypeObj,
             File,
@@ -716,7 +871,13 @@ impl fmt::Debug for dyn ObjectTrait {
             Module,
             Nil,
             Prop,
+            Range,
+            Response,
+            Set,
             Str,
+            Task,
+            TempPath,
+            Thread,
             Tuple
         );
         panic!("Debug must be defined");
@@ -0,0 +1,314 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::vm::{RuntimeBoolResult, RuntimeErr};
+
+use super::bytes::{
+    byte_val, data_from_obj, decode, slice_range, to_base64, to_hex, DATA_ARG_MSG,
+};
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// ByteBuffer Type -----------------------------------------------------
+
+static DOC: &str = "
+Intrinsic ByteBuffer type
+
+A mutable sequence of bytes. Items can be set by index and bytes can be
+appended with push() and extend(). Use to_bytes() to get an immutable
+copy.
+";
+
+gen::type_and_impls!(ByteBufferType, ByteBuffer);
+
+pub static BYTE_BUFFER_TYPE: Lazy<gen::obj_ref_t!(ByteBufferType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(ByteBufferType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Class Methods -----------------------------------------------
+        gen::meth!(
+            "new",
+            type_ref,
+            &[""],
+            "Create a ByteBuffer.
+
+            # Args
+
+            - data?: Str | Bytes | ByteBuffer | List | Tuple
+
+              Initial data. See `Bytes.new()`. If not specified, the
+              buffer will be empty.
+
+            ",
+            |_, args, _| {
+                let var_args = args[0].read().unwrap();
+                let var_args = var_args.down_to_tuple().unwrap();
+                let data = match var_args.len() {
+                    0 => Some(vec![]),
+                    1 => data_from_obj(&*var_args.get(0).unwrap().read().unwrap()),
+                    n => {
+                        let msg = format!("new() expected at most 1 arg; got {n}");
+                        return Ok(new::arg_err(msg, new::nil()));
+                    }
+                };
+                Ok(match data {
+                    Some(data) => new::byte_buffer(data),
+                    None => new::arg_err(DATA_ARG_MSG, new::nil()),
+                })
+            }
+        ),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("length", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_byte_buffer().unwrap();
+            Ok(new::int(this.len()))
+        }),
+        gen::prop!("is_empty", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_byte_buffer().unwrap();
+            Ok(new::bool(this.len() == 0))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!("iter", type_ref, &[], "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_byte_buffer().unwrap();
+            Ok(new::iterator(this.to_vec().into_iter().map(new::int).collect()))
+        }),
+        gen::meth!(
+            "push",
+            type_ref,
+            &["byte"],
+            "Push byte and return it.
+
+            # Args
+
+            - byte: Int (0 to 255)
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                if let Some(byte) = byte_val(&*arg) {
+                    this.value.write().unwrap().push(byte);
+                    Ok(args[0].clone())
+                } else {
+                    let msg = "push() expected byte to be an Int in the range 0 to 255";
+                    Ok(new::arg_err(msg, new::nil()))
+                }
+            }
+        ),
+        gen::meth!(
+            "pop",
+            type_ref,
+            &[],
+            "Remove the last byte and return it or nil if empty.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                let byte = this.value.write().unwrap().pop();
+                Ok(byte.map_or_else(new::nil, new::int))
+            }
+        ),
+        gen::meth!(
+            "extend",
+            type_ref,
+            &["data"],
+            "Append data and return this.
+
+            # Args
+
+            - data: Str | Bytes | ByteBuffer | List | Tuple
+
+            ",
+            |this_ref, args, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                // NOTE: The data is copied first in case a buffer is
+                //       extended with itself.
+                if let Some(data) = data_from_obj(&*arg) {
+                    this.value.write().unwrap().extend(data);
+                    Ok(this_ref.clone())
+                } else {
+                    Ok(new::arg_err(DATA_ARG_MSG, new::nil()))
+                }
+            }
+        ),
+        gen::meth!(
+            "clear",
+            type_ref,
+            &[],
+            "Remove all bytes and return this.",
+            |this_ref, _, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                this.value.write().unwrap().clear();
+                Ok(this_ref.clone())
+            }
+        ),
+        gen::meth!(
+            "slice",
+            type_ref,
+            &["start", ""],
+            "Copy the bytes from start up to, but not including, end
+            into a new ByteBuffer. See `Bytes.slice()`.",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                let value = this.value.read().unwrap();
+                Ok(match slice_range(&args, value.len()) {
+                    Ok(range) => new::byte_buffer(value[range].to_vec()),
+                    Err(err) => err,
+                })
+            }
+        ),
+        gen::meth!(
+            "decode",
+            type_ref,
+            &["encoding"],
+            "Decode bytes to a Str. See `Bytes.decode()`.",
+            |this_ref, args, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                let encoding = gen::use_arg_str!(decode, encoding, arg);
+                Ok(decode(&this.to_vec(), encoding, this_ref.clone()))
+            }
+        ),
+        gen::meth!(
+            "to_hex",
+            type_ref,
+            &[],
+            "Encode as lowercase hex.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                Ok(new::str(to_hex(&this.to_vec())))
+            }
+        ),
+        gen::meth!(
+            "to_base64",
+            type_ref,
+            &[],
+            "Encode as standard, padded base64.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                Ok(new::str(to_base64(&this.to_vec())))
+            }
+        ),
+        gen::meth!(
+            "to_bytes",
+            type_ref,
+            &[],
+            "Copy into a new, immutable Bytes object.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_byte_buffer().unwrap();
+                Ok(new::bytes(this.to_vec()))
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// ByteBuffer Object ---------------------------------------------------
+
+pub struct ByteBuffer {
+    ns: Namespace,
+    value: RwLock<Vec<u8>>,
+}
+
+gen::standard_object_impls!(ByteBuffer);
+
+impl ByteBuffer {
+    pub fn new(value: Vec<u8>) -> Self {
+        Self { ns: Namespace::default(), value: RwLock::new(value) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.read().unwrap().len()
+    }
+
+    /// Get a snapshot of the buffer's current bytes.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.value.read().unwrap().clone()
+    }
+}
+
+impl ObjectTrait for ByteBuffer {
+    gen::object_trait_header!(BYTE_BUFFER_TYPE);
+
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(self.len() > 0)
+    }
+
+    fn get_item(&self, index: usize, this: ObjectRef) -> ObjectRef {
+        if let Some(byte) = self.value.read().unwrap().get(index) {
+            new::int(*byte)
+        } else {
+            self.index_out_of_bounds(index, this)
+        }
+    }
+
+    fn set_item(
+        &mut self,
+        index: usize,
+        this: ObjectRef,
+        value: ObjectRef,
+    ) -> ObjectRef {
+        let Some(byte) = byte_val(&*value.read().unwrap()) else {
+            let msg = "ByteBuffer items must be Ints in the range 0 to 255";
+            return new::type_err(msg, this);
+        };
+        if let Some(item) = self.value.write().unwrap().get_mut(index) {
+            *item = byte;
+            new::nil()
+        } else {
+            self.index_out_of_bounds(index, this)
+        }
+    }
+
+    /// Only Ints in the range 0 to 255 can be members of a ByteBuffer.
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        let value = self.value.read().unwrap();
+        Ok(byte_val(member).is_some_and(|byte| value.contains(&byte)))
+    }
+
+    fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
+        if self.is(rhs) || rhs.is_always() {
+            true
+        } else if let Some(rhs) = rhs.down_to_byte_buffer() {
+            self.to_vec() == rhs.to_vec()
+        } else {
+            false
+        }
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for ByteBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.value.read().unwrap();
+        let items: Vec<String> = value.iter().map(|b| b.to_string()).collect();
+        write!(f, "ByteBuffer({})", items.join(", "))
+    }
+}
+
+impl fmt::Debug for ByteBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -0,0 +1,485 @@
+use std::any::Any;
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::vm::{RuntimeBoolResult, RuntimeErr};
+
+use super::file::data_to_write;
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+use super::result::Args;
+
+// Bytes Type ----------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Bytes type
+
+An immutable sequence of bytes. Indexing a Bytes object produces an
+Int in the range 0 to 255. See ByteBuffer for a mutable version.
+";
+
+gen::type_and_impls!(BytesType, Bytes);
+
+pub static BYTES_TYPE: Lazy<gen::obj_ref_t!(BytesType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(BytesType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Class Methods -----------------------------------------------
+        gen::meth!(
+            "new",
+            type_ref,
+            &["data"],
+            "Create Bytes.
+
+            # Args
+
+            - data: Str | Bytes | ByteBuffer | List | Tuple
+
+              Strs are encoded as UTF-8. Lists and Tuples must contain
+              only Ints in the range 0 to 255.
+
+            ",
+            |_, args, _| {
+                let arg = gen::use_arg!(args, 0);
+                Ok(match data_from_obj(&*arg) {
+                    Some(data) => new::bytes(data),
+                    None => new::arg_err(DATA_ARG_MSG, new::nil()),
+                })
+            }
+        ),
+        gen::meth!(
+            "from_hex",
+            type_ref,
+            &["text"],
+            "Create Bytes from a hex string like `\"00ff\"`.
+
+            # Returns
+
+            - Bytes
+            - Err: If text isn't valid hex
+
+            ",
+            |_, args, _| {
+                let arg = gen::use_arg!(args, 0);
+                let text = gen::use_arg_str!(from_hex, text, arg);
+                Ok(match from_hex(text) {
+                    Ok(data) => new::bytes(data),
+                    Err(err) => new::string_err(err, new::nil()),
+                })
+            }
+        ),
+        gen::meth!(
+            "from_base64",
+            type_ref,
+            &["text"],
+            "Create Bytes from a standard base64 string. Padding is
+            optional.
+
+            # Returns
+
+            - Bytes
+            - Err: If text isn't valid base64
+
+            ",
+            |_, args, _| {
+                let arg = gen::use_arg!(args, 0);
+                let text = gen::use_arg_str!(from_base64, text, arg);
+                Ok(match from_base64(text) {
+                    Ok(data) => new::bytes(data),
+                    Err(err) => new::string_err(err, new::nil()),
+                })
+            }
+        ),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("length", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_bytes().unwrap();
+            Ok(new::int(this.len()))
+        }),
+        gen::prop!("is_empty", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_bytes().unwrap();
+            Ok(new::bool(this.len() == 0))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!("iter", type_ref, &[], "", |this_ref, _, _| {
+            let this = this_ref.read().unwrap();
+            let this = this.down_to_bytes().unwrap();
+            Ok(new::iterator(this.value.iter().map(|b| new::int(*b)).collect()))
+        }),
+        gen::meth!(
+            "slice",
+            type_ref,
+            &["start", ""],
+            "Get the bytes from start up to, but not including, end.
+
+            # Args
+
+            - start: Int
+            - end?: Int (defaults to the length)
+
+            # Returns
+
+            - Bytes
+            - Err: If start or end is out of bounds
+
+            ",
+            |this_ref, args, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_bytes().unwrap();
+                Ok(match slice_range(&args, this.len()) {
+                    Ok(range) => new::bytes(this.value[range].to_vec()),
+                    Err(err) => err,
+                })
+            }
+        ),
+        gen::meth!(
+            "decode",
+            type_ref,
+            &["encoding"],
+            "Decode bytes to a Str.
+
+            # Args
+
+            - encoding: Str
+
+              One of \"utf-8\", \"ascii\", or \"latin-1\". Case,
+              dashes, and underscores are ignored.
+
+            # Returns
+
+            - Str
+            - Err: If the encoding isn't known or the bytes aren't valid
+              in the encoding
+
+            ",
+            |this_ref, args, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_bytes().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                let encoding = gen::use_arg_str!(decode, encoding, arg);
+                Ok(decode(&this.value, encoding, this_ref.clone()))
+            }
+        ),
+        gen::meth!(
+            "to_str",
+            type_ref,
+            &[],
+            "Decode bytes as UTF-8.
+
+            # Returns
+
+            - Str: If the bytes are valid UTF-8
+            - Err: If the bytes aren't valid UTF-8
+
+            ",
+            |this_ref, _, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_bytes().unwrap();
+                Ok(decode(&this.value, "utf-8", this_ref.clone()))
+            }
+        ),
+        gen::meth!(
+            "to_hex",
+            type_ref,
+            &[],
+            "Encode as lowercase hex.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_bytes().unwrap();
+                Ok(new::str(to_hex(&this.value)))
+            }
+        ),
+        gen::meth!(
+            "to_base64",
+            type_ref,
+            &[],
+            "Encode as standard, padded base64.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_bytes().unwrap();
+                Ok(new::str(to_base64(&this.value)))
+            }
+        ),
+        gen::meth!(
+            "to_buffer",
+            type_ref,
+            &[],
+            "Copy into a new ByteBuffer.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_bytes().unwrap();
+                Ok(new::byte_buffer(this.value.clone()))
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Bytes Object --------------------------------------------------------
+
+pub struct Bytes {
+    ns: Namespace,
+    value: Vec<u8>,
+}
+
+gen::standard_object_impls!(Bytes);
+
+impl Bytes {
+    pub fn new(value: Vec<u8>) -> Self {
+        Self { ns: Namespace::default(), value }
+    }
+
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+}
+
+impl ObjectTrait for Bytes {
+    gen::object_trait_header!(BYTES_TYPE);
+
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(self.len() > 0)
+    }
+
+    fn get_item(&self, index: usize, this: ObjectRef) -> ObjectRef {
+        if let Some(byte) = self.value.get(index) {
+            new::int(*byte)
+        } else {
+            self.index_out_of_bounds(index, this)
+        }
+    }
+
+    /// Only Ints in the range 0 to 255 can be members of Bytes.
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(byte_val(member).is_some_and(|byte| self.value.contains(&byte)))
+    }
+
+    fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
+        if self.is(rhs) || rhs.is_always() {
+            true
+        } else if let Some(rhs) = rhs.down_to_bytes() {
+            self.value == rhs.value
+        } else {
+            false
+        }
+    }
+}
+
+// Helpers -------------------------------------------------------------
+//
+// These are shared with ByteBuffer.
+
+pub static DATA_ARG_MSG: &str =
+    "Expected data to be a Str, Bytes, ByteBuffer, or a List or Tuple of bytes";
+
+/// Get an object's value if it's an Int in the range 0 to 255.
+pub fn byte_val(obj: &dyn ObjectTrait) -> Option<u8> {
+    obj.get_usize_val().and_then(|val| u8::try_from(val).ok())
+}
+
+/// Get a copy of the data in a Str, Bytes, or ByteBuffer or a List or
+/// Tuple of Ints in the range 0 to 255.
+pub fn data_from_obj(obj: &dyn ObjectTrait) -> Option<Vec<u8>> {
+    if let Some(data) = data_to_write(obj) {
+        return Some(data.into_owned());
+    }
+    let items = if let Some(list) = obj.down_to_list() {
+        list.to_vec()
+    } else if let Some(tuple) = obj.down_to_tuple() {
+        tuple.iter().cloned().collect()
+    } else {
+        return None;
+    };
+    items.iter().map(|item| byte_val(&*item.read().unwrap())).collect()
+}
+
+/// Get the range for `slice(start, end?)` from its args, where the end
+/// defaults to `len`. If either index is out of bounds or the end is
+/// before the start, an arg Err is returned.
+pub fn slice_range(args: &Args, len: usize) -> Result<Range<usize>, ObjectRef> {
+    let start = args[0].read().unwrap().get_usize_val();
+    let var_args = args[1].read().unwrap();
+    let var_args = var_args.down_to_tuple().unwrap();
+    let end = match var_args.len() {
+        0 => Some(len),
+        1 => var_args.get(0).unwrap().read().unwrap().get_usize_val(),
+        n => {
+            let msg = format!("slice() expected at most 2 args; got {}", n + 1);
+            return Err(new::arg_err(msg, new::nil()));
+        }
+    };
+    match (start, end) {
+        (Some(start), Some(end)) if start <= end && end <= len => Ok(start..end),
+        _ => {
+            let msg = format!("slice() expected 0 <= start <= end <= {len}");
+            Err(new::arg_err(msg, new::nil()))
+        }
+    }
+}
+
+/// Text encodings supported by `Str.encode()` and `decode()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Ascii,
+    Latin1,
+}
+
+impl Encoding {
+    /// Look up an encoding by name, ignoring case, dashes, and
+    /// underscores (e.g., "UTF-8" and "utf8" are equivalent).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase().replace(['-', '_'], "");
+        match name.as_str() {
+            "utf8" => Some(Self::Utf8),
+            "ascii" | "usascii" => Some(Self::Ascii),
+            "latin1" | "iso88591" => Some(Self::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Ascii => "ASCII",
+            Self::Latin1 => "Latin-1",
+        }
+    }
+
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let max = match self {
+            Self::Utf8 => return Ok(text.as_bytes().to_vec()),
+            Self::Ascii => 0x7f,
+            Self::Latin1 => 0xff,
+        };
+        text.chars()
+            .map(|c| match u8::try_from(c) {
+                Ok(byte) if byte <= max => Ok(byte),
+                _ => Err(format!("{c:?} can't be encoded as {}", self.name())),
+            })
+            .collect()
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<String, String> {
+        match self {
+            Self::Utf8 => match std::str::from_utf8(data) {
+                Ok(text) => Ok(text.to_owned()),
+                Err(err) => Err(err.to_string()),
+            },
+            Self::Ascii => match data.iter().position(|b| !b.is_ascii()) {
+                Some(pos) => Err(format!("Byte {} at {pos} isn't ASCII", data[pos])),
+                None => Ok(data.iter().map(|b| char::from(*b)).collect()),
+            },
+            Self::Latin1 => Ok(data.iter().map(|b| char::from(*b)).collect()),
+        }
+    }
+}
+
+/// Decode data with the named encoding, returning a Str or an Err.
+pub fn decode(data: &[u8], encoding: &str, this: ObjectRef) -> ObjectRef {
+    match Encoding::from_name(encoding) {
+        Some(encoding) => match encoding.decode(data) {
+            Ok(text) => new::str(text),
+            Err(err) => new::string_err(err, this),
+        },
+        None => new::arg_err(format!("Unknown encoding: {encoding}"), new::nil()),
+    }
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err("Hex string must have an even number of digits".to_owned());
+    }
+    text.as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| format!("Invalid hex digits at {}", i * 2))
+        })
+        .collect()
+}
+
+static BASE64_CHARS: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn to_base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (u32::from(*b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - 6 * i)) & 0x3f;
+                text.push(char::from(BASE64_CHARS[index as usize]));
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+pub fn from_base64(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim_end_matches('=');
+    if text.len() % 4 == 1 {
+        return Err("Invalid base64 length".to_owned());
+    }
+    let mut data = Vec::with_capacity(text.len() * 3 / 4);
+    for (chunk_index, chunk) in text.as_bytes().chunks(4).enumerate() {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let Some(val) = BASE64_CHARS.iter().position(|b| b == c) else {
+                let pos = chunk_index * 4 + i;
+                return Err(format!(
+                    "Invalid base64 char at {pos}: {:?}",
+                    char::from(*c)
+                ));
+            };
+            n |= (val as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            data.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(data)
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self.value.iter().map(|b| b.to_string()).collect();
+        write!(f, "Bytes({})", items.join(", "))
+    }
+}
+
+impl fmt::Debug for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -50,6 +50,12 @@ impl Cell {
     }
 }
 
+/// Get the value of a cell var. Other objects are returned as is.
+pub(crate) fn unwrap_cell(obj: ObjectRef) -> ObjectRef {
+    let value = obj.read().unwrap().down_to_cell().map(|cell| cell.value());
+    value.unwrap_or(obj)
+}
+
 impl ObjectTrait for Cell {
     gen::object_trait_header!(CELL_TYPE);
 
@@ -0,0 +1,183 @@
+//! Channels are created by `std.thread.channel()` and are used to send
+//! objects between threads. Any number of threads can send to and
+//! receive from the same channel. Objects are received in the order
+//! they were sent.
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+
+use once_cell::sync::Lazy;
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// Channel Type --------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Channel type
+
+Channels are created with `std.thread.channel()`. Objects sent with
+`send()` are received with `recv()` in the order they were sent.
+";
+
+gen::type_and_impls!(ChannelType, Channel);
+
+pub static CHANNEL_TYPE: Lazy<gen::obj_ref_t!(ChannelType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(ChannelType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("is_closed", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_channel().unwrap();
+            Ok(new::bool(this.is_closed()))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!(
+            "send",
+            type_ref,
+            &["obj"],
+            "Send obj to the channel.
+
+            # Returns
+
+            - nil: If obj was sent
+            - Err: If the channel is closed
+
+            ",
+            |this_ref, args, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_channel().unwrap();
+                Ok(if this.send(args[0].clone()) {
+                    new::nil()
+                } else {
+                    new::thread_err("Channel is closed", this_ref.clone())
+                })
+            }
+        ),
+        gen::meth!(
+            "recv",
+            type_ref,
+            &[],
+            "Receive the next object from the channel, waiting for one to
+            be sent if necessary.
+
+            # Returns
+
+            - Any: The next object
+            - Err: If the channel is closed and all objects sent before
+              it was closed have been received
+
+            ",
+            |this_ref, _, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_channel().unwrap();
+                Ok(this.recv().unwrap_or_else(|| {
+                    new::thread_err("Channel is closed", this_ref.clone())
+                }))
+            }
+        ),
+        gen::meth!(
+            "close",
+            type_ref,
+            &[],
+            "Close the channel. Objects can no longer be sent, but objects
+            that were already sent can still be received. Threads waiting
+            to receive are woken up if there's nothing left to receive.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_channel().unwrap();
+                this.close();
+                Ok(new::nil())
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Channel Object ------------------------------------------------------
+
+#[derive(Default)]
+struct Queue {
+    items: VecDeque<ObjectRef>,
+    closed: bool,
+}
+
+pub struct Channel {
+    ns: Namespace,
+    queue: Mutex<Queue>,
+    // Signaled when an object is sent or the channel is closed.
+    ready: Condvar,
+}
+
+gen::standard_object_impls!(Channel);
+
+impl Channel {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            ns: Namespace::default(),
+            queue: Mutex::new(Queue::default()),
+            ready: Condvar::new(),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.lock().unwrap().closed
+    }
+
+    /// Send obj. If the channel is closed, obj isn't sent and `false`
+    /// is returned.
+    pub fn send(&self, obj: ObjectRef) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.closed {
+            return false;
+        }
+        queue.items.push_back(obj);
+        self.ready.notify_one();
+        true
+    }
+
+    /// Wait for the next object. `None` is returned when the channel is
+    /// closed and there are no more objects.
+    pub fn recv(&self) -> Option<ObjectRef> {
+        let mut queue = self.queue.lock().unwrap();
+        while queue.items.is_empty() && !queue.closed {
+            queue = self.ready.wait(queue).unwrap();
+        }
+        queue.items.pop_front()
+    }
+
+    pub fn close(&self) {
+        self.queue.lock().unwrap().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+impl ObjectTrait for Channel {
+    gen::object_trait_header!(CHANNEL_TYPE);
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<channel>")
+    }
+}
+
+impl fmt::Debug for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let queue = self.queue.lock().unwrap();
+        let state = if queue.closed { ", closed" } else { "" };
+        write!(f, "<channel ({} pending{state})>", queue.items.len())
+    }
+}
@@ -0,0 +1,290 @@
+//! Conversions between Rust values and objects.
+//!
+//! `TryFrom<&dyn ObjectTrait>` is implemented for common Rust types so
+//! intrinsic functions and embedders can extract values without
+//! hand-writing downcast chains:
+//!
+//! ```ignore
+//! let count = usize::try_from(&*arg.read().unwrap())?;
+//! ```
+//!
+//! Conversions are strict with one exception: an `Int` converts to an
+//! `f64` (possibly losing precision), mirroring how `Int`s are promoted
+//! to `Float`s in mixed arithmetic.
+//!
+//! [`FromObjectRef`] works on `ObjectRef`s directly and extends these
+//! conversions to containers (`Vec<T>`, `HashMap<String, T>`, and
+//! `Option<T>`), converting their items recursively:
+//!
+//! ```ignore
+//! let names = Vec::<String>::from_obj_ref(&arg)?;
+//! ```
+//!
+//! The reverse direction is provided by [`IntoObjectRef`]. `From<T>`
+//! can't be implemented for `ObjectRef` because it's an `Arc`, which is
+//! a foreign type.
+//!
+//! Objects are also converted to and from the public [`Value`] type
+//! used for embedding.
+use std::collections::HashMap;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::value::Value;
+use crate::vm::RuntimeErr;
+
+use super::base::{ObjectRef, ObjectTrait};
+use super::new;
+
+fn conv_err(obj: &dyn ObjectTrait, expected: &str) -> RuntimeErr {
+    RuntimeErr::type_err(format!(
+        "Could not convert {} to {expected}",
+        obj.class().read().unwrap()
+    ))
+}
+
+impl TryFrom<&dyn ObjectTrait> for bool {
+    type Error = RuntimeErr;
+
+    fn try_from(obj: &dyn ObjectTrait) -> Result<Self, Self::Error> {
+        obj.get_bool_val().copied().ok_or_else(|| conv_err(obj, "bool"))
+    }
+}
+
+impl TryFrom<&dyn ObjectTrait> for BigInt {
+    type Error = RuntimeErr;
+
+    fn try_from(obj: &dyn ObjectTrait) -> Result<Self, Self::Error> {
+        obj.get_int_val().cloned().ok_or_else(|| conv_err(obj, "BigInt"))
+    }
+}
+
+impl TryFrom<&dyn ObjectTrait> for i64 {
+    type Error = RuntimeErr;
+
+    fn try_from(obj: &dyn ObjectTrait) -> Result<Self, Self::Error> {
+        obj.get_int_val().and_then(|v| v.to_i64()).ok_or_else(|| conv_err(obj, "i64"))
+    }
+}
+
+impl TryFrom<&dyn ObjectTrait> for usize {
+    type Error = RuntimeErr;
+
+    fn try_from(obj: &dyn ObjectTrait) -> Result<Self, Self::Error> {
+        obj.get_usize_val().ok_or_else(|| conv_err(obj, "usize"))
+    }
+}
+
+impl TryFrom<&dyn ObjectTrait> for f64 {
+    type Error = RuntimeErr;
+
+    fn try_from(obj: &dyn ObjectTrait) -> Result<Self, Self::Error> {
+        if let Some(val) = obj.get_float_val() {
+            Ok(*val)
+        } else if let Some(val) = obj.get_int_val().and_then(|v| v.to_f64()) {
+            Ok(val)
+        } else {
+            Err(conv_err(obj, "f64"))
+        }
+    }
+}
+
+impl TryFrom<&dyn ObjectTrait> for String {
+    type Error = RuntimeErr;
+
+    fn try_from(obj: &dyn ObjectTrait) -> Result<Self, Self::Error> {
+        obj.get_str_val().map(|v| v.to_owned()).ok_or_else(|| conv_err(obj, "String"))
+    }
+}
+
+impl TryFrom<&dyn ObjectTrait> for Value {
+    type Error = RuntimeErr;
+
+    fn try_from(obj: &dyn ObjectTrait) -> Result<Self, Self::Error> {
+        let items = if let Some(list) = obj.down_to_list() {
+            list.to_vec()
+        } else if let Some(tuple) = obj.down_to_tuple() {
+            tuple.iter().cloned().collect()
+        } else if obj.is_nil() {
+            return Ok(Value::Nil);
+        } else if obj.is_bool() {
+            return bool::try_from(obj).map(Value::Bool);
+        } else if obj.is_int() {
+            return i64::try_from(obj).map(Value::Int);
+        } else if obj.is_float() {
+            return f64::try_from(obj).map(Value::Float);
+        } else if obj.is_str() {
+            return String::try_from(obj).map(Value::Str);
+        } else {
+            return Err(conv_err(obj, "Value"));
+        };
+        let mut values = vec![];
+        for item in items {
+            let item = item.read().unwrap();
+            values.push(Value::try_from(&*item)?);
+        }
+        Ok(Value::List(values))
+    }
+}
+
+/// Convert an object into a Rust value.
+pub trait FromObjectRef: Sized {
+    fn from_obj_ref(obj: &ObjectRef) -> Result<Self, RuntimeErr>;
+}
+
+macro_rules! impl_from_obj_ref {
+    ( $($t:ty),+ ) => {
+        $(
+            impl FromObjectRef for $t {
+                fn from_obj_ref(obj: &ObjectRef) -> Result<Self, RuntimeErr> {
+                    <$t>::try_from(&*obj.read().unwrap())
+                }
+            }
+        )+
+    };
+}
+
+impl_from_obj_ref!(bool, BigInt, i64, usize, f64, String, Value);
+
+impl FromObjectRef for ObjectRef {
+    fn from_obj_ref(obj: &ObjectRef) -> Result<Self, RuntimeErr> {
+        Ok(obj.clone())
+    }
+}
+
+/// `nil` is converted to `None`.
+impl<T: FromObjectRef> FromObjectRef for Option<T> {
+    fn from_obj_ref(obj: &ObjectRef) -> Result<Self, RuntimeErr> {
+        if obj.read().unwrap().is_nil() {
+            Ok(None)
+        } else {
+            T::from_obj_ref(obj).map(Some)
+        }
+    }
+}
+
+/// Lists and tuples are converted to `Vec`s.
+impl<T: FromObjectRef> FromObjectRef for Vec<T> {
+    fn from_obj_ref(obj: &ObjectRef) -> Result<Self, RuntimeErr> {
+        let items = {
+            let obj = obj.read().unwrap();
+            if let Some(list) = obj.down_to_list() {
+                list.to_vec()
+            } else if let Some(tuple) = obj.down_to_tuple() {
+                tuple.iter().cloned().collect()
+            } else {
+                return Err(conv_err(&*obj, "Vec"));
+            }
+        };
+        items.iter().map(T::from_obj_ref).collect()
+    }
+}
+
+impl<T: FromObjectRef> FromObjectRef for HashMap<String, T> {
+    fn from_obj_ref(obj: &ObjectRef) -> Result<Self, RuntimeErr> {
+        let entries = {
+            let obj = obj.read().unwrap();
+            if let Some(map) = obj.down_to_map() {
+                map.entries().read().unwrap().clone()
+            } else {
+                return Err(conv_err(&*obj, "HashMap"));
+            }
+        };
+        entries
+            .iter()
+            .map(|(key, val)| Ok((key.clone(), T::from_obj_ref(val)?)))
+            .collect()
+    }
+}
+
+/// Convert a Rust value into an object.
+pub trait IntoObjectRef {
+    fn into_obj_ref(self) -> ObjectRef;
+}
+
+impl IntoObjectRef for ObjectRef {
+    fn into_obj_ref(self) -> ObjectRef {
+        self
+    }
+}
+
+impl IntoObjectRef for () {
+    fn into_obj_ref(self) -> ObjectRef {
+        new::nil()
+    }
+}
+
+impl IntoObjectRef for bool {
+    fn into_obj_ref(self) -> ObjectRef {
+        new::bool(self)
+    }
+}
+
+macro_rules! impl_into_int {
+    ( $($t:ty),+ ) => {
+        $(
+            impl IntoObjectRef for $t {
+                fn into_obj_ref(self) -> ObjectRef {
+                    new::int(self)
+                }
+            }
+        )+
+    };
+}
+
+impl_into_int!(BigInt, i32, i64, u32, u64, usize);
+
+impl IntoObjectRef for f64 {
+    fn into_obj_ref(self) -> ObjectRef {
+        new::float(self)
+    }
+}
+
+impl IntoObjectRef for String {
+    fn into_obj_ref(self) -> ObjectRef {
+        new::str(self)
+    }
+}
+
+impl IntoObjectRef for &str {
+    fn into_obj_ref(self) -> ObjectRef {
+        new::str(self)
+    }
+}
+
+impl IntoObjectRef for Value {
+    fn into_obj_ref(self) -> ObjectRef {
+        match self {
+            Value::Nil => new::nil(),
+            Value::Bool(value) => new::bool(value),
+            Value::Int(value) => new::int(value),
+            Value::Float(value) => new::float(value),
+            Value::Str(value) => new::str(value),
+            Value::List(items) => items.into_obj_ref(),
+        }
+    }
+}
+
+/// `Vec`s are converted to lists.
+impl<T: IntoObjectRef> IntoObjectRef for Vec<T> {
+    fn into_obj_ref(self) -> ObjectRef {
+        new::list(self.into_iter().map(IntoObjectRef::into_obj_ref).collect())
+    }
+}
+
+impl<T: IntoObjectRef> IntoObjectRef for HashMap<String, T> {
+    fn into_obj_ref(self) -> ObjectRef {
+        new::map(self.into_iter().map(|(key, val)| (key, val.into_obj_ref())).collect())
+    }
+}
+
+/// `None` is converted to `nil`.
+impl<T: IntoObjectRef> IntoObjectRef for Option<T> {
+    fn into_obj_ref(self) -> ObjectRef {
+        match self {
+            Some(val) => val.into_obj_ref(),
+            None => new::nil(),
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use std::any::Any;
 use std::fmt;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, Weak};
 
 use super::gen;
 use super::new;
+use super::result::Params;
 
 use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
 use super::class::TYPE_TYPE;
@@ -11,32 +12,53 @@ use super::ns::Namespace;
 
 // Custom Type ---------------------------------------------------------
 
-/// TODO: This shouldn't need to be cloneable
-#[derive(Clone)]
 pub struct CustomType {
     ns: Namespace,
     module: ObjectRef,
     name: String,
     full_name: String,
+    fields: Params,
+    // Weak reference to the type's own object ref so instances created
+    // via the type can refer to it (rather than to a copy of it).
+    this: Weak<RwLock<CustomType>>,
 }
 
 impl CustomType {
-    pub fn new(module_ref: ObjectRef, name: String) -> Self {
+    pub fn new(
+        module_ref: ObjectRef,
+        name: String,
+        fields: Params,
+    ) -> gen::obj_ref_t!(Self) {
         let module = module_ref.read().unwrap();
         let module = module.down_to_mod().unwrap();
         let full_name = format!("{}.{name}", module.name());
-        let type_ref = Self {
-            ns: Namespace::with_entries(&[
-                // Class Attributes
-                ("$module_name", new::str(module.name())),
-                ("$full_name", new::str(&full_name)),
-                ("$name", new::str(&name)),
-            ]),
-            module: module_ref.clone(),
-            name,
-            full_name,
-        };
-        type_ref
+        let field_names = fields.iter().map(new::str).collect();
+        Arc::new_cyclic(|this| {
+            RwLock::new(Self {
+                ns: Namespace::with_entries(&[
+                    // Class Attributes
+                    ("$module_name", new::str(module.name())),
+                    ("$full_name", new::str(&full_name)),
+                    ("$name", new::str(&name)),
+                    ("$fields", new::tuple(field_names)),
+                ]),
+                module: module_ref.clone(),
+                name,
+                full_name,
+                fields,
+                this: this.clone(),
+            })
+        })
+    }
+
+    /// Names of the fields declared for the type. Types created via
+    /// `new_type()` don't have any declared fields.
+    pub fn fields(&self) -> &Params {
+        &self.fields
+    }
+
+    pub fn type_ref(&self) -> gen::obj_ref_t!(Self) {
+        self.this.upgrade().expect("Custom type dropped")
     }
 }
 
@@ -108,6 +130,11 @@ impl CustomObj {
     pub fn new(type_obj: gen::obj_ref_t!(CustomType), attrs: Namespace) -> Self {
         Self { type_obj, ns: attrs }
     }
+
+    /// Make an object of the same type with different attributes.
+    pub fn with_attrs(&self, attrs: Namespace) -> Self {
+        Self::new(self.type_obj.clone(), attrs)
+    }
 }
 
 impl ObjectTrait for CustomObj {
@@ -150,7 +177,14 @@ impl ObjectTrait for CustomObj {
     }
 
     fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
-        self.is(rhs) || rhs.is_always() || self.ns.is_equal(rhs.ns())
+        if self.is(rhs) || rhs.is_always() {
+            return true;
+        }
+        let t = self.type_obj();
+        let t = t.read().unwrap();
+        let u = rhs.type_obj();
+        let u = u.read().unwrap();
+        t.is(&*u) && self.ns.is_equal(rhs.ns())
     }
 }
 
@@ -0,0 +1,445 @@
+//! Date/times with a fixed UTC offset. DateTimes created by `new()`,
+//! `from_timestamp()`, and `std.time.now()` are in UTC; `to_local()`
+//! converts to the local offset. Durations are represented as seconds
+//! (Int or Float), so adding seconds to or subtracting seconds from a
+//! DateTime returns a new DateTime, and subtracting one DateTime from
+//! another returns the difference in seconds as a Float.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{
+    Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone,
+    Timelike, Utc,
+};
+use num_traits::ToPrimitive;
+use once_cell::sync::Lazy;
+
+use crate::vm::{RuntimeBoolResult, RuntimeErr, RuntimeObjResult};
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// DateTime Type -------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic DateTime type
+
+A DateTime is an instant in time with a fixed UTC offset. Use
+`std.time.now()` to get the current time and `std.time.parse()` to
+parse a DateTime from a Str.
+
+Seconds (Int or Float) can be added to or subtracted from a DateTime.
+Subtracting one DateTime from another gives the difference in seconds.
+DateTimes are compared by instant, regardless of offset.
+";
+
+gen::type_and_impls!(DateTimeType, DateTime);
+
+pub static DATE_TIME_TYPE: Lazy<gen::obj_ref_t!(DateTimeType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(DateTimeType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Class Methods -----------------------------------------------
+        gen::meth!(
+            "new",
+            type_ref,
+            &["year", "month", "day", ""],
+            "Create a UTC DateTime.
+
+            # Args
+
+            - year: Int
+            - month: Int
+            - day: Int
+            - hour?: Int
+            - minute?: Int
+            - second?: Int | Float
+
+            # Returns
+
+            - DateTime
+            - Err: If the date or time is invalid
+
+            ",
+            |_, args, _| {
+                let var_args = args[3].read().unwrap();
+                let var_args = var_args.down_to_tuple().unwrap();
+                if var_args.len() > 3 {
+                    let msg = format!(
+                        "new() expected at most 6 args; got {}",
+                        var_args.len() + 3
+                    );
+                    return Ok(new::arg_err(msg, new::nil()));
+                }
+                let mut parts = vec![];
+                for (name, arg) in ["year", "month", "day"].iter().zip(args.iter()) {
+                    match int_arg(name, &*arg.read().unwrap()) {
+                        Ok(val) => parts.push(val),
+                        Err(err) => return Ok(err),
+                    }
+                }
+                for (name, arg) in ["hour", "minute"].iter().zip(var_args.iter()) {
+                    match int_arg(name, &*arg.read().unwrap()) {
+                        Ok(val) => parts.push(val),
+                        Err(err) => return Ok(err),
+                    }
+                }
+                parts.resize(5, 0);
+                let (second, nanosecond) = match var_args.get(2) {
+                    Some(arg) => {
+                        let arg = arg.read().unwrap();
+                        if let Some(val) = arg.get_float_val() {
+                            if !(0.0..60.0).contains(val) {
+                                let msg = format!("Invalid second: {val}");
+                                return Ok(new::time_err(msg, new::nil()));
+                            }
+                            (val.trunc() as i64, (val.fract() * 1e9).round() as u32)
+                        } else {
+                            match int_arg("second", &*arg) {
+                                Ok(val) => (val, 0),
+                                Err(err) => return Ok(err),
+                            }
+                        }
+                    }
+                    None => (0, 0),
+                };
+                let date_time = parts[0]
+                    .to_i32()
+                    .zip(parts[1].to_u32())
+                    .zip(parts[2].to_u32())
+                    .and_then(|((y, m), d)| NaiveDate::from_ymd_opt(y, m, d))
+                    .and_then(|date| {
+                        date.and_hms_nano_opt(
+                            parts[3].to_u32()?,
+                            parts[4].to_u32()?,
+                            second.to_u32()?,
+                            nanosecond.min(999_999_999),
+                        )
+                    });
+                Ok(match date_time {
+                    Some(date_time) => new::date_time(from_utc(date_time)),
+                    None => {
+                        let msg = format!(
+                            "Invalid date/time: {}-{}-{} {}:{}:{}",
+                            parts[0], parts[1], parts[2], parts[3], parts[4], second
+                        );
+                        new::time_err(msg, new::nil())
+                    }
+                })
+            }
+        ),
+        gen::meth!(
+            "from_timestamp",
+            type_ref,
+            &["seconds"],
+            "Create a UTC DateTime from seconds since the Unix epoch.
+
+            # Args
+
+            - seconds: Int | Float
+
+            # Returns
+
+            - DateTime
+            - Err: If seconds is out of range
+
+            ",
+            |_, args, _| {
+                let arg = gen::use_arg!(args, 0);
+                let Some(duration) = seconds_to_duration(&*arg) else {
+                    let msg = "from_timestamp() expected seconds to be an Int or Float";
+                    return Ok(new::arg_err(msg, new::nil()));
+                };
+                let epoch = Utc.timestamp_opt(0, 0).unwrap();
+                Ok(match epoch.checked_add_signed(duration) {
+                    Some(date_time) => new::date_time(to_fixed(date_time)),
+                    None => new::time_err(
+                        format!("Timestamp out of range: {arg}"),
+                        new::nil(),
+                    ),
+                })
+            }
+        ),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("year", type_ref, "", |this, _, _| {
+            Ok(new::int(value(&this).year()))
+        }),
+        gen::prop!("month", type_ref, "1 to 12", |this, _, _| {
+            Ok(new::int(value(&this).month()))
+        }),
+        gen::prop!("day", type_ref, "1 to 31", |this, _, _| {
+            Ok(new::int(value(&this).day()))
+        }),
+        gen::prop!("hour", type_ref, "0 to 23", |this, _, _| {
+            Ok(new::int(value(&this).hour()))
+        }),
+        gen::prop!("minute", type_ref, "0 to 59", |this, _, _| {
+            Ok(new::int(value(&this).minute()))
+        }),
+        gen::prop!("second", type_ref, "0 to 59", |this, _, _| {
+            Ok(new::int(value(&this).second()))
+        }),
+        gen::prop!("nanosecond", type_ref, "", |this, _, _| {
+            Ok(new::int(value(&this).nanosecond()))
+        }),
+        gen::prop!("weekday", type_ref, "1 (Monday) to 7 (Sunday)", |this, _, _| {
+            Ok(new::int(value(&this).weekday().number_from_monday()))
+        }),
+        gen::prop!("day_of_year", type_ref, "1 to 366", |this, _, _| {
+            Ok(new::int(value(&this).ordinal()))
+        }),
+        gen::prop!(
+            "offset",
+            type_ref,
+            "UTC offset in seconds (positive east of UTC)",
+            |this, _, _| { Ok(new::int(value(&this).offset().local_minus_utc())) }
+        ),
+        gen::prop!(
+            "timestamp",
+            type_ref,
+            "Seconds since the Unix epoch as a Float",
+            |this, _, _| {
+                let value = value(&this);
+                let seconds = value.timestamp() as f64
+                    + value.timestamp_subsec_nanos() as f64 / 1e9;
+                Ok(new::float(seconds))
+            }
+        ),
+        // Instance Methods --------------------------------------------
+        gen::meth!(
+            "format",
+            type_ref,
+            &["pattern"],
+            "Format using a strftime-like pattern (e.g., `%Y-%m-%d`).
+
+            # Args
+
+            - pattern: Str
+
+            # Returns
+
+            - Str
+            - Err: If pattern is invalid
+
+            ",
+            |this, args, _| {
+                let arg = gen::use_arg!(args, 0);
+                let pattern = gen::use_arg_str!(format, pattern, arg);
+                let items: Vec<Item> = StrftimeItems::new(pattern).collect();
+                if items.iter().any(|item| matches!(item, Item::Error)) {
+                    let msg = format!("format() got invalid pattern: {pattern:?}");
+                    return Ok(new::arg_err(msg, new::nil()));
+                }
+                let value = value(&this);
+                Ok(new::str(value.format_with_items(items.into_iter()).to_string()))
+            }
+        ),
+        gen::meth!("to_utc", type_ref, &[], "Convert to UTC.", |this, _, _| {
+            Ok(new::date_time(to_fixed(value(&this).with_timezone(&Utc))))
+        }),
+        gen::meth!(
+            "to_local",
+            type_ref,
+            &[],
+            "Convert to the local UTC offset.",
+            |this, _, _| {
+                Ok(new::date_time(to_fixed(value(&this).with_timezone(&Local))))
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+/// Get the value of a DateTime `this`.
+fn value(this: &ObjectRef) -> chrono::DateTime<FixedOffset> {
+    let this = this.read().unwrap();
+    *this.down_to_date_time().unwrap().value()
+}
+
+/// Get an Int arg to `DateTime.new()` or return an arg Err.
+fn int_arg(name: &str, arg: &dyn ObjectTrait) -> Result<i64, ObjectRef> {
+    match arg.get_int_val().and_then(|val| val.to_i64()) {
+        Some(val) => Ok(val),
+        None => {
+            let msg = format!("new() expected {name} to be an Int");
+            Err(new::arg_err(msg, new::nil()))
+        }
+    }
+}
+
+fn to_fixed<Tz: TimeZone>(
+    date_time: chrono::DateTime<Tz>,
+) -> chrono::DateTime<FixedOffset> {
+    let offset = date_time.offset().fix();
+    date_time.with_timezone(&offset)
+}
+
+fn from_utc(date_time: NaiveDateTime) -> chrono::DateTime<FixedOffset> {
+    to_fixed(Utc.from_utc_datetime(&date_time))
+}
+
+#[cfg(feature = "std-full")]
+pub fn from_system_time(time: std::time::SystemTime) -> chrono::DateTime<FixedOffset> {
+    to_fixed(chrono::DateTime::<Utc>::from(time))
+}
+
+/// Parse a DateTime. If no pattern is given, the text must be in RFC
+/// 3339 format (e.g., `2000-01-01T00:00:00Z`). Otherwise, the pattern
+/// uses the same syntax as `DateTime.format()`. If the pattern doesn't
+/// include an offset, the DateTime is assumed to be in UTC, and if it
+/// doesn't include a time, the time is midnight.
+#[cfg(feature = "std-full")]
+pub fn parse(
+    text: &str,
+    pattern: Option<&str>,
+) -> Result<chrono::DateTime<FixedOffset>, String> {
+    let Some(pattern) = pattern else {
+        return chrono::DateTime::parse_from_rfc3339(text)
+            .map_err(|err| err.to_string());
+    };
+    chrono::DateTime::parse_from_str(text, pattern).or_else(|err| {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(text, pattern) {
+            Ok(from_utc(date_time))
+        } else if let Ok(date) = NaiveDate::parse_from_str(text, pattern) {
+            Ok(from_utc(date.and_hms_opt(0, 0, 0).unwrap()))
+        } else {
+            Err(err.to_string())
+        }
+    })
+}
+
+/// Convert an Int or Float number of seconds to a Duration. Floats
+/// are rounded to the nearest nanosecond.
+fn seconds_to_duration(seconds: &dyn ObjectTrait) -> Option<Duration> {
+    if let Some(seconds) = seconds.get_int_val() {
+        seconds.to_i64()?.checked_mul(1000).map(Duration::milliseconds)
+    } else if let Some(seconds) = seconds.get_float_val() {
+        let nanos = (seconds * 1e9).round();
+        let millis = (seconds * 1e3).round();
+        if nanos.abs() < i64::MAX as f64 {
+            Some(Duration::nanoseconds(nanos as i64))
+        } else if millis.abs() < i64::MAX as f64 {
+            Some(Duration::milliseconds(millis as i64))
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+fn duration_to_seconds(duration: Duration) -> f64 {
+    match duration.num_nanoseconds() {
+        Some(nanos) => nanos as f64 / 1e9,
+        None => duration.num_milliseconds() as f64 / 1e3,
+    }
+}
+
+// DateTime Object -----------------------------------------------------
+
+pub struct DateTime {
+    ns: Namespace,
+    value: chrono::DateTime<FixedOffset>,
+}
+
+gen::standard_object_impls!(DateTime);
+
+impl DateTime {
+    pub fn new(value: chrono::DateTime<FixedOffset>) -> Self {
+        Self { ns: Namespace::default(), value }
+    }
+
+    pub fn value(&self) -> &chrono::DateTime<FixedOffset> {
+        &self.value
+    }
+
+    fn compare_err(&self, rhs: &dyn ObjectTrait, op: &str) -> RuntimeErr {
+        RuntimeErr::type_err(format!(
+            "Could not compare {} to {}: {op}",
+            self.class().read().unwrap(),
+            rhs.class().read().unwrap()
+        ))
+    }
+}
+
+impl ObjectTrait for DateTime {
+    gen::object_trait_header!(DATE_TIME_TYPE);
+
+    fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
+        if self.is(rhs) || rhs.is_always() {
+            true
+        } else if let Some(rhs) = rhs.down_to_date_time() {
+            self.value == rhs.value
+        } else {
+            false
+        }
+    }
+
+    fn less_than(&self, rhs: &dyn ObjectTrait) -> RuntimeBoolResult {
+        if let Some(rhs) = rhs.down_to_date_time() {
+            Ok(self.value < rhs.value)
+        } else {
+            Err(self.compare_err(rhs, "<"))
+        }
+    }
+
+    fn greater_than(&self, rhs: &dyn ObjectTrait) -> RuntimeBoolResult {
+        if let Some(rhs) = rhs.down_to_date_time() {
+            Ok(self.value > rhs.value)
+        } else {
+            Err(self.compare_err(rhs, ">"))
+        }
+    }
+
+    fn add(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
+        let Some(duration) = seconds_to_duration(rhs) else {
+            return Err(RuntimeErr::type_err(format!(
+                "Could not add {} to DateTime",
+                rhs.class().read().unwrap()
+            )));
+        };
+        Ok(match self.value.checked_add_signed(duration) {
+            Some(value) => new::date_time(value),
+            None => new::time_err("DateTime out of range", new::nil()),
+        })
+    }
+
+    fn sub(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
+        if let Some(rhs) = rhs.down_to_date_time() {
+            return Ok(new::float(duration_to_seconds(self.value - rhs.value)));
+        }
+        let Some(duration) = seconds_to_duration(rhs) else {
+            return Err(RuntimeErr::type_err(format!(
+                "Could not subtract {} from DateTime",
+                rhs.class().read().unwrap()
+            )));
+        };
+        Ok(match self.value.checked_sub_signed(duration) {
+            Some(value) => new::date_time(value),
+            None => new::time_err("DateTime out of range", new::nil()),
+        })
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value.to_rfc3339())
+    }
+}
+
+impl fmt::Debug for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
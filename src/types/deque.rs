@@ -0,0 +1,291 @@
+//! Double-ended queues. Unlike a List, items can be pushed onto and
+//! popped from both ends of a Deque in constant time.
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::types::IntoObjectRef;
+use crate::vm::RuntimeBoolResult;
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+use super::seq;
+
+// Deque Type ----------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Deque type
+
+A double-ended queue. Items can be pushed onto and popped from either
+end in constant time.
+
+```
+→ from std.collections import Deque
+→ d = Deque.new([1, 2])
+→ d.push_front(0)
+→ d.pop_back()
+2
+→ d.to_list()
+[0, 1]
+```
+";
+
+gen::type_and_impls!(DequeType, Deque);
+
+pub static DEQUE_TYPE: Lazy<gen::obj_ref_t!(DequeType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(DequeType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Class Methods -----------------------------------------------
+        gen::meth!(
+            "new",
+            type_ref,
+            &[""],
+            "Create a Deque, optionally from the items in a List, Tuple,
+            or Range.
+
+            # Args
+
+            - items?: List | Tuple | Range
+
+            ",
+            |_, args, _| {
+                let var_args = args[0].read().unwrap();
+                let var_args = var_args.down_to_tuple().unwrap();
+                let items = match var_args.len() {
+                    0 => vec![],
+                    1 => {
+                        let arg = var_args.get(0).unwrap();
+                        let arg = arg.read().unwrap();
+                        if let Some(list) = arg.down_to_list() {
+                            list.to_vec()
+                        } else if let Some(tuple) = arg.down_to_tuple() {
+                            tuple.iter().cloned().collect()
+                        } else if let Some(range) = arg.down_to_range() {
+                            range.to_vec()
+                        } else {
+                            let msg = format!(
+                                "Deque.new() expected items to be a List, Tuple, \
+                                or Range; got {}",
+                                arg.class().read().unwrap()
+                            );
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                    }
+                    n => {
+                        let msg =
+                            format!("Deque.new() expected at most 1 arg; got {n}");
+                        return Ok(new::arg_err(msg, new::nil()));
+                    }
+                };
+                Ok(new::deque(items))
+            }
+        ),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("length", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_deque().unwrap();
+            Ok(new::int(this.len()))
+        }),
+        gen::prop!("is_empty", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_deque().unwrap();
+            Ok(new::bool(this.len() == 0))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!(
+            "push_back",
+            type_ref,
+            &["item"],
+            "Push item onto the back and return it.",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                this.push_back(args[0].clone());
+                Ok(args[0].clone())
+            }
+        ),
+        gen::meth!(
+            "push_front",
+            type_ref,
+            &["item"],
+            "Push item onto the front and return it.",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                this.items.write().unwrap().push_front(args[0].clone());
+                Ok(args[0].clone())
+            }
+        ),
+        gen::meth!(
+            "pop_back",
+            type_ref,
+            &[],
+            "Remove and return the back item or nil if empty.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                let item = this.items.write().unwrap().pop_back();
+                Ok(item.into_obj_ref())
+            }
+        ),
+        gen::meth!(
+            "pop_front",
+            type_ref,
+            &[],
+            "Remove and return the front item or nil if empty.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                let item = this.items.write().unwrap().pop_front();
+                Ok(item.into_obj_ref())
+            }
+        ),
+        gen::meth!(
+            "peek_back",
+            type_ref,
+            &[],
+            "Get the back item without removing it or nil if empty.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                let item = this.items.read().unwrap().back().cloned();
+                Ok(item.into_obj_ref())
+            }
+        ),
+        gen::meth!(
+            "peek_front",
+            type_ref,
+            &[],
+            "Get the front item without removing it or nil if empty.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                let item = this.items.read().unwrap().front().cloned();
+                Ok(item.into_obj_ref())
+            }
+        ),
+        gen::meth!("clear", type_ref, &[], "Remove all items.", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_deque().unwrap();
+            this.items.write().unwrap().clear();
+            Ok(new::nil())
+        }),
+        gen::meth!(
+            "each",
+            type_ref,
+            &["each_fn"],
+            "Apply function to each item, front to back.
+
+            # Args
+
+            - func: Func
+
+              A function that will be passed each item in turn and, optionally, the
+              index of the item.
+
+            ",
+            |this_obj, args, vm| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                seq::each(&this_obj, &this.to_vec(), &args, vm)
+            }
+        ),
+        gen::meth!("iter", type_ref, &[], "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_deque().unwrap();
+            Ok(new::iterator(this.to_vec()))
+        }),
+        gen::meth!(
+            "to_list",
+            type_ref,
+            &[],
+            "Get the items as a List, front to back.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_deque().unwrap();
+                Ok(new::list(this.to_vec()))
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Deque Object --------------------------------------------------------
+
+pub struct Deque {
+    ns: Namespace,
+    items: RwLock<VecDeque<ObjectRef>>,
+}
+
+gen::standard_object_impls!(Deque);
+
+impl Deque {
+    pub fn new(items: Vec<ObjectRef>) -> Self {
+        Self { ns: Namespace::default(), items: RwLock::new(items.into()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.read().unwrap().len()
+    }
+
+    pub fn push_back(&self, item: ObjectRef) {
+        self.items.write().unwrap().push_back(item);
+    }
+
+    /// Get a snapshot of the deque's current items, front to back.
+    pub fn to_vec(&self) -> Vec<ObjectRef> {
+        self.items.read().unwrap().iter().cloned().collect()
+    }
+}
+
+impl ObjectTrait for Deque {
+    gen::object_trait_header!(DEQUE_TYPE);
+
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(self.len() > 0)
+    }
+
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        let items = self.items.read().unwrap();
+        Ok(items.iter().any(|item| member.is_equal(&*item.read().unwrap())))
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Deque {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let this_id = self.id();
+        let items = self.items.read().unwrap();
+        let items: Vec<String> = items
+            .iter()
+            .map(|item| {
+                let item = item.read().unwrap();
+                if item.id() == this_id {
+                    "<deque ...>".to_owned()
+                } else {
+                    format!("{:?}", &*item)
+                }
+            })
+            .collect();
+        let items_str = items.join(", ");
+        write!(f, "<deque [{items_str}]>")
+    }
+}
+
+impl fmt::Debug for Deque {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -0,0 +1,224 @@
+//! Enum Types
+//!
+//! Enums are declared in code like `Color = enum (Red, Green, Blue)`
+//! or created at runtime via `new_enum()`. Each variant is a singleton,
+//! so variants are compared by identity.
+//!
+//! NOTE: An enum type refers to its variants via its namespace and each
+//!       variant refers back to its type, so enum types are never
+//!       freed. This is fine since they're typically defined at the
+//!       module level.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::vm::{RuntimeBoolResult, RuntimeErr};
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// Enum Type -----------------------------------------------------------
+
+pub struct EnumType {
+    ns: Namespace,
+    module: ObjectRef,
+    name: String,
+    full_name: String,
+    variants: Vec<ObjectRef>,
+}
+
+impl EnumType {
+    pub fn new(module_ref: ObjectRef, name: String) -> Self {
+        let module = module_ref.read().unwrap();
+        let module = module.down_to_mod().unwrap();
+        let full_name = format!("{}.{name}", module.name());
+        Self {
+            ns: Namespace::with_entries(&[
+                // Class Attributes
+                ("$module_name", new::str(module.name())),
+                ("$full_name", new::str(&full_name)),
+                ("$name", new::str(&name)),
+            ]),
+            module: module_ref.clone(),
+            name,
+            full_name,
+            variants: vec![],
+        }
+    }
+
+    /// Variants in declaration order.
+    pub fn variants(&self) -> &[ObjectRef] {
+        &self.variants
+    }
+
+    /// Add variant, making it available as a class attribute too.
+    pub fn add_variant(&mut self, variant: ObjectRef) {
+        let name =
+            variant.read().unwrap().down_to_enum_variant().unwrap().name().to_owned();
+        self.ns.insert(name, variant.clone());
+        self.variants.push(variant);
+    }
+}
+
+gen::standard_object_impls!(EnumType);
+
+impl TypeTrait for EnumType {
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn full_name(&self) -> &str {
+        self.full_name.as_str()
+    }
+
+    fn ns(&self) -> &Namespace {
+        &self.ns
+    }
+
+    fn module(&self) -> ObjectRef {
+        self.module.clone()
+    }
+}
+
+/// NOTE: This is customized so the module is correct.
+impl ObjectTrait for EnumType {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn class(&self) -> TypeRef {
+        TYPE_TYPE.clone()
+    }
+
+    fn type_obj(&self) -> ObjectRef {
+        TYPE_TYPE.clone()
+    }
+
+    fn ns(&self) -> &Namespace {
+        &self.ns
+    }
+
+    fn ns_mut(&mut self) -> &mut Namespace {
+        &mut self.ns
+    }
+
+    fn as_type(&self) -> Option<&dyn TypeTrait> {
+        Some(self)
+    }
+
+    fn module(&self) -> ObjectRef {
+        self.module.clone()
+    }
+}
+
+// Enum Variant --------------------------------------------------------
+
+pub struct EnumVariant {
+    type_obj: gen::obj_ref_t!(EnumType),
+    ns: Namespace,
+    name: String,
+    ordinal: usize,
+}
+
+gen::standard_object_impls!(EnumVariant);
+
+impl EnumVariant {
+    pub fn new(
+        type_obj: gen::obj_ref_t!(EnumType),
+        name: String,
+        ordinal: usize,
+    ) -> Self {
+        let ns = Namespace::with_entries(&[
+            ("name", new::str(&name)),
+            ("ordinal", new::int(ordinal)),
+        ]);
+        Self { type_obj, ns, name, ordinal }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Get the ordinal of `rhs` if it's a variant of the same enum.
+    fn rhs_ordinal(
+        &self,
+        rhs: &dyn ObjectTrait,
+        op: &str,
+    ) -> Result<usize, RuntimeErr> {
+        if let Some(rhs) = rhs.down_to_enum_variant() {
+            if Arc::ptr_eq(&self.type_obj, &rhs.type_obj) {
+                return Ok(rhs.ordinal);
+            }
+        }
+        Err(RuntimeErr::type_err(format!(
+            "Cannot compare {self} {op} {}: variants must be from the same enum",
+            rhs.class().read().unwrap()
+        )))
+    }
+}
+
+impl ObjectTrait for EnumVariant {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn class(&self) -> TypeRef {
+        self.type_obj.clone()
+    }
+
+    fn type_obj(&self) -> ObjectRef {
+        self.type_obj.clone()
+    }
+
+    fn ns(&self) -> &Namespace {
+        &self.ns
+    }
+
+    fn ns_mut(&mut self) -> &mut Namespace {
+        &mut self.ns
+    }
+
+    fn as_type(&self) -> Option<&dyn TypeTrait> {
+        None
+    }
+
+    /// Variants are singletons, so they're only equal to themselves.
+    fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
+        self.is(rhs) || rhs.is_always()
+    }
+
+    fn less_than(&self, rhs: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(self.ordinal < self.rhs_ordinal(rhs, "<")?)
+    }
+
+    fn greater_than(&self, rhs: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(self.ordinal > self.rhs_ordinal(rhs, ">")?)
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for EnumVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let type_obj = self.type_obj.read().unwrap();
+        write!(f, "{}.{}", TypeTrait::name(&*type_obj), self.name)
+    }
+}
+
+impl fmt::Debug for EnumVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -13,6 +13,12 @@
 //!
 //! if !assert(false) ->
 //!     print("false is not true")
+//!
+//! Errs can also carry structured data. When the data is a `Map`, its
+//! entries are accessible as attributes of the `Err`:
+//!
+//! result = err(ErrType.arg, $"bad value: {x}", {"value": x})
+//! result.err.value  # -> x
 use std::any::Any;
 use std::fmt;
 use std::sync::{Arc, RwLock};
@@ -29,6 +35,7 @@ use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
 use super::class::TYPE_TYPE;
 use super::err_type::ErrKind;
 use super::ns::Namespace;
+use super::result::Args;
 
 // Err Type ------------------------------------------------------------
 
@@ -40,44 +47,26 @@ pub static ERR_TYPE: Lazy<gen::obj_ref_t!(ErrType)> = Lazy::new(|| {
 
     type_obj.add_attrs(&[
         // Class Methods -----------------------------------------------
-        gen::meth!("new", type_ref, &["type", "msg"], "", |_, args, _| {
-            let name = "Err.new()";
-
-            let result = check_args(name, &args, false, 2, Some(2));
-            if let Err(err) = result {
-                return Ok(err);
-            }
-
-            let type_arg = gen::use_arg!(args, 0);
-            let msg_arg = gen::use_arg!(args, 1);
-
-            let err_type = if let Some(err_type) = type_arg.down_to_err_type_obj() {
-                err_type
-            } else {
-                let arg_err_msg = format!("{name} expected type to be an ErrType");
-                // NOTE: This is problematic because user code won't be
-                //       able to tell if the arg error was the result of
-                //       creating an arg err explicitly or the result of
-                //       an internal error. Note that this applies to
-                //       *any* user-constructible error.
-                //
-                // TODO: Figure out a solution for this, perhaps an err
-                //       type that is *not* user-constructible or a
-                //       nested err type?
-                return Ok(new::arg_err(arg_err_msg, new::nil()));
-            };
-
-            let kind = err_type.kind().clone();
-
-            let msg = if let Some(msg) = msg_arg.get_str_val() {
-                msg
-            } else {
-                let arg_err_msg = format!("{name} expected message to be a Str");
-                return Ok(new::arg_err(arg_err_msg, new::nil()));
-            };
-
-            Ok(new::err(kind, msg, new::nil()))
-        }),
+        gen::meth!(
+            "new",
+            type_ref,
+            &["type", "msg", ""],
+            "Create a new Err. The `err()` builtin is a shortcut for this.
+
+            # Args
+
+            - type: ErrType
+            - msg: Str
+            - data?: Any
+
+              Structured data describing the error. If data is a Map,
+              its entries are also accessible as attributes of the Err,
+              except for entries that have the same name as an Err
+              attribute (e.g., `type` or `message`).
+
+            ",
+            |_, args, _| Ok(err_from_args("Err.new()", &args))
+        ),
         // Instance Attributes -----------------------------------------
         gen::prop!("type", type_ref, "", |this, _, _| {
             let this = this.read().unwrap();
@@ -89,11 +78,63 @@ pub static ERR_TYPE: Lazy<gen::obj_ref_t!(ErrType)> = Lazy::new(|| {
             let this = this.down_to_err().unwrap();
             Ok(new::str(&this.message))
         }),
+        gen::prop!("data", type_ref, "Structured data or nil", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_err().unwrap();
+            Ok(this.data.clone())
+        }),
     ]);
 
     type_ref.clone()
 });
 
+/// Create an Err from the args passed to `Err.new()` or `err()`: type,
+/// message, and optional data in a var args tuple. If the args are
+/// invalid, an arg Err is returned instead.
+pub fn err_from_args(name: &str, args: &Args) -> ObjectRef {
+    let (n_args, _, var_args) = match check_args(name, args, true, 2, Some(3)) {
+        Ok(result) => result,
+        Err(err) => return err,
+    };
+
+    if n_args > 3 {
+        let arg_err_msg = format!("{name} expected 2 to 3 args; got {n_args}");
+        return new::arg_err(arg_err_msg, new::nil());
+    }
+
+    let type_arg = args[0].read().unwrap();
+    let msg_arg = args[1].read().unwrap();
+
+    let err_type = if let Some(err_type) = type_arg.down_to_err_type_obj() {
+        err_type
+    } else {
+        let arg_err_msg = format!("{name} expected type to be an ErrType");
+        // NOTE: This is problematic because user code won't be able to
+        //       tell if the arg error was the result of creating an arg
+        //       err explicitly or the result of an internal error. Note
+        //       that this applies to *any* user-constructible error.
+        //
+        // TODO: Figure out a solution for this, perhaps an err type
+        //       that is *not* user-constructible or a nested err type?
+        return new::arg_err(arg_err_msg, new::nil());
+    };
+
+    let kind = err_type.kind().clone();
+
+    let msg = if let Some(msg) = msg_arg.get_str_val() {
+        msg
+    } else {
+        let arg_err_msg = format!("{name} expected message to be a Str");
+        return new::arg_err(arg_err_msg, new::nil());
+    };
+
+    let data = var_args.read().unwrap().down_to_tuple().unwrap().get(0);
+    match data {
+        Some(data) => new::err_with_data(kind, msg, new::nil(), data),
+        None => new::err(kind, msg, new::nil()),
+    }
+}
+
 // Error Object --------------------------------------------------------
 
 // NOTE: This is named `ErrObj` instead of `Err` to avoid conflict with
@@ -103,8 +144,8 @@ pub struct ErrObj {
     pub kind: ErrKind,
     pub message: String,
     pub obj: ObjectRef,
+    pub data: ObjectRef,
     bool_val: bool,
-    responds_to_bool: bool,
 }
 
 gen::standard_object_impls!(ErrObj);
@@ -117,19 +158,28 @@ impl ErrObj {
             kind,
             message,
             obj,
+            data: new::nil(),
             bool_val,
-            responds_to_bool: false,
         }
     }
 
-    pub fn with_responds_to_bool(
-        kind: ErrKind,
-        message: String,
-        obj: ObjectRef,
-    ) -> Self {
-        let mut instance = Self::new(kind, message, obj);
-        instance.responds_to_bool = true;
-        instance
+    /// Attach structured data to this Err. If data is a Map, its
+    /// entries are also added as attributes, except for those that
+    /// would shadow a standard attribute.
+    pub fn with_data(mut self, data: ObjectRef) -> Self {
+        if let Some(map) = data.read().unwrap().down_to_map() {
+            let err_type = ERR_TYPE.read().unwrap();
+            for (name, val) in map.entries().read().unwrap().iter() {
+                let is_std_attr = name.starts_with('$')
+                    || matches!(name.as_str(), "err" | "ok" | "to_str")
+                    || ObjectTrait::ns(&*err_type).get(name).is_some();
+                if !is_std_attr {
+                    self.ns.insert(name, val.clone());
+                }
+            }
+        }
+        self.data = data;
+        self
     }
 
     pub fn retrieve_bool_val(&self) -> bool {
@@ -140,16 +190,10 @@ impl ErrObj {
 impl ObjectTrait for ErrObj {
     gen::object_trait_header!(ERR_TYPE);
 
+    /// OK Errs are false and all other Errs are true, whether or not
+    /// the Err was accessed via `.err`.
     fn bool_val(&self) -> RuntimeBoolResult {
-        if self.responds_to_bool {
-            Ok(self.bool_val)
-        } else {
-            Err(RuntimeErr::type_err(concat!(
-                "An Err object cannot be evaluated directly as a ",
-                "Bool. You must access it via the `.err` attribute of ",
-                "the result object.",
-            )))
-        }
+        Ok(self.bool_val)
     }
 
     fn and(&self, rhs: &dyn ObjectTrait) -> RuntimeBoolResult {
@@ -163,6 +207,14 @@ impl ObjectTrait for ErrObj {
         let rhs = rhs.bool_val()?;
         Ok(lhs || rhs)
     }
+
+    /// Calling an Err usually means a method lookup failed (e.g.,
+    /// `obj.missing()`), so the Err is included to show why.
+    fn not_callable(&self) -> RuntimeErr {
+        let class = self.class();
+        let class = class.read().unwrap();
+        RuntimeErr::not_callable(format!("{} ({self})", class.full_name()))
+    }
 }
 
 // Display -------------------------------------------------------------
@@ -22,8 +22,20 @@ pub enum ErrKind {
     AttrNotFound, // more specific attribute not found error
     FileNotFound,
     FileUnreadable,
+    FileUnwritable,
+    Http, // error sending an HTTP request (not an error status)
     IndexOutOfBounds,
+    Import,
+    Json, // malformed JSON or object that can't be encoded as JSON
+    KeyNotFound,
+    ModuleNotFound,
+    NameNotFound,
+    Native, // error returned by a function in a native module
+    Proc,   // error starting a process
     String,
+    Task,
+    Thread,
+    Time, // unparseable or out of range date/time
     Type,
     Ok,
 }
@@ -37,8 +49,20 @@ static ERR_KINDS: Lazy<Vec<ErrKind>> = Lazy::new(|| {
         AttrNotFound,
         FileNotFound,
         FileUnreadable,
+        FileUnwritable,
+        Http,
         IndexOutOfBounds,
+        Import,
+        Json,
+        KeyNotFound,
+        ModuleNotFound,
+        NameNotFound,
+        Native,
+        Proc,
         String,
+        Task,
+        Thread,
+        Time,
         Type,
         Ok,
     ]
@@ -54,8 +78,20 @@ impl ErrKind {
             AttrNotFound => "attr_not_found",
             FileNotFound => "file_not_found",
             FileUnreadable => "file_unreadable",
+            FileUnwritable => "file_unwritable",
+            Http => "http",
             IndexOutOfBounds => "index_out_of_bounds",
+            Import => "import",
+            Json => "json",
+            KeyNotFound => "key_not_found",
+            ModuleNotFound => "module_not_found",
+            NameNotFound => "name_not_found",
+            Native => "native",
+            Proc => "proc",
             String => "string",
+            Task => "task",
+            Thread => "thread",
+            Time => "time",
             Type => "type",
             Ok => "ok",
         }
@@ -141,8 +177,20 @@ impl fmt::Display for ErrKind {
             AttrNotFound => "Attribute not found",
             FileNotFound => "File not found",
             FileUnreadable => "File could not be read",
+            FileUnwritable => "File could not be written",
+            Http => "HTTP error",
             IndexOutOfBounds => "Index out of bounds",
+            Import => "Import error",
+            Json => "JSON error",
+            KeyNotFound => "Key not found",
+            ModuleNotFound => "Module not found",
+            NameNotFound => "Name not found",
+            Native => "Native error",
+            Proc => "Process error",
             String => "String error",
+            Task => "Task error",
+            Thread => "Thread error",
+            Time => "Time error",
             Type => "Type error",
             Ok => "OK (not an error)",
         };
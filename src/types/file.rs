@@ -1,13 +1,16 @@
 use std::any::Any;
+use std::borrow::Cow;
 use std::fmt;
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 
-use once_cell::sync::{Lazy, OnceCell};
+use once_cell::sync::Lazy;
 
-use crate::vm::{RuntimeBoolResult, RuntimeErr};
+use crate::types::IntoObjectRef;
+use crate::vm::RuntimeErr;
 
 use super::gen;
 use super::new;
@@ -46,11 +49,112 @@ pub static FILE_TYPE: Lazy<gen::obj_ref_t!(FileType)> = Lazy::new(|| {
             let this = this.down_to_file().unwrap();
             Ok(this.text())
         }),
-        gen::prop!("lines", type_ref, "", |this, _, _| {
-            let this = this.read().unwrap();
-            let this = &mut this.down_to_file().unwrap();
-            Ok(this.lines())
-        }),
+        gen::prop!(
+            "bytes",
+            type_ref,
+            "Read the entire file as Bytes.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_file().unwrap();
+                Ok(this.bytes())
+            }
+        ),
+        // Instance Methods
+        gen::meth!(
+            "lines",
+            type_ref,
+            &[],
+            "Iterate over the lines in the file.
+
+            Lines are read on demand, so large files aren't read into
+            memory all at once. Iteration stops early on SIGINT.
+
+            ",
+            |this, _, vm| {
+                let this = this.read().unwrap();
+                let this = this.down_to_file().unwrap();
+                Ok(this.lines(vm.sigint_flag()))
+            }
+        ),
+        gen::meth!(
+            "read_chunk",
+            type_ref,
+            &["n"],
+            "Read up to n bytes from the current position.
+
+            This allows files that are too large to read into memory to be
+            processed in chunks. The position is tracked separately from
+            `text` and `lines()`, which always read the entire file.
+
+            # Returns
+
+            Bytes: Empty when the end of the file has been reached
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_file().unwrap();
+                let n = gen::use_arg_usize!(read_chunk, n, args, 0);
+                Ok(this.read_chunk(n))
+            }
+        ),
+        gen::meth!(
+            "seek",
+            type_ref,
+            &["pos"],
+            "Move to byte position pos and return it.",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_file().unwrap();
+                let pos = gen::use_arg_usize!(seek, pos, args, 0);
+                Ok(this.with_handle(|file| file.seek(SeekFrom::Start(pos as u64))))
+            }
+        ),
+        gen::meth!(
+            "tell",
+            type_ref,
+            &[],
+            "Get the current byte position.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_file().unwrap();
+                Ok(this.with_handle(|file| file.stream_position()))
+            }
+        ),
+        gen::meth!(
+            "write",
+            type_ref,
+            &["data"],
+            "Write data to the file.
+
+            Files opened in write mode are written at the current
+            position. Files opened in append mode are always written at
+            the end.
+
+            # Args
+
+            - data: Str | Bytes | ByteBuffer
+
+            # Returns
+
+            - Int: The number of bytes written
+            - Err: If the file was opened for reading or couldn't be
+              written
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_file().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                let data = if let Some(data) = data_to_write(&*arg) {
+                    data
+                } else {
+                    let msg = "write() expected data to be a Str, Bytes, or ByteBuffer";
+                    return Ok(new::arg_err(msg, new::nil()));
+                };
+                Ok(this.write(&data))
+            }
+        ),
     ]);
 
     type_ref.clone()
@@ -58,69 +162,172 @@ pub static FILE_TYPE: Lazy<gen::obj_ref_t!(FileType)> = Lazy::new(|| {
 
 // File Object ----------------------------------------------------------
 
+/// The mode a file was opened with, which determines whether it can be
+/// written. Files can only be opened for writing with `std.fs.open()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "std-full"), allow(dead_code))]
+pub enum FileMode {
+    Read,
+    Write,
+    Append,
+}
+
+impl FileMode {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Read => "r",
+            Self::Write => "w",
+            Self::Append => "a",
+        }
+    }
+}
+
+/// Get the data to write from a Str, Bytes, or ByteBuffer object.
+/// ByteBuffers are copied since their data is behind a lock.
+pub fn data_to_write(obj: &dyn ObjectTrait) -> Option<Cow<'_, [u8]>> {
+    if let Some(text) = obj.get_str_val() {
+        Some(Cow::Borrowed(text.as_bytes()))
+    } else if let Some(bytes) = obj.down_to_bytes() {
+        Some(Cow::Borrowed(bytes.value()))
+    } else {
+        obj.down_to_byte_buffer().map(|buffer| Cow::Owned(buffer.to_vec()))
+    }
+}
+
 pub struct File {
     ns: Namespace,
     file_name: String,
     path: PathBuf,
-    text: OnceCell<ObjectRef>,
-    lines: OnceCell<ObjectRef>,
+    mode: FileMode,
+    // Cleared when the file is written.
+    text: RwLock<Option<ObjectRef>>,
+    // Opened on first use by positioned reads.
+    handle: RwLock<Option<fs::File>>,
 }
 
 gen::standard_object_impls!(File);
 
 impl File {
     pub fn new(file_name: String) -> Self {
+        Self::with_mode(file_name, FileMode::Read)
+    }
+
+    /// Create a File for a file that has already been opened (created,
+    /// truncated, etc) with the specified mode.
+    pub fn with_mode(file_name: String, mode: FileMode) -> Self {
         let path = fs::canonicalize(&file_name);
         let path = path.map_or_else(|_| Path::new(&file_name).to_path_buf(), |p| p);
         let name_obj = new::str(file_name.as_str());
+        let mode_obj = new::str(mode.name());
         Self {
-            ns: Namespace::with_entries(&[("name", name_obj)]),
+            ns: Namespace::with_entries(&[("name", name_obj), ("mode", mode_obj)]),
             file_name,
             path,
-            text: OnceCell::default(),
-            lines: OnceCell::default(),
+            mode,
+            text: RwLock::new(None),
+            handle: RwLock::new(None),
         }
     }
 
     fn text(&self) -> ObjectRef {
-        let result = self.text.get_or_try_init(|| {
-            fs::read_to_string(&self.file_name)
-                .map(new::str)
-                .map_err(|err| new::file_unreadable_err(err.to_string(), new::nil()))
-        });
-        match result {
-            Ok(text) => text.clone(),
-            Err(err) => err,
+        let text = &mut self.text.write().unwrap();
+        if let Some(text) = &**text {
+            return text.clone();
+        }
+        match fs::read_to_string(&self.file_name) {
+            Ok(val) => {
+                let val = new::str(val);
+                **text = Some(val.clone());
+                val
+            }
+            Err(err) => new::file_unreadable_err(err.to_string(), new::nil()),
         }
     }
 
-    fn lines(&self) -> ObjectRef {
-        let result = self.lines.get_or_try_init(|| {
-            let file = fs::File::open(&self.file_name);
-            file.map(|file| {
-                let reader = BufReader::new(file);
-                let lines = reader
-                    .lines()
-                    // TODO: Handle lines that can't be read
-                    .map(|line| new::str(line.unwrap()))
-                    .collect();
-                new::tuple(lines)
-            })
-            .map_err(|err| new::file_unreadable_err(err.to_string(), new::nil()))
-        });
-        match result {
-            Ok(lines) => lines.clone(),
-            Err(err) => err,
+    fn bytes(&self) -> ObjectRef {
+        match fs::read(&self.file_name) {
+            Ok(data) => new::bytes(data),
+            Err(err) => new::file_unreadable_err(err.to_string(), new::nil()),
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> ObjectRef {
+        if self.mode == FileMode::Read {
+            let msg = format!("{} was opened for reading", self.file_name);
+            return new::file_unwritable_err(msg, new::nil());
+        }
+        *self.text.write().unwrap() = None;
+        self.with_handle(|file| file.write_all(data).map(|_| data.len()))
+    }
+
+    fn read_chunk(&self, n: usize) -> ObjectRef {
+        self.with_handle(|file| {
+            let mut chunk = vec![];
+            file.take(n as u64).read_to_end(&mut chunk)?;
+            Ok(new::bytes(chunk))
+        })
+    }
+
+    /// Run `op` on the file's handle, opening the file on first use,
+    /// and convert its result to an object. The handle stays open so
+    /// the position is kept between calls.
+    fn with_handle<T, F>(&self, op: F) -> ObjectRef
+    where
+        T: IntoObjectRef,
+        F: FnOnce(&mut fs::File) -> io::Result<T>,
+    {
+        let make_err = match self.mode {
+            FileMode::Read => new::file_unreadable_err::<String>,
+            FileMode::Write | FileMode::Append => new::file_unwritable_err::<String>,
+        };
+        let handle = &mut self.handle.write().unwrap();
+        if handle.is_none() {
+            let mut options = fs::OpenOptions::new();
+            match self.mode {
+                FileMode::Read => options.read(true),
+                FileMode::Write => options.read(true).write(true),
+                FileMode::Append => options.read(true).append(true),
+            };
+            match options.open(&self.file_name) {
+                Ok(file) => **handle = Some(file),
+                Err(err) => return make_err(err.to_string(), new::nil()),
+            }
+        }
+        match op(handle.as_mut().unwrap()) {
+            Ok(val) => val.into_obj_ref(),
+            Err(err) => make_err(err.to_string(), new::nil()),
         }
     }
+
+    /// Open the file and return an iterator that reads its lines on
+    /// demand. If a line can't be read, an `Err` is produced in its
+    /// place and iteration stops. Iteration also stops when the
+    /// `interrupted` flag is set.
+    fn lines(&self, interrupted: Arc<AtomicBool>) -> ObjectRef {
+        let file = match fs::File::open(&self.file_name) {
+            Ok(file) => file,
+            Err(err) => return new::file_unreadable_err(err.to_string(), new::nil()),
+        };
+        let mut lines = BufReader::new(file).lines();
+        let mut done = false;
+        let source = std::iter::from_fn(move || {
+            if done || interrupted.load(Ordering::Relaxed) {
+                return None;
+            }
+            match lines.next()? {
+                Ok(line) => Some(new::str(line)),
+                Err(err) => {
+                    done = true;
+                    Some(new::file_unreadable_err(err.to_string(), new::nil()))
+                }
+            }
+        });
+        new::lazy_iterator(Box::new(source))
+    }
 }
 
 impl ObjectTrait for File {
     gen::object_trait_header!(FILE_TYPE);
-
-    fn bool_val(&self) -> RuntimeBoolResult {
-        Ok(false)
-    }
 }
 
 // Display -------------------------------------------------------------
@@ -2,7 +2,8 @@ use std::any::Any;
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
-use num_traits::ToPrimitive;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, ToPrimitive};
 use once_cell::sync::Lazy;
 
 use crate::vm::{RuntimeBoolResult, RuntimeErr, RuntimeObjResult};
@@ -10,7 +11,7 @@ use crate::vm::{RuntimeBoolResult, RuntimeErr, RuntimeObjResult};
 use super::gen;
 
 use super::new;
-use super::util::{eq_int_float, float_gt_int, float_lt_int};
+use super::util::{eq_int_float, float_gt_int, float_lt_int, floor_div_float};
 
 use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
 use super::class::TYPE_TYPE;
@@ -40,15 +41,131 @@ pub static FLOAT_TYPE: Lazy<gen::obj_ref_t!(FloatType)> = Lazy::new(|| {
             };
             Ok(float)
         }),
+        // Instance Methods --------------------------------------------
+        gen::meth!("abs", type_ref, &[], "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_float().unwrap();
+            Ok(new::float(this.value().abs()))
+        }),
+        gen::meth!(
+            "floor",
+            type_ref,
+            &[],
+            "Round down to the nearest Int.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_float().unwrap();
+                Ok(whole_float_to_int("floor", this.value().floor()))
+            }
+        ),
+        gen::meth!(
+            "ceil",
+            type_ref,
+            &[],
+            "Round up to the nearest Int.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_float().unwrap();
+                Ok(whole_float_to_int("ceil", this.value().ceil()))
+            }
+        ),
+        gen::meth!(
+            "round",
+            type_ref,
+            &[""],
+            "Round to the nearest Int or to a number of decimal places.
+            Halves are rounded away from zero.
+
+            # Args
+
+            - digits?: Int
+
+              Number of decimal places. Negative values round to tens,
+              hundreds, etc.
+
+            # Returns
+
+            - Int: If digits isn't specified
+            - Float: If digits is specified
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_float().unwrap();
+                Ok(match digits_arg("round", &args[0]) {
+                    Ok(None) => whole_float_to_int("round", this.value().round()),
+                    Ok(Some(digits)) => {
+                        new::float(round_to_digits(*this.value(), digits))
+                    }
+                    Err(err) => err,
+                })
+            }
+        ),
     ]);
 
     type_ref.clone()
 });
 
+/// Convert a Float value with no fractional part (e.g., the result of
+/// `f64::floor()`) to an Int. Infinity and NaN can't be converted, so
+/// an arg Err is returned for them.
+pub fn whole_float_to_int(func_name: &str, value: f64) -> ObjectRef {
+    match BigInt::from_f64(value) {
+        Some(int) => new::int(int),
+        None => {
+            let msg = format!("{func_name}() could not convert {value} to an Int");
+            new::arg_err(msg, new::nil())
+        }
+    }
+}
+
+/// Round `value` to `digits` decimal places. Negative digits round to
+/// tens, hundreds, etc.
+pub fn round_to_digits(value: f64, digits: i32) -> f64 {
+    let scale = 10f64.powi(digits);
+    if scale == 0.0 {
+        return 0.0;
+    }
+    // If scaling overflows, there aren't enough significant digits for
+    // rounding to change the value.
+    let scaled = value * scale;
+    if scaled.is_finite() {
+        scaled.round() / scale
+    } else {
+        value
+    }
+}
+
+/// Get the optional digits arg to `round()` from a var args tuple.
+pub fn digits_arg(
+    func_name: &str,
+    var_args: &ObjectRef,
+) -> Result<Option<i32>, ObjectRef> {
+    let var_args = var_args.read().unwrap();
+    let var_args = var_args.down_to_tuple().unwrap();
+    match var_args.len() {
+        0 => Ok(None),
+        1 => {
+            let digits = var_args.get(0).unwrap();
+            let digits = digits.read().unwrap();
+            if let Some(digits) = digits.get_int_val().and_then(|d| d.to_i32()) {
+                Ok(Some(digits))
+            } else {
+                let msg = format!("{func_name}() expected digits to be an Int");
+                Err(new::arg_err(msg, new::nil()))
+            }
+        }
+        n => {
+            let msg = format!("{func_name}() expected at most 1 arg; got {n}");
+            Err(new::arg_err(msg, new::nil()))
+        }
+    }
+}
+
 // Float Object --------------------------------------------------------
 
 macro_rules! make_op {
-    ( $meth:ident, $op:tt, $message:literal, $trunc:literal ) => {
+    ( $meth:ident, $op:tt, $message:literal ) => {
         fn $meth(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
             let value = if let Some(rhs) = rhs.down_to_float() {
                 *rhs.value()
@@ -57,10 +174,7 @@ macro_rules! make_op {
             } else {
                 return Err(RuntimeErr::type_err(format!($message, rhs.class().read().unwrap())));
             };
-            let mut value = &self.value $op value;
-            if $trunc {
-                value = value.trunc();
-            }
+            let value = &self.value $op value;
             let value = new::float(value);
             Ok(value)
         }
@@ -82,17 +196,29 @@ impl Float {
     pub fn value(&self) -> &f64 {
         &self.value
     }
+
+    /// NOTE: This is only used by the VM to reuse pooled temporaries.
+    ///       Floats are otherwise immutable.
+    pub(crate) fn set_value(&mut self, value: f64) {
+        self.value = value;
+    }
 }
 
 impl ObjectTrait for Float {
     gen::object_trait_header!(FLOAT_TYPE);
 
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(*self.value() != 0.0)
+    }
+
     fn negate(&self) -> RuntimeObjResult {
         Ok(new::float(-*self.value()))
     }
 
     fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
-        if self.is(rhs) || rhs.is_always() {
+        // NOTE: There's no identity check here because NaN isn't equal
+        //       to itself.
+        if rhs.is_always() {
             true
         } else if let Some(rhs) = rhs.down_to_float() {
             self.value() == rhs.value()
@@ -148,12 +274,27 @@ impl ObjectTrait for Float {
         Ok(value)
     }
 
-    make_op!(modulo, %, "Could not divide {} with Float", false);
-    make_op!(mul, *, "Could not multiply {} with Float", false);
-    make_op!(div, /, "Could not divide {} into Float", false);
-    make_op!(floor_div, /, "Could not divide {} into Float", true); // truncates
-    make_op!(add, +, "Could not add {} to Float", false);
-    make_op!(sub, -, "Could not subtract {} from Float", false);
+    make_op!(modulo, %, "Could not divide {} with Float");
+    make_op!(mul, *, "Could not multiply {} with Float");
+    make_op!(div, /, "Could not divide {} into Float");
+    make_op!(add, +, "Could not add {} to Float");
+    make_op!(sub, -, "Could not subtract {} from Float");
+
+    // Float *floor* division *always* returns an Int
+    fn floor_div(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
+        let rhs_val = if let Some(rhs) = rhs.down_to_float() {
+            *rhs.value()
+        } else if let Some(rhs) = rhs.down_to_int() {
+            rhs.value().to_f64().unwrap()
+        } else {
+            return Err(RuntimeErr::type_err(format!(
+                "Could not divide {} into Float",
+                rhs.class().read().unwrap()
+            )));
+        };
+        let value = floor_div_float(*self.value(), rhs_val)?;
+        Ok(new::int(value))
+    }
 }
 
 // Display -------------------------------------------------------------
@@ -32,12 +32,19 @@ pub struct Func {
     name: String,
     params: Params,
     code: Code,
+    is_method: bool,
 }
 
 gen::standard_object_impls!(Func);
 
 impl Func {
-    pub fn new(module_name: String, name: String, params: Params, code: Code) -> Self {
+    pub fn new(
+        module_name: String,
+        name: String,
+        params: Params,
+        code: Code,
+        is_method: bool,
+    ) -> Self {
         Self {
             ns: Namespace::with_entries(&[
                 // Instance Attributes
@@ -45,12 +52,15 @@ impl Func {
                 ("$full_name", new::str(format!("{module_name}.{name}"))),
                 ("$name", new::str(&name)),
                 ("$doc", code.get_doc()),
+                ("$is_method", new::bool(is_method)),
+                ("$is_async", new::bool(code.is_async())),
             ]),
             module_name,
             module: OnceCell::default(),
             name,
             params,
             code,
+            is_method,
         }
     }
 
@@ -69,6 +79,18 @@ impl Func {
     pub fn code(&self) -> &Code {
         &self.code
     }
+
+    /// Whether the function was defined as a type member or assigned
+    /// to an attribute, meaning it's expected to be bound.
+    pub fn is_method(&self) -> bool {
+        self.is_method
+    }
+
+    /// Whether the function was defined with `async`, meaning calling
+    /// it creates a task rather than running it.
+    pub fn is_async(&self) -> bool {
+        self.code.is_async()
+    }
 }
 
 impl FuncTrait for Func {
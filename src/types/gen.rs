@@ -58,6 +58,10 @@ macro_rules! type_and_impls {
             }
 
             pub fn add_attrs(&mut self, attrs: &[(&str, ObjectRef)]) {
+                $crate::modules::registry::register_group(
+                    concat!("std.", stringify!($name)),
+                    attrs,
+                );
                 self.ns.extend(attrs);
             }
         }
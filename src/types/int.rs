@@ -3,7 +3,7 @@ use std::fmt;
 use std::sync::{Arc, RwLock};
 
 use num_bigint::BigInt;
-use num_traits::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
 
 use once_cell::sync::Lazy;
 
@@ -12,7 +12,9 @@ use crate::vm::{RuntimeBoolResult, RuntimeErr, RuntimeObjResult};
 use super::gen;
 
 use super::new;
-use super::util::{eq_int_float, int_gt_float, int_lt_float};
+use super::util::{
+    eq_int_float, floor_div_float, floor_div_int, int_gt_float, int_lt_float,
+};
 
 use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
 use super::class::TYPE_TYPE;
@@ -47,6 +49,12 @@ pub static INT_TYPE: Lazy<gen::obj_ref_t!(IntType)> = Lazy::new(|| {
             };
             Ok(int)
         }),
+        // Instance Methods --------------------------------------------
+        gen::meth!("abs", type_ref, &[], "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_int().unwrap();
+            Ok(new::int(this.value().abs()))
+        }),
     ]);
 
     type_ref.clone()
@@ -90,6 +98,12 @@ impl Int {
         &self.value
     }
 
+    /// NOTE: This is only used by the VM to reuse pooled temporaries.
+    ///       Ints are otherwise immutable.
+    pub(crate) fn set_value(&mut self, value: BigInt) {
+        self.value = value;
+    }
+
     // Cast both LHS and RHS to f64 and divide them
     fn div_f64(&self, rhs: &dyn ObjectTrait) -> Result<f64, RuntimeErr> {
         let lhs_val = self.value().to_f64().unwrap();
@@ -110,6 +124,10 @@ impl Int {
 impl ObjectTrait for Int {
     gen::object_trait_header!(INT_TYPE);
 
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(!self.value().is_zero())
+    }
+
     fn negate(&self) -> RuntimeObjResult {
         Ok(new::int(-self.value.clone()))
     }
@@ -178,11 +196,27 @@ impl ObjectTrait for Int {
         }
     }
 
-    make_op!(modulo, %, "Could not divide {} with Int");
     make_op!(mul, *, "Could not multiply {} with Int");
     make_op!(add, +, "Could not add {} to Int");
     make_op!(sub, -, "Could not subtract {} from Int");
 
+    fn modulo(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
+        if let Some(rhs) = rhs.down_to_int() {
+            if rhs.value().is_zero() {
+                return Err(RuntimeErr::arg_err("Division by zero"));
+            }
+            Ok(new::int(self.value() % rhs.value()))
+        } else if let Some(rhs) = rhs.down_to_float() {
+            let value = self.value().to_f64().unwrap() % rhs.value();
+            Ok(new::float(value))
+        } else {
+            Err(RuntimeErr::type_err(format!(
+                "Could not divide {} with Int",
+                rhs.class().read().unwrap()
+            )))
+        }
+    }
+
     // Int division *always* returns a Float
     fn div(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
         let value = self.div_f64(rhs)?;
@@ -192,10 +226,17 @@ impl ObjectTrait for Int {
 
     // Int *floor* division *always* returns an Int
     fn floor_div(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
-        let value = self.div_f64(rhs)?;
-        let value = BigInt::from_f64(value).unwrap();
-        let value = new::int(value);
-        Ok(value)
+        let value = if let Some(rhs) = rhs.down_to_int() {
+            floor_div_int(self.value(), rhs.value())?
+        } else if let Some(rhs) = rhs.down_to_float() {
+            floor_div_float(self.value().to_f64().unwrap(), *rhs.value())?
+        } else {
+            return Err(RuntimeErr::type_err(format!(
+                "Could not divide {} into Int",
+                rhs.class().read().unwrap()
+            )));
+        };
+        Ok(new::int(value))
     }
 }
 
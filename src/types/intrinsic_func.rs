@@ -17,7 +17,11 @@ use super::class::TYPE_TYPE;
 use super::func_trait::FuncTrait;
 use super::ns::Namespace;
 
-pub type IntrinsicFn = fn(ObjectRef, Args, &mut VM) -> CallResult;
+/// Intrinsic functions are usually plain functions, but they can also
+/// be closures (e.g., functions in native modules registered by host
+/// applications).
+pub type IntrinsicFn =
+    Arc<dyn Fn(ObjectRef, Args, &mut VM) -> CallResult + Send + Sync>;
 
 // Intrinsic Function Type ---------------------------------------------
 
@@ -1,5 +1,6 @@
 use std::any::Any;
 use std::fmt;
+use std::iter::Peekable;
 use std::sync::{Arc, RwLock};
 
 use once_cell::sync::Lazy;
@@ -27,8 +28,8 @@ pub static ITERATOR_TYPE: Lazy<gen::obj_ref_t!(IteratorType)> = Lazy::new(|| {
             Ok(this.next())
         }),
         gen::meth!("peek", type_ref, &[], "", |this, _, _| {
-            let this = this.write().unwrap();
-            let this = this.down_to_iterator().unwrap();
+            let mut this = this.write().unwrap();
+            let this = this.down_to_iterator_mut().unwrap();
             Ok(this.peek())
         }),
     ]);
@@ -38,40 +39,43 @@ pub static ITERATOR_TYPE: Lazy<gen::obj_ref_t!(IteratorType)> = Lazy::new(|| {
 
 // Iterator Object -----------------------------------------------------
 
+/// The wrapped items are produced on demand, so an iterator may be
+/// backed by a fixed list of items or by a lazy (possibly infinite)
+/// Rust iterator. Sources have to be `Send` since objects can be moved
+/// to other threads.
+pub type IteratorSource = Box<dyn Iterator<Item = ObjectRef> + Send>;
+
 pub struct FIIterator {
     ns: Namespace,
-    wrapped: Vec<ObjectRef>,
-    current: usize,
+    wrapped: Peekable<IteratorSource>,
 }
 
 gen::standard_object_impls!(FIIterator);
 
 impl FIIterator {
     pub fn new(wrapped: Vec<ObjectRef>) -> Self {
-        Self { ns: Namespace::default(), wrapped, current: 0 }
+        Self::from_source(Box::new(wrapped.into_iter()))
     }
 
-    fn next(&mut self) -> ObjectRef {
-        let obj = self.get_or_nil(self.current);
-        if self.current < self.len() {
-            self.current += 1;
-        }
-        obj
+    pub fn from_source(source: IteratorSource) -> Self {
+        Self { ns: Namespace::default(), wrapped: source.peekable() }
     }
 
-    fn peek(&self) -> ObjectRef {
-        self.get_or_nil(self.current)
+    /// Get the next item or `None` when the iterator is exhausted.
+    /// Unlike `next()`, this allows `nil` items to be distinguished
+    /// from exhaustion.
+    pub fn next_item(&mut self) -> Option<ObjectRef> {
+        self.wrapped.next()
     }
 
-    fn len(&self) -> usize {
-        self.wrapped.len()
+    fn next(&mut self) -> ObjectRef {
+        self.next_item().unwrap_or_else(new::nil)
     }
 
-    fn get_or_nil(&self, index: usize) -> ObjectRef {
-        if index >= self.len() {
-            new::nil()
-        } else {
-            self.wrapped[index].clone()
+    fn peek(&mut self) -> ObjectRef {
+        match self.wrapped.peek() {
+            Some(obj) => obj.clone(),
+            None => new::nil(),
         }
     }
 }
@@ -4,7 +4,8 @@ use std::sync::{Arc, RwLock};
 
 use once_cell::sync::Lazy;
 
-use crate::vm::{RuntimeErr, RuntimeResult};
+use crate::types::IntoObjectRef;
+use crate::vm::{RuntimeBoolResult, RuntimeErr, RuntimeResult};
 
 use super::gen;
 
@@ -42,6 +43,11 @@ pub static LIST_TYPE: Lazy<gen::obj_ref_t!(ListType)> = Lazy::new(|| {
             seq::sum(items)
         }),
         // Instance Methods --------------------------------------------
+        gen::meth!("contains", type_ref, &["member"], "", |this, args, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_list().unwrap();
+            seq::has(&this.to_vec(), &args)
+        }),
         gen::meth!(
             "each",
             type_ref,
@@ -76,15 +82,35 @@ pub static LIST_TYPE: Lazy<gen::obj_ref_t!(ListType)> = Lazy::new(|| {
                 Ok(return_val)
             }
         ),
+        gen::meth!(
+            "filter",
+            type_ref,
+            &["filter_fn"],
+            "Get the List items the function returns a truthy value for.
+
+            # Args
+
+            - func: Func
+
+              A function that will be passed each item in turn and, optionally, the
+              index of the item.
+
+            # Returns
+
+            Tuple
+
+            ",
+            |this_obj, args, vm| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_list().unwrap();
+                seq::filter(&this_obj, &this.to_vec(), &args, vm)
+            }
+        ),
         gen::meth!("get", type_ref, &["index"], "", |this, args, _| {
             let this = this.read().unwrap();
             let this = this.down_to_list().unwrap();
             let index = gen::use_arg_usize!(get, index, args, 0);
-            let result = match this.get(index) {
-                Some(obj) => obj,
-                None => new::nil(),
-            };
-            Ok(result)
+            Ok(this.get(index).into_obj_ref())
         }),
         gen::meth!("has", type_ref, &["member"], "", |this, args, _| {
             let this = this.read().unwrap();
@@ -92,6 +118,38 @@ pub static LIST_TYPE: Lazy<gen::obj_ref_t!(ListType)> = Lazy::new(|| {
             let items = &this.items.read().unwrap();
             seq::has(items, &args)
         }),
+        gen::meth!(
+            "index_of",
+            type_ref,
+            &["member"],
+            "Get the index of the first item equal to member or nil.",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_list().unwrap();
+                seq::index_of(&this.to_vec(), &args)
+            }
+        ),
+        gen::meth!(
+            "insert",
+            type_ref,
+            &["index", "item"],
+            "Insert item before index and return it.
+
+            An index equal to the List's length appends the item.
+
+            ",
+            |this_obj, args, _| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_list().unwrap();
+                let index = gen::use_arg_usize!(insert, index, args, 0);
+                let item = args[1].clone();
+                if this.insert(index, item.clone()) {
+                    Ok(item)
+                } else {
+                    Ok(this.index_out_of_bounds(index, this_obj.clone()))
+                }
+            }
+        ),
         gen::meth!("join", type_ref, &["sep"], "", |this, args, _| {
             let this = this.read().unwrap();
             let this = this.down_to_list().unwrap();
@@ -107,11 +165,7 @@ pub static LIST_TYPE: Lazy<gen::obj_ref_t!(ListType)> = Lazy::new(|| {
         gen::meth!("pop", type_ref, &[], "", |this, _, _| {
             let this = this.read().unwrap();
             let this = this.down_to_list().unwrap();
-            let result = match this.pop() {
-                Some(obj) => obj,
-                None => new::nil(),
-            };
-            Ok(result)
+            Ok(this.pop().into_obj_ref())
         }),
         gen::meth!(
             "push",
@@ -126,6 +180,96 @@ pub static LIST_TYPE: Lazy<gen::obj_ref_t!(ListType)> = Lazy::new(|| {
                 Ok(arg)
             }
         ),
+        gen::meth!(
+            "reduce",
+            type_ref,
+            &["reduce_fn", "initial"],
+            "Reduce List items to a single value.
+
+            # Args
+
+            - func: Func
+
+              A function that will be passed the accumulated value (starting
+              with `initial`) and each item in turn. The value it returns is
+              passed along with the next item.
+
+            - initial: Any
+
+            ",
+            |this_obj, args, vm| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_list().unwrap();
+                seq::reduce(&this_obj, &this.to_vec(), &args, vm)
+            }
+        ),
+        gen::meth!(
+            "remove",
+            type_ref,
+            &["index"],
+            "Remove item at index and return it.",
+            |this_obj, args, _| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_list().unwrap();
+                let index = gen::use_arg_usize!(remove, index, args, 0);
+                match this.remove(index) {
+                    Some(item) => Ok(item),
+                    None => Ok(this.index_out_of_bounds(index, this_obj.clone())),
+                }
+            }
+        ),
+        gen::meth!(
+            "reverse",
+            type_ref,
+            &[],
+            "Reverse items in place and return this.",
+            |this_obj, _, _| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_list().unwrap();
+                this.items.write().unwrap().reverse();
+                Ok(this_obj.clone())
+            }
+        ),
+        gen::meth!(
+            "sort",
+            type_ref,
+            &[""],
+            "Sort items in place and return this.
+
+            # Args
+
+            - cmp?: Func
+
+              A function that will be passed two items and must return a
+              negative Int if the first item is less than the second, 0 if
+              they're equal, or a positive Int if the first item is greater.
+              By default, items are compared with `<`.
+
+            ",
+            |this_obj, args, vm| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_list().unwrap();
+                let var_args = args[0].read().unwrap();
+                let var_args = var_args.down_to_tuple().unwrap();
+                let cmp_fn = match var_args.len() {
+                    0 => None,
+                    1 => var_args.get(0),
+                    n => {
+                        let msg = format!("sort() expected at most 1 arg; got {n}");
+                        return Ok(new::arg_err(msg, this_obj.clone()));
+                    }
+                };
+                if let Some(cmp_fn) = &cmp_fn {
+                    if cmp_fn.read().unwrap().as_func().is_none() {
+                        let msg = "sort() expected cmp to be a function";
+                        return Ok(new::arg_err(msg, this_obj.clone()));
+                    }
+                }
+                let items = seq::sort(this.to_vec(), cmp_fn.as_ref(), vm)?;
+                *this.items.write().unwrap() = items;
+                Ok(this_obj.clone())
+            }
+        ),
     ]);
 
     type_ref.clone()
@@ -178,6 +322,27 @@ impl List {
         Ok(())
     }
 
+    /// Insert item before index. Returns `false` if the index is out of
+    /// bounds (an index equal to the length is allowed).
+    pub fn insert(&self, index: usize, item: ObjectRef) -> bool {
+        let items = &mut self.items.write().unwrap();
+        if index <= items.len() {
+            items.insert(index, item);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove(&self, index: usize) -> Option<ObjectRef> {
+        let items = &mut self.items.write().unwrap();
+        if index < items.len() {
+            Some(items.remove(index))
+        } else {
+            None
+        }
+    }
+
     pub fn pop(&self) -> Option<ObjectRef> {
         let items = &mut self.items.write().unwrap();
         if let Some(item) = items.pop() {
@@ -187,6 +352,12 @@ impl List {
         }
     }
 
+    /// Get a snapshot of the list's current items.
+    pub fn to_vec(&self) -> Vec<ObjectRef> {
+        let items = self.items.read().unwrap();
+        items.clone()
+    }
+
     pub fn get(&self, index: usize) -> Option<ObjectRef> {
         let items = self.items.read().unwrap();
         if let Some(item) = items.get(index) {
@@ -200,6 +371,10 @@ impl List {
 impl ObjectTrait for List {
     gen::object_trait_header!(LIST_TYPE);
 
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(self.len() > 0)
+    }
+
     fn get_item(&self, index: usize, this: ObjectRef) -> ObjectRef {
         if let Some(item) = self.get(index) {
             item.clone()
@@ -208,6 +383,26 @@ impl ObjectTrait for List {
         }
     }
 
+    fn set_item(
+        &mut self,
+        index: usize,
+        this: ObjectRef,
+        value: ObjectRef,
+    ) -> ObjectRef {
+        let items = &mut self.items.write().unwrap();
+        if let Some(item) = items.get_mut(index) {
+            *item = value;
+            new::nil()
+        } else {
+            self.index_out_of_bounds(index, this)
+        }
+    }
+
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        let items = self.items.read().unwrap();
+        Ok(items.iter().any(|item| member.is_equal(&*item.read().unwrap())))
+    }
+
     fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
         if self.is(rhs) || rhs.is_always() {
             return true;
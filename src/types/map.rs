@@ -5,7 +5,7 @@ use std::sync::{Arc, RwLock};
 use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 
-use crate::vm::RuntimeErr;
+use crate::vm::{RuntimeBoolResult, RuntimeErr};
 
 use super::gen;
 use super::new;
@@ -119,24 +119,36 @@ pub static MAP_TYPE: Lazy<gen::obj_ref_t!(MapType)> = Lazy::new(|| {
                 Ok(new::nil())
             }
         ),
+        gen::meth!(
+            "entries",
+            type_ref,
+            &[],
+            "Get Map entries as a Tuple of (key, value) Tuples.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_map().unwrap();
+                Ok(new::tuple(this.entry_tuples()))
+            }
+        ),
         gen::meth!(
             "get",
             type_ref,
-            &["key"],
+            &["key", ""],
             "Get value for key from Map.
 
             # Args
 
             - key: Key
+            - default?: Any Returned when key isn't present (default: nil)
 
             # Returns
 
             - Any: If key is present
-            - nil: If key is not present
+            - default: If key is not present
 
             > NOTE: There's no way to distinguish between a key that isn't present
             > versus a key that has `nil` as its value. To avoid ambiguity, don't
-            > store `nil` values.
+            > store `nil` values or pass a default.
 
             ",
             |this, args, _| {
@@ -144,21 +156,201 @@ pub static MAP_TYPE: Lazy<gen::obj_ref_t!(MapType)> = Lazy::new(|| {
                 let this = this.down_to_map().unwrap();
                 let arg = gen::use_arg!(args, 0);
                 let key = gen::use_arg_str!(get, key, arg);
-                let result = match this.get(key) {
-                    Some(obj) => obj,
-                    None => new::nil(),
+                let var_args = args[1].read().unwrap();
+                let var_args = var_args.down_to_tuple().unwrap();
+                let default = match var_args.len() {
+                    0 => new::nil(),
+                    1 => var_args.get(0).unwrap(),
+                    n => {
+                        let msg =
+                            format!("get() expected at most 2 args; got {}", n + 1);
+                        return Ok(new::arg_err(msg, new::nil()));
+                    }
                 };
-                Ok(result)
+                Ok(this.get(key).unwrap_or(default))
+            }
+        ),
+        gen::meth!(
+            "get_or_insert",
+            type_ref,
+            &["key", "default_fn"],
+            "Get value for key from Map, inserting a default value first
+            if key isn't present.
+
+            # Args
+
+            - key: Str
+            - default_fn: Func
+
+              Called with no args to create the default value. It's only
+              called when key isn't present.
+
+            # Returns
+
+            Any: The existing or inserted value
+
+            ```
+            → groups = {}
+            → groups.get_or_insert('a', () => []).push(1)
+            → groups
+            {'a' => [1]}
+            ```
+
+            ",
+            |this, args, vm| {
+                let arg = gen::use_arg!(args, 0);
+                let key = gen::use_arg_str!(get_or_insert, key, arg);
+                if let Some(val) = this.read().unwrap().down_to_map().unwrap().get(key)
+                {
+                    return Ok(val);
+                }
+                // NOTE: The Map isn't locked while default_fn is called
+                //       so that it can access the Map.
+                vm.call(args[1].clone(), vec![])?;
+                let val = vm.pop_obj()?;
+                let this = this.read().unwrap();
+                let this = this.down_to_map().unwrap();
+                let mut entries = this.entries.write().unwrap();
+                Ok(entries.entry(key.to_owned()).or_insert(val).clone())
             }
         ),
         gen::meth!("has", type_ref, &["member"], "", |this, args, _| {
             let this = this.read().unwrap();
             let this = this.down_to_map().unwrap();
             let arg = gen::use_arg!(args, 0);
-            let key = gen::use_arg_str!(get, key, arg);
+            let key = gen::use_arg_str!(has, key, arg);
             let result = this.contains_key(key);
             Ok(new::bool(result))
         }),
+        gen::meth!(
+            "iter",
+            type_ref,
+            &[],
+            "Iterate over Map entries as (key, value) Tuples.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_map().unwrap();
+                Ok(new::iterator(this.entry_tuples()))
+            }
+        ),
+        gen::meth!("keys", type_ref, &[], "Get Map keys as a Tuple.", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_map().unwrap();
+            let entries = this.entries.read().unwrap();
+            Ok(new::tuple(entries.keys().map(new::str).collect()))
+        }),
+        gen::meth!(
+            "merge",
+            type_ref,
+            &["other"],
+            "Create a new Map with the entries from this Map and other.
+
+            When a key is in both Maps, the value from other is used.
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_map().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                let other = gen::use_arg_map!(merge, other, arg);
+                let mut entries = this.entries.read().unwrap().clone();
+                entries.extend(other.entries.read().unwrap().clone());
+                Ok(new::map(entries))
+            }
+        ),
+        gen::meth!(
+            "remove",
+            type_ref,
+            &["key"],
+            "Remove entry from Map.
+
+            # Returns
+
+            - Any: The removed value if key was present
+            - nil: If key was not present
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_map().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                let key = gen::use_arg_str!(remove, key, arg);
+                Ok(this.remove(key).unwrap_or_else(new::nil))
+            }
+        ),
+        gen::meth!(
+            "update",
+            type_ref,
+            &["other", ""],
+            "Update this Map.
+
+            # Forms
+
+            - update(other: Map): Add entries from other to this Map and
+              return this.
+            - update(key: Str, update_fn: Func): Replace the value for
+              key with the result of calling update_fn with the current
+              value (or nil if key isn't present) and return the new
+              value.
+
+            ```
+            → counts = {'a': 1}
+            → counts.update('a', (n) => n + 1)
+            2
+            ```
+
+            ",
+            |this_obj, args, vm| {
+                let var_args = args[1].read().unwrap();
+                let var_args = var_args.down_to_tuple().unwrap();
+                match var_args.len() {
+                    0 => (),
+                    1 => {
+                        let arg = gen::use_arg!(args, 0);
+                        let key = gen::use_arg_str!(update, key, arg).to_owned();
+                        let update_fn = var_args.get(0).unwrap();
+                        drop(arg);
+                        let current = {
+                            let this = this_obj.read().unwrap();
+                            let this = this.down_to_map().unwrap();
+                            this.get(&key).unwrap_or_else(new::nil)
+                        };
+                        vm.call(update_fn, vec![current])?;
+                        let val = vm.pop_obj()?;
+                        let this = this_obj.read().unwrap();
+                        let this = this.down_to_map().unwrap();
+                        this.insert(key, val.clone());
+                        return Ok(val);
+                    }
+                    n => {
+                        let msg =
+                            format!("update() expected at most 2 args; got {}", n + 1);
+                        return Ok(new::arg_err(msg, new::nil()));
+                    }
+                }
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_map().unwrap();
+                let arg = gen::use_arg!(args, 0);
+                let other = gen::use_arg_map!(update, other, arg);
+                // NOTE: Other entries are copied first in case other is
+                //       this Map.
+                let other_entries = other.entries.read().unwrap().clone();
+                this.entries.write().unwrap().extend(other_entries);
+                Ok(this_obj.clone())
+            }
+        ),
+        gen::meth!(
+            "values",
+            type_ref,
+            &[],
+            "Get Map values as a Tuple.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_map().unwrap();
+                let entries = this.entries.read().unwrap();
+                Ok(new::tuple(entries.values().cloned().collect()))
+            }
+        ),
     ]);
 
     type_ref.clone()
@@ -208,6 +400,21 @@ impl Map {
         }
     }
 
+    /// Remove entry, preserving the order of the remaining entries.
+    pub fn remove(&self, key: &str) -> Option<ObjectRef> {
+        let entries = &mut self.entries.write().unwrap();
+        entries.shift_remove(key)
+    }
+
+    /// Get a snapshot of the Map's entries as (key, value) Tuples.
+    pub fn entry_tuples(&self) -> Vec<ObjectRef> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .iter()
+            .map(|(key, val)| new::tuple(vec![new::str(key), val.clone()]))
+            .collect()
+    }
+
     pub fn contains_key(&self, key: &str) -> bool {
         let entries = self.entries.read().unwrap();
         entries.contains_key(key)
@@ -221,6 +428,15 @@ impl Map {
 impl ObjectTrait for Map {
     gen::object_trait_header!(MAP_TYPE);
 
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(!self.is_empty())
+    }
+
+    /// Check whether the Map has the specified key.
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(member.get_str_val().is_some_and(|key| self.contains_key(key)))
+    }
+
     fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
         if self.is(rhs) || rhs.is_always() {
             return true;
@@ -244,6 +460,24 @@ impl ObjectTrait for Map {
             false
         }
     }
+
+    fn get_item_by_key(&self, key: &str, this: ObjectRef) -> ObjectRef {
+        if let Some(item) = self.get(key) {
+            item
+        } else {
+            new::key_not_found_err(key, this)
+        }
+    }
+
+    fn set_item_by_key(
+        &mut self,
+        key: &str,
+        _this: ObjectRef,
+        value: ObjectRef,
+    ) -> ObjectRef {
+        self.insert(key, value);
+        new::nil()
+    }
 }
 
 // Display -------------------------------------------------------------
@@ -1,5 +1,6 @@
 // Objects
 pub(crate) use base::{ObjectRef, ObjectTrait};
+pub(crate) use conv::{FromObjectRef, IntoObjectRef};
 pub(crate) use map::Map;
 
 // Namespacing
@@ -15,6 +16,7 @@ pub(crate) mod new;
 pub(crate) use result::{Args, Params, ThisOpt};
 
 mod base;
+mod conv;
 mod func_trait;
 
 // Namespace (not a type)
@@ -22,12 +24,19 @@ pub(crate) mod ns;
 
 // Intrinsic Types
 pub(crate) mod always;
+pub(crate) mod atom;
 pub(crate) mod bool;
 pub(crate) mod bound_func;
+pub(crate) mod byte_buffer;
+pub(crate) mod bytes;
 pub(crate) mod cell;
+pub(crate) mod channel;
 pub(crate) mod class;
 pub(crate) mod closure;
 pub(crate) mod custom;
+pub(crate) mod date_time;
+pub(crate) mod deque;
+pub(crate) mod enum_type;
 pub(crate) mod err;
 pub(crate) mod err_type;
 pub(crate) mod file;
@@ -41,9 +50,16 @@ pub(crate) mod list;
 pub(crate) mod map;
 pub(crate) mod module;
 pub(crate) mod nil;
+pub(crate) mod priority_queue;
 pub(crate) mod prop;
+pub(crate) mod range;
+pub(crate) mod response;
 pub(crate) mod result;
 pub(crate) mod seq;
+pub(crate) mod set;
 pub(crate) mod str;
+pub(crate) mod task;
+pub(crate) mod temp_path;
+pub(crate) mod thread;
 pub(crate) mod tuple;
 pub(crate) mod util;
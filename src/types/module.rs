@@ -11,6 +11,7 @@ use super::gen;
 use super::new;
 
 use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::cell::unwrap_cell;
 use super::class::TYPE_TYPE;
 use super::map::Map;
 use super::ns::Namespace;
@@ -131,8 +132,10 @@ impl Module {
         self.ns.insert(name, val.clone());
     }
 
+    /// Get global. Globals that are assigned from functions are stored
+    /// in cells, so their values are unwrapped.
     pub fn get_global(&self, name: &str) -> Option<ObjectRef> {
-        self.ns.get(name)
+        self.ns.get(name).map(unwrap_cell)
     }
 
     pub fn has_global(&self, name: &str) -> bool {
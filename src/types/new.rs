@@ -1,39 +1,67 @@
 //! Type Constructors.
 //!
 //! These constructors simplify the creation of system objects.
+#[cfg(feature = "std-full")]
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+#[cfg(feature = "std-full")]
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 use num_bigint::BigInt;
 use num_traits::{FromPrimitive, Num, Signed, ToPrimitive};
 
+use chrono::FixedOffset;
 use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 
+use crate::modules::registry;
+use crate::scanner::KEYWORDS;
 use crate::util::format_doc;
-use crate::vm::{globals, Code, RuntimeErr};
+use crate::vm::{globals, Code, RuntimeErr, VM};
 
 use super::base::{ObjectRef, ObjectTrait};
 use super::gen::{obj_ref, obj_ref_t, use_arg};
-use super::result::Params;
+use super::result::{Args, CallResult, Params, ThisOpt};
 
+use super::atom::Atom;
 use super::bound_func::BoundFunc;
+use super::byte_buffer::ByteBuffer;
+use super::bytes::Bytes;
 use super::cell::Cell;
+#[cfg(feature = "std-full")]
+use super::channel::Channel;
 use super::closure::Closure;
 use super::custom::{CustomObj, CustomType};
+use super::date_time::DateTime;
+use super::deque::Deque;
+use super::enum_type::{EnumType, EnumVariant};
 use super::err::ErrObj;
 use super::err_type::ErrKind;
 use super::file::File;
+#[cfg(feature = "std-full")]
+use super::file::FileMode;
 use super::float::Float;
 use super::func::Func;
 use super::int::Int;
 use super::intrinsic_func::{IntrinsicFn, IntrinsicFunc};
-use super::iterator::FIIterator;
+use super::iterator::{FIIterator, IteratorSource};
 use super::list::List;
 use super::map::Map;
 use super::module::Module;
 use super::ns::Namespace;
+use super::priority_queue::PriorityQueue;
 use super::prop::Prop;
+use super::range::Range;
+#[cfg(feature = "http")]
+use super::response::Response;
+use super::set::Set;
 use super::str::Str;
+use super::task::Task;
+#[cfg(feature = "std-full")]
+use super::temp_path::TempPath;
+#[cfg(feature = "std-full")]
+use super::thread::{Thread, ThreadResult};
 use super::tuple::Tuple;
 
 // Global singletons ---------------------------------------------------
@@ -64,18 +92,34 @@ pub fn empty_tuple() -> ObjectRef {
 
 // Intrinsic type constructors ---------------------------------
 
+pub fn atom(value: ObjectRef) -> ObjectRef {
+    obj_ref!(Atom::new(value))
+}
+
 pub fn bound_func(func: ObjectRef, this: ObjectRef) -> ObjectRef {
     obj_ref!(BoundFunc::new(func, this))
 }
 
-pub fn intrinsic_func(
+pub fn byte_buffer(value: Vec<u8>) -> ObjectRef {
+    obj_ref!(ByteBuffer::new(value))
+}
+
+pub fn bytes(value: Vec<u8>) -> ObjectRef {
+    obj_ref!(Bytes::new(value))
+}
+
+pub fn intrinsic_func<F>(
     module_name: &str,
     name: &str,
     this_type: Option<ObjectRef>,
     params: &[&str],
     doc: &str,
-    func: IntrinsicFn,
-) -> ObjectRef {
+    func: F,
+) -> ObjectRef
+where
+    F: Fn(ObjectRef, Args, &mut VM) -> CallResult + Send + Sync + 'static,
+{
+    let func: IntrinsicFn = Arc::new(func);
     let params = params.iter().map(|n| n.to_string()).collect();
     let doc = format_doc(doc);
     obj_ref!(IntrinsicFunc::new(
@@ -94,6 +138,7 @@ pub fn intrinsic_module(
     doc: &str,
     entries: &[(&str, ObjectRef)],
 ) -> obj_ref_t!(Module) {
+    registry::register_group(name, entries);
     obj_ref!(Module::with_entries(
         entries,
         name.to_owned(),
@@ -111,22 +156,36 @@ pub fn cell_with_value(value: ObjectRef) -> ObjectRef {
     obj_ref!(Cell::with_value(value))
 }
 
+#[cfg(feature = "std-full")]
+pub fn channel() -> ObjectRef {
+    obj_ref!(Channel::new())
+}
+
 pub fn closure(func: ObjectRef, captured: ObjectRef) -> ObjectRef {
     obj_ref!(Closure::new(func, captured))
 }
 
 // Errors --------------------------------------------------------------
 
+pub fn date_time(value: chrono::DateTime<FixedOffset>) -> ObjectRef {
+    obj_ref!(DateTime::new(value))
+}
+
+pub fn deque(items: Vec<ObjectRef>) -> ObjectRef {
+    obj_ref!(Deque::new(items))
+}
+
 pub fn err<S: Into<String>>(kind: ErrKind, msg: S, obj: ObjectRef) -> ObjectRef {
     obj_ref!(ErrObj::new(kind, msg.into(), obj))
 }
 
-pub fn err_with_responds_to_bool<S: Into<String>>(
+pub fn err_with_data<S: Into<String>>(
     kind: ErrKind,
     msg: S,
     obj: ObjectRef,
+    data: ObjectRef,
 ) -> ObjectRef {
-    obj_ref!(ErrObj::with_responds_to_bool(kind, msg.into(), obj))
+    obj_ref!(ErrObj::new(kind, msg.into(), obj).with_data(data))
 }
 
 pub fn arg_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
@@ -149,21 +208,51 @@ pub fn file_unreadable_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef
     err(ErrKind::FileUnreadable, msg, obj)
 }
 
+pub fn file_unwritable_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
+    err(ErrKind::FileUnwritable, msg, obj)
+}
+
 pub fn index_out_of_bounds_err(index: usize, obj: ObjectRef) -> ObjectRef {
     err(ErrKind::IndexOutOfBounds, index.to_string(), obj)
 }
 
+#[cfg(feature = "http")]
+pub fn http_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
+    err(ErrKind::Http, msg, obj)
+}
+
+#[cfg(feature = "std-min")]
+pub fn json_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
+    err(ErrKind::Json, msg, obj)
+}
+
+pub fn key_not_found_err<S: Into<String>>(key: S, obj: ObjectRef) -> ObjectRef {
+    err(ErrKind::KeyNotFound, key, obj)
+}
+
+#[cfg(feature = "std-full")]
+pub fn proc_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
+    err(ErrKind::Proc, msg, obj)
+}
+
 pub fn string_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
     err(ErrKind::String, msg, obj)
 }
 
+pub fn thread_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
+    err(ErrKind::Thread, msg, obj)
+}
+
+pub fn time_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
+    err(ErrKind::Time, msg, obj)
+}
+
 pub fn type_err<S: Into<String>>(msg: S, obj: ObjectRef) -> ObjectRef {
     err(ErrKind::Type, msg, obj)
 }
 
-static OK_ERR: Lazy<obj_ref_t!(ErrObj)> = Lazy::new(|| {
-    obj_ref!(ErrObj::with_responds_to_bool(ErrKind::Ok, "".to_string(), nil()))
-});
+static OK_ERR: Lazy<obj_ref_t!(ErrObj)> =
+    Lazy::new(|| obj_ref!(ErrObj::new(ErrKind::Ok, "".to_string(), nil())));
 
 pub fn ok_err() -> ObjectRef {
     OK_ERR.clone()
@@ -175,6 +264,11 @@ pub fn file<S: Into<String>>(file_name: S) -> ObjectRef {
     obj_ref!(File::new(file_name.into()))
 }
 
+#[cfg(feature = "std-full")]
+pub fn file_with_mode<S: Into<String>>(file_name: S, mode: FileMode) -> ObjectRef {
+    obj_ref!(File::with_mode(file_name.into(), mode))
+}
+
 pub fn float(value: f64) -> ObjectRef {
     obj_ref!(Float::new(value))
 }
@@ -190,8 +284,9 @@ pub fn func<S: Into<String>>(
     func_name: S,
     params: Params,
     code: Code,
+    is_method: bool,
 ) -> ObjectRef {
-    obj_ref!(Func::new(module_name.into(), func_name.into(), params, code))
+    obj_ref!(Func::new(module_name.into(), func_name.into(), params, code, is_method))
 }
 
 pub fn int<I: Into<BigInt>>(value: I) -> ObjectRef {
@@ -219,6 +314,10 @@ pub fn iterator(wrapped: Vec<ObjectRef>) -> ObjectRef {
     obj_ref!(FIIterator::new(wrapped))
 }
 
+pub fn lazy_iterator(source: IteratorSource) -> ObjectRef {
+    obj_ref!(FIIterator::from_source(source))
+}
+
 pub fn list(items: Vec<ObjectRef>) -> ObjectRef {
     obj_ref!(List::new(items.to_vec()))
 }
@@ -232,10 +331,28 @@ pub fn map_from_keys_and_vals(keys: Vec<String>, vals: Vec<ObjectRef>) -> Object
     obj_ref!(Map::new(IndexMap::from_iter(keys.into_iter().zip(vals))))
 }
 
+#[cfg(feature = "http")]
+pub fn response(
+    url: String,
+    status: u16,
+    headers: IndexMap<String, String>,
+    body: Vec<u8>,
+) -> ObjectRef {
+    obj_ref!(Response::new(url, status, headers, body))
+}
+
+pub fn priority_queue(key_fn: Option<ObjectRef>) -> ObjectRef {
+    obj_ref!(PriorityQueue::new(key_fn))
+}
+
 pub fn prop(getter: ObjectRef) -> ObjectRef {
     obj_ref!(Prop::new(getter))
 }
 
+pub fn range(start: BigInt, stop: BigInt, step: BigInt) -> ObjectRef {
+    obj_ref!(Range::new(start, stop, step))
+}
+
 pub fn str<S: Into<String>>(val: S) -> ObjectRef {
     let val = val.into();
     if val.is_empty() {
@@ -247,6 +364,34 @@ pub fn str<S: Into<String>>(val: S) -> ObjectRef {
     }
 }
 
+pub fn set(items: Vec<ObjectRef>) -> ObjectRef {
+    obj_ref!(Set::new(items))
+}
+
+pub fn task(
+    name: &str,
+    func: ObjectRef,
+    this_opt: ThisOpt,
+    args: Args,
+    closure: Option<ObjectRef>,
+) -> ObjectRef {
+    obj_ref!(Task::new_call(name, func, this_opt, args, closure))
+}
+
+pub fn sleep_task(duration: Duration) -> ObjectRef {
+    obj_ref!(Task::new_sleep(duration))
+}
+
+#[cfg(feature = "std-full")]
+pub fn temp_path(path: PathBuf, is_dir: bool) -> ObjectRef {
+    obj_ref!(TempPath::new(path, is_dir))
+}
+
+#[cfg(feature = "std-full")]
+pub fn thread(handle: JoinHandle<ThreadResult>) -> ObjectRef {
+    obj_ref!(Thread::new(handle))
+}
+
 pub fn tuple(items: Vec<ObjectRef>) -> ObjectRef {
     if items.is_empty() {
         globals::EMPTY_TUPLE.clone()
@@ -259,10 +404,19 @@ pub fn argv_tuple(argv: &[String]) -> ObjectRef {
     obj_ref!(Tuple::new(argv.iter().map(str).collect()))
 }
 
-// Custom type constructor ---------------------------------------------
+/// Create a tuple of all keywords, sorted alphabetically.
+pub fn keywords_tuple() -> ObjectRef {
+    let mut keywords: Vec<&str> = KEYWORDS.keys().copied().collect();
+    keywords.sort_unstable();
+    obj_ref!(Tuple::new(keywords.into_iter().map(str).collect()))
+}
+
+// Custom type constructors --------------------------------------------
 
+/// Create a custom type whose `new` method takes a map of attributes.
+/// This is used for types created at runtime via `new_type()`.
 pub fn custom_type(module: ObjectRef, name: &str) -> ObjectRef {
-    let class_ref = obj_ref!(CustomType::new(module.clone(), name.to_owned()));
+    let class_ref = CustomType::new(module.clone(), name.to_owned(), vec![]);
 
     {
         let mut class = class_ref.write().unwrap();
@@ -288,31 +442,120 @@ pub fn custom_type(module: ObjectRef, name: &str) -> ObjectRef {
                     let attrs = attrs_arg.down_to_map().unwrap();
                     let mut ns = Namespace::default();
                     ns.extend_from_map(attrs);
+                    let type_obj = custom_type_ref(this);
+                    Ok(obj_ref!(CustomObj::new(type_obj, ns)))
+                },
+            ),
+        );
+    }
+
+    class_ref
+}
+
+/// Create a custom type with declared fields. The type's `new` method
+/// takes the field values as positional args. This is used for types
+/// declared in code.
+pub fn custom_type_with_fields(
+    module: ObjectRef,
+    name: &str,
+    fields: Params,
+) -> ObjectRef {
+    let class_ref = CustomType::new(module.clone(), name.to_owned(), fields.clone());
+    let params: Vec<&str> = fields.iter().map(|n| n.as_str()).collect();
+
+    {
+        let mut class = class_ref.write().unwrap();
+        let ns = class.ns_mut();
+        ns.insert(
+            "new",
+            intrinsic_func(
+                module.read().unwrap().down_to_mod().unwrap().name(),
+                name,
+                Some(class_ref.clone()),
+                &params,
+                "Create a new instance of a custom type.
+
+                # Args
+
+                - ...: Field values in declaration order
 
-                    // XXX: Cloning the inner object is wonky and breaks
-                    //      identity testing.
-                    let type_obj = this.read().unwrap();
-                    let type_obj = if type_obj.is_type_object() {
-                        // Called via custom type.
-                        let type_obj = type_obj.down_to_custom_type().unwrap();
-                        obj_ref!(type_obj.clone())
+                ",
+                |this, args, _| {
+                    let type_obj = custom_type_ref(this);
+                    let mut ns = Namespace::default();
+                    {
+                        let class = type_obj.read().unwrap();
+                        for (name, arg) in class.fields().iter().zip(args) {
+                            ns.insert(name, arg);
+                        }
+                    }
+                    Ok(obj_ref!(CustomObj::new(type_obj, ns)))
+                },
+            ),
+        );
+    }
+
+    class_ref
+}
+
+/// Create an enum type with the specified variants. Each variant is a
+/// singleton that's accessible as an attribute of the type.
+pub fn enum_type(module: ObjectRef, name: &str, variants: &[String]) -> ObjectRef {
+    let module_name = module.read().unwrap().down_to_mod().unwrap().name().to_owned();
+    let type_ref = obj_ref!(EnumType::new(module, name.to_owned()));
+
+    {
+        let mut type_obj = type_ref.write().unwrap();
+        for (ordinal, variant_name) in variants.iter().enumerate() {
+            let variant =
+                EnumVariant::new(type_ref.clone(), variant_name.clone(), ordinal);
+            type_obj.add_variant(obj_ref!(variant));
+        }
+        type_obj.ns_mut().insert(
+            "values",
+            intrinsic_func(
+                module_name.as_str(),
+                "values",
+                Some(type_ref.clone()),
+                &[],
+                "Get the variants of the enum in declaration order.
+
+                # Returns
+
+                Tuple
+
+                ",
+                |this, _, _| {
+                    let this = this.read().unwrap();
+                    let variants = if this.is_type_object() {
+                        // Called via enum type.
+                        this.down_to_enum_type().unwrap().variants().to_vec()
                     } else {
-                        // Called via custom instance.
-                        // XXX: This branch isn't reachable because the
-                        //      VM will panic due to the identity test
-                        //      issue noted above.
-                        let type_obj = type_obj.type_obj();
+                        // Called via enum variant.
+                        let type_obj = this.type_obj();
                         let type_obj = type_obj.read().unwrap();
-                        let type_obj = type_obj.down_to_custom_type().unwrap();
-                        obj_ref!(type_obj.clone())
+                        type_obj.down_to_enum_type().unwrap().variants().to_vec()
                     };
-
-                    let instance = CustomObj::new(type_obj, ns);
-                    Ok(obj_ref!(instance))
+                    Ok(tuple(variants))
                 },
             ),
         );
     }
 
-    class_ref
+    type_ref
+}
+
+/// Get the custom type for `this`, which may be either a custom type
+/// or an instance of a custom type.
+fn custom_type_ref(this: ObjectRef) -> obj_ref_t!(CustomType) {
+    let this = this.read().unwrap();
+    if this.is_type_object() {
+        // Called via custom type.
+        this.down_to_custom_type().unwrap().type_ref()
+    } else {
+        // Called via custom instance.
+        let type_obj = this.type_obj();
+        let type_obj = type_obj.read().unwrap();
+        type_obj.down_to_custom_type().unwrap().type_ref()
+    }
 }
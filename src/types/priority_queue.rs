@@ -0,0 +1,324 @@
+//! Priority queues backed by a binary min-heap. Items are ordered by
+//! key, which is the item itself unless a key function is specified.
+//! Items with equal keys are popped in the order they were pushed.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::types::IntoObjectRef;
+use crate::vm::{RuntimeBoolResult, RuntimeErr};
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// PriorityQueue Type --------------------------------------------------
+
+static DOC: &str = "
+Intrinsic PriorityQueue type
+
+A queue that always pops its smallest item first. Items are compared
+with `<`, or, if a key function is specified, their keys are.
+
+```
+→ from std.collections import PriorityQueue
+→ q = PriorityQueue.new((n) => -n)
+→ q.push(1)
+→ q.push(3)
+→ q.pop_min()
+3
+```
+";
+
+gen::type_and_impls!(PriorityQueueType, PriorityQueue);
+
+pub static PRIORITY_QUEUE_TYPE: Lazy<gen::obj_ref_t!(PriorityQueueType)> =
+    Lazy::new(|| {
+        let type_ref = gen::obj_ref!(PriorityQueueType::new());
+        let mut type_obj = type_ref.write().unwrap();
+
+        type_obj.add_attrs(&[
+            ("$doc", new::str(DOC)),
+            // Class Methods -------------------------------------------
+            gen::meth!(
+                "new",
+                type_ref,
+                &[""],
+                "Create an empty PriorityQueue.
+
+                # Args
+
+                - key?: Func
+
+                  A function that will be passed each item when it's
+                  pushed and returns the key the item is ordered by. By
+                  default, items are their own keys.
+
+                ",
+                |_, args, _| {
+                    let var_args = args[0].read().unwrap();
+                    let var_args = var_args.down_to_tuple().unwrap();
+                    let key_fn = match var_args.len() {
+                        0 => None,
+                        1 => var_args.get(0),
+                        n => {
+                            let msg = format!(
+                                "PriorityQueue.new() expected at most 1 arg; got {n}"
+                            );
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                    };
+                    if let Some(key_fn) = &key_fn {
+                        let key_fn = key_fn.read().unwrap();
+                        if !(key_fn.is_func()
+                            || key_fn.is_closure()
+                            || key_fn.is_bound_func()
+                            || key_fn.is_intrinsic_func())
+                        {
+                            let msg = "PriorityQueue.new() expected key to be a \
+                                function";
+                            return Ok(new::arg_err(msg, new::nil()));
+                        }
+                    }
+                    Ok(new::priority_queue(key_fn))
+                }
+            ),
+            // Instance Attributes -------------------------------------
+            gen::prop!("length", type_ref, "", |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_priority_queue().unwrap();
+                Ok(new::int(this.len()))
+            }),
+            gen::prop!("is_empty", type_ref, "", |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_priority_queue().unwrap();
+                Ok(new::bool(this.len() == 0))
+            }),
+            // Instance Methods ----------------------------------------
+            gen::meth!(
+                "push",
+                type_ref,
+                &["item"],
+                "Push item and return it.
+
+                An error is returned if the item's key can't be compared
+                with the keys of the items already in the queue.
+
+                ",
+                |this_ref, args, vm| {
+                    let item = args[0].clone();
+                    let key_fn = {
+                        let this = this_ref.read().unwrap();
+                        this.down_to_priority_queue().unwrap().key_fn.clone()
+                    };
+                    let key = if let Some(key_fn) = key_fn {
+                        vm.call(key_fn, vec![item.clone()])?;
+                        vm.pop_obj()?
+                    } else {
+                        item.clone()
+                    };
+                    let this = this_ref.read().unwrap();
+                    let this = this.down_to_priority_queue().unwrap();
+                    this.push(key, item.clone())?;
+                    Ok(item)
+                }
+            ),
+            gen::meth!(
+                "pop_min",
+                type_ref,
+                &[],
+                "Remove and return the smallest item or nil if empty.",
+                |this, _, _| {
+                    let this = this.read().unwrap();
+                    let this = this.down_to_priority_queue().unwrap();
+                    Ok(this.pop_min()?.into_obj_ref())
+                }
+            ),
+            gen::meth!(
+                "peek",
+                type_ref,
+                &[],
+                "Get the smallest item without removing it or nil if empty.",
+                |this, _, _| {
+                    let this = this.read().unwrap();
+                    let this = this.down_to_priority_queue().unwrap();
+                    Ok(this.peek().into_obj_ref())
+                }
+            ),
+            gen::meth!("clear", type_ref, &[], "Remove all items.", |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_priority_queue().unwrap();
+                this.heap.lock().unwrap().clear();
+                Ok(new::nil())
+            }),
+        ]);
+
+        type_ref.clone()
+    });
+
+// PriorityQueue Object ------------------------------------------------
+
+pub struct PriorityQueue {
+    ns: Namespace,
+    key_fn: Option<ObjectRef>,
+    heap: Mutex<Heap>,
+}
+
+gen::standard_object_impls!(PriorityQueue);
+
+impl PriorityQueue {
+    pub fn new(key_fn: Option<ObjectRef>) -> Self {
+        Self { ns: Namespace::default(), key_fn, heap: Mutex::new(Heap::default()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.lock().unwrap().entries.len()
+    }
+
+    pub fn push(&self, key: ObjectRef, item: ObjectRef) -> Result<(), RuntimeErr> {
+        self.heap.lock().unwrap().push(key, item)
+    }
+
+    pub fn pop_min(&self) -> Result<Option<ObjectRef>, RuntimeErr> {
+        self.heap.lock().unwrap().pop_min()
+    }
+
+    pub fn peek(&self) -> Option<ObjectRef> {
+        let heap = self.heap.lock().unwrap();
+        heap.entries.first().map(|entry| entry.item.clone())
+    }
+}
+
+impl ObjectTrait for PriorityQueue {
+    gen::object_trait_header!(PRIORITY_QUEUE_TYPE);
+
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(self.len() > 0)
+    }
+}
+
+// Heap ----------------------------------------------------------------
+
+struct Entry {
+    key: ObjectRef,
+    // Order in which the entry was pushed, used to break ties between
+    // equal keys.
+    seq: usize,
+    item: ObjectRef,
+}
+
+impl Entry {
+    /// NOTE: Comparing keys can fail (e.g., when comparing an Int to a
+    ///       Str), so this is fallible and `BinaryHeap` can't be used.
+    fn less_than(&self, other: &Entry) -> Result<bool, RuntimeErr> {
+        let a = self.key.read().unwrap();
+        let b = other.key.read().unwrap();
+        if a.less_than(&*b)? {
+            Ok(true)
+        } else if b.less_than(&*a)? {
+            Ok(false)
+        } else {
+            Ok(self.seq < other.seq)
+        }
+    }
+}
+
+/// Binary min-heap. When an entry is pushed or popped, the position
+/// it will end up at is found *before* any entries are moved so that
+/// the heap is left intact if a comparison fails.
+#[derive(Default)]
+struct Heap {
+    entries: Vec<Entry>,
+    next_seq: usize,
+}
+
+impl Heap {
+    fn push(&mut self, key: ObjectRef, item: ObjectRef) -> Result<(), RuntimeErr> {
+        let entry = Entry { key, seq: self.next_seq, item };
+        let mut pos = self.entries.len();
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if !entry.less_than(&self.entries[parent])? {
+                break;
+            }
+            pos = parent;
+        }
+        self.next_seq += 1;
+        self.entries.push(entry);
+        // Move the new entry up to its position, moving its ancestors
+        // down along the way.
+        let mut i = self.entries.len() - 1;
+        while i > pos {
+            let parent = (i - 1) / 2;
+            self.entries.swap(i, parent);
+            i = parent;
+        }
+        Ok(())
+    }
+
+    fn pop_min(&mut self) -> Result<Option<ObjectRef>, RuntimeErr> {
+        if self.entries.is_empty() {
+            return Ok(None);
+        }
+        // The last entry replaces the root and sinks to its position.
+        // Only entries before it will remain in the heap.
+        let last = self.entries.len() - 1;
+        let mut pos = 0;
+        loop {
+            let left = 2 * pos + 1;
+            if left >= last {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < last
+                && self.entries[right].less_than(&self.entries[left])?
+            {
+                right
+            } else {
+                left
+            };
+            if !self.entries[child].less_than(&self.entries[last])? {
+                break;
+            }
+            pos = child;
+        }
+        let root = self.entries.swap_remove(0);
+        // Move the former last entry down to its position, moving the
+        // entries on the path up along the way.
+        let mut path = vec![];
+        let mut i = pos;
+        while i > 0 {
+            path.push(i);
+            i = (i - 1) / 2;
+        }
+        let mut i = 0;
+        for child in path.into_iter().rev() {
+            self.entries.swap(i, child);
+            i = child;
+        }
+        Ok(Some(root.item))
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for PriorityQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<priority queue length={}>", self.len())
+    }
+}
+
+impl fmt::Debug for PriorityQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
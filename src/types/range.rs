@@ -0,0 +1,272 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+use once_cell::sync::Lazy;
+
+use crate::vm::{RuntimeBoolResult, RuntimeErr};
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::iterator::IteratorSource;
+use super::ns::Namespace;
+use super::seq;
+
+// Range Type ----------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Range type
+
+Ranges are created with `start..stop` (exclusive) or `start..=stop`
+(inclusive) or with `Range.new(start, stop, step)`.
+";
+
+gen::type_and_impls!(RangeType, Range);
+
+pub static RANGE_TYPE: Lazy<gen::obj_ref_t!(RangeType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(RangeType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Class Methods -----------------------------------------------
+        gen::meth!(
+            "new",
+            type_ref,
+            &["start", "stop", "step"],
+            "Create a Range from start (inclusive) to stop (exclusive).",
+            |_, args, _| {
+                let mut bounds = vec![];
+                for (arg, name) in args.iter().zip(["start", "stop", "step"]) {
+                    let arg = arg.read().unwrap();
+                    if let Some(val) = arg.get_int_val() {
+                        bounds.push(val.clone());
+                    } else {
+                        let msg = format!("Range.new() expected {name} to be an Int");
+                        return Ok(new::arg_err(msg, new::nil()));
+                    }
+                }
+                let step = bounds.pop().unwrap();
+                let stop = bounds.pop().unwrap();
+                let start = bounds.pop().unwrap();
+                if step.is_zero() {
+                    let msg = "Range.new() expected step to be non-zero";
+                    return Ok(new::arg_err(msg, new::nil()));
+                }
+                Ok(new::range(start, stop, step))
+            }
+        ),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("start", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_range().unwrap();
+            Ok(new::int(this.start.clone()))
+        }),
+        gen::prop!("stop", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_range().unwrap();
+            Ok(new::int(this.stop.clone()))
+        }),
+        gen::prop!("step", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_range().unwrap();
+            Ok(new::int(this.step.clone()))
+        }),
+        gen::prop!("length", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_range().unwrap();
+            Ok(new::int(this.len()))
+        }),
+        gen::prop!("is_empty", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_range().unwrap();
+            Ok(new::bool(this.len().is_zero()))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!(
+            "each",
+            type_ref,
+            &["each_fn"],
+            "Apply function to each Int in Range.
+
+            # Args
+
+            - func: Func
+
+              A function that will be passed each item in turn and, optionally, the
+              index of the item.
+
+            ",
+            |this_obj, args, vm| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_range().unwrap();
+                seq::each(&this_obj, &this.to_vec(), &args, vm)
+            }
+        ),
+        gen::meth!("has", type_ref, &["member"], "", |this, args, _| {
+            let this = this.read().unwrap();
+            let member = gen::use_arg!(args, 0);
+            Ok(new::bool(this.contains(&*member)?))
+        }),
+        gen::meth!("iter", type_ref, &[], "", |this_ref, _, _| {
+            let this = this_ref.read().unwrap();
+            let this = this.down_to_range().unwrap();
+            Ok(new::lazy_iterator(this.source()))
+        }),
+        gen::meth!("map", type_ref, &["map_fn"], "", |this_obj, args, vm| {
+            let this = this_obj.read().unwrap();
+            let this = this.down_to_range().unwrap();
+            seq::map(&this_obj, &this.to_vec(), &args, vm)
+        }),
+    ]);
+
+    type_ref.clone()
+});
+
+// Range Object --------------------------------------------------------
+
+/// Ranges are always stored with an exclusive stop (e.g., `1..=10` is
+/// stored as `1..11`). The step must be non-zero.
+pub struct Range {
+    ns: Namespace,
+    start: BigInt,
+    stop: BigInt,
+    step: BigInt,
+}
+
+gen::standard_object_impls!(Range);
+
+impl Range {
+    pub fn new(start: BigInt, stop: BigInt, step: BigInt) -> Self {
+        assert!(!step.is_zero(), "Range step must be non-zero");
+        Self { ns: Namespace::default(), start, stop, step }
+    }
+
+    pub fn len(&self) -> BigInt {
+        let span = if self.step.is_positive() {
+            &self.stop - &self.start
+        } else {
+            &self.start - &self.stop
+        };
+        if span.is_positive() {
+            let step = self.step.abs();
+            (span + &step - BigInt::one()) / step
+        } else {
+            BigInt::zero()
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<BigInt> {
+        if BigInt::from(index) < self.len() {
+            Some(&self.start + &self.step * index)
+        } else {
+            None
+        }
+    }
+
+    pub fn has(&self, value: &BigInt) -> bool {
+        let in_bounds = if self.step.is_positive() {
+            &self.start <= value && value < &self.stop
+        } else {
+            &self.stop < value && value <= &self.start
+        };
+        in_bounds && ((value - &self.start) % &self.step).is_zero()
+    }
+
+    /// Lazily produce the Ints in the Range.
+    pub fn source(&self) -> IteratorSource {
+        let mut current = self.start.clone();
+        let stop = self.stop.clone();
+        let step = self.step.clone();
+        let ascending = step.is_positive();
+        Box::new(std::iter::from_fn(move || {
+            let done = if ascending { current >= stop } else { current <= stop };
+            if done {
+                None
+            } else {
+                let item = new::int(current.clone());
+                current += &step;
+                Some(item)
+            }
+        }))
+    }
+
+    pub fn to_vec(&self) -> Vec<ObjectRef> {
+        self.source().collect()
+    }
+}
+
+impl ObjectTrait for Range {
+    gen::object_trait_header!(RANGE_TYPE);
+
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(!self.len().is_zero())
+    }
+
+    fn get_item(&self, index: usize, this: ObjectRef) -> ObjectRef {
+        if let Some(item) = self.get(index) {
+            new::int(item)
+        } else {
+            self.index_out_of_bounds(index, this)
+        }
+    }
+
+    fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
+        if self.is(rhs) || rhs.is_always() {
+            true
+        } else if let Some(rhs) = rhs.down_to_range() {
+            self.start == rhs.start && self.stop == rhs.stop && self.step == rhs.step
+        } else {
+            false
+        }
+    }
+
+    /// Only Ints can be members of a Range.
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(member.get_int_val().is_some_and(|value| self.has(value)))
+    }
+}
+
+/// Make a Range from the operands of `a..b` or `a..=b`, which must be
+/// Ints.
+pub fn from_operands(
+    a: &dyn ObjectTrait,
+    b: &dyn ObjectTrait,
+    inclusive: bool,
+) -> Result<ObjectRef, RuntimeErr> {
+    if let (Some(start), Some(stop)) = (a.get_int_val(), b.get_int_val()) {
+        let stop = if inclusive { stop + BigInt::one() } else { stop.clone() };
+        Ok(new::range(start.clone(), stop, BigInt::one()))
+    } else {
+        let op = if inclusive { "..=" } else { ".." };
+        Err(RuntimeErr::type_err(format!(
+            "Range bounds must be Ints: {} {op} {}",
+            a.class().read().unwrap(),
+            b.class().read().unwrap()
+        )))
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.step.is_one() {
+            write!(f, "{}..{}", self.start, self.stop)
+        } else {
+            write!(f, "Range.new({}, {}, {})", self.start, self.stop, self.step)
+        }
+    }
+}
+
+impl fmt::Debug for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -0,0 +1,131 @@
+//! HTTP responses returned by the request functions in `std.http`. The
+//! whole body is read before the response is returned, so responses
+//! are plain data and can be inspected any number of times.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// Response Type -------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Response type
+
+Responses are returned by `std.http.get()`, `std.http.post()`, and
+`std.http.request()`. A Response is returned for any status code, so
+check `ok` or `status` to see whether the request succeeded.
+";
+
+gen::type_and_impls!(ResponseType, Response);
+
+pub static RESPONSE_TYPE: Lazy<gen::obj_ref_t!(ResponseType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(ResponseType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("url", type_ref, "Final URL after redirects", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_response().unwrap();
+            Ok(new::str(&this.url))
+        }),
+        gen::prop!("status", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_response().unwrap();
+            Ok(new::int(this.status))
+        }),
+        gen::prop!("ok", type_ref, "Whether status is 2xx", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_response().unwrap();
+            Ok(new::bool(this.is_ok()))
+        }),
+        gen::prop!(
+            "headers",
+            type_ref,
+            "Map of lowercased header name to value. Values of repeated
+            headers are joined with commas.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_response().unwrap();
+                let headers: IndexMap<String, ObjectRef> = this
+                    .headers
+                    .iter()
+                    .map(|(name, val)| (name.clone(), new::str(val)))
+                    .collect();
+                Ok(new::map(headers))
+            }
+        ),
+        gen::prop!("body", type_ref, "Body as Bytes", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_response().unwrap();
+            Ok(new::bytes(this.body.clone()))
+        }),
+        gen::prop!(
+            "text",
+            type_ref,
+            "Body as Str. Invalid UTF-8 sequences are replaced.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_response().unwrap();
+                Ok(new::str(String::from_utf8_lossy(&this.body)))
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Response Object -----------------------------------------------------
+
+pub struct Response {
+    ns: Namespace,
+    url: String,
+    status: u16,
+    headers: IndexMap<String, String>,
+    body: Vec<u8>,
+}
+
+gen::standard_object_impls!(Response);
+
+impl Response {
+    pub fn new(
+        url: String,
+        status: u16,
+        headers: IndexMap<String, String>,
+        body: Vec<u8>,
+    ) -> Self {
+        Self { ns: Namespace::default(), url, status, headers, body }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+impl ObjectTrait for Response {
+    gen::object_trait_header!(RESPONSE_TYPE);
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<response {} {}>", self.status, self.url)
+    }
+}
+
+impl fmt::Debug for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<response {} {} ({} bytes)>", self.status, self.url, self.body.len())
+    }
+}
@@ -1,6 +1,8 @@
 //! Common sequence operations
+use std::cmp::Ordering;
 
 use num_bigint::BigInt;
+use num_traits::Zero;
 
 use crate::vm::{RuntimeErr, RuntimeObjResult, VM};
 
@@ -45,6 +47,44 @@ pub fn each(
     Ok(new::nil())
 }
 
+/// Get the number of args to pass to a func that's called with each
+/// item and, optionally, the index of the item. If `func` isn't a
+/// function, `None` is returned.
+fn item_func_n_args(func: &ObjectRef) -> Option<usize> {
+    let func = func.read().unwrap();
+    let func = func.as_func()?;
+    Some(if func.has_var_args() { 2 } else { func.arity() })
+}
+
+pub fn filter(
+    this: &ObjectRef,
+    items: &[ObjectRef],
+    args: &Args,
+    vm: &mut VM,
+) -> RuntimeObjResult {
+    let filter_fn = &args[0];
+    let n_args = match item_func_n_args(filter_fn) {
+        Some(n_args) => n_args,
+        None => return Ok(new::arg_err("filter/1 expects a function", this.clone())),
+    };
+
+    let mut results = vec![];
+    for (i, item) in items.iter().enumerate() {
+        let filter = filter_fn.clone();
+        if n_args == 1 {
+            vm.call(filter, vec![item.clone()])?;
+        } else {
+            vm.call(filter, vec![item.clone(), new::int(i)])?;
+        }
+        let keep = vm.pop_obj()?;
+        if keep.read().unwrap().bool_val()? {
+            results.push(item.clone());
+        }
+    }
+
+    Ok(new::tuple(results))
+}
+
 pub fn has(items: &[ObjectRef], args: &Args) -> RuntimeObjResult {
     if items.is_empty() {
         return Ok(new::bool(false));
@@ -58,6 +98,16 @@ pub fn has(items: &[ObjectRef], args: &Args) -> RuntimeObjResult {
     Ok(new::bool(false))
 }
 
+pub fn index_of(items: &[ObjectRef], args: &Args) -> RuntimeObjResult {
+    let member = use_arg!(args, 0);
+    for (i, item) in items.iter().enumerate() {
+        if member.is_equal(&*item.read().unwrap()) {
+            return Ok(new::int(i));
+        }
+    }
+    Ok(new::nil())
+}
+
 pub fn join(items: &[ObjectRef], args: &Args) -> RuntimeObjResult {
     if items.is_empty() {
         return Ok(new::empty_str());
@@ -121,6 +171,103 @@ pub fn map(
     Ok(new::tuple(results))
 }
 
+pub fn reduce(
+    this: &ObjectRef,
+    items: &[ObjectRef],
+    args: &Args,
+    vm: &mut VM,
+) -> RuntimeObjResult {
+    let reduce_fn = &args[0];
+    if reduce_fn.read().unwrap().as_func().is_none() {
+        return Ok(new::arg_err("reduce/2 expects a function", this.clone()));
+    }
+
+    let mut acc = args[1].clone();
+    for item in items.iter() {
+        vm.call(reduce_fn.clone(), vec![acc, item.clone()])?;
+        acc = vm.pop_obj()?;
+    }
+
+    Ok(acc)
+}
+
+/// Sort items using `cmp_fn`, which is passed two items and must
+/// return a negative Int, 0, or a positive Int. If `cmp_fn` isn't
+/// specified, items are compared using `<`. The sort is stable.
+pub fn sort(
+    items: Vec<ObjectRef>,
+    cmp_fn: Option<&ObjectRef>,
+    vm: &mut VM,
+) -> Result<Vec<ObjectRef>, RuntimeErr> {
+    merge_sort(items, &mut |a: &ObjectRef, b: &ObjectRef| compare(a, b, cmp_fn, vm))
+}
+
+fn compare(
+    a: &ObjectRef,
+    b: &ObjectRef,
+    cmp_fn: Option<&ObjectRef>,
+    vm: &mut VM,
+) -> Result<Ordering, RuntimeErr> {
+    if let Some(cmp_fn) = cmp_fn {
+        vm.call(cmp_fn.clone(), vec![a.clone(), b.clone()])?;
+        let result = vm.pop_obj()?;
+        let result = result.read().unwrap();
+        if let Some(val) = result.get_int_val() {
+            Ok(val.cmp(&BigInt::zero()))
+        } else {
+            Err(RuntimeErr::type_err(format!(
+                "sort() expected comparison func to return an Int; got {}",
+                result.class().read().unwrap()
+            )))
+        }
+    } else {
+        let a = a.read().unwrap();
+        let b = b.read().unwrap();
+        if a.less_than(&*b)? {
+            Ok(Ordering::Less)
+        } else if b.less_than(&*a)? {
+            Ok(Ordering::Greater)
+        } else {
+            Ok(Ordering::Equal)
+        }
+    }
+}
+
+/// NOTE: `slice::sort_by()` isn't used because comparisons can fail
+///       and comparison funcs defined in scripts aren't guaranteed to
+///       be consistent.
+fn merge_sort<F>(
+    mut items: Vec<ObjectRef>,
+    cmp: &mut F,
+) -> Result<Vec<ObjectRef>, RuntimeErr>
+where
+    F: FnMut(&ObjectRef, &ObjectRef) -> Result<Ordering, RuntimeErr>,
+{
+    if items.len() < 2 {
+        return Ok(items);
+    }
+
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, cmp)?;
+    let right = merge_sort(right, cmp)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Take from the left on ties to keep the sort stable
+        if cmp(b, a)? == Ordering::Less {
+            merged.push(right.next().unwrap());
+        } else {
+            merged.push(left.next().unwrap());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+
+    Ok(merged)
+}
+
 pub fn sum(items: &[ObjectRef]) -> RuntimeObjResult {
     let mut sum = new::int(BigInt::from(0));
     for item in items.iter() {
@@ -0,0 +1,304 @@
+//! Sets are unordered collections of unique items. Items are compared
+//! using equality (like `==`), so membership checks are linear in the
+//! size of the set. Items are kept in insertion order.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::vm::{RuntimeBoolResult, RuntimeErr, RuntimeObjResult};
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+use super::seq;
+
+// Set Type ------------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Set type
+
+Sets are created with `{a, b, ...}` or with `Set.new(items)`. Note that
+`{}` is an empty Map, so use `Set.new([])` to create an empty Set.
+";
+
+gen::type_and_impls!(SetType, Set);
+
+pub static SET_TYPE: Lazy<gen::obj_ref_t!(SetType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(SetType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Class Methods -----------------------------------------------
+        gen::meth!(
+            "new",
+            type_ref,
+            &["items"],
+            "Create a Set from the items in a List, Tuple, Range, or Set.",
+            |_, args, _| {
+                let arg = gen::use_arg!(args, 0);
+                let items = if let Some(list) = arg.down_to_list() {
+                    list.to_vec()
+                } else if let Some(tuple) = arg.down_to_tuple() {
+                    tuple.iter().cloned().collect()
+                } else if let Some(range) = arg.down_to_range() {
+                    range.to_vec()
+                } else if let Some(set) = arg.down_to_set() {
+                    set.to_vec()
+                } else {
+                    let msg = format!(
+                        "Set.new() expected items to be a List, Tuple, Range, or Set; \
+                        got {}",
+                        arg.class().read().unwrap()
+                    );
+                    return Ok(new::arg_err(msg, new::nil()));
+                };
+                Ok(new::set(items))
+            }
+        ),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("length", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_set().unwrap();
+            Ok(new::int(this.len()))
+        }),
+        gen::prop!("is_empty", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_set().unwrap();
+            Ok(new::bool(this.len() == 0))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!(
+            "add",
+            type_ref,
+            &["item"],
+            "Add item to Set.
+
+            # Returns
+
+            Bool: true if the item was added or false if it was already
+            present
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_set().unwrap();
+                Ok(new::bool(this.add(args[0].clone())))
+            }
+        ),
+        gen::meth!(
+            "each",
+            type_ref,
+            &["each_fn"],
+            "Apply function to each Set item.
+
+            # Args
+
+            - func: Func
+
+              A function that will be passed each item in turn and, optionally, the
+              index of the item.
+
+            ",
+            |this_obj, args, vm| {
+                let this = this_obj.read().unwrap();
+                let this = this.down_to_set().unwrap();
+                seq::each(&this_obj, &this.to_vec(), &args, vm)
+            }
+        ),
+        gen::meth!("has", type_ref, &["member"], "", |this, args, _| {
+            let this = this.read().unwrap();
+            let member = gen::use_arg!(args, 0);
+            Ok(new::bool(this.contains(&*member)?))
+        }),
+        gen::meth!("iter", type_ref, &[], "", |this_ref, _, _| {
+            let this = this_ref.read().unwrap();
+            let this = this.down_to_set().unwrap();
+            Ok(new::iterator(this.to_vec()))
+        }),
+        gen::meth!(
+            "remove",
+            type_ref,
+            &["item"],
+            "Remove item from Set.
+
+            # Returns
+
+            Bool: true if the item was removed or false if it wasn't present
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_set().unwrap();
+                let item = gen::use_arg!(args, 0);
+                Ok(new::bool(this.remove(&*item)))
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Set Object ----------------------------------------------------------
+
+pub struct Set {
+    ns: Namespace,
+    items: RwLock<Vec<ObjectRef>>,
+}
+
+gen::standard_object_impls!(Set);
+
+impl Set {
+    /// Duplicate items are dropped, keeping the first occurrence.
+    pub fn new(items: Vec<ObjectRef>) -> Self {
+        let mut unique: Vec<ObjectRef> = Vec::with_capacity(items.len());
+        for item in items {
+            if find(&unique, &*item.read().unwrap()).is_none() {
+                unique.push(item);
+            }
+        }
+        Self { ns: Namespace::default(), items: RwLock::new(unique) }
+    }
+
+    pub fn len(&self) -> usize {
+        let items = self.items.read().unwrap();
+        items.len()
+    }
+
+    /// Get a snapshot of the set's current items.
+    pub fn to_vec(&self) -> Vec<ObjectRef> {
+        let items = self.items.read().unwrap();
+        items.clone()
+    }
+
+    pub fn has(&self, member: &dyn ObjectTrait) -> bool {
+        let items = self.items.read().unwrap();
+        find(&items, member).is_some()
+    }
+
+    /// Add item if it's not already present. Returns `true` if the
+    /// item was added.
+    pub fn add(&self, item: ObjectRef) -> bool {
+        let items = &mut self.items.write().unwrap();
+        if find(items, &*item.read().unwrap()).is_some() {
+            return false;
+        }
+        items.push(item);
+        true
+    }
+
+    /// Remove item if it's present. Returns `true` if the item was
+    /// removed.
+    pub fn remove(&self, item: &dyn ObjectTrait) -> bool {
+        let items = &mut self.items.write().unwrap();
+        if let Some(index) = find(items, item) {
+            items.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the items that are in `rhs` (when `keep` is `true`) or that
+    /// aren't in `rhs` (when `keep` is `false`).
+    fn filter_by(&self, rhs: &Set, keep: bool) -> Vec<ObjectRef> {
+        self.to_vec()
+            .into_iter()
+            .filter(|item| rhs.has(&*item.read().unwrap()) == keep)
+            .collect()
+    }
+}
+
+/// Get RHS of set operation, which must also be a Set.
+fn rhs_set<'a>(rhs: &'a dyn ObjectTrait, op: &str) -> Result<&'a Set, RuntimeErr> {
+    rhs.down_to_set().ok_or_else(|| {
+        RuntimeErr::type_err(format!(
+            "Binary operator {op} expected a Set; got {}",
+            rhs.class().read().unwrap()
+        ))
+    })
+}
+
+/// Find the index of the item equal to `member`.
+fn find(items: &[ObjectRef], member: &dyn ObjectTrait) -> Option<usize> {
+    items.iter().position(|item| member.is_equal(&*item.read().unwrap()))
+}
+
+impl ObjectTrait for Set {
+    gen::object_trait_header!(SET_TYPE);
+
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(self.len() > 0)
+    }
+
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(self.has(member))
+    }
+
+    /// Sets are equal when they have the same items, regardless of
+    /// order.
+    fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
+        if self.is(rhs) || rhs.is_always() {
+            true
+        } else if let Some(rhs) = rhs.down_to_set() {
+            self.len() == rhs.len() && self.filter_by(rhs, false).is_empty()
+        } else {
+            false
+        }
+    }
+
+    /// Union
+    fn bit_or(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
+        let rhs = rhs_set(rhs, "|")?;
+        let mut items = self.to_vec();
+        items.extend(rhs.filter_by(self, false));
+        Ok(new::set(items))
+    }
+
+    /// Intersection
+    fn bit_and(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
+        let rhs = rhs_set(rhs, "&")?;
+        Ok(new::set(self.filter_by(rhs, true)))
+    }
+
+    /// Difference
+    fn sub(&self, rhs: &dyn ObjectTrait) -> RuntimeObjResult {
+        let rhs = rhs_set(rhs, "-")?;
+        Ok(new::set(self.filter_by(rhs, false)))
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let this_id = self.id();
+        let items = self.items.read().unwrap();
+        if items.is_empty() {
+            return write!(f, "Set.new([])");
+        }
+        let items: Vec<String> = items
+            .iter()
+            .map(|item| {
+                let item = item.read().unwrap();
+                if item.id() == this_id {
+                    "{...}".to_owned()
+                } else {
+                    format!("{:?}", &*item)
+                }
+            })
+            .collect();
+        write!(f, "{{{}}}", items.join(", "))
+    }
+}
+
+impl fmt::Debug for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
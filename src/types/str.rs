@@ -11,6 +11,7 @@ use super::gen::{self, use_arg, use_arg_str, use_arg_usize};
 use super::new;
 
 use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::bytes::Encoding;
 use super::class::TYPE_TYPE;
 use super::ns::Namespace;
 
@@ -49,6 +50,33 @@ pub static STR_TYPE: Lazy<gen::obj_ref_t!(StrType)> = Lazy::new(|| {
             let suffix = use_arg_str!(ends_with, suffix, arg);
             Ok(new::bool(value.ends_with(suffix)))
         }),
+        gen::meth!(
+            "find",
+            type_ref,
+            &["substring"],
+            "Find substring.
+
+            # Args
+
+            - substring: Str
+
+            # Returns
+
+            Int | Nil: Index of the first char of the first occurrence of the
+            substring or nil if the substring isn't found.
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let substring = use_arg_str!(find, substring, arg);
+                Ok(match value.find(substring) {
+                    Some(i) => new::int(value[..i].chars().count()),
+                    None => new::nil(),
+                })
+            }
+        ),
         gen::meth!("upper", type_ref, &[], "", |this, _, _| {
             let this = this.read().unwrap();
             let value = this.get_str_val().unwrap();
@@ -80,10 +108,250 @@ pub static STR_TYPE: Lazy<gen::obj_ref_t!(StrType)> = Lazy::new(|| {
                 Ok(result)
             }
         ),
+        gen::meth!("trim", type_ref, &[], "", |this, _, _| {
+            let this = this.read().unwrap();
+            let value = this.get_str_val().unwrap();
+            Ok(new::str(value.trim()))
+        }),
+        gen::meth!(
+            "pad_left",
+            type_ref,
+            &["width", ""],
+            "Pad string on the left to the specified width.
+
+            # Args
+
+            - width: Int
+            - fill?: Str A single char (default: space)
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let width = use_arg_usize!(pad_left, width, args, 0);
+                let fill = match get_fill_char(&args[1]) {
+                    Some(fill) => fill,
+                    None => return Ok(fill_char_err("pad_left")),
+                };
+                Ok(new::str(pad(value, width, fill, true)))
+            }
+        ),
+        gen::meth!(
+            "pad_right",
+            type_ref,
+            &["width", ""],
+            "Pad string on the right to the specified width.
+
+            # Args
+
+            - width: Int
+            - fill?: Str A single char (default: space)
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let width = use_arg_usize!(pad_right, width, args, 0);
+                let fill = match get_fill_char(&args[1]) {
+                    Some(fill) => fill,
+                    None => return Ok(fill_char_err("pad_right")),
+                };
+                Ok(new::str(pad(value, width, fill, false)))
+            }
+        ),
+        gen::meth!(
+            "split",
+            type_ref,
+            &["sep"],
+            "Split string on separator.
+
+            # Args
+
+            - sep: Str Must not be empty
+
+            # Returns
+
+            Tuple<Str>
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let sep = use_arg_str!(split, sep, arg);
+                if sep.is_empty() {
+                    let msg = "split() expected sep to be non-empty";
+                    return Ok(new::arg_err(msg, new::nil()));
+                }
+                Ok(new::tuple(value.split(sep).map(new::str).collect()))
+            }
+        ),
+        gen::meth!(
+            "splitn",
+            type_ref,
+            &["sep", "n"],
+            "Split string on separator into at most n parts.
+
+            The last part contains the remainder of the string.
+
+            # Args
+
+            - sep: Str Must not be empty
+            - n: Int
+
+            # Returns
+
+            Tuple<Str>
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let sep = use_arg_str!(splitn, sep, arg);
+                let n = use_arg_usize!(splitn, n, args, 1);
+                if sep.is_empty() {
+                    let msg = "splitn() expected sep to be non-empty";
+                    return Ok(new::arg_err(msg, new::nil()));
+                }
+                Ok(new::tuple(value.splitn(n, sep).map(new::str).collect()))
+            }
+        ),
+        gen::meth!(
+            "partition",
+            type_ref,
+            &["sep"],
+            "Split string at the first occurrence of separator.
+
+            # Args
+
+            - sep: Str Must not be empty
+
+            # Returns
+
+            (Str, Str, Str): The part before the separator, the separator,
+            and the part after the separator. If the separator isn't found,
+            the first item is the entire string and the others are empty.
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let sep = use_arg_str!(partition, sep, arg);
+                if sep.is_empty() {
+                    let msg = "partition() expected sep to be non-empty";
+                    return Ok(new::arg_err(msg, new::nil()));
+                }
+                let (before, sep, after) = match value.split_once(sep) {
+                    Some((before, after)) => (before, sep, after),
+                    None => (value, "", ""),
+                };
+                Ok(new::tuple(vec![new::str(before), new::str(sep), new::str(after)]))
+            }
+        ),
+        gen::meth!(
+            "count",
+            type_ref,
+            &["substring"],
+            "Count non-overlapping occurrences of substring.
+
+            # Args
+
+            - substring: Str Must not be empty
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let substring = use_arg_str!(count, substring, arg);
+                if substring.is_empty() {
+                    let msg = "count() expected substring to be non-empty";
+                    return Ok(new::arg_err(msg, new::nil()));
+                }
+                Ok(new::int(value.matches(substring).count()))
+            }
+        ),
+        gen::meth!(
+            "join",
+            type_ref,
+            &["items"],
+            "Join items using string as separator.
+
+            Items that aren't strings are converted to strings.
+
+            # Args
+
+            - items: List | Tuple
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let sep = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let items = if let Some(list) = arg.down_to_list() {
+                    list.to_vec()
+                } else if let Some(tuple) = arg.down_to_tuple() {
+                    tuple.iter().cloned().collect()
+                } else {
+                    let msg = "join() expected items to be a List or Tuple";
+                    return Ok(new::arg_err(msg, new::nil()));
+                };
+                let items: Vec<String> =
+                    items.iter().map(|item| item.read().unwrap().to_string()).collect();
+                Ok(new::str(items.join(sep)))
+            }
+        ),
+        gen::meth!("chars", type_ref, &[], "", |this, _, _| {
+            let this = this.read().unwrap();
+            let value = this.get_str_val().unwrap();
+            Ok(new::tuple(value.chars().map(new::str).collect()))
+        }),
+        gen::meth!(
+            "encode",
+            type_ref,
+            &["encoding"],
+            "Encode to Bytes.
+
+            # Args
+
+            - encoding: Str
+
+              One of \"utf-8\", \"ascii\", or \"latin-1\". Case,
+              dashes, and underscores are ignored.
+
+            # Returns
+
+            - Bytes
+            - Err: If the encoding isn't known or the string contains
+              chars that can't be encoded
+
+            ",
+            |this_ref, args, _| {
+                let this = this_ref.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let name = use_arg_str!(encode, encoding, arg);
+                let Some(encoding) = Encoding::from_name(name) else {
+                    let msg = format!("Unknown encoding: {name}");
+                    return Ok(new::arg_err(msg, new::nil()));
+                };
+                Ok(match encoding.encode(value) {
+                    Ok(data) => new::bytes(data),
+                    Err(err) => new::string_err(err, this_ref.clone()),
+                })
+            }
+        ),
+        gen::meth!("lines", type_ref, &[], "", |this, _, _| {
+            let this = this.read().unwrap();
+            let value = this.get_str_val().unwrap();
+            Ok(new::tuple(value.lines().map(new::str).collect()))
+        }),
         gen::meth!("repeat", type_ref, &["count"], "", |this, args, _| {
             let this = this.read().unwrap();
             let value = this.get_str_val().unwrap();
-            let count = use_arg_usize!(get, index, args, 0);
+            let count = use_arg_usize!(repeat, count, args, 0);
             Ok(new::str(value.repeat(count)))
         }),
         gen::meth!("replace", type_ref, &["old", "new"], "", |this, args, _| {
@@ -96,11 +364,64 @@ pub static STR_TYPE: Lazy<gen::obj_ref_t!(StrType)> = Lazy::new(|| {
             let result = value.replace(old, new);
             Ok(new::str(result))
         }),
+        gen::meth!(
+            "strip_prefix",
+            type_ref,
+            &["prefix"],
+            "Strip prefix from string.
+
+            Unlike `remove_prefix()`, this returns nil when the string doesn't
+            start with the prefix, so it can be used to check for the prefix and
+            remove it in one step.
+
+            # Args
+
+            - prefix: Str
+
+            # Returns
+
+            Str | Nil
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let prefix = use_arg_str!(strip_prefix, prefix, arg);
+                Ok(value.strip_prefix(prefix).map_or_else(new::nil, new::str))
+            }
+        ),
+        gen::meth!(
+            "strip_suffix",
+            type_ref,
+            &["suffix"],
+            "Strip suffix from string.
+
+            Returns nil when the string doesn't end with the suffix (see
+            `strip_prefix()`).
+
+            # Args
+
+            - suffix: Str
+
+            # Returns
+
+            Str | Nil
+
+            ",
+            |this, args, _| {
+                let this = this.read().unwrap();
+                let value = this.get_str_val().unwrap();
+                let arg = use_arg!(args, 0);
+                let suffix = use_arg_str!(strip_suffix, suffix, arg);
+                Ok(value.strip_suffix(suffix).map_or_else(new::nil, new::str))
+            }
+        ),
         gen::meth!("remove_prefix", type_ref, &["prefix"], "", |this_ref, args, _| {
             let this = this_ref.read().unwrap();
             let val = this.get_str_val().unwrap();
             let arg = use_arg!(args, 0);
-            let prefix = use_arg_str!(starts_with, prefix, arg);
+            let prefix = use_arg_str!(remove_prefix, prefix, arg);
             Ok(if let Some(new_val) = val.strip_prefix(prefix) {
                 new::str(new_val)
             } else {
@@ -113,6 +434,42 @@ pub static STR_TYPE: Lazy<gen::obj_ref_t!(StrType)> = Lazy::new(|| {
     type_ref.clone()
 });
 
+/// Get the fill char for `pad_left()` and `pad_right()` from their var
+/// args. Defaults to a space.
+fn get_fill_char(var_args: &ObjectRef) -> Option<char> {
+    let var_args = var_args.read().unwrap();
+    let var_args = var_args.down_to_tuple().unwrap();
+    match var_args.len() {
+        0 => Some(' '),
+        1 => {
+            let fill = var_args.get(0).unwrap();
+            let fill = fill.read().unwrap();
+            let mut chars = fill.get_str_val()?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn fill_char_err(func_name: &str) -> ObjectRef {
+    let msg = format!("{func_name}() expected fill to be a single char");
+    new::arg_err(msg, new::nil())
+}
+
+/// Pad value with fill chars to the specified width (in chars).
+fn pad(value: &str, width: usize, fill: char, left: bool) -> String {
+    let n = width.saturating_sub(value.chars().count());
+    let padding = fill.to_string().repeat(n);
+    if left {
+        format!("{padding}{value}")
+    } else {
+        format!("{value}{padding}")
+    }
+}
+
 // Str Object ----------------------------------------------------------
 
 pub struct Str {
@@ -141,6 +498,15 @@ impl Str {
 impl ObjectTrait for Str {
     gen::object_trait_header!(STR_TYPE);
 
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(!self.value().is_empty())
+    }
+
+    /// Check whether the specified string is a substring.
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(member.get_str_val().is_some_and(|s| self.value().contains(s)))
+    }
+
     fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
         if self.is(rhs) || rhs.is_always() {
             true
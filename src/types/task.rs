@@ -0,0 +1,173 @@
+//! Tasks are created by calling async functions and by `std.async`
+//! (e.g., `sleep()`). A task doesn't run until it's spawned or awaited,
+//! at which point it's scheduled on the event loop, which runs it until
+//! it finishes or awaits another task that isn't done yet.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use super::gen;
+use super::new;
+use super::result::{Args, ThisOpt};
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// Task Type -----------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Task type
+
+Tasks are created by calling async functions. A task runs when it's
+spawned with `std.async.spawn()` or awaited in another async function.
+";
+
+gen::type_and_impls!(TaskType, Task);
+
+pub static TASK_TYPE: Lazy<gen::obj_ref_t!(TaskType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(TaskType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("name", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_task().unwrap();
+            Ok(new::str(&this.name))
+        }),
+        gen::prop!("done", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_task().unwrap();
+            Ok(new::bool(this.is_done()))
+        }),
+        gen::prop!(
+            "result",
+            type_ref,
+            "The task's result or nil if the task isn't done. If the task
+            raised an error, the result is the error.",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_task().unwrap();
+                Ok(this.result().unwrap_or_else(new::nil))
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Task Object ---------------------------------------------------------
+
+/// The `this`, args, and closure an async function is started with.
+pub type TaskStart = (ThisOpt, Args, Option<ObjectRef>);
+
+pub enum TaskKind {
+    /// Call of an async function. The args are taken when the task is
+    /// started.
+    Call { func: ObjectRef, start: Option<TaskStart> },
+    /// Finishes with nil after the duration has passed.
+    Sleep(Duration),
+}
+
+pub struct Task {
+    ns: Namespace,
+    name: String,
+    kind: TaskKind,
+    scheduled: bool,
+    // Set when the task finishes. The flag indicates whether the task
+    // raised an error, in which case the result is the error.
+    result: Option<(ObjectRef, bool)>,
+}
+
+gen::standard_object_impls!(Task);
+
+impl Task {
+    /// `func` is the async function and `closure` is the closure
+    /// wrapping it, if any. `args` must already have been checked.
+    pub fn new_call(
+        name: &str,
+        func: ObjectRef,
+        this_opt: ThisOpt,
+        args: Args,
+        closure: Option<ObjectRef>,
+    ) -> Self {
+        let start = Some((this_opt, args, closure));
+        Self::new(name.to_owned(), TaskKind::Call { func, start })
+    }
+
+    pub fn new_sleep(duration: Duration) -> Self {
+        let name = format!("sleep({})", duration.as_secs_f64());
+        Self::new(name, TaskKind::Sleep(duration))
+    }
+
+    fn new(name: String, kind: TaskKind) -> Self {
+        Self { ns: Namespace::default(), name, kind, scheduled: false, result: None }
+    }
+
+    pub fn kind(&self) -> &TaskKind {
+        &self.kind
+    }
+
+    /// Get the async function along with the `this`, args, and closure
+    /// it should be started with. The latter are `None` if the task
+    /// has already been started (or if this isn't a call).
+    pub fn take_start(&mut self) -> Option<(ObjectRef, Option<TaskStart>)> {
+        if let TaskKind::Call { func, start } = &mut self.kind {
+            Some((func.clone(), start.take()))
+        } else {
+            None
+        }
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        self.scheduled
+    }
+
+    pub fn set_scheduled(&mut self) {
+        self.scheduled = true;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.result.is_some()
+    }
+
+    pub fn result(&self) -> Option<ObjectRef> {
+        self.result.as_ref().map(|(result, _)| result.clone())
+    }
+
+    /// Get the error the task raised, if it raised one.
+    pub fn raised(&self) -> Option<ObjectRef> {
+        match &self.result {
+            Some((err, true)) => Some(err.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn finish(&mut self, result: ObjectRef, raised: bool) {
+        self.result = Some((result, raised));
+    }
+}
+
+impl ObjectTrait for Task {
+    gen::object_trait_header!(TASK_TYPE);
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<task {}>", self.name)
+    }
+}
+
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.is_done() { "done" } else { "pending" };
+        write!(f, "<task {} ({state})>", self.name)
+    }
+}
@@ -0,0 +1,169 @@
+//! Temporary files and directories created via `std.fs`. The path is
+//! removed when `cleanup()` is called or, failing that, when the
+//! object is freed.
+use std::any::Any;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// TempPath Type -------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic TempPath type
+
+TempPaths are created with `std.fs.temp_file()` and
+`std.fs.temp_dir()`. The path is removed by `cleanup()` or when the
+TempPath is no longer referenced.
+";
+
+gen::type_and_impls!(TempPathType, TempPath);
+
+pub static TEMP_PATH_TYPE: Lazy<gen::obj_ref_t!(TempPathType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(TempPathType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("path", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_temp_path().unwrap();
+            Ok(new::str(this.path.to_string_lossy()))
+        }),
+        gen::prop!("is_dir", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_temp_path().unwrap();
+            Ok(new::bool(this.is_dir))
+        }),
+        gen::prop!("is_cleaned_up", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_temp_path().unwrap();
+            Ok(new::bool(this.is_cleaned_up()))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!(
+            "cleanup",
+            type_ref,
+            &[],
+            "Remove the temporary file or directory.
+
+            A directory is removed along with its contents. Calling this
+            more than once has no effect.
+
+            # Returns
+
+            - nil: If the path was removed
+            - Err: If the path couldn't be removed
+
+            ",
+            |this_ref, _, _| {
+                let this = this_ref.read().unwrap();
+                let this = this.down_to_temp_path().unwrap();
+                Ok(match this.cleanup() {
+                    Ok(()) => new::nil(),
+                    Err(err) => new::file_unwritable_err(
+                        format!("{}: {err}", this.path.display()),
+                        this_ref.clone(),
+                    ),
+                })
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// TempPath Object -----------------------------------------------------
+
+pub struct TempPath {
+    ns: Namespace,
+    path: PathBuf,
+    is_dir: bool,
+    cleaned_up: AtomicBool,
+}
+
+gen::standard_object_impls!(TempPath);
+
+impl TempPath {
+    /// NOTE: The file or directory at `path` must already exist.
+    pub fn new(path: PathBuf, is_dir: bool) -> Self {
+        Self {
+            ns: Namespace::default(),
+            path,
+            is_dir,
+            cleaned_up: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_cleaned_up(&self) -> bool {
+        self.cleaned_up.load(Ordering::SeqCst)
+    }
+
+    /// Remove the path if it hasn't been removed already. A path that
+    /// was removed by some other means is considered cleaned up.
+    pub fn cleanup(&self) -> io::Result<()> {
+        if self.cleaned_up.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = if self.is_dir {
+            fs::remove_dir_all(&self.path)
+        } else {
+            fs::remove_file(&self.path)
+        };
+        match result {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => {
+                self.cleaned_up.store(false, Ordering::SeqCst);
+                Err(err)
+            }
+            ok => ok,
+        }
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        // Errors can't be reported here, so they're ignored.
+        let _ = self.cleanup();
+    }
+}
+
+impl ObjectTrait for TempPath {
+    gen::object_trait_header!(TEMP_PATH_TYPE);
+
+    fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
+        if self.is(rhs) || rhs.is_always() {
+            true
+        } else if let Some(rhs) = rhs.down_to_temp_path() {
+            self.path == rhs.path
+        } else {
+            false
+        }
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for TempPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+impl fmt::Debug for TempPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TempPath({:?})", self.path)
+    }
+}
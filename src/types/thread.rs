@@ -0,0 +1,150 @@
+//! Threads are created by `std.thread.spawn()`. Each thread runs a
+//! function in its own VM. The thread's VM starts with a copy of the
+//! spawning VM's globals, so reassigning a global in one thread doesn't
+//! affect other threads, but objects themselves are shared.
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+
+use once_cell::sync::Lazy;
+
+use crate::vm::RuntimeErr;
+
+use super::gen;
+use super::new;
+
+use super::base::{ObjectRef, ObjectTrait, TypeRef, TypeTrait};
+use super::class::TYPE_TYPE;
+use super::ns::Namespace;
+
+// Thread Type ---------------------------------------------------------
+
+static DOC: &str = "
+Intrinsic Thread type
+
+Threads are created with `std.thread.spawn()`. Use `join()` to wait
+for a thread to finish and get its result.
+";
+
+gen::type_and_impls!(ThreadType, Thread);
+
+pub static THREAD_TYPE: Lazy<gen::obj_ref_t!(ThreadType)> = Lazy::new(|| {
+    let type_ref = gen::obj_ref!(ThreadType::new());
+    let mut type_obj = type_ref.write().unwrap();
+
+    type_obj.add_attrs(&[
+        ("$doc", new::str(DOC)),
+        // Instance Attributes -----------------------------------------
+        gen::prop!("done", type_ref, "", |this, _, _| {
+            let this = this.read().unwrap();
+            let this = this.down_to_thread().unwrap();
+            Ok(new::bool(this.is_done()))
+        }),
+        // Instance Methods --------------------------------------------
+        gen::meth!(
+            "join",
+            type_ref,
+            &[],
+            "Wait for the thread to finish and return its result.
+
+            If the thread's function raised an error, the error is
+            raised again here. Joining a thread more than once returns
+            the same result each time.
+
+            ",
+            |this, _, _| {
+                let this = this.read().unwrap();
+                let this = this.down_to_thread().unwrap();
+                match this.join() {
+                    ThreadResult::Ok(result) => Ok(result),
+                    ThreadResult::Raised(err) => Err(RuntimeErr::raised(err)),
+                    ThreadResult::Failed(msg) => Err(RuntimeErr::thread_err(msg)),
+                }
+            }
+        ),
+    ]);
+
+    type_ref.clone()
+});
+
+// Thread Object -------------------------------------------------------
+
+/// The result of running a function in a thread.
+#[derive(Clone)]
+pub enum ThreadResult {
+    Ok(ObjectRef),
+    /// The function raised an error, which can be raised again when
+    /// the thread is joined.
+    Raised(ObjectRef),
+    /// The function failed in a way that can't be caught (e.g., it
+    /// exceeded the max call depth) or the thread panicked.
+    Failed(String),
+}
+
+// Object types are `Send` and `Sync` (see `gen::standard_object_impls`)
+// but `dyn ObjectTrait` isn't, so this has to be marked `Send` so the
+// result can be returned from the thread.
+unsafe impl Send for ThreadResult {}
+
+pub struct Thread {
+    ns: Namespace,
+    handle: Mutex<Option<JoinHandle<ThreadResult>>>,
+    // Set when the thread is joined.
+    result: Mutex<Option<ThreadResult>>,
+}
+
+gen::standard_object_impls!(Thread);
+
+impl Thread {
+    pub fn new(handle: JoinHandle<ThreadResult>) -> Self {
+        Self {
+            ns: Namespace::default(),
+            handle: Mutex::new(Some(handle)),
+            result: Mutex::new(None),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        if let Some(handle) = &*self.handle.lock().unwrap() {
+            return handle.is_finished();
+        }
+        // The thread has been joined or is being joined, in which case
+        // the result is locked until the thread finishes.
+        self.result.try_lock().is_ok_and(|result| result.is_some())
+    }
+
+    /// Wait for the thread to finish, if it hasn't been joined already,
+    /// and return its result.
+    pub fn join(&self) -> ThreadResult {
+        let mut result = self.result.lock().unwrap();
+        if let Some(result) = &*result {
+            return result.clone();
+        }
+        let handle = self.handle.lock().unwrap().take().unwrap();
+        let joined = handle
+            .join()
+            .unwrap_or_else(|_| ThreadResult::Failed("Thread panicked".to_owned()));
+        *result = Some(joined.clone());
+        joined
+    }
+}
+
+impl ObjectTrait for Thread {
+    gen::object_trait_header!(THREAD_TYPE);
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Thread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<thread>")
+    }
+}
+
+impl fmt::Debug for Thread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.is_done() { "done" } else { "running" };
+        write!(f, "<thread ({state})>")
+    }
+}
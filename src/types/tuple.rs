@@ -5,7 +5,8 @@ use std::sync::{Arc, RwLock};
 
 use once_cell::sync::Lazy;
 
-use crate::vm::RuntimeErr;
+use crate::types::IntoObjectRef;
+use crate::vm::{RuntimeBoolResult, RuntimeErr};
 
 use super::gen;
 use super::new;
@@ -65,11 +66,7 @@ pub static TUPLE_TYPE: Lazy<gen::obj_ref_t!(TupleType)> = Lazy::new(|| {
             let this = this.read().unwrap();
             let this = this.down_to_tuple().unwrap();
             let index = gen::use_arg_usize!(get, index, args, 0);
-            let result = match this.get(index) {
-                Some(obj) => obj,
-                None => new::nil(),
-            };
-            Ok(result)
+            Ok(this.get(index).into_obj_ref())
         }),
         gen::meth!("has", type_ref, &["member"], "", |this, args, _| {
             let this = this.read().unwrap();
@@ -130,6 +127,10 @@ impl Tuple {
 impl ObjectTrait for Tuple {
     gen::object_trait_header!(TUPLE_TYPE);
 
+    fn bool_val(&self) -> RuntimeBoolResult {
+        Ok(self.len() > 0)
+    }
+
     fn get_item(&self, index: usize, this: ObjectRef) -> ObjectRef {
         if let Some(item) = self.items.get(index) {
             item.clone()
@@ -138,6 +139,10 @@ impl ObjectTrait for Tuple {
         }
     }
 
+    fn contains(&self, member: &dyn ObjectTrait) -> RuntimeBoolResult {
+        Ok(self.iter().any(|item| member.is_equal(&*item.read().unwrap())))
+    }
+
     fn is_equal(&self, rhs: &dyn ObjectTrait) -> bool {
         if self.is(rhs) || rhs.is_always() {
             return true;
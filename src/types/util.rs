@@ -1,5 +1,7 @@
 use num_bigint::BigInt;
-use num_traits::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
+
+use crate::vm::RuntimeErr;
 
 use super::float::Float;
 use super::int::Int;
@@ -39,3 +41,31 @@ pub fn int_gt_float(lhs_int: &Int, rhs_float: &Float) -> bool {
     let lhs_as_float = lhs_int.value().to_f64().unwrap();
     lhs_as_float > *rhs_float.value()
 }
+
+/// Divide two Ints, rounding toward negative infinity.
+pub fn floor_div_int(lhs: &BigInt, rhs: &BigInt) -> Result<BigInt, RuntimeErr> {
+    if rhs.is_zero() {
+        return Err(RuntimeErr::arg_err("Division by zero"));
+    }
+    let quotient = lhs / rhs;
+    let remainder = lhs % rhs;
+    // BigInt division truncates toward zero, so the quotient is one too
+    // high when the remainder and divisor have different signs.
+    if !remainder.is_zero() && remainder.is_negative() != rhs.is_negative() {
+        Ok(quotient - BigInt::one())
+    } else {
+        Ok(quotient)
+    }
+}
+
+/// Divide two Floats, rounding toward negative infinity, and convert
+/// the result to an Int.
+pub fn floor_div_float(lhs: f64, rhs: f64) -> Result<BigInt, RuntimeErr> {
+    if rhs == 0.0 {
+        return Err(RuntimeErr::arg_err("Division by zero"));
+    }
+    let value = (lhs / rhs).floor();
+    BigInt::from_f64(value).ok_or_else(|| {
+        RuntimeErr::arg_err(format!("Could not convert {value} to Int: {lhs} // {rhs}"))
+    })
+}
@@ -0,0 +1,101 @@
+//! Process-wide deterministic mode.
+//!
+//! When enabled (via `--deterministic`), sources of nondeterminism are
+//! pinned so that program output is reproducible:
+//!
+//! - random number generators are seeded from `DETERMINISTIC_SEED`
+//! - the clock is frozen at `DETERMINISTIC_NOW` unless a different
+//!   time is injected via `set_clock`, and `std.time.ticks()` always
+//!   returns 0
+//!
+//! Map iteration is always ordered (by insertion) regardless of mode,
+//! since maps are backed by `IndexMap`.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seed used for random number generators in deterministic mode.
+pub const DETERMINISTIC_SEED: u64 = 0x5EED_FE17;
+
+/// Time the clock is frozen at in deterministic mode (by default):
+/// 2000-01-01T00:00:00Z.
+pub const DETERMINISTIC_NOW: Duration = Duration::from_secs(946_684_800);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Incremented whenever the mode is set so generators seeded from
+// `random_seed` know to reseed (see `generation`).
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+static CLOCK: RwLock<Option<SystemTime>> = RwLock::new(None);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Get the number of times the mode has been set. Generators seeded
+/// from `random_seed` should be reseeded when this changes so that
+/// their seed reflects the current mode and so that each deterministic
+/// run starts with the same sequence.
+pub fn generation() -> usize {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Inject the time returned by `now` in deterministic mode. Passing
+/// `None` restores the default frozen time.
+pub fn set_clock(time: Option<SystemTime>) {
+    *CLOCK.write().unwrap() = time;
+}
+
+/// Get the current time. In deterministic mode, this returns the
+/// injected time if one was set or `DETERMINISTIC_NOW` otherwise.
+pub fn now() -> SystemTime {
+    if is_enabled() {
+        if let Some(time) = *CLOCK.read().unwrap() {
+            time
+        } else {
+            UNIX_EPOCH + DETERMINISTIC_NOW
+        }
+    } else {
+        system_now()
+    }
+}
+
+/// Get a seed for a random number generator. In deterministic mode,
+/// this is always `DETERMINISTIC_SEED`.
+pub fn random_seed() -> u64 {
+    if is_enabled() {
+        DETERMINISTIC_SEED
+    } else {
+        let mut hasher = RandomState::new().build_hasher();
+        if let Ok(elapsed) = system_now().duration_since(UNIX_EPOCH) {
+            hasher.write_u128(elapsed.as_nanos());
+        }
+        hasher.finish()
+    }
+}
+
+/// Get the time from the system clock. There's no system clock in
+/// WebAssembly, where `SystemTime::now()` panics, so the time comes
+/// from JS when the `wasm` feature is enabled and is otherwise frozen.
+#[cfg(not(target_arch = "wasm32"))]
+fn system_now() -> SystemTime {
+    SystemTime::now()
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn system_now() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+}
+
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+fn system_now() -> SystemTime {
+    UNIX_EPOCH + DETERMINISTIC_NOW
+}
@@ -1,7 +1,10 @@
 pub(crate) use call::check_args;
 pub(crate) use stack::Stack;
 pub(crate) use string::format_doc;
+pub(crate) use symbol::Symbol;
 
 mod call;
+pub(crate) mod deterministic;
 mod stack;
 mod string;
+mod symbol;
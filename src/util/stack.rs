@@ -51,6 +51,12 @@ impl<T> Stack<T> {
         }
     }
 
+    /// Push items in order, so the last item will be at the top of the
+    /// stack. This is the inverse of `pop_n()`.
+    pub fn extend(&mut self, items: Vec<T>) {
+        self.storage.extend(items);
+    }
+
     pub fn peek(&self) -> Option<&T> {
         self.storage.last()
     }
@@ -0,0 +1,70 @@
+//! Interned strings for identifiers.
+//!
+//! Names are interned by the compiler so that instructions can refer to
+//! them by ID and the VM can look up vars by comparing integers rather
+//! than strings. The interner is process-wide, so a name has the same
+//! ID in every code unit and namespace.
+//!
+//! Interned strings are never freed, which is fine since the set of
+//! names in a program is small and mostly fixed.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+static INTERNER: Lazy<RwLock<Interner>> =
+    Lazy::new(|| RwLock::new(Interner::default()));
+
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<&'static str, Symbol>,
+    names: Vec<&'static str>,
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Get the symbol for name, interning name if necessary.
+    pub fn intern(name: &str) -> Self {
+        if let Some(symbol) = Self::lookup(name) {
+            return symbol;
+        }
+        let mut interner = INTERNER.write().unwrap();
+        // The name may have been interned since the read lock was
+        // released.
+        if let Some(symbol) = interner.ids.get(name) {
+            return *symbol;
+        }
+        let id = u32::try_from(interner.names.len()).expect("Too many symbols");
+        let symbol = Self(id);
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        interner.ids.insert(name, symbol);
+        interner.names.push(name);
+        symbol
+    }
+
+    /// Get the symbol for name if name has been interned. This is
+    /// useful for looking up names that don't come from the compiler,
+    /// since a name that hasn't been interned can't be a var name.
+    pub fn lookup(name: &str) -> Option<Self> {
+        INTERNER.read().unwrap().ids.get(name).copied()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.read().unwrap().names[self.0 as usize]
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
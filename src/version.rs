@@ -0,0 +1,38 @@
+//! Version and build info.
+//!
+//! The build info is set by the build script.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_HASH: &str = env!("FEINT_GIT_HASH");
+pub const BUILD_DATE: &str = env!("FEINT_BUILD_DATE");
+pub const RUSTC_VERSION: &str = env!("FEINT_RUSTC_VERSION");
+
+/// Shown by `feint --version`.
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("FEINT_GIT_HASH"),
+    " ",
+    env!("FEINT_BUILD_DATE"),
+    ")\n",
+    env!("FEINT_RUSTC_VERSION"),
+);
+
+/// Language features that can be checked via `system.feature(name)`.
+/// Add new features here as they're implemented, and never remove any,
+/// so scripts can rely on feature detection across versions.
+pub const FEATURES: &[&str] = &[
+    "async",
+    "custom_types",
+    "destructuring",
+    "enums",
+    "from_import",
+    "native_modules",
+    "ranges",
+    "sets",
+    "subscripts",
+    "try_catch",
+];
+
+pub fn has_feature(name: &str) -> bool {
+    FEATURES.contains(&name)
+}
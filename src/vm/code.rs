@@ -1,11 +1,16 @@
-use std::ops::Index;
+use std::collections::HashMap;
+use std::mem;
+use std::ops::{Index, Range};
 use std::slice::Iter;
 
+use num_bigint::BigInt;
+
 use crate::source::Location;
-use crate::types::{new, FuncTrait, ObjectRef};
-use crate::util::format_doc;
+use crate::types::{new, FuncTrait, ObjectRef, ObjectTrait};
+use crate::util::{format_doc, Symbol};
 
 use super::inst::Inst;
+use super::num::Num;
 use super::result::RuntimeErr;
 
 type FreeVarEntry = (
@@ -15,13 +20,114 @@ type FreeVarEntry = (
     Location, // source end
 );
 
+/// Maps instruction addresses to the source locations of the statements
+/// they belong to. Each entry holds the address of the first instruction
+/// of a statement along with the statement's start and end locations,
+/// and it applies to the instructions up to the next entry. Entries are
+/// ordered by address.
+#[derive(Debug, Default)]
+pub struct LocationTable {
+    entries: Vec<(usize, (Location, Location))>,
+}
+
+impl LocationTable {
+    /// Add an entry for the statement starting at `addr`, which must be
+    /// at or after the address of the last entry. If a statement didn't
+    /// produce any instructions, its entry is replaced.
+    pub fn add(&mut self, addr: usize, start: Location, end: Location) {
+        if let Some(last) = self.entries.last_mut() {
+            if last.0 == addr {
+                last.1 = (start, end);
+                return;
+            }
+        }
+        self.entries.push((addr, (start, end)));
+    }
+
+    /// Get the range of addresses that share the location of the
+    /// instruction at `addr` along with the location. The range ends at
+    /// the next entry or at `len_chunk`.
+    pub fn span(
+        &self,
+        addr: usize,
+        len_chunk: usize,
+    ) -> (Range<usize>, Option<(Location, Location)>) {
+        let index = self.entries.partition_point(|(a, _)| *a <= addr);
+        let end = self.entries.get(index).map_or(len_chunk, |(a, _)| *a);
+        match index.checked_sub(1) {
+            Some(index) => {
+                let (start, loc) = self.entries[index];
+                (start..end, Some(loc))
+            }
+            None => (0..end, None),
+        }
+    }
+
+    /// Append the entries of another table for instructions that are
+    /// appended at `offset`.
+    pub fn extend(&mut self, other: Self, offset: usize) {
+        for (addr, (start, end)) in other.entries {
+            self.add(addr + offset, start, end);
+        }
+    }
+
+    /// Update entry addresses after instructions are inserted or
+    /// removed. `new_addr` maps old addresses to new addresses.
+    pub fn remap<F: Fn(usize) -> usize>(&mut self, new_addr: F) {
+        let entries = mem::take(&mut self.entries);
+        for (addr, (start, end)) in entries {
+            self.add(new_addr(addr), start, end);
+        }
+    }
+}
+
+/// Key for looking up Int, Float, and Str constants by value. Floats
+/// are keyed by their bits so that Ints and Floats (e.g., `1` and
+/// `1.0`) and `0.0` and `-0.0` aren't merged.
+#[derive(Debug, Eq, Hash, PartialEq)]
+enum ConstKey {
+    Int(BigInt),
+    Float(u64),
+    Str(String),
+}
+
+impl ConstKey {
+    fn from_obj(obj: &dyn ObjectTrait) -> Option<Self> {
+        if let Some(value) = obj.get_int_val() {
+            Some(Self::Int(value.clone()))
+        } else if let Some(value) = obj.get_float_val() {
+            Some(Self::Float(value.to_bits()))
+        } else {
+            obj.get_str_val().map(|value| Self::Str(value.to_owned()))
+        }
+    }
+}
+
 /// Code for a module or function.
 #[derive(Debug)]
 pub struct Code {
     chunk: Vec<Inst>,
     constants: Vec<ObjectRef>,
+    // Indexes of Int, Float, and Str constants so constants can be
+    // deduplicated without comparing against every existing constant,
+    // which is too slow for large (e.g., generated) modules.
+    const_index: HashMap<ConstKey, usize>,
+    // Unboxed values of Int and Float constants, indexed like the
+    // constants, so they can be loaded without locking the objects.
+    nums: Vec<Option<Num>>,
     // Vars defined outside of this unit of code.
     free_vars: Vec<FreeVarEntry>,
+    // Vars defined in this unit of code that are captured by inner
+    // functions.
+    cell_vars: Vec<Symbol>,
+    // Vars declared in the top level scope of a function, excluding
+    // `this` and the function's params, in slot order.
+    local_names: Vec<Symbol>,
+    // Whether this is the code for an async function, which runs as a
+    // task when called.
+    is_async: bool,
+    // Locations of the statements the instructions belong to.
+    locations: LocationTable,
 }
 
 impl Default for Code {
@@ -49,6 +155,15 @@ impl PartialEq for Code {
         if self.free_vars != other.free_vars {
             return false;
         }
+        if self.cell_vars != other.cell_vars {
+            return false;
+        }
+        if self.local_names != other.local_names {
+            return false;
+        }
+        if self.is_async != other.is_async {
+            return false;
+        }
         for (c, d) in self.constants.iter().zip(other.constants.iter()) {
             let c = c.read().unwrap();
             let d = d.read().unwrap();
@@ -66,7 +181,21 @@ impl Code {
         constants: Vec<ObjectRef>,
         free_vars: Vec<FreeVarEntry>,
     ) -> Self {
-        Self { chunk, constants, free_vars }
+        let nums =
+            constants.iter().map(|obj| Num::from_obj(&*obj.read().unwrap())).collect();
+        let mut code = Self {
+            chunk,
+            constants: vec![],
+            const_index: HashMap::new(),
+            nums,
+            free_vars,
+            cell_vars: vec![],
+            local_names: vec![],
+            is_async: false,
+            locations: LocationTable::default(),
+        };
+        code.extend_constants(constants);
+        code
     }
 
     /// Initialize code object with a list of instructions, also known
@@ -78,32 +207,30 @@ impl Code {
     /// Extend this `Code` object with another `Code` object:
     ///
     /// - Extend instructions, adjusting constant indexes
-    /// - Extend constants
-    /// - Free vars are ignored for now since this is mainly intended
-    ///   for extending modules (where there are no free vars) and not
-    ///   functions
+    /// - Extend constants and locations, adjusting location addresses
+    /// - Free vars and cell vars are ignored for now since this is mainly intended
+    ///   for extending modules (where there are no free or cell vars)
+    ///   and not functions
     ///
     /// IMPORTANT: ALL instructions that hold a const index MUST be
     ///            updated here.
     pub fn extend(&mut self, mut code: Self) {
-        use Inst::LoadConst;
-        let mut replacements = vec![];
         let const_offset = self.constants.len();
-        for (addr, inst) in code.iter_chunk().enumerate() {
-            if let LoadConst(index) = inst {
-                replacements.push((addr, LoadConst(const_offset + index)));
+        let addr_offset = self.chunk.len();
+        for inst in code.chunk.iter_mut() {
+            if let Inst::LoadConst(index) = inst {
+                *index += const_offset;
             }
         }
-        for (addr, inst) in replacements {
-            code.replace_inst(addr, inst);
-        }
         self.chunk.extend(code.chunk);
-        self.constants.extend(code.constants);
+        self.extend_constants(code.constants);
+        self.nums.extend(code.nums);
+        self.locations.extend(code.locations, addr_offset);
     }
 
     /// Get docstring for code unit, if there is one.
     pub fn get_doc(&self) -> ObjectRef {
-        if let Some(Inst::LoadConst(0)) = self.chunk.get(1) {
+        if let Some(Inst::LoadConst(0)) = self.chunk.first() {
             if let Ok(obj_ref) = self.get_const(0) {
                 let obj = obj_ref.read().unwrap();
                 if let Some(doc) = obj.get_str_val() {
@@ -140,6 +267,31 @@ impl Code {
         self.chunk[index] = inst;
     }
 
+    /// Take the instructions out of this code object, leaving it with
+    /// an empty chunk. This is used with `set_chunk` to rewrite the
+    /// instructions (e.g., in the optimizer).
+    ///
+    /// NOTE: The addresses of free vars aren't updated when the
+    ///       instructions are rewritten.
+    pub fn take_chunk(&mut self) -> Vec<Inst> {
+        mem::take(&mut self.chunk)
+    }
+
+    pub fn set_chunk(&mut self, chunk: Vec<Inst>) {
+        self.chunk = chunk;
+    }
+
+    /// Take the location table out of this code object. This is used
+    /// with `set_locations` to update locations when the instructions
+    /// are rewritten.
+    pub fn take_locations(&mut self) -> LocationTable {
+        mem::take(&mut self.locations)
+    }
+
+    pub fn set_locations(&mut self, locations: LocationTable) {
+        self.locations = locations;
+    }
+
     /// Explicit return statements need to jump to the end of the
     /// function so that the function can be cleanly exited.
     pub fn fix_up_explicit_returns(&mut self) {
@@ -163,20 +315,48 @@ impl Code {
         //      potentially unsafe to treat them as such here.
         let is_comparable = val.is_immutable() && !val.is_func();
 
-        for (index, other_ref) in self.iter_constants().enumerate() {
-            let other = other_ref.read().unwrap();
-            let other_is_comparable = other.is_immutable() && !other.is_func();
-            if is_comparable && other_is_comparable && other.is_equal(val) {
+        let key = ConstKey::from_obj(val);
+        if let Some(key) = &key {
+            if let Some(&index) = self.const_index.get(key) {
                 return index;
             }
+        } else if is_comparable {
+            // Other comparable types are rare, so a linear scan is OK.
+            for (index, other_ref) in self.iter_constants().enumerate() {
+                let other = other_ref.read().unwrap();
+                let other_is_comparable = other.is_immutable() && !other.is_func();
+                if other_is_comparable
+                    && ConstKey::from_obj(&*other).is_none()
+                    && other.is_equal(val)
+                {
+                    return index;
+                }
+            }
         }
 
         let index = self.constants.len();
+        self.nums.push(Num::from_obj(val));
         drop(val_guard);
+        if let Some(key) = key {
+            self.const_index.insert(key, index);
+        }
         self.constants.push(val_ref);
         index
     }
 
+    /// Append constants without deduplicating them, since instructions
+    /// may already refer to them by index. Only the first index for
+    /// each key is recorded.
+    fn extend_constants(&mut self, constants: Vec<ObjectRef>) {
+        for val_ref in constants {
+            let key = ConstKey::from_obj(&*val_ref.read().unwrap());
+            if let Some(key) = key {
+                self.const_index.entry(key).or_insert(self.constants.len());
+            }
+            self.constants.push(val_ref);
+        }
+    }
+
     pub fn get_const(&self, index: usize) -> Result<&ObjectRef, RuntimeErr> {
         if let Some(obj) = self.constants.get(index) {
             Ok(obj)
@@ -185,6 +365,12 @@ impl Code {
         }
     }
 
+    /// Get the unboxed value of the constant at index if it's an Int
+    /// or Float.
+    pub fn get_num_const(&self, index: usize) -> Option<Num> {
+        self.nums.get(index).copied().flatten()
+    }
+
     pub fn iter_constants(&self) -> Iter<'_, ObjectRef> {
         self.constants.iter()
     }
@@ -201,6 +387,30 @@ impl Code {
         maybe_index.map(|index| self.constants[index].clone())
     }
 
+    // Locations -------------------------------------------------------
+
+    /// Mark the start of a statement. The instructions added from here
+    /// until the start of the next statement will have its location.
+    pub fn add_location(&mut self, start: Location, end: Location) {
+        self.locations.add(self.chunk.len(), start, end);
+    }
+
+    /// Get the location of the statement the instruction at `addr`
+    /// belongs to.
+    #[cfg(feature = "dis")]
+    pub fn get_location(&self, addr: usize) -> Option<(Location, Location)> {
+        self.location_span(addr).1
+    }
+
+    /// Get the range of addresses that share the location of the
+    /// instruction at `addr` along with the location.
+    pub fn location_span(
+        &self,
+        addr: usize,
+    ) -> (Range<usize>, Option<(Location, Location)>) {
+        self.locations.span(addr, self.chunk.len())
+    }
+
     // Vars ------------------------------------------------------------
 
     pub fn free_vars(&self) -> &Vec<FreeVarEntry> {
@@ -218,7 +428,41 @@ impl Code {
     ) {
         let addr = self.len_chunk();
         let name = name.into();
-        self.free_vars.push((addr, name.clone(), start, end));
-        self.push_inst(Inst::FreeVarPlaceholder(addr, name));
+        let symbol = Symbol::intern(&name);
+        self.free_vars.push((addr, name, start, end));
+        self.push_inst(Inst::FreeVarPlaceholder(addr, symbol));
+    }
+
+    /// Check whether the var with the specified name is captured by an
+    /// inner function. Function args are wrapped in cells only when
+    /// this is the case.
+    pub fn is_cell_var(&self, name: Symbol) -> bool {
+        self.cell_vars.contains(&name)
+    }
+
+    /// Mark the var with the specified name as captured by an inner
+    /// function.
+    pub fn add_cell_var(&mut self, name: Symbol) {
+        if !self.is_cell_var(name) {
+            self.cell_vars.push(name);
+        }
+    }
+
+    /// Get the names of the locals that are declared when a function
+    /// is called (after `this` and the args).
+    pub fn local_names(&self) -> &[Symbol] {
+        &self.local_names
+    }
+
+    pub fn set_local_names(&mut self, names: Vec<Symbol>) {
+        self.local_names = names;
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.is_async
+    }
+
+    pub fn set_async(&mut self, is_async: bool) {
+        self.is_async = is_async;
     }
 }
@@ -3,11 +3,13 @@ use indexmap::IndexMap;
 
 use crate::modules::std::STD;
 use crate::types::{new, ObjectRef, ObjectTrait};
+use crate::util::Symbol;
 use crate::vm::RuntimeObjResult;
 
 use super::result::{RuntimeErr, RuntimeResult};
 
-type Namespace = IndexMap<String, ObjectRef>;
+// Vars are keyed by symbol so looking them up by name compares ints.
+pub(super) type Namespace = IndexMap<Symbol, ObjectRef>;
 type NamespaceStack = Vec<Namespace>;
 
 /// Holds info relating to execution of the current module.
@@ -17,7 +19,7 @@ type NamespaceStack = Vec<Namespace>;
 ///
 /// At the bottom is the namespace corresponding to the module's global
 /// scope. This namespace cannot be popped from the stack--trying to
-/// do so will cause a `panic`.
+/// do so is an error.
 ///
 /// At the top is the namespace corresponding to the current scope. A
 /// new namespace is pushed every time the VM encounters a `SCOPE_START`
@@ -25,21 +27,36 @@ type NamespaceStack = Vec<Namespace>;
 /// instruction.
 pub struct ModuleExecutionContext {
     ns_stack: NamespaceStack,
+    // Globals that have been declared but not assigned yet. If the
+    // statement declaring them fails, they're removed so that they
+    // don't linger as nil (see `remove_unassigned_globals`).
+    unassigned_globals: Vec<Symbol>,
 }
 
 impl Default for ModuleExecutionContext {
     fn default() -> Self {
-        Self { ns_stack: vec![IndexMap::default()] }
+        Self { ns_stack: vec![IndexMap::default()], unassigned_globals: vec![] }
     }
 }
 
 impl ModuleExecutionContext {
-    /// This will panic if the builtin doesn't exist because builtin
-    /// names are resolved during compilation.
-    pub(super) fn get_builtin(&self, name: &str) -> ObjectRef {
+    /// Create a context whose global namespace starts out with the
+    /// specified globals.
+    #[cfg(feature = "std-full")]
+    pub(crate) fn with_globals(globals: Namespace) -> Self {
+        Self { ns_stack: vec![globals], unassigned_globals: vec![] }
+    }
+
+    /// Builtin names are resolved during compilation, so the builtin
+    /// should always exist.
+    pub(super) fn get_builtin(&self, name: &str) -> RuntimeObjResult {
         let std = STD.read().unwrap();
         let std = std.down_to_mod().unwrap();
-        std.get_global(name).expect("Builtin unexpectedly undefined: {name}")
+        std.get_global(name).ok_or_else(|| {
+            RuntimeErr::unexpected_state(format!(
+                "Builtin unexpectedly undefined: {name}"
+            ))
+        })
     }
 
     #[inline]
@@ -47,31 +64,62 @@ impl ModuleExecutionContext {
         &self.ns_stack[0]
     }
 
-    pub(super) fn get_global(&self, name: &str) -> Option<ObjectRef> {
-        self.globals().get(name).cloned()
+    pub(super) fn get_global(&self, name: Symbol) -> Option<ObjectRef> {
+        self.globals().get(&name).cloned()
     }
 
     pub(super) fn enter_scope(&mut self) {
         self.ns_stack.push(IndexMap::default());
     }
 
-    pub(super) fn exit_scope(&mut self) {
+    pub(super) fn exit_scope(&mut self) -> RuntimeResult {
         if self.current_depth() == 0 {
-            panic!("Global namespace cannot be exited");
+            return Err(RuntimeErr::unexpected_state(
+                "Global namespace cannot be exited",
+            ));
         }
-        let mut ns = self.ns_stack.pop().expect("Expected namespace");
-        ns.clear();
+        self.ns_stack.pop();
+        Ok(())
+    }
+
+    /// Exit scopes until the specified depth is reached.
+    pub(super) fn exit_scopes_to(&mut self, depth: usize) {
+        self.ns_stack.truncate(depth + 1);
+    }
+
+    /// Remove the namespaces above the specified depth and return them
+    /// so they can be restored later with `restore_scopes()`. This is
+    /// used to suspend tasks.
+    pub(super) fn split_off_scopes(&mut self, depth: usize) -> Vec<Namespace> {
+        self.ns_stack.split_off(depth + 1)
+    }
+
+    pub(super) fn restore_scopes(&mut self, namespaces: Vec<Namespace>) {
+        self.ns_stack.extend(namespaces);
     }
 
     pub(super) fn exit_all_scopes(&mut self) {
-        while self.ns_stack.len() > 1 {
-            self.exit_scope();
-        }
+        self.exit_scopes_to(0);
     }
 
     pub(super) fn reset(&mut self) {
         self.exit_all_scopes();
         self.ns_stack[0].clear();
+        self.unassigned_globals.clear();
+    }
+
+    /// Forget the globals that are waiting to be assigned so that they
+    /// won't be removed by `remove_unassigned_globals`.
+    pub(super) fn clear_unassigned_globals(&mut self) {
+        self.unassigned_globals.clear();
+    }
+
+    /// Remove globals that were declared but never assigned because
+    /// the statement declaring them failed (e.g., `y` in `y = 1 + nil`).
+    pub(super) fn remove_unassigned_globals(&mut self) {
+        for name in self.unassigned_globals.drain(..) {
+            self.ns_stack[0].shift_remove(&name);
+        }
     }
 
     #[inline]
@@ -85,7 +133,7 @@ impl ModuleExecutionContext {
     }
 
     #[inline]
-    fn current_depth(&self) -> usize {
+    pub(super) fn current_depth(&self) -> usize {
         self.ns_stack.len() - 1
     }
 
@@ -94,26 +142,34 @@ impl ModuleExecutionContext {
     /// Declare a new var in the current namespace. This adds a slot for
     /// the var in the current namespace and sets its initial value to
     /// nil.
-    pub(super) fn declare_var(&mut self, name: &str) {
+    pub(super) fn declare_var(&mut self, name: Symbol) {
         let initial = new::nil();
+        let is_global = self.current_depth() == 0;
         let ns = self.current_mut();
-        ns.insert(name.to_owned(), initial);
+        if ns.insert(name, initial).is_none() && is_global {
+            self.unassigned_globals.push(name);
+        }
     }
 
     /// Assign value to var in *current* namespace. This looks up the
     /// var by name in the current namespace, updates its value, and
-    /// returns the depth of the namespace where the var lives. If the
-    /// var doesn't exist in the current namespace, an error is returned
-    /// instead (indicating an internal error).
+    /// returns the depth of the namespace where the var lives along
+    /// with the var's index in that namespace. If the var doesn't exist
+    /// in the current namespace, an error is returned instead
+    /// (indicating an internal error).
     pub(super) fn assign_var(
         &mut self,
-        name: &str,
+        name: Symbol,
         obj: ObjectRef,
-    ) -> Result<usize, RuntimeErr> {
+    ) -> Result<(usize, usize), RuntimeErr> {
+        let depth = self.current_depth();
+        if depth == 0 {
+            self.unassigned_globals.retain(|n| *n != name);
+        }
         let ns = self.current_mut();
-        if ns.contains_key(name) {
-            ns.insert(name.to_owned(), obj);
-            Ok(self.ns_stack.len() - 1)
+        if let Some((index, _, slot)) = ns.get_full_mut(&name) {
+            *slot = obj;
+            Ok((depth, index))
         } else {
             let message = format!("Name not defined in current scope: {name}");
             Err(RuntimeErr::name_err(message))
@@ -123,42 +179,46 @@ impl ModuleExecutionContext {
     /// Conveniently declare and assign a var in one step.
     pub(super) fn declare_and_assign_var(
         &mut self,
-        name: &str,
+        name: Symbol,
         obj: ObjectRef,
-    ) -> Result<usize, RuntimeErr> {
+    ) -> Result<(usize, usize), RuntimeErr> {
         self.declare_var(name);
         self.assign_var(name, obj)
     }
 
     /// Assign value to var--reach into the scope at depth and set the
     /// var at the specified index.
-    pub(super) fn assign_var_at_depth(
+    pub(super) fn assign_var_at(
         &mut self,
         depth: usize,
-        name: &str,
+        index: usize,
         obj: ObjectRef,
     ) -> RuntimeResult {
-        let ns = &mut self.ns_stack[depth];
-        if ns.contains_key(name) {
-            ns.insert(name.to_owned(), obj);
+        if let Some((name, slot)) = self.ns_stack[depth].get_index_mut(index) {
+            if depth == 0 {
+                let name = *name;
+                self.unassigned_globals.retain(|n| *n != name);
+            }
+            *slot = obj;
             Ok(())
         } else {
-            let message = format!("Name not defined at depth {depth}: {name}");
+            let message = format!("Var not defined at depth {depth}: {index}");
             Err(RuntimeErr::name_err(message))
         }
     }
 
-    /// Get depth of scope where var is defined.
-    pub(super) fn get_var_depth(
+    /// Get depth of scope where var is defined along with the var's
+    /// index in that scope's namespace.
+    pub(super) fn get_var_location(
         &self,
-        name: &str,
+        name: Symbol,
         offset: usize,
-    ) -> Result<usize, RuntimeErr> {
+    ) -> Result<(usize, usize), RuntimeErr> {
         let ns_stack = &self.ns_stack;
         let mut var_depth = self.current_depth() - offset;
         loop {
-            if ns_stack[var_depth].get(name).is_some() {
-                break Ok(var_depth);
+            if let Some(index) = ns_stack[var_depth].get_index_of(&name) {
+                break Ok((var_depth, index));
             }
             if var_depth == 0 {
                 let message = format!("Name not found: {name}");
@@ -169,9 +229,9 @@ impl ModuleExecutionContext {
     }
 
     /// Get var from current scope.
-    pub(super) fn get_var_in_current_ns(&self, name: &str) -> RuntimeObjResult {
+    pub(super) fn get_var_in_current_ns(&self, name: Symbol) -> RuntimeObjResult {
         let ns = self.current();
-        if let Some(obj) = ns.get(name) {
+        if let Some(obj) = ns.get(&name) {
             Ok(obj.clone())
         } else {
             let message = format!("Name not defined in current scope: {name}");
@@ -179,23 +239,31 @@ impl ModuleExecutionContext {
         }
     }
 
-    /// Reach into the scope at depth and get the specified var.
-    pub(super) fn get_var_at_depth(
-        &self,
-        depth: usize,
-        name: &str,
-    ) -> RuntimeObjResult {
-        if let Some(obj) = self.ns_stack[depth].get(name) {
+    /// Reach into the scope at depth and get the var at the specified
+    /// index.
+    pub(super) fn get_var_at(&self, depth: usize, index: usize) -> RuntimeObjResult {
+        if let Some((_, obj)) = self.ns_stack[depth].get_index(index) {
             Ok(obj.clone())
         } else {
-            let message = format!("Name not defined at depth {depth}: {name}");
+            let message = format!("Var not defined at depth {depth}: {index}");
             Err(RuntimeErr::name_err(message))
         }
     }
 
+    /// Get the vars in the scopes from `depth` up to and including the
+    /// current scope. Vars in inner scopes shadow vars with the same
+    /// name in outer scopes.
+    pub(super) fn vars_from(&self, depth: usize) -> Namespace {
+        let mut vars = Namespace::default();
+        for ns in self.ns_stack[depth..].iter() {
+            vars.extend(ns.iter().map(|(name, obj)| (*name, obj.clone())));
+        }
+        vars
+    }
+
     /// Get var in current scope or any ancestor scope.
-    pub(super) fn get_var(&self, name: &str, offset: usize) -> RuntimeObjResult {
-        let depth = self.get_var_depth(name, offset)?;
-        self.get_var_at_depth(depth, name)
+    pub(super) fn get_var(&self, name: Symbol, offset: usize) -> RuntimeObjResult {
+        let (depth, index) = self.get_var_location(name, offset)?;
+        self.get_var_at(depth, index)
     }
 }
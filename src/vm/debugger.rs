@@ -0,0 +1,236 @@
+//! Interactive debugger. When a debugger is attached to the VM, the VM
+//! checks with it at the start of every statement. When it's time to
+//! stop--at a breakpoint or after stepping--the VM shows the current
+//! location and reads commands until one resumes execution (see
+//! `VM::debug_statement()`).
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::compiler::{CompErrKind, Compiler};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::source::source_from_text;
+
+use super::code::Code;
+use super::result::CallDepth;
+
+pub(super) const PROMPT: &str = "(fdb) ";
+
+pub(super) const HELP: &str = "\
+s, step                 Run to the next statement, stepping into calls
+n, next                 Run to the next statement in the current function
+c, continue             Run until a breakpoint is reached
+b, break [FILE:]LINE    Set a breakpoint (FILE defaults to the current file)
+cl, clear [FILE:]LINE   Remove a breakpoint
+bl, breakpoints         List breakpoints
+w, where                Show the current location
+st, stack               Show the value stack
+l, locals               Show the vars in the current frame
+p, print EXPR           Evaluate an expression in the current frame
+q, quit                 Stop the program (exits with code 1)
+h, help                 Show this help
+
+An empty line repeats the previous command.
+";
+
+/// Determines where execution stops next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StepMode {
+    /// Stop at the next statement.
+    Step,
+    /// Stop at the next statement at or above the specified call depth
+    /// (i.e., step over calls).
+    Next(CallDepth),
+    /// Only stop at breakpoints.
+    Continue,
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Command {
+    Step,
+    Next,
+    Continue,
+    Break(Option<String>, usize),
+    Clear(Option<String>, usize),
+    Breakpoints,
+    Where,
+    Stack,
+    Locals,
+    Print(String),
+    Quit,
+    Help,
+    Invalid(String),
+}
+
+impl Command {
+    pub(super) fn parse(line: &str) -> Self {
+        let line = line.trim();
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+        match (name, arg) {
+            ("s" | "step", "") => Self::Step,
+            ("n" | "next", "") => Self::Next,
+            ("c" | "continue", "") => Self::Continue,
+            ("b" | "break", arg) => match parse_breakpoint(arg) {
+                Some((file, line)) => Self::Break(file, line),
+                None => Self::Invalid(format!("Invalid breakpoint: {arg}")),
+            },
+            ("cl" | "clear", arg) => match parse_breakpoint(arg) {
+                Some((file, line)) => Self::Clear(file, line),
+                None => Self::Invalid(format!("Invalid breakpoint: {arg}")),
+            },
+            ("bl" | "breakpoints", "") => Self::Breakpoints,
+            ("w" | "where", "") => Self::Where,
+            ("st" | "stack", "") => Self::Stack,
+            ("l" | "locals", "") => Self::Locals,
+            ("p" | "print", expr) if !expr.is_empty() => Self::Print(expr.to_owned()),
+            ("q" | "quit", "") => Self::Quit,
+            ("h" | "help", "") => Self::Help,
+            _ => Self::Invalid(format!("Invalid command: {line} (enter h for help)")),
+        }
+    }
+}
+
+/// Parse `[FILE:]LINE`. Line numbers start at 1.
+fn parse_breakpoint(arg: &str) -> Option<(Option<String>, usize)> {
+    let (file, line) = match arg.rsplit_once(':') {
+        Some((file, line)) => (Some(file.to_owned()), line),
+        None => (None, arg),
+    };
+    match line.parse() {
+        Ok(line) if line > 0 => Some((file, line)),
+        _ => None,
+    }
+}
+
+pub(crate) struct Debugger {
+    mode: StepMode,
+    // Breakpoints are file paths (as entered or, if not specified, the
+    // path of the module where execution stopped) with line numbers.
+    breakpoints: BTreeSet<(String, usize)>,
+    input: Box<dyn BufRead>,
+    last_command: String,
+    // Lines of module source files, read when they're first shown.
+    sources: HashMap<String, Option<Vec<String>>>,
+}
+
+impl Debugger {
+    /// Create a debugger that reads commands from `input`. It starts
+    /// out stepping, so execution stops at the next statement.
+    pub(super) fn new(input: Box<dyn BufRead>) -> Self {
+        Self {
+            mode: StepMode::Step,
+            breakpoints: BTreeSet::new(),
+            input,
+            last_command: String::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Check whether execution should stop at the statement on `line`
+    /// of the module at `path`, which is being executed at call depth
+    /// `depth`.
+    pub(super) fn should_stop(
+        &self,
+        path: &str,
+        line: usize,
+        depth: CallDepth,
+    ) -> bool {
+        match self.mode {
+            StepMode::Step => true,
+            StepMode::Next(next_depth) if depth <= next_depth => true,
+            _ => self.breakpoints.iter().any(|(file, bp_line)| {
+                *bp_line == line && (file == path || Path::new(path).ends_with(file))
+            }),
+        }
+    }
+
+    pub(super) fn step(&mut self) {
+        self.mode = StepMode::Step;
+    }
+
+    pub(super) fn next(&mut self, depth: CallDepth) {
+        self.mode = StepMode::Next(depth);
+    }
+
+    pub(super) fn resume(&mut self) {
+        self.mode = StepMode::Continue;
+    }
+
+    /// Returns `false` if the breakpoint was already set.
+    pub(super) fn add_breakpoint(&mut self, file: String, line: usize) -> bool {
+        self.breakpoints.insert((file, line))
+    }
+
+    /// Returns `false` if the breakpoint wasn't set.
+    pub(super) fn remove_breakpoint(&mut self, file: String, line: usize) -> bool {
+        self.breakpoints.remove(&(file, line))
+    }
+
+    pub(super) fn breakpoints(&self) -> impl Iterator<Item = &(String, usize)> {
+        self.breakpoints.iter()
+    }
+
+    /// Read the next command. An empty line repeats the previous
+    /// command. `None` is returned when the input is exhausted.
+    pub(super) fn read_command(&mut self) -> Option<Command> {
+        loop {
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => break None,
+                Ok(_) => (),
+            }
+            if !line.trim().is_empty() {
+                self.last_command = line;
+            } else if self.last_command.is_empty() {
+                continue;
+            }
+            break Some(Command::parse(&self.last_command));
+        }
+    }
+
+    /// Format the location of the statement on `line` of the module at
+    /// `path`, including the source line if the module's source file
+    /// can be read.
+    pub(super) fn format_location(&mut self, path: &str, line: usize) -> String {
+        let lines = self.sources.entry(path.to_owned()).or_insert_with(|| {
+            let text = fs::read_to_string(path).ok()?;
+            Some(text.lines().map(str::to_owned).collect())
+        });
+        let source_line =
+            lines.as_ref().and_then(|lines| lines.get(line.checked_sub(1)?));
+        match source_line {
+            Some(source_line) => {
+                format!("-> {path}:{line}\n{line:>4} | {source_line}\n")
+            }
+            None => format!("-> {path}:{line}\n"),
+        }
+    }
+}
+
+/// Compile expression text entered at the debugger prompt. `names` are
+/// the names of the vars that are visible in the current frame, which
+/// are compiled as globals. The value of the expression is left on the
+/// stack.
+pub(super) fn compile_expr(text: &str, names: HashSet<String>) -> Result<Code, String> {
+    let mut source = source_from_text(text);
+    let mut parser = Parser::new(Scanner::new(&mut source));
+    let module = parser.parse().map_err(|errs| {
+        let kinds: Vec<String> =
+            errs.iter().map(|err| format!("{:?}", err.kind)).collect();
+        format!("Could not parse expression: {}", kinds.join("; "))
+    })?;
+    let mut compiler = Compiler::new(names);
+    let mut code = compiler.compile_module_to_code("$debugger", module).map_err(
+        |err| match err.kind {
+            CompErrKind::NameNotFound(name, ..) => format!("Name not found: {name}"),
+            kind => format!("Could not compile expression: {kind:?}"),
+        },
+    )?;
+    code.pop_inst();
+    Ok(code)
+}
@@ -0,0 +1,128 @@
+//! Event loop for running tasks (see `types::task`).
+//!
+//! The event loop is run by `VM::run_until_complete()`. Tasks that are
+//! ready run in the order they became ready. A task runs until it
+//! finishes or until it awaits a task that isn't done yet, at which
+//! point it's suspended until that task finishes. When no tasks are
+//! ready, the loop waits for the next timer (e.g., from `sleep()`) to
+//! expire.
+//!
+//! There's no clock in WebAssembly, where `Instant::now()` panics, and
+//! the loop can't block, so time is simulated there instead. It starts
+//! at zero and advances to each timer's deadline when the timer expires.
+use std::collections::{HashMap, VecDeque};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+#[cfg(target_arch = "wasm32")]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use crate::types::task::TaskKind;
+use crate::types::ObjectRef;
+
+use super::vm::SuspendedTask;
+
+#[cfg(not(target_arch = "wasm32"))]
+type Time = Instant;
+
+#[cfg(target_arch = "wasm32")]
+type Time = Duration;
+
+#[derive(Default)]
+pub(super) struct EventLoop {
+    ready: VecDeque<ObjectRef>,
+    // Tasks waiting for other tasks to finish, keyed by the ID of the
+    // task they're waiting for.
+    waiters: HashMap<usize, Vec<ObjectRef>>,
+    // Sleep tasks along with the time they finish.
+    timers: Vec<(Time, ObjectRef)>,
+    // Simulated time (see above).
+    #[cfg(target_arch = "wasm32")]
+    simulated_now: Duration,
+    // The saved state of tasks that were suspended, keyed by task ID.
+    suspended: HashMap<usize, SuspendedTask>,
+    // The task that's currently running, if any.
+    pub current: Option<ObjectRef>,
+}
+
+impl EventLoop {
+    /// Schedule task unless it's already scheduled or done. Call tasks
+    /// are made ready to run. Sleep tasks get a timer instead.
+    pub fn schedule(&mut self, task_ref: &ObjectRef) {
+        let mut task = task_ref.write().unwrap();
+        let task = task.down_to_task_mut().unwrap();
+        if task.is_scheduled() || task.is_done() {
+            return;
+        }
+        task.set_scheduled();
+        if let TaskKind::Sleep(duration) = task.kind() {
+            let deadline = self.now() + *duration;
+            self.timers.push((deadline, task_ref.clone()));
+        } else {
+            self.ready.push_back(task_ref.clone());
+        }
+    }
+
+    /// Make waiter wait for the task with the specified ID to finish.
+    pub fn wait(&mut self, task_id: usize, waiter: ObjectRef) {
+        self.waiters.entry(task_id).or_default().push(waiter);
+    }
+
+    /// Make the tasks waiting for the task with the specified ID ready
+    /// to run.
+    pub fn wake(&mut self, task_id: usize) {
+        if let Some(waiters) = self.waiters.remove(&task_id) {
+            self.ready.extend(waiters);
+        }
+    }
+
+    pub fn next_ready(&mut self) -> Option<ObjectRef> {
+        self.ready.pop_front()
+    }
+
+    /// Wait for the earliest timer to expire and return its task.
+    /// Timers with the same deadline expire in the order they were
+    /// started. `None` is returned if there are no timers.
+    pub fn next_timer(&mut self) -> Option<ObjectRef> {
+        let (index, deadline) = self
+            .timers
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (deadline, _))| *deadline)
+            .map(|(index, (deadline, _))| (index, *deadline))?;
+        self.sleep_until(deadline);
+        Some(self.timers.remove(index).1)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now(&self) -> Time {
+        Instant::now()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn sleep_until(&mut self, deadline: Time) {
+        let now = Instant::now();
+        if deadline > now {
+            thread::sleep(deadline - now);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn now(&self) -> Time {
+        self.simulated_now
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn sleep_until(&mut self, deadline: Time) {
+        self.simulated_now = self.simulated_now.max(deadline);
+    }
+
+    pub fn suspend(&mut self, task_id: usize, state: SuspendedTask) {
+        self.suspended.insert(task_id, state);
+    }
+
+    pub fn take_suspended(&mut self, task_id: usize) -> Option<SuspendedTask> {
+        self.suspended.remove(&task_id)
+    }
+}
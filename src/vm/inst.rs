@@ -1,6 +1,15 @@
 use crate::op::{BinaryOperator, CompareOperator, InplaceOperator, UnaryOperator};
-use crate::source::Location;
-
+use crate::util::Symbol;
+
+/// Instructions are kept small (32 bytes) since they're iterated over in
+/// the VM's main loop. Names are interned as symbols, source locations
+/// are kept in a side table in the code unit (see `LocationTable`), and
+/// the rare instructions with larger args have them boxed.
+///
+/// Operands such as jump distances and constant indexes are full-width
+/// `usize`s rather than being packed into narrower fields, so large
+/// (e.g., machine-generated) modules can't overflow them.
+///
 /// NOTE: When adding or removing instructions, the PartialEq impl
 ///       below must also be updated.
 #[derive(Debug)]
@@ -10,6 +19,9 @@ pub enum Inst {
     // Pop TOS and discard it.
     Pop,
 
+    // Duplicate the top N items on the stack, preserving their order.
+    DupTop(usize),
+
     // Global constants are shared globally by all code units.
     LoadGlobalConst(usize),
 
@@ -25,35 +37,67 @@ pub enum Inst {
     ScopeStart,
     ScopeEnd,
 
-    StatementStart(Location, Location),
-
     // Other constants are local to a given code unit.
     LoadConst(usize),
 
-    DeclareVar(String),
-    AssignVar(String),
+    DeclareVar(Symbol),
+    AssignVar(Symbol),
 
     // Args: name, offset
     //
     // `offset` is the number of scopes above the current scope to start
     // the search. 0 means the current scope, 1 means the parent scope,
     // and so on.
-    LoadVar(String, usize),
+    LoadVar(Symbol, usize),
+
+    // Declare the local vars of a block scope in slot order. This
+    // comes right after ScopeStart so that each var's index in the
+    // scope's namespace is the slot assigned by the compiler.
+    DeclareLocals(Vec<Symbol>),
+
+    // Args: offset, index
+    //
+    // Load local var from slot `index` in the scope `offset` scopes
+    // above the current scope. Unlike LoadVar, no name lookup is done.
+    LoadLocal(usize, usize),
+
+    // Assign TOS to the local var in slot `index` of the current scope.
+    StoreLocal(usize),
+
+    // Args: offset, index
+    //
+    // Load a value hoisted out of a loop by the optimizer from slot
+    // `index` of the scope `offset` scopes above the current scope.
+    // Unlike LoadLocal, the value is loaded as a temporary, the same as
+    // the load it replaces (e.g., LoadGlobal).
+    LoadHoisted(usize, usize),
 
     // Load module global
-    LoadGlobal(String),
+    LoadGlobal(Symbol),
+
+    // Assign TOS to module global from a function (see `global`).
+    // Globals that are assigned this way are stored in cells, which
+    // are shared by the module and the context it's executed in.
+    StoreGlobal(Symbol),
 
     // Load builtin
-    LoadBuiltin(String),
+    LoadBuiltin(Symbol),
 
     // These are analogous to AssignVar and LoadVar. Assignment wraps
     // the value in a cell so that it can be shared. Loading unwraps the
     // value.
-    AssignCell(String),
-    LoadCell(String),
+    AssignCell(Symbol),
+    LoadCell(Symbol),
 
     // Load captured value to TOS (a special case of LoadCell).
-    LoadCaptured(String),
+    LoadCaptured(Symbol),
+
+    // Replace the cells in the specified slots of the current scope
+    // with new cells holding the same values. Slots that don't hold
+    // cells are skipped. This runs at the start of each iteration of a
+    // loop so that closures created in different iterations capture
+    // different cells.
+    RenewCells(Vec<usize>),
 
     // Jumps -----------------------------------------------------------
     //
@@ -82,16 +126,72 @@ pub enum Inst {
     // If top of stack is NOT nil, jump to address. Otherwise, continue.
     JumpIfNotNil(usize, bool, usize),
 
+    // Error handling --------------------------------------------------
+    //
+    // Push a handler frame. The arg is the address of the handler
+    // relative to this instruction (always forward). When a catchable
+    // runtime error occurs while the handler is active, the stacks are
+    // unwound to where they were when the handler was pushed, the `Err`
+    // object corresponding to the error is pushed onto the stack, and
+    // execution resumes at the handler.
+    PushHandler(usize),
+
+    // Pop the current handler frame.
+    PopHandler,
+
+    // If TOS is an `Err` object (and not `ok`), pop and raise it.
+    RaiseIfErr,
+
+    // Pop TOS, which must be an `Err` object, and raise it.
+    Raise,
+
     UnaryOp(UnaryOperator),
     BinaryOp(BinaryOperator),
     CompareOp(CompareOperator),
     InplaceOp(InplaceOperator),
 
+    // Get attribute of object at TOS-1 by name at TOS. This is the
+    // same as `BinaryOp(Dot)` except that, if the object is nil and
+    // doesn't have the attribute, the resulting `Err` includes the
+    // arg, which is the source text of the object expression.
+    GetAttr(Box<str>),
+
+    // Get item from object at TOS-1 using key at TOS. For sequences,
+    // the key must be an index; for other objects (e.g., maps), the
+    // key is looked up by its string value.
+    GetItem,
+
+    // Pop sequence at TOS and push its N items in reverse order, so
+    // the first item ends up at TOS, raising an error if the sequence
+    // doesn't have exactly N items. This is used when destructuring
+    // (e.g., `a, b = b, a`).
+    UnpackSequence(usize),
+
+    // Assign value at TOS to attribute of object at TOS-2. The name
+    // (or index) of the attribute is at TOS-1. The value is pushed
+    // back onto the stack as the result.
+    SetAttr,
+
+    // Assign value at TOS to item of object at TOS-2 using key at
+    // TOS-1 (see GET_ITEM). The value is pushed back onto the stack as
+    // the result.
+    SetItem,
+
     // Call function with N values from top of stack. The args are
     // ordered such that the 1st arg is at TOS and other args are below
     // it.
     Call(usize),
 
+    // Like CALL, but used when the call is the last operation before
+    // RETURN. The call is made after the current function returns so
+    // that it can reuse the function's call frame.
+    TailCall(usize),
+
+    // Wait for the task at TOS to finish and replace it with the
+    // task's result. If the task isn't done, the current task is
+    // suspended and AWAIT is run again when it's resumed.
+    Await,
+
     // RETURN is a jump target at the end of a function. Its only
     // purpose is to serve as a jump target for explicit returns.
     Return,
@@ -101,10 +201,20 @@ pub enum Inst {
     MakeTuple(usize),
     MakeList(usize),
     MakeMap(usize),
+    MakeSet(usize),
+
+    // These append the top N items on the stack to the list or map
+    // below them. They're used to build large literals in chunks.
+    ExtendList(usize),
+    ExtendMap(usize),
+
+    // Convert list at TOS to tuple or set.
+    ListToTuple,
+    ListToSet,
 
     // Capture set for function--a list of names for the function to
     // capture. If empty, a regular function will be created.
-    CaptureSet(Vec<String>),
+    CaptureSet(Vec<Symbol>),
 
     // Make function or closure depending on capture set. MAKE_FUNC
     // expects the following entries at TOS:
@@ -113,7 +223,16 @@ pub enum Inst {
     //     func: Func       (added by LOAD_CONST)
     MakeFunc,
 
-    LoadModule(String),
+    // Make custom type. Args: (type name, field names), number of
+    // members. MAKE_TYPE expects the type's members to be at TOS as
+    // name/value pairs (similar to MAKE_MAP).
+    MakeType(Box<(String, Vec<String>)>, usize),
+
+    // Make enum type. Args: (type name, variant names).
+    MakeEnum(Box<(String, Vec<String>)>),
+
+    // Arg: module name
+    LoadModule(Symbol),
 
     Halt(u8),
     HaltTop,
@@ -123,10 +242,10 @@ pub enum Inst {
     // Placeholders are inserted during compilation and later updated.
     // All placeholders must be replaced or a runtime error will be
     // thrown.
-    Placeholder(usize, Box<Inst>, String), // address, instruction, error message
-    FreeVarPlaceholder(usize, String),     // address, var name
-    BreakPlaceholder(usize, usize),        // jump address, scope depth
-    ContinuePlaceholder(usize, usize),     // jump address, scope depth
+    Placeholder(usize, Box<(Inst, String)>), // address, (instruction, error message)
+    FreeVarPlaceholder(usize, Symbol),       // address, var name
+    BreakPlaceholder(usize, usize),          // jump address, scope depth
+    ContinuePlaceholder(usize, usize),       // jump address, scope depth
 
     // NOTE: This is used for explicit return statements. It will be
     //       replaced with a jump to a RETURN target.
@@ -159,6 +278,7 @@ impl PartialEq for Inst {
         match (self, other) {
             (NoOp, NoOp) => true,
             (Pop, Pop) => true,
+            (DupTop(a), DupTop(b)) => a == b,
             (LoadGlobalConst(a), LoadGlobalConst(b)) => a == b,
             (LoadNil, LoadNil) => true,
             (LoadTrue, LoadTrue) => true,
@@ -166,31 +286,59 @@ impl PartialEq for Inst {
             (LoadAlways, LoadAlways) => true,
             (LoadEmptyStr, LoadEmptyStr) => true,
             (LoadEmptyTuple, LoadEmptyTuple) => true,
+            (LoadNewline, LoadNewline) => true,
             (ScopeStart, ScopeStart) => true,
             (ScopeEnd, ScopeEnd) => true,
-            (StatementStart(..), StatementStart(..)) => true,
             (LoadConst(a), LoadConst(b)) => a == b,
             (DeclareVar(a), DeclareVar(b)) => a == b,
             (AssignVar(a), AssignVar(b)) => a == b,
             (LoadVar(a, i), LoadVar(b, j)) => (a, i) == (b, j),
+            (DeclareLocals(a), DeclareLocals(b)) => a == b,
+            (LoadLocal(a, i), LoadLocal(b, j)) => (a, i) == (b, j),
+            (StoreLocal(a), StoreLocal(b)) => a == b,
+            (LoadHoisted(a, i), LoadHoisted(b, j)) => (a, i) == (b, j),
+            (LoadGlobal(a), LoadGlobal(b)) => a == b,
+            (LoadBuiltin(a), LoadBuiltin(b)) => a == b,
+            (StoreGlobal(a), StoreGlobal(b)) => a == b,
             (AssignCell(a), AssignCell(b)) => a == b,
             (LoadCell(a), LoadCell(b)) => a == b,
             (LoadCaptured(a), LoadCaptured(b)) => a == b,
+            (RenewCells(a), RenewCells(b)) => a == b,
             (Jump(a, b, c), Jump(d, e, f)) => (a, b, c) == (d, e, f),
             (JumpPushNil(a, b, c), JumpPushNil(d, e, f)) => (a, b, c) == (d, e, f),
+            (JumpIf(a, b, c), JumpIf(d, e, f)) => (a, b, c) == (d, e, f),
             (JumpIfNot(a, b, c), JumpIfNot(d, e, f)) => (a, b, c) == (d, e, f),
+            (JumpIfNotNil(a, b, c), JumpIfNotNil(d, e, f)) => (a, b, c) == (d, e, f),
+            (PushHandler(a), PushHandler(b)) => a == b,
+            (PopHandler, PopHandler) => true,
+            (RaiseIfErr, RaiseIfErr) => true,
+            (Raise, Raise) => true,
             (UnaryOp(a), UnaryOp(b)) => a == b,
             (BinaryOp(a), BinaryOp(b)) => a == b,
             (CompareOp(a), CompareOp(b)) => a == b,
             (InplaceOp(a), InplaceOp(b)) => a == b,
+            (GetAttr(a), GetAttr(b)) => a == b,
+            (GetItem, GetItem) => true,
+            (UnpackSequence(a), UnpackSequence(b)) => a == b,
+            (SetAttr, SetAttr) => true,
+            (SetItem, SetItem) => true,
             (Call(a), Call(b)) => a == b,
+            (TailCall(a), TailCall(b)) => a == b,
+            (Await, Await) => true,
             (Return, Return) => true,
             (MakeString(a), MakeString(b)) => a == b,
             (MakeTuple(a), MakeTuple(b)) => a == b,
             (MakeList(a), MakeList(b)) => a == b,
             (MakeMap(a), MakeMap(b)) => a == b,
+            (MakeSet(a), MakeSet(b)) => a == b,
+            (ExtendList(a), ExtendList(b)) => a == b,
+            (ExtendMap(a), ExtendMap(b)) => a == b,
+            (ListToTuple, ListToTuple) => true,
+            (ListToSet, ListToSet) => true,
             (CaptureSet(a), CaptureSet(b)) => a == b,
             (MakeFunc, MakeFunc) => true,
+            (MakeType(a, b), MakeType(c, d)) => (a, b) == (c, d),
+            (MakeEnum(a), MakeEnum(b)) => a == b,
             (LoadModule(a), LoadModule(b)) => a == b,
             (Halt(a), Halt(b)) => a == b,
             (HaltTop, HaltTop) => true,
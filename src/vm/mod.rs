@@ -1,8 +1,11 @@
 pub use result::VMState;
-pub use result::{CallDepth, RuntimeErr};
-pub use vm::{DEFAULT_MAX_CALL_DEPTH, VM};
+pub use result::{CallDepth, RuntimeErr, StepResult};
+pub use vm::{
+    CancelHandle, ImportResolver, OutputHandler, TraceFrame, DEFAULT_MAX_CALL_DEPTH,
+    MAX_VALUE_STACK_SIZE, VM,
+};
 
-pub(crate) use code::Code;
+pub(crate) use code::{Code, LocationTable};
 pub(crate) use context::ModuleExecutionContext;
 pub(crate) use inst::Inst;
 pub(crate) use inst::PrintFlags;
@@ -14,6 +17,10 @@ pub(crate) mod globals;
 
 mod code;
 mod context;
+mod debugger;
+mod event_loop;
 mod inst;
+mod num;
+mod pool;
 mod result;
 mod vm;
@@ -0,0 +1,116 @@
+//! Unboxed numbers.
+//!
+//! Ints and Floats are normally objects behind an `RwLock`, so every
+//! arithmetic operation has to lock both operands and allocate (or take
+//! from the temp pool) an object for the result. Numbers that fit in
+//! an `i64` or `f64` are kept unboxed on the value stack instead, and
+//! they're boxed only when they're used as objects (e.g., when they're
+//! assigned to a var or passed to a function).
+use num_traits::ToPrimitive;
+
+use crate::op::{BinaryOperator, CompareOperator};
+use crate::types::{new, ObjectRef, ObjectTrait};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Num {
+    Int(i64),
+    Float(f64),
+}
+
+impl Num {
+    /// Get the unboxed value of an Int or Float. `None` is returned for
+    /// other types and for Ints that don't fit in an `i64`.
+    pub fn from_obj(obj: &dyn ObjectTrait) -> Option<Self> {
+        if let Some(value) = obj.get_int_val() {
+            value.to_i64().map(Self::Int)
+        } else {
+            obj.get_float_val().map(|value| Self::Float(*value))
+        }
+    }
+
+    pub fn to_obj(self) -> ObjectRef {
+        match self {
+            Self::Int(value) => new::int(value),
+            Self::Float(value) => new::float(value),
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        match self {
+            Self::Int(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+
+    /// Apply an arithmetic operation. `None` is returned for operations
+    /// that aren't handled here and when an Int operation overflows, in
+    /// which case the caller should fall back to the regular object
+    /// methods.
+    ///
+    /// NOTE: The results *must* be the same as the results of the
+    ///       corresponding `ObjectTrait` methods.
+    pub fn binary_op(self, op: &BinaryOperator, rhs: Self) -> Option<Self> {
+        use BinaryOperator::{Add, Div, FloorDiv, Mul, Sub};
+        if let (Self::Int(a), Self::Int(b)) = (self, rhs) {
+            let value = match op {
+                Add => a.checked_add(b)?,
+                Sub => a.checked_sub(b)?,
+                Mul => a.checked_mul(b)?,
+                // Int division always returns a Float
+                Div => return Some(Self::Float(a as f64 / b as f64)),
+                // Division by zero falls back so the error is raised
+                FloorDiv => floor_div(a, b)?,
+                _ => return None,
+            };
+            return Some(Self::Int(value));
+        }
+        let (a, b) = (self.to_f64(), rhs.to_f64());
+        let value = match op {
+            Add => a + b,
+            Sub => a - b,
+            Mul => a * b,
+            Div => a / b,
+            _ => return None,
+        };
+        Some(Self::Float(value))
+    }
+
+    /// Compare two numbers of the same type. `None` is returned for
+    /// operations that aren't handled here.
+    ///
+    /// NOTE: Float equality isn't handled because an object is always
+    ///       equal to itself, even when its value is NaN, and that
+    ///       can't be determined without the objects.
+    pub fn compare(self, op: &CompareOperator, rhs: Self) -> Option<bool> {
+        use CompareOperator::*;
+        match (self, rhs) {
+            (Self::Int(a), Self::Int(b)) => match op {
+                IsEqual => Some(a == b),
+                NotEqual => Some(a != b),
+                LessThan => Some(a < b),
+                LessThanOrEqual => Some(a <= b),
+                GreaterThan => Some(a > b),
+                GreaterThanOrEqual => Some(a >= b),
+                _ => None,
+            },
+            (Self::Float(a), Self::Float(b)) => match op {
+                LessThan => Some(a < b),
+                GreaterThan => Some(a > b),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Divide, rounding toward negative infinity. `None` is returned on
+/// division by zero and overflow.
+fn floor_div(a: i64, b: i64) -> Option<i64> {
+    let quotient = a.checked_div(b)?;
+    let remainder = a.checked_rem(b)?;
+    if remainder != 0 && (remainder < 0) != (b < 0) {
+        quotient.checked_sub(1)
+    } else {
+        Some(quotient)
+    }
+}
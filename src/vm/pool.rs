@@ -0,0 +1,117 @@
+//! Pool of Int and Float temporaries.
+//!
+//! Arithmetic creates a new object for every intermediate result
+//! (e.g., `a * b` in `a * b + c`). When an operand is popped off the
+//! stack and nothing else refers to it, it's returned to the pool and
+//! its allocation is reused for the next result of the same type.
+use std::sync::Arc;
+
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+
+use crate::op::BinaryOperator;
+use crate::types::{new, ObjectRef, ObjectTrait};
+
+use super::globals::SHARED_INT_MAX_BIGINT;
+use super::num::Num;
+
+/// Max number of objects of each type kept in the pool.
+const MAX_POOL_SIZE: usize = 64;
+
+#[derive(Default)]
+pub(crate) struct TempPool {
+    ints: Vec<ObjectRef>,
+    floats: Vec<ObjectRef>,
+}
+
+impl TempPool {
+    /// Apply an arithmetic operation to two Ints or Floats, taking the
+    /// result object from the pool if possible. `None` is returned for
+    /// other operations and operand types, in which case the caller
+    /// should fall back to the regular object methods.
+    ///
+    /// NOTE: The results *must* be the same as the results of the
+    ///       corresponding `ObjectTrait` methods.
+    pub fn binary_op(
+        &mut self,
+        op: &BinaryOperator,
+        a: &dyn ObjectTrait,
+        b: &dyn ObjectTrait,
+    ) -> Option<ObjectRef> {
+        use BinaryOperator::{Add, Mul, Sub};
+
+        if !matches!(op, Add | Sub | Mul) {
+            return None;
+        }
+
+        if let (Some(a), Some(b)) = (a.get_int_val(), b.get_int_val()) {
+            let value = match op {
+                Add => a + b,
+                Sub => a - b,
+                _ => a * b,
+            };
+            return Some(self.int(value));
+        }
+
+        let (a, b) = match (a.get_float_val(), b.get_float_val()) {
+            (Some(a), Some(b)) => (*a, *b),
+            (Some(a), None) => (*a, b.get_int_val()?.to_f64()?),
+            (None, Some(b)) => (a.get_int_val()?.to_f64()?, *b),
+            (None, None) => return None,
+        };
+        let value = match op {
+            Add => a + b,
+            Sub => a - b,
+            _ => a * b,
+        };
+        Some(self.float(value))
+    }
+
+    /// Get an Int with the specified value. Shared ints are never
+    /// pooled, so they're returned as usual.
+    pub fn int(&mut self, value: BigInt) -> ObjectRef {
+        if value.is_positive() && value <= *SHARED_INT_MAX_BIGINT {
+            return new::int(value);
+        }
+        if let Some(obj) = self.ints.pop() {
+            obj.write().unwrap().down_to_int_mut().unwrap().set_value(value);
+            obj
+        } else {
+            new::int(value)
+        }
+    }
+
+    pub fn float(&mut self, value: f64) -> ObjectRef {
+        if let Some(obj) = self.floats.pop() {
+            obj.write().unwrap().down_to_float_mut().unwrap().set_value(value);
+            obj
+        } else {
+            new::float(value)
+        }
+    }
+
+    /// Box an unboxed number.
+    pub fn num(&mut self, num: Num) -> ObjectRef {
+        match num {
+            Num::Int(value) => self.int(value.into()),
+            Num::Float(value) => self.float(value),
+        }
+    }
+
+    /// Return an Int or Float to the pool if nothing else refers to
+    /// it. Other objects are dropped as usual.
+    pub fn release(&mut self, mut obj: ObjectRef) {
+        let (is_int, is_float) = match Arc::get_mut(&mut obj) {
+            Some(lock) => {
+                let obj = lock.get_mut().unwrap();
+                (obj.is_int(), obj.is_float())
+            }
+            None => return,
+        };
+        if is_int && self.ints.len() < MAX_POOL_SIZE {
+            self.ints.push(obj);
+        } else if is_float && self.floats.len() < MAX_POOL_SIZE {
+            self.floats.push(obj);
+        }
+    }
+}
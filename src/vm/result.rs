@@ -3,10 +3,14 @@ use std::fmt::Formatter;
 
 use crate::compiler::CompErr;
 use crate::parser::ParseErr;
-use crate::types::ObjectRef;
+use crate::types::err_type::ErrKind;
+use crate::types::{new, ObjectRef};
+
+use super::num::Num;
 
 pub type CallDepth = usize;
 pub type VMExeResult = Result<(), RuntimeErr>;
+pub type StepResult = Result<VMState, RuntimeErr>;
 pub type RuntimeResult = Result<(), RuntimeErr>;
 pub type RuntimeObjResult = Result<ObjectRef, RuntimeErr>;
 pub type RuntimeBoolResult = Result<bool, RuntimeErr>;
@@ -22,16 +26,28 @@ pub enum VMState {
     Running,
     Idle(Option<ObjectRef>),
     Halted(u8),
+    // Stopped by `VM::step()` before the end of the code was reached.
+    // Contains the address of the next instruction to execute.
+    Paused(usize),
 }
 
+/// Vars are identified by the depth of the namespace they're defined
+/// in and their index in that namespace (rather than by name) so that
+/// loading a var doesn't require cloning its name.
 #[derive(Clone, Debug)]
 pub enum ValueStackKind {
     GlobalConstant(ObjectRef, usize),
     Constant(ObjectRef, usize),
-    Var(ObjectRef, usize, String),
-    CellVar(ObjectRef, usize, String),
+    // An Int or Float constant along with its unboxed value so that
+    // arithmetic on it doesn't need to lock the object.
+    NumConstant(ObjectRef, Num),
+    Var(ObjectRef, usize, usize),
+    CellVar(ObjectRef, usize, usize),
     Temp(ObjectRef),
     ReturnVal(ObjectRef),
+    // An unboxed result of arithmetic. It's boxed only when it's used
+    // as an object.
+    Num(Num),
 }
 
 // Runtime errors ------------------------------------------------------
@@ -90,6 +106,10 @@ impl RuntimeErr {
         Self::new(RuntimeErrKind::RecursionDepthExceeded(max_call_depth))
     }
 
+    pub fn value_stack_overflow(max_size: usize) -> Self {
+        Self::new(RuntimeErrKind::ValueStackOverflow(max_size))
+    }
+
     pub fn constant_not_found(index: usize) -> Self {
         Self::new(RuntimeErrKind::ConstantNotFound(index))
     }
@@ -121,6 +141,62 @@ impl RuntimeErr {
     pub fn arg_err<S: Into<String>>(message: S) -> Self {
         Self::new(RuntimeErrKind::ArgErr(message.into()))
     }
+
+    pub fn task_err<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::TaskErr(message.into()))
+    }
+
+    pub fn thread_err<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::ThreadErr(message.into()))
+    }
+
+    pub fn module_not_found<S: Into<String>>(name: S) -> Self {
+        Self::new(RuntimeErrKind::ModuleNotFound(name.into()))
+    }
+
+    pub fn import_err<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::ImportErr(message.into()))
+    }
+
+    pub fn unexpected_state<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::UnexpectedState(message.into()))
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(RuntimeErrKind::Cancelled)
+    }
+
+    pub fn raised(err_obj: ObjectRef) -> Self {
+        Self::new(RuntimeErrKind::Raised(err_obj))
+    }
+
+    /// Get the `Err` object corresponding to this error if it can be
+    /// caught by a `try` block. Exits, cancellations, recursion errors,
+    /// and internal VM errors can't be caught, so `None` is returned for
+    /// those.
+    pub fn to_err_obj(&self) -> Option<ObjectRef> {
+        use RuntimeErrKind::*;
+        let (kind, message) = match &self.kind {
+            Raised(err_obj) => return Some(err_obj.clone()),
+            AssertionFailed(message) => (ErrKind::Assertion, message.clone()),
+            TypeErr(message) => (ErrKind::Type, message.clone()),
+            NameErr(message) => (ErrKind::NameNotFound, message.clone()),
+            StringFormatErr(message) => (ErrKind::String, message.clone()),
+            IndexOutOfBounds(type_name, index) => {
+                (ErrKind::IndexOutOfBounds, format!("{type_name}: {index}"))
+            }
+            NotCallable(type_name) => {
+                (ErrKind::Type, format!("Object is not callable: {type_name}"))
+            }
+            ArgErr(message) => (ErrKind::Arg, message.clone()),
+            TaskErr(message) => (ErrKind::Task, message.clone()),
+            ThreadErr(message) => (ErrKind::Thread, message.clone()),
+            ModuleNotFound(name) => (ErrKind::ModuleNotFound, name.clone()),
+            ImportErr(message) => (ErrKind::Import, message.clone()),
+            _ => return None,
+        };
+        Some(new::err(kind, message, new::nil()))
+    }
 }
 
 impl fmt::Display for RuntimeErr {
@@ -135,6 +211,8 @@ pub enum RuntimeErrKind {
     ConfigValueNotSet(String),
     ConfigValueIsNotValid(String, String),
     Exit(u8),
+    // Execution was cancelled by the host (see `CancelHandle`).
+    Cancelled,
     AssertionFailed(String),
     EmptyStack,
     NotEnoughValuesOnStack(usize),
@@ -142,6 +220,7 @@ pub enum RuntimeErrKind {
     StackIndexOutOfBounds(usize),
     FrameIndexOutOfBounds(usize),
     RecursionDepthExceeded(CallDepth),
+    ValueStackOverflow(usize),
     ConstantNotFound(usize),
     CapturedVarNotFound(String),
     ExpectedVar(String),
@@ -154,6 +233,12 @@ pub enum RuntimeErrKind {
     IndexOutOfBounds(String, usize),
     NotCallable(String),
     ArgErr(String),
+    TaskErr(String),
+    ThreadErr(String),
+    Raised(ObjectRef),
+    ModuleNotFound(String),
+    ImportErr(String),
+    UnexpectedState(String),
 }
 
 impl fmt::Display for RuntimeErrKind {
@@ -3,49 +3,108 @@
 //! execute. After instructions are executed, it goes back into idle
 //! mode.
 use std::cmp;
+use std::io::{self, BufRead, Write};
+use std::mem;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
 use ctrlc;
 use indexmap::IndexMap;
 use num_traits::ToPrimitive;
 
-use crate::modules::get_module;
+use crate::modules::maybe_get_module;
 use crate::op::{BinaryOperator, CompareOperator, InplaceOperator, UnaryOperator};
 use crate::source::Location;
+use crate::types::cell::unwrap_cell;
+use crate::types::err_type::ErrKind;
+use crate::types::range;
 use crate::types::{
-    new, Args, Func, FuncTrait, IntrinsicFunc, Module, ObjectRef, ThisOpt,
+    new, Args, Func, FuncTrait, IntrinsicFunc, Module, ObjectRef, ObjectTrait, ThisOpt,
 };
-use crate::util::Stack;
+use crate::util::{Stack, Symbol};
 
 use super::code::Code;
-use super::context::ModuleExecutionContext;
+use super::context::{ModuleExecutionContext, Namespace};
+use super::debugger::{self, Command, Debugger};
+use super::event_loop::EventLoop;
 use super::globals;
 use super::inst::{Inst, PrintFlags};
+use super::num::Num;
+use super::pool::TempPool;
 use super::result::{
     CallDepth, PeekObjResult, PeekResult, PopNObjResult, PopNResult, PopObjResult,
-    PopResult, RuntimeErr, RuntimeObjResult, RuntimeResult, VMExeResult, VMState,
-    ValueStackKind,
+    PopResult, RuntimeBoolResult, RuntimeErr, RuntimeObjResult, RuntimeResult,
+    StepResult, VMExeResult, VMState, ValueStackKind,
 };
 
+/// Callback provided by the host (e.g., the executor) to load modules
+/// that haven't been registered yet. This allows modules to be loaded
+/// at runtime, such as modules imported inside functions, which are
+/// loaded lazily when the function is first called.
+///
+/// NOTE: Callbacks are shared with the VMs of threads spawned by FeInt
+///       code (see `std.thread`), so they have to be thread safe.
+pub type ImportResolver = Box<dyn Fn(&str) -> RuntimeObjResult + Send + Sync>;
+
+/// Callback provided by the host to capture output printed by FeInt
+/// code (e.g., when there's no stdout, as in the browser). It's called
+/// with the text and whether the text was printed to stderr.
+pub type OutputHandler = Box<dyn Fn(&str, bool) + Send + Sync>;
+
+/// Handle the host uses to cancel execution from another thread (e.g.,
+/// to stop a script that's taking too long) without sending a signal.
+/// The VM checks for cancellation along with SIGINT as it executes
+/// instructions and, when execution is cancelled, returns a `Cancelled`
+/// error, which can't be caught by FeInt code.
+///
+/// NOTE: Cancellation isn't noticed while an intrinsic function is
+///       running (e.g., during `sleep()`), only between instructions.
+#[derive(Clone)]
+pub struct CancelHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    /// Cancel the code that's executing. If nothing is executing, the
+    /// next code that's executed is cancelled instead.
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+}
+
 pub const DEFAULT_MAX_CALL_DEPTH: CallDepth =
     if cfg!(debug_assertions) { 256 } else { 1024 };
 
+/// Max number of values on the value stack. Normally, the stack stays
+/// small, but a runaway program could otherwise exhaust memory.
+pub const MAX_VALUE_STACK_SIZE: usize = 1 << 20;
+
 struct CallFrame {
     stack_pointer: usize,
+    // Depth of the frame's namespace in the context's namespace stack.
+    ctx_depth: usize,
     this_opt: ThisOpt,
     closure: Option<ObjectRef>,
+    // The function being called. This isn't set for intrinsic
+    // functions, which don't have source locations.
+    func: Option<ObjectRef>,
+    // Location of the statement that made the call.
+    call_loc: (Location, Location),
 }
 
 impl CallFrame {
     pub fn new(
         stack_pointer: usize,
+        ctx_depth: usize,
         this_opt: ThisOpt,
         closure: Option<ObjectRef>,
+        func: Option<ObjectRef>,
+        call_loc: (Location, Location),
     ) -> Self {
-        Self { stack_pointer, this_opt, closure }
+        Self { stack_pointer, ctx_depth, this_opt, closure, func, call_loc }
     }
 
     pub fn get_captured(&self, name: &str) -> RuntimeObjResult {
@@ -60,6 +119,54 @@ impl CallFrame {
     }
 }
 
+/// An entry in a traceback. Entries show where the top level code of a
+/// module and each function in the call chain was when an error
+/// occurred.
+#[derive(Clone, Debug)]
+pub struct TraceFrame {
+    /// Function name or `<module>` for top level code.
+    pub name: String,
+    pub module_path: String,
+    pub loc: (Location, Location),
+}
+
+/// Handler frames are pushed by PUSH_HANDLER when entering a `try`
+/// block. They record the state of the VM so that it can be restored
+/// when an error is caught.
+struct Handler {
+    addr: usize,
+    call_depth: usize,
+    scope_depth: usize,
+    value_stack_len: usize,
+    ctx_depth: usize,
+}
+
+/// Sizes of the VM's stacks. These are recorded before a task runs so
+/// that the part of the stacks belonging to the task can be split off
+/// when the task is suspended.
+#[derive(Clone, Copy, PartialEq)]
+struct StackDepths {
+    call: usize,
+    scope: usize,
+    value: usize,
+    handler: usize,
+    ctx: usize,
+}
+
+/// The state of a suspended task: where to resume and the parts of the
+/// stacks belonging to the task. Positions in the saved state (e.g.,
+/// call frame stack pointers) are absolute, so a task has to be resumed
+/// from the same stack depths it was suspended at.
+pub(super) struct SuspendedTask {
+    ip: usize,
+    depths: StackDepths,
+    call_stack: Vec<CallFrame>,
+    scope_stack: Vec<usize>,
+    value_stack: Vec<ValueStackKind>,
+    handler_stack: Vec<Handler>,
+    namespaces: Vec<Namespace>,
+}
+
 pub struct VM {
     pub(crate) ctx: ModuleExecutionContext,
     pub(crate) state: VMState,
@@ -75,14 +182,45 @@ pub struct VM {
     value_stack: Stack<ValueStackKind>,
     // Call stack. We manually track the size to avoid calling len().
     call_stack: Stack<CallFrame>,
+    // Handler stack for `try` blocks.
+    handler_stack: Stack<Handler>,
     // Maximum depth of "call stack" (quotes because there's no explicit
     // call stack).
     max_call_depth: CallDepth,
-    // The location of the current statement. Used for error reporting.
+    // The location of the current statement, which is updated as the
+    // instructions of each statement are reached. Used for error
+    // reporting.
     loc: (Location, Location),
+    // Path of the module whose top level code is being executed.
+    module_path: String,
+    // Captured when an error propagates out of a function call without
+    // being handled (see `take_traceback()`).
+    traceback: Vec<TraceFrame>,
     // SIGINT (Ctrl-C) handling.
     handle_sigint: bool, // whether the VM should handle SIGINT
     sigint_flag: Arc<AtomicBool>, // indicates SIGINT was sent
+    // Set by the host via a `CancelHandle`. Forked VMs share this flag
+    // and the SIGINT flag with the VM they were forked from but leave
+    // clearing them to that VM, so cancelling stops all of them.
+    cancel_flag: Arc<AtomicBool>,
+    is_fork: bool,
+    // Counts instructions between checks of the cancel and SIGINT
+    // flags. It's kept across calls to `run_code()` so that code that
+    // makes many short calls (e.g., tail calls) is still checked.
+    poll_counter: u32,
+    // Used to load modules that aren't registered yet.
+    import_resolver: Option<Arc<ImportResolver>>,
+    // Used to capture output instead of writing it to stdout/stderr.
+    output_handler: Option<Arc<OutputHandler>>,
+    // Reusable Int and Float objects for arithmetic results.
+    temp_pool: TempPool,
+    // Call made in tail position, which is deferred until the calling
+    // function returns.
+    tail_call: Option<(ObjectRef, Args)>,
+    // Set while tasks are being run by `run_until_complete()`.
+    event_loop: Option<EventLoop>,
+    // Attached by the host to debug code interactively.
+    debugger: Option<Debugger>,
 }
 
 unsafe impl Send for VM {}
@@ -103,25 +241,132 @@ impl VM {
             scope_stack: Stack::with_capacity(max_call_depth),
             value_stack: Stack::with_capacity(max_call_depth * 8),
             call_stack: Stack::with_capacity(max_call_depth),
+            handler_stack: Stack::new(),
             max_call_depth,
             loc: (Location::default(), Location::default()),
+            module_path: String::new(),
+            traceback: vec![],
             handle_sigint: false,
             sigint_flag: Arc::new(AtomicBool::new(false)),
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+            is_fork: false,
+            poll_counter: 0,
+            import_resolver: None,
+            output_handler: None,
+            temp_pool: TempPool::default(),
+            tail_call: None,
+            event_loop: None,
+            debugger: None,
+        }
+    }
+
+    /// Create a VM for running code in another thread with the
+    /// specified globals, which should be copies of this VM's globals
+    /// (see `std.thread`). The new VM shares this VM's import resolver
+    /// and output handler and is stopped when this VM is cancelled or
+    /// interrupted but is otherwise independent of this VM.
+    #[cfg(feature = "std-full")]
+    pub(crate) fn fork(&self, globals: IndexMap<Symbol, ObjectRef>) -> Self {
+        let ctx = ModuleExecutionContext::with_globals(globals);
+        let mut vm = Self::new(ctx, self.max_call_depth);
+        vm.module_path = self.module_path.clone();
+        vm.handle_sigint = self.handle_sigint;
+        vm.sigint_flag = self.sigint_flag.clone();
+        vm.cancel_flag = self.cancel_flag.clone();
+        vm.is_fork = true;
+        vm.import_resolver = self.import_resolver.clone();
+        vm.output_handler = self.output_handler.clone();
+        vm
+    }
+
+    /// Set the callback used to load modules that aren't registered
+    /// when they're imported.
+    pub fn set_import_resolver(&mut self, resolver: ImportResolver) {
+        self.import_resolver = Some(Arc::new(resolver));
+    }
+
+    /// Set the callback used to capture output instead of writing it
+    /// to stdout/stderr.
+    pub fn set_output_handler(&mut self, handler: OutputHandler) {
+        self.output_handler = Some(Arc::new(handler));
+    }
+
+    /// Attach a debugger that reads commands from `input`. Execution
+    /// stops at the next statement (see `debugger`).
+    pub fn attach_debugger(&mut self, input: Box<dyn BufRead>) {
+        self.debugger = Some(Debugger::new(input));
+    }
+
+    /// Write text to stdout or, if `is_err` is set, stderr. If an
+    /// output handler is set, the text is passed to it instead.
+    pub fn write_output(&self, text: &str, is_err: bool) {
+        match &self.output_handler {
+            Some(handler) => handler(text, is_err),
+            None if is_err => eprint!("{text}"),
+            None => print!("{text}"),
+        }
+    }
+
+    /// Get a registered module or, if the module isn't registered yet,
+    /// load it via the import resolver. Modules loaded by the resolver
+    /// are expected to be registered so they're only loaded once.
+    pub fn import_module(&self, name: &str) -> RuntimeObjResult {
+        if let Some(module) = maybe_get_module(name) {
+            Ok(module)
+        } else if let Some(resolver) = &self.import_resolver {
+            resolver(name)
+        } else {
+            Err(RuntimeErr::module_not_found(name))
         }
     }
 
     pub fn execute_module(&mut self, module: &Module, start: usize) -> VMExeResult {
         self.reset();
+        self.module_path = module.path().to_owned();
+        self.traceback.clear();
         self.execute_code(module, module.code(), start)
     }
 
     pub fn execute_func(&mut self, func: &Func, start: usize) -> VMExeResult {
-        let module = func.module();
+        let module = FuncTrait::module(func);
         let module = module.read().unwrap();
         let module = module.down_to_mod().unwrap();
         self.execute_code(module, func.code(), start)
     }
 
+    /// Execute a chunk of code in the context of a module, continuing
+    /// from where the previous chunk left off. Unlike `execute_module`,
+    /// the VM is *not* reset first, so its state (e.g., globals) is
+    /// preserved between chunks. This is used to execute code
+    /// incrementally, as in the REPL.
+    ///
+    /// The previous chunk must have left the VM idle with empty stacks
+    /// and no nested scopes. If it didn't, an error is returned, the
+    /// chunk isn't executed, and the VM is unwound so that the next
+    /// chunk can be executed.
+    ///
+    /// If the chunk fails, the VM is likewise unwound back to its idle
+    /// state, and globals declared by the failed statement are removed.
+    /// Globals from statements that completed before the failure are
+    /// kept.
+    pub fn continue_with(&mut self, module: &Module, code: &Code) -> VMExeResult {
+        if let Err(err) = self.check_idle() {
+            if !matches!(self.state, VMState::Halted(_)) {
+                self.unwind();
+            }
+            return Err(err);
+        }
+        self.module_path = module.path().to_owned();
+        self.traceback.clear();
+        self.ctx.clear_unassigned_globals();
+        let result = self.execute_code(module, code, 0);
+        if result.is_err() && !matches!(self.state, VMState::Halted(_)) {
+            self.unwind();
+            self.ctx.remove_unassigned_globals();
+        }
+        result
+    }
+
     /// Execute the given code object's instructions and return the VM's
     /// state.
     ///
@@ -131,11 +376,84 @@ impl VM {
     /// If a HALT instruction is *not* encountered, the VM will go
     /// "idle"--it will maintain its internal state and await further
     /// instructions.
+    ///
+    /// If a catchable error occurs while a handler pushed by this code
+    /// is active, execution resumes at the handler.
     pub fn execute_code(
+        &mut self,
+        module: &Module,
+        code: &Code,
+        ip: usize,
+    ) -> VMExeResult {
+        let handler_base = self.handler_stack.len();
+        self.execute_code_with_handlers(module, code, ip, handler_base)
+    }
+
+    /// Like `execute_code()`, but errors can be caught by any handler
+    /// pushed at or above `handler_base`. This is used when resuming a
+    /// task, since the task's handlers are restored before its code is
+    /// executed again.
+    fn execute_code_with_handlers(
         &mut self,
         module: &Module,
         code: &Code,
         mut ip: usize,
+        handler_base: usize,
+    ) -> VMExeResult {
+        loop {
+            match self.run_code(module, code, ip, None) {
+                Err(err) => match self.catch_err(handler_base, &err) {
+                    Some(handler_addr) => ip = handler_addr,
+                    None => break Err(err),
+                },
+                result => break result,
+            }
+        }
+    }
+
+    /// Execute at most `n` of the given code object's instructions,
+    /// starting at `ip`, and return the VM's state.
+    ///
+    /// If the end of the code isn't reached, the VM is paused with the
+    /// address of the next instruction (`VMState::Paused(ip)`), and
+    /// execution can be resumed by calling `step()` again with that
+    /// address. Otherwise, the VM goes idle or halts as it would with
+    /// `execute_code()`.
+    ///
+    /// Function calls are executed to completion and count as a single
+    /// instruction. If an error is caught, the VM is paused at the
+    /// start of the handler.
+    ///
+    /// NOTE: All active handlers are considered when an error occurs,
+    ///       so this is intended for stepping through top level code.
+    pub fn step(
+        &mut self,
+        module: &Module,
+        code: &Code,
+        ip: usize,
+        n: usize,
+    ) -> StepResult {
+        match self.run_code(module, code, ip, Some(n)) {
+            Ok(()) => Ok(self.state.clone()),
+            Err(err) => match self.catch_err(0, &err) {
+                Some(handler_addr) => {
+                    self.state = VMState::Paused(handler_addr);
+                    Ok(self.state.clone())
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// The instruction loop used by `execute_code()` and `step()`. If
+    /// `max_steps` is set, the VM is paused after that many
+    /// instructions.
+    fn run_code(
+        &mut self,
+        module: &Module,
+        code: &Code,
+        mut ip: usize,
+        max_steps: Option<usize>,
     ) -> VMExeResult {
         use Inst::*;
 
@@ -149,13 +467,36 @@ impl VM {
                 self.set_idle(None);
                 return Ok(());
             }
-            cmp::Ordering::Greater => panic!("Code start index out of bounds"),
+            cmp::Ordering::Greater => {
+                return Err(RuntimeErr::unexpected_state(format!(
+                    "Code start index out of bounds: {ip}"
+                )));
+            }
+        }
+
+        if max_steps == Some(0) {
+            self.state = VMState::Paused(ip);
+            return Ok(());
         }
 
-        let mut sigint_counter = 0u32;
+        let mut step_counter = 0usize;
         let mut jump_ip = None;
+        // Addresses of the instructions that belong to the statement at
+        // the current location.
+        let mut loc_span = 0..0;
 
         loop {
+            if !loc_span.contains(&ip) {
+                let (span, loc) = code.location_span(ip);
+                if let Some(loc) = loc {
+                    self.loc = loc;
+                    if self.debugger.is_some() && span.start == ip {
+                        self.debug_statement(module)?;
+                    }
+                }
+                loc_span = span;
+            }
+
             match &code[ip] {
                 NoOp => {
                     // do nothing
@@ -163,9 +504,18 @@ impl VM {
                 Pop => {
                     self.pop()?;
                 }
+                DupTop(n) => {
+                    let kinds = self.pop_n(*n)?;
+                    for kind in kinds.iter() {
+                        self.push(kind.clone());
+                    }
+                    for kind in kinds {
+                        self.push(kind);
+                    }
+                }
                 // Well-known global constants
                 LoadNil => {
-                    self.push_global_const(globals::NIL_INDEX)?;
+                    self.push_nil()?;
                 }
                 LoadTrue => {
                     self.push_global_const(globals::TRUE_INDEX)?;
@@ -194,34 +544,59 @@ impl VM {
                     self.enter_scope();
                 }
                 ScopeEnd => {
-                    self.exit_scope();
-                }
-                StatementStart(start, end) => {
-                    self.loc = (*start, *end);
+                    self.exit_scope()?;
                 }
                 LoadConst(index) => {
                     let obj = code.get_const(*index)?.clone();
-                    self.push(ValueStackKind::Constant(obj, *index));
+                    if let Some(num) = code.get_num_const(*index) {
+                        self.push(ValueStackKind::NumConstant(obj, num));
+                    } else {
+                        self.push(ValueStackKind::Constant(obj, *index));
+                    }
                 }
                 // Modules
                 LoadModule(name) => {
-                    let module = get_module(name.as_str());
+                    let module = self.import_module(name.as_str())?;
                     self.push_temp(module);
                 }
                 // Vars
                 DeclareVar(name) => {
+                    let name = *name;
                     if self.ctx.get_var_in_current_ns(name).is_err() {
-                        self.ctx.declare_var(name.as_str());
+                        self.ctx.declare_var(name);
                     }
                 }
                 AssignVar(name) => {
+                    let name = *name;
+                    let obj = self.pop_obj()?;
+                    let (depth, index) = self.ctx.assign_var(name, obj)?;
+                    self.push_var(depth, index)?;
+                }
+                DeclareLocals(names) => {
+                    for name in names.iter() {
+                        self.ctx.declare_var(*name);
+                    }
+                }
+                LoadLocal(offset, index) => {
+                    let depth = self.ctx.current_depth() - offset;
+                    self.push_var(depth, *index)?;
+                }
+                StoreLocal(index) => {
                     let obj = self.pop_obj()?;
-                    let depth = self.ctx.assign_var(name, obj)?;
-                    self.push_var(depth, name.clone())?;
+                    let depth = self.ctx.current_depth();
+                    self.ctx.assign_var_at(depth, *index, obj)?;
+                    self.push_var(depth, *index)?;
+                }
+                LoadHoisted(offset, index) => {
+                    let depth = self.ctx.current_depth() - offset;
+                    let obj = self.ctx.get_var_at(depth, *index)?;
+                    self.push_temp(obj);
                 }
                 LoadVar(name, offset) => {
-                    if let Ok(depth) = self.ctx.get_var_depth(name, *offset) {
-                        self.push_var(depth, name.clone())?;
+                    let name = *name;
+                    if let Ok((depth, index)) = self.ctx.get_var_location(name, *offset)
+                    {
+                        self.push_var(depth, index)?;
                     } else {
                         return Err(RuntimeErr::name_err(format!(
                             "Var not found: {name}"
@@ -229,7 +604,8 @@ impl VM {
                     }
                 }
                 LoadGlobal(name) => {
-                    if let Some(obj) = module.get_global(name) {
+                    let name = *name;
+                    if let Some(obj) = module.get_global(name.as_str()) {
                         self.push_temp(obj);
                     } else if let Some(obj) = self.ctx.get_global(name) {
                         // XXX: This branch allows a global to refer to
@@ -254,26 +630,33 @@ impl VM {
                         // var in the runtime context, so the module
                         // doesn't need to be consulted (or have `x`
                         // initialized as a global at this point).
-                        self.push_temp(obj);
+                        self.push_temp(unwrap_cell(obj));
                     } else {
                         return Err(RuntimeErr::name_err(format!(
                             "Global not found: {name}"
                         )));
                     }
                 }
+                StoreGlobal(name) => {
+                    let value = self.pop_obj()?;
+                    self.store_global(module, *name, value.clone())?;
+                    self.push_temp(value);
+                }
                 LoadBuiltin(name) => {
-                    let obj = self.ctx.get_builtin(name);
+                    let name = *name;
+                    let obj = self.ctx.get_builtin(name.as_str())?;
                     self.push_temp(obj);
                 }
                 AssignCell(name) => {
                     // Store TOS value into cell. This is similar to
                     // AssignVar except that it wraps the TOS value in
                     // a cell before storing it as var.
+                    let name = *name;
                     let value = self.pop_obj()?;
                     // Get the var, which might not already be a cell.
                     let var_ref = self.ctx.get_var(name, 0)?;
                     let mut var = var_ref.write().unwrap();
-                    let depth = if let Some(cell) = var.down_to_cell_mut() {
+                    let (depth, index) = if let Some(cell) = var.down_to_cell_mut() {
                         // Wrap TOS in existing cell.
                         cell.set_value(value.clone());
                         self.ctx.assign_var(name, var_ref.clone())?
@@ -284,21 +667,25 @@ impl VM {
                         self.ctx.assign_var(name, cell_ref)?
                     };
                     // Push cell *value* to TOS.
-                    self.push(ValueStackKind::CellVar(value, depth, name.to_owned()));
+                    self.push(ValueStackKind::CellVar(value, depth, index));
                 }
                 LoadCell(name) => {
                     // Load cell value onto TOS. This is similar to
                     // LoadVar except that it unwraps the value from the
                     // retrieved cell.
+                    let name = *name;
                     log::trace!("LOAD CELL: {name}");
-                    let depth = self.ctx.get_var_depth(name, 0)?;
-                    let cell = self.ctx.get_var_at_depth(depth, name.as_str())?;
+                    let (depth, index) = self.ctx.get_var_location(name, 0)?;
+                    let cell = self.ctx.get_var_at(depth, index)?;
                     let cell = cell.read().unwrap();
-                    let cell =
-                        cell.down_to_cell().expect("Expected cell: {name} @ {ip}");
+                    let cell = cell.down_to_cell().ok_or_else(|| {
+                        RuntimeErr::unexpected_state(format!(
+                            "Expected cell: {name} @ {ip}"
+                        ))
+                    })?;
                     let value = cell.value();
                     // Push cell *value* to TOS.
-                    self.push(ValueStackKind::CellVar(value, depth, name.to_owned()));
+                    self.push(ValueStackKind::CellVar(value, depth, index));
                 }
                 LoadCaptured(name) => {
                     // This is similar to LoadCell except that it loads
@@ -306,19 +693,38 @@ impl VM {
                     // value, and loads it to TOS as a temporary.
                     let frame = self.current_call_frame()?;
                     if frame.closure.is_some() {
-                        let cell = frame.get_captured(name)?;
+                        let cell = frame.get_captured(name.as_str())?;
                         let cell = cell.read().unwrap();
-                        let cell =
-                            cell.down_to_cell().expect("Expected cell: {name} @ {ip}");
+                        let cell = cell.down_to_cell().ok_or_else(|| {
+                            RuntimeErr::unexpected_state(format!(
+                                "Expected cell: {name} @ {ip}"
+                            ))
+                        })?;
                         let value = cell.value();
                         self.push_temp(value);
                     } else {
-                        panic!("Expected closure");
+                        return Err(RuntimeErr::unexpected_state(format!(
+                            "Expected closure when loading captured var: {name} @ {ip}"
+                        )));
+                    }
+                }
+                RenewCells(indices) => {
+                    let depth = self.ctx.current_depth();
+                    for index in indices {
+                        let var_ref = self.ctx.get_var_at(depth, *index)?;
+                        let value = {
+                            let var = var_ref.read().unwrap();
+                            var.down_to_cell().map(|cell| cell.value())
+                        };
+                        if let Some(value) = value {
+                            let cell_ref = new::cell_with_value(value);
+                            self.ctx.assign_var_at(depth, *index, cell_ref)?;
+                        }
                     }
                 }
                 // Jumps
                 Jump(addr, forward, scope_exit_count) => {
-                    self.exit_scopes(*scope_exit_count);
+                    self.exit_scopes(*scope_exit_count)?;
                     if *forward {
                         jump_ip = Some(ip + *addr);
                     } else {
@@ -326,8 +732,8 @@ impl VM {
                     }
                 }
                 JumpPushNil(addr, forward, scope_exit_count) => {
-                    self.push_global_const(0)?;
-                    self.exit_scopes(*scope_exit_count);
+                    self.push_nil()?;
+                    self.exit_scopes(*scope_exit_count)?;
                     if *forward {
                         jump_ip = Some(ip + *addr);
                     } else {
@@ -335,10 +741,8 @@ impl VM {
                     }
                 }
                 JumpIf(addr, forward, scope_exit_count) => {
-                    let obj = self.peek_obj()?;
-                    let obj = obj.read().unwrap();
-                    if obj.bool_val()? {
-                        self.exit_scopes(*scope_exit_count);
+                    if self.peek_bool_val()? {
+                        self.exit_scopes(*scope_exit_count)?;
                         if *forward {
                             jump_ip = Some(ip + *addr);
                         } else {
@@ -347,10 +751,8 @@ impl VM {
                     }
                 }
                 JumpIfNot(addr, forward, scope_exit_count) => {
-                    let obj = self.peek_obj()?;
-                    let obj = obj.read().unwrap();
-                    if !obj.bool_val()? {
-                        self.exit_scopes(*scope_exit_count);
+                    if !self.peek_bool_val()? {
+                        self.exit_scopes(*scope_exit_count)?;
                         if *forward {
                             jump_ip = Some(ip + *addr);
                         } else {
@@ -362,7 +764,7 @@ impl VM {
                     let obj = self.peek_obj()?;
                     let obj = obj.read().unwrap();
                     if !obj.is_nil() {
-                        self.exit_scopes(*scope_exit_count);
+                        self.exit_scopes(*scope_exit_count)?;
                         if *forward {
                             jump_ip = Some(ip + *addr);
                         } else {
@@ -370,6 +772,40 @@ impl VM {
                         }
                     }
                 }
+                // Error handling
+                PushHandler(addr) => {
+                    self.handler_stack.push(Handler {
+                        addr: ip + *addr,
+                        call_depth: self.call_stack.len(),
+                        scope_depth: self.scope_stack.len(),
+                        value_stack_len: self.value_stack.len(),
+                        ctx_depth: self.ctx.current_depth(),
+                    });
+                }
+                PopHandler => {
+                    self.handler_stack.pop();
+                }
+                RaiseIfErr => {
+                    let obj = self.peek_obj()?;
+                    let is_err = {
+                        let obj = obj.read().unwrap();
+                        obj.down_to_err().is_some_and(|err| err.kind != ErrKind::Ok)
+                    };
+                    if is_err {
+                        self.pop()?;
+                        return Err(RuntimeErr::raised(obj));
+                    }
+                }
+                Raise => {
+                    let obj = self.pop_obj()?;
+                    if obj.read().unwrap().down_to_err().is_none() {
+                        return Err(RuntimeErr::type_err(format!(
+                            "Expected an Err object to raise; got {}",
+                            obj.read().unwrap().class().read().unwrap()
+                        )));
+                    }
+                    return Err(RuntimeErr::raised(obj));
+                }
                 // Operations
                 UnaryOp(op) => {
                     self.handle_unary_op(op)?;
@@ -377,12 +813,41 @@ impl VM {
                 BinaryOp(op) => {
                     self.handle_binary_op(op)?;
                 }
+                GetAttr(obj_text) => {
+                    // NOTE: The object is checked before the lookup
+                    //       because the object is popped by the lookup.
+                    let obj_ref = match self.value_stack.iter().nth(1) {
+                        Some(ValueStackKind::Num(_)) | None => None,
+                        Some(kind) => Some(self.get_obj(kind)),
+                    };
+                    let obj_is_nil = obj_ref
+                        .as_ref()
+                        .is_some_and(|obj| obj.read().unwrap().is_nil());
+                    self.handle_binary_op(&BinaryOperator::Dot)?;
+                    if obj_is_nil {
+                        self.add_nil_attr_hint(obj_text)?;
+                    } else if let Some(obj_ref) = obj_ref {
+                        self.propagate_nil_attr_err(obj_ref)?;
+                    }
+                }
                 CompareOp(op) => {
                     self.handle_compare_op(op)?;
                 }
                 InplaceOp(op) => {
                     self.handle_inplace_op(op)?;
                 }
+                GetItem => {
+                    self.handle_get_item()?;
+                }
+                UnpackSequence(n) => {
+                    self.handle_unpack_sequence(*n)?;
+                }
+                SetAttr => {
+                    self.handle_set_attr()?;
+                }
+                SetItem => {
+                    self.handle_set_item()?;
+                }
                 // Functions
                 Call(num_args) => {
                     log::trace!("STACK before call:\n{}", self.format_stack());
@@ -391,18 +856,42 @@ impl VM {
                     log::trace!("STACK before call:\n{}", self.format_stack());
                     self.call(callable, args)?;
                 }
+                TailCall(num_args) => {
+                    let callable = self.pop_obj()?;
+                    let args = self.pop_n_obj(*num_args)?;
+                    if self.in_try_block() {
+                        // Errors raised by the call have to be caught
+                        // by the handler, so the call can't be made
+                        // after this function returns.
+                        self.call(callable, args)?;
+                    } else {
+                        self.tail_call = Some((callable, args));
+                        // Placeholder for this function's return value
+                        self.push_nil()?;
+                    }
+                }
                 Return => {
                     // RETURN doesn't do anything in and of itself. It's
                     // a marker for the end of a function and a jump
                     // target for explicit returns.
                 }
+                Await => {
+                    if let Some(result) = self.await_task()? {
+                        self.pop()?;
+                        self.push_temp(result);
+                    } else {
+                        // Suspend the current task. AWAIT is executed
+                        // again when the task is resumed.
+                        self.state = VMState::Paused(ip);
+                        return Ok(());
+                    }
+                }
                 // Object construction
                 MakeString(n) => {
                     let objects = self.pop_n_obj(*n)?;
                     let mut string = String::with_capacity(32);
                     for obj in objects {
-                        let obj = obj.read().unwrap();
-                        string.push_str(obj.to_string().as_str());
+                        string.push_str(self.obj_to_str(&obj)?.as_str());
                     }
                     let string_obj = new::str(string);
                     self.push_temp(string_obj);
@@ -418,33 +907,67 @@ impl VM {
                     self.push_temp(list);
                 }
                 MakeMap(n) => {
-                    let objects = self.pop_n_obj(*n)?;
-                    let mut keys = vec![];
-                    let mut vals = vec![];
-                    for (i, obj) in objects.iter().enumerate() {
-                        if i % 2 == 0 {
-                            let obj = obj.read().unwrap();
-                            let key = obj.to_string();
-                            keys.push(key);
-                        } else {
-                            vals.push(obj.clone());
-                        }
-                    }
+                    let (keys, vals) = self.pop_map_entries(*n)?;
                     let map = new::map_from_keys_and_vals(keys, vals);
                     self.push_temp(map);
                 }
+                MakeSet(n) => {
+                    let objects = self.pop_n_obj(*n)?;
+                    let set = new::set(objects);
+                    self.push_temp(set);
+                }
+                ExtendList(n) => {
+                    let objects = self.pop_n_obj(*n)?;
+                    let list_ref = self.peek_obj()?;
+                    let list = list_ref.read().unwrap();
+                    let list = list.down_to_list().ok_or_else(|| {
+                        RuntimeErr::unexpected_state("Expected list at TOS")
+                    })?;
+                    for obj in objects {
+                        list.push(obj);
+                    }
+                }
+                ExtendMap(n) => {
+                    let (keys, vals) = self.pop_map_entries(*n)?;
+                    let map_ref = self.peek_obj()?;
+                    let map = map_ref.read().unwrap();
+                    let map = map.down_to_map().ok_or_else(|| {
+                        RuntimeErr::unexpected_state("Expected map at TOS")
+                    })?;
+                    for (key, val) in keys.into_iter().zip(vals) {
+                        map.insert(key, val);
+                    }
+                }
+                ListToTuple => {
+                    let list_ref = self.pop_obj()?;
+                    let list = list_ref.read().unwrap();
+                    let list = list.down_to_list().ok_or_else(|| {
+                        RuntimeErr::unexpected_state("Expected list at TOS")
+                    })?;
+                    let tuple = new::tuple(list.to_vec());
+                    self.push_temp(tuple);
+                }
+                ListToSet => {
+                    let list_ref = self.pop_obj()?;
+                    let list = list_ref.read().unwrap();
+                    let list = list.down_to_list().ok_or_else(|| {
+                        RuntimeErr::unexpected_state("Expected list at TOS")
+                    })?;
+                    let set = new::set(list.to_vec());
+                    self.push_temp(set);
+                }
                 CaptureSet(names) => {
                     let mut capture_set = IndexMap::default();
                     for name in names.iter() {
                         log::trace!("GETTING CAPTURED: {name}");
-                        if let Ok(var_ref) = self.ctx.get_var(name, 0) {
+                        if let Ok(var_ref) = self.ctx.get_var(*name, 0) {
                             // Capture cell already exists.
                             let var = var_ref.read().unwrap();
                             if var.is_cell() {
-                                capture_set.insert(name.to_owned(), var_ref.clone());
+                                capture_set.insert(name.to_string(), var_ref.clone());
                             } else {
                                 assert!(var.is_nil());
-                                capture_set.insert(name.to_owned(), new::cell());
+                                capture_set.insert(name.to_string(), new::cell());
                             }
                         } else if let Some(frame) = self.call_stack.peek() {
                             // Capture cell does not exist.
@@ -452,8 +975,9 @@ impl VM {
                                 log::trace!("CAPTURING OUTER");
                                 let closure = closure.read().unwrap();
                                 let closure = closure.down_to_closure().unwrap();
-                                if let Some(cell) = closure.get_captured(name) {
-                                    capture_set.insert(name.to_owned(), cell.clone());
+                                if let Some(cell) = closure.get_captured(name.as_str())
+                                {
+                                    capture_set.insert(name.to_string(), cell.clone());
                                     log::trace!(
                                         "CAPTURED FROM OUTER: {name} = {cell:?}"
                                     );
@@ -480,8 +1004,10 @@ impl VM {
                             if let AssignCell(_) = &code[ip + 1] {
                                 let closure_cell =
                                     new::cell_with_value(func_ref.clone());
-                                self.ctx
-                                    .assign_var(func.name(), closure_cell.clone())?;
+                                self.ctx.assign_var(
+                                    Symbol::intern(func.name()),
+                                    closure_cell.clone(),
+                                )?;
                                 capture_set
                                     .insert(func.name().to_owned(), closure_cell);
                             }
@@ -493,6 +1019,27 @@ impl VM {
                         ));
                     }
                 }
+                MakeType(type_args, n) => {
+                    let (name, fields) = &**type_args;
+                    let members = self.pop_n_obj(*n * 2)?;
+                    let module_ref = registered_module(module.name())?;
+                    let type_ref =
+                        new::custom_type_with_fields(module_ref, name, fields.clone());
+                    {
+                        let mut type_obj = type_ref.write().unwrap();
+                        let ns = type_obj.ns_mut();
+                        for member in members.chunks(2) {
+                            let member_name = member[0].read().unwrap().to_string();
+                            ns.insert(member_name, member[1].clone());
+                        }
+                    }
+                    self.push_temp(type_ref);
+                }
+                MakeEnum(type_args) => {
+                    let (name, variants) = &**type_args;
+                    let module_ref = registered_module(module.name())?;
+                    self.push_temp(new::enum_type(module_ref, name, variants));
+                }
                 // VM control
                 Halt(return_code) => {
                     return self.halt(*return_code);
@@ -501,7 +1048,8 @@ impl VM {
                     return self.halt_top();
                 }
                 // Placeholders
-                Placeholder(addr, inst, message) => {
+                Placeholder(addr, inst_and_message) => {
+                    let (inst, message) = &**inst_and_message;
                     eprintln!(
                         "Placeholder at {addr} was not updated: {inst:?}\n{message}"
                     );
@@ -534,15 +1082,30 @@ impl VM {
                 }
             }
 
-            if self.handle_sigint {
-                sigint_counter += 1;
-                if sigint_counter == 1024 {
-                    if self.sigint_flag.load(Ordering::Relaxed) {
+            if self.value_stack.len() > MAX_VALUE_STACK_SIZE {
+                break Err(RuntimeErr::value_stack_overflow(MAX_VALUE_STACK_SIZE));
+            }
+
+            // The cancel and SIGINT flags are only checked periodically
+            // to keep the overhead down.
+            self.poll_counter += 1;
+            if self.poll_counter == 1024 {
+                self.poll_counter = 0;
+                if self.is_fork {
+                    let interrupted =
+                        self.handle_sigint && self.sigint_flag.load(Ordering::Relaxed);
+                    if interrupted || self.cancel_flag.load(Ordering::Relaxed) {
+                        break Err(RuntimeErr::cancelled());
+                    }
+                } else {
+                    if self.cancel_flag.swap(false, Ordering::Relaxed) {
+                        break Err(RuntimeErr::cancelled());
+                    }
+                    if self.handle_sigint && self.sigint_flag.load(Ordering::Relaxed) {
                         self.handle_sigint();
                         self.set_idle(None);
                         break Ok(());
                     }
-                    sigint_counter = 0;
                 }
             }
 
@@ -552,11 +1115,29 @@ impl VM {
             } else {
                 ip += 1;
                 if ip == len_chunk {
+                    // SIGINT may have been received since the last poll
+                    // (e.g., while an intrinsic func was reading a file).
+                    if self.handle_sigint
+                        && !self.is_fork
+                        && self.sigint_flag.load(Ordering::Relaxed)
+                    {
+                        self.handle_sigint();
+                        self.set_idle(None);
+                        break Ok(());
+                    }
                     let top = self.peek_obj().map_or_else(|_| None, Some);
                     self.set_idle(top.clone());
                     break Ok(());
                 }
             }
+
+            if let Some(max_steps) = max_steps {
+                step_counter += 1;
+                if step_counter == max_steps {
+                    self.state = VMState::Paused(ip);
+                    break Ok(());
+                }
+            }
         }
     }
 
@@ -565,6 +1146,14 @@ impl VM {
         self.loc
     }
 
+    /// Take the traceback for the last error that propagated out of a
+    /// function call without being handled, oldest entry first. If the
+    /// error occurred in top level code, the traceback is empty.
+    pub fn take_traceback(&mut self) -> Vec<TraceFrame> {
+        mem::take(&mut self.traceback)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn install_sigint_handler(&mut self) {
         let flag = self.sigint_flag.clone();
         self.handle_sigint = true;
@@ -575,6 +1164,23 @@ impl VM {
         }
     }
 
+    /// There are no signals in WebAssembly, so this does nothing.
+    #[cfg(target_arch = "wasm32")]
+    pub fn install_sigint_handler(&mut self) {}
+
+    /// Get the flag that's set when SIGINT is received. Intrinsic funcs
+    /// that produce items on demand (e.g., `File.lines()`) check it so
+    /// they stop producing items when interrupted.
+    pub fn sigint_flag(&self) -> Arc<AtomicBool> {
+        self.sigint_flag.clone()
+    }
+
+    /// Get a handle that can be used to cancel execution from another
+    /// thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle { flag: self.cancel_flag.clone() }
+    }
+
     fn handle_sigint(&mut self) {
         self.sigint_flag.store(false, Ordering::Relaxed);
         self.reset();
@@ -592,7 +1198,7 @@ impl VM {
         self.state = VMState::Idle(obj);
     }
 
-    fn halt(&mut self, exit_code: u8) -> VMExeResult {
+    pub fn halt(&mut self, exit_code: u8) -> VMExeResult {
         self.reset();
         self.state = VMState::Halted(exit_code);
         Err(RuntimeErr::exit(exit_code))
@@ -608,12 +1214,128 @@ impl VM {
         self.halt(return_code)
     }
 
+    /// Check that the VM is idle and that there's no leftover state
+    /// from previously executed code.
+    fn check_idle(&self) -> RuntimeResult {
+        let message = match &self.state {
+            VMState::Running => "previous chunk did not finish".to_owned(),
+            VMState::Halted(code) => format!("VM halted with exit code {code}"),
+            VMState::Paused(ip) => format!("previous chunk paused at {ip}"),
+            VMState::Idle(_) => {
+                if !self.call_stack.is_empty() {
+                    format!("{} call frame(s) still active", self.call_stack.len())
+                } else if !self.handler_stack.is_empty() {
+                    format!("{} handler(s) still active", self.handler_stack.len())
+                } else if !self.scope_stack.is_empty() || self.ctx.current_depth() > 0 {
+                    "previous chunk did not exit all scopes".to_owned()
+                } else if !self.value_stack.is_empty() {
+                    format!(
+                        "previous chunk left {} value(s) on the stack",
+                        self.value_stack.len()
+                    )
+                } else {
+                    return Ok(());
+                }
+            }
+        };
+        Err(RuntimeErr::unexpected_state(message))
+    }
+
+    /// Unwind the VM back to its idle state, preserving globals.
+    fn unwind(&mut self) {
+        self.scope_stack.truncate(0);
+        self.value_stack.truncate(0);
+        self.call_stack.truncate(0);
+        self.handler_stack.truncate(0);
+        self.ctx.exit_all_scopes();
+        self.set_idle(None);
+    }
+
     /// Completely reset internal state.
-    fn reset(&mut self) {
+    pub(crate) fn reset(&mut self) {
         self.scope_stack.truncate(0);
         self.value_stack.truncate(0);
         self.call_stack.truncate(0);
+        self.handler_stack.truncate(0);
         self.ctx.reset();
+        self.tail_call = None;
+    }
+
+    /// Reset internal state after a function call fails *unless* the
+    /// error will be caught by an active handler, in which case the
+    /// handler will take care of unwinding.
+    ///
+    /// Catchable errors raised by a task are handled by the event loop
+    /// too, since they're stored as the task's result.
+    fn reset_unless_handled(&mut self, err: &RuntimeErr) {
+        let in_task = self.event_loop.as_ref().is_some_and(|el| el.current.is_some());
+        if (self.handler_stack.is_empty() && !in_task) || err.to_err_obj().is_none() {
+            // The traceback is captured by the innermost failed call,
+            // before the call stack is cleared.
+            if !self.call_stack.is_empty() {
+                self.traceback = self.capture_traceback();
+            }
+            self.reset();
+        }
+    }
+
+    /// Build a traceback from the call stack. Each function is at the
+    /// location of the call it made or, for the innermost function, at
+    /// the current location.
+    fn capture_traceback(&self) -> Vec<TraceFrame> {
+        let mut traceback = vec![];
+        let mut loc = self.loc;
+        for frame in self.call_stack.iter() {
+            if let Some(func_ref) = &frame.func {
+                let func = func_ref.read().unwrap();
+                let func = func.down_to_func().unwrap();
+                let module = FuncTrait::module(func);
+                let module = module.read().unwrap();
+                let module_path = match module.down_to_mod() {
+                    Some(module) => module.path().to_owned(),
+                    None => func.module_name().clone(),
+                };
+                traceback.push(TraceFrame {
+                    name: func.name().clone(),
+                    module_path,
+                    loc,
+                });
+            }
+            loc = frame.call_loc;
+        }
+        traceback.push(TraceFrame {
+            name: "<module>".to_owned(),
+            module_path: self.module_path.clone(),
+            loc,
+        });
+        traceback.reverse();
+        traceback
+    }
+
+    // Error handling --------------------------------------------------
+
+    /// Attempt to catch an error using the most recently pushed handler.
+    /// Only handlers pushed at or above `handler_base` are considered
+    /// (i.e., handlers pushed by the currently executing code). If the
+    /// error is caught, the VM's stacks are restored to their state at
+    /// the time the handler was pushed, the `Err` object for the error
+    /// is pushed, and the handler's address is returned.
+    fn catch_err(&mut self, handler_base: usize, err: &RuntimeErr) -> Option<usize> {
+        if self.handler_stack.len() <= handler_base {
+            return None;
+        }
+        let err_obj = err.to_err_obj()?;
+        let handler = self.handler_stack.pop().unwrap();
+        if handler.call_depth < self.call_stack.len() {
+            self.loc = self.call_stack[handler.call_depth].call_loc;
+        }
+        self.call_stack.truncate(handler.call_depth);
+        self.scope_stack.truncate(handler.scope_depth);
+        self.value_stack.truncate(handler.value_stack_len);
+        self.ctx.exit_scopes_to(handler.ctx_depth);
+        self.push_temp(err_obj);
+        self.set_running();
+        Some(handler.addr)
     }
 
     // Handlers --------------------------------------------------------
@@ -623,16 +1345,23 @@ impl VM {
         let a_kind = self.pop()?;
         let a_ref = self.get_obj(&a_kind);
         let a = a_ref.read().unwrap();
-        let result = match op {
+        match op {
             Plus => {
                 drop(a);
-                a_ref // no-op
+                self.push_temp(a_ref); // no-op
             }
-            Negate => a.negate()?,
-            AsBool => new::bool(a.bool_val()?),
-            Not => new::bool(a.not()?),
-        };
-        self.push_temp(result);
+            Negate => {
+                let result = a.negate()?;
+                self.push_temp(result);
+            }
+            AsBool => self.push_bool(a.bool_val()?)?,
+            Not => self.push_bool(a.not()?)?,
+            Repr => {
+                drop(a);
+                let repr = self.obj_to_repr(&a_ref)?;
+                self.push_temp(new::str(repr));
+            }
+        }
         Ok(())
     }
 
@@ -640,9 +1369,24 @@ impl VM {
     /// result value onto stack.
     fn handle_binary_op(&mut self, op: &BinaryOperator) -> RuntimeResult {
         use BinaryOperator::*;
-        let b_ref = self.pop_obj()?;
+        let b_kind = self.pop()?;
         let a_kind = self.pop()?;
-        let a_ref = self.get_obj(&a_kind);
+        if let (Some(a), Some(b)) = (self.get_num(&a_kind), self.get_num(&b_kind)) {
+            if let Some(result) = a.binary_op(op, b) {
+                self.release(a_kind);
+                self.release(b_kind);
+                self.push(ValueStackKind::Num(result));
+                return Ok(());
+            }
+        }
+        let b_ref = self.get_boxed_obj(b_kind);
+        let a_ref = self.get_boxed_obj(a_kind);
+        if let Some(result) = self.pooled_binary_op(op, &a_ref, &b_ref) {
+            self.temp_pool.release(a_ref);
+            self.temp_pool.release(b_ref);
+            self.push_temp(result);
+            return Ok(());
+        }
         let a = a_ref.read().unwrap();
         let b = b_ref.read().unwrap();
         let b = &*b;
@@ -654,6 +1398,10 @@ impl VM {
             Mod => a.modulo(b)?,
             Add => a.add(b)?,
             Sub => a.sub(b)?,
+            BitOr => a.bit_or(b)?,
+            BitAnd => a.bit_and(b)?,
+            Range => range::from_operands(&*a, b, false)?,
+            RangeInclusive => range::from_operands(&*a, b, true)?,
             Dot => {
                 let obj_ref = if let Some(name) = b.get_str_val() {
                     let mut result = a.get_attr(name, a_ref.clone());
@@ -664,8 +1412,9 @@ impl VM {
                     // or isn't an index--the original attr err will be
                     // returned.
                     if result.read().unwrap().is_err() && (a.is_seq()) {
-                        let i = self.ctx.get_var(name, 0);
-                        if let Ok(i) = i {
+                        let i =
+                            Symbol::lookup(name).map(|name| self.ctx.get_var(name, 0));
+                        if let Some(Ok(i)) = i {
                             let i = i.read().unwrap();
                             if let Some(i) = i.get_usize_val() {
                                 result = a.get_item(i, a_ref.clone());
@@ -687,7 +1436,13 @@ impl VM {
                 };
 
                 let obj = obj_ref.read().unwrap();
-                if obj.is_intrinsic_func() || obj.is_func() || obj.is_closure() {
+                if a.is_mod() {
+                    // Module globals aren't bound to the module so that
+                    // the same object is returned on each access (e.g.,
+                    // for `from <module> import <name>` in a function).
+                    drop(obj);
+                    obj_ref
+                } else if obj.is_intrinsic_func() || obj.is_func() || obj.is_closure() {
                     // If `b` in `a.b` is a function, bind `b` to `a`.
 
                     // TODO: Check whether `a` is a type or an instance.
@@ -717,12 +1472,198 @@ impl VM {
         Ok(())
     }
 
+    /// If TOS is the attr Err from looking up an attribute of nil,
+    /// replace it with an Err that says which expression was nil and
+    /// suggests using `?.` instead.
+    fn add_nil_attr_hint(&mut self, obj_text: &str) -> RuntimeResult {
+        let result_ref = self.peek_obj()?;
+        let result = result_ref.read().unwrap();
+        let Some(err) = result.down_to_err() else {
+            return Ok(());
+        };
+        if err.kind != ErrKind::AttrNotFound {
+            return Ok(());
+        }
+        let name = &err.message;
+        let msg = format!(
+            "{obj_text}.{name}: {obj_text} is nil; use {obj_text}?.{name} if it \
+            can be nil"
+        );
+        let err = new::attr_not_found_err(msg, err.obj.clone());
+        drop(result);
+        self.pop()?;
+        self.push_temp(err);
+        Ok(())
+    }
+
+    /// If `obj_ref`, the object an attribute was just looked up on, is
+    /// an Err from looking up an attribute of nil, and the attribute
+    /// wasn't found on it, replace the new attr Err on TOS with
+    /// `obj_ref`. This way, the Err for a chain like `f(1).a.b` says
+    /// which part of the chain was nil rather than just that `b`
+    /// wasn't found.
+    fn propagate_nil_attr_err(&mut self, obj_ref: ObjectRef) -> RuntimeResult {
+        let is_nil_attr_err = |obj: &dyn ObjectTrait| {
+            obj.down_to_err().is_some_and(|err| {
+                err.kind == ErrKind::AttrNotFound && err.obj.read().unwrap().is_nil()
+            })
+        };
+        if !is_nil_attr_err(&*obj_ref.read().unwrap()) {
+            return Ok(());
+        }
+        let result_ref = self.peek_obj()?;
+        let is_attr_not_found = result_ref
+            .read()
+            .unwrap()
+            .down_to_err()
+            .is_some_and(|err| err.kind == ErrKind::AttrNotFound);
+        if is_attr_not_found {
+            self.pop()?;
+            self.push_temp(obj_ref);
+        }
+        Ok(())
+    }
+
+    /// Pop key and object from stack, look up item in object by key,
+    /// and push temp result value onto stack.
+    fn handle_get_item(&mut self) -> RuntimeResult {
+        let key_ref = self.pop_obj()?;
+        let obj_ref = self.pop_obj()?;
+        let obj = obj_ref.read().unwrap();
+        let key = key_ref.read().unwrap();
+        let result = if obj.is_seq() {
+            if let Some(index) = key.get_usize_val() {
+                obj.get_item(index, obj_ref.clone())
+            } else {
+                new::type_err(
+                    format!("Sequence index must be a non-negative Int: {key}"),
+                    obj_ref.clone(),
+                )
+            }
+        } else {
+            obj.get_item_by_key(key.to_string().as_str(), obj_ref.clone())
+        };
+        self.push_temp(result);
+        Ok(())
+    }
+
+    /// Pop sequence and push its `n` items in reverse order so they
+    /// can be assigned from left to right.
+    fn handle_unpack_sequence(&mut self, n: usize) -> RuntimeResult {
+        let obj = self.pop_obj()?;
+        let items = {
+            let obj = obj.read().unwrap();
+            if let Some(tuple) = obj.down_to_tuple() {
+                tuple.iter().cloned().collect::<Vec<_>>()
+            } else if let Some(list) = obj.down_to_list() {
+                list.to_vec()
+            } else {
+                return Err(RuntimeErr::type_err(format!(
+                    "Cannot unpack {}",
+                    obj.class().read().unwrap()
+                )));
+            }
+        };
+        if items.len() != n {
+            return Err(RuntimeErr::type_err(format!(
+                "Cannot unpack {} item(s) into {n} target(s)",
+                items.len()
+            )));
+        }
+        for item in items.into_iter().rev() {
+            self.push_temp(item);
+        }
+        Ok(())
+    }
+
+    /// Pop value, attribute name, and object from stack, set attribute
+    /// on object, and push value back onto stack. If the attribute
+    /// can't be set, the resulting error is raised.
+    fn handle_set_attr(&mut self) -> RuntimeResult {
+        let value = self.pop_obj()?;
+        let name_ref = self.pop_obj()?;
+        let obj_ref = self.pop_obj()?;
+        let (name, index) = {
+            let name = name_ref.read().unwrap();
+            (name.get_str_val().map(|n| n.to_owned()), name.get_usize_val())
+        };
+        let result = {
+            let mut obj = obj_ref.write().unwrap();
+            if let Some(name) = name {
+                obj.set_attr(name.as_str(), value.clone(), obj_ref.clone())
+            } else if let Some(index) = index {
+                obj.set_item(index, obj_ref.clone(), value.clone())
+            } else {
+                new::attr_err(
+                    format!(
+                        "Not an attribute name or index: {:?}",
+                        &*name_ref.read().unwrap()
+                    ),
+                    obj_ref.clone(),
+                )
+            }
+        };
+        self.finish_set(result, value)
+    }
+
+    /// Pop value, key, and object from stack, set item on object, and
+    /// push value back onto stack. If the item can't be set, the
+    /// resulting error is raised.
+    fn handle_set_item(&mut self) -> RuntimeResult {
+        let value = self.pop_obj()?;
+        let key_ref = self.pop_obj()?;
+        let obj_ref = self.pop_obj()?;
+        let (key, index) = {
+            let key = key_ref.read().unwrap();
+            (key.to_string(), key.get_usize_val())
+        };
+        let result = {
+            let mut obj = obj_ref.write().unwrap();
+            if obj.is_seq() {
+                if let Some(index) = index {
+                    obj.set_item(index, obj_ref.clone(), value.clone())
+                } else {
+                    new::type_err(
+                        format!("Sequence index must be a non-negative Int: {key}"),
+                        obj_ref.clone(),
+                    )
+                }
+            } else {
+                obj.set_item_by_key(key.as_str(), obj_ref.clone(), value.clone())
+            }
+        };
+        self.finish_set(result, value)
+    }
+
+    /// Raise the result of a set operation if it's an error. Otherwise,
+    /// push the assigned value onto the stack.
+    fn finish_set(&mut self, result: ObjectRef, value: ObjectRef) -> RuntimeResult {
+        let is_err = {
+            let result = result.read().unwrap();
+            result.down_to_err().is_some_and(|err| err.kind != ErrKind::Ok)
+        };
+        if is_err {
+            return Err(RuntimeErr::raised(result));
+        }
+        self.push_temp(value);
+        Ok(())
+    }
+
     /// Pop top two operands from stack, compare them, and push bool
     /// temp value onto stack.
     fn handle_compare_op(&mut self, op: &CompareOperator) -> RuntimeResult {
         use CompareOperator::*;
-        let b_ref = self.pop_obj()?;
+        let b_kind = self.pop()?;
         let a_kind = self.pop()?;
+        if let (Some(a), Some(b)) = (self.get_num(&a_kind), self.get_num(&b_kind)) {
+            if let Some(result) = a.compare(op, b) {
+                self.release(a_kind);
+                self.release(b_kind);
+                self.push_bool(result)?;
+                return Ok(());
+            }
+        }
+        let b_ref = self.get_boxed_obj(b_kind);
         let a_ref = self.get_obj(&a_kind);
         let a = a_ref.read().unwrap();
         let b = b_ref.read().unwrap();
@@ -738,65 +1679,95 @@ impl VM {
             LessThanOrEqual => a.less_than(b)? || a.is_equal(b),
             GreaterThan => a.greater_than(b)?,
             GreaterThanOrEqual => a.greater_than(b)? || a.is_equal(b),
+            In => b.contains(&*a)?,
+            NotIn => !b.contains(&*a)?,
         };
-        self.push_temp(new::bool(result));
-        Ok(())
+        self.push_bool(result)
     }
 
     /// Pop top two operands from stack, apply operation, assign result,
     /// and push temp result value onto stack. The first operand *must*
     /// be a variable.
     fn handle_inplace_op(&mut self, op: &InplaceOperator) -> RuntimeResult {
-        let b_ref = self.pop_obj()?;
+        let b_kind = self.pop()?;
         let a_kind = self.pop()?;
-        let a_ref = self.get_obj(&a_kind);
-        let a = a_ref.read().unwrap();
-        let b = b_ref.read().unwrap();
-        let b = &*b;
-        let result = match op {
-            InplaceOperator::Mul => a.mul(b)?,
-            InplaceOperator::Div => a.div(b)?,
-            InplaceOperator::Add => a.add(b)?,
-            InplaceOperator::Sub => a.sub(b)?,
+        let num_result = match (self.get_num(&a_kind), self.get_num(&b_kind)) {
+            (Some(a), Some(b)) => a.binary_op(&op.binary_op(), b),
+            _ => None,
         };
-        if let ValueStackKind::Var(_, depth, name) = a_kind {
-            self.ctx.assign_var_at_depth(depth, name.as_str(), result.clone())?;
-            self.push_temp(result);
-        } else if let ValueStackKind::CellVar(_, depth, name) = a_kind {
-            let cell = self.ctx.get_var_at_depth(depth, name.as_str())?;
-            let mut cell = cell.write().unwrap();
-            let cell = cell.down_to_cell_mut().expect("Expected cell");
-            cell.set_value(result.clone());
-            self.push_temp(result);
+        let b_ref = self.get_boxed_obj(b_kind);
+        let a_ref = self.get_obj(&a_kind);
+        let result = if let Some(result) = num_result {
+            self.temp_pool.num(result)
+        } else if let Some(result) =
+            self.pooled_binary_op(&op.binary_op(), &a_ref, &b_ref)
+        {
+            result
         } else {
-            return Err(RuntimeErr::expected_var(format!("Binary op: {op}")));
-        }
+            let a = a_ref.read().unwrap();
+            let b = b_ref.read().unwrap();
+            let b = &*b;
+            match op {
+                InplaceOperator::Mul => a.mul(b)?,
+                InplaceOperator::Div => a.div(b)?,
+                InplaceOperator::Add => a.add(b)?,
+                InplaceOperator::Sub => a.sub(b)?,
+            }
+        };
+        drop(a_ref);
+        let old_val = match a_kind {
+            ValueStackKind::Var(old_val, depth, index) => {
+                self.ctx.assign_var_at(depth, index, result.clone())?;
+                old_val
+            }
+            ValueStackKind::CellVar(old_val, depth, index) => {
+                let cell = self.ctx.get_var_at(depth, index)?;
+                let mut cell = cell.write().unwrap();
+                let cell = cell.down_to_cell_mut().ok_or_else(|| {
+                    RuntimeErr::unexpected_state(format!("Expected cell: {op}"))
+                })?;
+                cell.set_value(result.clone());
+                old_val
+            }
+            _ => {
+                return Err(RuntimeErr::expected_var(format!("Binary op: {op}")));
+            }
+        };
+        // The old value is typically unreferenced now (e.g., when a
+        // loop counter is incremented), so it can be reused.
+        self.temp_pool.release(old_val);
+        self.temp_pool.release(b_ref);
+        self.push_temp(result);
         Ok(())
     }
 
+    /// Apply an arithmetic operation to Ints and Floats using the temp
+    /// pool. Returns `None` for other operations and operand types.
+    fn pooled_binary_op(
+        &mut self,
+        op: &BinaryOperator,
+        a_ref: &ObjectRef,
+        b_ref: &ObjectRef,
+    ) -> Option<ObjectRef> {
+        let a = a_ref.read().unwrap();
+        let b = b_ref.read().unwrap();
+        self.temp_pool.binary_op(op, &*a, &*b)
+    }
+
     fn handle_print(&mut self, flags: &PrintFlags) -> RuntimeResult {
         if let Ok(obj) = self.pop_obj() {
-            let obj = obj.read().unwrap();
-            if flags.contains(PrintFlags::NO_NIL) && obj.is_nil() {
+            if flags.contains(PrintFlags::NO_NIL) && obj.read().unwrap().is_nil() {
                 // do nothing
-            } else if flags.contains(PrintFlags::ERR) {
-                if flags.contains(PrintFlags::REPR) {
-                    eprint!("{:?}", &*obj);
-                } else {
-                    eprint!("{obj}");
-                }
-                if flags.contains(PrintFlags::NL) {
-                    eprintln!();
-                }
             } else {
-                if flags.contains(PrintFlags::REPR) {
-                    print!("{:?}", &*obj);
+                let mut text = if flags.contains(PrintFlags::REPR) {
+                    self.obj_to_repr(&obj)?
                 } else {
-                    print!("{obj}");
-                }
+                    self.obj_to_str(&obj)?
+                };
                 if flags.contains(PrintFlags::NL) {
-                    println!();
+                    text.push('\n');
                 }
+                self.write_output(&text, flags.contains(PrintFlags::ERR));
             }
             Ok(())
         } else {
@@ -804,6 +1775,54 @@ impl VM {
         }
     }
 
+    /// Convert an object to a Str for display (e.g., in format strings
+    /// and when printing). If the object's type defines a `$str`
+    /// method, it's called; otherwise, `Display` is used.
+    fn obj_to_str(&mut self, obj_ref: &ObjectRef) -> Result<String, RuntimeErr> {
+        match self.str_method(obj_ref, "$str") {
+            Some(method) => self.call_str_method(obj_ref, method, "$str"),
+            None => Ok(obj_ref.read().unwrap().to_string()),
+        }
+    }
+
+    /// Like `obj_to_str()` but uses the `$repr` method or `Debug`.
+    fn obj_to_repr(&mut self, obj_ref: &ObjectRef) -> Result<String, RuntimeErr> {
+        match self.str_method(obj_ref, "$repr") {
+            Some(method) => self.call_str_method(obj_ref, method, "$repr"),
+            None => Ok(format!("{:?}", &*obj_ref.read().unwrap())),
+        }
+    }
+
+    /// Get a string conversion method defined by an object's type.
+    /// Types themselves don't use these methods.
+    fn str_method(&self, obj_ref: &ObjectRef, name: &str) -> Option<ObjectRef> {
+        let obj = obj_ref.read().unwrap();
+        if obj.is_type_object() {
+            return None;
+        }
+        let class = obj.class();
+        let class = class.read().unwrap();
+        class.ns().get(name)
+    }
+
+    fn call_str_method(
+        &mut self,
+        obj_ref: &ObjectRef,
+        method: ObjectRef,
+        name: &str,
+    ) -> Result<String, RuntimeErr> {
+        self.call(new::bound_func(method, obj_ref.clone()), vec![])?;
+        let result = self.pop_obj()?;
+        let result = result.read().unwrap();
+        match result.get_str_val() {
+            Some(string) => Ok(string.to_owned()),
+            None => Err(RuntimeErr::type_err(format!(
+                "{name} must return a Str; got {}",
+                result.class().read().unwrap()
+            ))),
+        }
+    }
+
     // Call Stack ------------------------------------------------------
 
     // NOTE: Pushing a call frame is similar to entering a scope.
@@ -811,6 +1830,7 @@ impl VM {
         &mut self,
         this_opt: ThisOpt,
         closure: Option<ObjectRef>,
+        func: Option<ObjectRef>,
     ) -> RuntimeResult {
         if self.call_stack.len() == self.max_call_depth {
             self.reset();
@@ -818,27 +1838,34 @@ impl VM {
         }
         self.ctx.enter_scope();
         let stack_pointer = self.value_stack.len();
-        let frame = CallFrame::new(stack_pointer, this_opt, closure);
+        let ctx_depth = self.ctx.current_depth();
+        let frame =
+            CallFrame::new(stack_pointer, ctx_depth, this_opt, closure, func, self.loc);
         self.call_stack.push(frame);
         Ok(())
     }
 
     // NOTE: Popping a call frame is very similar to exiting a scope.
     fn pop_call_frame(&mut self) -> RuntimeResult {
-        let return_val = self.pop_obj();
+        // Ensure the frame left a value on the stack.
+        let return_val = self.pop_obj()?;
         if let Some(frame) = self.call_stack.pop() {
             self.value_stack.truncate(frame.stack_pointer);
+            // Errors after the call are reported at the caller's
+            // statement rather than the callee's last statement.
+            self.loc = frame.call_loc;
         } else {
-            panic!("Call stack unexpectedly empty");
+            return Err(RuntimeErr::empty_call_stack());
         }
-        // Ensure the frame left a value on the stack.
-        if let Ok(obj) = return_val {
-            self.push_return_val(obj.clone());
-        } else {
-            panic!("Value stack unexpectedly empty when exiting scope");
+        // Discard handlers left active by returning from a `try` block.
+        while let Some(handler) = self.handler_stack.peek() {
+            if handler.call_depth <= self.call_stack.len() {
+                break;
+            }
+            self.handler_stack.pop();
         }
-        self.ctx.exit_scope();
-        Ok(())
+        self.push_return_val(return_val);
+        self.ctx.exit_scope()
     }
 
     fn current_call_frame(&self) -> Result<&CallFrame, RuntimeErr> {
@@ -868,16 +1895,37 @@ impl VM {
         new::nil()
     }
 
+    /// Check whether the current function has an active `try` block.
+    fn in_try_block(&self) -> bool {
+        self.handler_stack
+            .peek()
+            .is_some_and(|handler| handler.call_depth == self.call_stack.len())
+    }
+
     // Function calls --------------------------------------------------
 
+    /// Call callable with args. If callable makes a tail call, the tail
+    /// call is made here once callable returns, and so on, so that tail
+    /// calls run in constant stack space.
     pub fn call(&mut self, callable_ref: ObjectRef, args: Args) -> RuntimeResult {
+        self.call_callable(callable_ref, args)?;
+        while let Some((callable_ref, args)) = self.tail_call.take() {
+            // Discard the placeholder return value of the function that
+            // made the tail call.
+            self.pop()?;
+            self.call_callable(callable_ref, args)?;
+        }
+        Ok(())
+    }
+
+    fn call_callable(&mut self, callable_ref: ObjectRef, args: Args) -> RuntimeResult {
         let callable = callable_ref.read().unwrap();
         if let Some(func) = callable.down_to_intrinsic_func() {
             log::trace!("CALL intrinsic func {}", func.name());
             self.call_intrinsic_func(func, None, args)
         } else if let Some(func) = callable.down_to_func() {
             log::trace!("CALL func {}", func.name());
-            self.call_func(func, None, args, None)
+            self.call_func(&callable_ref, func, None, args, None)
         } else if callable.is_closure() {
             log::trace!("CALL closure");
             self.call_closure(callable_ref.clone(), None, args)
@@ -901,7 +1949,9 @@ impl VM {
                     // XXX: Not sure this is the best way to distinguish
                     //      between class vs instance methods
                     if !(this.is(expected_type) || this_type.is(expected_type)) {
-                        panic!("Expected this type {expected_type}; got {this_type}");
+                        return Err(RuntimeErr::type_err(format!(
+                            "Expected this type {expected_type}; got {this_type}"
+                        )));
                     }
                 }
                 self.call_intrinsic_func(func, this_opt, args)
@@ -911,8 +1961,8 @@ impl VM {
                     func.name(),
                     bound_func.this().read().unwrap()
                 );
-                self.call_func(func, this_opt, args, None)
-            } else if callable.is_closure() {
+                self.call_func(&func_ref, func, this_opt, args, None)
+            } else if func_obj.is_closure() {
                 log::trace!(
                     "CALL bound closure with this: {}",
                     bound_func.this().read().unwrap()
@@ -933,7 +1983,7 @@ impl VM {
         args: Args,
     ) -> RuntimeResult {
         let args = self.check_call_args(func, &this_opt, args)?;
-        self.push_call_frame(this_opt.clone(), None)?;
+        self.push_call_frame(this_opt.clone(), None, None)?;
         let result = (func.func())(self.find_this(), args, self);
         match result {
             Ok(return_val) => {
@@ -942,43 +1992,92 @@ impl VM {
                 Ok(())
             }
             Err(err) => {
-                self.reset();
+                self.reset_unless_handled(&err);
                 Err(err)
             }
         }
     }
 
+    /// Call func, which is the function `func_ref` refers to. Calling
+    /// an async function doesn't run it; a task is returned instead,
+    /// which runs when it's spawned or awaited.
     pub fn call_func(
         &mut self,
+        func_ref: &ObjectRef,
         func: &Func,
         this_opt: ThisOpt,
         args: Args,
         closure: Option<ObjectRef>,
     ) -> RuntimeResult {
         let args = self.check_call_args(func, &None, args)?;
-        self.push_call_frame(this_opt, closure)?;
-        self.ctx.declare_and_assign_var("this", self.find_this())?;
-        // XXX: All args are created as cells, which allows them to be
-        //      captured without having to track whether they were in
-        //      fact captured. This isn't a great solution--it would be
-        //      better to track which params are captured. See related
-        //      note in push_var().
-        for (name, arg) in func.arg_names().iter().zip(args) {
-            let cell = new::cell_with_value(arg);
-            self.ctx.declare_and_assign_var(name, cell)?;
+        if func.is_async() {
+            let task =
+                new::task(func.name(), func_ref.clone(), this_opt, args, closure);
+            self.push_return_val(task);
+            return Ok(());
         }
+        self.enter_func(func_ref, func, this_opt, args, closure)?;
         match self.execute_func(func, 0) {
             Ok(_) => {
                 self.pop_call_frame()?;
                 Ok(())
             }
             Err(err) => {
-                self.reset();
+                self.reset_unless_handled(&err);
                 Err(err)
             }
         }
     }
 
+    /// Push a call frame for func, which is the function `func_ref`
+    /// refers to, and declare `this`, func's args, and func's locals in
+    /// the frame's namespace.
+    fn enter_func(
+        &mut self,
+        func_ref: &ObjectRef,
+        func: &Func,
+        this_opt: ThisOpt,
+        args: Args,
+        closure: Option<ObjectRef>,
+    ) -> RuntimeResult {
+        // Functions nested in methods capture `this` from the method,
+        // in which case the captured cell is used so that it can be
+        // passed down to functions nested further. In methods, `this`
+        // is created as a cell if it's captured.
+        let this_name = Symbol::intern("this");
+        let code = func.code();
+        let captured_this = closure.as_ref().and_then(|closure| {
+            let closure = closure.read().unwrap();
+            closure.down_to_closure().and_then(|c| c.get_captured("this"))
+        });
+        self.push_call_frame(this_opt, closure, Some(func_ref.clone()))?;
+        let this = match captured_this {
+            Some(cell) => cell,
+            None if code.is_cell_var(this_name) => {
+                new::cell_with_value(self.find_this())
+            }
+            None => self.find_this(),
+        };
+        self.ctx.declare_and_assign_var(this_name, this)?;
+        // Args that are captured by inner functions are created as
+        // cells so the inner functions can share them.
+        for (name, arg) in func.arg_names().iter().zip(args) {
+            let name = Symbol::intern(name);
+            if code.is_cell_var(name) {
+                let cell = new::cell_with_value(arg);
+                self.ctx.declare_and_assign_var(name, cell)?;
+            } else {
+                self.ctx.declare_and_assign_var(name, arg)?;
+            }
+        }
+        // Declare locals after args so their slots match the ones
+        // assigned by the compiler.
+        for name in code.local_names() {
+            self.ctx.declare_var(*name);
+        }
+        Ok(())
+    }
+
     pub fn call_closure(
         &mut self,
         closure_ref: ObjectRef,
@@ -987,10 +2086,10 @@ impl VM {
     ) -> RuntimeResult {
         let closure = closure_ref.read().unwrap();
         let closure = closure.down_to_closure().unwrap();
-        let func = closure.func();
-        let func = func.read().unwrap();
+        let func_ref = closure.func();
+        let func = func_ref.read().unwrap();
         let func = func.down_to_func().unwrap();
-        self.call_func(func, this_opt, args, Some(closure_ref.clone()))
+        self.call_func(&func_ref, func, this_opt, args, Some(closure_ref.clone()))
     }
 
     /// Check call args to ensure they're valid. This ensures the
@@ -1043,6 +2142,262 @@ impl VM {
         Ok(())
     }
 
+    // Tasks -----------------------------------------------------------
+
+    /// Run an event loop until task finishes and return the task's
+    /// result. If the task raised an error, the error is raised again
+    /// here. Other tasks that were spawned but not awaited may be left
+    /// unfinished when the loop exits.
+    pub fn run_until_complete(&mut self, task_ref: ObjectRef) -> RuntimeObjResult {
+        if self.event_loop.is_some() {
+            return Err(RuntimeErr::task_err("Event loop is already running"));
+        }
+        self.event_loop = Some(EventLoop::default());
+        let result = self.run_event_loop(&task_ref);
+        self.event_loop = None;
+        result?;
+        let task = task_ref.read().unwrap();
+        let task = task.down_to_task().unwrap();
+        if let Some(err) = task.raised() {
+            Err(RuntimeErr::raised(err))
+        } else {
+            task.result()
+                .ok_or_else(|| RuntimeErr::unexpected_state("Task did not finish"))
+        }
+    }
+
+    fn run_event_loop(&mut self, main_ref: &ObjectRef) -> RuntimeResult {
+        self.spawn(main_ref)?;
+        while !main_ref.read().unwrap().down_to_task().unwrap().is_done() {
+            let event_loop = self.event_loop.as_mut().unwrap();
+            if let Some(task_ref) = event_loop.next_ready() {
+                self.run_task(task_ref)?;
+            } else if let Some(task_ref) = event_loop.next_timer() {
+                self.finish_task(&task_ref, new::nil(), false);
+            } else {
+                return Err(RuntimeErr::task_err(
+                    "Deadlock: all unfinished tasks are waiting on each other",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Schedule task to run on the event loop. Tasks can only be
+    /// spawned while the event loop is running.
+    pub fn spawn(&mut self, task_ref: &ObjectRef) -> RuntimeResult {
+        let obj = task_ref.read().unwrap();
+        if !obj.is_task() {
+            return Err(RuntimeErr::type_err(format!(
+                "Expected a Task; got {}",
+                obj.class().read().unwrap()
+            )));
+        }
+        drop(obj);
+        if let Some(event_loop) = self.event_loop.as_mut() {
+            event_loop.schedule(task_ref);
+            Ok(())
+        } else {
+            Err(RuntimeErr::task_err(
+                "Tasks can only be spawned while the event loop is running",
+            ))
+        }
+    }
+
+    /// Run task until it finishes or until it's suspended by AWAIT.
+    ///
+    /// Catchable errors raised by the task are stored as its result.
+    /// Other errors (e.g., exits) stop the event loop.
+    fn run_task(&mut self, task_ref: ObjectRef) -> RuntimeResult {
+        let task_id = task_ref.read().unwrap().id();
+        let start = task_ref.write().unwrap().down_to_task_mut().unwrap().take_start();
+        let Some((func_ref, start)) = start else {
+            return Err(RuntimeErr::unexpected_state(
+                "Expected task to call a function",
+            ));
+        };
+        let func = func_ref.read().unwrap();
+        let func = func.down_to_func().unwrap();
+        let depths = self.stack_depths();
+        let ip = if let Some((this_opt, args, closure)) = start {
+            self.enter_func(&func_ref, func, this_opt, args, closure)?;
+            0
+        } else {
+            let state = self.event_loop.as_mut().unwrap().take_suspended(task_id);
+            let state = state.ok_or_else(|| {
+                RuntimeErr::unexpected_state("Suspended task state not found")
+            })?;
+            self.resume_task(state, depths)?
+        };
+        let module = FuncTrait::module(func);
+        let module = module.read().unwrap();
+        let module = module.down_to_mod().unwrap();
+        self.event_loop.as_mut().unwrap().current = Some(task_ref.clone());
+        let result =
+            self.execute_code_with_handlers(module, func.code(), ip, depths.handler);
+        self.event_loop.as_mut().unwrap().current = None;
+        match result {
+            Ok(()) => {
+                if let VMState::Paused(ip) = self.state {
+                    let state = self.suspend_task(ip, depths);
+                    self.event_loop.as_mut().unwrap().suspend(task_id, state);
+                    self.set_running();
+                } else {
+                    self.pop_call_frame()?;
+                    let result = self.pop_obj()?;
+                    self.finish_task(&task_ref, result, false);
+                }
+                Ok(())
+            }
+            Err(err) => {
+                let Some(err_obj) = err.to_err_obj() else {
+                    return Err(err);
+                };
+                self.unwind_to(depths);
+                self.finish_task(&task_ref, err_obj, true);
+                Ok(())
+            }
+        }
+    }
+
+    /// Get the result of the task at TOS if it's done. If the task
+    /// raised an error, the error is raised again here.
+    ///
+    /// If the task isn't done, it's scheduled if it isn't already, the
+    /// current task is made to wait for it, and `None` is returned.
+    fn await_task(&mut self) -> Result<Option<ObjectRef>, RuntimeErr> {
+        let task_ref = self.peek_obj()?;
+        let obj = task_ref.read().unwrap();
+        let Some(task) = obj.down_to_task() else {
+            return Err(RuntimeErr::type_err(format!(
+                "Expected a Task to await; got {}",
+                obj.class().read().unwrap()
+            )));
+        };
+        if let Some(err) = task.raised() {
+            return Err(RuntimeErr::raised(err));
+        }
+        if let Some(result) = task.result() {
+            return Ok(Some(result));
+        }
+        let task_id = obj.id();
+        drop(obj);
+        let current = self
+            .event_loop
+            .as_ref()
+            .and_then(|event_loop| event_loop.current.clone())
+            .ok_or_else(|| RuntimeErr::unexpected_state("AWAIT outside of task"))?;
+        if current.read().unwrap().id() == task_id {
+            return Err(RuntimeErr::task_err("A task can't await itself"));
+        }
+        let event_loop = self.event_loop.as_mut().unwrap();
+        event_loop.schedule(&task_ref);
+        event_loop.wait(task_id, current);
+        Ok(None)
+    }
+
+    /// Store task's result and make the tasks waiting for it ready.
+    fn finish_task(&mut self, task_ref: &ObjectRef, result: ObjectRef, raised: bool) {
+        let mut obj = task_ref.write().unwrap();
+        let task_id = obj.id();
+        obj.down_to_task_mut().unwrap().finish(result, raised);
+        drop(obj);
+        if let Some(event_loop) = self.event_loop.as_mut() {
+            event_loop.wake(task_id);
+        }
+    }
+
+    /// Split off the parts of the stacks above `depths`, which belong
+    /// to the task that's being suspended.
+    fn suspend_task(&mut self, ip: usize, depths: StackDepths) -> SuspendedTask {
+        let call_n = self.call_stack.len() - depths.call;
+        let scope_n = self.scope_stack.len() - depths.scope;
+        let value_n = self.value_stack.len() - depths.value;
+        let handler_n = self.handler_stack.len() - depths.handler;
+        SuspendedTask {
+            ip,
+            depths,
+            call_stack: self.call_stack.pop_n(call_n).unwrap_or_default(),
+            scope_stack: self.scope_stack.pop_n(scope_n).unwrap_or_default(),
+            value_stack: self.value_stack.pop_n(value_n).unwrap_or_default(),
+            handler_stack: self.handler_stack.pop_n(handler_n).unwrap_or_default(),
+            namespaces: self.ctx.split_off_scopes(depths.ctx),
+        }
+    }
+
+    /// Restore the parts of the stacks belonging to a suspended task and
+    /// return the address of the instruction to resume at.
+    fn resume_task(
+        &mut self,
+        state: SuspendedTask,
+        depths: StackDepths,
+    ) -> Result<usize, RuntimeErr> {
+        if state.depths != depths {
+            return Err(RuntimeErr::unexpected_state(
+                "Task resumed at different stack depths than it was suspended at",
+            ));
+        }
+        self.call_stack.extend(state.call_stack);
+        self.scope_stack.extend(state.scope_stack);
+        self.value_stack.extend(state.value_stack);
+        self.handler_stack.extend(state.handler_stack);
+        self.ctx.restore_scopes(state.namespaces);
+        Ok(state.ip)
+    }
+
+    fn stack_depths(&self) -> StackDepths {
+        StackDepths {
+            call: self.call_stack.len(),
+            scope: self.scope_stack.len(),
+            value: self.value_stack.len(),
+            handler: self.handler_stack.len(),
+            ctx: self.ctx.current_depth(),
+        }
+    }
+
+    /// Unwind the stacks to the specified depths (e.g., after a task
+    /// raises an error).
+    fn unwind_to(&mut self, depths: StackDepths) {
+        self.call_stack.truncate(depths.call);
+        self.scope_stack.truncate(depths.scope);
+        self.value_stack.truncate(depths.value);
+        self.handler_stack.truncate(depths.handler);
+        self.ctx.exit_scopes_to(depths.ctx);
+    }
+
+    // Globals ---------------------------------------------------------
+
+    /// Assign a module global from a function. The global is looked up
+    /// in the module first and then in the current context, which is
+    /// where the globals of a module that's being executed live.
+    ///
+    /// Globals that are assigned from functions are stored in cells so
+    /// that the module and the context share them. Globals are usually
+    /// wrapped in cells when they're first assigned at the module
+    /// level, but globals defined before the function was compiled
+    /// (e.g., in an earlier REPL chunk) are wrapped here instead.
+    fn store_global(
+        &mut self,
+        module: &Module,
+        name: Symbol,
+        value: ObjectRef,
+    ) -> RuntimeResult {
+        let cell_ref = module
+            .ns()
+            .get(name.as_str())
+            .filter(|obj| obj.read().unwrap().is_cell())
+            .or_else(|| self.ctx.get_global(name))
+            .ok_or_else(|| RuntimeErr::name_err(format!("Global not found: {name}")))?;
+        let mut cell = cell_ref.write().unwrap();
+        if let Some(cell) = cell.down_to_cell_mut() {
+            cell.set_value(value);
+        } else {
+            let index = self.ctx.globals().get_index_of(&name).unwrap();
+            self.ctx.assign_var_at(0, index, new::cell_with_value(value))?;
+        }
+        Ok(())
+    }
+
     // Scopes ----------------------------------------------------------
 
     fn enter_scope(&mut self) {
@@ -1055,30 +2410,34 @@ impl VM {
     /// added in the scope, including locals, and finally push the
     /// scope's "return value" back onto the stack. Finally, the scope's
     /// namespace is then cleared and removed.
-    fn exit_scope(&mut self) {
-        let return_val = self.pop_obj();
+    fn exit_scope(&mut self) -> RuntimeResult {
+        // Ensure the scope left a value on the stack.
+        let return_val = self.pop_obj()?;
         if let Some(pointer) = self.scope_stack.pop() {
             self.value_stack.truncate(pointer);
         } else {
-            panic!("Scope stack unexpectedly empty when exiting scope");
+            return Err(RuntimeErr::unexpected_state(
+                "Scope stack unexpectedly empty when exiting scope",
+            ));
         };
-        // Ensure the scope left a value on the stack.
-        if let Ok(obj) = return_val {
-            self.push_return_val(obj.clone());
-        } else {
-            panic!("Value stack unexpectedly empty when exiting scope");
+        // Discard handlers left active by jumping out of a `try` block.
+        while let Some(handler) = self.handler_stack.peek() {
+            if handler.scope_depth <= self.scope_stack.len() {
+                break;
+            }
+            self.handler_stack.pop();
         }
-        self.ctx.exit_scope();
+        self.push_return_val(return_val);
+        self.ctx.exit_scope()
     }
 
     /// This is a convenience for jumping out multiple scopes when
     /// jumping.
-    fn exit_scopes(&mut self, count: usize) {
-        if count > 0 {
-            for _ in 0..count {
-                self.exit_scope();
-            }
+    fn exit_scopes(&mut self, count: usize) -> RuntimeResult {
+        for _ in 0..count {
+            self.exit_scope()?;
         }
+        Ok(())
     }
 
     // Value stack -----------------------------------------------------
@@ -1096,16 +2455,31 @@ impl VM {
         }
     }
 
-    fn push_var(&mut self, depth: usize, name: String) -> RuntimeResult {
-        let obj_ref = self.ctx.get_var_at_depth(depth, name.as_str())?;
-        // XXX: This is a workaround for function args being created
-        //      as cells.
+    /// Push the shared nil object. Like other global constants, it's
+    /// never returned to the temp pool.
+    fn push_nil(&mut self) -> RuntimeResult {
+        self.push_global_const(globals::NIL_INDEX)
+    }
+
+    /// Push the shared true or false object. Bools should always be
+    /// pushed this way rather than as temps so that jumps can check
+    /// them without locking (see `peek_bool_val`).
+    fn push_bool(&mut self, val: bool) -> RuntimeResult {
+        let index = if val { globals::TRUE_INDEX } else { globals::FALSE_INDEX };
+        self.push_global_const(index)
+    }
+
+    fn push_var(&mut self, depth: usize, index: usize) -> RuntimeResult {
+        let obj_ref = self.ctx.get_var_at(depth, index)?;
+        // XXX: Loads of cell vars are converted to LoadCell by the
+        //      compiler only in the scope where the var is defined, so
+        //      a LoadLocal in a nested scope may still get a cell.
         let obj = obj_ref.read().unwrap();
         if let Some(cell) = obj.down_to_cell() {
             let value = cell.value();
-            self.push(ValueStackKind::CellVar(value, depth, name));
+            self.push(ValueStackKind::CellVar(value, depth, index));
         } else {
-            self.push(ValueStackKind::Var(obj_ref.clone(), depth, name));
+            self.push(ValueStackKind::Var(obj_ref.clone(), depth, index));
         }
         Ok(())
     }
@@ -1127,7 +2501,7 @@ impl VM {
 
     pub fn pop_obj(&mut self) -> PopObjResult {
         let kind = self.pop()?;
-        Ok(self.get_obj(&kind))
+        Ok(self.get_boxed_obj(kind))
     }
 
     fn pop_n(&mut self, n: usize) -> PopNResult {
@@ -1149,6 +2523,26 @@ impl VM {
         Ok(objects)
     }
 
+    /// Pop N objects that are name/value pairs, converting the names
+    /// to strings.
+    fn pop_map_entries(
+        &mut self,
+        n: usize,
+    ) -> Result<(Vec<String>, Vec<ObjectRef>), RuntimeErr> {
+        let objects = self.pop_n_obj(n)?;
+        let mut keys = vec![];
+        let mut vals = vec![];
+        for (i, obj) in objects.into_iter().enumerate() {
+            if i % 2 == 0 {
+                let obj = obj.read().unwrap();
+                keys.push(obj.to_string());
+            } else {
+                vals.push(obj);
+            }
+        }
+        Ok((keys, vals))
+    }
+
     fn peek(&self) -> PeekResult {
         match self.value_stack.peek() {
             Some(kind) => Ok(kind),
@@ -1161,15 +2555,215 @@ impl VM {
         Ok(self.get_obj(kind))
     }
 
+    /// Get the bool value of TOS. The shared true, false, and nil
+    /// objects are checked without locking.
+    fn peek_bool_val(&self) -> RuntimeBoolResult {
+        use globals::{FALSE_INDEX, NIL_INDEX, TRUE_INDEX};
+        match self.peek()? {
+            ValueStackKind::GlobalConstant(_, TRUE_INDEX) => Ok(true),
+            ValueStackKind::GlobalConstant(_, FALSE_INDEX | NIL_INDEX) => Ok(false),
+            kind => {
+                let obj = self.get_obj(kind);
+                let obj = obj.read().unwrap();
+                obj.bool_val()
+            }
+        }
+    }
+
     fn get_obj(&self, kind: &ValueStackKind) -> ObjectRef {
         use ValueStackKind::*;
         match kind {
             GlobalConstant(obj, ..) => obj.clone(),
             Constant(obj, ..) => obj.clone(),
+            NumConstant(obj, _) => obj.clone(),
             Var(obj, ..) => obj.clone(),
             CellVar(obj, ..) => obj.clone(),
             Temp(obj) => obj.clone(),
             ReturnVal(obj) => obj.clone(),
+            Num(num) => num.to_obj(),
+        }
+    }
+
+    /// Like `get_obj` but boxes unboxed numbers using the temp pool.
+    fn get_boxed_obj(&mut self, kind: ValueStackKind) -> ObjectRef {
+        if let ValueStackKind::Num(num) = kind {
+            self.temp_pool.num(num)
+        } else {
+            self.get_obj(&kind)
+        }
+    }
+
+    /// Get the unboxed value of an Int or Float. Unboxed numbers and
+    /// numeric constants are read without locking.
+    fn get_num(&self, kind: &ValueStackKind) -> Option<Num> {
+        match kind {
+            ValueStackKind::NumConstant(_, num) | ValueStackKind::Num(num) => {
+                Some(*num)
+            }
+            ValueStackKind::Constant(..) | ValueStackKind::GlobalConstant(..) => None,
+            ValueStackKind::Var(obj, ..)
+            | ValueStackKind::CellVar(obj, ..)
+            | ValueStackKind::Temp(obj)
+            | ValueStackKind::ReturnVal(obj) => Num::from_obj(&*obj.read().unwrap()),
+        }
+    }
+
+    /// Return a popped operand to the temp pool if nothing else refers
+    /// to it.
+    fn release(&mut self, kind: ValueStackKind) {
+        use ValueStackKind::*;
+        match kind {
+            Var(obj, ..) | CellVar(obj, ..) | Temp(obj) | ReturnVal(obj) => {
+                self.temp_pool.release(obj)
+            }
+            _ => (),
+        }
+    }
+
+    // Debugger --------------------------------------------------------
+
+    /// Check with the debugger at the start of a statement. If it's
+    /// time to stop, show the location and handle commands until one
+    /// resumes execution. If the debugger's input is exhausted, the
+    /// debugger is detached and execution continues normally.
+    fn debug_statement(&mut self, module: &Module) -> RuntimeResult {
+        let depth = self.call_stack.len();
+        let path = module.path();
+        let line = self.loc.0.line;
+        // The debugger is taken out while it's in use so that code run
+        // from the prompt (e.g., `$repr` methods) isn't debugged.
+        let mut debugger = match self.debugger.take() {
+            Some(debugger) => debugger,
+            None => return Ok(()),
+        };
+        if !debugger.should_stop(path, line, depth) {
+            self.debugger = Some(debugger);
+            return Ok(());
+        }
+        self.write_output(&debugger.format_location(path, line), false);
+        loop {
+            self.write_output(debugger::PROMPT, false);
+            io::stdout().flush().unwrap_or(());
+            let command = match debugger.read_command() {
+                Some(command) => command,
+                None => {
+                    self.write_output("\n", false);
+                    return Ok(());
+                }
+            };
+            let output = match command {
+                Command::Step => {
+                    debugger.step();
+                    break;
+                }
+                Command::Next => {
+                    debugger.next(depth);
+                    break;
+                }
+                Command::Continue => {
+                    debugger.resume();
+                    break;
+                }
+                Command::Quit => return self.halt(1),
+                Command::Break(file, bp_line) => {
+                    let file = file.unwrap_or_else(|| path.to_owned());
+                    let location = format!("{file}:{bp_line}");
+                    if debugger.add_breakpoint(file, bp_line) {
+                        format!("Breakpoint set at {location}")
+                    } else {
+                        format!("Breakpoint already set at {location}")
+                    }
+                }
+                Command::Clear(file, bp_line) => {
+                    let file = file.unwrap_or_else(|| path.to_owned());
+                    let location = format!("{file}:{bp_line}");
+                    if debugger.remove_breakpoint(file, bp_line) {
+                        format!("Breakpoint cleared at {location}")
+                    } else {
+                        format!("No breakpoint at {location}")
+                    }
+                }
+                Command::Breakpoints => {
+                    let breakpoints: Vec<String> = debugger
+                        .breakpoints()
+                        .map(|(file, bp_line)| format!("{file}:{bp_line}"))
+                        .collect();
+                    if breakpoints.is_empty() {
+                        "No breakpoints".to_owned()
+                    } else {
+                        breakpoints.join("\n")
+                    }
+                }
+                Command::Where => {
+                    let location = debugger.format_location(path, line);
+                    location.trim_end().to_owned()
+                }
+                Command::Stack => self.format_stack(),
+                Command::Locals => self.format_frame_vars(),
+                Command::Print(expr) => match self.debug_eval(&expr) {
+                    Ok(repr) => repr,
+                    Err(message) => message,
+                },
+                Command::Help => debugger::HELP.trim_end().to_owned(),
+                Command::Invalid(message) => message,
+            };
+            self.write_output(&format!("{output}\n"), false);
+        }
+        self.debugger = Some(debugger);
+        Ok(())
+    }
+
+    /// Depth of the current call frame's namespace. At the top level,
+    /// this is the global namespace.
+    fn frame_ctx_depth(&self) -> usize {
+        self.call_stack.peek().map_or(0, |frame| frame.ctx_depth)
+    }
+
+    /// Format the vars in the current call frame, one per line.
+    fn format_frame_vars(&mut self) -> String {
+        let vars = self.ctx.vars_from(self.frame_ctx_depth());
+        if vars.is_empty() {
+            return "No vars".to_owned();
+        }
+        let mut lines = vec![];
+        for (name, obj) in vars {
+            let obj = unwrap_cell(obj);
+            let repr = self.obj_to_repr(&obj).unwrap_or_else(|err| format!("<{err}>"));
+            lines.push(format!("{name} = {repr}"));
+        }
+        lines.join("\n")
+    }
+
+    /// Evaluate expression text in the current call frame and return
+    /// the repr of its value. The expression is run on a separate VM
+    /// whose globals are the vars visible in the current frame, so it
+    /// can't disturb the state of this VM (though it can modify
+    /// objects).
+    fn debug_eval(&self, text: &str) -> Result<String, String> {
+        let mut vars = self.ctx.globals().clone();
+        vars.extend(self.ctx.vars_from(self.frame_ctx_depth()));
+        let names = vars.keys().map(|name| name.as_str().to_owned()).collect();
+        let code = debugger::compile_expr(text, names)?;
+        let mut vm = VM::new(ModuleExecutionContext::default(), self.max_call_depth);
+        for (name, obj) in vars {
+            vm.ctx
+                .declare_and_assign_var(name, unwrap_cell(obj))
+                .map_err(|err| err.to_string())?;
+        }
+        // An empty module is used so that its globals don't shadow the
+        // frame's vars.
+        let module = Module::new(
+            "$debugger".to_owned(),
+            "<debugger>".to_owned(),
+            Code::default(),
+            None,
+        );
+        vm.execute_code(&module, &code, 0).map_err(|err| err.to_string())?;
+        match vm.state.clone() {
+            VMState::Idle(Some(obj)) => {
+                vm.obj_to_repr(&obj).map_err(|err| err.to_string())
+            }
+            _ => Ok("nil".to_owned()),
         }
     }
 
@@ -1189,11 +2783,12 @@ impl VM {
         for (i, kind) in self.value_stack.iter().enumerate() {
             let kind_marker = match kind {
                 GlobalConstant(..) => "G",
-                Constant(..) => "C",
+                Constant(..) | NumConstant(..) => "C",
                 Var(..) => "V",
                 CellVar(..) => "CV",
                 Temp(..) => "T",
                 ReturnVal(..) => "R",
+                Num(..) => "N",
             };
             let obj = self.get_obj(kind);
             let obj = &*obj.read().unwrap();
@@ -1207,3 +2802,10 @@ impl VM {
         items.join("\n")
     }
 }
+
+/// Get a module from the module registry (e.g., to attach a type to the
+/// module it's defined in).
+fn registered_module(name: &str) -> Result<ObjectRef, RuntimeErr> {
+    maybe_get_module(name)
+        .ok_or_else(|| RuntimeErr::name_err(format!("Module not registered: {name}")))
+}
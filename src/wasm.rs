@@ -0,0 +1,63 @@
+//! WebAssembly interface, used to run FeInt in the browser (e.g., in an
+//! online playground).
+//!
+//! Each call runs the code in a new executor. Output is captured rather
+//! than written to stdout/stderr, which don't exist in the browser.
+//!
+//! NOTE: Modules that need the OS (e.g., `std.fs`, `std.os`, and
+//!       `std.proc`) aren't available, and the event loop in
+//!       `std.async` can't block, so sleeping tasks finish without
+//!       waiting (see `vm::event_loop`).
+use std::sync::{Arc, Mutex};
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::exe::Executor;
+use crate::vm::{OutputHandler, DEFAULT_MAX_CALL_DEPTH};
+
+/// Run code and return everything it printed, including the value of
+/// the last expression (as in the REPL) and any error messages.
+#[wasm_bindgen]
+pub fn eval(code: &str) -> String {
+    let output = Arc::new(Mutex::new(String::new()));
+    let handler_output = output.clone();
+    run(code, Box::new(move |text, _| handler_output.lock().unwrap().push_str(text)));
+    let text = output.lock().unwrap();
+    text.clone()
+}
+
+/// Run code, passing output to `on_output` as it's printed. The
+/// callback is called with the text and whether it was printed to
+/// stderr. Returns whether the code ran successfully.
+#[wasm_bindgen(js_name = evalWithOutput)]
+pub fn eval_with_output(code: &str, on_output: Function) -> bool {
+    let on_output = Callback(on_output);
+    run(
+        code,
+        Box::new(move |text, is_err| {
+            // Errors thrown by the callback can't be reported anywhere
+            // useful, so they're ignored.
+            let _ = on_output.0.call2(
+                &JsValue::NULL,
+                &JsValue::from_str(text),
+                &JsValue::from_bool(is_err),
+            );
+        }),
+    )
+}
+
+/// JS functions aren't `Send` or `Sync`, but output handlers have to be
+/// since they're shared with threads. There are no threads in the
+/// browser, so the function is only ever called from the main thread.
+struct Callback(Function);
+
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+fn run(code: &str, handler: OutputHandler) -> bool {
+    let mut exe = Executor::new(DEFAULT_MAX_CALL_DEPTH, vec![], false, false, false);
+    exe.set_output_handler(handler);
+    exe.set_print_result(true);
+    exe.bootstrap().is_ok() && exe.execute_text(code).is_ok()
+}
@@ -0,0 +1,418 @@
+use crate::{feint, feint_with_stdin};
+
+// Scripts -------------------------------------------------------------
+
+#[test]
+fn test_run_script_file() {
+    feint(&["hello.fi"]).assert_code(0).assert_stdout("hello\n");
+}
+
+#[test]
+fn test_run_subcommand() {
+    feint(&["run", "hello.fi"]).assert_code(0).assert_stdout("hello\n");
+}
+
+#[test]
+fn test_script_argv() {
+    feint(&["argv.fi", "a", "b"]).assert_code(0).assert_stdout("a,b\n");
+}
+
+#[test]
+fn test_script_from_scripts_dir() {
+    feint(&["greet"]).assert_code(0).assert_stdout("hello from scripts\n");
+}
+
+#[test]
+fn test_main_script() {
+    feint(&["main"]).assert_code(0).assert_stdout("hello from main\n");
+}
+
+#[test]
+fn test_script_not_found() {
+    feint(&["no_such_script"])
+        .assert_code(255)
+        .assert_stderr_contains("Module not found: no_such_script");
+}
+
+#[test]
+fn test_script_file_not_found() {
+    feint(&["no_such_script.fi"])
+        .assert_code(255)
+        .assert_stderr_contains("Could not read source file: no_such_script.fi");
+}
+
+#[test]
+fn test_search_path() {
+    feint(&["uses_path.fi"])
+        .assert_code(255)
+        .assert_stderr_contains("Module not found: greeting");
+    feint(&["-p", "lib", "uses_path.fi"])
+        .assert_code(0)
+        .assert_stdout("hello from path\n");
+}
+
+#[test]
+fn test_global_assigned_in_imported_module() {
+    feint(&["-p", "lib", "uses_counter.fi"]).assert_code(0).assert_stdout("2\n");
+}
+
+// Watching ------------------------------------------------------------
+
+#[test]
+fn test_watch_reruns_script_when_imported_module_changes() {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read};
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+
+    let dir = std::env::temp_dir().join(format!("feint-watch-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("watched.fi");
+    let module = dir.join("watched_msg.fi");
+    fs::write(&script, "import watched_msg\nprint(watched_msg.msg)\n").unwrap();
+    fs::write(&module, "msg = 'before'\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_feint"))
+        .arg("--watch")
+        .arg(&script)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("could not run feint");
+
+    // Read stdout and stderr on separate threads so reading one can't
+    // block the other.
+    let read_lines = |reader: Box<dyn Read + Send>| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    };
+    let stdout = read_lines(Box::new(child.stdout.take().unwrap()));
+    let stderr = read_lines(Box::new(child.stderr.take().unwrap()));
+    let timeout = Duration::from_secs(30);
+    let wait_for_exit = || loop {
+        match stderr.recv_timeout(timeout) {
+            Ok(line) if line.starts_with("[watch] Exited") => return Some(line),
+            Ok(_) => continue,
+            Err(_) => return None,
+        }
+    };
+
+    let first_output = stdout.recv_timeout(timeout).ok();
+    let first_exit = wait_for_exit();
+
+    // Give the watcher time to record the initial modification times,
+    // then bump the module's explicitly in case the file system's
+    // timestamps are too coarse to see the change.
+    thread::sleep(Duration::from_millis(500));
+    fs::write(&module, "msg = 'after'\n").unwrap();
+    let file = fs::File::options().write(true).open(&module).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+    drop(file);
+
+    let second_output = stdout.recv_timeout(timeout).ok();
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(dir).unwrap();
+
+    assert_eq!(first_output.as_deref(), Some("before"));
+    let first_exit = first_exit.unwrap_or_default();
+    assert!(first_exit.ends_with("watching 2 files for changes"), "{first_exit}");
+    assert_eq!(second_output.as_deref(), Some("after"));
+}
+
+// Code and stdin ------------------------------------------------------
+
+#[test]
+fn test_code_prints_result() {
+    feint(&["-c", "1 + 2"]).assert_code(0).assert_stdout("3\n");
+}
+
+#[test]
+fn test_code_no_print() {
+    feint(&["-c", "1 + 2", "--no-print"]).assert_code(0).assert_stdout("");
+}
+
+#[test]
+fn test_code_nil_result_isnt_printed() {
+    feint(&["-c", "nil"]).assert_code(0).assert_stdout("");
+}
+
+#[test]
+fn test_code_argv() {
+    feint(&["-c", "import std.system\nprint(system.argv.join(','))", "x", "y"])
+        .assert_code(0)
+        .assert_stdout("x,y\n");
+}
+
+#[test]
+fn test_stdin() {
+    feint_with_stdin(&["-"], "x = 2\nprint(x * 3)\n")
+        .assert_code(0)
+        .assert_stdout("6\n");
+}
+
+// Exit codes ----------------------------------------------------------
+
+#[test]
+fn test_halt_exit_code() {
+    feint(&["halt.fi"]).assert_code(3).assert_stdout("before\n");
+}
+
+#[test]
+fn test_halt_exit_code_from_code() {
+    feint(&["-c", "$halt 7"]).assert_code(7);
+}
+
+#[test]
+fn test_assertion_failure() {
+    feint(&["assert_fail.fi"])
+        .assert_code(1)
+        .assert_stdout("")
+        .assert_stderr_contains("Assertion failed: one is not two");
+}
+
+#[test]
+fn test_syntax_err() {
+    feint(&["syntax_err.fi"])
+        .assert_code(255)
+        .assert_stdout("")
+        .assert_stderr_contains("syntax_err.fi");
+}
+
+#[test]
+fn test_multiple_parse_errs() {
+    feint(&["parse_errs.fi"])
+        .assert_code(255)
+        .assert_stdout("")
+        .assert_stderr_contains("unexpected break at 1:1")
+        .assert_stderr_contains("unexpected return at 3:1");
+}
+
+#[test]
+fn test_traceback() {
+    let output = feint(&["-p", "lib", "traceback.fi"]);
+    output
+        .assert_code(255)
+        .assert_stderr_contains("fails.fi on line 3:")
+        .assert_stderr_contains("|    y + nil")
+        .assert_stderr_contains("Traceback (most recent call last):");
+    let frames: Vec<&str> = output
+        .stderr
+        .lines()
+        .skip_while(|line| !line.contains("Traceback"))
+        .filter_map(|line| line.trim().rsplit_once('/').map(|(_, frame)| frame))
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            "traceback.fi:11 in <module>",
+            "traceback.fi:4 in outer",
+            "traceback.fi:8 in inner",
+            "fails.fi:3 in double_plus_nil",
+        ]
+    );
+}
+
+#[test]
+fn test_no_traceback_for_top_level_err() {
+    let output = feint(&["-c", "1 + nil"]);
+    output.assert_code(255).assert_stderr_contains("Could not add");
+    assert!(!output.stderr.contains("Traceback"), "{}", output.stderr);
+}
+
+// Debugger ------------------------------------------------------------
+
+#[test]
+fn test_debugger_breakpoint() {
+    let output =
+        feint_with_stdin(&["--debugger", "debug.fi"], "b 5\nc\nl\np a * 10\nc\n");
+    output.assert_code(0);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("-> "), "{stdout}");
+    assert!(stdout.contains("debug.fi:1\n   1 | x = 1\n"), "{stdout}");
+    assert!(stdout.contains("Breakpoint set at "), "{stdout}");
+    assert!(stdout.contains("debug.fi:5\n   5 |     b + 1\n"), "{stdout}");
+    assert!(stdout.contains("a = 1\nb = 2\n"), "{stdout}");
+    assert!(stdout.contains("(fdb) 10\n"), "{stdout}");
+    assert!(stdout.ends_with("3\n"), "{stdout}");
+}
+
+#[test]
+fn test_debugger_next_steps_over_calls() {
+    let output = feint_with_stdin(&["--debugger", "debug.fi"], "n\nn\nn\np y\nc\n");
+    output.assert_code(0);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("debug.fi:8\n"), "{stdout}");
+    assert!(!stdout.contains("debug.fi:4\n"), "{stdout}");
+    assert!(stdout.contains("(fdb) 3\n"), "{stdout}");
+}
+
+#[test]
+fn test_debugger_step_into_call() {
+    let output = feint_with_stdin(&["--debugger", "debug.fi"], "s\ns\ns\nq\n");
+    output.assert_code(1);
+    assert!(output.stdout.contains("debug.fi:4\n"), "{}", output.stdout);
+}
+
+#[test]
+fn test_debugger_detaches_at_end_of_input() {
+    let output = feint_with_stdin(&["--debugger", "debug.fi"], "");
+    output.assert_code(0);
+    assert!(output.stdout.ends_with("3\n"), "{}", output.stdout);
+}
+
+// Checking ------------------------------------------------------------
+
+#[test]
+fn test_check() {
+    feint(&["check", "hello.fi", "funcs.fi"]).assert_code(0).assert_stdout("");
+}
+
+#[test]
+fn test_check_dir() {
+    // lib/fails.fi fails when it's run but compiles
+    feint(&["check", "lib"]).assert_code(0).assert_stdout("");
+}
+
+#[test]
+fn test_check_reports_all_errors() {
+    feint(&["check", "syntax_err.fi", "hello.fi", "name_err.fi"])
+        .assert_code(255)
+        .assert_stdout("")
+        .assert_stderr_contains("syntax_err.fi")
+        .assert_stderr_contains("Name not found: no_such_name")
+        .assert_stderr_contains("2 of 3 files had errors");
+}
+
+// Formatting ----------------------------------------------------------
+
+#[test]
+fn test_fmt_check() {
+    feint(&["fmt", "--check", "hello.fi", "funcs.fi"]).assert_code(0).assert_stdout("");
+}
+
+#[test]
+fn test_fmt_check_lists_unformatted_files() {
+    feint(&["fmt", "--check", "hello.fi", "unformatted.fi"])
+        .assert_code(1)
+        .assert_stdout("unformatted.fi\n")
+        .assert_stderr_contains("1 of 2 files would be reformatted");
+}
+
+#[test]
+fn test_fmt_syntax_err() {
+    feint(&["fmt", "--check", "syntax_err.fi"])
+        .assert_code(255)
+        .assert_stdout("")
+        .assert_stderr_contains("Could not format syntax_err.fi");
+}
+
+// Language Server -----------------------------------------------------
+
+#[test]
+fn test_lsp() {
+    let input: String = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ]
+    .iter()
+    .map(|message| format!("Content-Length: {}\r\n\r\n{message}", message.len()))
+    .collect();
+    let response = r#"{"jsonrpc":"2.0","id":1,"result":null}"#;
+    feint_with_stdin(&["lsp"], &input).assert_code(0).assert_stdout(&format!(
+        "Content-Length: {}\r\n\r\n{response}",
+        response.len()
+    ));
+}
+
+// Disassembly ---------------------------------------------------------
+
+#[cfg(feature = "dis")]
+#[test]
+fn test_dis_doesnt_run_code() {
+    let output = feint(&["--dis", "hello.fi"]);
+    output.assert_code(0);
+    assert!(output.stdout.contains("INSTRUCTION"), "{}", output.stdout);
+    assert!(output.stdout.contains("LOAD_CONST"), "{}", output.stdout);
+    assert!(!output.stdout.contains("hello\n"), "{}", output.stdout);
+}
+
+#[cfg(feature = "dis")]
+#[test]
+fn test_dis_shows_jump_targets_and_constants() {
+    let output = feint(&["--dis", "jumps.fi"]);
+    output.assert_code(0);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("JUMP_IF_NOT"), "{stdout}");
+    assert!(stdout.contains("(forward) -> "), "{stdout}");
+    assert!(stdout.contains(">>"), "{stdout}");
+    assert!(stdout.contains("CONSTANTS\n"), "{stdout}");
+}
+
+#[cfg(feature = "dis")]
+#[test]
+fn test_dis_json() {
+    let output = feint(&["--dis-json", "jumps.fi"]);
+    output.assert_code(0);
+    let stdout = output.stdout.trim();
+    assert!(stdout.starts_with("{\"instructions\":[{\"ip\":0,"), "{stdout}");
+    assert!(stdout.contains("\"type\":\"Str\",\"value\":\"\\\"pos\\\"\""), "{stdout}");
+    assert!(stdout.ends_with("\"free_vars\":[]}"), "{stdout}");
+    // The jump's absolute target is included in its arg and on its own.
+    let (_, rest) = stdout.split_once("\"op\":\"JUMP_IF_NOT\",").unwrap();
+    let (inst, _) = rest.split_once('}').unwrap();
+    let (arg, target) = inst.split_once(",\"target\":").unwrap();
+    assert!(arg.ends_with(&format!("-> {target}\"")), "{inst}");
+}
+
+#[cfg(feature = "dis")]
+#[test]
+fn test_dis_subcommand() {
+    let output = feint(&["dis", "funcs.fi"]);
+    output.assert_code(0);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("INSTRUCTION"), "{stdout}");
+    // Nested functions are disassembled too.
+    assert!(stdout.contains("function outer/1"), "{stdout}");
+    assert!(stdout.contains("function inner/1"), "{stdout}");
+}
+
+#[cfg(feature = "dis")]
+#[test]
+fn test_dis_subcommand_doesnt_load_imports() {
+    let output = feint(&["dis", "uses_path.fi"]);
+    output.assert_code(0);
+    assert!(output.stdout.contains("IMPORT"), "{}", output.stdout);
+}
+
+#[cfg(feature = "dis")]
+#[test]
+fn test_dis_subcommand_code() {
+    let output = feint(&["dis", "-c", "1 + 2"]);
+    output.assert_code(0);
+    assert!(output.stdout.contains("BINARY_OP"), "{}", output.stdout);
+}
+
+#[cfg(feature = "dis")]
+#[test]
+fn test_dis_subcommand_func() {
+    let output = feint(&["dis", "--func", "inner", "funcs.fi"]);
+    output.assert_code(0);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("BINARY_OP"), "{stdout}");
+    assert!(!stdout.contains("CALL"), "{stdout}");
+    feint(&["dis", "-f", "nope", "funcs.fi"])
+        .assert_code(1)
+        .assert_stderr_contains("Function not found: nope");
+}
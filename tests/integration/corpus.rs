@@ -0,0 +1,20 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::feint;
+
+/// The benchmark corpus scripts double as regression tests, which also
+/// keeps them from silently breaking.
+#[test]
+fn test_corpus_scripts_run() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("benches/corpus");
+    let mut count = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "fi") {
+            feint(&[path.to_str().unwrap()]).assert_code(0);
+            count += 1;
+        }
+    }
+    assert!(count > 0, "no corpus scripts found");
+}
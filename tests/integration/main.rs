@@ -0,0 +1,79 @@
+//! Integration tests that run the compiled `feint` binary against the
+//! fixture scripts in `tests/integration/fixtures`. The binary is run
+//! from the fixtures directory so script resolution (`./scripts` and
+//! `main`) can be tested.
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+mod cli;
+mod corpus;
+
+/// The result of running `feint`.
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+}
+
+impl Output {
+    /// Assert the exit code, showing the output on failure.
+    pub fn assert_code(&self, code: i32) -> &Self {
+        assert_eq!(
+            self.code,
+            Some(code),
+            "unexpected exit code\n--- stdout ---\n{}\n--- stderr ---\n{}",
+            self.stdout,
+            self.stderr
+        );
+        self
+    }
+
+    pub fn assert_stdout(&self, expected: &str) -> &Self {
+        assert_eq!(self.stdout, expected, "stderr:\n{}", self.stderr);
+        self
+    }
+
+    pub fn assert_stderr_contains(&self, expected: &str) -> &Self {
+        assert!(
+            self.stderr.contains(expected),
+            "expected stderr to contain {expected:?}; got:\n{}",
+            self.stderr
+        );
+        self
+    }
+}
+
+pub fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/integration/fixtures")
+}
+
+/// Run `feint` with args from the fixtures directory.
+pub fn feint(args: &[&str]) -> Output {
+    feint_with_stdin(args, "")
+}
+
+/// Run `feint` with args from the fixtures directory, writing `stdin`
+/// to its standard input.
+pub fn feint_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_feint"))
+        .args(args)
+        .current_dir(fixtures_dir())
+        // Keep the environment the tests run in from changing
+        // behavior.
+        .env_remove("FEINT_DEBUG")
+        .env_remove("FEINT_DETERMINISTIC")
+        .env_remove("FEINT_MAX_CALL_DEPTH")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("could not run feint");
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().expect("could not wait for feint");
+    Output {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        code: output.status.code(),
+    }
+}